@@ -7,15 +7,10 @@ use proc_macro::TokenStream;
 use proc_macro2::Ident;
 use quote::quote;
 use syn::{
-    parse::{
-        Parse,
-        ParseStream,
-    },
+    parse::{Parse, ParseStream},
     parse_macro_input,
     token::Comma,
-    DeriveInput,
-    LitInt,
-    Result,
+    Data, DeriveInput, Fields, LitInt, Result,
 };
 
 fn get_calling_crate() -> String {
@@ -48,6 +43,198 @@ fn impl_derive_resource(ast: &DeriveInput) -> TokenStream {
     gen.into()
 }
 
+#[proc_macro_derive(Reflect, attributes(reflect))]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    impl_derive_reflect(&ast)
+}
+
+fn impl_derive_reflect(ast: &DeriveInput) -> TokenStream {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let app_mod_path = app_mod_path();
+    let reflect_path = quote! { #app_mod_path::reflect };
+
+    let fields = match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .filter(|field| !is_reflect_ignored(&field.attrs))
+                .map(|field| field.ident.clone().unwrap())
+                .collect::<Vec<_>>(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => {
+                return syn::Error::new_spanned(
+                    name,
+                    "Reflect can only be derived for structs with named fields.",
+                )
+                .to_compile_error()
+                .into();
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "Reflect can only be derived for structs.")
+                .to_compile_error()
+                .into();
+        }
+    };
+    let field_names = fields
+        .iter()
+        .map(|field| field.to_string())
+        .collect::<Vec<_>>();
+
+    let gen = quote! {
+        impl #impl_generics #reflect_path::Reflect for #name #ty_generics #where_clause {
+            fn reflect_value(&self) -> #reflect_path::ReflectValue {
+                #reflect_path::ReflectValue::Struct(vec![
+                    #((#field_names, #reflect_path::Reflect::reflect_value(&self.#fields))),*
+                ])
+            }
+
+            fn apply(
+                &mut self,
+                value: &#reflect_path::ReflectValue,
+            ) -> Result<(), #reflect_path::ReflectError> {
+                let #reflect_path::ReflectValue::Struct(values) = value else {
+                    return Err(#reflect_path::ReflectError::TypeMismatch {
+                        expected: std::any::type_name::<Self>(),
+                        found: value.clone(),
+                    });
+                };
+
+                for (field_name, field_value) in values {
+                    #reflect_path::Reflect::field_mut(self, field_name)
+                        .ok_or_else(|| {
+                            #reflect_path::ReflectError::FieldNotFound(field_name.to_string())
+                        })?
+                        .apply(field_value)?;
+                }
+                Ok(())
+            }
+
+            fn field_names(&self) -> &'static [&'static str] {
+                &[#(#field_names),*]
+            }
+
+            fn field(&self, name: &str) -> Option<&dyn #reflect_path::Reflect> {
+                match name {
+                    #(#field_names => Some(&self.#fields),)*
+                    _ => None,
+                }
+            }
+
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn #reflect_path::Reflect> {
+                match name {
+                    #(#field_names => Some(&mut self.#fields),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+
+    gen.into()
+}
+
+#[proc_macro_derive(SystemParam)]
+pub fn derive_system_param(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    impl_derive_system_param(&ast)
+}
+
+fn impl_derive_system_param(ast: &DeriveInput) -> TokenStream {
+    let name = &ast.ident;
+    let system_path = {
+        let app_mod_path = app_mod_path();
+        quote! { #app_mod_path::system }
+    };
+    let resource_path = {
+        let app_mod_path = app_mod_path();
+        quote! { #app_mod_path::resource }
+    };
+
+    let lifetimes = ast.generics.lifetimes().collect::<Vec<_>>();
+    if lifetimes.len() != 1 || ast.generics.type_params().next().is_some() {
+        return syn::Error::new_spanned(
+            &ast.generics,
+            "SystemParam can only be derived for structs with a single lifetime parameter.",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let lifetime = &lifetimes[0].lifetime;
+
+    let fields = match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            _ => {
+                return syn::Error::new_spanned(
+                    name,
+                    "SystemParam can only be derived for structs with named fields.",
+                )
+                .to_compile_error()
+                .into()
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "SystemParam can only be derived for structs.")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let field_names = fields.iter().map(|field| &field.ident).collect::<Vec<_>>();
+    let field_types = fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
+
+    let gen = quote! {
+        impl<#lifetime> #system_path::SystemParam for #name<#lifetime> {
+            type Item<'__rb> = #name<'__rb>;
+
+            fn from_resource_bank(
+                resource_bank: &#resource_path::ResourceBank,
+                system_ticks: #resource_path::SystemTicks,
+            ) -> Self::Item<'_> {
+                #name {
+                    #(#field_names: <#field_types as #system_path::SystemParam>::from_resource_bank(
+                        resource_bank,
+                        system_ticks,
+                    ),)*
+                }
+            }
+
+            fn dependencies() -> Vec<#system_path::ResourceDependency> {
+                let mut dependencies = Vec::new();
+                #(dependencies.extend(
+                    <#field_types as #system_path::SystemParam>::dependencies(),
+                );)*
+                dependencies
+            }
+        }
+    };
+
+    gen.into()
+}
+
+fn is_reflect_ignored(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        if !attr.path().is_ident("reflect") {
+            return false;
+        }
+
+        let mut ignored = false;
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("ignore") {
+                ignored = true;
+            }
+            Ok(())
+        });
+        ignored
+    })
+}
+
 struct GenerateSystemHandlersInput {
     macro_impl: Ident,
     count: usize,
@@ -1,56 +1,277 @@
-use std::{any::TypeId, collections::HashMap};
-
-use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::{any::TypeId, time::Instant};
 
 use crate::{
+    command::CommandQueue,
+    diagnostics::DiagnosticsStore,
+    event::Events,
     executor::ScheduleExecutor,
+    plugin::Plugin,
     prelude::ResMut,
-    resource::{BoxedResource, Res, Resource, ResourceBank},
-    schedule::Schedule,
+    reflect::{Inspector, Reflect},
+    resource::{Res, Resource, ResourceBank},
+    schedule::{IntoScheduleSystemConfig, Schedule, ScheduleBuilder},
+    stage::{Stage, StageGraph, PRE_UPDATE_STAGE, UPDATE_STAGE},
+    state::{self, AppState, StateDriver, StateScheduleBuilder, StateScheduleKind},
+    task::AsyncTaskPool,
 };
 
+/// Requests the app to exit, the entry point stops executing the schedule at the end of the
+/// frame and runs the shutdown systems.
+#[derive(Default)]
+pub struct AppExit {
+    requested: bool,
+}
+
+impl Resource for AppExit {}
+
+impl AppExit {
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested
+    }
+}
+
 pub struct AppBuilder {
-    resources: HashMap<TypeId, RwLock<BoxedResource>>,
-    schedule: Option<Schedule>,
+    resource_bank: ResourceBank,
+    stage_graph: StageGraph,
+    event_updaters: Vec<fn(&ResourceBank)>,
+    state_schedules: Vec<(TypeId, Box<dyn StateScheduleBuilder>)>,
+    plugin_names: Vec<&'static str>,
+    shutdown_schedule: ScheduleBuilder,
     entry_point: Option<Box<dyn FnOnce(Application)>>,
 }
 
 impl AppBuilder {
     pub fn new() -> Self {
-        Self {
-            resources: HashMap::new(),
-            schedule: None,
+        let mut app_builder = Self {
+            resource_bank: ResourceBank::new(),
+            stage_graph: StageGraph::default(),
+            event_updaters: Vec::new(),
+            state_schedules: Vec::new(),
+            plugin_names: Vec::new(),
+            shutdown_schedule: ScheduleBuilder::new(),
             entry_point: None,
-        }
+        };
+        app_builder.add_resource(AppExit::default());
+        app_builder.add_resource(CommandQueue::default());
+        app_builder.add_resource(AsyncTaskPool::default());
+        app_builder.add_system_to_stage(AsyncTaskPool::drain_completed_tasks, PRE_UPDATE_STAGE);
+        app_builder
     }
 
     pub fn add_resource<R: Resource>(&mut self, resource: R) -> &mut Self {
-        self.resources
-            .insert(TypeId::of::<R>(), RwLock::new(Box::new(resource)));
+        self.resource_bank.insert_resource(resource);
+        self
+    }
+
+    /// Builds the plugin into the app.
+    ///
+    /// # Panics
+    /// If the plugin is unique and was already added.
+    pub fn add_plugin(&mut self, plugin: impl Plugin) -> &mut Self {
+        let name = plugin.name();
+        if plugin.is_unique() && self.is_plugin_added(name) {
+            panic!("Plugin {} was already added.", name);
+        }
+
+        log::debug!("Building plugin: {}", name);
+        self.plugin_names.push(name);
+        plugin.build(self);
+        self
+    }
+
+    /// Adds the plugin unless a plugin with the same name was already added, used by plugins to
+    /// add the plugins they depend on.
+    pub fn add_plugin_once(&mut self, plugin: impl Plugin) -> &mut Self {
+        if !self.is_plugin_added(plugin.name()) {
+            self.add_plugin(plugin);
+        }
         self
     }
 
+    pub fn is_plugin_added(&self, name: &str) -> bool {
+        self.plugin_names.contains(&name)
+    }
+
+    /// The names of the added plugins, in the order they were built.
+    pub fn plugin_names(&self) -> &[&'static str] {
+        &self.plugin_names
+    }
+
+    pub fn contains_resource<R: Resource>(&self) -> bool {
+        self.resource_bank.contains_resource::<R>()
+    }
+
     pub fn get_resource<R: Resource>(&self) -> Res<R> {
-        RwLockReadGuard::map(
-            self.resources.get(&TypeId::of::<R>()).unwrap().read(),
-            |r| r.downcast_ref().unwrap(),
-        )
+        self.resource_bank.get_resource()
     }
 
-    pub fn get_resource_mut<R: Resource>(&self) -> ResMut<R>
-    where
-        R: Resource,
-    {
-        RwLockWriteGuard::map(
-            self.resources.get(&TypeId::of::<R>()).unwrap().write(),
-            |r| r.downcast_mut().unwrap(),
-        )
+    pub fn get_resource_mut<R: Resource>(&self) -> ResMut<R> {
+        self.resource_bank.get_resource_mut()
+    }
+
+    /// Registers the resource with the [`Inspector`], adding the inspector if it doesn't exist
+    /// yet.
+    pub fn register_inspectable<R: Resource + Reflect>(&mut self) -> &mut Self {
+        if !self.contains_resource::<Inspector>() {
+            self.add_resource(Inspector::new());
+        }
+        self.get_resource_mut::<Inspector>().register::<R>();
+        self
+    }
+
+    /// Adds the [`Events<T>`] resource, which is updated at the end of every frame.
+    pub fn add_event<T: Send + Sync + 'static>(&mut self) -> &mut Self {
+        if !self.contains_resource::<Events<T>>() {
+            self.add_resource(Events::<T>::new());
+            self.event_updaters.push(Events::<T>::update_system);
+        }
+        self
+    }
+
+    /// Adds the [`state::State<S>`] and [`state::NextState<S>`] resources, starting in the
+    /// `initial` state.
+    pub fn add_state<S: AppState>(&mut self, initial: S) -> &mut Self {
+        if self.state_schedule_builder::<S>().is_some() {
+            return self;
+        }
+
+        let (state, next_state) = state::new_state_resources(initial);
+        self.add_resource(state);
+        self.add_resource(next_state);
+        self.state_schedules
+            .push((TypeId::of::<S>(), state::new_state_schedule_builder::<S>()));
+        self
+    }
+
+    /// Adds a system that runs every frame while the app is in `state`.
+    pub fn add_state_system<S: AppState, M>(
+        &mut self,
+        state: S,
+        system: impl IntoScheduleSystemConfig<M>,
+    ) -> &mut Self {
+        self.add_state_schedule_system(StateScheduleKind::Update, state, system)
+    }
+
+    /// Adds a system that runs once when `state` is entered.
+    pub fn on_enter<S: AppState, M>(
+        &mut self,
+        state: S,
+        system: impl IntoScheduleSystemConfig<M>,
+    ) -> &mut Self {
+        self.add_state_schedule_system(StateScheduleKind::Enter, state, system)
+    }
+
+    /// Adds a system that runs once when `state` is exited.
+    pub fn on_exit<S: AppState, M>(
+        &mut self,
+        state: S,
+        system: impl IntoScheduleSystemConfig<M>,
+    ) -> &mut Self {
+        self.add_state_schedule_system(StateScheduleKind::Exit, state, system)
+    }
+
+    fn add_state_schedule_system<S: AppState, M>(
+        &mut self,
+        kind: StateScheduleKind,
+        state: S,
+        system: impl IntoScheduleSystemConfig<M>,
+    ) -> &mut Self {
+        let builder = self.state_schedule_builder::<S>().unwrap_or_else(|| {
+            panic!(
+                "State {} must be added before adding systems to it.",
+                std::any::type_name::<S>()
+            )
+        });
+        state::add_state_system(builder, kind, state, system);
+        self
+    }
+
+    fn state_schedule_builder<S: AppState>(
+        &mut self,
+    ) -> Option<&mut (dyn StateScheduleBuilder + 'static)> {
+        self.state_schedules
+            .iter_mut()
+            .find(|(type_id, _)| *type_id == TypeId::of::<S>())
+            .map(|(_, builder)| builder.as_mut())
+    }
+
+    /// Replaces the schedule of the update stage, schedules are built and validated when the app
+    /// is run.
+    pub fn set_schedule(&mut self, schedule: ScheduleBuilder) {
+        self.stage_graph.set_schedule(UPDATE_STAGE, schedule);
+    }
+
+    /// Adds a system to the update stage.
+    pub fn add_system<M>(&mut self, system: impl IntoScheduleSystemConfig<M>) -> &mut Self {
+        self.add_system_to_stage(system, UPDATE_STAGE)
     }
 
-    pub fn set_schedule(&mut self, schedule: impl Into<Schedule>) {
-        self.schedule = Some(schedule.into());
+    pub fn add_system_to_stage<M>(
+        &mut self,
+        system: impl IntoScheduleSystemConfig<M>,
+        stage: &str,
+    ) -> &mut Self {
+        self.stage_graph
+            .schedule_mut(stage)
+            .unwrap_or_else(|| panic!("Stage {} doesn't exist.", stage))
+            .add_task(system);
+        self
+    }
+
+    /// Adds a stage after all other stages.
+    pub fn add_stage(&mut self, stage: impl Into<String>) -> &mut Self {
+        self.stage_graph.add_stage(stage);
+        self
+    }
+
+    /// Adds a stage right after `target`, which is either a stage or a stage set.
+    pub fn add_stage_after(&mut self, stage: impl Into<String>, target: &str) -> &mut Self {
+        self.stage_graph.add_stage_after(stage, target);
+        self
+    }
+
+    /// Adds a stage right before `target`, which is either a stage or a stage set.
+    pub fn add_stage_before(&mut self, stage: impl Into<String>, target: &str) -> &mut Self {
+        self.stage_graph.add_stage_before(stage, target);
+        self
+    }
+
+    pub fn add_stage_to_set(&mut self, stage: &str, set: impl Into<String>) -> &mut Self {
+        self.stage_graph.add_stage_to_set(stage, set);
+        self
+    }
+
+    /// Runs the stage again and again within a frame for as long as `condition` returns true,
+    /// see [`StageGraph::repeat_stage_while`].
+    pub fn repeat_stage_while(
+        &mut self,
+        stage: &str,
+        condition: impl FnMut(&ResourceBank) -> bool + 'static,
+    ) -> &mut Self {
+        self.stage_graph.repeat_stage_while(stage, condition);
+        self
+    }
+
+    pub fn stage_graph(&self) -> &StageGraph {
+        &self.stage_graph
+    }
+
+    /// Adds a system that runs once when the app shuts down, shutdown systems are ordered the
+    /// same way as the systems of a schedule.
+    pub fn add_shutdown_system<M>(
+        &mut self,
+        system: impl IntoScheduleSystemConfig<M>,
+    ) -> &mut Self {
+        self.shutdown_schedule.add_task(system);
+        self
     }
 
+    /// Sets the function that drives the app, by default the schedule is executed in a loop
+    /// until [`AppExit`] is requested.
     pub fn set_entry_point<E>(&mut self, entry_point: E)
     where
         E: FnOnce(Application) + 'static,
@@ -60,22 +281,66 @@ impl AppBuilder {
 
     pub fn run(self) {
         let app = Application {
-            resource_bank: ResourceBank::new(self.resources),
+            resource_bank: self.resource_bank,
             schedule_executor: ScheduleExecutor::new(),
-            schedule: self.schedule.expect("No schedule was defined"),
+            stages: self
+                .stage_graph
+                .build()
+                .unwrap_or_else(|error| panic!("Failed to build a stage schedule: {}", error)),
+            event_updaters: self.event_updaters,
+            state_drivers: self
+                .state_schedules
+                .into_iter()
+                .map(|(_, builder)| builder.build())
+                .collect::<Result<_, _>>()
+                .unwrap_or_else(|error| panic!("Failed to build a state schedule: {}", error)),
+            shutdown_schedule: self
+                .shutdown_schedule
+                .build()
+                .unwrap_or_else(|error| panic!("Failed to build the shutdown schedule: {}", error)),
         };
 
-        self.entry_point.expect("No entry point was defined")(app);
+        match self.entry_point {
+            Some(entry_point) => entry_point(app),
+            None => Application::run_loop(app),
+        }
     }
 }
 
 pub struct Application {
     resource_bank: ResourceBank,
     schedule_executor: ScheduleExecutor,
-    schedule: Schedule,
+    stages: Vec<Stage>,
+    event_updaters: Vec<fn(&ResourceBank)>,
+    state_drivers: Vec<Box<dyn StateDriver>>,
+    shutdown_schedule: Schedule,
 }
 
 impl Application {
+    /// Executes the schedule until [`AppExit`] is requested, then shuts down.
+    pub fn run_loop(mut app: Application) {
+        while !app.should_exit() {
+            app.execute_schedule();
+        }
+        app.shutdown();
+    }
+
+    pub fn should_exit(&self) -> bool {
+        self.resource_bank.get_resource::<AppExit>().is_requested()
+    }
+
+    /// Runs the shutdown systems before dropping the resources.
+    pub fn shutdown(mut self) {
+        log::debug!("Shutting down");
+        self.schedule_executor
+            .execute(&mut self.shutdown_schedule, &self.resource_bank);
+        CommandQueue::apply(&mut self.resource_bank);
+    }
+
+    pub fn contains_resource<R: Resource>(&self) -> bool {
+        self.resource_bank.contains_resource::<R>()
+    }
+
     pub fn get_resource<R: Resource>(&self) -> Res<R> {
         self.resource_bank.get_resource()
     }
@@ -85,7 +350,105 @@ impl Application {
     }
 
     pub fn execute_schedule(&mut self) {
-        self.schedule_executor
-            .execute(&mut self.schedule, &self.resource_bank);
+        pyrite_util::profile_frame!();
+        let frame_start = Instant::now();
+
+        Inspector::sync(&self.resource_bank);
+        for state_driver in &mut self.state_drivers {
+            state_driver.apply_transition(&mut self.schedule_executor, &self.resource_bank);
+            record_system_timings(&self.schedule_executor, &self.resource_bank);
+        }
+        CommandQueue::apply(&mut self.resource_bank);
+
+        for stage in &mut self.stages {
+            pyrite_util::profile_scope!("stage", stage.name.as_str());
+            let stage_start = Instant::now();
+
+            match &mut stage.repeat_condition {
+                Some(repeat_condition) => {
+                    while repeat_condition(&self.resource_bank) {
+                        self.schedule_executor
+                            .execute(&mut stage.schedule, &self.resource_bank);
+                        record_system_timings(&self.schedule_executor, &self.resource_bank);
+                        // Every run sees the commands of the previous one, like separate stages.
+                        CommandQueue::apply(&mut self.resource_bank);
+                    }
+                }
+                None => {
+                    self.schedule_executor
+                        .execute(&mut stage.schedule, &self.resource_bank);
+                    record_system_timings(&self.schedule_executor, &self.resource_bank);
+                }
+            }
+
+            // State systems run as part of the update stage.
+            if stage.name == UPDATE_STAGE {
+                for state_driver in &mut self.state_drivers {
+                    state_driver.run(&mut self.schedule_executor, &self.resource_bank);
+                    record_system_timings(&self.schedule_executor, &self.resource_bank);
+                }
+            }
+
+            if self.resource_bank.contains_resource::<DiagnosticsStore>() {
+                self.resource_bank
+                    .get_resource_mut::<DiagnosticsStore>()
+                    .record_stage(&stage.name, stage_start.elapsed());
+            }
+            CommandQueue::apply(&mut self.resource_bank);
+        }
+
+        for event_updater in &self.event_updaters {
+            event_updater(&self.resource_bank);
+        }
+
+        if self.resource_bank.contains_resource::<DiagnosticsStore>() {
+            self.resource_bank
+                .get_resource_mut::<DiagnosticsStore>()
+                .finish_frame(frame_start.elapsed());
+        }
+    }
+}
+
+fn record_system_timings(schedule_executor: &ScheduleExecutor, resource_bank: &ResourceBank) {
+    if !resource_bank.contains_resource::<DiagnosticsStore>() {
+        return;
+    }
+
+    let mut diagnostics = resource_bank.get_resource_mut::<DiagnosticsStore>();
+    for (name, time) in schedule_executor.system_timings() {
+        diagnostics.record_system(name, *time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Frames(u32);
+    impl Resource for Frames {}
+
+    fn count_frames(mut frames: ResMut<Frames>, mut app_exit: ResMut<AppExit>) {
+        frames.0 += 1;
+        if frames.0 == 3 {
+            app_exit.request();
+        }
+    }
+
+    fn check_frames(frames: Res<Frames>) {
+        assert_eq!(frames.0, 3);
+    }
+
+    #[test]
+    fn exit_runs_shutdown_systems() {
+        let mut schedule = ScheduleBuilder::new();
+        schedule.add_task(count_frames);
+
+        let mut app_builder = AppBuilder::new();
+        app_builder
+            .add_resource(Frames::default())
+            .add_shutdown_system(check_frames)
+            .set_schedule(schedule);
+        app_builder.run();
     }
 }
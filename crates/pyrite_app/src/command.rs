@@ -0,0 +1,104 @@
+use std::any::TypeId;
+
+use parking_lot::Mutex;
+
+use crate::{
+    resource::{Res, Resource, ResourceBank, SystemTicks},
+    system::{ResourceDependency, SystemParam},
+};
+
+type Command = Box<dyn FnOnce(&mut ResourceBank) + Send>;
+
+/// The queue of commands sent by systems, applied by the app once the current schedule has
+/// finished executing.
+#[derive(Default)]
+pub struct CommandQueue {
+    commands: Mutex<Vec<Command>>,
+}
+
+impl Resource for CommandQueue {}
+
+impl CommandQueue {
+    pub fn push(&self, command: impl FnOnce(&mut ResourceBank) + Send + 'static) {
+        self.commands.lock().push(Box::new(command));
+    }
+
+    pub(crate) fn apply(resource_bank: &mut ResourceBank) {
+        let commands =
+            std::mem::take(&mut *resource_bank.get_resource::<CommandQueue>().commands.lock());
+        for command in commands {
+            command(resource_bank);
+        }
+    }
+}
+
+/// Queues structural changes to the app, such as adding or removing resources.
+///
+/// The commands are deferred until the schedule the system is part of has finished, since the
+/// resource bank can't be changed while systems are borrowing from it. Multiple systems can use
+/// commands at the same time without conflicting.
+pub struct Commands<'rb> {
+    queue: Res<'rb, CommandQueue>,
+}
+
+impl Commands<'_> {
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) {
+        self.queue
+            .push(move |resource_bank| resource_bank.insert_resource(resource));
+    }
+
+    pub fn remove_resource<R: Resource>(&mut self) {
+        self.queue.push(|resource_bank| {
+            resource_bank.remove_resource::<R>();
+        });
+    }
+
+    /// Queues a custom command with mutable access to the resource bank.
+    pub fn add(&mut self, command: impl FnOnce(&mut ResourceBank) + Send + 'static) {
+        self.queue.push(command);
+    }
+}
+
+impl SystemParam for Commands<'_> {
+    type Item<'rb> = Commands<'rb>;
+
+    fn from_resource_bank(
+        resource_bank: &ResourceBank,
+        system_ticks: SystemTicks,
+    ) -> Self::Item<'_> {
+        Commands {
+            queue: resource_bank.get_resource_with_ticks(system_ticks),
+        }
+    }
+
+    fn dependencies() -> Vec<ResourceDependency> {
+        vec![ResourceDependency::Res(TypeId::of::<CommandQueue>())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{schedule::ScheduleBuilder, AppBuilder};
+
+    struct Score(u32);
+    impl Resource for Score {}
+
+    fn add_score(mut commands: Commands) {
+        commands.insert_resource(Score(1));
+    }
+
+    #[test]
+    fn commands_are_applied_after_the_schedule() {
+        let mut schedule = ScheduleBuilder::new();
+        schedule.add_task(add_score);
+
+        let mut app_builder = AppBuilder::new();
+        app_builder.set_schedule(schedule);
+        app_builder.set_entry_point(|mut app| {
+            app.execute_schedule();
+            assert_eq!(app.get_resource::<Score>().0, 1);
+        });
+        app_builder.run();
+    }
+}
@@ -0,0 +1,166 @@
+use std::{any::TypeId, marker::PhantomData};
+
+use crate::{
+    resource::{Resource, ResourceBank},
+    system::{BoxedSystem, ResourceDependency, System},
+};
+
+/// Decides whether a system runs, added to a system with
+/// [`crate::schedule::ScheduleSystemConfig::run_if`].
+pub trait RunCondition: Send + 'static {
+    /// `last_run_tick` is the change tick of the last time the system ran.
+    fn should_run(&mut self, resource_bank: &ResourceBank, last_run_tick: u64) -> bool;
+
+    /// The resources the condition reads, so it isn't evaluated while they're being written to.
+    fn dependencies(&self) -> Vec<ResourceDependency> {
+        Vec::new()
+    }
+}
+
+impl<F: FnMut(&ResourceBank) -> bool + Send + 'static> RunCondition for F {
+    fn should_run(&mut self, resource_bank: &ResourceBank, _last_run_tick: u64) -> bool {
+        self(resource_bank)
+    }
+}
+
+/// Runs the system only if the resource changed since the system last ran.
+pub struct Changed<R: Resource>(PhantomData<fn() -> R>);
+
+impl<R: Resource> Changed<R> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<R: Resource> Default for Changed<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Resource> RunCondition for Changed<R> {
+    fn should_run(&mut self, resource_bank: &ResourceBank, last_run_tick: u64) -> bool {
+        resource_bank.is_resource_changed::<R>(last_run_tick)
+    }
+
+    fn dependencies(&self) -> Vec<ResourceDependency> {
+        vec![ResourceDependency::Res(TypeId::of::<R>())]
+    }
+}
+
+/// Runs the system only if the resource was added since the system last ran.
+pub struct Added<R: Resource>(PhantomData<fn() -> R>);
+
+impl<R: Resource> Added<R> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<R: Resource> Default for Added<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Resource> RunCondition for Added<R> {
+    fn should_run(&mut self, resource_bank: &ResourceBank, last_run_tick: u64) -> bool {
+        resource_bank.is_resource_added::<R>(last_run_tick)
+    }
+
+    fn dependencies(&self) -> Vec<ResourceDependency> {
+        vec![ResourceDependency::Res(TypeId::of::<R>())]
+    }
+}
+
+pub(crate) struct ConditionalSystem {
+    system: BoxedSystem,
+    condition: Box<dyn RunCondition>,
+}
+
+impl ConditionalSystem {
+    pub(crate) fn new_boxed(system: BoxedSystem, condition: Box<dyn RunCondition>) -> BoxedSystem {
+        Box::new(Self { system, condition })
+    }
+}
+
+impl System for ConditionalSystem {
+    fn run(&mut self, resource_bank: &ResourceBank) {
+        if self
+            .condition
+            .should_run(resource_bank, self.system.last_run_tick())
+        {
+            self.system.run(resource_bank);
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.system.name()
+    }
+
+    fn dependencies(&self) -> Vec<ResourceDependency> {
+        let mut dependencies = self.system.dependencies();
+        dependencies.extend(self.condition.dependencies());
+        dependencies
+    }
+
+    fn last_run_tick(&self) -> u64 {
+        self.system.last_run_tick()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        resource::{Res, ResMut},
+        schedule::IntoScheduleSystemConfig,
+        AppBuilder,
+    };
+
+    #[derive(Default)]
+    struct Value(u32);
+    impl Resource for Value {}
+
+    #[derive(Default)]
+    struct Toggle(bool);
+    impl Resource for Toggle {}
+
+    fn write_every_other_frame(mut value: ResMut<Value>, mut toggle: ResMut<Toggle>) {
+        toggle.0 = !toggle.0;
+        if toggle.0 {
+            value.0 += 1;
+        } else {
+            // Writing without change detection isn't seen by the changed condition.
+            value.bypass_change_detection().0 += 0;
+        }
+    }
+
+    fn count_changes(value: Res<Value>, mut changes: ResMut<Changes>) {
+        assert!(value.is_changed());
+        changes.0 += 1;
+    }
+
+    #[derive(Default)]
+    struct Changes(u32);
+    impl Resource for Changes {}
+
+    #[test]
+    fn changed_systems_only_run_after_changes() {
+        let mut app_builder = AppBuilder::new();
+        app_builder
+            .add_resource(Value::default())
+            .add_resource(Toggle::default())
+            .add_resource(Changes::default())
+            .add_system(write_every_other_frame)
+            .add_system(count_changes.run_if(Changed::<Value>::new()));
+        app_builder.set_entry_point(|mut app| {
+            for _ in 0..4 {
+                app.execute_schedule();
+            }
+            // The value is only changed every other frame.
+            assert_eq!(app.get_resource::<Changes>().0, 2);
+        });
+        app_builder.run();
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use crate::resource::Resource;
+
+/// A rolling history of timings.
+pub struct DiagnosticHistory {
+    samples: VecDeque<Duration>,
+    max_samples: usize,
+}
+
+impl DiagnosticHistory {
+    fn new(max_samples: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(max_samples),
+            max_samples,
+        }
+    }
+
+    fn push(&mut self, sample: Duration) {
+        if self.samples.len() == self.max_samples {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn latest(&self) -> Option<Duration> {
+        self.samples.back().copied()
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.iter().max().copied().unwrap_or_default()
+    }
+
+    /// The samples from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.samples.iter().copied()
+    }
+}
+
+/// Per system, per stage and per frame cpu timings recorded by the app, keeping the last
+/// `history_len` frames.
+///
+/// Add the resource to the app to enable recording, the timings of systems that run more than
+/// once in a frame are summed. Gpu timings can be published by a gpu profiler with
+/// [`DiagnosticsStore::record_gpu_scope`].
+pub struct DiagnosticsStore {
+    history_len: usize,
+    systems: HashMap<&'static str, DiagnosticHistory>,
+    stages: HashMap<String, DiagnosticHistory>,
+    gpu_scopes: HashMap<String, DiagnosticHistory>,
+    frame: DiagnosticHistory,
+    current_systems: HashMap<&'static str, Duration>,
+    current_stages: HashMap<String, Duration>,
+    current_gpu_scopes: HashMap<String, Duration>,
+}
+
+impl Resource for DiagnosticsStore {}
+
+impl Default for DiagnosticsStore {
+    fn default() -> Self {
+        Self::new(120)
+    }
+}
+
+impl DiagnosticsStore {
+    pub fn new(history_len: usize) -> Self {
+        assert!(history_len > 0, "The diagnostics history can't be empty.");
+
+        Self {
+            history_len,
+            systems: HashMap::new(),
+            stages: HashMap::new(),
+            gpu_scopes: HashMap::new(),
+            frame: DiagnosticHistory::new(history_len),
+            current_systems: HashMap::new(),
+            current_stages: HashMap::new(),
+            current_gpu_scopes: HashMap::new(),
+        }
+    }
+
+    pub fn system(&self, name: &str) -> Option<&DiagnosticHistory> {
+        self.systems.get(name)
+    }
+
+    pub fn systems(&self) -> impl Iterator<Item = (&'static str, &DiagnosticHistory)> {
+        self.systems.iter().map(|(name, history)| (*name, history))
+    }
+
+    pub fn stage(&self, name: &str) -> Option<&DiagnosticHistory> {
+        self.stages.get(name)
+    }
+
+    pub fn stages(&self) -> impl Iterator<Item = (&str, &DiagnosticHistory)> {
+        self.stages
+            .iter()
+            .map(|(name, history)| (name.as_str(), history))
+    }
+
+    pub fn gpu_scope(&self, name: &str) -> Option<&DiagnosticHistory> {
+        self.gpu_scopes.get(name)
+    }
+
+    pub fn gpu_scopes(&self) -> impl Iterator<Item = (&str, &DiagnosticHistory)> {
+        self.gpu_scopes
+            .iter()
+            .map(|(name, history)| (name.as_str(), history))
+    }
+
+    pub fn frame(&self) -> &DiagnosticHistory {
+        &self.frame
+    }
+
+    /// The `count` systems with the highest average time, slowest first.
+    pub fn slowest_systems(&self, count: usize) -> Vec<(&'static str, Duration)> {
+        let mut systems = self
+            .systems
+            .iter()
+            .map(|(name, history)| (*name, history.average()))
+            .collect::<Vec<_>>();
+        systems.sort_by(|(_, a), (_, b)| b.cmp(a));
+        systems.truncate(count);
+        systems
+    }
+
+    /// Records the gpu time of a scope, the times of scopes recorded more than once in a frame
+    /// are summed.
+    ///
+    /// Gpu timings usually lag a few frames behind since they are only available once the frame
+    /// finished executing.
+    pub fn record_gpu_scope(&mut self, name: &str, time: Duration) {
+        match self.current_gpu_scopes.get_mut(name) {
+            Some(total) => *total += time,
+            None => {
+                self.current_gpu_scopes.insert(name.to_string(), time);
+            }
+        }
+    }
+
+    pub(crate) fn record_system(&mut self, name: &'static str, time: Duration) {
+        *self.current_systems.entry(name).or_default() += time;
+    }
+
+    pub(crate) fn record_stage(&mut self, name: &str, time: Duration) {
+        match self.current_stages.get_mut(name) {
+            Some(total) => *total += time,
+            None => {
+                self.current_stages.insert(name.to_string(), time);
+            }
+        }
+    }
+
+    pub(crate) fn finish_frame(&mut self, frame_time: Duration) {
+        let history_len = self.history_len;
+
+        for (name, time) in self.current_systems.drain() {
+            self.systems
+                .entry(name)
+                .or_insert_with(|| DiagnosticHistory::new(history_len))
+                .push(time);
+        }
+        for (name, time) in self.current_stages.drain() {
+            self.stages
+                .entry(name)
+                .or_insert_with(|| DiagnosticHistory::new(history_len))
+                .push(time);
+        }
+        for (name, time) in self.current_gpu_scopes.drain() {
+            self.gpu_scopes
+                .entry(name)
+                .or_insert_with(|| DiagnosticHistory::new(history_len))
+                .push(time);
+        }
+        self.frame.push(frame_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_is_rolling() {
+        let mut diagnostics = DiagnosticsStore::new(2);
+        for millis in [1, 2, 3] {
+            diagnostics.record_system("system", Duration::from_millis(millis));
+            diagnostics.record_system("system", Duration::from_millis(millis));
+            diagnostics.finish_frame(Duration::from_millis(millis));
+        }
+
+        let system = diagnostics.system("system").unwrap();
+        assert_eq!(system.latest(), Some(Duration::from_millis(6)));
+        assert_eq!(system.average(), Duration::from_millis(5));
+        assert_eq!(diagnostics.frame().max(), Duration::from_millis(3));
+    }
+
+    #[test]
+    fn gpu_scopes_are_summed_per_frame() {
+        let mut diagnostics = DiagnosticsStore::new(4);
+        diagnostics.record_gpu_scope("shadows", Duration::from_micros(300));
+        diagnostics.record_gpu_scope("shadows", Duration::from_micros(200));
+        assert!(diagnostics.gpu_scope("shadows").is_none());
+
+        diagnostics.finish_frame(Duration::from_millis(1));
+        let shadows = diagnostics.gpu_scope("shadows").unwrap();
+        assert_eq!(shadows.latest(), Some(Duration::from_micros(500)));
+    }
+}
@@ -0,0 +1,161 @@
+use std::any::TypeId;
+
+use pyrite_util::DoubleBuffered;
+
+use crate::{
+    resource::{Res, ResMut, Resource, ResourceBank, SystemTicks},
+    system::{ResourceDependency, SystemParam},
+};
+
+/// A double buffered channel of events, added to the app with [`crate::AppBuilder::add_event`].
+///
+/// Events sent during a frame are written to the current buffer and become readable by
+/// [`EventReader`]s on the next frame, after which they are cleared. This way every reader sees
+/// each event exactly once regardless of the order the systems run in.
+pub struct Events<T> {
+    buffers: DoubleBuffered<Vec<T>>,
+}
+
+impl<T: Send + Sync + 'static> Resource for Events<T> {}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self {
+            buffers: DoubleBuffered::from_fn(|_| Vec::new()),
+        }
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.buffers.current_mut().push(event);
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        self.buffers.current_mut().extend(events);
+    }
+
+    /// The events sent during the previous frame.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.buffers.previous().iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.previous().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffers.previous().is_empty()
+    }
+
+    /// Makes the events sent this frame readable and drops the events from the previous frame.
+    pub fn update(&mut self) {
+        self.buffers.swap();
+        self.buffers.current_mut().clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.buffers.iter_mut().for_each(Vec::clear);
+    }
+
+    pub(crate) fn update_system(resource_bank: &ResourceBank)
+    where
+        T: Send + Sync + 'static,
+    {
+        resource_bank.get_resource_mut::<Events<T>>().update();
+    }
+}
+
+/// Reads the events of type `T` sent during the previous frame.
+pub struct EventReader<'rb, T: Send + Sync + 'static> {
+    events: Res<'rb, Events<T>>,
+}
+
+impl<T: Send + Sync + 'static> EventReader<'_, T> {
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.events.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for EventReader<'_, T> {
+    type Item<'rb> = EventReader<'rb, T>;
+
+    fn from_resource_bank(
+        resource_bank: &ResourceBank,
+        system_ticks: SystemTicks,
+    ) -> Self::Item<'_> {
+        EventReader {
+            events: resource_bank.get_resource_with_ticks(system_ticks),
+        }
+    }
+
+    fn dependencies() -> Vec<ResourceDependency> {
+        vec![ResourceDependency::Res(TypeId::of::<Events<T>>())]
+    }
+}
+
+/// Sends events of type `T`, which are readable by [`EventReader`]s on the next frame.
+pub struct EventWriter<'rb, T: Send + Sync + 'static> {
+    events: ResMut<'rb, Events<T>>,
+}
+
+impl<T: Send + Sync + 'static> EventWriter<'_, T> {
+    pub fn send(&mut self, event: T) {
+        self.events.send(event);
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        self.events.send_batch(events);
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for EventWriter<'_, T> {
+    type Item<'rb> = EventWriter<'rb, T>;
+
+    fn from_resource_bank(
+        resource_bank: &ResourceBank,
+        system_ticks: SystemTicks,
+    ) -> Self::Item<'_> {
+        EventWriter {
+            events: resource_bank.get_resource_mut_with_ticks(system_ticks),
+        }
+    }
+
+    fn dependencies() -> Vec<ResourceDependency> {
+        vec![ResourceDependency::ResMut(TypeId::of::<Events<T>>())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_readable_for_one_frame() {
+        let mut events = Events::new();
+        events.send(1);
+        assert!(events.is_empty());
+
+        events.update();
+        events.send(2);
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), vec![1]);
+
+        events.update();
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), vec![2]);
+
+        events.update();
+        assert!(events.is_empty());
+    }
+}
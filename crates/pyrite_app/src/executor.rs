@@ -1,22 +1,106 @@
-use crate::{resource::ResourceBank, schedule::Schedule};
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 
+use parking_lot::Mutex;
+
+use crate::{resource::ResourceBank, schedule::Schedule, system::BoxedSystem};
+
+/// Runs the systems of a schedule on a thread pool.
+///
+/// A system is dispatched as soon as all of the systems it depends on have finished, so systems
+/// that don't conflict over any resources run in parallel.
 pub struct ScheduleExecutor {
     threads: rayon::ThreadPool,
+    system_timings: Vec<(&'static str, Duration)>,
 }
 
 impl ScheduleExecutor {
     pub fn new() -> Self {
         Self {
-            threads: rayon::ThreadPoolBuilder::new().build().unwrap(),
+            threads: rayon::ThreadPoolBuilder::new()
+                .thread_name(|index| format!("pyrite_system_{}", index))
+                .build()
+                .unwrap(),
+            system_timings: Vec::new(),
         }
     }
 
     pub fn execute(&mut self, schedule: &mut Schedule, resource_bank: &ResourceBank) {
-        for system in schedule.systems_mut() {
-            self.threads.install(|| {
-                // println!("[pyrite_app]: Executing system - {}", system.name());
-                system.run(resource_bank);
-            });
+        pyrite_util::profile_scope!("ScheduleExecutor::execute");
+        self.system_timings.clear();
+
+        let system_dependencies = schedule.system_dependencies().clone();
+        let system_count = schedule.systems_mut().len();
+
+        let mut remaining_dependencies = vec![0; system_count];
+        let mut dependents = vec![Vec::new(); system_count];
+        for (index, dependencies) in &system_dependencies {
+            remaining_dependencies[*index as usize] = dependencies.len();
+            for dependency in dependencies {
+                dependents[*dependency as usize].push(*index as usize);
+            }
         }
+
+        let systems = schedule
+            .systems_mut()
+            .iter_mut()
+            .map(Mutex::new)
+            .collect::<Vec<_>>();
+
+        // The scope runs in place so the calling thread can block on finished systems without
+        // taking a worker away from the pool.
+        self.threads.in_place_scope(|scope| {
+            let (finished_sender, finished_receiver) = mpsc::channel();
+
+            let dispatch = |index: usize| {
+                let system = &systems[index];
+                let finished_sender = finished_sender.clone();
+                scope.spawn(move |_| {
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        run_system(&mut system.lock(), resource_bank)
+                    }));
+                    let _ = finished_sender.send((index, result));
+                });
+            };
+
+            for (index, remaining) in remaining_dependencies.iter().enumerate() {
+                if *remaining == 0 {
+                    dispatch(index);
+                }
+            }
+
+            for _ in 0..system_count {
+                let (index, result) = finished_receiver
+                    .recv()
+                    .expect("A system finished without reporting back to the executor.");
+                match result {
+                    Ok(timing) => self.system_timings.push(timing),
+                    Err(panic) => std::panic::resume_unwind(panic),
+                }
+
+                for dependent in &dependents[index] {
+                    remaining_dependencies[*dependent] -= 1;
+                    if remaining_dependencies[*dependent] == 0 {
+                        dispatch(*dependent);
+                    }
+                }
+            }
+        });
+    }
+
+    /// The cpu time of each system during the last execution, in the order they finished.
+    pub fn system_timings(&self) -> &[(&'static str, Duration)] {
+        &self.system_timings
     }
 }
+
+fn run_system(system: &mut BoxedSystem, resource_bank: &ResourceBank) -> (&'static str, Duration) {
+    pyrite_util::profile_scope!("system", system.name());
+    log::trace!("Executing system - {}", system.name());
+
+    let start = Instant::now();
+    system.run(resource_bank);
+    (system.name(), start.elapsed())
+}
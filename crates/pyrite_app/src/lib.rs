@@ -1,15 +1,38 @@
+// Lets the derive macros, which name `pyrite_app`, be used within the crate.
+extern crate self as pyrite_app;
+
 mod app;
 pub use app::*;
 
+pub mod command;
+pub mod condition;
+pub mod diagnostics;
+pub mod event;
 pub mod executor;
+pub mod plugin;
+pub mod reflect;
 pub mod resource;
 pub mod schedule;
+pub mod stage;
+pub mod state;
 pub mod system;
+pub mod task;
 
 pub mod prelude {
     pub use crate::{
-        app::{AppBuilder, Application},
+        app::{AppBuilder, AppExit, Application},
+        command::Commands,
+        condition::{Added, Changed},
+        diagnostics::DiagnosticsStore,
+        event::{EventReader, EventWriter, Events},
+        plugin::Plugin,
+        reflect::{Inspector, Reflect},
         resource::{Res, ResMut, Resource},
+        schedule::{IntoScheduleSystemConfig, ScheduleBuilder},
+        stage::{POST_UPDATE_STAGE, PRE_UPDATE_STAGE, UPDATE_STAGE},
+        state::{AppState, NextState, State},
+        system::SystemParam,
+        task::{AsyncTaskPool, Task},
     };
 }
 
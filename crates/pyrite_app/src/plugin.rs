@@ -0,0 +1,59 @@
+use crate::AppBuilder;
+
+/// A reusable piece of app setup, e.g. adding the resources and systems of a renderer.
+///
+/// Plugins are built in the order they're added with [`AppBuilder::add_plugin`], so a plugin can
+/// rely on the resources of the plugins added before it. A plugin may also add the plugins it
+/// depends on from its own `build`.
+pub trait Plugin: 'static {
+    fn build(&self, app_builder: &mut AppBuilder);
+
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Whether adding the plugin more than once is an error, by default plugins are unique.
+    fn is_unique(&self) -> bool {
+        true
+    }
+}
+
+impl<F: Fn(&mut AppBuilder) + 'static> Plugin for F {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        self(app_builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct A;
+    impl Plugin for A {
+        fn build(&self, app_builder: &mut AppBuilder) {
+            app_builder.add_plugin_once(B);
+        }
+    }
+
+    struct B;
+    impl Plugin for B {
+        fn build(&self, _app_builder: &mut AppBuilder) {}
+    }
+
+    #[test]
+    fn plugins_are_built_in_order() {
+        let mut app_builder = AppBuilder::new();
+        app_builder.add_plugin(A).add_plugin_once(B);
+
+        assert_eq!(
+            app_builder.plugin_names(),
+            &[std::any::type_name::<A>(), std::any::type_name::<B>()]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate_plugins_panic() {
+        AppBuilder::new().add_plugin(B).add_plugin(B);
+    }
+}
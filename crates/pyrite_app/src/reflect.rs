@@ -0,0 +1,274 @@
+use std::{
+    any::TypeId,
+    fmt::{Display, Formatter},
+};
+
+pub use pyrite_app_macros::Reflect;
+
+use crate::resource::{Resource, ResourceBank};
+
+/// A snapshot of a reflected value that debug tools can display and edit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReflectValue {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Struct(Vec<(&'static str, ReflectValue)>),
+    /// A value that can't be viewed or edited, holding its type name.
+    Opaque(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReflectError {
+    FieldNotFound(String),
+    TypeMismatch {
+        expected: &'static str,
+        found: ReflectValue,
+    },
+}
+
+impl Display for ReflectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReflectError::FieldNotFound(path) => write!(f, "Field not found: {}", path),
+            ReflectError::TypeMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Expected a value of type {}, found {:?}",
+                    expected, found
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReflectError {}
+
+/// Runtime access to a value's fields, implemented with `#[derive(Reflect)]` for structs with
+/// named fields. Fields marked with `#[reflect(ignore)]` are skipped.
+pub trait Reflect: 'static {
+    fn reflect_value(&self) -> ReflectValue;
+
+    fn apply(&mut self, value: &ReflectValue) -> Result<(), ReflectError>;
+
+    fn field_names(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+}
+
+impl dyn Reflect {
+    /// Gets a nested field by a dot separated path, e.g. `camera.fov`.
+    pub fn path(&self, path: &str) -> Option<&dyn Reflect> {
+        path.split('.')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |value, name| value.field(name))
+    }
+
+    pub fn path_mut(&mut self, path: &str) -> Option<&mut dyn Reflect> {
+        path.split('.')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |value, name| value.field_mut(name))
+    }
+
+    /// Applies the value to the nested field at `path`.
+    pub fn apply_path(&mut self, path: &str, value: &ReflectValue) -> Result<(), ReflectError> {
+        self.path_mut(path)
+            .ok_or_else(|| ReflectError::FieldNotFound(path.to_string()))?
+            .apply(value)
+    }
+}
+
+macro_rules! impl_reflect_value {
+    ($variant:ident, $as:ty, $($ty:ty),*) => {
+        $(
+            impl Reflect for $ty {
+                fn reflect_value(&self) -> ReflectValue {
+                    ReflectValue::$variant(*self as $as)
+                }
+
+                fn apply(&mut self, value: &ReflectValue) -> Result<(), ReflectError> {
+                    match value {
+                        ReflectValue::Int(v) => *self = *v as $ty,
+                        ReflectValue::UInt(v) => *self = *v as $ty,
+                        ReflectValue::Float(v) => *self = *v as $ty,
+                        _ => {
+                            return Err(ReflectError::TypeMismatch {
+                                expected: std::any::type_name::<$ty>(),
+                                found: value.clone(),
+                            })
+                        }
+                    }
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_reflect_value!(Int, i64, i8, i16, i32, i64, isize);
+impl_reflect_value!(UInt, u64, u8, u16, u32, u64, usize);
+impl_reflect_value!(Float, f64, f32, f64);
+
+impl Reflect for bool {
+    fn reflect_value(&self) -> ReflectValue {
+        ReflectValue::Bool(*self)
+    }
+
+    fn apply(&mut self, value: &ReflectValue) -> Result<(), ReflectError> {
+        match value {
+            ReflectValue::Bool(v) => *self = *v,
+            _ => {
+                return Err(ReflectError::TypeMismatch {
+                    expected: "bool",
+                    found: value.clone(),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for String {
+    fn reflect_value(&self) -> ReflectValue {
+        ReflectValue::String(self.clone())
+    }
+
+    fn apply(&mut self, value: &ReflectValue) -> Result<(), ReflectError> {
+        match value {
+            ReflectValue::String(v) => *self = v.clone(),
+            _ => {
+                return Err(ReflectError::TypeMismatch {
+                    expected: "String",
+                    found: value.clone(),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A resource as seen by the [`Inspector`] during the last sync.
+pub struct InspectedResource {
+    pub type_id: TypeId,
+    pub name: &'static str,
+    pub value: ReflectValue,
+}
+
+struct InspectorRegistration {
+    type_id: TypeId,
+    name: &'static str,
+    snapshot: fn(&ResourceBank) -> ReflectValue,
+    apply: fn(&ResourceBank, &str, &ReflectValue) -> Result<(), ReflectError>,
+}
+
+struct PendingEdit {
+    type_id: TypeId,
+    path: String,
+    value: ReflectValue,
+}
+
+/// Lists the registered reflectable resources and queues edits to them.
+///
+/// The inspector is synced by the application before the schedule is executed, applying the
+/// queued edits and refreshing the resource snapshots that debug uis display.
+#[derive(Default)]
+pub struct Inspector {
+    registrations: Vec<InspectorRegistration>,
+    resources: Vec<InspectedResource>,
+    pending_edits: Vec<PendingEdit>,
+    errors: Vec<ReflectError>,
+}
+
+impl Resource for Inspector {}
+
+impl Inspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<R: Resource + Reflect>(&mut self) {
+        if self.is_registered::<R>() {
+            return;
+        }
+
+        self.registrations.push(InspectorRegistration {
+            type_id: TypeId::of::<R>(),
+            name: std::any::type_name::<R>(),
+            snapshot: |resource_bank| resource_bank.get_resource::<R>().reflect_value(),
+            apply: |resource_bank, path, value| {
+                let mut resource = resource_bank.get_resource_mut::<R>();
+                (&mut *resource as &mut dyn Reflect).apply_path(path, value)
+            },
+        });
+    }
+
+    pub fn is_registered<R: Resource>(&self) -> bool {
+        self.registrations
+            .iter()
+            .any(|registration| registration.type_id == TypeId::of::<R>())
+    }
+
+    /// The registered resources as of the last sync.
+    pub fn resources(&self) -> &[InspectedResource] {
+        &self.resources
+    }
+
+    /// Queues an edit to the field at the dot separated `path` of the resource, an empty path
+    /// edits the whole resource.
+    pub fn set(&mut self, type_id: TypeId, path: impl Into<String>, value: ReflectValue) {
+        self.pending_edits.push(PendingEdit {
+            type_id,
+            path: path.into(),
+            value,
+        });
+    }
+
+    /// Takes the errors from edits that failed to apply during the last sync.
+    pub fn take_errors(&mut self) -> Vec<ReflectError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub(crate) fn sync(resource_bank: &ResourceBank) {
+        if !resource_bank.contains_resource::<Inspector>() {
+            return;
+        }
+
+        let mut inspector = resource_bank.get_resource_mut::<Inspector>();
+        let inspector = &mut *inspector;
+
+        for edit in std::mem::take(&mut inspector.pending_edits) {
+            let Some(registration) = inspector
+                .registrations
+                .iter()
+                .find(|registration| registration.type_id == edit.type_id)
+            else {
+                continue;
+            };
+
+            if let Err(error) = (registration.apply)(resource_bank, &edit.path, &edit.value) {
+                inspector.errors.push(error);
+            }
+        }
+
+        inspector.resources = inspector
+            .registrations
+            .iter()
+            .filter(|registration| registration.type_id != TypeId::of::<Inspector>())
+            .map(|registration| InspectedResource {
+                type_id: registration.type_id,
+                name: registration.name,
+                value: (registration.snapshot)(resource_bank),
+            })
+            .collect();
+    }
+}
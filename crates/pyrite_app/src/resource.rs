@@ -1,4 +1,9 @@
-use std::{any::TypeId, collections::HashMap};
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use downcast::{downcast, Any};
 
@@ -9,77 +14,230 @@ pub use pyrite_app_macros::Resource;
 
 pub(crate) type BoxedResource = Box<dyn Resource>;
 
-pub type Res<'rb, R> = MappedRwLockReadGuard<'rb, R>;
-pub type ResMut<'rb, R> = MappedRwLockWriteGuard<'rb, R>;
-
 pub trait Resource: Any + Send + Sync {}
 downcast!(dyn Resource);
 
-// reemove this and just put it in the ystem param implementation because there is no need to
-// define it twice and the system param implemntation defines the dependencies.
-pub(crate) trait FromResourceBank
-where
-    Self: Sized,
-{
-    fn from_resource_bank(resource_bank: &ResourceBank) -> Res<Self>;
-    fn from_resource_bank_mut(resource_bank: &ResourceBank) -> ResMut<Self>;
+/// The ticks of the system fetching a resource, used to check if the resource changed since the
+/// system last ran.
+///
+/// The resource bank's change tick is incremented every time a system runs, a resource is
+/// changed if it was mutably dereferenced at a later tick than the system's last run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemTicks {
+    pub last_run: u64,
+    pub this_run: u64,
 }
 
-impl<R> FromResourceBank for R
-where
-    R: Resource,
-{
-    fn from_resource_bank(resource_bank: &ResourceBank) -> Res<Self> {
-        RwLockReadGuard::map(
-            resource_bank
-                .resources
-                .get(&TypeId::of::<R>())
-                .expect(&format!(
-                    "Resource {} is not in the resource bank.",
-                    std::any::type_name::<R>()
-                ))
-                .read(),
-            |r| r.downcast_ref().unwrap(),
-        )
-    }
-    fn from_resource_bank_mut(resource_bank: &ResourceBank) -> ResMut<Self> {
-        RwLockWriteGuard::map(
-            resource_bank
-                .resources
-                .get(&TypeId::of::<R>())
-                .expect(&format!(
-                    "Resource {} is not in the resource bank.",
-                    std::any::type_name::<R>()
-                ))
-                .write(),
-            |r| r.downcast_mut().unwrap(),
-        )
+struct ResourceCell {
+    resource: RwLock<BoxedResource>,
+    added_tick: AtomicU64,
+    changed_tick: AtomicU64,
+}
+
+impl ResourceCell {
+    fn new(resource: BoxedResource, tick: u64) -> Self {
+        Self {
+            resource: RwLock::new(resource),
+            added_tick: AtomicU64::new(tick),
+            changed_tick: AtomicU64::new(tick),
+        }
+    }
+}
+
+struct Ticks<'rb> {
+    added: &'rb AtomicU64,
+    changed: &'rb AtomicU64,
+    system_ticks: SystemTicks,
+}
+
+impl Ticks<'_> {
+    fn is_added(&self) -> bool {
+        self.added.load(Ordering::Relaxed) > self.system_ticks.last_run
+    }
+
+    fn is_changed(&self) -> bool {
+        self.changed.load(Ordering::Relaxed) > self.system_ticks.last_run
+    }
+}
+
+/// Shared access to a resource.
+pub struct Res<'rb, R> {
+    value: MappedRwLockReadGuard<'rb, R>,
+    ticks: Ticks<'rb>,
+}
+
+impl<R> Res<'_, R> {
+    /// Returns true if the resource was added since the system last ran.
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_added()
+    }
+
+    /// Returns true if the resource was mutably accessed since the system last ran.
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed()
+    }
+}
+
+impl<R> Deref for Res<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.value
+    }
+}
+
+/// Exclusive access to a resource, mutably dereferencing it marks the resource as changed.
+pub struct ResMut<'rb, R> {
+    value: MappedRwLockWriteGuard<'rb, R>,
+    ticks: Ticks<'rb>,
+}
+
+impl<R> ResMut<'_, R> {
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_added()
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed()
+    }
+
+    /// Mutably accesses the resource without marking it as changed.
+    pub fn bypass_change_detection(&mut self) -> &mut R {
+        &mut self.value
+    }
+}
+
+impl<R> Deref for ResMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.value
+    }
+}
+
+impl<R> DerefMut for ResMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut R {
+        self.ticks
+            .changed
+            .store(self.ticks.system_ticks.this_run, Ordering::Relaxed);
+        &mut self.value
     }
 }
 
 pub struct ResourceBank {
-    resources: HashMap<TypeId, RwLock<BoxedResource>>,
+    resources: HashMap<TypeId, ResourceCell>,
+    change_tick: AtomicU64,
 }
 
 impl ResourceBank {
-    pub fn new(resources: HashMap<TypeId, RwLock<BoxedResource>>) -> Self {
-        Self { resources }
+    pub fn new() -> Self {
+        Self {
+            resources: HashMap::new(),
+            change_tick: AtomicU64::new(1),
+        }
+    }
+
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) {
+        let tick = self.change_tick();
+        self.resources.insert(
+            TypeId::of::<R>(),
+            ResourceCell::new(Box::new(resource), tick),
+        );
+    }
+
+    /// Removes the resource, returning it if it was in the resource bank.
+    pub fn remove_resource<R: Resource>(&mut self) -> Option<R> {
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .map(|cell| *cell.resource.into_inner().downcast().ok().unwrap())
+    }
+
+    pub fn contains_resource<R: Resource>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
     }
 
     pub fn get_resource<R: Resource>(&self) -> Res<R> {
-        RwLockReadGuard::map(
-            self.resources.get(&TypeId::of::<R>()).unwrap().read(),
-            |r| r.downcast_ref().unwrap(),
-        )
-    }
-
-    pub fn get_resource_mut<R: Resource>(&self) -> ResMut<R>
-    where
-        R: Resource,
-    {
-        RwLockWriteGuard::map(
-            self.resources.get(&TypeId::of::<R>()).unwrap().write(),
-            |r| r.downcast_mut().unwrap(),
-        )
+        self.get_resource_with_ticks(self.outside_system_ticks())
+    }
+
+    pub fn get_resource_mut<R: Resource>(&self) -> ResMut<R> {
+        self.get_resource_mut_with_ticks(self.outside_system_ticks())
+    }
+
+    pub fn change_tick(&self) -> u64 {
+        self.change_tick.load(Ordering::Relaxed)
+    }
+
+    /// Increments the change tick, returning the tick of the system about to run.
+    pub(crate) fn increment_change_tick(&self) -> u64 {
+        self.change_tick.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Returns true if the resource was mutably accessed after `last_run`.
+    pub fn is_resource_changed<R: Resource>(&self, last_run: u64) -> bool {
+        self.resources
+            .get(&TypeId::of::<R>())
+            .is_some_and(|cell| cell.changed_tick.load(Ordering::Relaxed) > last_run)
+    }
+
+    /// Returns true if the resource was added after `last_run`.
+    pub fn is_resource_added<R: Resource>(&self, last_run: u64) -> bool {
+        self.resources
+            .get(&TypeId::of::<R>())
+            .is_some_and(|cell| cell.added_tick.load(Ordering::Relaxed) > last_run)
+    }
+
+    pub(crate) fn get_resource_with_ticks<R: Resource>(
+        &self,
+        system_ticks: SystemTicks,
+    ) -> Res<'_, R> {
+        let cell = self.cell::<R>();
+        Res {
+            value: RwLockReadGuard::map(cell.resource.read(), |r| r.downcast_ref().unwrap()),
+            ticks: Ticks {
+                added: &cell.added_tick,
+                changed: &cell.changed_tick,
+                system_ticks,
+            },
+        }
+    }
+
+    pub(crate) fn get_resource_mut_with_ticks<R: Resource>(
+        &self,
+        system_ticks: SystemTicks,
+    ) -> ResMut<'_, R> {
+        let cell = self.cell::<R>();
+        ResMut {
+            value: RwLockWriteGuard::map(cell.resource.write(), |r| r.downcast_mut().unwrap()),
+            ticks: Ticks {
+                added: &cell.added_tick,
+                changed: &cell.changed_tick,
+                system_ticks,
+            },
+        }
+    }
+
+    /// Access from outside of a system treats every resource as changed and marks changes at
+    /// the current tick.
+    fn outside_system_ticks(&self) -> SystemTicks {
+        SystemTicks {
+            last_run: 0,
+            this_run: self.change_tick(),
+        }
+    }
+
+    fn cell<R: Resource>(&self) -> &ResourceCell {
+        self.resources.get(&TypeId::of::<R>()).unwrap_or_else(|| {
+            panic!(
+                "Resource {} is not in the resource bank.",
+                std::any::type_name::<R>()
+            )
+        })
+    }
+}
+
+impl Default for ResourceBank {
+    fn default() -> Self {
+        Self::new()
     }
 }
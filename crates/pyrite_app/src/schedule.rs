@@ -1,13 +1,125 @@
-use std::{any::TypeId, collections::HashMap};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt::{Display, Formatter},
+};
 
-use crate::system::{BoxedSystem, SystemFunction, SystemFunctionHandler};
+use crate::{
+    condition::{ConditionalSystem, RunCondition},
+    system::{BoxedSystem, ResourceDependency, SystemFunction, SystemFunctionHandler},
+};
 
+/// A system along with its labels and ordering constraints.
+///
+/// Every system is implicitly labeled with its name, so systems added with the tuple syntax
+/// `(system, dependency)` are ordered after `dependency` as before.
 pub struct ScheduleSystemConfig {
     name: String,
+    labels: Vec<String>,
+    before: Vec<String>,
     system_dependencies: Vec<String>,
     boxed_system: BoxedSystem,
 }
 
+impl ScheduleSystemConfig {
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.labels.push(label.into());
+        self
+    }
+
+    /// Runs this system before all systems with the label.
+    pub fn before(mut self, label: impl Into<String>) -> Self {
+        self.before.push(label.into());
+        self
+    }
+
+    /// Runs this system after all systems with the label.
+    pub fn after(mut self, label: impl Into<String>) -> Self {
+        self.system_dependencies.push(label.into());
+        self
+    }
+
+    pub fn before_system<S: ScheduleTaskDependency<M>, M>(mut self, _system: S) -> Self {
+        self.before.extend(S::collect_dependencies());
+        self
+    }
+
+    pub fn after_system<S: ScheduleTaskDependency<M>, M>(mut self, _system: S) -> Self {
+        self.system_dependencies.extend(S::collect_dependencies());
+        self
+    }
+
+    /// Only runs the system if the condition is met, when called multiple times all of the
+    /// conditions must be met.
+    pub fn run_if(mut self, condition: impl RunCondition) -> Self {
+        self.boxed_system = ConditionalSystem::new_boxed(self.boxed_system, Box::new(condition));
+        self
+    }
+
+    fn has_label(&self, label: &str) -> bool {
+        self.name == label || self.labels.iter().any(|l| l == label)
+    }
+}
+
+pub trait IntoScheduleSystemConfig<M>: Sized {
+    fn into_system_config(self) -> ScheduleSystemConfig;
+
+    fn label(self, label: impl Into<String>) -> ScheduleSystemConfig {
+        self.into_system_config().label(label)
+    }
+
+    fn before(self, label: impl Into<String>) -> ScheduleSystemConfig {
+        self.into_system_config().before(label)
+    }
+
+    fn after(self, label: impl Into<String>) -> ScheduleSystemConfig {
+        self.into_system_config().after(label)
+    }
+
+    fn run_if(self, condition: impl RunCondition) -> ScheduleSystemConfig {
+        self.into_system_config().run_if(condition)
+    }
+}
+
+impl<T: ScheduleTask<M> + 'static, M> IntoScheduleSystemConfig<M> for T {
+    fn into_system_config(self) -> ScheduleSystemConfig {
+        let system_dependencies = T::collect_dependencies();
+        let boxed_system = self.into_boxed_system();
+
+        ScheduleSystemConfig {
+            name: boxed_system.name().to_string(),
+            labels: Vec::new(),
+            before: Vec::new(),
+            system_dependencies,
+            boxed_system,
+        }
+    }
+}
+
+impl IntoScheduleSystemConfig<ScheduleSystemConfig> for ScheduleSystemConfig {
+    fn into_system_config(self) -> ScheduleSystemConfig {
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// The systems whose ordering constraints form a cycle.
+    DependencyCycle(Vec<String>),
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::DependencyCycle(systems) => {
+                write!(f, "Systems have cyclic ordering constraints: {:?}", systems)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
 pub struct ScheduleBuilder {
     systems: Vec<ScheduleSystemConfig>,
 }
@@ -19,39 +131,174 @@ impl ScheduleBuilder {
         }
     }
 
-    pub fn add_task<T: ScheduleTask<M> + 'static, M>(&mut self, schedule_task: T) {
-        let system = schedule_task.into_boxed_system();
-        let system_dependencies = T::collect_dependencies();
+    pub fn add_task<M>(&mut self, schedule_task: impl IntoScheduleSystemConfig<M>) {
+        let system_config = schedule_task.into_system_config();
 
-        println!("Added system: {}", system.name());
-        println!("with dependencies: {:?}", system_dependencies);
+        log::debug!(
+            "Added system: {} with labels: {:?}, before: {:?}, after: {:?}",
+            system_config.name,
+            system_config.labels,
+            system_config.before,
+            system_config.system_dependencies
+        );
 
-        self.systems.push(ScheduleSystemConfig {
-            name: system.name().to_string(),
-            system_dependencies,
-            boxed_system: system,
-        });
+        self.systems.push(system_config);
     }
 
-    pub fn build(self) -> Schedule {
+    /// Builds the schedule, ordering each system after the systems it is constrained to run
+    /// after and after any earlier system it conflicts with over a resource.
+    ///
+    /// Two systems conflict if either of them mutably borrows a resource the other uses, systems
+    /// that don't conflict may be run in parallel by the executor. Conflicting systems without
+    /// an ordering constraint between them run in the order they were added.
+    pub fn build(self) -> Result<Schedule, ScheduleError> {
+        let system_count = self.systems.len();
+
+        let mut system_resource_dependencies = HashMap::new();
+        for (index, system_config) in self.systems.iter().enumerate() {
+            system_resource_dependencies
+                .insert(index as u32, system_config.boxed_system.dependencies());
+        }
+
+        let mut explicit_dependencies = vec![Vec::new(); system_count];
+        for (index, system_config) in self.systems.iter().enumerate() {
+            for label in &system_config.system_dependencies {
+                let labeled = self.labeled_systems(label, index);
+                explicit_dependencies[index].extend(labeled);
+            }
+            for label in &system_config.before {
+                for labeled in self.labeled_systems(label, index) {
+                    explicit_dependencies[labeled].push(index);
+                }
+            }
+        }
+
+        let order = self.ordered_systems(&explicit_dependencies)?;
+        let mut order_positions = vec![0; system_count];
+        for (position, index) in order.iter().enumerate() {
+            order_positions[*index] = position;
+        }
+
+        let mut system_dependencies = HashMap::new();
+        for (index, explicit_dependencies) in explicit_dependencies.into_iter().enumerate() {
+            let mut dependencies = explicit_dependencies
+                .into_iter()
+                .map(|dependency| dependency as u32)
+                .collect::<Vec<_>>();
+
+            let resource_dependencies = &system_resource_dependencies[&(index as u32)];
+            for earlier_index in &order[..order_positions[index]] {
+                let earlier_index = *earlier_index as u32;
+                if !dependencies.contains(&earlier_index)
+                    && resources_conflict(
+                        resource_dependencies,
+                        &system_resource_dependencies[&earlier_index],
+                    )
+                {
+                    dependencies.push(earlier_index);
+                }
+            }
+
+            dependencies.sort_unstable();
+            dependencies.dedup();
+            system_dependencies.insert(index as u32, dependencies);
+        }
+
         let systems = self
             .systems
             .into_iter()
             .map(|system_config| system_config.boxed_system)
             .collect::<Vec<_>>();
 
-        Schedule {
+        Ok(Schedule {
             systems,
-            system_dependencies: HashMap::new(),
-            system_resource_dependencies: HashMap::new(),
+            system_dependencies,
+            system_resource_dependencies,
+        })
+    }
+
+    /// The systems with the label, excluding the system at `index`.
+    fn labeled_systems(&self, label: &str, index: usize) -> Vec<usize> {
+        let labeled = self
+            .systems
+            .iter()
+            .enumerate()
+            .filter(|(other_index, other)| *other_index != index && other.has_label(label))
+            .map(|(other_index, _)| other_index)
+            .collect::<Vec<_>>();
+
+        if labeled.is_empty() {
+            log::warn!(
+                "System {} is ordered relative to {} which is not in the schedule.",
+                self.systems[index].name,
+                label
+            );
         }
+
+        labeled
+    }
+
+    /// Orders the systems so they come after their explicit dependencies, otherwise keeping the
+    /// order they were added in.
+    fn ordered_systems(
+        &self,
+        explicit_dependencies: &[Vec<usize>],
+    ) -> Result<Vec<usize>, ScheduleError> {
+        let mut remaining_dependencies = vec![0; self.systems.len()];
+        let mut dependents = vec![Vec::new(); self.systems.len()];
+        for (index, dependencies) in explicit_dependencies.iter().enumerate() {
+            remaining_dependencies[index] = dependencies.len();
+            for dependency in dependencies {
+                dependents[*dependency].push(index);
+            }
+        }
+
+        let mut ready = remaining_dependencies
+            .iter()
+            .enumerate()
+            .filter(|(_, remaining)| **remaining == 0)
+            .map(|(index, _)| Reverse(index))
+            .collect::<BinaryHeap<_>>();
+
+        let mut order = Vec::with_capacity(self.systems.len());
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+            for dependent in &dependents[index] {
+                remaining_dependencies[*dependent] -= 1;
+                if remaining_dependencies[*dependent] == 0 {
+                    ready.push(Reverse(*dependent));
+                }
+            }
+        }
+
+        if order.len() != self.systems.len() {
+            return Err(ScheduleError::DependencyCycle(
+                remaining_dependencies
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, remaining)| **remaining > 0)
+                    .map(|(index, _)| self.systems[index].name.clone())
+                    .collect(),
+            ));
+        }
+
+        Ok(order)
     }
 }
 
+fn resources_conflict(a: &[ResourceDependency], b: &[ResourceDependency]) -> bool {
+    a.iter().any(|a| {
+        b.iter().any(|b| match (a, b) {
+            (ResourceDependency::Res(_), ResourceDependency::Res(_)) => false,
+            _ => a.type_id() == b.type_id(),
+        })
+    })
+}
+
 pub struct Schedule {
     systems: Vec<BoxedSystem>,
     system_dependencies: HashMap<u32, Vec<u32>>,
-    system_resource_dependencies: HashMap<u32, Vec<TypeId>>,
+    system_resource_dependencies: HashMap<u32, Vec<ResourceDependency>>,
 }
 
 impl Schedule {
@@ -59,11 +306,12 @@ impl Schedule {
         &mut self.systems
     }
 
+    /// The indices of the systems that must finish before each system can run.
     pub fn system_dependencies(&self) -> &HashMap<u32, Vec<u32>> {
         &self.system_dependencies
     }
 
-    pub fn system_resource_dependencies(&self) -> &HashMap<u32, Vec<TypeId>> {
+    pub fn system_resource_dependencies(&self) -> &HashMap<u32, Vec<ResourceDependency>> {
         &self.system_resource_dependencies
     }
 }
@@ -122,3 +370,59 @@ where
         dependencies
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{Res, ResMut, Resource};
+
+    struct A;
+    impl Resource for A {}
+
+    struct B;
+    impl Resource for B {}
+
+    fn read_a(_a: Res<A>) {}
+    fn write_a(_a: ResMut<A>) {}
+    fn write_b(_b: ResMut<B>) {}
+
+    #[test]
+    fn conflicting_systems_are_ordered() {
+        let mut schedule_builder = ScheduleBuilder::new();
+        schedule_builder.add_task(read_a);
+        schedule_builder.add_task(write_b);
+        schedule_builder.add_task(write_a);
+        schedule_builder.add_task(read_a);
+        let schedule = schedule_builder.build().unwrap();
+
+        assert!(schedule.system_dependencies()[&0].is_empty());
+        assert!(schedule.system_dependencies()[&1].is_empty());
+        assert_eq!(schedule.system_dependencies()[&2], vec![0]);
+        assert_eq!(schedule.system_dependencies()[&3], vec![2]);
+    }
+
+    #[test]
+    fn ordering_constraints_override_insertion_order() {
+        let mut schedule_builder = ScheduleBuilder::new();
+        schedule_builder.add_task(read_a.after("writer"));
+        schedule_builder.add_task(write_b);
+        schedule_builder.add_task(write_a.label("writer"));
+        let schedule = schedule_builder.build().unwrap();
+
+        assert_eq!(schedule.system_dependencies()[&0], vec![2]);
+        assert!(schedule.system_dependencies()[&2].is_empty());
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut schedule_builder = ScheduleBuilder::new();
+        schedule_builder.add_task(read_a.label("read").after("write"));
+        schedule_builder.add_task(write_b.label("write").after("other_read"));
+        schedule_builder.add_task(read_a.label("other_read").after("read"));
+
+        assert!(matches!(
+            schedule_builder.build(),
+            Err(ScheduleError::DependencyCycle(_))
+        ));
+    }
+}
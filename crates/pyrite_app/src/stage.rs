@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use crate::{
+    resource::ResourceBank,
+    schedule::{Schedule, ScheduleBuilder, ScheduleError},
+};
+
+pub const PRE_UPDATE_STAGE: &str = "pre_update";
+pub const UPDATE_STAGE: &str = "update";
+pub const POST_UPDATE_STAGE: &str = "post_update";
+
+/// Decides whether a repeating stage runs again, see [`StageGraph::repeat_stage_while`].
+pub type StageRepeatCondition = Box<dyn FnMut(&ResourceBank) -> bool>;
+
+pub(crate) struct Stage {
+    pub name: String,
+    pub schedule: Schedule,
+    pub repeat_condition: Option<StageRepeatCondition>,
+}
+
+/// The ordered stages of an app, each stage having its own schedule.
+///
+/// Stages run one after the other, so every system of a stage finishes before the next stage
+/// starts. Stages can be grouped into named sets, which new stages can then be placed before or
+/// after as a whole, e.g. a plugin can add its stage after every render stage without knowing
+/// what those stages are.
+pub struct StageGraph {
+    stages: Vec<(String, ScheduleBuilder)>,
+    sets: HashMap<String, Vec<String>>,
+    repeat_conditions: HashMap<String, StageRepeatCondition>,
+}
+
+impl StageGraph {
+    pub fn new() -> Self {
+        Self {
+            stages: Vec::new(),
+            sets: HashMap::new(),
+            repeat_conditions: HashMap::new(),
+        }
+    }
+
+    /// Adds a stage after all other stages.
+    pub fn add_stage(&mut self, stage: impl Into<String>) {
+        let stage = stage.into();
+        self.assert_unique(&stage);
+        self.stages.push((stage, ScheduleBuilder::new()));
+    }
+
+    /// Adds a stage right after `target`, which is either a stage or a stage set.
+    pub fn add_stage_after(&mut self, stage: impl Into<String>, target: &str) {
+        let stage = stage.into();
+        self.assert_unique(&stage);
+        let index = self.target_indices(target).max().unwrap() + 1;
+        self.stages.insert(index, (stage, ScheduleBuilder::new()));
+    }
+
+    /// Adds a stage right before `target`, which is either a stage or a stage set.
+    pub fn add_stage_before(&mut self, stage: impl Into<String>, target: &str) {
+        let stage = stage.into();
+        self.assert_unique(&stage);
+        let index = self.target_indices(target).min().unwrap();
+        self.stages.insert(index, (stage, ScheduleBuilder::new()));
+    }
+
+    pub fn add_stage_to_set(&mut self, stage: &str, set: impl Into<String>) {
+        assert!(
+            self.contains_stage(stage),
+            "Tried to add stage {} to a set but it doesn't exist.",
+            stage
+        );
+
+        let members = self.sets.entry(set.into()).or_default();
+        if !members.iter().any(|member| member == stage) {
+            members.push(stage.to_string());
+        }
+    }
+
+    /// Runs the stage again and again within a frame for as long as `condition` returns true.
+    /// The condition is checked before every run, so the stage may not run at all in a frame,
+    /// e.g. a fixed timestep stage running once for every step that is due.
+    pub fn repeat_stage_while(
+        &mut self,
+        stage: &str,
+        condition: impl FnMut(&ResourceBank) -> bool + 'static,
+    ) {
+        assert!(
+            self.contains_stage(stage),
+            "Tried to repeat stage {} but it doesn't exist.",
+            stage
+        );
+        self.repeat_conditions
+            .insert(stage.to_string(), Box::new(condition));
+    }
+
+    pub fn contains_stage(&self, stage: &str) -> bool {
+        self.stages.iter().any(|(name, _)| name == stage)
+    }
+
+    /// The stage names in the order they run.
+    pub fn stage_names(&self) -> impl Iterator<Item = &str> {
+        self.stages.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn schedule_mut(&mut self, stage: &str) -> Option<&mut ScheduleBuilder> {
+        self.stages
+            .iter_mut()
+            .find(|(name, _)| name == stage)
+            .map(|(_, schedule)| schedule)
+    }
+
+    pub(crate) fn set_schedule(&mut self, stage: &str, schedule: ScheduleBuilder) {
+        *self
+            .schedule_mut(stage)
+            .unwrap_or_else(|| panic!("Stage {} doesn't exist.", stage)) = schedule;
+    }
+
+    pub(crate) fn build(mut self) -> Result<Vec<Stage>, ScheduleError> {
+        self.stages
+            .into_iter()
+            .map(|(name, schedule)| {
+                Ok(Stage {
+                    schedule: schedule.build()?,
+                    repeat_condition: self.repeat_conditions.remove(&name),
+                    name,
+                })
+            })
+            .collect()
+    }
+
+    fn target_indices<'a>(&'a self, target: &'a str) -> impl Iterator<Item = usize> + 'a {
+        let members = match self.sets.get(target) {
+            Some(members) => members.iter().map(String::as_str).collect::<Vec<_>>(),
+            None => vec![target],
+        };
+
+        let indices = members
+            .into_iter()
+            .filter_map(|member| self.stages.iter().position(|(name, _)| name == member))
+            .collect::<Vec<_>>();
+        assert!(
+            !indices.is_empty(),
+            "Stage or stage set {} doesn't exist.",
+            target
+        );
+
+        indices.into_iter()
+    }
+
+    fn assert_unique(&self, stage: &str) {
+        assert!(
+            !self.contains_stage(stage),
+            "Stage {} was already added.",
+            stage
+        );
+    }
+}
+
+impl Default for StageGraph {
+    fn default() -> Self {
+        let mut stage_graph = Self::new();
+        stage_graph.add_stage(PRE_UPDATE_STAGE);
+        stage_graph.add_stage(UPDATE_STAGE);
+        stage_graph.add_stage(POST_UPDATE_STAGE);
+        stage_graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_are_inserted_relative_to_targets() {
+        let mut stage_graph = StageGraph::default();
+        stage_graph.add_stage("render");
+        stage_graph.add_stage_to_set(UPDATE_STAGE, "logic");
+        stage_graph.add_stage_to_set(POST_UPDATE_STAGE, "logic");
+
+        stage_graph.add_stage_after("physics", PRE_UPDATE_STAGE);
+        stage_graph.add_stage_after("late", "logic");
+        stage_graph.add_stage_before("early", "logic");
+
+        assert_eq!(
+            stage_graph.stage_names().collect::<Vec<_>>(),
+            vec![
+                PRE_UPDATE_STAGE,
+                "physics",
+                "early",
+                UPDATE_STAGE,
+                POST_UPDATE_STAGE,
+                "late",
+                "render"
+            ]
+        );
+    }
+}
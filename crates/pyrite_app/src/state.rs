@@ -0,0 +1,238 @@
+use std::{any::Any, collections::HashMap, fmt::Debug, hash::Hash};
+
+use crate::{
+    executor::ScheduleExecutor,
+    resource::{Resource, ResourceBank},
+    schedule::{IntoScheduleSystemConfig, Schedule, ScheduleBuilder, ScheduleError},
+};
+
+/// A high level mode of the app, e.g. `Loading`, `MainMenu` or `InGame`, usually an enum.
+///
+/// States are added with [`crate::AppBuilder::add_state`], after which systems can be added that
+/// only run while the app is in a certain state, or when the state is entered or exited.
+pub trait AppState: Debug + Clone + Eq + Hash + Send + Sync + 'static {}
+
+impl<T: Debug + Clone + Eq + Hash + Send + Sync + 'static> AppState for T {}
+
+/// The current state of type `S`.
+pub struct State<S: AppState> {
+    current: S,
+}
+
+impl<S: AppState> Resource for State<S> {}
+
+impl<S: AppState> State<S> {
+    pub fn current(&self) -> &S {
+        &self.current
+    }
+}
+
+/// Requests a transition to another state, which is applied at the start of the next frame.
+pub struct NextState<S: AppState> {
+    next: Option<S>,
+}
+
+impl<S: AppState> Resource for NextState<S> {}
+
+impl<S: AppState> NextState<S> {
+    pub fn set(&mut self, state: S) {
+        self.next = Some(state);
+    }
+
+    pub fn get(&self) -> Option<&S> {
+        self.next.as_ref()
+    }
+}
+
+struct StateScheduleBuilders<S: AppState> {
+    update: HashMap<S, ScheduleBuilder>,
+    enter: HashMap<S, ScheduleBuilder>,
+    exit: HashMap<S, ScheduleBuilder>,
+}
+
+struct StateSchedules<S: AppState> {
+    update: HashMap<S, Schedule>,
+    enter: HashMap<S, Schedule>,
+    exit: HashMap<S, Schedule>,
+    entered_initial_state: bool,
+}
+
+pub(crate) enum StateScheduleKind {
+    Update,
+    Enter,
+    Exit,
+}
+
+/// The type erased schedules of a state, so the app can store the schedules of all of its state
+/// types together.
+pub(crate) trait StateScheduleBuilder {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn build(self: Box<Self>) -> Result<Box<dyn StateDriver>, ScheduleError>;
+}
+
+pub(crate) trait StateDriver {
+    /// Applies a requested transition, running the exit and enter schedules.
+    fn apply_transition(&mut self, executor: &mut ScheduleExecutor, resource_bank: &ResourceBank);
+
+    /// Runs the systems of the current state.
+    fn run(&mut self, executor: &mut ScheduleExecutor, resource_bank: &ResourceBank);
+}
+
+pub(crate) fn add_state_system<S: AppState, M>(
+    builder: &mut dyn StateScheduleBuilder,
+    kind: StateScheduleKind,
+    state: S,
+    system: impl IntoScheduleSystemConfig<M>,
+) {
+    let builders = builder
+        .as_any_mut()
+        .downcast_mut::<StateScheduleBuilders<S>>()
+        .unwrap();
+    let schedules = match kind {
+        StateScheduleKind::Update => &mut builders.update,
+        StateScheduleKind::Enter => &mut builders.enter,
+        StateScheduleKind::Exit => &mut builders.exit,
+    };
+
+    schedules
+        .entry(state)
+        .or_insert_with(ScheduleBuilder::new)
+        .add_task(system);
+}
+
+pub(crate) fn new_state_resources<S: AppState>(initial: S) -> (State<S>, NextState<S>) {
+    (State { current: initial }, NextState { next: None })
+}
+
+pub(crate) fn new_state_schedule_builder<S: AppState>() -> Box<dyn StateScheduleBuilder> {
+    Box::new(StateScheduleBuilders::<S> {
+        update: HashMap::new(),
+        enter: HashMap::new(),
+        exit: HashMap::new(),
+    })
+}
+
+impl<S: AppState> StateScheduleBuilder for StateScheduleBuilders<S> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn build(self: Box<Self>) -> Result<Box<dyn StateDriver>, ScheduleError> {
+        fn build_all<S: AppState>(
+            builders: HashMap<S, ScheduleBuilder>,
+        ) -> Result<HashMap<S, Schedule>, ScheduleError> {
+            builders
+                .into_iter()
+                .map(|(state, builder)| Ok((state, builder.build()?)))
+                .collect()
+        }
+
+        Ok(Box::new(StateSchedules {
+            update: build_all(self.update)?,
+            enter: build_all(self.enter)?,
+            exit: build_all(self.exit)?,
+            entered_initial_state: false,
+        }))
+    }
+}
+
+impl<S: AppState> StateDriver for StateSchedules<S> {
+    fn apply_transition(&mut self, executor: &mut ScheduleExecutor, resource_bank: &ResourceBank) {
+        if !self.entered_initial_state {
+            self.entered_initial_state = true;
+
+            let current = resource_bank.get_resource::<State<S>>().current.clone();
+            if let Some(schedule) = self.enter.get_mut(&current) {
+                executor.execute(schedule, resource_bank);
+            }
+        }
+
+        let Some(next) = resource_bank.get_resource_mut::<NextState<S>>().next.take() else {
+            return;
+        };
+        let current = resource_bank.get_resource::<State<S>>().current.clone();
+        if next == current {
+            return;
+        }
+
+        log::debug!("Transitioning from state {:?} to {:?}", current, next);
+
+        if let Some(schedule) = self.exit.get_mut(&current) {
+            executor.execute(schedule, resource_bank);
+        }
+        resource_bank.get_resource_mut::<State<S>>().current = next.clone();
+        if let Some(schedule) = self.enter.get_mut(&next) {
+            executor.execute(schedule, resource_bank);
+        }
+    }
+
+    fn run(&mut self, executor: &mut ScheduleExecutor, resource_bank: &ResourceBank) {
+        let current = resource_bank.get_resource::<State<S>>().current.clone();
+        if let Some(schedule) = self.update.get_mut(&current) {
+            executor.execute(schedule, resource_bank);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        resource::{Res, ResMut},
+        AppBuilder,
+    };
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum GameState {
+        Loading,
+        InGame,
+    }
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+    impl Resource for Log {}
+
+    fn enter_loading(mut log: ResMut<Log>) {
+        log.0.push("enter_loading");
+    }
+
+    fn finish_loading(mut log: ResMut<Log>, mut next_state: ResMut<NextState<GameState>>) {
+        log.0.push("loading");
+        next_state.set(GameState::InGame);
+    }
+
+    fn exit_loading(mut log: ResMut<Log>) {
+        log.0.push("exit_loading");
+    }
+
+    fn in_game(mut log: ResMut<Log>, _state: Res<State<GameState>>) {
+        log.0.push("in_game");
+    }
+
+    #[test]
+    fn transitions_run_enter_and_exit_systems() {
+        let mut app_builder = AppBuilder::new();
+        app_builder
+            .add_resource(Log::default())
+            .add_state(GameState::Loading)
+            .on_enter(GameState::Loading, enter_loading)
+            .add_state_system(GameState::Loading, finish_loading)
+            .on_exit(GameState::Loading, exit_loading)
+            .add_state_system(GameState::InGame, in_game);
+        app_builder.set_schedule(ScheduleBuilder::new());
+        app_builder.set_entry_point(|mut app| {
+            app.execute_schedule();
+            app.execute_schedule();
+
+            assert_eq!(
+                app.get_resource::<Log>().0,
+                vec!["enter_loading", "loading", "exit_loading", "in_game"]
+            );
+            assert_eq!(
+                *app.get_resource::<State<GameState>>().current(),
+                GameState::InGame
+            );
+        });
+        app_builder.run();
+    }
+}
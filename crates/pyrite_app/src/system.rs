@@ -1,55 +1,83 @@
 use pyrite_app_macros::generate_system_function_handlers;
+pub use pyrite_app_macros::SystemParam;
 use std::any::TypeId;
 
-use crate::resource::{FromResourceBank, Res, ResMut, ResourceBank};
+use crate::resource::{Res, ResMut, Resource, ResourceBank, SystemTicks};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ResourceDependency {
     Res(TypeId),
     ResMut(TypeId),
 }
 
+impl ResourceDependency {
+    pub fn type_id(&self) -> TypeId {
+        match self {
+            ResourceDependency::Res(type_id) | ResourceDependency::ResMut(type_id) => *type_id,
+        }
+    }
+}
+
 type SystemParamItem<'rb, P> = <P as SystemParam>::Item<'rb>;
 
+/// A value that can be fetched from the resource bank as a parameter of a system.
+///
+/// Structs of system params can derive the trait to be used as a single param:
+///
+/// ```ignore
+/// #[derive(SystemParam)]
+/// struct RenderContext<'rb> {
+///     vulkan: Res<'rb, Vulkan>,
+///     swapchain: ResMut<'rb, Swapchain>,
+/// }
+/// ```
 pub trait SystemParam {
     type Item<'rb>: SystemParam;
 
-    fn from_resource_bank(resource_bank: &ResourceBank) -> Self::Item<'_>;
+    fn from_resource_bank(
+        resource_bank: &ResourceBank,
+        system_ticks: SystemTicks,
+    ) -> Self::Item<'_>;
 
-    /// Used to validate that the system isn't using the same resource twice.
-    fn dependency() -> ResourceDependency;
-
-    // TODO: fn scheduling_dependencies() -> Vec<ScheduingDependencyType>;
+    /// The resources the param borrows, used to schedule systems that don't conflict in
+    /// parallel.
+    fn dependencies() -> Vec<ResourceDependency>;
 }
 
 // Generic system param over any generic resource from the resource bank.
 impl<R> SystemParam for Res<'_, R>
 where
-    R: FromResourceBank + 'static,
+    R: Resource,
 {
     type Item<'rb> = Res<'rb, R>;
 
-    fn from_resource_bank(resource_bank: &ResourceBank) -> Self::Item<'_> {
-        R::from_resource_bank(resource_bank)
+    fn from_resource_bank(
+        resource_bank: &ResourceBank,
+        system_ticks: SystemTicks,
+    ) -> Self::Item<'_> {
+        resource_bank.get_resource_with_ticks(system_ticks)
     }
 
-    fn dependency() -> ResourceDependency {
-        ResourceDependency::Res(TypeId::of::<R>())
+    fn dependencies() -> Vec<ResourceDependency> {
+        vec![ResourceDependency::Res(TypeId::of::<R>())]
     }
 }
 
 impl<R> SystemParam for ResMut<'_, R>
 where
-    R: FromResourceBank + 'static,
+    R: Resource,
 {
     type Item<'rb> = ResMut<'rb, R>;
 
-    fn from_resource_bank(resource_bank: &ResourceBank) -> Self::Item<'_> {
-        R::from_resource_bank_mut(resource_bank)
+    fn from_resource_bank(
+        resource_bank: &ResourceBank,
+        system_ticks: SystemTicks,
+    ) -> Self::Item<'_> {
+        resource_bank.get_resource_mut_with_ticks(system_ticks)
     }
 
-    fn dependency() -> ResourceDependency {
-        ResourceDependency::ResMut(TypeId::of::<R>())
+    fn dependencies() -> Vec<ResourceDependency> {
+        vec![ResourceDependency::ResMut(TypeId::of::<R>())]
     }
 }
 
@@ -59,10 +87,13 @@ pub trait System: Send {
     fn run(&mut self, resource_bank: &ResourceBank);
     fn name(&self) -> &'static str;
     fn dependencies(&self) -> Vec<ResourceDependency>;
+
+    /// The change tick of the last time the system ran, 0 if it hasn't run yet.
+    fn last_run_tick(&self) -> u64;
 }
 
 pub trait SystemFunctionHandler<M>: Send {
-    fn handle(&mut self, resource_bank: &ResourceBank);
+    fn handle(&mut self, resource_bank: &ResourceBank, system_ticks: SystemTicks);
     fn name() -> &'static str {
         std::any::type_name::<Self>()
     }
@@ -71,6 +102,7 @@ pub trait SystemFunctionHandler<M>: Send {
 
 pub struct SystemFunction<M, F: SystemFunctionHandler<M>> {
     f: F,
+    last_run_tick: u64,
     _marker: std::marker::PhantomData<fn(M) -> ()>,
 }
 
@@ -78,6 +110,7 @@ impl<M, F: SystemFunctionHandler<M>> SystemFunction<M, F> {
     fn new(f: F) -> Self {
         Self {
             f,
+            last_run_tick: 0,
             _marker: std::marker::PhantomData,
         }
     }
@@ -89,7 +122,12 @@ impl<M, F: SystemFunctionHandler<M>> SystemFunction<M, F> {
 
 impl<M, F: SystemFunctionHandler<M>> System for SystemFunction<M, F> {
     fn run(&mut self, resource_bank: &ResourceBank) {
-        self.f.handle(resource_bank);
+        let system_ticks = SystemTicks {
+            last_run: self.last_run_tick,
+            this_run: resource_bank.increment_change_tick(),
+        };
+        self.f.handle(resource_bank, system_ticks);
+        self.last_run_tick = system_ticks.this_run;
     }
 
     fn name(&self) -> &'static str {
@@ -98,6 +136,10 @@ impl<M, F: SystemFunctionHandler<M>> System for SystemFunction<M, F> {
     fn dependencies(&self) -> Vec<ResourceDependency> {
         F::dependencies()
     }
+
+    fn last_run_tick(&self) -> u64 {
+        self.last_run_tick
+    }
 }
 
 macro_rules! impl_system_function_handler {
@@ -106,7 +148,7 @@ macro_rules! impl_system_function_handler {
         where
             F: FnMut($($param),*) + FnMut($(SystemParamItem<$param>),*) + Send,
         {
-            fn handle(&mut self, _resource_bank: &ResourceBank) {
+            fn handle(&mut self, _resource_bank: &ResourceBank, _system_ticks: SystemTicks) {
                 // Function needs to be generified again since rust can't infer the type correctly.
                 fn call<F, $($param),*>(mut f: F, $($param: $param),*)
                 where
@@ -115,14 +157,88 @@ macro_rules! impl_system_function_handler {
                     (f)($($param),*);
                 }
 
-                call(self, $($param::from_resource_bank(_resource_bank)),*);
+                call(self, $($param::from_resource_bank(_resource_bank, _system_ticks)),*);
             }
 
             fn dependencies() -> Vec<ResourceDependency> {
-                vec![$($param::dependency()),*]
+                std::iter::empty()
+                    $(.chain($param::dependencies()))*
+                    .collect()
             }
         }
     };
 }
 
 generate_system_function_handlers!(impl_system_function_handler, 16);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        command::{CommandQueue, Commands},
+        schedule::ScheduleBuilder,
+        AppBuilder,
+    };
+
+    #[derive(Resource)]
+    struct Gravity(f32);
+
+    #[derive(Resource)]
+    struct Velocity(f32);
+
+    #[derive(Resource)]
+    struct Landed;
+
+    #[derive(SystemParam)]
+    struct Physics<'rb> {
+        gravity: Res<'rb, Gravity>,
+        velocity: ResMut<'rb, Velocity>,
+        commands: Commands<'rb>,
+    }
+
+    fn step_physics(mut physics: Physics) {
+        physics.velocity.0 += physics.gravity.0;
+        physics.commands.insert_resource(Landed);
+    }
+
+    fn write_gravity(mut gravity: ResMut<Gravity>) {
+        gravity.0 = 0.0;
+    }
+
+    #[test]
+    fn derived_params_combine_field_dependencies() {
+        let mut schedule_builder = ScheduleBuilder::new();
+        schedule_builder.add_task(step_physics);
+        schedule_builder.add_task(write_gravity);
+        let schedule = schedule_builder.build().unwrap();
+
+        assert_eq!(
+            schedule.system_resource_dependencies()[&0],
+            vec![
+                ResourceDependency::Res(TypeId::of::<Gravity>()),
+                ResourceDependency::ResMut(TypeId::of::<Velocity>()),
+                ResourceDependency::Res(TypeId::of::<CommandQueue>()),
+            ]
+        );
+        // Writing a resource the bundle reads is a conflict.
+        assert_eq!(schedule.system_dependencies()[&1], vec![0]);
+    }
+
+    #[test]
+    fn derived_params_are_fetched_for_systems() {
+        let mut schedule_builder = ScheduleBuilder::new();
+        schedule_builder.add_task(step_physics);
+
+        let mut app_builder = AppBuilder::new();
+        app_builder
+            .add_resource(Gravity(-1.0))
+            .add_resource(Velocity(0.0));
+        app_builder.set_schedule(schedule_builder);
+        app_builder.set_entry_point(|mut app| {
+            app.execute_schedule();
+            assert_eq!(app.get_resource::<Velocity>().0, -1.0);
+            assert!(app.contains_resource::<Landed>());
+        });
+        app_builder.run();
+    }
+}
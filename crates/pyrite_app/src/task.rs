@@ -0,0 +1,235 @@
+use std::{any::Any, panic::AssertUnwindSafe, sync::Arc};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::{
+    command::Commands,
+    resource::{Res, Resource, ResourceBank},
+};
+
+type Completion = Box<dyn FnOnce(&mut ResourceBank) + Send>;
+
+/// A shared pool for long running work that shouldn't block a frame, such as asset loading,
+/// shader compilation or file io.
+///
+/// The pool is separate from the threads executing the schedule, so a slow task never delays
+/// systems from running. Clones share the same threads, so other crates can keep one to spawn
+/// from outside of systems, e.g. the asset loading in pyrite_asset.
+///
+/// A panicking task doesn't take down its worker thread, the panic is resumed wherever the output
+/// is consumed: [`Task::poll`] and [`Task::block`], or the main thread for
+/// [`AsyncTaskPool::spawn_then`].
+#[derive(Clone)]
+pub struct AsyncTaskPool {
+    threads: Arc<rayon::ThreadPool>,
+    completed: Arc<Mutex<Vec<Completion>>>,
+}
+
+impl Resource for AsyncTaskPool {}
+
+impl AsyncTaskPool {
+    /// Creates a pool with `num_threads` worker threads, defaults to the number of logical cores.
+    pub fn new(num_threads: Option<usize>) -> Self {
+        let mut builder =
+            rayon::ThreadPoolBuilder::new().thread_name(|index| format!("pyrite_async_{}", index));
+        if let Some(num_threads) = num_threads {
+            builder = builder.num_threads(num_threads);
+        }
+
+        Self {
+            threads: Arc::new(
+                builder
+                    .build()
+                    .expect("Failed to create async task pool threads."),
+            ),
+            completed: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn num_threads(&self) -> usize {
+        self.threads.current_num_threads()
+    }
+
+    /// Spawns a task, its output can be polled from the returned [`Task`].
+    pub fn spawn<T, F>(&self, f: F) -> Task<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let task = Task {
+            shared: Arc::new(TaskShared {
+                output: Mutex::new(TaskOutput::Pending),
+                finished_condvar: Condvar::new(),
+            }),
+        };
+
+        let shared = task.shared.clone();
+        self.threads.spawn(move || {
+            let output = match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+                Ok(output) => TaskOutput::Ready(output),
+                Err(panic) => TaskOutput::Panicked(panic),
+            };
+            *shared.output.lock() = output;
+            shared.finished_condvar.notify_all();
+        });
+
+        task
+    }
+
+    /// Spawns a task and applies its output to the resource bank once it has completed.
+    ///
+    /// `on_complete` runs on the main thread at the start of the first frame after the task
+    /// completed, so it can freely insert or change resources. If the task panicked, the panic is
+    /// resumed there instead.
+    pub fn spawn_then<T, F, C>(&self, f: F, on_complete: C)
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+        C: FnOnce(T, &mut ResourceBank) + Send + 'static,
+    {
+        let completed = self.completed.clone();
+        self.threads.spawn(move || {
+            let completion: Completion = match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+                Ok(output) => Box::new(move |resource_bank| on_complete(output, resource_bank)),
+                Err(panic) => Box::new(move |_| std::panic::resume_unwind(panic)),
+            };
+            completed.lock().push(completion);
+        });
+    }
+
+    /// Moves the completion callbacks of finished tasks into the command queue, added to the
+    /// pre update stage by default.
+    pub(crate) fn drain_completed_tasks(task_pool: Res<AsyncTaskPool>, mut commands: Commands) {
+        for completion in std::mem::take(&mut *task_pool.completed.lock()) {
+            commands.add(completion);
+        }
+    }
+}
+
+impl Default for AsyncTaskPool {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+enum TaskOutput<T> {
+    Pending,
+    Ready(T),
+    Panicked(Box<dyn Any + Send>),
+    Taken,
+}
+
+struct TaskShared<T> {
+    output: Mutex<TaskOutput<T>>,
+    finished_condvar: Condvar,
+}
+
+/// A handle to a task spawned on the [`AsyncTaskPool`].
+///
+/// Dropping the handle doesn't cancel the task, its output is just discarded.
+pub struct Task<T> {
+    shared: Arc<TaskShared<T>>,
+}
+
+impl<T> Task<T> {
+    /// Returns true if the task has completed and its output hasn't been taken yet.
+    pub fn is_ready(&self) -> bool {
+        matches!(
+            *self.shared.output.lock(),
+            TaskOutput::Ready(_) | TaskOutput::Panicked(_)
+        )
+    }
+
+    /// Takes the output of the task if it has completed, meant to be called every frame from a
+    /// system until the output is available.
+    ///
+    /// If the task panicked, the panic is resumed on the calling thread.
+    pub fn poll(&mut self) -> Option<T> {
+        let mut output = self.shared.output.lock();
+        match std::mem::replace(&mut *output, TaskOutput::Taken) {
+            TaskOutput::Ready(value) => Some(value),
+            TaskOutput::Panicked(panic) => std::panic::resume_unwind(panic),
+            pending => {
+                *output = pending;
+                None
+            }
+        }
+    }
+
+    /// Blocks the current thread until the task has completed and returns its output.
+    ///
+    /// If the task panicked, the panic is resumed on the calling thread.
+    ///
+    /// # Panics
+    /// If the output was already taken by [`Task::poll`].
+    pub fn block(self) -> T {
+        let mut output = self.shared.output.lock();
+        while let TaskOutput::Pending = *output {
+            self.shared.finished_condvar.wait(&mut output);
+        }
+
+        match std::mem::replace(&mut *output, TaskOutput::Taken) {
+            TaskOutput::Ready(value) => value,
+            TaskOutput::Panicked(panic) => std::panic::resume_unwind(panic),
+            _ => panic!("Task output was already taken."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppBuilder;
+
+    struct Loaded(u32);
+    impl Resource for Loaded {}
+
+    #[test]
+    fn polled_tasks_return_their_output() {
+        let task_pool = AsyncTaskPool::new(Some(1));
+        let mut task = task_pool.spawn(|| 1 + 1);
+        while !task.is_ready() {
+            std::thread::yield_now();
+        }
+
+        assert_eq!(task.poll(), Some(2));
+        assert_eq!(task.poll(), None);
+        assert_eq!(task_pool.spawn(|| 3).block(), 3);
+    }
+
+    #[test]
+    fn task_panics_are_resumed_when_taking_the_output() {
+        let task_pool = AsyncTaskPool::new(Some(1));
+
+        let panic = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            task_pool.spawn(|| -> u32 { panic!("task failed") }).block()
+        }))
+        .unwrap_err();
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"task failed"));
+
+        let mut task = task_pool.spawn(|| -> u32 { panic!("task failed") });
+        while !task.is_ready() {
+            std::thread::yield_now();
+        }
+        assert!(std::panic::catch_unwind(AssertUnwindSafe(|| task.poll())).is_err());
+
+        // The worker thread survived the panics.
+        assert_eq!(task_pool.spawn(|| 3).block(), 3);
+    }
+
+    #[test]
+    fn completed_tasks_are_applied_to_the_app() {
+        let mut app_builder = AppBuilder::new();
+        app_builder.get_resource::<AsyncTaskPool>().spawn_then(
+            || 7,
+            |value, resource_bank| resource_bank.insert_resource(Loaded(value)),
+        );
+        app_builder.set_entry_point(|mut app| {
+            while !app.contains_resource::<Loaded>() {
+                app.execute_schedule();
+            }
+            assert_eq!(app.get_resource::<Loaded>().0, 7);
+        });
+        app_builder.run();
+    }
+}
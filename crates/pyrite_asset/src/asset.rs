@@ -1,26 +1,45 @@
 use std::{
-    any::Any,
-    collections::HashMap,
+    any::{Any, TypeId},
+    collections::{HashMap, VecDeque},
     error::Error,
     fmt::{Display, Formatter},
     ops::Deref,
-    path::Path,
+    path::{Path, PathBuf},
     sync::{
-        atomic::{self, AtomicBool},
-        Arc,
+        atomic::{self, AtomicBool, AtomicUsize},
+        Arc, Weak,
     },
+    time::{Duration, Instant},
 };
 
 use notify::Watcher;
-use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
-use pyrite_app::resource::Resource;
+use parking_lot::{MappedRwLockReadGuard, Mutex, RwLock, RwLockReadGuard};
+use pyrite_app::{resource::Resource, task::AsyncTaskPool};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 
+use crate::{
+    processor::AssetProcessors, watcher::AssetWatcher, AssetEvent, AssetProcessor, AssetSettings,
+    AssetSource, DirectorySource,
+};
+
 #[derive(Resource)]
 pub struct Assets {
-    loaders: HashMap<String, Box<dyn ErasedAssetLoader>>,
-    queue: Vec<(String, Box<dyn ErasedHandle>)>,
-    pool: rayon::ThreadPool,
+    shared: Arc<LoadShared>,
+    queue: Vec<QueuedLoad>,
+    task_pool: AsyncTaskPool,
+    /// The main thread time [`Assets::update`] spends finishing loads each frame.
+    finish_budget: Duration,
+    watcher: Option<AssetWatcher>,
+    /// Every handle which was loaded, by the absolute path of its file, to reload them when the
+    /// watcher sees their file change.
+    handles: HashMap<PathBuf, Vec<Box<dyn WeakErasedHandle>>>,
+    /// The loaded assets by their path and type, kept until [`Assets::garbage_collect`] finds
+    /// them without strong handles.
+    loaded: HashMap<(String, TypeId), Box<dyn ErasedHandle>>,
+    /// The assets added with [`Assets::insert`], which have no file.
+    inserted: Vec<Box<dyn ErasedHandle>>,
+    unload_grace_period: Duration,
+    events: Vec<AssetEvent>,
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -43,12 +62,44 @@ impl AssetLoadError {
             kind: AssetLoadErrorKind::FileNotFound,
         }
     }
+
+    pub fn new_processing_failed(file_path: String, message: String) -> Self {
+        Self {
+            file_path,
+            kind: AssetLoadErrorKind::ProcessingFailed { message },
+        }
+    }
+
+    pub fn new_label_not_found(file_path: String, label: String) -> Self {
+        Self {
+            file_path,
+            kind: AssetLoadErrorKind::LabelNotFound { label },
+        }
+    }
+
+    pub fn new_cancelled(file_path: String) -> Self {
+        Self {
+            file_path,
+            kind: AssetLoadErrorKind::Cancelled,
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum AssetLoadErrorKind {
     FileNotFound,
-    InvalidFile { message: String },
+    InvalidFile {
+        message: String,
+    },
+    ProcessingFailed {
+        message: String,
+    },
+    /// The loader didn't add a labeled asset with the label of the path.
+    LabelNotFound {
+        label: String,
+    },
+    /// Every handle to the asset was dropped before its background load started.
+    Cancelled,
 }
 
 impl Display for AssetLoadErrorKind {
@@ -58,6 +109,13 @@ impl Display for AssetLoadErrorKind {
             AssetLoadErrorKind::InvalidFile { message } => {
                 write!(f, "Invalid file: {}", message)
             }
+            AssetLoadErrorKind::ProcessingFailed { message } => {
+                write!(f, "Processing failed: {}", message)
+            }
+            AssetLoadErrorKind::LabelNotFound { label } => {
+                write!(f, "No asset labeled {}", label)
+            }
+            AssetLoadErrorKind::Cancelled => write!(f, "Cancelled"),
         }
     }
 }
@@ -75,100 +133,724 @@ impl Display for AssetLoadError {
 impl Error for AssetLoadError {}
 
 trait ErasedAssetLoader: Send + Sync {
-    fn load(&self, file_path: String) -> Result<Box<dyn Any>, AssetLoadError>;
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Box<dyn Any>, AssetLoadError>;
 }
 
 struct AssetLoaderWrapper<T: AssetLoader>(T);
 
 impl<T: AssetLoader> ErasedAssetLoader for AssetLoaderWrapper<T> {
-    fn load(&self, file_path: String) -> Result<Box<dyn Any>, AssetLoadError> {
-        Ok(Box::new(self.0.load(file_path)?))
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Box<dyn Any>, AssetLoadError> {
+        Ok(Box::new(self.0.load(file_path, context)?))
     }
 }
 
+/// The first phase of loading an asset, importing a file into data which is either the asset
+/// itself or the input of the [`AssetProcessor`]s producing the asset.
 pub trait AssetLoader: Send + Sync + 'static {
-    type Asset;
+    type Asset: 'static;
 
     fn new() -> Self
     where
         Self: Sized;
-    fn load(&self, file_path: String) -> Result<Self::Asset, AssetLoadError>
+    /// Loads the file, the context holds the settings from its settings file and loads the
+    /// assets it depends on.
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Self::Asset, AssetLoadError>
     where
         Self: Sized;
     fn identifiers() -> &'static [&'static str];
 }
 
+/// Passed to [`AssetLoader::load`] to give the loader the settings of the asset and let it load
+/// the assets it depends on, e.g. the textures of a material.
+pub struct LoadContext {
+    source: Arc<dyn AssetSource>,
+    file_path: String,
+    settings: AssetSettings,
+    dependencies: Vec<Box<dyn ErasedHandle>>,
+    queue: Vec<(String, Box<dyn ErasedHandle>)>,
+    labeled_assets: HashMap<String, Box<dyn Any>>,
+}
+
+impl LoadContext {
+    fn new(source: Arc<dyn AssetSource>, file_path: String) -> Result<Self, AssetLoadError> {
+        Ok(Self {
+            settings: AssetSettings::load(source.as_ref(), &file_path)?,
+            source,
+            file_path,
+            dependencies: Vec::new(),
+            queue: Vec::new(),
+            labeled_assets: HashMap::new(),
+        })
+    }
+
+    pub fn settings(&self) -> &AssetSettings {
+        &self.settings
+    }
+
+    pub fn source(&self) -> &dyn AssetSource {
+        self.source.as_ref()
+    }
+
+    /// Reads a file from the asset source, loaders read their file through this instead of the
+    /// filesystem so they work with every [`AssetSource`].
+    pub fn read(&self, file_path: &str) -> Result<Vec<u8>, AssetLoadError> {
+        self.source.read(file_path).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => {
+                AssetLoadError::new_file_not_found(file_path.to_string())
+            }
+            _ => AssetLoadError::new_invalid_file(file_path.to_string(), err.to_string()),
+        })
+    }
+
+    /// Loads an asset the asset being loaded depends on, its handle only reports loaded once all
+    /// of its dependencies have loaded, even if they failed to.
+    ///
+    /// Dependencies are loaded with the [`LoadPriority`] of the asset depending on them.
+    pub fn load<T: Send + Sync + 'static>(&mut self, file_path: impl ToString) -> Handle<T> {
+        let handle = Handle::new(file_path.to_string());
+
+        self.dependencies.push(Box::new(handle.inner.clone()));
+        self.queue
+            .push((file_path.to_string(), Box::new(handle.inner.clone())));
+
+        handle
+    }
+
+    /// Adds an asset loaded from a part of the file, e.g. a mesh of a scene, which can be loaded
+    /// on its own with the path `file_path#label`.
+    ///
+    /// Labeled assets are run through the processors turning them into the type they're loaded
+    /// as, like the asset of the loader.
+    pub fn add_labeled_asset<T: Send + Sync + 'static>(&mut self, label: impl ToString, asset: T) {
+        self.labeled_assets
+            .insert(label.to_string(), Box::new(asset));
+    }
+
+    /// Resolves a path relative to the directory of the asset being loaded, for paths
+    /// referenced from inside the file.
+    pub fn resolve_path(&self, relative_path: &str) -> String {
+        Path::new(&self.file_path)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(relative_path)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
 impl Assets {
-    pub fn new() -> Self {
-        let pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(4)
-            .build()
-            .unwrap();
+    /// Creates the assets reading loose files relative to the working directory.
+    ///
+    /// Background loads run on the `task_pool`, usually the app's [`AsyncTaskPool`] resource.
+    pub fn new(task_pool: &AsyncTaskPool) -> Self {
+        Self::with_source(DirectorySource::new(""), task_pool)
+    }
 
+    /// Creates the assets reading files from the source, e.g. a [`crate::PackSource`] in
+    /// shipping builds.
+    pub fn with_source(source: impl AssetSource, task_pool: &AsyncTaskPool) -> Self {
         Self {
-            loaders: HashMap::new(),
+            shared: Arc::new(LoadShared {
+                source: Arc::new(source),
+                loaders: RwLock::new(HashMap::new()),
+                processors: RwLock::new(AssetProcessors::default()),
+                lanes: Mutex::new(Default::default()),
+                finished: Mutex::new(VecDeque::new()),
+            }),
             queue: Vec::new(),
-            pool,
+            task_pool: task_pool.clone(),
+            finish_budget: Duration::from_millis(2),
+            watcher: None,
+            handles: HashMap::new(),
+            loaded: HashMap::new(),
+            inserted: Vec::new(),
+            unload_grace_period: Duration::ZERO,
+            events: Vec::new(),
         }
     }
 
     pub fn add_loader<T: AssetLoader>(&mut self) {
         for identifier in T::identifiers() {
-            self.loaders.insert(
+            self.shared.loaders.write().insert(
                 identifier.to_string(),
                 Box::new(AssetLoaderWrapper(T::new())),
             );
         }
     }
 
-    /// Load an asset from a file using the extension to determine the loader.
-    /// Currently, the load is synchronous
+    /// Adds a processor, which is run when an asset is loaded as a type which is reachable from
+    /// the loader's asset type through it.
+    pub fn add_processor<T: AssetProcessor>(&mut self, processor: T) {
+        self.shared.processors.write().add(processor);
+    }
+
+    /// Load an asset from a file using the extension to determine the loader, followed by the
+    /// processors needed to turn the loader's asset into `T`.
+    ///
+    /// A path with a label, e.g. `scene.gltf#Mesh0`, loads the asset the loader added with
+    /// [`LoadContext::add_labeled_asset`] instead. Every labeled asset of a file queued in the
+    /// same update is served by a single load of the file.
+    ///
+    /// The asset is loaded in the background with [`LoadPriority::High`], see
+    /// [`Assets::load_with_priority`]. Loading a path which is already loaded as `T` returns a
+    /// handle to the loaded asset.
     pub fn load<T: Send + Sync + 'static>(&mut self, file_path: impl ToString) -> Handle<T> {
-        let handle = Handle::new(file_path.to_string());
+        self.load_with_priority(file_path, LoadPriority::High)
+    }
 
-        self.queue
-            .push((file_path.to_string(), Box::new(handle.inner.clone())));
+    /// Loads an asset like [`Assets::load`] with a priority, blocking loads are finished by the
+    /// next [`Assets::update`] and background loads wait for the high priority loads.
+    ///
+    /// Background loads which haven't started when every handle to them was dropped are
+    /// cancelled, blocking and high priority loads always run.
+    pub fn load_with_priority<T: Send + Sync + 'static>(
+        &mut self,
+        file_path: impl ToString,
+        priority: LoadPriority,
+    ) -> Handle<T> {
+        let file_path = file_path.to_string();
+        let key = (file_path.clone(), TypeId::of::<T>());
+        if let Some(inner) = self
+            .loaded
+            .get(&key)
+            .and_then(|loaded| loaded.as_any().downcast_ref::<Arc<HandleInner<T>>>())
+        {
+            return Handle::from_inner(inner.clone());
+        }
+
+        let handle = Handle::new(file_path.clone());
+
+        self.queue.push(QueuedLoad {
+            file_path,
+            handle: Box::new(handle.inner.clone()),
+            priority,
+        });
+        self.loaded.insert(key, Box::new(handle.inner.clone()));
+
+        handle
+    }
+
+    /// Adds an asset which wasn't loaded from a file, e.g. a generated mesh, so it can be shared
+    /// through a [`Handle`] like loaded assets. The handle is loaded right away.
+    ///
+    /// Inserted assets are released by [`Assets::garbage_collect`] like loaded assets.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, asset: T) -> Handle<T> {
+        let handle = Handle::new(String::new());
+        handle.inner.set_asset(asset);
+        self.inserted.push(Box::new(handle.inner.clone()));
 
         handle
     }
 
+    /// The loaded and inserted assets of type `T`, skipping assets which are still loading or
+    /// failed to load.
+    pub fn iter<T: Send + Sync + 'static>(
+        &self,
+    ) -> impl Iterator<Item = MappedRwLockReadGuard<'_, T>> + '_ {
+        self.loaded
+            .values()
+            .chain(&self.inserted)
+            .filter_map(|handle| handle.as_any().downcast_ref::<Arc<HandleInner<T>>>())
+            .filter(|inner| !inner.is_error())
+            .filter_map(|inner| inner.get())
+    }
+
+    /// The main thread time [`Assets::update`] spends finishing background loads each frame,
+    /// at least one load is finished per update regardless of the budget. 2ms by default.
+    ///
+    /// Finishing a load makes its asset visible through its handles and queues its dependencies.
+    pub fn set_finish_budget(&mut self, budget: Duration) {
+        self.finish_budget = budget;
+    }
+
+    /// How long an asset is kept after its last strong [`Handle`] was dropped, so assets which
+    /// are dropped and loaded again shortly after aren't reloaded. Zero by default.
+    pub fn set_unload_grace_period(&mut self, grace_period: Duration) {
+        self.unload_grace_period = grace_period;
+    }
+
+    /// Releases the loaded assets which have had no strong [`Handle`] for the unload grace
+    /// period, their [`WeakHandle`]s can't be upgraded anymore once nothing else holds them.
+    ///
+    /// Assets which are dependencies of other assets stay alive as long as the assets depending
+    /// on them.
+    pub fn garbage_collect(&mut self) {
+        pyrite_util::profile_scope!("Assets::garbage_collect");
+
+        let grace_period = self.unload_grace_period;
+        let is_used = |handle: &dyn ErasedHandle| {
+            handle.strong_count() > 0
+                || handle
+                    .released_at()
+                    .is_none_or(|released_at| released_at.elapsed() < grace_period)
+        };
+        self.loaded.retain(|_, handle| is_used(handle.as_ref()));
+        self.inserted.retain(|handle| is_used(handle.as_ref()));
+
+        self.handles.retain(|_, handles| {
+            handles.retain(|handle| handle.is_alive());
+            !handles.is_empty()
+        });
+    }
+
+    /// Watches the directory and its subdirectories for changes, reloading the loaded assets
+    /// whose file or settings file changed and sending [`AssetEvent`]s for every change.
+    ///
+    /// Unlike a [`WatchedHandle`] one watcher covers every asset in the directory.
+    pub fn watch(&mut self, directory: impl AsRef<Path>) {
+        self.watcher
+            .get_or_insert_with(AssetWatcher::new)
+            .watch(directory.as_ref());
+    }
+
+    /// Takes the events of the watched directories since the last call.
+    pub fn drain_events(&mut self) -> Vec<AssetEvent> {
+        std::mem::take(&mut self.events)
+    }
+
     pub fn update(&mut self) {
-        let queue = std::mem::take(&mut self.queue);
+        pyrite_util::profile_scope!("Assets::update");
+
+        if let Some(watcher) = &self.watcher {
+            let events = watcher.drain_events();
+            for event in &events {
+                if let AssetEvent::Modified(path) = event {
+                    self.reload_path(path);
+                    if let Some(asset_path) = path
+                        .to_str()
+                        .and_then(|path| path.strip_suffix(".settings"))
+                    {
+                        self.reload_path(Path::new(asset_path));
+                    }
+                }
+            }
+            self.events.extend(events);
+        }
+
+        self.submit_queue();
+
+        let start = Instant::now();
+        loop {
+            let finished = self.shared.finished.lock().pop_front();
+            let Some(finished) = finished else {
+                break;
+            };
+            self.finish(finished);
+
+            if start.elapsed() >= self.finish_budget {
+                break;
+            }
+        }
+
+        // Submits the dependencies of the finished loads.
+        self.submit_queue();
+    }
+
+    fn register_handle(&mut self, file_path: &str, handle: &dyn ErasedHandle) {
+        let (file_path, _) = split_label(file_path);
+        let Some(native_path) = self.shared.source.native_path(file_path) else {
+            return;
+        };
+
+        let handles = self.handles.entry(native_path).or_default();
+        handles.retain(|registered| registered.is_alive());
+        if !handles
+            .iter()
+            .any(|registered| registered.id() == handle.id())
+        {
+            handles.push(handle.downgrade());
+        }
+    }
+
+    /// Queues the live handles of the file to be loaded again.
+    fn reload_path(&mut self, path: &Path) {
+        let Some(handles) = self.handles.get(path) else {
+            return;
+        };
+
+        for handle in handles.iter().filter_map(|handle| handle.upgrade()) {
+            handle.begin_reload();
+            self.queue.push(QueuedLoad {
+                file_path: handle.file_path(),
+                handle,
+                priority: LoadPriority::High,
+            });
+        }
+    }
+
+    /// Sends the queued loads to the loading threads. Blocking loads are loaded and finished
+    /// right away, along with the dependencies they request.
+    fn submit_queue(&mut self) {
+        while !self.queue.is_empty() {
+            let queue = std::mem::take(&mut self.queue);
+            for load in &queue {
+                self.register_handle(&load.file_path, load.handle.as_ref());
+            }
+
+            let mut blocking = Vec::new();
+            for job in Self::batch(queue) {
+                if job.priority == LoadPriority::Blocking {
+                    blocking.push(job);
+                    continue;
+                }
+                self.shared.push(job);
+
+                // A panicking loader is resumed on the main thread by the completion.
+                let shared = self.shared.clone();
+                self.task_pool
+                    .spawn_then(move || shared.run_next(), |(), _| {});
+            }
+
+            // Blocking loads run on the global pool, so they don't wait for the background loads
+            // occupying the loading threads.
+            let shared = &self.shared;
+            let finished: Vec<FinishedLoad> = blocking
+                .into_par_iter()
+                .map(|job| shared.run(job))
+                .collect();
+            for finished in finished {
+                self.finish(finished);
+            }
+        }
+    }
+
+    /// Groups the queued loads into jobs which are served by one load of their file, a job holds
+    /// at most one handle per label since processing consumes the asset.
+    fn batch(queue: Vec<QueuedLoad>) -> Vec<LoadJob> {
+        let mut jobs: Vec<LoadJob> = Vec::new();
+        for load in queue {
+            let (file_path, label) = split_label(&load.file_path);
+            let label = label.map(|label| label.to_string());
+
+            let job = jobs.iter_mut().find(|job| {
+                job.file_path == file_path
+                    && job.priority == load.priority
+                    && job.handles.iter().all(|(job_label, _)| *job_label != label)
+            });
+            match job {
+                Some(job) => job.handles.push((label, load.handle)),
+                None => jobs.push(LoadJob {
+                    file_path: file_path.to_string(),
+                    priority: load.priority,
+                    handles: vec![(label, load.handle)],
+                }),
+            }
+        }
+
+        jobs
+    }
+
+    /// Commits the staged assets of a load and queues the dependencies it requested.
+    fn finish(&mut self, finished: FinishedLoad) {
+        if finished.cancelled {
+            for handle in finished.handles {
+                // Loading the path again starts a new load instead of returning this handle.
+                let key = (handle.file_path(), handle.asset_type());
+                if self
+                    .loaded
+                    .get(&key)
+                    .is_some_and(|loaded| loaded.id() == handle.id())
+                {
+                    self.loaded.remove(&key);
+                }
+                handle.update_error(AssetLoadError::new_cancelled(handle.file_path()));
+            }
+            return;
+        }
+
+        for handle in &finished.handles {
+            // The dependencies are set first so the handle never reports loaded before them.
+            handle.set_dependencies(
+                finished
+                    .dependencies
+                    .iter()
+                    .map(|dependency| dependency.clone_handle())
+                    .collect(),
+            );
+            handle.commit();
+        }
+
+        let priority = finished.priority;
+        self.queue.extend(
+            finished
+                .queue
+                .into_iter()
+                .map(|(file_path, handle)| QueuedLoad {
+                    file_path,
+                    handle,
+                    priority,
+                }),
+        );
+    }
+}
+
+/// How urgently an asset is loaded, see [`Assets::load_with_priority`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LoadPriority {
+    /// Loaded and finished by the next [`Assets::update`], which waits for it.
+    Blocking,
+    /// Loaded in the background, before any background load which hasn't started yet.
+    #[default]
+    High,
+    /// Loaded in the background once no high priority loads are waiting, cancelled if every
+    /// handle to it was dropped before it started.
+    Background,
+}
+
+struct QueuedLoad {
+    file_path: String,
+    handle: Box<dyn ErasedHandle>,
+    priority: LoadPriority,
+}
+
+/// The handles served by one load of a file.
+struct LoadJob {
+    file_path: String,
+    priority: LoadPriority,
+    handles: Vec<QueuedHandle>,
+}
 
-        let loaders = &self.loaders;
+/// A load whose assets are staged in its handles, waiting to be committed on the main thread.
+struct FinishedLoad {
+    priority: LoadPriority,
+    handles: Vec<Box<dyn ErasedHandle>>,
+    dependencies: Vec<Box<dyn ErasedHandle>>,
+    queue: Vec<(String, Box<dyn ErasedHandle>)>,
+    /// Whether the load was skipped since every handle to it was dropped.
+    cancelled: bool,
+}
+
+/// The state the loading threads share with the [`Assets`].
+struct LoadShared {
+    source: Arc<dyn AssetSource>,
+    loaders: RwLock<HashMap<String, Box<dyn ErasedAssetLoader>>>,
+    processors: RwLock<AssetProcessors>,
+    /// The jobs waiting for a loading thread, the high priority lane first.
+    lanes: Mutex<[VecDeque<LoadJob>; 2]>,
+    finished: Mutex<VecDeque<FinishedLoad>>,
+}
+
+impl LoadShared {
+    /// Adds a job to the lane of its priority, a task running [`LoadShared::run_next`] must be
+    /// spawned for every pushed job.
+    fn push(&self, job: LoadJob) {
+        let lane = match job.priority {
+            LoadPriority::Blocking => unreachable!("Blocking loads don't wait in a lane"),
+            LoadPriority::High => 0,
+            LoadPriority::Background => 1,
+        };
+        self.lanes.lock()[lane].push_back(job);
+    }
+
+    /// Runs the most urgent waiting job. A task is spawned for every job pushed to a lane, so
+    /// every job runs even if the task which pushed it ran another one.
+    fn run_next(&self) {
+        let job = self.lanes.lock().iter_mut().find_map(VecDeque::pop_front);
+        if let Some(job) = job {
+            let finished = self.run(job);
+            self.finished.lock().push_back(finished);
+        }
+    }
+
+    /// Loads the file of the job once for all of its handles, staging their assets.
+    fn run(&self, job: LoadJob) -> FinishedLoad {
+        let LoadJob {
+            file_path,
+            priority,
+            handles,
+        } = job;
+        pyrite_util::profile_scope!("asset load", &file_path);
 
-        let pool = &self.pool;
+        let mut finished = FinishedLoad {
+            priority,
+            handles: Vec::with_capacity(handles.len()),
+            dependencies: Vec::new(),
+            queue: Vec::new(),
+            cancelled: false,
+        };
 
-        pool.install(|| {
-            queue.into_par_iter().for_each(|(file_path, handle)| {
-                let extension = file_path
-                    .split('.')
-                    .last()
-                    .expect("Asset file path has no extension");
+        if priority == LoadPriority::Background
+            && handles.iter().all(|(_, handle)| handle.strong_count() == 0)
+        {
+            finished.cancelled = true;
+            finished.handles = handles.into_iter().map(|(_, handle)| handle).collect();
+            return finished;
+        }
 
-                let loader = loaders
-                    .get(extension)
-                    .expect("No loader for asset extension");
+        let extension = file_path
+            .split('.')
+            .last()
+            .expect("Asset file path has no extension");
 
-                match loader.load(file_path) {
-                    Ok(asset) => handle.update_asset(asset),
-                    Err(error) => {
-                        handle.update_error(error);
+        let loaders = self.loaders.read();
+        let loader = loaders
+            .get(extension)
+            .expect("No loader for asset extension");
+
+        let (asset, mut context) =
+            match Self::load_file(self.source.clone(), file_path.clone(), loader.as_ref()) {
+                Ok(loaded) => loaded,
+                Err(error) => {
+                    for (_, handle) in handles {
+                        handle.stage_error(error.clone());
+                        finished.handles.push(handle);
                     }
+                    return finished;
                 }
+            };
+
+        let processors = self.processors.read();
+        let mut asset = Some(asset);
+        for (label, handle) in handles {
+            let result = match &label {
+                None => Ok(asset.take().unwrap()),
+                Some(label) => context.labeled_assets.remove(label).ok_or_else(|| {
+                    AssetLoadError::new_label_not_found(file_path.clone(), label.clone())
+                }),
+            }
+            .and_then(|asset| {
+                Self::process_asset(&processors, &file_path, asset, handle.as_ref(), &context)
             });
-        });
+
+            match result {
+                Ok(asset) => handle.stage_asset(asset),
+                Err(error) => handle.stage_error(error),
+            }
+            finished.handles.push(handle);
+        }
+
+        finished.dependencies = context.dependencies;
+        finished.queue = context.queue;
+        finished
+    }
+
+    /// Loads the file with its settings.
+    fn load_file(
+        source: Arc<dyn AssetSource>,
+        file_path: String,
+        loader: &dyn ErasedAssetLoader,
+    ) -> Result<(Box<dyn Any>, LoadContext), AssetLoadError> {
+        let mut context = LoadContext::new(source, file_path.clone())?;
+        let asset = loader.load(file_path, &mut context)?;
+
+        Ok((asset, context))
+    }
+
+    /// Runs the loaded or labeled asset through the processor chain turning it into the type of
+    /// the handle.
+    fn process_asset(
+        processors: &AssetProcessors,
+        file_path: &str,
+        mut asset: Box<dyn Any>,
+        handle: &dyn ErasedHandle,
+        context: &LoadContext,
+    ) -> Result<Box<dyn Any>, AssetLoadError> {
+        let chain = processors
+            .find_chain(asset.as_ref().type_id(), handle.asset_type())
+            .unwrap_or_else(|| {
+                panic!(
+                    "No processors turn the asset loaded from {} into the requested type",
+                    handle.file_path()
+                )
+            });
+
+        for processor in chain {
+            asset = processor
+                .process(asset, &context.settings)
+                .map_err(|message| {
+                    AssetLoadError::new_processing_failed(file_path.to_string(), message)
+                })?;
+        }
+
+        Ok(asset)
+    }
+}
+
+/// A queued handle with the label of its path.
+type QueuedHandle = (Option<String>, Box<dyn ErasedHandle>);
+
+/// Splits a path like `scene.gltf#Mesh0` into the file path and the label.
+fn split_label(file_path: &str) -> (&str, Option<&str>) {
+    match file_path.split_once('#') {
+        Some((file_path, label)) => (file_path, Some(label)),
+        None => (file_path, None),
     }
 }
 
 trait ErasedHandle: Send + Sync {
+    fn asset_type(&self) -> TypeId;
+    /// Identifies the handle, shared by every clone of it.
+    fn id(&self) -> usize;
+    fn file_path(&self) -> String;
+    fn clone_handle(&self) -> Box<dyn ErasedHandle>;
+    fn as_any(&self) -> &dyn Any;
+    /// The number of [`Handle`]s to the asset, which doesn't count the [`Assets`] and the assets
+    /// depending on it.
+    fn strong_count(&self) -> usize;
+    /// When the last [`Handle`] was dropped, `None` while there are handles.
+    fn released_at(&self) -> Option<Instant>;
+    fn downgrade(&self) -> Box<dyn WeakErasedHandle>;
+    fn begin_reload(&self);
     fn is_loaded(&self) -> bool;
     fn is_error(&self) -> bool;
-    fn update_asset(&self, asset: Box<dyn Any>);
     fn update_error(&self, error: AssetLoadError);
+    /// Stores the asset of a load on a loading thread until it's committed on the main thread.
+    fn stage_asset(&self, asset: Box<dyn Any>);
+    fn stage_error(&self, error: AssetLoadError);
+    /// Makes the staged asset or error visible through the handle.
+    fn commit(&self);
+    fn set_dependencies(&self, dependencies: Vec<Box<dyn ErasedHandle>>);
 }
 
 impl<T: Send + Sync + 'static> ErasedHandle for Arc<HandleInner<T>> {
+    fn asset_type(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn id(&self) -> usize {
+        Arc::as_ptr(self) as usize
+    }
+
+    fn file_path(&self) -> String {
+        self.file_path.clone()
+    }
+
+    fn clone_handle(&self) -> Box<dyn ErasedHandle> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn strong_count(&self) -> usize {
+        self.strong_count.load(atomic::Ordering::Acquire)
+    }
+
+    fn released_at(&self) -> Option<Instant> {
+        *self.released_at.lock()
+    }
+
+    fn downgrade(&self) -> Box<dyn WeakErasedHandle> {
+        Box::new(Arc::downgrade(self))
+    }
+
+    fn begin_reload(&self) {
+        self.is_loaded.swap(false, atomic::Ordering::Relaxed);
+    }
+
     fn is_loaded(&self) -> bool {
         HandleInner::<T>::is_loaded(self.deref())
     }
@@ -177,32 +859,101 @@ impl<T: Send + Sync + 'static> ErasedHandle for Arc<HandleInner<T>> {
         HandleInner::<T>::is_error(self.deref())
     }
 
-    fn update_asset(&self, asset: Box<dyn Any>) {
-        self.asset.write().replace(
-            *asset
-                .downcast::<T>()
-                .expect("Failed to downcast asset to expected type"),
-        );
-        self.is_error.swap(false, atomic::Ordering::Relaxed);
-        self.is_loaded.swap(true, atomic::Ordering::Relaxed);
-    }
-
     fn update_error(&self, error: AssetLoadError) {
         self.error.write().replace(error);
         self.is_error.swap(true, atomic::Ordering::Relaxed);
         self.is_loaded.swap(true, atomic::Ordering::Relaxed);
     }
+
+    fn stage_asset(&self, asset: Box<dyn Any>) {
+        let asset = *asset
+            .downcast::<T>()
+            .expect("Failed to downcast asset to expected type");
+        *self.staged.lock() = Some(Ok(asset));
+    }
+
+    fn stage_error(&self, error: AssetLoadError) {
+        *self.staged.lock() = Some(Err(error));
+    }
+
+    fn commit(&self) {
+        let staged = self.staged.lock().take();
+        match staged {
+            Some(Ok(asset)) => self.set_asset(asset),
+            Some(Err(error)) => self.update_error(error),
+            None => {}
+        }
+    }
+
+    fn set_dependencies(&self, dependencies: Vec<Box<dyn ErasedHandle>>) {
+        *self.dependencies.write() = dependencies;
+    }
+}
+
+/// A handle which doesn't keep the asset alive, so the [`Assets`] can track every loaded handle.
+trait WeakErasedHandle: Send + Sync {
+    fn id(&self) -> usize;
+    fn is_alive(&self) -> bool;
+    fn upgrade(&self) -> Option<Box<dyn ErasedHandle>>;
+}
+
+impl<T: Send + Sync + 'static> WeakErasedHandle for Weak<HandleInner<T>> {
+    fn id(&self) -> usize {
+        self.as_ptr() as usize
+    }
+
+    fn is_alive(&self) -> bool {
+        self.strong_count() > 0
+    }
+
+    fn upgrade(&self) -> Option<Box<dyn ErasedHandle>> {
+        Weak::upgrade(self).map(|handle| Box::new(handle) as Box<dyn ErasedHandle>)
+    }
 }
 
+/// A strong handle to an asset, the asset is kept loaded while any strong handle to it exists.
 pub struct Handle<T> {
     inner: Arc<HandleInner<T>>,
 }
 
+/// Clones share the asset, a reload through any of them is seen by all of them.
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self::from_inner(self.inner.clone())
+    }
+}
+
+impl<T> Drop for Handle<T> {
+    fn drop(&mut self) {
+        if self
+            .inner
+            .strong_count
+            .fetch_sub(1, atomic::Ordering::AcqRel)
+            == 1
+        {
+            *self.inner.released_at.lock() = Some(Instant::now());
+        }
+    }
+}
+
+impl<T> Handle<T> {
+    fn from_inner(inner: Arc<HandleInner<T>>) -> Self {
+        inner.strong_count.fetch_add(1, atomic::Ordering::AcqRel);
+        *inner.released_at.lock() = None;
+        Self { inner }
+    }
+
+    /// Creates a handle which doesn't keep the asset loaded.
+    pub fn downgrade(&self) -> WeakHandle<T> {
+        WeakHandle {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+}
+
 impl<T: Send + Sync + 'static> Handle<T> {
     pub fn new(file_path: String) -> Self {
-        Self {
-            inner: Arc::new(HandleInner::new(file_path)),
-        }
+        Self::from_inner(Arc::new(HandleInner::new(file_path)))
     }
 
     pub fn is_loaded(&self) -> bool {
@@ -221,11 +972,18 @@ impl<T: Send + Sync + 'static> Handle<T> {
         self.inner.get_error()
     }
 
+    /// Loads the file of the asset again, does nothing for inserted assets.
     pub fn reload(&mut self, assets: &mut Assets) {
+        if self.inner.file_path.is_empty() {
+            return;
+        }
+
         self.inner.is_loaded.swap(false, atomic::Ordering::Relaxed);
-        assets
-            .queue
-            .push((self.inner.file_path.clone(), Box::new(self.inner.clone())));
+        assets.queue.push(QueuedLoad {
+            file_path: self.inner.file_path.clone(),
+            handle: Box::new(self.inner.clone()),
+            priority: LoadPriority::High,
+        });
     }
 
     pub fn into_watched(self) -> WatchedHandle<T> {
@@ -233,11 +991,40 @@ impl<T: Send + Sync + 'static> Handle<T> {
     }
 }
 
+/// A handle which doesn't keep the asset loaded, upgraded to a [`Handle`] to access the asset.
+pub struct WeakHandle<T> {
+    inner: Weak<HandleInner<T>>,
+}
+
+impl<T> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> WeakHandle<T> {
+    /// Returns a strong handle if the asset is still loaded, which keeps it from being released
+    /// by [`Assets::garbage_collect`].
+    pub fn upgrade(&self) -> Option<Handle<T>> {
+        self.inner.upgrade().map(Handle::from_inner)
+    }
+}
+
 pub struct HandleInner<T> {
     asset: RwLock<Option<T>>,
+    /// The assets the loader requested through the [`LoadContext`].
+    dependencies: RwLock<Vec<Box<dyn ErasedHandle>>>,
     error: RwLock<Option<AssetLoadError>>,
     is_loaded: AtomicBool,
     is_error: AtomicBool,
+    /// The result of a finished load which wasn't committed yet.
+    staged: Mutex<Option<Result<T, AssetLoadError>>>,
+    /// The number of [`Handle`]s, separate from the reference count of the `Arc` since the
+    /// [`Assets`] and the assets depending on this one hold it too.
+    strong_count: AtomicUsize,
+    released_at: Mutex<Option<Instant>>,
     file_path: String,
 }
 
@@ -245,21 +1032,36 @@ impl<T: Send + Sync + 'static> HandleInner<T> {
     fn new(file_path: String) -> Self {
         Self {
             asset: RwLock::new(None),
+            dependencies: RwLock::new(Vec::new()),
             error: RwLock::new(None),
             is_loaded: AtomicBool::new(false),
             is_error: AtomicBool::new(false),
+            staged: Mutex::new(None),
+            strong_count: AtomicUsize::new(0),
+            released_at: Mutex::new(None),
             file_path,
         }
     }
 
     fn is_loaded(&self) -> bool {
         self.is_loaded.load(atomic::Ordering::Relaxed)
+            && self
+                .dependencies
+                .read()
+                .iter()
+                .all(|dependency| dependency.is_loaded())
     }
 
     fn is_error(&self) -> bool {
         self.is_error.load(atomic::Ordering::Relaxed)
     }
 
+    fn set_asset(&self, asset: T) {
+        self.asset.write().replace(asset);
+        self.is_error.swap(false, atomic::Ordering::Relaxed);
+        self.is_loaded.swap(true, atomic::Ordering::Relaxed);
+    }
+
     fn get(&self) -> Option<MappedRwLockReadGuard<'_, T>> {
         if self.is_loaded() {
             Some(RwLockReadGuard::map(
@@ -280,6 +1082,9 @@ impl<T: Send + Sync + 'static> HandleInner<T> {
     }
 }
 
+/// A handle with its own file watcher, reloading the asset when its file changes.
+///
+/// Every watched handle creates a watcher, prefer [`Assets::watch`] when watching many assets.
 pub struct WatchedHandle<T> {
     handle: Handle<T>,
     should_reload: Arc<AtomicBool>,
@@ -306,17 +1111,23 @@ impl<T: Send + Sync + 'static> WatchedHandle<T> {
                     notify::EventKind::Modify(_) => {
                         let regex = regex::Regex::new(r"\\|\\\\").unwrap();
 
-                        if event
-                            .paths
-                            .iter()
-                            .any(|path| regex.replace_all(path.to_str().unwrap(), "/").to_string().ends_with(&regex.replace_all(&watcher_file_path, "/").to_string().as_str()))
-                        {
+                        if event.paths.iter().any(|path| {
+                            regex
+                                .replace_all(path.to_str().unwrap(), "/")
+                                .to_string()
+                                .ends_with(
+                                    &regex
+                                        .replace_all(&watcher_file_path, "/")
+                                        .to_string()
+                                        .as_str(),
+                                )
+                        }) {
                             watcher_should_reload.store(true, atomic::Ordering::Relaxed);
                         }
                     }
                     _ => {}
                 },
-                Err(e) => println!("watch error: {:?}", e),
+                Err(e) => log::error!("Asset watch error: {:?}", e),
             },
         )
         .expect("Failed to create file watcher");
@@ -371,3 +1182,266 @@ impl<T: Send + Sync + 'static> WatchedHandle<T> {
         self.handle.reload(assets);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    /// Serves files from memory and records the order they're read in.
+    #[derive(Default)]
+    struct TestSource {
+        files: HashMap<String, String>,
+        reads: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl TestSource {
+        fn with_file(mut self, path: &str, contents: &str) -> Self {
+            self.files.insert(path.to_string(), contents.to_string());
+            self
+        }
+    }
+
+    impl AssetSource for TestSource {
+        fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+            let contents = self
+                .files
+                .get(path)
+                .ok_or_else(|| crate::source::not_found(path))?;
+            self.reads.lock().push(path.to_string());
+            Ok(contents.as_bytes().to_vec())
+        }
+    }
+
+    /// Loaded from a file listing the nodes it depends on, one path per line.
+    struct Node {
+        dependencies: Vec<Handle<Node>>,
+    }
+
+    struct NodeLoader;
+
+    impl AssetLoader for NodeLoader {
+        type Asset = Node;
+
+        fn new() -> Self {
+            Self
+        }
+
+        fn load(
+            &self,
+            file_path: String,
+            context: &mut LoadContext,
+        ) -> Result<Node, AssetLoadError> {
+            let contents = String::from_utf8(context.read(&file_path)?).unwrap();
+            Ok(Node {
+                dependencies: contents.lines().map(|line| context.load(line)).collect(),
+            })
+        }
+
+        fn identifiers() -> &'static [&'static str] {
+            &["node"]
+        }
+    }
+
+    fn test_assets(source: TestSource) -> Assets {
+        let mut assets = Assets::with_source(source, &AsyncTaskPool::new(Some(1)));
+        assets.add_loader::<NodeLoader>();
+        assets
+    }
+
+    /// Sends the queued loads to the loading threads and waits for them to finish, without
+    /// finishing them on the main thread.
+    fn run_queued_loads(assets: &mut Assets) {
+        let count = assets.queue.len();
+        assets.submit_queue();
+        while assets.shared.finished.lock().len() < count {
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn high_priority_loads_run_before_background_loads() {
+        let source = TestSource::default()
+            .with_file("a.node", "")
+            .with_file("b.node", "")
+            .with_file("c.node", "");
+        let reads = source.reads.clone();
+        let mut assets = test_assets(source);
+
+        let background = assets.load_with_priority::<Node>("a.node", LoadPriority::Background);
+        let high = [assets.load::<Node>("b.node"), assets.load::<Node>("c.node")];
+
+        // Runs the jobs on this thread instead of spawning tasks, so they run in lane order.
+        for job in Assets::batch(std::mem::take(&mut assets.queue)) {
+            assets.shared.push(job);
+        }
+        for _ in 0..3 {
+            assets.shared.run_next();
+        }
+        assert_eq!(*reads.lock(), ["b.node", "c.node", "a.node"]);
+
+        assets.set_finish_budget(Duration::from_secs(10));
+        assets.update();
+        assert!(background.is_loaded());
+        assert!(high.iter().all(Handle::is_loaded));
+    }
+
+    #[test]
+    fn update_finishes_loads_within_the_budget() {
+        let mut assets = test_assets(
+            TestSource::default()
+                .with_file("a.node", "")
+                .with_file("b.node", "")
+                .with_file("c.node", ""),
+        );
+        let handles = ["a.node", "b.node", "c.node"].map(|path| assets.load::<Node>(path));
+        let loaded = |handles: &[Handle<Node>]| handles.iter().filter(|h| h.is_loaded()).count();
+
+        run_queued_loads(&mut assets);
+
+        // One load is finished per update even without any budget.
+        assets.set_finish_budget(Duration::ZERO);
+        assets.update();
+        assert_eq!(loaded(&handles), 1);
+        assets.update();
+        assert_eq!(loaded(&handles), 2);
+
+        assets.set_finish_budget(Duration::from_secs(10));
+        assets.update();
+        assert_eq!(loaded(&handles), 3);
+    }
+
+    #[test]
+    fn only_dropped_background_loads_are_cancelled() {
+        let source = TestSource::default()
+            .with_file("a.node", "")
+            .with_file("b.node", "");
+        let reads = source.reads.clone();
+        let mut assets = test_assets(source);
+        assets.set_finish_budget(Duration::from_secs(10));
+
+        let background = assets.load_with_priority::<Node>("a.node", LoadPriority::Background);
+        let high = assets.load::<Node>("b.node");
+        // Keeps the shared state without counting as a handle.
+        let background_inner = background.inner.clone();
+        let high_inner = high.inner.clone();
+        drop((background, high));
+
+        run_queued_loads(&mut assets);
+        assets.update();
+
+        assert_eq!(*reads.lock(), ["b.node"]);
+        assert_eq!(
+            background_inner.get_error().map(|error| error.kind),
+            Some(AssetLoadErrorKind::Cancelled)
+        );
+        assert!(high_inner.is_loaded());
+
+        // Loading the cancelled path again starts a new load.
+        let reloaded = assets.load_with_priority::<Node>("a.node", LoadPriority::Background);
+        assert!(!Arc::ptr_eq(&reloaded.inner, &background_inner));
+        run_queued_loads(&mut assets);
+        assets.update();
+        assert!(reloaded.is_loaded());
+    }
+
+    #[test]
+    fn blocking_loads_finish_with_their_dependencies() {
+        let mut assets = test_assets(
+            TestSource::default()
+                .with_file("scene.node", "mesh.node\ntexture.node")
+                .with_file("mesh.node", "material.node")
+                .with_file("material.node", "")
+                .with_file("texture.node", ""),
+        );
+        assets.set_finish_budget(Duration::ZERO);
+
+        let scene = assets.load_with_priority::<Node>("scene.node", LoadPriority::Blocking);
+        assets.update();
+
+        assert!(scene.is_loaded());
+        let scene = scene.get().unwrap();
+        let mesh = scene.dependencies[0].get().unwrap();
+        assert!(mesh.dependencies[0].is_loaded());
+        assert!(scene.dependencies[1].is_loaded());
+    }
+
+    fn load_blocking(assets: &mut Assets, file_path: &str) -> Handle<Node> {
+        let handle = assets.load_with_priority(file_path, LoadPriority::Blocking);
+        assets.update();
+        assert!(handle.is_loaded());
+        handle
+    }
+
+    #[test]
+    fn garbage_collect_releases_assets_without_handles() {
+        let mut assets = test_assets(
+            TestSource::default()
+                .with_file("a.node", "")
+                .with_file("b.node", "")
+                .with_file("scene.node", "mesh.node")
+                .with_file("mesh.node", ""),
+        );
+
+        let a = load_blocking(&mut assets, "a.node");
+        let weak_a = a.downgrade();
+        let b = load_blocking(&mut assets, "b.node");
+        let weak_b = b.downgrade();
+        let scene = load_blocking(&mut assets, "scene.node");
+        let weak_mesh = scene.get().unwrap().dependencies[0].downgrade();
+
+        drop(a);
+        assets.garbage_collect();
+        assert!(weak_a.upgrade().is_none());
+        assert!(weak_b.upgrade().is_some());
+        // The dependency is held by the scene.
+        assert!(weak_mesh.upgrade().is_some());
+
+        drop(scene);
+        assets.garbage_collect();
+        assert!(weak_mesh.upgrade().is_none());
+
+        // Loading a released asset starts a new load.
+        let a = assets.load::<Node>("a.node");
+        assert!(!a.is_loaded());
+    }
+
+    #[test]
+    fn garbage_collect_keeps_released_assets_for_the_grace_period() {
+        let mut assets = test_assets(TestSource::default().with_file("a.node", ""));
+        assets.set_unload_grace_period(Duration::from_secs(3600));
+
+        let a = load_blocking(&mut assets, "a.node");
+        let weak_a = a.downgrade();
+        drop(a);
+        assets.garbage_collect();
+
+        // Loading it again within the grace period returns the loaded asset.
+        let a = assets.load::<Node>("a.node");
+        assert!(a.is_loaded());
+        assert!(Arc::ptr_eq(&a.inner, &weak_a.upgrade().unwrap().inner));
+
+        drop(a);
+        assets.set_unload_grace_period(Duration::ZERO);
+        assets.garbage_collect();
+        assert!(weak_a.upgrade().is_none());
+    }
+
+    #[test]
+    fn garbage_collect_releases_inserted_assets() {
+        let mut assets = test_assets(TestSource::default());
+
+        let inserted = assets.insert(Node {
+            dependencies: Vec::new(),
+        });
+        let weak = inserted.downgrade();
+        assets.garbage_collect();
+        assert_eq!(assets.iter::<Node>().count(), 1);
+
+        drop(inserted);
+        assets.garbage_collect();
+        assert!(weak.upgrade().is_none());
+        assert_eq!(assets.iter::<Node>().count(), 0);
+    }
+}
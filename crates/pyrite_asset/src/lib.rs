@@ -2,9 +2,20 @@ extern crate shaderc;
 
 mod asset;
 pub mod loaders;
+mod pack;
+mod processor;
+mod source;
+mod watcher;
 
 pub use asset::*;
+pub use pack::*;
+pub use processor::{AssetProcessor, AssetSettings};
+pub use source::*;
+pub use watcher::AssetEvent;
 
 pub mod prelude {
-    pub use crate::{AssetLoader, Assets, Handle};
+    pub use crate::{
+        AssetEvent, AssetLoader, AssetProcessor, AssetSettings, AssetSource, Assets, Handle,
+        LoadContext, LoadPriority,
+    };
 }
@@ -0,0 +1,57 @@
+use std::{
+    io::{Read, Seek},
+    time::Duration,
+};
+
+/// Decoded PCM audio with the channels of each frame interleaved.
+#[derive(Clone)]
+pub struct AudioClip {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// The samples in the -1 to 1 range, `channels` samples per frame.
+    pub samples: Vec<f32>,
+}
+
+impl AudioClip {
+    /// The number of frames, i.e. samples per channel.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate as f64)
+    }
+}
+
+/// The encoded audio an [`AudioDecoder`] reads from, e.g. a file or the bytes of a file read from
+/// an [`crate::AssetSource`].
+pub trait AudioStream: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> AudioStream for T {}
+
+/// Decodes an audio file a chunk at a time, so long files can be streamed instead of being
+/// decoded into an [`AudioClip`] up front.
+pub trait AudioDecoder: Send {
+    fn sample_rate(&self) -> u32;
+
+    fn channels(&self) -> u16;
+
+    /// Decodes up to `max_frames` frames, appending their interleaved samples to `samples`.
+    /// Returns the number of frames decoded, which is only 0 once the end of the file is reached.
+    fn decode(&mut self, samples: &mut Vec<f32>, max_frames: usize) -> Result<usize, String>;
+}
+
+/// The frames decoded per chunk when a whole file is decoded.
+const DECODE_CHUNK_FRAMES: usize = 16384;
+
+/// Decodes the rest of the file into an [`AudioClip`].
+pub fn decode_to_end(decoder: &mut dyn AudioDecoder) -> Result<AudioClip, String> {
+    let mut samples = Vec::new();
+    while decoder.decode(&mut samples, DECODE_CHUNK_FRAMES)? > 0 {}
+
+    Ok(AudioClip {
+        sample_rate: decoder.sample_rate(),
+        channels: decoder.channels(),
+        samples,
+    })
+}
@@ -0,0 +1,61 @@
+use std::sync::atomic::{self, AtomicU64};
+
+use crate::{AssetLoadError, AssetLoader, LoadContext};
+
+pub use ab_glyph;
+
+static NEXT_FONT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A parsed TrueType or OpenType font.
+pub struct Font {
+    id: u64,
+    font: ab_glyph::FontVec,
+}
+
+impl Font {
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, ab_glyph::InvalidFont> {
+        Ok(Self {
+            id: NEXT_FONT_ID.fetch_add(1, atomic::Ordering::Relaxed),
+            font: ab_glyph::FontVec::try_from_vec(bytes)?,
+        })
+    }
+
+    /// An id unique to this font, a reloaded font gets a new id so caches keyed by it don't
+    /// return glyphs of the old file.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn font(&self) -> &ab_glyph::FontVec {
+        &self.font
+    }
+}
+
+pub struct FontLoader {}
+
+impl AssetLoader for FontLoader {
+    type Asset = Font;
+
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Self::Asset, AssetLoadError>
+    where
+        Self: Sized,
+    {
+        Font::from_bytes(context.read(&file_path)?)
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path, err.to_string()))
+    }
+
+    fn identifiers() -> &'static [&'static str] {
+        &["ttf", "otf"]
+    }
+}
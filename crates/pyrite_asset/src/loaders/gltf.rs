@@ -1,21 +1,454 @@
-use crate::{AssetLoadError, AssetLoader};
+use std::collections::HashSet;
 
-pub struct Gltf {
-    pub document: gltf::Document,
-    pub buffers: Vec<gltf::buffer::Data>,
-    pub images: Vec<gltf::image::Data>,
+use base64::Engine;
+use pyrite_math::{
+    Mat4, OrthographicProjection, PerspectiveProjection, Quat, Transform, Vec2, Vec3, Vec4,
+};
+
+use crate::{AssetLoadError, AssetLoader, LoadContext};
+
+use super::image::{ImageData, ImageFormat};
+
+/// A glTF file resolved into plain data, with every buffer accessor read and every image decoded.
+///
+/// Everything references each other through indices into the vectors of the scene, e.g.
+/// [`GltfNode::mesh`] indexes [`GltfScene::meshes`].
+///
+/// The meshes and materials are also added as labeled assets, `scene.gltf#Mesh0` loads the first
+/// [`GltfMesh`] and `scene.gltf#Material0` the first [`GltfMaterial`].
+pub struct GltfScene {
+    pub nodes: Vec<GltfNode>,
+    /// The nodes without a parent in the default scene, or the first scene if there is no
+    /// default.
+    pub roots: Vec<usize>,
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub textures: Vec<GltfTexture>,
+    /// The images converted to RGBA8, images used as base color or emissive textures are sRGB and
+    /// everything else is linear.
+    pub images: Vec<ImageData>,
+    pub cameras: Vec<GltfCamera>,
+    pub skins: Vec<GltfSkin>,
+}
+
+pub struct GltfNode {
+    pub name: Option<String>,
+    /// The transform relative to the parent node.
+    pub transform: Transform,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub skin: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// A triangle list, attributes missing from the file are empty.
+#[derive(Clone)]
+pub struct GltfPrimitive {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    /// The tangents with the bitangent sign in w.
+    pub tangents: Vec<Vec4>,
+    /// The first set of texture coordinates.
+    pub uvs: Vec<Vec2>,
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<Vec4>,
+    /// The indices, generated if the primitive isn't indexed.
+    pub indices: Vec<u32>,
+    /// The material, `None` for the default material.
+    pub material: Option<usize>,
+}
+
+/// A reference to a texture from a material.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GltfTextureRef {
+    pub texture: usize,
+    /// The texture coordinate set the texture is sampled with.
+    pub tex_coord: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GltfAlphaMode {
+    Opaque,
+    /// Fragments with an alpha below the cutoff are discarded.
+    Mask {
+        cutoff: f32,
+    },
+    Blend,
+}
+
+/// A metallic roughness PBR material.
+#[derive(Clone)]
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: Vec4,
+    pub base_color_texture: Option<GltfTextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// Roughness in the green channel and metalness in the blue channel.
+    pub metallic_roughness_texture: Option<GltfTextureRef>,
+    pub normal_texture: Option<GltfTextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<GltfTextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: Vec3,
+    pub emissive_texture: Option<GltfTextureRef>,
+    pub alpha_mode: GltfAlphaMode,
+    pub double_sided: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GltfFilter {
+    Nearest,
+    Linear,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GltfWrap {
+    ClampToEdge,
+    MirroredRepeat,
+    Repeat,
+}
+
+/// How a texture is sampled, filters the file leaves unspecified are linear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GltfSampler {
+    pub mag_filter: GltfFilter,
+    pub min_filter: GltfFilter,
+    pub mipmap_filter: GltfFilter,
+    pub wrap_s: GltfWrap,
+    pub wrap_t: GltfWrap,
+}
+
+pub struct GltfTexture {
+    /// The index of the image in [`GltfScene::images`].
+    pub image: usize,
+    pub sampler: GltfSampler,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GltfCamera {
+    /// An aspect ratio or far plane the file leaves unspecified uses the default projection's.
+    Perspective(PerspectiveProjection),
+    Orthographic(OrthographicProjection),
+}
+
+pub struct GltfSkin {
+    pub name: Option<String>,
+    /// The joint nodes, in the order the joint indices of the primitives refer to them.
+    pub joints: Vec<usize>,
+    /// The inverse bind matrix of each joint, identity if the file doesn't specify them.
+    pub inverse_bind_matrices: Vec<Mat4>,
+    pub skeleton: Option<usize>,
+}
+
+/// Loads glTF files into a [`GltfScene`].
+///
+/// Only triangle list primitives are loaded, other primitives are skipped with a warning.
 pub struct GltfLoader {}
 
 impl GltfLoader {
     pub fn new() -> Self {
         Self {}
     }
+
+    fn load_node(node: gltf::Node) -> GltfNode {
+        let (translation, rotation, scale) = node.transform().decomposed();
+
+        GltfNode {
+            name: node.name().map(str::to_string),
+            transform: Transform {
+                translation: Vec3::from(translation),
+                rotation: Quat::from_array(rotation),
+                scale: Vec3::from(scale),
+            },
+            parent: None,
+            children: node.children().map(|child| child.index()).collect(),
+            mesh: node.mesh().map(|mesh| mesh.index()),
+            camera: node.camera().map(|camera| camera.index()),
+            skin: node.skin().map(|skin| skin.index()),
+        }
+    }
+
+    fn load_mesh(mesh: gltf::Mesh, buffers: &[Vec<u8>]) -> GltfMesh {
+        let primitives = mesh
+            .primitives()
+            .filter(|primitive| {
+                let is_triangle_list = primitive.mode() == gltf::mesh::Mode::Triangles;
+                if !is_triangle_list {
+                    log::warn!(
+                        "Skipping {:?} primitive of gltf mesh {}, only triangle lists are supported.",
+                        primitive.mode(),
+                        mesh.index()
+                    );
+                }
+                is_triangle_list
+            })
+            .map(|primitive| {
+                let reader = primitive.reader(|buffer| Some(buffers[buffer.index()].as_slice()));
+
+                let positions: Vec<Vec3> = reader
+                    .read_positions()
+                    .map(|positions| positions.map(Vec3::from).collect())
+                    .unwrap_or_default();
+                let indices = reader
+                    .read_indices()
+                    .map(|indices| indices.into_u32().collect())
+                    .unwrap_or_else(|| (0..positions.len() as u32).collect());
+
+                GltfPrimitive {
+                    normals: reader
+                        .read_normals()
+                        .map(|normals| normals.map(Vec3::from).collect())
+                        .unwrap_or_default(),
+                    tangents: reader
+                        .read_tangents()
+                        .map(|tangents| tangents.map(Vec4::from).collect())
+                        .unwrap_or_default(),
+                    uvs: reader
+                        .read_tex_coords(0)
+                        .map(|uvs| uvs.into_f32().map(Vec2::from).collect())
+                        .unwrap_or_default(),
+                    joints: reader
+                        .read_joints(0)
+                        .map(|joints| joints.into_u16().collect())
+                        .unwrap_or_default(),
+                    weights: reader
+                        .read_weights(0)
+                        .map(|weights| weights.into_f32().map(Vec4::from).collect())
+                        .unwrap_or_default(),
+                    positions,
+                    indices,
+                    material: primitive.material().index(),
+                }
+            })
+            .collect();
+
+        GltfMesh {
+            name: mesh.name().map(str::to_string),
+            primitives,
+        }
+    }
+
+    fn load_material(material: gltf::Material) -> GltfMaterial {
+        fn texture_ref(info: gltf::texture::Info) -> GltfTextureRef {
+            GltfTextureRef {
+                texture: info.texture().index(),
+                tex_coord: info.tex_coord(),
+            }
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let normal_texture = material.normal_texture();
+        let occlusion_texture = material.occlusion_texture();
+
+        GltfMaterial {
+            name: material.name().map(str::to_string),
+            base_color_factor: Vec4::from(pbr.base_color_factor()),
+            base_color_texture: pbr.base_color_texture().map(texture_ref),
+            metallic_factor: pbr.metallic_factor(),
+            roughness_factor: pbr.roughness_factor(),
+            metallic_roughness_texture: pbr.metallic_roughness_texture().map(texture_ref),
+            normal_texture: normal_texture.as_ref().map(|texture| GltfTextureRef {
+                texture: texture.texture().index(),
+                tex_coord: texture.tex_coord(),
+            }),
+            normal_scale: normal_texture.map_or(1.0, |texture| texture.scale()),
+            occlusion_texture: occlusion_texture.as_ref().map(|texture| GltfTextureRef {
+                texture: texture.texture().index(),
+                tex_coord: texture.tex_coord(),
+            }),
+            occlusion_strength: occlusion_texture.map_or(1.0, |texture| texture.strength()),
+            emissive_factor: Vec3::from(material.emissive_factor()),
+            emissive_texture: material.emissive_texture().map(texture_ref),
+            alpha_mode: match material.alpha_mode() {
+                gltf::material::AlphaMode::Opaque => GltfAlphaMode::Opaque,
+                gltf::material::AlphaMode::Mask => GltfAlphaMode::Mask {
+                    cutoff: material.alpha_cutoff().unwrap_or(0.5),
+                },
+                gltf::material::AlphaMode::Blend => GltfAlphaMode::Blend,
+            },
+            double_sided: material.double_sided(),
+        }
+    }
+
+    fn load_texture(texture: gltf::Texture) -> GltfTexture {
+        use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+        let sampler = texture.sampler();
+        let wrap = |mode: WrappingMode| match mode {
+            WrappingMode::ClampToEdge => GltfWrap::ClampToEdge,
+            WrappingMode::MirroredRepeat => GltfWrap::MirroredRepeat,
+            WrappingMode::Repeat => GltfWrap::Repeat,
+        };
+        let (min_filter, mipmap_filter) = match sampler.min_filter() {
+            Some(MinFilter::Nearest) | Some(MinFilter::NearestMipmapNearest) => {
+                (GltfFilter::Nearest, GltfFilter::Nearest)
+            }
+            Some(MinFilter::NearestMipmapLinear) => (GltfFilter::Nearest, GltfFilter::Linear),
+            Some(MinFilter::LinearMipmapNearest) => (GltfFilter::Linear, GltfFilter::Nearest),
+            Some(MinFilter::Linear) | Some(MinFilter::LinearMipmapLinear) | None => {
+                (GltfFilter::Linear, GltfFilter::Linear)
+            }
+        };
+
+        GltfTexture {
+            image: texture.source().index(),
+            sampler: GltfSampler {
+                mag_filter: match sampler.mag_filter() {
+                    Some(MagFilter::Nearest) => GltfFilter::Nearest,
+                    Some(MagFilter::Linear) | None => GltfFilter::Linear,
+                },
+                min_filter,
+                mipmap_filter,
+                wrap_s: wrap(sampler.wrap_s()),
+                wrap_t: wrap(sampler.wrap_t()),
+            },
+        }
+    }
+
+    fn load_camera(camera: gltf::Camera) -> GltfCamera {
+        match camera.projection() {
+            gltf::camera::Projection::Perspective(perspective) => {
+                let default = PerspectiveProjection::default();
+                GltfCamera::Perspective(PerspectiveProjection {
+                    fov_y: perspective.yfov(),
+                    aspect_ratio: perspective.aspect_ratio().unwrap_or(default.aspect_ratio),
+                    near: perspective.znear(),
+                    far: perspective.zfar().unwrap_or(default.far),
+                    reverse_z: false,
+                })
+            }
+            gltf::camera::Projection::Orthographic(orthographic) => {
+                GltfCamera::Orthographic(OrthographicProjection {
+                    left: -orthographic.xmag(),
+                    right: orthographic.xmag(),
+                    bottom: -orthographic.ymag(),
+                    top: orthographic.ymag(),
+                    near: orthographic.znear(),
+                    far: orthographic.zfar(),
+                })
+            }
+        }
+    }
+
+    fn load_skin(skin: gltf::Skin, buffers: &[Vec<u8>]) -> GltfSkin {
+        let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+        let inverse_bind_matrices = skin
+            .reader(|buffer| Some(buffers[buffer.index()].as_slice()))
+            .read_inverse_bind_matrices()
+            .map(|matrices| {
+                matrices
+                    .map(|matrix| Mat4::from_cols_array_2d(&matrix))
+                    .collect()
+            })
+            .unwrap_or_else(|| vec![Mat4::IDENTITY; joints.len()]);
+
+        GltfSkin {
+            name: skin.name().map(str::to_string),
+            joints,
+            inverse_bind_matrices,
+            skeleton: skin.skeleton().map(|skeleton| skeleton.index()),
+        }
+    }
+
+    /// Reads a buffer or image URI, which is either a base64 data URI or a path relative to the
+    /// glTF file.
+    fn read_uri(uri: &str, context: &LoadContext) -> Result<Vec<u8>, AssetLoadError> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (_, base64_data) = data.split_once(";base64,").ok_or_else(|| {
+                AssetLoadError::new_invalid_file(
+                    uri.to_string(),
+                    "Unsupported data URI".to_string(),
+                )
+            })?;
+            return base64::engine::general_purpose::STANDARD
+                .decode(base64_data)
+                .map_err(|err| AssetLoadError::new_invalid_file(uri.to_string(), err.to_string()));
+        }
+
+        context.read(&context.resolve_path(&percent_decode(uri)))
+    }
+
+    fn load_buffers(
+        document: &gltf::Document,
+        mut blob: Option<Vec<u8>>,
+        context: &LoadContext,
+    ) -> Result<Vec<Vec<u8>>, AssetLoadError> {
+        document
+            .buffers()
+            .map(|buffer| match buffer.source() {
+                gltf::buffer::Source::Bin => blob.take().ok_or_else(|| {
+                    AssetLoadError::new_invalid_file(
+                        format!("buffer {}", buffer.index()),
+                        "Missing binary chunk".to_string(),
+                    )
+                }),
+                gltf::buffer::Source::Uri(uri) => Self::read_uri(uri, context),
+            })
+            .collect()
+    }
+
+    /// Decodes an image into RGBA8, the format is detected from the image data.
+    fn load_image(
+        image: gltf::Image,
+        format: ImageFormat,
+        buffers: &[Vec<u8>],
+        context: &LoadContext,
+    ) -> Result<ImageData, AssetLoadError> {
+        let uri_bytes;
+        let (bytes, name) = match image.source() {
+            gltf::image::Source::View { view, .. } => {
+                let start = view.offset();
+                let bytes = &buffers[view.buffer().index()][start..start + view.length()];
+                (bytes, format!("image {}", image.index()))
+            }
+            gltf::image::Source::Uri { uri, .. } => {
+                uri_bytes = Self::read_uri(uri, context)?;
+                (uri_bytes.as_slice(), uri.to_string())
+            }
+        };
+
+        let decoded = image::load_from_memory(bytes)
+            .map_err(|err| AssetLoadError::new_invalid_file(name, err.to_string()))?;
+        Ok(ImageData::from_image(decoded, format))
+    }
+}
+
+/// Decodes the `%XX` escapes in a relative URI.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
 impl AssetLoader for GltfLoader {
-    type Asset = Gltf;
+    type Asset = GltfScene;
 
     fn new() -> Self
     where
@@ -24,19 +457,82 @@ impl AssetLoader for GltfLoader {
         Self {}
     }
 
-    fn load(&self, file_path: String) -> Result<Self::Asset, AssetLoadError>
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Self::Asset, AssetLoadError>
     where
         Self: Sized,
     {
-        let (document, buffers, images) = gltf::import(file_path).unwrap();
-        Ok(Gltf {
-            document,
-            buffers,
+        let gltf::Gltf { document, blob } = gltf::Gltf::from_slice(&context.read(&file_path)?)
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path.clone(), err.to_string()))?;
+        let buffers = Self::load_buffers(&document, blob, context)?;
+
+        let mut nodes: Vec<GltfNode> = document.nodes().map(Self::load_node).collect();
+        for parent in 0..nodes.len() {
+            for child in nodes[parent].children.clone() {
+                nodes[child].parent = Some(parent);
+            }
+        }
+        let roots = match document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+        {
+            Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+            None => (0..nodes.len())
+                .filter(|node| nodes[*node].parent.is_none())
+                .collect(),
+        };
+
+        let materials: Vec<GltfMaterial> = document.materials().map(Self::load_material).collect();
+        let textures: Vec<GltfTexture> = document.textures().map(Self::load_texture).collect();
+
+        let srgb_images: HashSet<usize> = materials
+            .iter()
+            .flat_map(|material| [material.base_color_texture, material.emissive_texture])
+            .flatten()
+            .map(|texture_ref| textures[texture_ref.texture].image)
+            .collect();
+        let images = document
+            .images()
+            .map(|image| {
+                let format = if srgb_images.contains(&image.index()) {
+                    ImageFormat::Rgba8Srgb
+                } else {
+                    ImageFormat::Rgba8Unorm
+                };
+                Self::load_image(image, format, &buffers, context)
+            })
+            .collect::<Result<_, _>>()?;
+
+        let meshes: Vec<GltfMesh> = document
+            .meshes()
+            .map(|mesh| Self::load_mesh(mesh, &buffers))
+            .collect();
+        for (index, mesh) in meshes.iter().enumerate() {
+            context.add_labeled_asset(format!("Mesh{}", index), mesh.clone());
+        }
+        for (index, material) in materials.iter().enumerate() {
+            context.add_labeled_asset(format!("Material{}", index), material.clone());
+        }
+
+        Ok(GltfScene {
+            nodes,
+            roots,
+            meshes,
+            materials,
+            textures,
             images,
+            cameras: document.cameras().map(Self::load_camera).collect(),
+            skins: document
+                .skins()
+                .map(|skin| Self::load_skin(skin, &buffers))
+                .collect(),
         })
     }
 
     fn identifiers() -> &'static [&'static str] {
-        &["gltf"]
+        &["gltf", "glb"]
     }
 }
@@ -1,18 +1,71 @@
-use crate::{AssetLoadError, AssetLoader};
+use std::path::Path;
 
-pub struct Image {
+use crate::{AssetLoadError, AssetLoader, LoadContext};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageExtent {
     pub width: u32,
     pub height: u32,
+}
+
+/// The format of the bytes of an [`ImageData`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// RGBA8 color data in the sRGB color space.
+    Rgba8Srgb,
+    /// RGBA8 linear data, e.g. normal or roughness maps.
+    Rgba8Unorm,
+}
+
+/// A decoded image, converted to RGBA8 regardless of the channels in the file.
+pub struct ImageData {
+    pub extent: ImageExtent,
+    pub format: ImageFormat,
+    /// The number of channels in the file, before the conversion to RGBA8.
     pub channels: u8,
+    /// The tightly packed texels, row by row.
+    pub bytes: Vec<u8>,
+}
 
-    /// The image data in RGBA8 format.
-    pub data: Vec<u8>,
+impl ImageData {
+    pub(crate) fn from_image(image: image::DynamicImage, format: ImageFormat) -> Self {
+        let channels = image.color().channel_count();
+        let rgba8 = image.into_rgba8();
+        Self {
+            extent: ImageExtent {
+                width: rgba8.width(),
+                height: rgba8.height(),
+            },
+            format,
+            channels,
+            bytes: rgba8.into_vec(),
+        }
+    }
 }
 
+/// Decodes PNG, JPEG, TGA and BMP files into [`ImageData`].
+///
+/// Images are treated as sRGB color data unless their file name ends in `.linear` before the
+/// extension, e.g. `normals.linear.png`.
 pub struct ImageLoader {}
 
+impl ImageLoader {
+    fn format(file_path: &str) -> ImageFormat {
+        let is_linear = Path::new(file_path)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.ends_with(".linear"));
+
+        if is_linear {
+            ImageFormat::Rgba8Unorm
+        } else {
+            ImageFormat::Rgba8Srgb
+        }
+    }
+}
+
 impl AssetLoader for ImageLoader {
-    type Asset = Image;
+    type Asset = ImageData;
 
     fn new() -> Self
     where
@@ -21,22 +74,23 @@ impl AssetLoader for ImageLoader {
         Self {}
     }
 
-    fn load(&self, file_path: String) -> Result<Self::Asset, AssetLoadError>
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Self::Asset, AssetLoadError>
     where
         Self: Sized,
     {
-        let img = image::open(file_path).unwrap();
-        let channels = img.color().channel_count();
-        let rgba8 = img.into_rgba8();
-        Ok(Image {
-            width: rgba8.width(),
-            height: rgba8.height(),
-            channels: channels as u8,
-            data: rgba8.into_vec(),
-        })
+        let bytes = context.read(&file_path)?;
+        let img = image::ImageFormat::from_path(&file_path)
+            .and_then(|image_format| image::load_from_memory_with_format(&bytes, image_format))
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path.clone(), err.to_string()))?;
+
+        Ok(ImageData::from_image(img, Self::format(&file_path)))
     }
 
     fn identifiers() -> &'static [&'static str] {
-        &["png", "jpg", "jpeg"]
+        &["png", "jpg", "jpeg", "tga", "bmp"]
     }
 }
@@ -0,0 +1,237 @@
+use std::io::Read;
+
+use basis_universal::{
+    DecodeFlags, LowLevelUastcTranscoder, SliceParametersUastc, TranscoderBlockFormat,
+};
+
+use crate::{loaders::image::ImageExtent, AssetLoadError, AssetLoader, LoadContext};
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+];
+const HEADER_SIZE: usize = 80;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+const SUPERCOMPRESSION_NONE: u32 = 0;
+const SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+
+const COLOR_MODEL_UASTC: u8 = 166;
+const TRANSFER_FUNCTION_SRGB: u8 = 2;
+const UASTC_CHANNEL_RGBA: u8 = 3;
+const UASTC_CHANNEL_RRRG: u8 = 5;
+
+/// How the levels of a [`Ktx2Texture`] are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ktx2Encoding {
+    /// Texels or blocks of a format the gpu can sample directly, the raw `VkFormat`.
+    Native { vk_format: u32 },
+    /// UASTC blocks, which must be transcoded with [`Ktx2Texture::transcode`].
+    Uastc { has_alpha: bool },
+}
+
+/// A block format UASTC can be transcoded to, chosen by what the gpu supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscodeTarget {
+    Bc7,
+    Astc4x4,
+    Etc2Rgba,
+    /// Uncompressed RGBA8, supported everywhere.
+    Rgba8,
+}
+
+/// A 2d texture from a KTX2 file with its supercompression already removed.
+pub struct Ktx2Texture {
+    pub extent: ImageExtent,
+    pub encoding: Ktx2Encoding,
+    /// Whether the texels are sRGB color data.
+    pub srgb: bool,
+    /// The mip levels, largest first.
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl Ktx2Texture {
+    /// Parses a KTX2 file, decompressing Zstd supercompressed levels.
+    ///
+    /// BasisLZ (ETC1S) files aren't supported, textures should be encoded with UASTC instead.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.get(..IDENTIFIER.len()) != Some(&IDENTIFIER[..]) {
+            return Err("Not a KTX2 file".to_string());
+        }
+
+        let vk_format = read_u32(bytes, 12)?;
+        let width = read_u32(bytes, 20)?;
+        let height = read_u32(bytes, 24)?;
+        let depth = read_u32(bytes, 28)?;
+        let layer_count = read_u32(bytes, 32)?;
+        let face_count = read_u32(bytes, 36)?;
+        // A level count of 0 asks for the mips to be generated, which compressed formats can't.
+        let level_count = read_u32(bytes, 40)?.max(1);
+        let supercompression_scheme = read_u32(bytes, 44)?;
+        let dfd_offset = read_u32(bytes, 48)? as usize;
+
+        if depth > 1 || layer_count > 1 || face_count != 1 {
+            return Err("Only 2d textures without layers or faces are supported".to_string());
+        }
+
+        let color_model = read_u8(bytes, dfd_offset + 12)?;
+        let transfer_function = read_u8(bytes, dfd_offset + 14)?;
+        // The channel id of the first sample, in the low bits of its channel type.
+        let first_channel = read_u8(bytes, dfd_offset + 31)? & 0xF;
+
+        let encoding = match (vk_format, color_model) {
+            (0, COLOR_MODEL_UASTC) => Ktx2Encoding::Uastc {
+                has_alpha: matches!(first_channel, UASTC_CHANNEL_RGBA | UASTC_CHANNEL_RRRG),
+            },
+            (0, _) => return Err("BasisLZ (ETC1S) textures aren't supported".to_string()),
+            (vk_format, _) => Ktx2Encoding::Native { vk_format },
+        };
+
+        let levels = (0..level_count as usize)
+            .map(|level| {
+                let entry = HEADER_SIZE + level * LEVEL_INDEX_ENTRY_SIZE;
+                let offset = read_u64(bytes, entry)? as usize;
+                let length = read_u64(bytes, entry + 8)? as usize;
+                let uncompressed_length = read_u64(bytes, entry + 16)? as usize;
+                let data = bytes
+                    .get(offset..offset + length)
+                    .ok_or_else(|| format!("Level {} is out of bounds", level))?;
+
+                match supercompression_scheme {
+                    SUPERCOMPRESSION_NONE => Ok(data.to_vec()),
+                    SUPERCOMPRESSION_ZSTD => {
+                        let mut decoder = ruzstd::StreamingDecoder::new(data).map_err(|err| {
+                            format!("Failed to decompress level {}: {:?}", level, err)
+                        })?;
+                        let mut decompressed = Vec::with_capacity(uncompressed_length);
+                        decoder.read_to_end(&mut decompressed).map_err(|err| {
+                            format!("Failed to decompress level {}: {}", level, err)
+                        })?;
+                        Ok(decompressed)
+                    }
+                    SUPERCOMPRESSION_BASIS_LZ => {
+                        Err("BasisLZ (ETC1S) textures aren't supported".to_string())
+                    }
+                    scheme => Err(format!("Unsupported supercompression scheme {}", scheme)),
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            extent: ImageExtent { width, height },
+            encoding,
+            srgb: transfer_function == TRANSFER_FUNCTION_SRGB,
+            levels,
+        })
+    }
+
+    /// Whether the file contains every mip level down to 1x1.
+    pub fn is_mip_complete(&self) -> bool {
+        let full_mip_levels = u32::BITS - self.extent.width.max(self.extent.height).leading_zeros();
+        self.levels.len() as u32 >= full_mip_levels
+    }
+
+    /// The extent of a mip level.
+    pub fn level_extent(&self, level: usize) -> ImageExtent {
+        ImageExtent {
+            width: (self.extent.width >> level).max(1),
+            height: (self.extent.height >> level).max(1),
+        }
+    }
+
+    /// Transcodes UASTC levels to `target`, native levels are returned as they are.
+    pub fn transcode(&self, target: TranscodeTarget) -> Result<Vec<Vec<u8>>, String> {
+        let Ktx2Encoding::Uastc { has_alpha } = self.encoding else {
+            return Ok(self.levels.clone());
+        };
+
+        basis_universal::transcoder_init();
+        let transcoder = LowLevelUastcTranscoder::new();
+        let block_format = match target {
+            TranscodeTarget::Bc7 => TranscoderBlockFormat::BC7,
+            TranscodeTarget::Astc4x4 => TranscoderBlockFormat::ASTC_4x4,
+            TranscodeTarget::Etc2Rgba => TranscoderBlockFormat::ETC2_RGBA,
+            TranscodeTarget::Rgba8 => TranscoderBlockFormat::RGBA32,
+        };
+
+        self.levels
+            .iter()
+            .enumerate()
+            .map(|(level, data)| {
+                let extent = self.level_extent(level);
+                transcoder
+                    .transcode_slice(
+                        data,
+                        SliceParametersUastc {
+                            num_blocks_x: extent.width.div_ceil(4),
+                            num_blocks_y: extent.height.div_ceil(4),
+                            has_alpha,
+                            original_width: extent.width,
+                            original_height: extent.height,
+                        },
+                        DecodeFlags::HIGH_QUALITY,
+                        block_format,
+                    )
+                    .map_err(|err| {
+                        format!(
+                            "Failed to transcode level {} to {:?}: {:?}",
+                            level, target, err
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+fn read_u8(bytes: &[u8], offset: usize) -> Result<u8, String> {
+    bytes
+        .get(offset)
+        .copied()
+        .ok_or_else(|| "Unexpected end of file".to_string())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| "Unexpected end of file".to_string())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, String> {
+    bytes
+        .get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| "Unexpected end of file".to_string())
+}
+
+/// Loads 2d KTX2 textures, see [`Ktx2Texture::parse`].
+pub struct Ktx2Loader {}
+
+impl AssetLoader for Ktx2Loader {
+    type Asset = Ktx2Texture;
+
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Self::Asset, AssetLoadError>
+    where
+        Self: Sized,
+    {
+        let bytes = context.read(&file_path)?;
+
+        Ktx2Texture::parse(&bytes)
+            .map_err(|message| AssetLoadError::new_invalid_file(file_path, message))
+    }
+
+    fn identifiers() -> &'static [&'static str] {
+        &["ktx2"]
+    }
+}
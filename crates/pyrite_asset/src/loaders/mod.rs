@@ -1,4 +1,9 @@
+pub mod audio;
+pub mod font;
 pub mod gltf;
 pub mod image;
-pub mod spirv;
+pub mod ktx2;
+pub mod ogg;
+pub mod shader;
 pub mod txt;
+pub mod wav;
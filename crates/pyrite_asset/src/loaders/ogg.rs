@@ -0,0 +1,107 @@
+use std::{
+    fs::File,
+    io::{BufReader, Cursor},
+};
+
+use lewton::inside_ogg::OggStreamReader;
+
+use crate::{AssetLoadError, AssetLoader, LoadContext};
+
+use super::audio::{decode_to_end, AudioClip, AudioDecoder, AudioStream};
+
+/// Decodes Ogg Vorbis files.
+pub struct OggDecoder {
+    reader: OggStreamReader<Box<dyn AudioStream>>,
+    /// The samples of the last packet which didn't fit in the previous chunk.
+    pending: Vec<f32>,
+}
+
+impl OggDecoder {
+    pub fn new(stream: impl AudioStream + 'static) -> Result<Self, lewton::VorbisError> {
+        Ok(Self {
+            reader: OggStreamReader::new(Box::new(stream) as Box<dyn AudioStream>)?,
+            pending: Vec::new(),
+        })
+    }
+}
+
+impl AudioDecoder for OggDecoder {
+    fn sample_rate(&self) -> u32 {
+        self.reader.ident_hdr.audio_sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.reader.ident_hdr.audio_channels as u16
+    }
+
+    fn decode(&mut self, samples: &mut Vec<f32>, max_frames: usize) -> Result<usize, String> {
+        let channels = self.channels() as usize;
+        let max_samples = max_frames * channels;
+
+        // Vorbis packets are decoded whole, so a packet can overshoot the chunk.
+        while self.pending.len() < max_samples {
+            match self
+                .reader
+                .read_dec_packet_itl()
+                .map_err(|err| err.to_string())?
+            {
+                Some(packet) => self.pending.extend(
+                    packet
+                        .into_iter()
+                        .map(|sample| sample as f32 / -(i16::MIN as f32)),
+                ),
+                None => break,
+            }
+        }
+
+        let count = self.pending.len().min(max_samples);
+        samples.extend(self.pending.drain(..count));
+
+        Ok(count / channels)
+    }
+}
+
+/// Loads Ogg Vorbis files into [`AudioClip`]s.
+pub struct OggLoader {}
+
+impl OggLoader {
+    /// Opens an Ogg Vorbis file on disk for streaming.
+    pub fn open(file_path: String) -> Result<OggDecoder, AssetLoadError> {
+        let file = File::open(&file_path).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => AssetLoadError::new_file_not_found(file_path.clone()),
+            _ => AssetLoadError::new_invalid_file(file_path.clone(), err.to_string()),
+        })?;
+
+        OggDecoder::new(BufReader::new(file))
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path, err.to_string()))
+    }
+}
+
+impl AssetLoader for OggLoader {
+    type Asset = AudioClip;
+
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Self::Asset, AssetLoadError>
+    where
+        Self: Sized,
+    {
+        let mut decoder = OggDecoder::new(Cursor::new(context.read(&file_path)?))
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path.clone(), err.to_string()))?;
+        decode_to_end(&mut decoder)
+            .map_err(|message| AssetLoadError::new_invalid_file(file_path, message))
+    }
+
+    fn identifiers() -> &'static [&'static str] {
+        &["ogg"]
+    }
+}
@@ -0,0 +1,273 @@
+use std::cell::RefCell;
+
+use crate::{AssetLoadError, AssetLoader, AssetSettings, LoadContext};
+
+/// Includes nested deeper than this are assumed to be recursive.
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+    Geometry,
+    TessellationControl,
+    TessellationEvaluation,
+    Task,
+    Mesh,
+}
+
+impl ShaderStage {
+    /// The stage of a file extension, `None` for `.glsl` files which name their stage in their
+    /// settings or with `#pragma shader_stage(...)`.
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "vert" => Some(Self::Vertex),
+            "frag" => Some(Self::Fragment),
+            "comp" => Some(Self::Compute),
+            "geom" => Some(Self::Geometry),
+            "tesc" => Some(Self::TessellationControl),
+            "tese" => Some(Self::TessellationEvaluation),
+            "task" => Some(Self::Task),
+            "mesh" => Some(Self::Mesh),
+            _ => None,
+        }
+    }
+
+    /// The stage of a `stage` setting or `#pragma shader_stage(...)` name.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "vertex" => Some(Self::Vertex),
+            "fragment" => Some(Self::Fragment),
+            "compute" => Some(Self::Compute),
+            "geometry" => Some(Self::Geometry),
+            "tesscontrol" | "tess_control" => Some(Self::TessellationControl),
+            "tesseval" | "tess_evaluation" => Some(Self::TessellationEvaluation),
+            "task" => Some(Self::Task),
+            "mesh" => Some(Self::Mesh),
+            _ => None,
+        }
+    }
+
+    fn shader_kind(self) -> shaderc::ShaderKind {
+        match self {
+            Self::Vertex => shaderc::ShaderKind::Vertex,
+            Self::Fragment => shaderc::ShaderKind::Fragment,
+            Self::Compute => shaderc::ShaderKind::Compute,
+            Self::Geometry => shaderc::ShaderKind::Geometry,
+            Self::TessellationControl => shaderc::ShaderKind::TessControl,
+            Self::TessellationEvaluation => shaderc::ShaderKind::TessEvaluation,
+            Self::Task => shaderc::ShaderKind::Task,
+            Self::Mesh => shaderc::ShaderKind::Mesh,
+        }
+    }
+}
+
+/// A shader compiled to SPIR-V by the [`ShaderLoader`].
+pub struct SpirvShader {
+    pub code: Vec<u32>,
+    pub stage: ShaderStage,
+    pub entry_point: String,
+    /// The files pulled in through `#include`, relative to the asset root.
+    pub includes: Vec<String>,
+}
+
+/// Compiles GLSL shaders to SPIR-V with shaderc.
+///
+/// The stage is taken from the extension, `.glsl` files name it with a `stage` setting or a
+/// `#pragma shader_stage(...)` line. `#include "file"` resolves relative to the including file
+/// and `#include <file>` relative to the asset root, both are read through the asset source.
+///
+/// The compile options are read from the settings of the shader:
+/// - `entry_point`, `main` by default.
+/// - `target_env`, one of `vulkan1.0` to `vulkan1.3`, `vulkan1.3` by default.
+/// - `optimization`, one of `zero`, `size` or `performance`, `performance` by default in release
+///   builds and `zero` in debug builds.
+/// - `debug_info`, whether to emit debug info, the default of `optimization` being `zero`.
+/// - `define.<NAME> = <value>` defines a macro, an empty value defines it without a value.
+pub struct ShaderLoader {}
+
+impl ShaderLoader {
+    fn stage(
+        file_path: &str,
+        source: &str,
+        settings: &AssetSettings,
+    ) -> Result<ShaderStage, String> {
+        let extension = file_path.rsplit('.').next().unwrap_or_default();
+        if let Some(stage) = ShaderStage::from_extension(extension) {
+            return Ok(stage);
+        }
+
+        let name = match settings.get("stage") {
+            Some(name) => name,
+            None => source
+                .lines()
+                .find_map(|line| {
+                    line.trim()
+                        .strip_prefix("#pragma shader_stage(")?
+                        .strip_suffix(')')
+                })
+                .ok_or_else(|| {
+                    "No stage, set one with a `stage` setting or `#pragma shader_stage(...)`"
+                        .to_string()
+                })?,
+        };
+
+        ShaderStage::from_name(name.trim()).ok_or_else(|| format!("Unknown shader stage {}", name))
+    }
+
+    fn target_env(settings: &AssetSettings) -> Result<shaderc::EnvVersion, String> {
+        match settings.get("target_env").unwrap_or("vulkan1.3") {
+            "vulkan1.0" => Ok(shaderc::EnvVersion::Vulkan1_0),
+            "vulkan1.1" => Ok(shaderc::EnvVersion::Vulkan1_1),
+            "vulkan1.2" => Ok(shaderc::EnvVersion::Vulkan1_2),
+            "vulkan1.3" => Ok(shaderc::EnvVersion::Vulkan1_3),
+            target_env => Err(format!("Unknown target env {}", target_env)),
+        }
+    }
+
+    fn optimization_level(settings: &AssetSettings) -> Result<shaderc::OptimizationLevel, String> {
+        let default = if cfg!(debug_assertions) {
+            "zero"
+        } else {
+            "performance"
+        };
+        match settings.get("optimization").unwrap_or(default) {
+            "zero" => Ok(shaderc::OptimizationLevel::Zero),
+            "size" => Ok(shaderc::OptimizationLevel::Size),
+            "performance" => Ok(shaderc::OptimizationLevel::Performance),
+            optimization => Err(format!("Unknown optimization level {}", optimization)),
+        }
+    }
+}
+
+/// Joins an include path onto the directory of the including file, resolving `.` and `..`
+/// since not every asset source understands them.
+fn resolve_include_path(directory: &str, requested: &str) -> String {
+    let path = if requested.starts_with('/') || directory.is_empty() {
+        requested.to_string()
+    } else {
+        format!("{}/{}", directory, requested)
+    };
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+
+    let resolved = components.join("/");
+    if path.starts_with('/') {
+        format!("/{}", resolved)
+    } else {
+        resolved
+    }
+}
+
+impl AssetLoader for ShaderLoader {
+    type Asset = SpirvShader;
+
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Self::Asset, AssetLoadError>
+    where
+        Self: Sized,
+    {
+        let source = String::from_utf8(context.read(&file_path)?)
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path.clone(), err.to_string()))?;
+        let invalid =
+            |message: String| AssetLoadError::new_invalid_file(file_path.clone(), message);
+
+        let settings = context.settings();
+        let stage = Self::stage(&file_path, &source, settings).map_err(invalid)?;
+        let entry_point = settings.get("entry_point").unwrap_or("main").to_string();
+        let target_env = Self::target_env(settings).map_err(invalid)?;
+        let optimization_level = Self::optimization_level(settings).map_err(invalid)?;
+        let debug_info = settings.get_parsed("debug_info").unwrap_or(matches!(
+            optimization_level,
+            shaderc::OptimizationLevel::Zero
+        ));
+
+        let includes = RefCell::new(Vec::new());
+        let compiler = shaderc::Compiler::new()
+            .ok_or_else(|| invalid("Failed to create the shader compiler".to_string()))?;
+        let mut options = shaderc::CompileOptions::new()
+            .ok_or_else(|| invalid("Failed to create the compile options".to_string()))?;
+        options.set_target_env(shaderc::TargetEnv::Vulkan, target_env as u32);
+        options.set_optimization_level(optimization_level);
+        if debug_info {
+            options.generate_debug_info();
+        }
+        for (key, value) in settings.iter() {
+            if let Some(name) = key.strip_prefix("define.") {
+                options.add_macro_definition(name, Some(value).filter(|value| !value.is_empty()));
+            }
+        }
+
+        let context = &*context;
+        options.set_include_callback(|requested, include_type, requesting, depth| {
+            if depth > MAX_INCLUDE_DEPTH {
+                return Err(format!(
+                    "Includes are nested deeper than {}, {} may include itself",
+                    MAX_INCLUDE_DEPTH, requested
+                ));
+            }
+
+            let resolved_name = match include_type {
+                shaderc::IncludeType::Relative => {
+                    let directory = requesting.rsplit_once('/').map_or("", |(dir, _)| dir);
+                    resolve_include_path(directory, requested)
+                }
+                shaderc::IncludeType::Standard => resolve_include_path("", requested),
+            };
+            let content = context
+                .read(&resolved_name)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| String::from_utf8(bytes).map_err(|err| err.to_string()))?;
+
+            includes.borrow_mut().push(resolved_name.clone());
+            Ok(shaderc::ResolvedInclude {
+                resolved_name,
+                content,
+            })
+        });
+
+        let binary_result = compiler
+            .compile_into_spirv(
+                &source,
+                stage.shader_kind(),
+                &file_path,
+                &entry_point,
+                Some(&options),
+            )
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path.clone(), err.to_string()))?;
+        drop(options);
+
+        Ok(SpirvShader {
+            code: binary_result.as_binary().to_vec(),
+            stage,
+            entry_point,
+            includes: includes.into_inner(),
+        })
+    }
+
+    fn identifiers() -> &'static [&'static str] {
+        &[
+            "glsl", "vert", "frag", "comp", "geom", "tesc", "tese", "task", "mesh",
+        ]
+    }
+}
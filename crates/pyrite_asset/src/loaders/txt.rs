@@ -1,4 +1,4 @@
-use crate::{AssetLoadError, AssetLoader};
+use crate::{AssetLoadError, AssetLoader, LoadContext};
 
 pub struct TxtLoader {}
 
@@ -12,15 +12,16 @@ impl AssetLoader for TxtLoader {
         Self {}
     }
 
-    fn load(&self, file_path: String) -> Result<Self::Asset, AssetLoadError>
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Self::Asset, AssetLoadError>
     where
         Self: Sized,
     {
-        Ok(String::from_utf8(
-            std::fs::read(file_path.clone())
-                .map_err(|_| AssetLoadError::new_file_not_found(file_path.clone()))?,
-        )
-        .map_err(|err| AssetLoadError::new_invalid_file(file_path.clone(), err.to_string()))?)
+        String::from_utf8(context.read(&file_path)?)
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path, err.to_string()))
     }
 
     fn identifiers() -> &'static [&'static str] {
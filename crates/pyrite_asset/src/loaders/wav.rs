@@ -0,0 +1,104 @@
+use std::{
+    fs::File,
+    io::{BufReader, Cursor},
+};
+
+use crate::{AssetLoadError, AssetLoader, LoadContext};
+
+use super::audio::{decode_to_end, AudioClip, AudioDecoder, AudioStream};
+
+/// Decodes 8 to 32 bit integer and 32 bit float PCM WAV files.
+pub struct WavDecoder {
+    reader: hound::WavReader<Box<dyn AudioStream>>,
+    spec: hound::WavSpec,
+}
+
+impl WavDecoder {
+    pub fn new(stream: impl AudioStream + 'static) -> Result<Self, hound::Error> {
+        let reader = hound::WavReader::new(Box::new(stream) as Box<dyn AudioStream>)?;
+        let spec = reader.spec();
+
+        Ok(Self { reader, spec })
+    }
+
+    fn next_sample(&mut self) -> Option<Result<f32, hound::Error>> {
+        match self.spec.sample_format {
+            hound::SampleFormat::Float => self.reader.samples::<f32>().next(),
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (self.spec.bits_per_sample - 1)) as f32;
+                self.reader
+                    .samples::<i32>()
+                    .next()
+                    .map(|sample| sample.map(|sample| sample as f32 * scale))
+            }
+        }
+    }
+}
+
+impl AudioDecoder for WavDecoder {
+    fn sample_rate(&self) -> u32 {
+        self.spec.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.spec.channels
+    }
+
+    fn decode(&mut self, samples: &mut Vec<f32>, max_frames: usize) -> Result<usize, String> {
+        let channels = self.spec.channels as usize;
+        let start = samples.len();
+        while samples.len() - start < max_frames * channels {
+            match self.next_sample() {
+                Some(sample) => samples.push(sample.map_err(|err| err.to_string())?),
+                None => break,
+            }
+        }
+
+        Ok((samples.len() - start) / channels)
+    }
+}
+
+/// Loads WAV files into [`AudioClip`]s.
+pub struct WavLoader {}
+
+impl WavLoader {
+    /// Opens a WAV file on disk for streaming.
+    pub fn open(file_path: String) -> Result<WavDecoder, AssetLoadError> {
+        let file = File::open(&file_path).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => AssetLoadError::new_file_not_found(file_path.clone()),
+            _ => AssetLoadError::new_invalid_file(file_path.clone(), err.to_string()),
+        })?;
+
+        WavDecoder::new(BufReader::new(file))
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path, err.to_string()))
+    }
+}
+
+impl AssetLoader for WavLoader {
+    type Asset = AudioClip;
+
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn load(
+        &self,
+        file_path: String,
+        context: &mut LoadContext,
+    ) -> Result<Self::Asset, AssetLoadError>
+    where
+        Self: Sized,
+    {
+        let mut decoder = WavDecoder::new(Cursor::new(context.read(&file_path)?))
+            .map_err(|err| AssetLoadError::new_invalid_file(file_path.clone(), err.to_string()))?;
+        decode_to_end(&mut decoder)
+            .map_err(|message| AssetLoadError::new_invalid_file(file_path, message))
+    }
+
+    fn identifiers() -> &'static [&'static str] {
+        &["wav"]
+    }
+}
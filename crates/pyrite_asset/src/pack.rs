@@ -0,0 +1,375 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    source::{normalize_path, not_found},
+    AssetSource,
+};
+
+const PACK_MAGIC: &[u8; 8] = b"PYRPACK\0";
+const PACK_VERSION: u32 = 1;
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZSTD: u8 = 1;
+
+/// How the files of an asset pack are compressed by the [`AssetPackBuilder`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PackCompression {
+    #[default]
+    None,
+    /// Zstd with the compression level, 1 to 22. Files which don't get smaller are stored
+    /// uncompressed.
+    Zstd { level: i32 },
+}
+
+/// Builds an asset pack which is read by a [`PackSource`].
+///
+/// A pack starts with the magic `PYRPACK\0`, the format version and the entry count as little
+/// endian u32s. The index follows with an entry per file holding the path length as a u32, the
+/// path, the compression as a u8, the offset, stored size and uncompressed size of the file as
+/// u64s and the blake3 hash of the uncompressed file. The stored files follow the index, offsets
+/// are relative to the end of the index.
+#[derive(Default)]
+pub struct AssetPackBuilder {
+    files: Vec<(String, Vec<u8>)>,
+    compression: PackCompression,
+}
+
+impl AssetPackBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_compression(mut self, compression: PackCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Adds a file, replacing any file already added with the same path.
+    pub fn add_file(&mut self, path: &str, bytes: impl Into<Vec<u8>>) {
+        let path = normalize_path(path);
+        self.files.retain(|(file_path, _)| *file_path != path);
+        self.files.push((path, bytes.into()));
+    }
+
+    /// Adds every file in the directory tree, with paths relative to `root`.
+    pub fn add_directory(&mut self, root: impl AsRef<Path>) -> io::Result<()> {
+        self.add_directory_recursive(root.as_ref(), "")
+    }
+
+    fn add_directory_recursive(&mut self, directory: &Path, prefix: &str) -> io::Result<()> {
+        // Sorted so the same tree always produces the same pack.
+        let mut entries = std::fs::read_dir(directory)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let name = entry.file_name();
+            let name = name.to_str().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{:?} isn't a valid utf-8 path", entry.path()),
+                )
+            })?;
+            let path = format!("{}{}", prefix, name);
+
+            if entry.file_type()?.is_dir() {
+                self.add_directory_recursive(&entry.path(), &format!("{}/", path))?;
+            } else {
+                self.add_file(&path, std::fs::read(entry.path())?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compresses and hashes the files into a pack.
+    pub fn build(&self) -> io::Result<Vec<u8>> {
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+
+        for (path, bytes) in &self.files {
+            let compressed = match self.compression {
+                PackCompression::None => None,
+                PackCompression::Zstd { level } => Some(zstd::bulk::compress(bytes, level)?)
+                    .filter(|compressed| compressed.len() < bytes.len()),
+            };
+            let (compression, stored) = match &compressed {
+                Some(compressed) => (COMPRESSION_ZSTD, compressed.as_slice()),
+                None => (COMPRESSION_NONE, bytes.as_slice()),
+            };
+
+            index.extend_from_slice(&(path.len() as u32).to_le_bytes());
+            index.extend_from_slice(path.as_bytes());
+            index.push(compression);
+            index.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            index.extend_from_slice(&(stored.len() as u64).to_le_bytes());
+            index.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            index.extend_from_slice(blake3::hash(bytes).as_bytes());
+            data.extend_from_slice(stored);
+        }
+
+        let mut pack = PACK_MAGIC.to_vec();
+        pack.extend_from_slice(&PACK_VERSION.to_le_bytes());
+        pack.extend_from_slice(&(self.files.len() as u32).to_le_bytes());
+        pack.append(&mut index);
+        pack.append(&mut data);
+        Ok(pack)
+    }
+
+    /// Builds the pack and writes it to a file.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.build()?)
+    }
+}
+
+struct PackEntry {
+    compression: u8,
+    /// The range of the stored file in the pack.
+    start: usize,
+    end: usize,
+    size: usize,
+    hash: [u8; 32],
+}
+
+/// Reads files from an asset pack built by an [`AssetPackBuilder`], used for shipping builds.
+///
+/// The hash of every file is checked when it's read unless verification is turned off with
+/// [`PackSource::with_verification`], a file which doesn't match fails to load.
+pub struct PackSource {
+    bytes: Arc<[u8]>,
+    entries: HashMap<String, PackEntry>,
+    verify: bool,
+}
+
+impl PackSource {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: impl Into<Arc<[u8]>>) -> io::Result<Self> {
+        let bytes = bytes.into();
+        let invalid = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid asset pack: {}", message),
+            )
+        };
+
+        let mut offset = 0;
+        let mut take = |size: usize| -> io::Result<&[u8]> {
+            let taken = bytes
+                .get(offset..offset + size)
+                .ok_or_else(|| invalid("Unexpected end of file"))?;
+            offset += size;
+            Ok(taken)
+        };
+        let read_u32 = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap()) as usize;
+        let read_u64 = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().unwrap()) as usize;
+
+        if take(PACK_MAGIC.len())? != PACK_MAGIC {
+            return Err(invalid("Not an asset pack"));
+        }
+        let version = read_u32(take(4)?);
+        if version != PACK_VERSION as usize {
+            return Err(invalid(&format!("Unsupported version {}", version)));
+        }
+
+        let entry_count = read_u32(take(4)?);
+        let mut entries = Vec::with_capacity(entry_count);
+        for _ in 0..entry_count {
+            let path_len = read_u32(take(4)?);
+            let path = std::str::from_utf8(take(path_len)?)
+                .map_err(|_| invalid("Path isn't valid utf-8"))?
+                .to_string();
+            let compression = take(1)?[0];
+            let file_offset = read_u64(take(8)?);
+            let stored_size = read_u64(take(8)?);
+            let size = read_u64(take(8)?);
+            let hash = take(32)?.try_into().unwrap();
+
+            if compression != COMPRESSION_NONE && compression != COMPRESSION_ZSTD {
+                return Err(invalid(&format!("Unknown compression {}", compression)));
+            }
+            entries.push((path, compression, file_offset, stored_size, size, hash));
+        }
+
+        let data_start = offset;
+        let entries = entries
+            .into_iter()
+            .map(
+                |(path, compression, file_offset, stored_size, size, hash)| {
+                    let start = data_start + file_offset;
+                    let end = start + stored_size;
+                    if end > bytes.len() {
+                        return Err(invalid(&format!("{} is out of bounds", path)));
+                    }
+
+                    let entry = PackEntry {
+                        compression,
+                        start,
+                        end,
+                        size,
+                        hash,
+                    };
+                    Ok((path, entry))
+                },
+            )
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            bytes,
+            entries,
+            verify: true,
+        })
+    }
+
+    /// Whether the hash of every file is checked when it's read, on by default.
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(&normalize_path(path))
+    }
+
+    /// The paths of the files in the pack, in no particular order.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|path| path.as_str())
+    }
+
+    /// Reads and checks every file in the pack, returning the first one which is corrupted.
+    pub fn verify_all(&self) -> io::Result<()> {
+        for (path, entry) in &self.entries {
+            let bytes = self.decompress(path, entry)?;
+            Self::verify_hash(path, entry, &bytes)?;
+        }
+
+        Ok(())
+    }
+
+    fn decompress(&self, path: &str, entry: &PackEntry) -> io::Result<Vec<u8>> {
+        let stored = &self.bytes[entry.start..entry.end];
+        match entry.compression {
+            COMPRESSION_ZSTD => {
+                let mut decoder = ruzstd::StreamingDecoder::new(stored).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Failed to decompress {}: {:?}", path, err),
+                    )
+                })?;
+                let mut bytes = Vec::with_capacity(entry.size);
+                decoder.read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+            _ => Ok(stored.to_vec()),
+        }
+    }
+
+    fn verify_hash(path: &str, entry: &PackEntry, bytes: &[u8]) -> io::Result<()> {
+        if bytes.len() != entry.size || blake3::hash(bytes).as_bytes() != &entry.hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is corrupted, its hash doesn't match the pack", path),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl AssetSource for PackSource {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let entry = self
+            .entries
+            .get(&normalize_path(path))
+            .ok_or_else(|| not_found(path))?;
+
+        let bytes = self.decompress(path, entry)?;
+        if self.verify {
+            Self::verify_hash(path, entry, &bytes)?;
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_pack(compression: PackCompression) -> Vec<u8> {
+        let mut builder = AssetPackBuilder::new().with_compression(compression);
+        builder.add_file("./textures/brick.png", b"brick".repeat(64));
+        builder.add_file("shaders\\sprite.wgsl", b"sprite shader");
+        builder.add_file("empty.txt", Vec::new());
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn packed_files_read_back() {
+        for compression in [PackCompression::None, PackCompression::Zstd { level: 3 }] {
+            let source = PackSource::from_bytes(build_pack(compression)).unwrap();
+
+            let mut paths = source.paths().collect::<Vec<_>>();
+            paths.sort();
+            assert_eq!(
+                paths,
+                ["empty.txt", "shaders/sprite.wgsl", "textures/brick.png"]
+            );
+            assert_eq!(
+                source.read("textures/brick.png").unwrap(),
+                b"brick".repeat(64)
+            );
+            assert_eq!(
+                source.read("shaders/sprite.wgsl").unwrap(),
+                b"sprite shader"
+            );
+            assert_eq!(source.read("empty.txt").unwrap(), b"");
+            assert_eq!(
+                source.read("missing.txt").unwrap_err().kind(),
+                io::ErrorKind::NotFound
+            );
+            source.verify_all().unwrap();
+        }
+    }
+
+    #[test]
+    fn corrupted_files_fail_the_hash_check() {
+        let mut pack = build_pack(PackCompression::Zstd { level: 3 });
+        // Too small to shrink, so it's stored uncompressed.
+        let start = pack
+            .windows(b"sprite shader".len())
+            .position(|window| window == b"sprite shader")
+            .unwrap();
+        pack[start] = b'S';
+
+        let source = PackSource::from_bytes(pack).unwrap();
+        assert_eq!(
+            source.read("shaders/sprite.wgsl").unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert!(source.verify_all().is_err());
+        // The other files are still intact.
+        assert_eq!(
+            source.read("textures/brick.png").unwrap(),
+            b"brick".repeat(64)
+        );
+
+        let source = source.with_verification(false);
+        assert_eq!(
+            source.read("shaders/sprite.wgsl").unwrap(),
+            b"Sprite shader"
+        );
+    }
+
+    #[test]
+    fn invalid_packs_are_rejected() {
+        let pack = build_pack(PackCompression::None);
+        assert!(PackSource::from_bytes(&b"NOTAPACK"[..]).is_err());
+        assert!(PackSource::from_bytes(&pack[..pack.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, HashSet, VecDeque},
+    str::FromStr,
+};
+
+use crate::{AssetLoadError, AssetSource};
+
+/// The second phase of loading an asset, turning the data imported by an
+/// [`crate::AssetLoader`] or produced by another processor into another type.
+///
+/// When an asset is loaded as a type its loader doesn't produce, the shortest chain of processors
+/// from the loader's asset type to the requested type is run after the loader, e.g. an image
+/// loader followed by a compression processor followed by a mip generation processor.
+pub trait AssetProcessor: Send + Sync + 'static {
+    type Input: 'static;
+    type Output: Send + Sync + 'static;
+
+    fn process(&self, input: Self::Input, settings: &AssetSettings)
+        -> Result<Self::Output, String>;
+}
+
+/// The per asset settings read from the `<file>.settings` file next to the asset, which are
+/// passed to its loader and processors.
+///
+/// The file holds one `key = value` pair per line, empty lines and lines starting with `#` are
+/// ignored. Missing files result in empty settings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AssetSettings {
+    values: HashMap<String, String>,
+}
+
+impl AssetSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The path of the settings file of an asset.
+    pub fn file_path(asset_file_path: &str) -> String {
+        format!("{}.settings", asset_file_path)
+    }
+
+    /// Reads the settings file of an asset from the source, if it exists.
+    pub fn load(source: &dyn AssetSource, asset_file_path: &str) -> Result<Self, AssetLoadError> {
+        let file_path = Self::file_path(asset_file_path);
+        match source.read(&file_path) {
+            Ok(bytes) => String::from_utf8(bytes)
+                .map_err(|err| err.to_string())
+                .and_then(|contents| Self::parse(&contents))
+                .map_err(|message| AssetLoadError::new_invalid_file(file_path, message)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(err) => Err(AssetLoadError::new_invalid_file(file_path, err.to_string())),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Expected `key = value` on line {}", line_number + 1))?;
+            values.insert(key.trim().to_string(), value.trim().to_string());
+        }
+
+        Ok(Self { values })
+    }
+
+    pub fn with(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.values.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Every setting, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// The value parsed as `T`, `None` if the key is missing or the value doesn't parse.
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Option<T> {
+        let value = self.get(key)?;
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                log::warn!("Ignoring invalid asset setting {} = {}", key, value);
+                None
+            }
+        }
+    }
+}
+
+pub(crate) trait ErasedAssetProcessor: Send + Sync {
+    fn output_type(&self) -> TypeId;
+    fn process(
+        &self,
+        input: Box<dyn Any>,
+        settings: &AssetSettings,
+    ) -> Result<Box<dyn Any>, String>;
+}
+
+pub(crate) struct AssetProcessorWrapper<T: AssetProcessor>(pub T);
+
+impl<T: AssetProcessor> ErasedAssetProcessor for AssetProcessorWrapper<T> {
+    fn output_type(&self) -> TypeId {
+        TypeId::of::<T::Output>()
+    }
+
+    fn process(
+        &self,
+        input: Box<dyn Any>,
+        settings: &AssetSettings,
+    ) -> Result<Box<dyn Any>, String> {
+        let input = input
+            .downcast::<T::Input>()
+            .expect("Failed to downcast asset to processor input type");
+        Ok(Box::new(self.0.process(*input, settings)?))
+    }
+}
+
+/// The registered processors, keyed by their input type.
+#[derive(Default)]
+pub(crate) struct AssetProcessors {
+    processors: HashMap<TypeId, Vec<Box<dyn ErasedAssetProcessor>>>,
+}
+
+impl AssetProcessors {
+    pub fn add<T: AssetProcessor>(&mut self, processor: T) {
+        self.processors
+            .entry(TypeId::of::<T::Input>())
+            .or_default()
+            .push(Box::new(AssetProcessorWrapper(processor)));
+    }
+
+    /// The shortest chain of processors turning `from` into `to`, empty if the types are the
+    /// same and `None` if there is no chain.
+    pub fn find_chain(&self, from: TypeId, to: TypeId) -> Option<Vec<&dyn ErasedAssetProcessor>> {
+        let mut previous: HashMap<TypeId, (TypeId, &dyn ErasedAssetProcessor)> = HashMap::new();
+        let mut visited = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut chain = Vec::new();
+                let mut current = to;
+                while let Some((input, processor)) = previous.get(&current) {
+                    chain.push(*processor);
+                    current = *input;
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+
+            for processor in self.processors.get(&current).into_iter().flatten() {
+                let output = processor.output_type();
+                if visited.insert(output) {
+                    previous.insert(output, (current, processor.as_ref()));
+                    queue.push_back(output);
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,79 @@
+use std::{borrow::Cow, collections::HashMap, io, path::PathBuf};
+
+use crate::watcher::absolute_path;
+
+/// Where the [`crate::Assets`] read files from, set with [`crate::Assets::with_source`].
+///
+/// Paths are always relative to the source and use `/` as the separator.
+pub trait AssetSource: Send + Sync + 'static {
+    /// Reads the whole file.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+
+    /// The absolute path of the file on disk, `None` for sources which don't read loose files.
+    /// Only files with a native path are reloaded by [`crate::Assets::watch`].
+    fn native_path(&self, _path: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+pub(crate) fn not_found(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("No file at {}", path))
+}
+
+/// Normalizes the separators and leading `./` so equal paths have equal keys.
+pub(crate) fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.strip_prefix("./").unwrap_or(&path).to_string()
+}
+
+/// Reads loose files from a directory, used for development builds.
+pub struct DirectorySource {
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl AssetSource for DirectorySource {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        std::fs::read(self.root.join(path))
+    }
+
+    fn native_path(&self, path: &str) -> Option<PathBuf> {
+        Some(absolute_path(&self.root.join(path)))
+    }
+}
+
+/// Serves files from memory, e.g. files embedded in the executable with `include_bytes!` or
+/// generated at runtime.
+#[derive(Default)]
+pub struct MemorySource {
+    files: HashMap<String, Cow<'static, [u8]>>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, path: &str, bytes: impl Into<Cow<'static, [u8]>>) -> Self {
+        self.insert(path, bytes);
+        self
+    }
+
+    pub fn insert(&mut self, path: &str, bytes: impl Into<Cow<'static, [u8]>>) {
+        self.files.insert(normalize_path(path), bytes.into());
+    }
+}
+
+impl AssetSource for MemorySource {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.files
+            .get(&normalize_path(path))
+            .map(|bytes| bytes.to_vec())
+            .ok_or_else(|| not_found(path))
+    }
+}
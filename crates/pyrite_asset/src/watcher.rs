@@ -0,0 +1,85 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use notify::Watcher;
+use parking_lot::Mutex;
+
+/// A change to a file in a directory watched with [`crate::Assets::watch`], sent as an app event
+/// after the [`crate::Assets`] have updated.
+///
+/// The paths are absolute. Loaded assets whose file was modified are reloaded before the event
+/// is sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssetEvent {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+}
+
+/// Watches directories for changes, collecting the events until they're drained by the
+/// [`crate::Assets`].
+pub(crate) struct AssetWatcher {
+    /// Locked since the watcher isn't Sync on every platform.
+    watcher: Mutex<notify::RecommendedWatcher>,
+    events: Arc<Mutex<Vec<AssetEvent>>>,
+}
+
+impl AssetWatcher {
+    pub fn new() -> Self {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let watcher_events = events.clone();
+        let watcher = notify::recommended_watcher(
+            move |res: Result<notify::Event, notify::Error>| match res {
+                Ok(event) => {
+                    let to_asset_event: fn(PathBuf) -> AssetEvent = match event.kind {
+                        notify::EventKind::Create(_) => AssetEvent::Created,
+                        notify::EventKind::Modify(_) => AssetEvent::Modified,
+                        notify::EventKind::Remove(_) => AssetEvent::Removed,
+                        _ => return,
+                    };
+                    watcher_events
+                        .lock()
+                        .extend(event.paths.into_iter().map(to_asset_event));
+                }
+                Err(e) => log::error!("Asset watch error: {:?}", e),
+            },
+        )
+        .expect("Failed to create file watcher");
+
+        Self {
+            watcher: Mutex::new(watcher),
+            events,
+        }
+    }
+
+    pub fn watch(&mut self, directory: &Path) {
+        self.watcher
+            .lock()
+            .watch(directory, notify::RecursiveMode::Recursive)
+            .unwrap_or_else(|err| panic!("Failed to watch directory {:?}: {}", directory, err));
+    }
+
+    /// The events since the last drain, without duplicates since editors often write a file in
+    /// several steps.
+    pub fn drain_events(&self) -> Vec<AssetEvent> {
+        let mut events = Vec::new();
+        for event in self.events.lock().drain(..) {
+            if !events.contains(&event) {
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+/// The absolute path used to match the path of an asset file to the paths of watcher events.
+pub(crate) fn absolute_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| {
+        std::env::current_dir()
+            .map(|current_dir| current_dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    })
+}
@@ -0,0 +1,261 @@
+use std::{
+    sync::{mpsc, Arc},
+    thread::JoinHandle,
+};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use parking_lot::Mutex;
+use pyrite_app::{
+    plugin::Plugin,
+    resource::{Res, ResMut, Resource},
+    stage::POST_UPDATE_STAGE,
+    AppBuilder,
+};
+use pyrite_asset::{
+    loaders::{audio::AudioClip, ogg::OggLoader, wav::WavLoader},
+    Assets, Handle,
+};
+use pyrite_math::Vec3;
+
+use crate::{
+    mixer::{Mixer, PlaybackSettings, SoundId},
+    spatial::{AudioListener, Emitter},
+};
+
+/// Adds the [`Audio`] and [`AudioListener`] resources, and the WAV and Ogg Vorbis loaders if the
+/// [`Assets`] resource was added first.
+///
+/// The listener is copied to the mixer after the update stage, so gameplay systems can move it
+/// and the emitters of spatial sounds during the update.
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        if app_builder.contains_resource::<Assets>() {
+            let mut assets = app_builder.get_resource_mut::<Assets>();
+            assets.add_loader::<WavLoader>();
+            assets.add_loader::<OggLoader>();
+        }
+        app_builder
+            .add_resource(Audio::new())
+            .add_resource(AudioListener::default())
+            .add_system_to_stage(sync_audio_listener, POST_UPDATE_STAGE);
+    }
+}
+
+fn sync_audio_listener(mut audio: ResMut<Audio>, listener: Res<AudioListener>) {
+    audio.set_listener(*listener);
+}
+
+/// Plays [`AudioClip`]s on the default output device.
+///
+/// The output stream lives on its own thread, whose callback mixes the playing sounds. If there
+/// is no output device the sounds are still tracked but never advance, see
+/// [`Audio::is_available`].
+#[derive(Resource)]
+pub struct Audio {
+    mixer: Arc<Mutex<Mixer>>,
+    is_available: bool,
+    shutdown_sender: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        let mixer = Arc::new(Mutex::new(Mixer::new(48000, 2)));
+        let (shutdown_sender, shutdown_receiver) = mpsc::channel::<()>();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+
+        let thread_mixer = mixer.clone();
+        let thread = std::thread::Builder::new()
+            .name("audio".to_string())
+            .spawn(move || {
+                let stream = match open_output_stream(thread_mixer) {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        log::warn!("No audio output, sounds won't be heard: {}", error);
+                        let _ = ready_sender.send(false);
+                        return;
+                    }
+                };
+                let _ = ready_sender.send(true);
+
+                // The stream isn't Send on every platform, so it's kept alive on this thread
+                // until the audio is dropped.
+                let _ = shutdown_receiver.recv();
+                drop(stream);
+            })
+            .expect("Failed to spawn the audio thread");
+        let is_available = ready_receiver.recv().unwrap_or(false);
+
+        Self {
+            mixer,
+            is_available,
+            shutdown_sender: Some(shutdown_sender),
+            thread: Some(thread),
+        }
+    }
+
+    /// Whether an output stream was opened.
+    pub fn is_available(&self) -> bool {
+        self.is_available
+    }
+
+    /// Starts playing the clip, if it's still loading the sound starts once it has loaded.
+    pub fn play(&mut self, clip: &Handle<AudioClip>, settings: PlaybackSettings) -> SoundId {
+        self.mixer.lock().play(clip.clone(), settings)
+    }
+
+    /// Starts playing the clip at the emitter, see [`Audio::play`].
+    pub fn play_spatial(
+        &mut self,
+        clip: &Handle<AudioClip>,
+        settings: PlaybackSettings,
+        emitter: Emitter,
+    ) -> SoundId {
+        let mut mixer = self.mixer.lock();
+        let id = mixer.play(clip.clone(), settings);
+        if let Some(sound) = mixer.sound_mut(id) {
+            sound.emitter = Some(emitter);
+        }
+        id
+    }
+
+    /// Makes the sound spatial, or not spatial if `emitter` is `None`.
+    pub fn set_emitter(&mut self, sound: SoundId, emitter: Option<Emitter>) {
+        if let Some(sound) = self.mixer.lock().sound_mut(sound) {
+            sound.emitter = emitter;
+        }
+    }
+
+    /// Moves a spatial sound, does nothing if the sound isn't spatial.
+    pub fn set_emitter_position(&mut self, sound: SoundId, position: Vec3) {
+        if let Some(emitter) = self
+            .mixer
+            .lock()
+            .sound_mut(sound)
+            .and_then(|sound| sound.emitter.as_mut())
+        {
+            emitter.position = position;
+        }
+    }
+
+    /// Sets the listener spatial sounds are heard from, which the [`AudioPlugin`] does every
+    /// frame from the [`AudioListener`] resource.
+    pub fn set_listener(&mut self, listener: AudioListener) {
+        self.mixer.lock().listener = listener;
+    }
+
+    pub fn pause(&mut self, sound: SoundId) {
+        if let Some(sound) = self.mixer.lock().sound_mut(sound) {
+            sound.paused = true;
+        }
+    }
+
+    pub fn resume(&mut self, sound: SoundId) {
+        if let Some(sound) = self.mixer.lock().sound_mut(sound) {
+            sound.paused = false;
+        }
+    }
+
+    pub fn stop(&mut self, sound: SoundId) {
+        self.mixer.lock().stop(sound);
+    }
+
+    pub fn stop_all(&mut self) {
+        self.mixer.lock().stop_all();
+    }
+
+    /// Whether the sound is playing, false once it was paused, stopped or finished.
+    pub fn is_playing(&self, sound: SoundId) -> bool {
+        self.mixer.lock().is_playing(sound)
+    }
+
+    pub fn set_volume(&mut self, sound: SoundId, volume: f32) {
+        if let Some(sound) = self.mixer.lock().sound_mut(sound) {
+            sound.settings.volume = volume;
+        }
+    }
+
+    pub fn set_pitch(&mut self, sound: SoundId, pitch: f32) {
+        if let Some(sound) = self.mixer.lock().sound_mut(sound) {
+            sound.settings.pitch = pitch;
+        }
+    }
+
+    pub fn set_pan(&mut self, sound: SoundId, pan: f32) {
+        if let Some(sound) = self.mixer.lock().sound_mut(sound) {
+            sound.settings.pan = pan;
+        }
+    }
+
+    /// The gain applied on top of the volume of every sound.
+    pub fn master_volume(&self) -> f32 {
+        self.mixer.lock().master_volume
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.mixer.lock().master_volume = volume;
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the audio thread, which drops the stream.
+        self.shutdown_sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn open_output_stream(mixer: Arc<Mutex<Mixer>>) -> Result<cpal::Stream, String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| "No default output device".to_string())?;
+    let supported_config = device
+        .default_output_config()
+        .map_err(|err| err.to_string())?;
+    let sample_format = supported_config.sample_format();
+    let config: cpal::StreamConfig = supported_config.into();
+    mixer
+        .lock()
+        .set_output(config.sample_rate.0, config.channels);
+
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => build_output_stream::<f32>(&device, &config, mixer),
+        cpal::SampleFormat::I16 => build_output_stream::<i16>(&device, &config, mixer),
+        cpal::SampleFormat::U16 => build_output_stream::<u16>(&device, &config, mixer),
+        sample_format => return Err(format!("Unsupported sample format {:?}", sample_format)),
+    }
+    .map_err(|err| err.to_string())?;
+    stream.play().map_err(|err| err.to_string())?;
+
+    Ok(stream)
+}
+
+fn build_output_stream<T: cpal::SizedSample + cpal::FromSample<f32>>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mixer: Arc<Mutex<Mixer>>,
+) -> Result<cpal::Stream, cpal::BuildStreamError> {
+    let mut mix_buffer = Vec::new();
+    device.build_output_stream(
+        config,
+        move |output: &mut [T], _: &cpal::OutputCallbackInfo| {
+            mix_buffer.resize(output.len(), 0.0);
+            mixer.lock().mix(&mut mix_buffer);
+            for (output, sample) in output.iter_mut().zip(&mix_buffer) {
+                *output = T::from_sample(*sample);
+            }
+        },
+        |error| log::error!("Audio stream error: {}", error),
+        None,
+    )
+}
@@ -0,0 +1,15 @@
+mod audio;
+mod mixer;
+mod spatial;
+
+pub use audio::*;
+pub use mixer::{PlaybackSettings, SoundId};
+pub use spatial::*;
+
+pub mod prelude {
+    pub use crate::{
+        audio::{Audio, AudioPlugin},
+        mixer::{PlaybackSettings, SoundId},
+        spatial::{AudioListener, Emitter},
+    };
+}
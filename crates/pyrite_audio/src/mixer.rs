@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use pyrite_asset::{loaders::audio::AudioClip, Handle};
+
+use crate::spatial::{AudioListener, Emitter};
+
+/// Identifies a sound started by [`crate::Audio::play`].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct SoundId(u64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackSettings {
+    /// The linear gain, 1 plays the clip unchanged.
+    pub volume: f32,
+    /// The playback speed, which shifts the pitch along with it, 1 plays the clip unchanged.
+    pub pitch: f32,
+    /// The stereo balance from -1, only the left channel, to 1, only the right channel.
+    pub pan: f32,
+    /// Whether the sound restarts when it reaches the end instead of stopping.
+    pub looping: bool,
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pitch: 1.0,
+            pan: 0.0,
+            looping: false,
+        }
+    }
+}
+
+pub(crate) struct Sound {
+    clip: Handle<AudioClip>,
+    pub settings: PlaybackSettings,
+    /// The position in the clip in frames, fractional since the pitch resamples the clip.
+    position: f64,
+    pub paused: bool,
+    /// The position of the sound, `None` for sounds which aren't spatial.
+    pub emitter: Option<Emitter>,
+}
+
+impl Sound {
+    /// Reads the left and right sample at the position, linearly interpolating between frames.
+    fn sample(&self, clip: &AudioClip, frames: usize) -> (f32, f32) {
+        let frame = self.position as usize;
+        let next_frame = if frame + 1 < frames {
+            frame + 1
+        } else if self.settings.looping {
+            0
+        } else {
+            frame
+        };
+        let t = self.position.fract() as f32;
+
+        let channels = clip.channels as usize;
+        let read = |channel: usize| {
+            let a = clip.samples[frame * channels + channel];
+            let b = clip.samples[next_frame * channels + channel];
+            a + (b - a) * t
+        };
+        if channels == 1 {
+            let sample = read(0);
+            (sample, sample)
+        } else {
+            (read(0), read(1))
+        }
+    }
+
+    /// Adds the sound to the interleaved output, returns false once the sound finished.
+    #[allow(clippy::too_many_arguments)]
+    fn mix(
+        &mut self,
+        clip: &AudioClip,
+        output: &mut [f32],
+        channels: usize,
+        sample_rate: u32,
+        master_volume: f32,
+        listener: &AudioListener,
+    ) -> bool {
+        let frames = clip.frames();
+        if frames == 0 {
+            return false;
+        }
+
+        let (spatial_gain, spatial_pan) = self
+            .emitter
+            .map_or((1.0, 0.0), |emitter| listener.spatialize(&emitter));
+        let step =
+            self.settings.pitch.max(0.0) as f64 * clip.sample_rate as f64 / sample_rate as f64;
+        let volume = self.settings.volume * master_volume * spatial_gain;
+        let pan = (self.settings.pan + spatial_pan).clamp(-1.0, 1.0);
+        let left_gain = (1.0 - pan).min(1.0) * volume;
+        let right_gain = (1.0 + pan).min(1.0) * volume;
+
+        for output_frame in output.chunks_exact_mut(channels) {
+            if self.position >= frames as f64 {
+                if !self.settings.looping {
+                    return false;
+                }
+                self.position %= frames as f64;
+            }
+
+            let (left, right) = self.sample(clip, frames);
+            match output_frame {
+                [mono] => *mono += (left * left_gain + right * right_gain) * 0.5,
+                [output_left, output_right, ..] => {
+                    *output_left += left * left_gain;
+                    *output_right += right * right_gain;
+                }
+                [] => {}
+            }
+            self.position += step;
+        }
+
+        true
+    }
+}
+
+/// Mixes the playing sounds into the output stream, shared between [`crate::Audio`] and the
+/// stream's callback.
+pub(crate) struct Mixer {
+    sounds: HashMap<SoundId, Sound>,
+    next_id: u64,
+    pub master_volume: f32,
+    pub listener: AudioListener,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sounds: HashMap::new(),
+            next_id: 0,
+            master_volume: 1.0,
+            listener: AudioListener::default(),
+            sample_rate,
+            channels,
+        }
+    }
+
+    pub fn set_output(&mut self, sample_rate: u32, channels: u16) {
+        self.sample_rate = sample_rate;
+        self.channels = channels;
+    }
+
+    pub fn play(&mut self, clip: Handle<AudioClip>, settings: PlaybackSettings) -> SoundId {
+        let id = SoundId(self.next_id);
+        self.next_id += 1;
+        self.sounds.insert(
+            id,
+            Sound {
+                clip,
+                settings,
+                position: 0.0,
+                paused: false,
+                emitter: None,
+            },
+        );
+        id
+    }
+
+    pub fn sound_mut(&mut self, id: SoundId) -> Option<&mut Sound> {
+        self.sounds.get_mut(&id)
+    }
+
+    pub fn stop(&mut self, id: SoundId) {
+        self.sounds.remove(&id);
+    }
+
+    pub fn stop_all(&mut self) {
+        self.sounds.clear();
+    }
+
+    pub fn is_playing(&self, id: SoundId) -> bool {
+        self.sounds.get(&id).is_some_and(|sound| !sound.paused)
+    }
+
+    /// Overwrites the interleaved output with the mix of the playing sounds, removing the sounds
+    /// which finished or whose clip failed to load.
+    ///
+    /// Sounds whose clip is still loading stay at the start until it has loaded.
+    pub fn mix(&mut self, output: &mut [f32]) {
+        output.fill(0.0);
+
+        let channels = self.channels as usize;
+        let sample_rate = self.sample_rate;
+        let master_volume = self.master_volume;
+        let listener = self.listener;
+        self.sounds.retain(|_, sound| {
+            if let Some(error) = sound.clip.get_error() {
+                log::error!("Stopping sound, its clip failed to load: {}", error);
+                return false;
+            }
+            if sound.paused {
+                return true;
+            }
+
+            // The clip is borrowed through its own handle so the sound can be mutated while mixing.
+            let clip = sound.clip.clone();
+            let keep = match clip.get() {
+                Some(clip) => sound.mix(
+                    &clip,
+                    output,
+                    channels,
+                    sample_rate,
+                    master_volume,
+                    &listener,
+                ),
+                None => true,
+            };
+            keep
+        });
+    }
+}
@@ -0,0 +1,72 @@
+use pyrite_app::resource::Resource;
+use pyrite_math::{Quat, Transform, Vec3};
+
+/// Where spatial sounds are heard from, usually following the camera. Copied to the mixer every
+/// frame by the [`crate::AudioPlugin`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct AudioListener {
+    pub position: Vec3,
+    /// The orientation, where -Z is forward and +Y is up like a [`Transform`].
+    pub rotation: Quat,
+}
+
+impl Default for AudioListener {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+        }
+    }
+}
+
+impl AudioListener {
+    pub fn from_transform(transform: &Transform) -> Self {
+        Self {
+            position: transform.translation,
+            rotation: transform.rotation,
+        }
+    }
+
+    /// The gain and pan of the emitter as heard by the listener.
+    pub(crate) fn spatialize(&self, emitter: &Emitter) -> (f32, f32) {
+        let offset = emitter.position - self.position;
+        let distance = offset.length();
+        let gain = if distance <= emitter.min_distance {
+            1.0
+        } else {
+            let range = (emitter.max_distance - emitter.min_distance).max(f32::EPSILON);
+            (1.0 - (distance - emitter.min_distance) / range).max(0.0)
+        };
+        // The side of the listener the emitter is on, +X is right.
+        let pan = (self.rotation.inverse() * offset).normalize_or_zero().x;
+
+        (gain, pan)
+    }
+}
+
+/// The position of a spatial sound.
+///
+/// The sound is attenuated linearly from full volume at `min_distance` to silence at
+/// `max_distance`, and panned towards the side of the listener it's on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Emitter {
+    pub position: Vec3,
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+impl Emitter {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            min_distance: 1.0,
+            max_distance: 50.0,
+        }
+    }
+
+    pub fn with_distances(mut self, min_distance: f32, max_distance: f32) -> Self {
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self
+    }
+}
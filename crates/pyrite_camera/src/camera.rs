@@ -0,0 +1,142 @@
+use pyrite_app::resource::Resource;
+use pyrite_math::{
+    ndc_to_viewport, viewport_to_ndc, Frustum, Mat4, OrthographicProjection, PerspectiveProjection,
+    Projection, Transform, Vec2, Vec3,
+};
+
+/// The projection of a [`Camera`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraProjection {
+    Perspective(PerspectiveProjection),
+    Orthographic(OrthographicProjection),
+}
+
+impl Default for CameraProjection {
+    fn default() -> Self {
+        Self::Perspective(PerspectiveProjection::default())
+    }
+}
+
+impl Projection for CameraProjection {
+    fn compute_matrix(&self) -> Mat4 {
+        match self {
+            Self::Perspective(projection) => projection.compute_matrix(),
+            Self::Orthographic(projection) => projection.compute_matrix(),
+        }
+    }
+
+    fn resize(&mut self, width: f32, height: f32) {
+        match self {
+            Self::Perspective(projection) => projection.resize(width, height),
+            Self::Orthographic(projection) => projection.resize(width, height),
+        }
+    }
+}
+
+/// The region of the render target a camera draws to, in pixels from the top left.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub fn offset(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+}
+
+/// A camera looking down the negative z axis of its transform.
+///
+/// The viewport and projection don't follow the window on their own, [`Camera::resize`] should
+/// be called whenever the render target changes size.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Camera {
+    pub transform: Transform,
+    pub projection: CameraProjection,
+    pub viewport: Viewport,
+}
+
+impl Camera {
+    pub fn perspective(projection: PerspectiveProjection) -> Self {
+        Self {
+            projection: CameraProjection::Perspective(projection),
+            ..Default::default()
+        }
+    }
+
+    pub fn orthographic(projection: OrthographicProjection) -> Self {
+        Self {
+            projection: CameraProjection::Orthographic(projection),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Makes the viewport cover a whole render target of the given size and updates the
+    /// projection to match its aspect ratio.
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.viewport = Viewport {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+        };
+        if width > 0.0 && height > 0.0 {
+            self.projection.resize(width, height);
+        }
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        self.transform.compute_matrix().inverse()
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        self.projection.compute_matrix()
+    }
+
+    pub fn view_projection_matrix(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_projection(&self.view_projection_matrix())
+    }
+
+    /// The position of a world space point in the viewport, `None` if it's behind the camera.
+    pub fn world_to_viewport(&self, point: Vec3) -> Option<Vec2> {
+        let clip = self.view_projection_matrix() * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+
+        // The projection already flips Y, so NDC Y points down like the viewport.
+        let ndc = clip.truncate() / clip.w;
+        Some(ndc_to_viewport(
+            Vec2::new(ndc.x, ndc.y),
+            self.viewport.offset(),
+            self.viewport.size(),
+        ))
+    }
+
+    /// The ray in world space passing through a position in the viewport, as its origin on the
+    /// near plane and normalized direction.
+    pub fn viewport_to_ray(&self, position: Vec2) -> (Vec3, Vec3) {
+        let ndc = viewport_to_ndc(position, self.viewport.offset(), self.viewport.size());
+        let inverse_view_projection = self.view_projection_matrix().inverse();
+        let near = inverse_view_projection.project_point3(ndc.extend(0.0));
+        let far = inverse_view_projection.project_point3(ndc.extend(1.0));
+
+        (near, (far - near).normalize_or_zero())
+    }
+}
@@ -0,0 +1,172 @@
+use pyrite_app::{
+    plugin::Plugin,
+    resource::{Res, ResMut, Resource},
+    stage::UPDATE_STAGE,
+    AppBuilder,
+};
+use pyrite_input::{keyboard::Key, mouse::Button, Input};
+use pyrite_math::{EulerRot, Quat, Vec3};
+use pyrite_time::Time;
+
+use crate::Camera;
+
+/// How close the pitch of the controllers can get to looking straight up or down, so the camera
+/// never flips over.
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// The pixels of smooth scrolling counted as one scrolled line when zooming.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Adds a [`FlyCameraController`] moving the [`Camera`] during the update stage, requires the
+/// [`Camera`], [`Input`] and [`Time`] resources to be added first.
+pub struct FlyCameraPlugin;
+
+impl Plugin for FlyCameraPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        app_builder
+            .add_resource(FlyCameraController::default())
+            .add_system_to_stage(fly_camera_system, UPDATE_STAGE);
+    }
+}
+
+/// Moves the [`Camera`] like a free flying spectator.
+///
+/// WASD moves along the view, E and Q move up and down, and holding left shift boosts the speed.
+/// Holding the right mouse button looks around. Movement follows real time, so the camera keeps
+/// flying while the game is paused.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct FlyCameraController {
+    pub enabled: bool,
+    /// The movement speed in units per second.
+    pub speed: f32,
+    pub boost_multiplier: f32,
+    /// The rotation in radians per pixel of mouse movement.
+    pub sensitivity: f32,
+}
+
+impl Default for FlyCameraController {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            speed: 5.0,
+            boost_multiplier: 4.0,
+            sensitivity: 0.003,
+        }
+    }
+}
+
+fn fly_camera_system(
+    controller: Res<FlyCameraController>,
+    mut camera: ResMut<Camera>,
+    input: Res<Input>,
+    time: Res<Time>,
+) {
+    if !controller.enabled {
+        return;
+    }
+
+    let transform = &mut camera.transform;
+    if input.is_mouse_button_down(Button::Right) {
+        let (delta_x, delta_y) = input.mouse_delta();
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        transform.rotation = Quat::from_euler(
+            EulerRot::YXZ,
+            yaw - delta_x * controller.sensitivity,
+            (pitch - delta_y * controller.sensitivity).clamp(-MAX_PITCH, MAX_PITCH),
+            0.0,
+        );
+    }
+
+    let axis = |positive, negative| {
+        input.is_key_down(positive) as i32 as f32 - input.is_key_down(negative) as i32 as f32
+    };
+    let direction = transform.forward() * axis(Key::W, Key::S)
+        + transform.right() * axis(Key::D, Key::A)
+        + Vec3::Y * axis(Key::E, Key::Q);
+
+    let mut speed = controller.speed;
+    if input.is_key_down(Key::LShift) {
+        speed *= controller.boost_multiplier;
+    }
+    transform.translate(direction.normalize_or_zero() * speed * time.real_delta().as_secs_f32());
+}
+
+/// Adds an [`OrbitCameraController`] moving the [`Camera`] during the update stage, requires the
+/// [`Camera`] and [`Input`] resources to be added first.
+pub struct OrbitCameraPlugin;
+
+impl Plugin for OrbitCameraPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        app_builder
+            .add_resource(OrbitCameraController::default())
+            .add_system_to_stage(orbit_camera_system, UPDATE_STAGE);
+    }
+}
+
+/// Orbits the [`Camera`] around a target point.
+///
+/// Dragging with the left mouse button rotates around the target and dragging with the middle
+/// mouse button pans the target along the view plane. Scrolling zooms towards the target.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct OrbitCameraController {
+    pub enabled: bool,
+    pub target: Vec3,
+    pub distance: f32,
+    /// The rotation in radians per pixel of mouse movement.
+    pub sensitivity: f32,
+    /// The panned distance per pixel of mouse movement, relative to the distance to the target.
+    pub pan_speed: f32,
+    /// The fraction of the distance to the target zoomed per scrolled line.
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+impl Default for OrbitCameraController {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            target: Vec3::ZERO,
+            distance: 10.0,
+            sensitivity: 0.005,
+            pan_speed: 0.002,
+            zoom_speed: 0.1,
+            min_distance: 0.1,
+            max_distance: 1000.0,
+        }
+    }
+}
+
+fn orbit_camera_system(
+    mut controller: ResMut<OrbitCameraController>,
+    mut camera: ResMut<Camera>,
+    input: Res<Input>,
+) {
+    if !controller.enabled {
+        return;
+    }
+
+    let (delta_x, delta_y) = input.mouse_delta();
+    let transform = &mut camera.transform;
+    if input.is_mouse_button_down(Button::Left) {
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        transform.rotation = Quat::from_euler(
+            EulerRot::YXZ,
+            yaw - delta_x * controller.sensitivity,
+            (pitch - delta_y * controller.sensitivity).clamp(-MAX_PITCH, MAX_PITCH),
+            0.0,
+        );
+    }
+    if input.is_mouse_button_down(Button::Middle) {
+        let pan = controller.pan_speed * controller.distance;
+        controller.target += (transform.up() * delta_y - transform.right() * delta_x) * pan;
+    }
+
+    let zoom = input.scroll_lines().1 + input.scroll_pixels().1 / PIXELS_PER_LINE;
+    if zoom != 0.0 {
+        controller.distance = (controller.distance * (1.0 - controller.zoom_speed).powf(zoom))
+            .clamp(controller.min_distance, controller.max_distance);
+    }
+
+    transform.translation = controller.target - transform.forward() * controller.distance;
+}
@@ -0,0 +1,14 @@
+mod camera;
+mod controller;
+
+pub use camera::*;
+pub use controller::*;
+
+pub mod prelude {
+    pub use crate::{
+        camera::{Camera, CameraProjection, Viewport},
+        controller::{
+            FlyCameraController, FlyCameraPlugin, OrbitCameraController, OrbitCameraPlugin,
+        },
+    };
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler2D egui_texture;
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 1) in vec4 in_color;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    // Both the texture and vertex color are premultiplied, the pipeline blends with straight
+    // alpha.
+    vec4 color = in_color * texture(egui_texture, in_uv);
+    out_color = vec4(color.rgb / max(color.a, 1e-6), color.a);
+}
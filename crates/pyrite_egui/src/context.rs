@@ -0,0 +1,263 @@
+use std::time::Instant;
+
+use pyrite_app::resource::Resource;
+use winit::{
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+/// The points a line of mouse wheel scrolling moves.
+const POINTS_PER_SCROLL_LINE: f32 = 50.0;
+
+/// The egui context with the input collected for its next frame.
+///
+/// Window events are fed in with [`EguiContext::handle_window_event`], ui is built between the
+/// begin and end frame systems added by [`crate::setup_egui`], so any system in the
+/// [`pyrite_app::stage::UPDATE_STAGE`] can use [`EguiContext::context`].
+#[derive(Resource)]
+pub struct EguiContext {
+    context: egui::Context,
+    raw_input: egui::RawInput,
+    pointer_position: egui::Pos2,
+    modifiers: egui::Modifiers,
+    pixels_per_point: f32,
+    start: Instant,
+    output: Option<egui::FullOutput>,
+}
+
+impl Default for EguiContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EguiContext {
+    pub fn new() -> Self {
+        Self {
+            context: egui::Context::default(),
+            raw_input: egui::RawInput::default(),
+            pointer_position: egui::Pos2::ZERO,
+            modifiers: egui::Modifiers::default(),
+            pixels_per_point: 1.0,
+            start: Instant::now(),
+            output: None,
+        }
+    }
+
+    pub fn context(&self) -> &egui::Context {
+        &self.context
+    }
+
+    /// Whether egui is using the pointer, such as when it's over a window, so the game should
+    /// ignore it.
+    pub fn wants_pointer_input(&self) -> bool {
+        self.context.wants_pointer_input()
+    }
+
+    /// Whether a text field has focus, so the game should ignore the keyboard.
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.context.wants_keyboard_input()
+    }
+
+    /// Translates an event of the window's event loop into egui input for the next frame.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.pixels_per_point = *scale_factor as f32;
+            }
+            WindowEvent::Focused(focused) => {
+                self.raw_input.focused = *focused;
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                let state = modifiers.state();
+                self.modifiers = egui::Modifiers {
+                    alt: state.alt_key(),
+                    ctrl: state.control_key(),
+                    shift: state.shift_key(),
+                    mac_cmd: cfg!(target_os = "macos") && state.super_key(),
+                    command: if cfg!(target_os = "macos") {
+                        state.super_key()
+                    } else {
+                        state.control_key()
+                    },
+                };
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer_position = egui::pos2(
+                    position.x as f32 / self.pixels_per_point,
+                    position.y as f32 / self.pixels_per_point,
+                );
+                self.raw_input
+                    .events
+                    .push(egui::Event::PointerMoved(self.pointer_position));
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.raw_input.events.push(egui::Event::PointerGone);
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    MouseButton::Left => egui::PointerButton::Primary,
+                    MouseButton::Right => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    MouseButton::Back => egui::PointerButton::Extra1,
+                    MouseButton::Forward => egui::PointerButton::Extra2,
+                    MouseButton::Other(_) => return,
+                };
+                self.raw_input.events.push(egui::Event::PointerButton {
+                    pos: self.pointer_position,
+                    button,
+                    pressed: *state == ElementState::Pressed,
+                    modifiers: self.modifiers,
+                });
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        egui::vec2(*x, *y) * POINTS_PER_SCROLL_LINE
+                    }
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        egui::vec2(delta.x as f32, delta.y as f32) / self.pixels_per_point
+                    }
+                };
+                self.raw_input.events.push(egui::Event::Scroll(delta));
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state == ElementState::Pressed;
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    if let Some(key) = to_egui_key(code) {
+                        self.raw_input.events.push(egui::Event::Key {
+                            key,
+                            pressed,
+                            repeat: event.repeat,
+                            modifiers: self.modifiers,
+                        });
+                    }
+                }
+
+                // Text typed with ctrl or cmd held is a shortcut rather than input.
+                let text = event.text.as_ref().filter(|text| {
+                    pressed
+                        && !self.modifiers.ctrl
+                        && !self.modifiers.mac_cmd
+                        && text.chars().all(|c| !c.is_control())
+                });
+                if let Some(text) = text {
+                    self.raw_input
+                        .events
+                        .push(egui::Event::Text(text.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts a frame covering a screen of `width` by `height` pixels.
+    pub(crate) fn begin_frame(&mut self, width: u32, height: u32) {
+        let mut raw_input = std::mem::take(&mut self.raw_input);
+        raw_input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(width as f32, height as f32) / self.pixels_per_point,
+        ));
+        raw_input
+            .viewports
+            .entry(raw_input.viewport_id)
+            .or_default()
+            .native_pixels_per_point = Some(self.pixels_per_point);
+        raw_input.time = Some(self.start.elapsed().as_secs_f64());
+        raw_input.modifiers = self.modifiers;
+        self.raw_input.focused = raw_input.focused;
+
+        self.context.begin_frame(raw_input);
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        let mut output = self.context.end_frame();
+        // The texture changes of a frame which wasn't rendered must still be applied.
+        if let Some(previous) = self.output.take() {
+            let mut textures_delta = previous.textures_delta;
+            textures_delta.append(output.textures_delta);
+            output.textures_delta = textures_delta;
+        }
+        self.output = Some(output);
+    }
+
+    /// The output of the last frame which hasn't been rendered yet.
+    pub(crate) fn take_output(&mut self) -> Option<egui::FullOutput> {
+        self.output.take()
+    }
+
+    pub(crate) fn pixels_per_point(&self) -> f32 {
+        self.pixels_per_point
+    }
+}
+
+fn to_egui_key(code: KeyCode) -> Option<egui::Key> {
+    Some(match code {
+        KeyCode::ArrowDown => egui::Key::ArrowDown,
+        KeyCode::ArrowLeft => egui::Key::ArrowLeft,
+        KeyCode::ArrowRight => egui::Key::ArrowRight,
+        KeyCode::ArrowUp => egui::Key::ArrowUp,
+        KeyCode::Escape => egui::Key::Escape,
+        KeyCode::Tab => egui::Key::Tab,
+        KeyCode::Backspace => egui::Key::Backspace,
+        KeyCode::Enter | KeyCode::NumpadEnter => egui::Key::Enter,
+        KeyCode::Space => egui::Key::Space,
+        KeyCode::Insert => egui::Key::Insert,
+        KeyCode::Delete => egui::Key::Delete,
+        KeyCode::Home => egui::Key::Home,
+        KeyCode::End => egui::Key::End,
+        KeyCode::PageUp => egui::Key::PageUp,
+        KeyCode::PageDown => egui::Key::PageDown,
+        KeyCode::Minus => egui::Key::Minus,
+        KeyCode::Equal => egui::Key::PlusEquals,
+        KeyCode::Digit0 | KeyCode::Numpad0 => egui::Key::Num0,
+        KeyCode::Digit1 | KeyCode::Numpad1 => egui::Key::Num1,
+        KeyCode::Digit2 | KeyCode::Numpad2 => egui::Key::Num2,
+        KeyCode::Digit3 | KeyCode::Numpad3 => egui::Key::Num3,
+        KeyCode::Digit4 | KeyCode::Numpad4 => egui::Key::Num4,
+        KeyCode::Digit5 | KeyCode::Numpad5 => egui::Key::Num5,
+        KeyCode::Digit6 | KeyCode::Numpad6 => egui::Key::Num6,
+        KeyCode::Digit7 | KeyCode::Numpad7 => egui::Key::Num7,
+        KeyCode::Digit8 | KeyCode::Numpad8 => egui::Key::Num8,
+        KeyCode::Digit9 | KeyCode::Numpad9 => egui::Key::Num9,
+        KeyCode::KeyA => egui::Key::A,
+        KeyCode::KeyB => egui::Key::B,
+        KeyCode::KeyC => egui::Key::C,
+        KeyCode::KeyD => egui::Key::D,
+        KeyCode::KeyE => egui::Key::E,
+        KeyCode::KeyF => egui::Key::F,
+        KeyCode::KeyG => egui::Key::G,
+        KeyCode::KeyH => egui::Key::H,
+        KeyCode::KeyI => egui::Key::I,
+        KeyCode::KeyJ => egui::Key::J,
+        KeyCode::KeyK => egui::Key::K,
+        KeyCode::KeyL => egui::Key::L,
+        KeyCode::KeyM => egui::Key::M,
+        KeyCode::KeyN => egui::Key::N,
+        KeyCode::KeyO => egui::Key::O,
+        KeyCode::KeyP => egui::Key::P,
+        KeyCode::KeyQ => egui::Key::Q,
+        KeyCode::KeyR => egui::Key::R,
+        KeyCode::KeyS => egui::Key::S,
+        KeyCode::KeyT => egui::Key::T,
+        KeyCode::KeyU => egui::Key::U,
+        KeyCode::KeyV => egui::Key::V,
+        KeyCode::KeyW => egui::Key::W,
+        KeyCode::KeyX => egui::Key::X,
+        KeyCode::KeyY => egui::Key::Y,
+        KeyCode::KeyZ => egui::Key::Z,
+        KeyCode::F1 => egui::Key::F1,
+        KeyCode::F2 => egui::Key::F2,
+        KeyCode::F3 => egui::Key::F3,
+        KeyCode::F4 => egui::Key::F4,
+        KeyCode::F5 => egui::Key::F5,
+        KeyCode::F6 => egui::Key::F6,
+        KeyCode::F7 => egui::Key::F7,
+        KeyCode::F8 => egui::Key::F8,
+        KeyCode::F9 => egui::Key::F9,
+        KeyCode::F10 => egui::Key::F10,
+        KeyCode::F11 => egui::Key::F11,
+        KeyCode::F12 => egui::Key::F12,
+        _ => return None,
+    })
+}
@@ -0,0 +1,67 @@
+use pyrite_app::{
+    plugin::Plugin,
+    resource::{Res, ResMut},
+    stage::{POST_UPDATE_STAGE, PRE_UPDATE_STAGE},
+    AppBuilder,
+};
+use pyrite_vulkan::{swapchain::SwapchainManager, Vulkan};
+
+mod context;
+mod renderer;
+
+pub use context::*;
+pub use renderer::*;
+
+pub use egui;
+
+/// Adds the [`EguiContext`] and [`EguiRenderer`], see [`setup_egui`].
+pub struct EguiPlugin {
+    pub config: EguiRendererConfig,
+    pub frames_in_flight: usize,
+}
+
+impl Plugin for EguiPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        setup_egui(app_builder, self.frames_in_flight, &self.config);
+    }
+}
+
+/// Adds the [`EguiContext`] and [`EguiRenderer`] resources with systems beginning the egui frame
+/// in the [`PRE_UPDATE_STAGE`] and ending it in the [`POST_UPDATE_STAGE`].
+///
+/// Requires the [`Vulkan`] and [`SwapchainManager`] resources to be added first. Window events
+/// must be passed to [`EguiContext::handle_window_event`] and the ui drawn with
+/// [`EguiRenderer::render`] as the last pass over the backbuffer.
+pub fn setup_egui(
+    app_builder: &mut AppBuilder,
+    frames_in_flight: usize,
+    config: &EguiRendererConfig,
+) {
+    let egui_renderer = EguiRenderer::new(
+        &app_builder.get_resource::<Vulkan>(),
+        frames_in_flight,
+        config,
+    );
+
+    app_builder
+        .add_resource(EguiContext::new())
+        .add_resource(egui_renderer)
+        .add_system_to_stage(begin_frame_system, PRE_UPDATE_STAGE)
+        .add_system_to_stage(end_frame_system, POST_UPDATE_STAGE);
+}
+
+fn begin_frame_system(
+    mut egui_context: ResMut<EguiContext>,
+    swapchain_manager: Res<SwapchainManager>,
+) {
+    let extent = swapchain_manager.info().extent();
+    egui_context.begin_frame(extent.width, extent.height);
+}
+
+fn end_frame_system(mut egui_context: ResMut<EguiContext>) {
+    egui_context.end_frame();
+}
+
+pub mod prelude {
+    pub use crate::{EguiContext, EguiPlugin, EguiRenderer};
+}
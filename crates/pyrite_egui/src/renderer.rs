@@ -0,0 +1,489 @@
+use std::{collections::HashMap, sync::Arc};
+
+use pyrite_app::resource::Resource;
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator,
+    ash::vk,
+    objects::{
+        Buffer, CommandBuffer, DescriptorSetHandle, DescriptorSetPool, GraphicsPipeline,
+        GraphicsPipelineCreateInfo, Image, PipelineLayoutCreateInfo, RenderingAttachment,
+        RenderingFormats, RenderingInfo, Shader, Texture, TypedBuffer, TypedBufferCreateInfo,
+    },
+    stager::VulkanStager,
+    util::Extent2D,
+    Vulkan,
+};
+
+use crate::EguiContext;
+
+/// The descriptor set holding the texture of a mesh at binding 0.
+pub const EGUI_TEXTURE_SET: u32 = 0;
+
+/// The number of sets a [`DescriptorSetPool`] is created with.
+const TEXTURE_SETS_PER_POOL: usize = 100;
+
+#[derive(Clone)]
+pub struct EguiRendererConfig {
+    /// The SPIR-V of the vertex shader, `shaders/egui.vert` in this crate reads egui's vertices
+    /// and the screen size in points from the push constants.
+    pub vertex_shader: Vec<u32>,
+    /// The SPIR-V of the fragment shader, `shaders/egui.frag` in this crate samples the texture
+    /// from [`EGUI_TEXTURE_SET`].
+    pub fragment_shader: Vec<u32>,
+    /// The format of the images the ui is drawn over.
+    pub color_format: vk::Format,
+}
+
+struct EguiTexture {
+    texture: Arc<Texture>,
+    /// The texels of textures created by egui, kept to apply partial updates to. `None` for
+    /// user textures.
+    texels: Option<Vec<u8>>,
+    pool_index: usize,
+    descriptor_set: DescriptorSetHandle,
+}
+
+/// A mesh to draw with its clip rect in pixels.
+struct EguiDraw {
+    texture_id: egui::TextureId,
+    scissor: vk::Rect2D,
+    first_index: u32,
+    index_count: u32,
+    vertex_offset: i32,
+}
+
+/// Draws the output of the [`EguiContext`] over an image.
+#[derive(Resource)]
+pub struct EguiRenderer {
+    pipeline: GraphicsPipeline,
+    descriptor_set_pools: Vec<DescriptorSetPool>,
+    allocated_sets: usize,
+    textures: HashMap<egui::TextureId, EguiTexture>,
+    next_user_texture: u64,
+    vertex_buffers: Vec<Option<TypedBuffer<egui::epaint::Vertex>>>,
+    index_buffers: Vec<Option<TypedBuffer<u32>>>,
+    /// Textures freed during each frame in flight, dropped once the frame comes around again.
+    retired_textures: Vec<Vec<EguiTexture>>,
+}
+
+impl EguiRenderer {
+    pub fn new(vulkan: &Vulkan, frames_in_flight: usize, config: &EguiRendererConfig) -> Self {
+        let vertex_shader = Shader::new(vulkan, &config.vertex_shader);
+        let fragment_shader = Shader::new(vulkan, &config.fragment_shader);
+        let pipeline = GraphicsPipeline::new(
+            vulkan,
+            GraphicsPipelineCreateInfo {
+                vertex_shader: &vertex_shader,
+                vertex_entry_point: "main".to_string(),
+                fragment_shader: &fragment_shader,
+                fragment_entry_point: "main".to_string(),
+                pipeline_layout_info: PipelineLayoutCreateInfo::default(),
+                vertex_bindings: vec![vk::VertexInputBindingDescription {
+                    binding: 0,
+                    stride: std::mem::size_of::<egui::epaint::Vertex>() as u32,
+                    input_rate: vk::VertexInputRate::VERTEX,
+                }],
+                // The position and uv are 2 floats each, followed by the color as 4 bytes.
+                vertex_attributes: vec![
+                    vk::VertexInputAttributeDescription {
+                        location: 0,
+                        binding: 0,
+                        format: vk::Format::R32G32_SFLOAT,
+                        offset: 0,
+                    },
+                    vk::VertexInputAttributeDescription {
+                        location: 1,
+                        binding: 0,
+                        format: vk::Format::R32G32_SFLOAT,
+                        offset: 8,
+                    },
+                    vk::VertexInputAttributeDescription {
+                        location: 2,
+                        binding: 0,
+                        format: vk::Format::R8G8B8A8_UNORM,
+                        offset: 16,
+                    },
+                ],
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                polygon_mode: vk::PolygonMode::FILL,
+                cull_mode: vk::CullModeFlags::NONE,
+                front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+                alpha_blending: true,
+                depth_compare_op: vk::CompareOp::ALWAYS,
+                rendering_formats: RenderingFormats {
+                    color_formats: vec![config.color_format],
+                    depth_format: None,
+                    samples: vk::SampleCountFlags::TYPE_1,
+                },
+                name: Some("egui".to_string()),
+            },
+        );
+
+        Self {
+            pipeline,
+            descriptor_set_pools: Vec::new(),
+            allocated_sets: 0,
+            textures: HashMap::new(),
+            next_user_texture: 0,
+            vertex_buffers: (0..frames_in_flight).map(|_| None).collect(),
+            index_buffers: (0..frames_in_flight).map(|_| None).collect(),
+            retired_textures: (0..frames_in_flight).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    /// Makes a texture available to egui images, it must be in the shader read only layout
+    /// whenever the ui is drawn.
+    pub fn register_texture(&mut self, vulkan: &Vulkan, texture: Arc<Texture>) -> egui::TextureId {
+        let id = egui::TextureId::User(self.next_user_texture);
+        self.next_user_texture += 1;
+
+        let texture = self.create_egui_texture(vulkan, texture, None);
+        self.textures.insert(id, texture);
+        id
+    }
+
+    /// Stops drawing a registered texture, it's released once the frames using it finished.
+    pub fn unregister_texture(&mut self, id: egui::TextureId, frame_index: usize) {
+        if let Some(texture) = self.textures.remove(&id) {
+            self.retired_textures[frame_index].push(texture);
+        }
+    }
+
+    /// Records the texture uploads and a pass drawing the last ui built with the context over
+    /// `target`, which is left in the color attachment layout.
+    ///
+    /// Should be called once per frame after everything else was drawn to `target`, the
+    /// resources of `frame_index` must have been released.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+        frame_index: usize,
+        context: &mut EguiContext,
+        target: &dyn Image,
+        extent: vk::Extent2D,
+    ) {
+        pyrite_util::profile_scope!("EguiRenderer::render");
+
+        self.retired_textures[frame_index].clear();
+        let Some(output) = context.take_output() else {
+            return;
+        };
+
+        for (id, delta) in &output.textures_delta.set {
+            self.set_texture(
+                vulkan,
+                vulkan_allocator,
+                stager,
+                command_buffer,
+                frame_index,
+                *id,
+                delta,
+            );
+        }
+
+        let pixels_per_point = context.pixels_per_point();
+        let primitives = context
+            .context()
+            .tessellate(output.shapes, output.pixels_per_point);
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut draws = Vec::new();
+        for primitive in primitives {
+            let egui::epaint::Primitive::Mesh(mesh) = primitive.primitive else {
+                log::warn!("egui paint callbacks aren't supported");
+                continue;
+            };
+            let Some(scissor) = Self::scissor(primitive.clip_rect, pixels_per_point, extent) else {
+                continue;
+            };
+            if mesh.indices.is_empty() {
+                continue;
+            }
+
+            draws.push(EguiDraw {
+                texture_id: mesh.texture_id,
+                scissor,
+                first_index: indices.len() as u32,
+                index_count: mesh.indices.len() as u32,
+                vertex_offset: vertices.len() as i32,
+            });
+            vertices.extend(mesh.vertices);
+            indices.extend(mesh.indices);
+        }
+
+        if !draws.is_empty() {
+            Self::write_buffer(
+                vulkan,
+                vulkan_allocator,
+                &mut self.vertex_buffers[frame_index],
+                vk::BufferUsageFlags::VERTEX_BUFFER,
+                &vertices,
+            );
+            Self::write_buffer(
+                vulkan,
+                vulkan_allocator,
+                &mut self.index_buffers[frame_index],
+                vk::BufferUsageFlags::INDEX_BUFFER,
+                &indices,
+            );
+            self.record_draws(
+                command_buffer,
+                frame_index,
+                target,
+                extent,
+                pixels_per_point,
+                &draws,
+            );
+        }
+
+        for id in &output.textures_delta.free {
+            if let Some(texture) = self.textures.remove(id) {
+                self.retired_textures[frame_index].push(texture);
+            }
+        }
+    }
+
+    fn record_draws(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        frame_index: usize,
+        target: &dyn Image,
+        extent: vk::Extent2D,
+        pixels_per_point: f32,
+        draws: &[EguiDraw],
+    ) {
+        command_buffer.begin_label("egui", [1.0, 1.0, 1.0, 1.0]);
+        command_buffer.begin_rendering(RenderingInfo {
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D::default(),
+                extent,
+            },
+            color_attachments: vec![RenderingAttachment {
+                image: target,
+                load_op: vk::AttachmentLoadOp::LOAD,
+                store_op: vk::AttachmentStoreOp::STORE,
+                clear_value: vk::ClearValue::default(),
+                resolve_image: None,
+            }],
+            depth_attachment: None,
+            secondary_command_buffers: false,
+        });
+
+        command_buffer.bind_graphics_pipeline(&self.pipeline);
+        command_buffer.set_viewport_and_scissor(vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent,
+        });
+        command_buffer.push_constants(
+            vk::PipelineBindPoint::GRAPHICS,
+            vk::ShaderStageFlags::VERTEX,
+            0,
+            &[
+                extent.width as f32 / pixels_per_point,
+                extent.height as f32 / pixels_per_point,
+            ],
+        );
+        let vertex_buffer = self.vertex_buffers[frame_index].as_ref().unwrap();
+        command_buffer.bind_vertex_buffers(0, &[(vertex_buffer as &dyn Buffer, 0)]);
+        command_buffer.bind_index_buffer(
+            self.index_buffers[frame_index].as_ref().unwrap(),
+            0,
+            vk::IndexType::UINT32,
+        );
+
+        for draw in draws {
+            let Some(texture) = self.textures.get(&draw.texture_id) else {
+                log::warn!("egui drew with unknown texture {:?}", draw.texture_id);
+                continue;
+            };
+
+            command_buffer.bind_descriptor_sets(
+                vk::PipelineBindPoint::GRAPHICS,
+                EGUI_TEXTURE_SET,
+                &[self.descriptor_set_pools[texture.pool_index]
+                    .get(texture.descriptor_set)
+                    .unwrap()],
+            );
+            command_buffer.set_scissor(draw.scissor);
+            command_buffer.draw_indexed(
+                draw.index_count,
+                1,
+                draw.first_index,
+                draw.vertex_offset,
+                0,
+            );
+        }
+
+        command_buffer.end_rendering();
+        command_buffer.end_label();
+    }
+
+    /// The clip rect in pixels clamped to the target, `None` if nothing of it is visible.
+    fn scissor(
+        clip_rect: egui::Rect,
+        pixels_per_point: f32,
+        extent: vk::Extent2D,
+    ) -> Option<vk::Rect2D> {
+        let min_x = (clip_rect.min.x * pixels_per_point).round().max(0.0) as u32;
+        let min_y = (clip_rect.min.y * pixels_per_point).round().max(0.0) as u32;
+        let max_x =
+            ((clip_rect.max.x * pixels_per_point).round().max(0.0) as u32).min(extent.width);
+        let max_y =
+            ((clip_rect.max.y * pixels_per_point).round().max(0.0) as u32).min(extent.height);
+        if min_x >= max_x || min_y >= max_y {
+            return None;
+        }
+
+        Some(vk::Rect2D {
+            offset: vk::Offset2D {
+                x: min_x as i32,
+                y: min_y as i32,
+            },
+            extent: vk::Extent2D {
+                width: max_x - min_x,
+                height: max_y - min_y,
+            },
+        })
+    }
+
+    /// Creates a texture egui added, or writes the changed region of an existing one.
+    #[allow(clippy::too_many_arguments)]
+    fn set_texture(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+        frame_index: usize,
+        id: egui::TextureId,
+        delta: &egui::epaint::ImageDelta,
+    ) {
+        let [width, height] = delta.image.size();
+        let texels: Vec<u8> = match &delta.image {
+            egui::ImageData::Color(image) => image
+                .pixels
+                .iter()
+                .flat_map(|color| color.to_array())
+                .collect(),
+            egui::ImageData::Font(image) => image
+                .srgba_pixels(None)
+                .flat_map(|color| color.to_array())
+                .collect(),
+        };
+
+        if let Some([x, y]) = delta.pos {
+            let Some(texture) = self.textures.get_mut(&id) else {
+                log::warn!("egui updated unknown texture {:?}", id);
+                return;
+            };
+            let Some(texture_texels) = &mut texture.texels else {
+                log::warn!("egui tried to update user texture {:?}", id);
+                return;
+            };
+
+            let texture_width = texture.texture.extent().width as usize;
+            for (row, delta_row) in texels.chunks_exact(width * 4).enumerate() {
+                let start = ((y + row) * texture_width + x) * 4;
+                texture_texels[start..start + width * 4].copy_from_slice(delta_row);
+            }
+            texture.texture.write_rgba8(
+                vulkan,
+                vulkan_allocator,
+                stager,
+                command_buffer,
+                texture_texels,
+            );
+            return;
+        }
+
+        let texture = Arc::new(Texture::new_dynamic(
+            vulkan,
+            vulkan_allocator,
+            stager,
+            command_buffer,
+            &texels,
+            Extent2D {
+                width: width as u32,
+                height: height as u32,
+            },
+        ));
+        let texture = self.create_egui_texture(vulkan, texture, Some(texels));
+        if let Some(previous) = self.textures.insert(id, texture) {
+            self.retired_textures[frame_index].push(previous);
+        }
+    }
+
+    fn create_egui_texture(
+        &mut self,
+        vulkan: &Vulkan,
+        texture: Arc<Texture>,
+        texels: Option<Vec<u8>>,
+    ) -> EguiTexture {
+        if self.allocated_sets.is_multiple_of(TEXTURE_SETS_PER_POOL) {
+            self.descriptor_set_pools
+                .push(DescriptorSetPool::new(vulkan));
+        }
+        self.allocated_sets += 1;
+        let pool_index = self.descriptor_set_pools.len() - 1;
+        let descriptor_set_pool = &mut self.descriptor_set_pools[pool_index];
+
+        let layout = &self
+            .pipeline
+            .instance()
+            .pipeline_layout()
+            .descriptor_set_layouts()[EGUI_TEXTURE_SET as usize];
+        let [descriptor_set] = descriptor_set_pool.allocate_descriptor_sets::<1>(layout);
+        let mut writer = descriptor_set_pool
+            .get_mut(descriptor_set)
+            .unwrap()
+            .writer();
+        writer.combined_image_sampler(
+            0,
+            0,
+            texture.image(),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            texture.sampler(),
+        );
+        writer.submit(vulkan);
+
+        EguiTexture {
+            texture,
+            texels,
+            pool_index,
+            descriptor_set,
+        }
+    }
+
+    /// Writes the data to the buffer, replacing it with one of the next power of two length if
+    /// the data doesn't fit.
+    fn write_buffer<T: bytemuck::Pod>(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        buffer: &mut Option<TypedBuffer<T>>,
+        usage: vk::BufferUsageFlags,
+        data: &[T],
+    ) {
+        if buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.len() < data.len())
+        {
+            *buffer = Some(TypedBuffer::new(
+                vulkan,
+                vulkan_allocator,
+                &TypedBufferCreateInfo {
+                    len: data.len().next_power_of_two(),
+                    usage,
+                    memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                        | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    name: Some("egui".to_string()),
+                },
+            ));
+        }
+
+        buffer.as_mut().unwrap().write_slice(0, data);
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies a connected gamepad, ids of disconnected gamepads may be reused by the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GamepadId(pub usize);
+
+pub struct Gamepad {
+    name: String,
+    pressed_buttons: HashSet<GamepadButton>,
+    down_buttons: HashSet<GamepadButton>,
+    released_buttons: HashSet<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+impl Gamepad {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            pressed_buttons: HashSet::new(),
+            down_buttons: HashSet::new(),
+            released_buttons: HashSet::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_button_pressed(&self, button: GamepadButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    pub fn is_button_down(&self, button: GamepadButton) -> bool {
+        self.down_buttons.contains(&button)
+    }
+
+    pub fn is_button_released(&self, button: GamepadButton) -> bool {
+        self.released_buttons.contains(&button)
+    }
+
+    /// The raw value of the axis in the range -1 to 1, without the deadzone applied.
+    pub fn raw_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    pub(crate) fn down_buttons(&self) -> impl Iterator<Item = GamepadButton> + '_ {
+        self.down_buttons.iter().copied()
+    }
+
+    pub(crate) fn raw_axes(&self) -> impl Iterator<Item = (GamepadAxis, f32)> + '_ {
+        self.axes.iter().map(|(axis, value)| (*axis, *value))
+    }
+}
+
+pub struct Gamepads {
+    gamepads: HashMap<GamepadId, Gamepad>,
+    deadzone: f32,
+    rumble_requests: Vec<RumbleRequest>,
+}
+
+impl Gamepads {
+    /// The deadzone sticks are given if none was configured, enough to hide the drift of most
+    /// worn sticks.
+    pub const DEFAULT_DEADZONE: f32 = 0.1;
+
+    pub fn new() -> Self {
+        Self {
+            gamepads: HashMap::new(),
+            deadzone: Self::DEFAULT_DEADZONE,
+            rumble_requests: Vec::new(),
+        }
+    }
+
+    pub fn clear_inputs(&mut self) {
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.pressed_buttons.clear();
+            gamepad.released_buttons.clear();
+        }
+    }
+
+    pub fn submit_input(&mut self, input: SubmitInput) {
+        match input {
+            SubmitInput::Connected(id, name) => {
+                self.gamepads.insert(id, Gamepad::new(name));
+            }
+            SubmitInput::Disconnected(id) => {
+                self.gamepads.remove(&id);
+            }
+            SubmitInput::Pressed(id, button) => {
+                if let Some(gamepad) = self.gamepads.get_mut(&id) {
+                    gamepad.pressed_buttons.insert(button);
+                    gamepad.down_buttons.insert(button);
+                }
+            }
+            SubmitInput::Released(id, button) => {
+                if let Some(gamepad) = self.gamepads.get_mut(&id) {
+                    gamepad.released_buttons.insert(button);
+                    gamepad.down_buttons.remove(&button);
+                }
+            }
+            SubmitInput::Axis(id, axis, value) => {
+                if let Some(gamepad) = self.gamepads.get_mut(&id) {
+                    gamepad.axes.insert(axis, value.clamp(-1.0, 1.0));
+                }
+            }
+        }
+    }
+
+    /// The connected gamepads in no particular order.
+    pub fn gamepads(&self) -> impl Iterator<Item = (GamepadId, &Gamepad)> {
+        self.gamepads.iter().map(|(id, gamepad)| (*id, gamepad))
+    }
+
+    pub fn gamepad(&self, id: GamepadId) -> Option<&Gamepad> {
+        self.gamepads.get(&id)
+    }
+
+    pub fn is_button_pressed(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad(id)
+            .is_some_and(|gamepad| gamepad.is_button_pressed(button))
+    }
+
+    pub fn is_button_down(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad(id)
+            .is_some_and(|gamepad| gamepad.is_button_down(button))
+    }
+
+    pub fn is_button_released(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad(id)
+            .is_some_and(|gamepad| gamepad.is_button_released(button))
+    }
+
+    /// The value of the axis in the range -1 to 1, zero within the deadzone and rescaled outside
+    /// of it so the value still starts at zero.
+    pub fn axis(&self, id: GamepadId, axis: GamepadAxis) -> f32 {
+        let value = self
+            .gamepad(id)
+            .map_or(0.0, |gamepad| gamepad.raw_axis(axis));
+        if value.abs() <= self.deadzone {
+            return 0.0;
+        }
+
+        value.signum() * (value.abs() - self.deadzone) / (1.0 - self.deadzone)
+    }
+
+    pub fn deadzone(&self) -> f32 {
+        self.deadzone
+    }
+
+    /// Sets the deadzone applied to every axis, clamped to the range 0 to 0.9.
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone.clamp(0.0, 0.9);
+    }
+
+    /// Requests the gamepad to rumble, ignored by gamepads and backends without force feedback.
+    ///
+    /// `strong` drives the low frequency motor and `weak` the high frequency one, both in the
+    /// range 0 to 1.
+    pub fn rumble(&mut self, id: GamepadId, strong: f32, weak: f32, duration: Duration) {
+        self.rumble_requests.push(RumbleRequest {
+            gamepad: id,
+            strong: strong.clamp(0.0, 1.0),
+            weak: weak.clamp(0.0, 1.0),
+            duration,
+        });
+    }
+
+    /// Takes the rumble requests for the backend to play.
+    pub fn drain_rumble_requests(&mut self) -> impl Iterator<Item = RumbleRequest> + '_ {
+        self.rumble_requests.drain(..)
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RumbleRequest {
+    pub gamepad: GamepadId,
+    pub strong: f32,
+    pub weak: f32,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SubmitInput {
+    Connected(GamepadId, String),
+    Disconnected(GamepadId),
+    Pressed(GamepadId, GamepadButton),
+    Released(GamepadId, GamepadButton),
+    Axis(GamepadId, GamepadAxis, f32),
+}
+
+/// The buttons of a gamepad by their position, the south button is A on Xbox and Cross on
+/// PlayStation controllers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+
+    Select,
+    Start,
+    Mode,
+
+    LeftThumb,
+    RightThumb,
+
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// The axes of a gamepad, up and right are positive for the sticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    /// The left trigger in the range 0 to 1.
+    LeftTrigger,
+    /// The right trigger in the range 0 to 1.
+    RightTrigger,
+}
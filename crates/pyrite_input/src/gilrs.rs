@@ -0,0 +1,232 @@
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use gilrs::{
+    ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Replay, Ticks},
+    Axis, Button, EventType, Gilrs,
+};
+use pyrite_app::{
+    plugin::Plugin,
+    resource::{Res, ResMut, Resource},
+    stage::PRE_UPDATE_STAGE,
+    AppBuilder,
+};
+
+use crate::{
+    gamepad::{self, GamepadAxis, GamepadButton, GamepadId, RumbleRequest},
+    Input, InputEvent,
+};
+
+/// How long the gamepad thread waits for an event before checking for rumble requests.
+const POLL_TIMEOUT: Duration = Duration::from_millis(4);
+
+/// Submits the gamepads reported by gilrs to the [`Input`] every frame and plays their rumble
+/// requests.
+pub struct GilrsPlugin;
+
+impl Plugin for GilrsPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        setup_gilrs(app_builder);
+    }
+}
+
+pub fn setup_gilrs(app_builder: &mut AppBuilder) {
+    if !app_builder.contains_resource::<Input>() {
+        app_builder.add_resource(Input::new());
+    }
+
+    app_builder
+        .add_resource(GilrsGamepads::new())
+        .add_system_to_stage(update_gamepads, PRE_UPDATE_STAGE);
+}
+
+/// The events of the gamepad thread, which owns the gilrs context since it can't be shared
+/// between threads on every platform.
+#[derive(Resource)]
+pub struct GilrsGamepads {
+    events: Mutex<Receiver<gamepad::SubmitInput>>,
+    rumble_requests: Sender<RumbleRequest>,
+}
+
+impl GilrsGamepads {
+    pub fn new() -> Self {
+        let (event_sender, events) = mpsc::channel();
+        let (rumble_requests, rumble_receiver) = mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("pyrite_gamepads".to_string())
+            .spawn(move || run_gamepad_thread(event_sender, rumble_receiver))
+            .expect("Failed to spawn the gamepad thread.");
+
+        Self {
+            events: Mutex::new(events),
+            rumble_requests,
+        }
+    }
+}
+
+impl Default for GilrsGamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn update_gamepads(gilrs_gamepads: Res<GilrsGamepads>, mut input: ResMut<Input>) {
+    pyrite_util::profile_scope!("update_gamepads");
+
+    for event in gilrs_gamepads.events.lock().unwrap().try_iter() {
+        input.submit_input(InputEvent::Gamepad(event));
+    }
+    for request in input.gamepads_mut().drain_rumble_requests() {
+        let _ = gilrs_gamepads.rumble_requests.send(request);
+    }
+}
+
+fn run_gamepad_thread(
+    event_sender: Sender<gamepad::SubmitInput>,
+    rumble_receiver: Receiver<RumbleRequest>,
+) {
+    let mut gilrs = match Gilrs::new() {
+        Ok(gilrs) => gilrs,
+        Err(err) => {
+            log::warn!("Gamepads are unavailable: {}", err);
+            return;
+        }
+    };
+
+    for (id, gamepad) in gilrs.gamepads() {
+        let _ = event_sender.send(gamepad::SubmitInput::Connected(
+            GamepadId(id.into()),
+            gamepad.name().to_string(),
+        ));
+    }
+
+    // Effects stop playing once they're dropped.
+    let mut playing_effects: Vec<(Effect, Instant)> = Vec::new();
+    loop {
+        loop {
+            match rumble_receiver.try_recv() {
+                Ok(request) => {
+                    if let Some(effect) = play_rumble(&mut gilrs, &request) {
+                        playing_effects.push((effect, Instant::now() + request.duration));
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                // The app shut down.
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+        let now = Instant::now();
+        playing_effects.retain(|(_, end)| *end > now);
+
+        let Some(event) = gilrs.next_event_blocking(Some(POLL_TIMEOUT)) else {
+            continue;
+        };
+        let id = GamepadId(event.id.into());
+        let input = match event.event {
+            EventType::Connected => Some(gamepad::SubmitInput::Connected(
+                id,
+                gilrs.gamepad(event.id).name().to_string(),
+            )),
+            EventType::Disconnected => Some(gamepad::SubmitInput::Disconnected(id)),
+            EventType::ButtonPressed(button, _) => to_pyrite_gamepad_button(button)
+                .map(|button| gamepad::SubmitInput::Pressed(id, button)),
+            EventType::ButtonReleased(button, _) => to_pyrite_gamepad_button(button)
+                .map(|button| gamepad::SubmitInput::Released(id, button)),
+            // Analog triggers are reported as button values rather than axes.
+            EventType::ButtonChanged(Button::LeftTrigger2, value, _) => Some(
+                gamepad::SubmitInput::Axis(id, GamepadAxis::LeftTrigger, value),
+            ),
+            EventType::ButtonChanged(Button::RightTrigger2, value, _) => Some(
+                gamepad::SubmitInput::Axis(id, GamepadAxis::RightTrigger, value),
+            ),
+            EventType::AxisChanged(axis, value, _) => {
+                to_pyrite_gamepad_axis(axis).map(|axis| gamepad::SubmitInput::Axis(id, axis, value))
+            }
+            _ => None,
+        };
+
+        if let Some(input) = input {
+            if event_sender.send(input).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn play_rumble(gilrs: &mut Gilrs, request: &RumbleRequest) -> Option<Effect> {
+    let (id, gamepad) = gilrs
+        .gamepads()
+        .find(|(id, _)| usize::from(*id) == request.gamepad.0)?;
+    if !gamepad.is_ff_supported() {
+        return None;
+    }
+
+    let scheduling = Replay {
+        play_for: Ticks::from_ms(request.duration.as_millis().min(u32::MAX as u128) as u32),
+        ..Default::default()
+    };
+    let effect = EffectBuilder::new()
+        .add_effect(BaseEffect {
+            kind: BaseEffectType::Strong {
+                magnitude: (request.strong * u16::MAX as f32) as u16,
+            },
+            scheduling,
+            ..Default::default()
+        })
+        .add_effect(BaseEffect {
+            kind: BaseEffectType::Weak {
+                magnitude: (request.weak * u16::MAX as f32) as u16,
+            },
+            scheduling,
+            ..Default::default()
+        })
+        .gamepads(&[id])
+        .finish(gilrs);
+
+    match effect.and_then(|effect| effect.play().map(|_| effect)) {
+        Ok(effect) => Some(effect),
+        Err(err) => {
+            log::warn!("Failed to rumble gamepad {}: {}", request.gamepad.0, err);
+            None
+        }
+    }
+}
+
+fn to_pyrite_gamepad_button(button: Button) -> Option<GamepadButton> {
+    match button {
+        Button::South => Some(GamepadButton::South),
+        Button::East => Some(GamepadButton::East),
+        Button::North => Some(GamepadButton::North),
+        Button::West => Some(GamepadButton::West),
+        Button::LeftTrigger => Some(GamepadButton::LeftBumper),
+        Button::RightTrigger => Some(GamepadButton::RightBumper),
+        Button::LeftTrigger2 => Some(GamepadButton::LeftTrigger),
+        Button::RightTrigger2 => Some(GamepadButton::RightTrigger),
+        Button::Select => Some(GamepadButton::Select),
+        Button::Start => Some(GamepadButton::Start),
+        Button::Mode => Some(GamepadButton::Mode),
+        Button::LeftThumb => Some(GamepadButton::LeftThumb),
+        Button::RightThumb => Some(GamepadButton::RightThumb),
+        Button::DPadUp => Some(GamepadButton::DPadUp),
+        Button::DPadDown => Some(GamepadButton::DPadDown),
+        Button::DPadLeft => Some(GamepadButton::DPadLeft),
+        Button::DPadRight => Some(GamepadButton::DPadRight),
+        _ => None,
+    }
+}
+
+fn to_pyrite_gamepad_axis(axis: Axis) -> Option<GamepadAxis> {
+    match axis {
+        Axis::LeftStickX => Some(GamepadAxis::LeftStickX),
+        Axis::LeftStickY => Some(GamepadAxis::LeftStickY),
+        Axis::RightStickX => Some(GamepadAxis::RightStickX),
+        Axis::RightStickY => Some(GamepadAxis::RightStickY),
+        _ => None,
+    }
+}
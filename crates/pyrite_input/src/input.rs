@@ -1,13 +1,32 @@
 use crate::{
+    gamepad::{self, GamepadAxis, GamepadButton, GamepadId, Gamepads},
     keyboard::{self, Keyboard},
+    mapper::{ActionMap, BindingSource},
     mouse::{self, Mouse},
+    recording::{InputPlayback, InputRecording},
+    text::{self, ImePreedit, TextInput},
 };
 use pyrite_app::resource::Resource;
+use serde::{Deserialize, Serialize};
+
+/// An input submitted to one of the devices of the [`Input`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Keyboard(keyboard::SubmitInput),
+    Mouse(mouse::SubmitInput),
+    Text(text::SubmitInput),
+    Gamepad(gamepad::SubmitInput),
+}
 
 #[derive(Resource)]
 pub struct Input {
     keyboard: Keyboard,
     mouse: Mouse,
+    text_input: TextInput,
+    gamepads: Gamepads,
+    action_map: ActionMap,
+    recording: Option<InputRecording>,
+    playback: Option<InputPlayback>,
 }
 
 impl Input {
@@ -15,12 +34,129 @@ impl Input {
         Self {
             keyboard: Keyboard::new(),
             mouse: Mouse::new(),
+            text_input: TextInput::new(),
+            gamepads: Gamepads::new(),
+            action_map: ActionMap::new(),
+            recording: None,
+            playback: None,
+        }
+    }
+
+    /// Submits the input to its device, recording it if a recording is in progress. Ignored
+    /// during playback so only the recorded inputs are applied.
+    pub fn submit_input(&mut self, event: InputEvent) {
+        if self.playback.is_some() {
+            return;
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.record(event.clone());
+        }
+
+        self.apply_input(event);
+    }
+
+    fn apply_input(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Keyboard(input) => self.keyboard.submit_input(input),
+            InputEvent::Mouse(input) => self.mouse.submit_input(input),
+            InputEvent::Text(input) => self.text_input.submit_input(input),
+            InputEvent::Gamepad(input) => self.gamepads.submit_input(input),
         }
     }
 
     pub fn clear_inputs(&mut self) {
         self.keyboard.clear_inputs();
         self.mouse.clear_inputs();
+        self.text_input.clear_inputs();
+        self.gamepads.clear_inputs();
+
+        if let Some(recording) = &mut self.recording {
+            recording.end_frame();
+        }
+        self.play_frame();
+    }
+
+    // Recording functions
+    /// Starts recording the inputs submitted through [`Input::submit_input`], with every call to
+    /// [`Input::clear_inputs`] ending a frame. Should be started right after clearing the inputs.
+    pub fn start_recording(&mut self) {
+        let mut initial_events = Vec::new();
+        initial_events.extend(
+            self.keyboard
+                .down_keys()
+                .map(|key| InputEvent::Keyboard(keyboard::SubmitInput::Pressed(key))),
+        );
+        let (x, y) = self.mouse.mouse_position();
+        initial_events.push(InputEvent::Mouse(mouse::SubmitInput::Position(x, y)));
+        initial_events.extend(
+            self.mouse
+                .down_buttons()
+                .map(|button| InputEvent::Mouse(mouse::SubmitInput::Pressed(button))),
+        );
+        for (id, gamepad) in self.gamepads.gamepads() {
+            initial_events.push(InputEvent::Gamepad(gamepad::SubmitInput::Connected(
+                id,
+                gamepad.name().to_string(),
+            )));
+            initial_events.extend(
+                gamepad
+                    .down_buttons()
+                    .map(|button| InputEvent::Gamepad(gamepad::SubmitInput::Pressed(id, button))),
+            );
+            initial_events.extend(gamepad.raw_axes().map(|(axis, value)| {
+                InputEvent::Gamepad(gamepad::SubmitInput::Axis(id, axis, value))
+            }));
+        }
+
+        self.recording = Some(InputRecording::new(initial_events));
+    }
+
+    /// Stops recording, returning the recording or `None` if there was none in progress.
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        self.recording.take().map(InputRecording::finish)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Resets the devices and replays the recording, one recorded frame each time the inputs
+    /// are cleared, starting immediately. Inputs submitted during playback are ignored.
+    pub fn start_playback(&mut self, recording: InputRecording) {
+        self.keyboard = Keyboard::new();
+        self.mouse = Mouse::new();
+        self.text_input = TextInput::new();
+        self.gamepads = Gamepads::new();
+        self.playback = Some(InputPlayback {
+            recording,
+            frame: 0,
+        });
+        self.play_frame();
+    }
+
+    pub fn stop_playback(&mut self) {
+        self.playback = None;
+    }
+
+    /// Whether a recording is being played back, stops once its last frame was played.
+    pub fn is_playing_back(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    fn play_frame(&mut self) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        if playback.frame >= playback.recording.frame_count() {
+            self.playback = None;
+            return;
+        }
+
+        let events = playback.recording.frame(playback.frame).to_vec();
+        playback.frame += 1;
+        for event in events {
+            self.apply_input(event);
+        }
     }
 
     // Keyboard functions
@@ -87,6 +223,112 @@ impl Input {
         self.mouse.mouse_delta()
     }
 
+    pub fn scroll_lines(&self) -> (f32, f32) {
+        self.mouse.scroll_lines()
+    }
+
+    pub fn scroll_pixels(&self) -> (f32, f32) {
+        self.mouse.scroll_pixels()
+    }
+
+    pub fn is_cursor_in_window(&self) -> bool {
+        self.mouse.is_cursor_in_window()
+    }
+
+    pub fn mouse_click_count(&self, button: mouse::Button) -> u32 {
+        self.mouse.click_count(button)
+    }
+
+    pub fn is_mouse_double_click(&self, button: mouse::Button) -> bool {
+        self.mouse.click_count(button) == 2
+    }
+
+    // Text functions
+    /// The text typed this frame, for text fields and chat boxes.
+    pub fn text(&self) -> &str {
+        self.text_input.text()
+    }
+
+    /// The text being composed with an input method editor, if any.
+    pub fn ime_preedit(&self) -> Option<&ImePreedit> {
+        self.text_input.preedit()
+    }
+
+    // Gamepad functions
+    pub fn is_gamepad_button_pressed(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepads.is_button_pressed(id, button)
+    }
+
+    pub fn is_gamepad_button_down(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepads.is_button_down(id, button)
+    }
+
+    pub fn is_gamepad_button_released(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepads.is_button_released(id, button)
+    }
+
+    pub fn gamepad_axis(&self, id: GamepadId, axis: GamepadAxis) -> f32 {
+        self.gamepads.axis(id, axis)
+    }
+
+    // Action functions
+    /// Whether an input bound to the action was pressed this frame.
+    pub fn action_pressed(&self, action: &str) -> bool {
+        let source = self.binding_source();
+        self.action_map
+            .action_bindings(action)
+            .iter()
+            .any(|binding| source.is_pressed(*binding))
+    }
+
+    pub fn action_down(&self, action: &str) -> bool {
+        let source = self.binding_source();
+        self.action_map
+            .action_bindings(action)
+            .iter()
+            .any(|binding| source.is_down(*binding))
+    }
+
+    pub fn action_released(&self, action: &str) -> bool {
+        let source = self.binding_source();
+        self.action_map
+            .action_bindings(action)
+            .iter()
+            .any(|binding| source.is_released(*binding))
+    }
+
+    /// The sum of the inputs bound to the axis, clamped to the range -1 to 1.
+    pub fn axis(&self, axis: &str) -> f32 {
+        let source = self.binding_source();
+        self.action_map
+            .axis_bindings(axis)
+            .iter()
+            .map(|binding| source.axis(*binding))
+            .sum::<f32>()
+            .clamp(-1.0, 1.0)
+    }
+
+    pub fn action_map(&self) -> &ActionMap {
+        &self.action_map
+    }
+
+    /// The action map to rebind actions at runtime.
+    pub fn action_map_mut(&mut self) -> &mut ActionMap {
+        &mut self.action_map
+    }
+
+    pub fn set_action_map(&mut self, action_map: ActionMap) {
+        self.action_map = action_map;
+    }
+
+    fn binding_source(&self) -> BindingSource<'_> {
+        BindingSource {
+            keyboard: &self.keyboard,
+            mouse: &self.mouse,
+            gamepads: &self.gamepads,
+        }
+    }
+
     pub fn keyboard(&self) -> &Keyboard {
         &self.keyboard
     }
@@ -102,4 +344,20 @@ impl Input {
     pub fn mouse_mut(&mut self) -> &mut Mouse {
         &mut self.mouse
     }
+
+    pub fn text_input(&self) -> &TextInput {
+        &self.text_input
+    }
+
+    pub fn text_input_mut(&mut self) -> &mut TextInput {
+        &mut self.text_input
+    }
+
+    pub fn gamepads(&self) -> &Gamepads {
+        &self.gamepads
+    }
+
+    pub fn gamepads_mut(&mut self) -> &mut Gamepads {
+        &mut self.gamepads
+    }
 }
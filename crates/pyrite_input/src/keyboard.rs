@@ -1,5 +1,7 @@
 use std::collections::HashSet;
 
+use serde::{Deserialize, Serialize};
+
 pub struct Keyboard {
     pressed_keys: HashSet<Key>,
     down_keys: HashSet<Key>,
@@ -79,15 +81,20 @@ impl Keyboard {
         }
         return true;
     }
+
+    pub(crate) fn down_keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.down_keys.iter().copied()
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SubmitInput {
     Pressed(Key),
     Repeated(Key),
     Released(Key),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Key {
     A,
     B,
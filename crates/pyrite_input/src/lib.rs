@@ -2,12 +2,20 @@ pub mod input;
 pub mod mapper;
 pub use input::*;
 
+pub mod gamepad;
+#[cfg(feature = "gilrs")]
+pub mod gilrs;
 pub mod keyboard;
 pub mod mouse;
+pub mod recording;
+pub mod text;
 
 pub mod prelude {
     pub use crate::{
-        input::Input,
+        gamepad::{GamepadAxis, GamepadButton, GamepadId},
+        input::{Input, InputEvent},
         keyboard::{Key, Keyboard, Modifier},
+        mapper::{ActionMap, AxisBinding, InputBinding},
+        text::ImePreedit,
     };
 }
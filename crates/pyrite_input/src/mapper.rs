@@ -0,0 +1,400 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gamepad::{GamepadAxis, GamepadButton, Gamepads},
+    keyboard::{Key, Keyboard},
+    mouse::{Button, Mouse},
+};
+
+/// An input an action can be bound to. Gamepad bindings respond to every connected gamepad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(Key),
+    MouseButton(Button),
+    GamepadButton(GamepadButton),
+}
+
+/// An input an axis can be bound to, producing a value in the range -1 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    Keys {
+        negative: Key,
+        positive: Key,
+    },
+    GamepadButtons {
+        negative: GamepadButton,
+        positive: GamepadButton,
+    },
+    GamepadAxis(GamepadAxis),
+}
+
+#[derive(Debug)]
+pub enum ActionMapError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl Display for ActionMapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionMapError::Io(err) => write!(f, "Failed to access the action map file: {}", err),
+            ActionMapError::Parse(err) => write!(f, "Failed to parse the action map: {}", err),
+            ActionMapError::Serialize(err) => {
+                write!(f, "Failed to serialize the action map: {}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ActionMapError {}
+
+/// Named actions and axes bound to keys, mouse buttons and gamepad inputs, so gameplay code can
+/// query `"jump"` instead of a key and players can rebind their controls.
+///
+/// ```ignore
+/// let mut action_map = ActionMap::new();
+/// action_map
+///     .bind_action("jump", InputBinding::Key(Key::Space))
+///     .bind_action("jump", InputBinding::GamepadButton(GamepadButton::South))
+///     .bind_axis(
+///         "move_x",
+///         AxisBinding::Keys {
+///             negative: Key::A,
+///             positive: Key::D,
+///         },
+///     )
+///     .bind_axis("move_x", AxisBinding::GamepadAxis(GamepadAxis::LeftStickX));
+/// input.set_action_map(action_map);
+///
+/// if input.action_pressed("jump") {
+///     velocity.y = JUMP_SPEED;
+/// }
+/// position.x += input.axis("move_x") * speed;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionMap {
+    actions: HashMap<String, Vec<InputBinding>>,
+    axes: HashMap<String, Vec<AxisBinding>>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads an action map saved with [`ActionMap::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ActionMapError> {
+        let contents = std::fs::read_to_string(path).map_err(ActionMapError::Io)?;
+        Self::from_ron(&contents)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ActionMapError> {
+        std::fs::write(path, self.to_ron()?).map_err(ActionMapError::Io)
+    }
+
+    pub fn from_ron(contents: &str) -> Result<Self, ActionMapError> {
+        ron::from_str(contents).map_err(ActionMapError::Parse)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ActionMapError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(ActionMapError::Serialize)
+    }
+
+    /// Adds a binding to the action, creating the action if it doesn't exist.
+    pub fn bind_action(&mut self, action: &str, binding: InputBinding) -> &mut Self {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+
+    pub fn unbind_action(&mut self, action: &str, binding: InputBinding) -> &mut Self {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|other| *other != binding);
+        }
+        self
+    }
+
+    /// Replaces `old` with `new`, keeping the position of the binding. Binds `new` if the action
+    /// wasn't bound to `old`.
+    pub fn rebind_action(
+        &mut self,
+        action: &str,
+        old: InputBinding,
+        new: InputBinding,
+    ) -> &mut Self {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        match bindings.iter().position(|binding| *binding == old) {
+            Some(index) => {
+                bindings[index] = new;
+                bindings.dedup();
+            }
+            None if !bindings.contains(&new) => bindings.push(new),
+            None => {}
+        }
+        self
+    }
+
+    pub fn action_bindings(&self, action: &str) -> &[InputBinding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) -> &mut Self {
+        let bindings = self.axes.entry(axis.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+
+    pub fn unbind_axis(&mut self, axis: &str, binding: AxisBinding) -> &mut Self {
+        if let Some(bindings) = self.axes.get_mut(axis) {
+            bindings.retain(|other| *other != binding);
+        }
+        self
+    }
+
+    /// Replaces `old` with `new`, keeping the position of the binding. Binds `new` if the axis
+    /// wasn't bound to `old`.
+    pub fn rebind_axis(&mut self, axis: &str, old: AxisBinding, new: AxisBinding) -> &mut Self {
+        let bindings = self.axes.entry(axis.to_string()).or_default();
+        match bindings.iter().position(|binding| *binding == old) {
+            Some(index) => {
+                bindings[index] = new;
+                bindings.dedup();
+            }
+            None if !bindings.contains(&new) => bindings.push(new),
+            None => {}
+        }
+        self
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn axes(&self) -> impl Iterator<Item = &str> {
+        self.axes.keys().map(String::as_str)
+    }
+}
+
+/// The devices bindings are evaluated against.
+pub(crate) struct BindingSource<'a> {
+    pub keyboard: &'a Keyboard,
+    pub mouse: &'a Mouse,
+    pub gamepads: &'a Gamepads,
+}
+
+impl BindingSource<'_> {
+    pub fn is_pressed(&self, binding: InputBinding) -> bool {
+        match binding {
+            InputBinding::Key(key) => self.keyboard.is_key_pressed(key),
+            InputBinding::MouseButton(button) => self.mouse.is_mouse_button_pressed(button),
+            InputBinding::GamepadButton(button) => self
+                .gamepads
+                .gamepads()
+                .any(|(_, gamepad)| gamepad.is_button_pressed(button)),
+        }
+    }
+
+    pub fn is_down(&self, binding: InputBinding) -> bool {
+        match binding {
+            InputBinding::Key(key) => self.keyboard.is_key_down(key),
+            InputBinding::MouseButton(button) => self.mouse.is_mouse_button_down(button),
+            InputBinding::GamepadButton(button) => self
+                .gamepads
+                .gamepads()
+                .any(|(_, gamepad)| gamepad.is_button_down(button)),
+        }
+    }
+
+    pub fn is_released(&self, binding: InputBinding) -> bool {
+        match binding {
+            InputBinding::Key(key) => self.keyboard.is_key_released(key),
+            InputBinding::MouseButton(button) => self.mouse.is_mouse_button_released(button),
+            InputBinding::GamepadButton(button) => self
+                .gamepads
+                .gamepads()
+                .any(|(_, gamepad)| gamepad.is_button_released(button)),
+        }
+    }
+
+    pub fn axis(&self, binding: AxisBinding) -> f32 {
+        match binding {
+            AxisBinding::Keys { negative, positive } => {
+                let value = |key| {
+                    if self.keyboard.is_key_down(key) {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                };
+                value(positive) - value(negative)
+            }
+            AxisBinding::GamepadButtons { negative, positive } => {
+                let value = |button| {
+                    let down = self
+                        .gamepads
+                        .gamepads()
+                        .any(|(_, gamepad)| gamepad.is_button_down(button));
+                    if down {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                };
+                value(positive) - value(negative)
+            }
+            // The gamepad pushed furthest wins, so idle gamepads don't cancel out the one in use.
+            AxisBinding::GamepadAxis(axis) => self
+                .gamepads
+                .gamepads()
+                .map(|(id, _)| self.gamepads.axis(id, axis))
+                .fold(0.0, |value: f32, other| {
+                    if other.abs() > value.abs() {
+                        other
+                    } else {
+                        value
+                    }
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gamepad, keyboard, mouse, Input, InputEvent};
+
+    fn action_map() -> ActionMap {
+        let mut action_map = ActionMap::new();
+        action_map
+            .bind_action("jump", InputBinding::Key(Key::Space))
+            .bind_action("jump", InputBinding::GamepadButton(GamepadButton::South))
+            .bind_action("fire", InputBinding::MouseButton(Button::Left))
+            .bind_axis(
+                "move_x",
+                AxisBinding::Keys {
+                    negative: Key::A,
+                    positive: Key::D,
+                },
+            )
+            .bind_axis("move_x", AxisBinding::GamepadAxis(GamepadAxis::LeftStickX));
+        action_map
+    }
+
+    #[test]
+    fn action_map_round_trips_through_ron() {
+        let action_map = action_map();
+        let ron = action_map.to_ron().unwrap();
+
+        assert_eq!(ActionMap::from_ron(&ron).unwrap(), action_map);
+        assert!(matches!(
+            ActionMap::from_ron("(actions: {\"jump\": [Key(NotAKey)]}, axes: {})"),
+            Err(ActionMapError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn rebinding_keeps_the_binding_position() {
+        let mut action_map = action_map();
+        action_map.rebind_action(
+            "jump",
+            InputBinding::Key(Key::Space),
+            InputBinding::Key(Key::W),
+        );
+
+        assert_eq!(
+            action_map.action_bindings("jump"),
+            &[
+                InputBinding::Key(Key::W),
+                InputBinding::GamepadButton(GamepadButton::South)
+            ]
+        );
+
+        action_map.unbind_action("jump", InputBinding::Key(Key::W));
+        assert_eq!(
+            action_map.action_bindings("jump"),
+            &[InputBinding::GamepadButton(GamepadButton::South)]
+        );
+        assert!(action_map.action_bindings("missing").is_empty());
+    }
+
+    #[test]
+    fn actions_resolve_through_any_binding() {
+        let mut input = Input::new();
+        input.set_action_map(action_map());
+        let gamepad_id = gamepad::GamepadId(0);
+        input.submit_input(InputEvent::Gamepad(gamepad::SubmitInput::Connected(
+            gamepad_id,
+            "Test gamepad".to_string(),
+        )));
+
+        input.submit_input(InputEvent::Keyboard(keyboard::SubmitInput::Pressed(
+            Key::Space,
+        )));
+        assert!(input.action_pressed("jump"));
+        assert!(input.action_down("jump"));
+        assert!(!input.action_down("fire"));
+
+        input.clear_inputs();
+        assert!(!input.action_pressed("jump"));
+        assert!(input.action_down("jump"));
+
+        input.submit_input(InputEvent::Keyboard(keyboard::SubmitInput::Released(
+            Key::Space,
+        )));
+        input.submit_input(InputEvent::Gamepad(gamepad::SubmitInput::Pressed(
+            gamepad_id,
+            GamepadButton::South,
+        )));
+        assert!(input.action_released("jump"));
+        assert!(input.action_down("jump"));
+
+        input.submit_input(InputEvent::Mouse(mouse::SubmitInput::Pressed(Button::Left)));
+        assert!(input.action_pressed("fire"));
+    }
+
+    #[test]
+    fn axes_sum_their_bindings() {
+        let mut input = Input::new();
+        input.set_action_map(action_map());
+        let gamepad_id = gamepad::GamepadId(0);
+        input.submit_input(InputEvent::Gamepad(gamepad::SubmitInput::Connected(
+            gamepad_id,
+            "Test gamepad".to_string(),
+        )));
+
+        input.submit_input(InputEvent::Keyboard(keyboard::SubmitInput::Pressed(Key::D)));
+        assert_eq!(input.axis("move_x"), 1.0);
+
+        input.submit_input(InputEvent::Keyboard(keyboard::SubmitInput::Pressed(Key::A)));
+        assert_eq!(input.axis("move_x"), 0.0);
+
+        input.submit_input(InputEvent::Gamepad(gamepad::SubmitInput::Axis(
+            gamepad_id,
+            GamepadAxis::LeftStickX,
+            -1.0,
+        )));
+        assert_eq!(input.axis("move_x"), -1.0);
+
+        input.submit_input(InputEvent::Keyboard(keyboard::SubmitInput::Released(
+            Key::A,
+        )));
+        assert_eq!(input.axis("move_x"), 0.0);
+        assert_eq!(input.axis("missing"), 0.0);
+    }
+}
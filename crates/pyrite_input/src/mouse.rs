@@ -1,4 +1,9 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
 
 pub struct Mouse {
     position: (f32, f32),
@@ -6,9 +11,31 @@ pub struct Mouse {
     pressed_buttons: HashSet<Button>,
     down_buttons: HashSet<Button>,
     released_buttons: HashSet<Button>,
+    scroll_lines: (f32, f32),
+    scroll_pixels: (f32, f32),
+    cursor_in_window: bool,
+    cursor_entered: bool,
+    cursor_left: bool,
+    clicks: HashMap<Button, Click>,
+    double_click_time: Duration,
+}
+
+/// The latest press of a button, continued by presses close to it in time and position.
+#[derive(Clone, Copy)]
+struct Click {
+    time: Instant,
+    position: (f32, f32),
+    count: u32,
+    /// Whether the press happened this frame.
+    pressed: bool,
 }
 
 impl Mouse {
+    /// The default longest time between the presses of a double click, matching most platforms.
+    pub const DEFAULT_DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+    /// The furthest in pixels the cursor can move between the presses of a double click.
+    pub const DOUBLE_CLICK_DISTANCE: f32 = 4.0;
+
     pub fn new() -> Self {
         Self {
             position: (0.0, 0.0),
@@ -16,6 +43,13 @@ impl Mouse {
             pressed_buttons: HashSet::new(),
             down_buttons: HashSet::new(),
             released_buttons: HashSet::new(),
+            scroll_lines: (0.0, 0.0),
+            scroll_pixels: (0.0, 0.0),
+            cursor_in_window: false,
+            cursor_entered: false,
+            cursor_left: false,
+            clicks: HashMap::new(),
+            double_click_time: Self::DEFAULT_DOUBLE_CLICK_TIME,
         }
     }
 
@@ -23,6 +57,13 @@ impl Mouse {
         self.pressed_buttons.clear();
         self.released_buttons.clear();
         self.delta = (0.0, 0.0);
+        self.scroll_lines = (0.0, 0.0);
+        self.scroll_pixels = (0.0, 0.0);
+        self.cursor_entered = false;
+        self.cursor_left = false;
+        for click in self.clicks.values_mut() {
+            click.pressed = false;
+        }
     }
 
     pub fn submit_input(&mut self, input: SubmitInput) {
@@ -30,6 +71,7 @@ impl Mouse {
             SubmitInput::Pressed(button) => {
                 self.pressed_buttons.insert(button);
                 self.down_buttons.insert(button);
+                self.register_click(button);
             }
             SubmitInput::Released(button) => {
                 self.released_buttons.insert(button);
@@ -41,9 +83,50 @@ impl Mouse {
             SubmitInput::Delta(x, y) => {
                 self.delta = (x, y);
             }
+            SubmitInput::Scroll(ScrollDelta::Lines(x, y)) => {
+                self.scroll_lines.0 += x;
+                self.scroll_lines.1 += y;
+            }
+            SubmitInput::Scroll(ScrollDelta::Pixels(x, y)) => {
+                self.scroll_pixels.0 += x;
+                self.scroll_pixels.1 += y;
+            }
+            SubmitInput::CursorEntered => {
+                self.cursor_in_window = true;
+                self.cursor_entered = true;
+            }
+            SubmitInput::CursorLeft => {
+                self.cursor_in_window = false;
+                self.cursor_left = true;
+            }
         }
     }
 
+    fn register_click(&mut self, button: Button) {
+        let now = Instant::now();
+        let position = self.position;
+        let count = match self.clicks.get(&button) {
+            Some(click)
+                if now.duration_since(click.time) <= self.double_click_time
+                    && (click.position.0 - position.0).abs() <= Self::DOUBLE_CLICK_DISTANCE
+                    && (click.position.1 - position.1).abs() <= Self::DOUBLE_CLICK_DISTANCE =>
+            {
+                click.count + 1
+            }
+            _ => 1,
+        };
+
+        self.clicks.insert(
+            button,
+            Click {
+                time: now,
+                position,
+                count,
+                pressed: true,
+            },
+        );
+    }
+
     pub fn is_mouse_button_pressed(&self, button: Button) -> bool {
         self.pressed_buttons.contains(&button)
     }
@@ -63,16 +146,72 @@ impl Mouse {
     pub fn mouse_delta(&self) -> (f32, f32) {
         self.delta
     }
+
+    /// The lines scrolled this frame by wheels scrolling in steps, positive y scrolls up.
+    pub fn scroll_lines(&self) -> (f32, f32) {
+        self.scroll_lines
+    }
+
+    /// The pixels scrolled this frame by touchpads and smooth scrolling wheels, positive y
+    /// scrolls up.
+    pub fn scroll_pixels(&self) -> (f32, f32) {
+        self.scroll_pixels
+    }
+
+    pub fn is_cursor_in_window(&self) -> bool {
+        self.cursor_in_window
+    }
+
+    /// Whether the cursor entered the window this frame.
+    pub fn cursor_entered(&self) -> bool {
+        self.cursor_entered
+    }
+
+    /// Whether the cursor left the window this frame.
+    pub fn cursor_left(&self) -> bool {
+        self.cursor_left
+    }
+
+    /// The number of consecutive clicks the press of the button this frame completed, 2 for a
+    /// double click and 3 for a triple click. Zero if the button wasn't pressed this frame.
+    pub fn click_count(&self, button: Button) -> u32 {
+        self.clicks
+            .get(&button)
+            .filter(|click| click.pressed)
+            .map_or(0, |click| click.count)
+    }
+
+    pub(crate) fn down_buttons(&self) -> impl Iterator<Item = Button> + '_ {
+        self.down_buttons.iter().copied()
+    }
+
+    pub fn double_click_time(&self) -> Duration {
+        self.double_click_time
+    }
+
+    pub fn set_double_click_time(&mut self, double_click_time: Duration) {
+        self.double_click_time = double_click_time;
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SubmitInput {
     Pressed(Button),
     Released(Button),
     Position(f32, f32),
     Delta(f32, f32),
+    Scroll(ScrollDelta),
+    CursorEntered,
+    CursorLeft,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScrollDelta {
+    Lines(f32, f32),
+    Pixels(f32, f32),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Button {
     Left,
     Right,
@@ -0,0 +1,195 @@
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::InputEvent;
+
+#[derive(Debug)]
+pub enum InputRecordingError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl Display for InputRecordingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputRecordingError::Io(err) => {
+                write!(f, "Failed to access the input recording file: {}", err)
+            }
+            InputRecordingError::Parse(err) => {
+                write!(f, "Failed to parse the input recording: {}", err)
+            }
+            InputRecordingError::Serialize(err) => {
+                write!(f, "Failed to serialize the input recording: {}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputRecordingError {}
+
+/// The input events submitted during each frame of a recording, made with
+/// [`crate::Input::start_recording`] and replayed with [`crate::Input::start_playback`].
+///
+/// A recording starts with events recreating the buttons held down and gamepads connected when
+/// it started, so it can be played back from a fresh state.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    frames: Vec<Vec<InputEvent>>,
+}
+
+impl InputRecording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, InputRecordingError> {
+        let contents = std::fs::read_to_string(path).map_err(InputRecordingError::Io)?;
+        Self::from_ron(&contents)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), InputRecordingError> {
+        std::fs::write(path, self.to_ron()?).map_err(InputRecordingError::Io)
+    }
+
+    pub fn from_ron(contents: &str) -> Result<Self, InputRecordingError> {
+        ron::from_str(contents).map_err(InputRecordingError::Parse)
+    }
+
+    /// Serializes the recording without pretty printing, since recordings get long quickly.
+    pub fn to_ron(&self) -> Result<String, InputRecordingError> {
+        ron::to_string(self).map_err(InputRecordingError::Serialize)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The events submitted during the frame.
+    pub fn frame(&self, frame: usize) -> &[InputEvent] {
+        self.frames.get(frame).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn new(initial_events: Vec<InputEvent>) -> Self {
+        Self {
+            frames: vec![initial_events],
+        }
+    }
+
+    pub(crate) fn record(&mut self, event: InputEvent) {
+        self.frames.last_mut().unwrap().push(event);
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.frames.push(Vec::new());
+    }
+
+    /// Drops the frame still being recorded if nothing happened during it.
+    pub(crate) fn finish(mut self) -> Self {
+        if self.frames.len() > 1 && self.frames.last().is_some_and(Vec::is_empty) {
+            self.frames.pop();
+        }
+        self
+    }
+}
+
+pub(crate) struct InputPlayback {
+    pub recording: InputRecording,
+    pub frame: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        keyboard::{self, Key},
+        mouse::{self, Button},
+        text, Input,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Snapshot {
+        w_down: bool,
+        space_pressed: bool,
+        space_down: bool,
+        space_released: bool,
+        left_pressed: bool,
+        mouse_position: (f32, f32),
+        text: String,
+    }
+
+    fn snapshot(input: &Input) -> Snapshot {
+        Snapshot {
+            w_down: input.is_key_down(Key::W),
+            space_pressed: input.is_key_pressed(Key::Space),
+            space_down: input.is_key_down(Key::Space),
+            space_released: input.is_key_released(Key::Space),
+            left_pressed: input.is_mouse_button_pressed(Button::Left),
+            mouse_position: input.mouse_position(),
+            text: input.text().to_string(),
+        }
+    }
+
+    fn frames() -> Vec<Vec<InputEvent>> {
+        vec![
+            vec![
+                InputEvent::Mouse(mouse::SubmitInput::Position(10.0, 20.0)),
+                InputEvent::Keyboard(keyboard::SubmitInput::Pressed(Key::Space)),
+            ],
+            vec![
+                InputEvent::Keyboard(keyboard::SubmitInput::Released(Key::Space)),
+                InputEvent::Text(text::SubmitInput::Text("hi".to_string())),
+            ],
+            vec![],
+            vec![
+                InputEvent::Mouse(mouse::SubmitInput::Pressed(Button::Left)),
+                InputEvent::Keyboard(keyboard::SubmitInput::Released(Key::W)),
+            ],
+        ]
+    }
+
+    #[test]
+    fn playback_recreates_the_recorded_input_states() {
+        let mut input = Input::new();
+        // Held down before the recording started, so it has to be part of its initial events.
+        input.submit_input(InputEvent::Keyboard(keyboard::SubmitInput::Pressed(Key::W)));
+        input.clear_inputs();
+
+        input.start_recording();
+        let mut recorded_snapshots = Vec::new();
+        for (index, events) in frames().into_iter().enumerate() {
+            if index > 0 {
+                input.clear_inputs();
+            }
+            for event in events {
+                input.submit_input(event);
+            }
+            recorded_snapshots.push(snapshot(&input));
+        }
+        let recording = input.stop_recording().unwrap();
+        assert_eq!(recording.frame_count(), 4);
+
+        let recording = InputRecording::from_ron(&recording.to_ron().unwrap()).unwrap();
+        let mut playback_input = Input::new();
+        playback_input.start_playback(recording);
+        let mut played_snapshots = Vec::new();
+        for index in 0..recorded_snapshots.len() {
+            if index > 0 {
+                playback_input.clear_inputs();
+            }
+            // Inputs submitted during playback are ignored.
+            playback_input.submit_input(InputEvent::Keyboard(keyboard::SubmitInput::Pressed(
+                Key::Space,
+            )));
+            played_snapshots.push(snapshot(&playback_input));
+        }
+
+        assert_eq!(played_snapshots, recorded_snapshots);
+        assert!(played_snapshots[0].w_down);
+        assert!(!played_snapshots[3].w_down);
+
+        assert!(playback_input.is_playing_back());
+        playback_input.clear_inputs();
+        assert!(!playback_input.is_playing_back());
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// The text typed during a frame, including text composed with an input method editor.
+pub struct TextInput {
+    text: String,
+    preedit: Option<ImePreedit>,
+}
+
+/// Text being composed with an input method editor which hasn't been committed yet, text fields
+/// usually show it underlined at the cursor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImePreedit {
+    pub text: String,
+    /// The byte range of the cursor or selection within the text, `None` if it's hidden.
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            preedit: None,
+        }
+    }
+
+    /// Clears the text typed this frame, the preedit text is kept until the composition changes.
+    pub fn clear_inputs(&mut self) {
+        self.text.clear();
+    }
+
+    pub fn submit_input(&mut self, input: SubmitInput) {
+        match input {
+            SubmitInput::Text(text) => {
+                // Control characters like backspace and enter are handled as keys instead.
+                self.text
+                    .extend(text.chars().filter(|character| !character.is_control()));
+            }
+            SubmitInput::Preedit(preedit) => {
+                self.preedit = preedit.filter(|preedit| !preedit.text.is_empty());
+            }
+            SubmitInput::Commit(text) => {
+                self.preedit = None;
+                self.text.push_str(&text);
+            }
+        }
+    }
+
+    /// The text typed this frame, in the order it was typed.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn preedit(&self) -> Option<&ImePreedit> {
+        self.preedit.as_ref()
+    }
+}
+
+impl Default for TextInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SubmitInput {
+    /// Text typed on the keyboard.
+    Text(String),
+    /// The composition of the input method editor changed, `None` once it was cancelled.
+    Preedit(Option<ImePreedit>),
+    /// The input method editor committed its composition.
+    Commit(String),
+}
@@ -0,0 +1,85 @@
+use glam::{Affine3A, Mat4, Vec3, Vec4};
+
+/// An axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box containing every point, `None` if there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, point| Self {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        }))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// The box containing this box after it's transformed.
+    pub fn transformed(&self, transform: &Affine3A) -> Self {
+        let center = transform.transform_point3(self.center());
+        let half_extents = self.half_extents();
+        let half_extents = transform.matrix3.x_axis.abs() * half_extents.x
+            + transform.matrix3.y_axis.abs() * half_extents.y
+            + transform.matrix3.z_axis.abs() * half_extents.z;
+
+        Self {
+            min: center - Vec3::from(half_extents),
+            max: center + Vec3::from(half_extents),
+        }
+    }
+}
+
+/// The planes enclosing the volume a camera sees, used to cull objects outside of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// The left, right, bottom, top, near and far planes, with normals pointing inwards.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a view projection matrix mapping depth to the 0 to 1 range, like
+    /// the [`crate::Projection`]s.
+    pub fn from_view_projection(view_projection: &Mat4) -> Self {
+        let row = |index| view_projection.row(index);
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(2),
+            row(3) - row(2),
+        ];
+
+        Self {
+            planes: planes.map(|plane| plane / plane.truncate().length()),
+        }
+    }
+
+    /// Whether any part of the box may be inside the frustum, boxes near the corners of the
+    /// frustum may be reported as visible.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let center = aabb.center();
+        let half_extents = aabb.half_extents();
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            let radius = half_extents.dot(normal.abs());
+            normal.dot(center) + plane.w >= -radius
+        })
+    }
+}
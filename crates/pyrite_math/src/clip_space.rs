@@ -0,0 +1,45 @@
+//! The clip space conventions of Vulkan, which every projection in this crate follows.
+//!
+//! NDC X points right and Y points down, both in the -1 to 1 range, and depth is in the 0 to 1
+//! range. Viewport positions are in pixels from the top left, so unlike OpenGL no Y flip is needed
+//! between NDC and the viewport once the projection flipped Y.
+
+use glam::{Mat4, Vec2};
+
+/// The depth NDC is clipped to.
+pub const NDC_DEPTH_RANGE: (f32, f32) = (0.0, 1.0);
+
+/// Flips the Y axis of a projection matrix, since Vulkan's clip space Y axis points down.
+pub fn flip_y(mut projection: Mat4) -> Mat4 {
+    projection.y_axis.y = -projection.y_axis.y;
+    projection
+}
+
+/// A right handed perspective projection mapping the near plane to depth 1 and the far plane to
+/// depth 0, with Y flipped for Vulkan.
+///
+/// Reversing depth spreads the float precision evenly over the distance, the depth test must use
+/// `GREATER` or `GREATER_OR_EQUAL` and the depth buffer be cleared to 0.
+pub fn perspective_reverse_z(fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> Mat4 {
+    flip_y(Mat4::perspective_rh(fov_y, aspect_ratio, far, near))
+}
+
+/// A right handed perspective projection without a far plane mapping the near plane to depth 1
+/// and infinity to depth 0, with Y flipped for Vulkan.
+pub fn perspective_infinite_reverse_z(fov_y: f32, aspect_ratio: f32, near: f32) -> Mat4 {
+    flip_y(Mat4::perspective_infinite_reverse_rh(
+        fov_y,
+        aspect_ratio,
+        near,
+    ))
+}
+
+/// Converts a position in NDC to pixels in a viewport starting at `offset` with the given size.
+pub fn ndc_to_viewport(ndc: Vec2, offset: Vec2, size: Vec2) -> Vec2 {
+    offset + (ndc * 0.5 + 0.5) * size
+}
+
+/// Converts a position in pixels in a viewport starting at `offset` with the given size to NDC.
+pub fn viewport_to_ndc(position: Vec2, offset: Vec2, size: Vec2) -> Vec2 {
+    (position - offset) / size * 2.0 - 1.0
+}
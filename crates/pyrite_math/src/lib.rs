@@ -0,0 +1,24 @@
+pub use glam;
+pub use glam::{
+    Affine2, Affine3A, BVec2, BVec3, BVec4, EulerRot, IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, Quat,
+    UVec2, UVec3, UVec4, Vec2, Vec3, Vec3A, Vec4,
+};
+
+pub mod bounds;
+pub mod clip_space;
+pub mod projection;
+pub mod transform;
+
+pub use bounds::*;
+pub use clip_space::*;
+pub use projection::*;
+pub use transform::*;
+
+pub mod prelude {
+    pub use crate::{
+        bounds::{Aabb, Frustum},
+        projection::{OrthographicProjection, PerspectiveProjection, Projection},
+        transform::{GlobalTransform, Transform},
+        Affine3A, EulerRot, IVec2, IVec3, Mat3, Mat4, Quat, UVec2, UVec3, Vec2, Vec3, Vec4,
+    };
+}
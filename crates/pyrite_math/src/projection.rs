@@ -0,0 +1,115 @@
+use glam::Mat4;
+
+use crate::{flip_y, perspective_reverse_z};
+
+/// A camera projection.
+///
+/// All projections map depth to the 0 to 1 range and flip the Y axis so the matrices can be used
+/// directly in Vulkan clip space.
+pub trait Projection {
+    fn compute_matrix(&self) -> Mat4;
+
+    /// Updates the projection for a new viewport size.
+    fn resize(&mut self, width: f32, height: f32);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerspectiveProjection {
+    /// The vertical field of view in radians.
+    pub fov_y: f32,
+    pub aspect_ratio: f32,
+    pub near: f32,
+    pub far: f32,
+    /// Maps the near plane to depth 1 and the far plane to depth 0, see
+    /// [`perspective_reverse_z`].
+    pub reverse_z: bool,
+}
+
+impl Default for PerspectiveProjection {
+    fn default() -> Self {
+        Self {
+            fov_y: std::f32::consts::FRAC_PI_4,
+            aspect_ratio: 1.0,
+            near: 0.1,
+            far: 1000.0,
+            reverse_z: false,
+        }
+    }
+}
+
+impl Projection for PerspectiveProjection {
+    fn compute_matrix(&self) -> Mat4 {
+        if self.reverse_z {
+            return perspective_reverse_z(self.fov_y, self.aspect_ratio, self.near, self.far);
+        }
+
+        flip_y(Mat4::perspective_rh(
+            self.fov_y,
+            self.aspect_ratio,
+            self.near,
+            self.far,
+        ))
+    }
+
+    fn resize(&mut self, width: f32, height: f32) {
+        self.aspect_ratio = width / height;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrthographicProjection {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for OrthographicProjection {
+    fn default() -> Self {
+        Self {
+            left: -1.0,
+            right: 1.0,
+            bottom: -1.0,
+            top: 1.0,
+            near: 0.0,
+            far: 1000.0,
+        }
+    }
+}
+
+impl OrthographicProjection {
+    /// A projection where one unit maps to one pixel with the origin at the top left.
+    pub fn from_screen_size(width: f32, height: f32) -> Self {
+        Self {
+            left: 0.0,
+            right: width,
+            bottom: height,
+            top: 0.0,
+            ..Default::default()
+        }
+    }
+}
+
+impl Projection for OrthographicProjection {
+    fn compute_matrix(&self) -> Mat4 {
+        flip_y(Mat4::orthographic_rh(
+            self.left,
+            self.right,
+            self.bottom,
+            self.top,
+            self.near,
+            self.far,
+        ))
+    }
+
+    /// Keeps the vertical extent and adjusts the horizontal extent to the new aspect ratio.
+    fn resize(&mut self, width: f32, height: f32) {
+        let center_x = (self.left + self.right) * 0.5;
+        let half_width = (self.top - self.bottom).abs() * (width / height) * 0.5;
+
+        self.left = center_x - half_width;
+        self.right = center_x + half_width;
+    }
+}
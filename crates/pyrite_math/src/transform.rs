@@ -0,0 +1,206 @@
+use glam::{Affine3A, Mat3, Mat4, Quat, Vec3};
+
+/// A local transform, relative to the parent transform if there is one.
+///
+/// Uses a right handed coordinate system where -Z is forward and +Y is up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Self {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self {
+            scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Rotates the transform so forward points at `target`.
+    pub fn looking_at(mut self, target: Vec3, up: Vec3) -> Self {
+        self.look_at(target, up);
+        self
+    }
+
+    /// Rotates the transform so forward points at `target`.
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let back = (self.translation - target).normalize_or_zero();
+        if back == Vec3::ZERO {
+            return;
+        }
+
+        let right = up.cross(back).normalize_or_zero();
+        if right == Vec3::ZERO {
+            return;
+        }
+        let up = back.cross(right);
+
+        self.rotation = Quat::from_mat3(&Mat3::from_cols(right, up, back));
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    pub fn translate(&mut self, translation: Vec3) {
+        self.translation += translation;
+    }
+
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.rotation = rotation * self.rotation;
+    }
+
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    pub fn compute_affine(&self) -> Affine3A {
+        Affine3A::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Combines this transform with a child transform, the result is the child's transform in
+    /// this transform's space.
+    pub fn mul_transform(&self, child: &Transform) -> Transform {
+        Transform {
+            translation: self.transform_point(child.translation),
+            rotation: self.rotation * child.rotation,
+            scale: self.scale * child.scale,
+        }
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.rotation * (self.scale * point) + self.translation
+    }
+}
+
+/// A transform in world space, computed by combining a [`Transform`] with all of its parents.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(Affine3A);
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl GlobalTransform {
+    pub const IDENTITY: Self = Self(Affine3A::IDENTITY);
+
+    pub fn from_affine(affine: Affine3A) -> Self {
+        Self(affine)
+    }
+
+    pub fn affine(&self) -> Affine3A {
+        self.0
+    }
+
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from(self.0)
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        self.0.translation.into()
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.0.transform_vector3(Vec3::NEG_Z).normalize_or_zero()
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.0.transform_vector3(Vec3::X).normalize_or_zero()
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.0.transform_vector3(Vec3::Y).normalize_or_zero()
+    }
+
+    /// Decomposes the global transform, this is lossy if the transform contains shear.
+    pub fn compute_transform(&self) -> Transform {
+        let (scale, rotation, translation) = self.0.to_scale_rotation_translation();
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Computes the global transform of a child with the given local transform.
+    pub fn mul_transform(&self, child: &Transform) -> GlobalTransform {
+        GlobalTransform(self.0 * child.compute_affine())
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.0.transform_point3(point)
+    }
+
+    /// The view matrix of a camera placed at this transform.
+    pub fn compute_view_matrix(&self) -> Mat4 {
+        Mat4::from(self.0.inverse())
+    }
+}
+
+impl From<Transform> for GlobalTransform {
+    fn from(transform: Transform) -> Self {
+        Self(transform.compute_affine())
+    }
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec3 in_position;
+layout(location = 1) in vec4 in_color;
+
+layout(push_constant) uniform Camera {
+    mat4 view_projection;
+} camera;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    gl_Position = camera.view_projection * vec4(in_position, 1.0);
+    out_color = in_color;
+}
@@ -0,0 +1,68 @@
+// The cascaded shadow maps of the directional light, bound by the forward renderer to set 2.
+//
+// Include with `GL_GOOGLE_include_directive` and shade with `shadow_visibility`, passing the
+// world position of the fragment and its depth along the camera's view direction.
+
+#define MAX_SHADOW_CASCADES 4
+
+layout(set = 2, binding = 0) uniform ShadowUniform {
+    mat4 light_view_projections[MAX_SHADOW_CASCADES];
+    // The view space distance each cascade ends at.
+    vec4 cascade_splits;
+    // The direction the light travels in.
+    vec4 light_direction;
+    uint cascade_count;
+    float depth_bias;
+    // The size of a texel of the shadow map in UV coordinates.
+    vec2 texel_size;
+} shadow;
+
+// The cascades side by side, from the closest to the farthest.
+layout(set = 2, binding = 1) uniform sampler2D shadow_map;
+
+// The cascade covering a fragment `view_depth` in front of the camera, `cascade_count` if it's
+// beyond the last one.
+uint shadow_cascade(float view_depth) {
+    for (uint i = 0; i < shadow.cascade_count; i++) {
+        if (view_depth < shadow.cascade_splits[i]) {
+            return i;
+        }
+    }
+    return shadow.cascade_count;
+}
+
+// 1 if `depth` is in front of the shadow map at `uv`, otherwise 0.
+float shadow_compare(vec2 uv, float depth) {
+    return depth - shadow.depth_bias <= texture(shadow_map, uv).r ? 1.0 : 0.0;
+}
+
+// The fraction of the light reaching a fragment, filtered over the 3x3 texels around it.
+// Fragments outside of every cascade are lit.
+float shadow_visibility(vec3 world_position, float view_depth) {
+    uint cascade = shadow_cascade(view_depth);
+    if (cascade >= shadow.cascade_count) {
+        return 1.0;
+    }
+
+    vec4 light_position = shadow.light_view_projections[cascade] * vec4(world_position, 1.0);
+    vec3 ndc = light_position.xyz / light_position.w;
+    vec2 cascade_uv = ndc.xy * 0.5 + 0.5;
+    if (any(lessThan(cascade_uv, vec2(0.0))) || any(greaterThan(cascade_uv, vec2(1.0)))
+        || ndc.z > 1.0) {
+        return 1.0;
+    }
+
+    // The filter is clamped to the cascade, so it never reads its neighbours.
+    vec2 cascade_texel_size = shadow.texel_size * vec2(float(shadow.cascade_count), 1.0);
+    vec2 min_uv = cascade_texel_size * 0.5;
+    vec2 max_uv = 1.0 - cascade_texel_size * 0.5;
+    float visibility = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 uv = clamp(cascade_uv + vec2(x, y) * cascade_texel_size, min_uv, max_uv);
+            uv.x = (float(cascade) + uv.x) / float(shadow.cascade_count);
+            visibility += shadow_compare(uv, ndc.z);
+        }
+    }
+    return visibility / 9.0;
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) in vec3 in_position;
+
+layout(push_constant) uniform Shadow {
+    mat4 light_model_view_projection;
+} shadow;
+
+void main() {
+    gl_Position = shadow.light_model_view_projection * vec4(in_position, 1.0);
+}
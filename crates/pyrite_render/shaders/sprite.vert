@@ -0,0 +1,29 @@
+#version 450
+
+// The quad is generated from the vertex index, one instance per sprite.
+layout(location = 0) in mat4 in_model;
+layout(location = 4) in vec4 in_uv_rect;
+layout(location = 5) in vec4 in_color;
+
+layout(push_constant) uniform Camera {
+    mat4 view_projection;
+} camera;
+
+layout(location = 0) out vec2 out_uv;
+layout(location = 1) out vec4 out_color;
+
+const vec2 CORNERS[6] = vec2[](
+    vec2(0.0, 0.0),
+    vec2(1.0, 0.0),
+    vec2(1.0, 1.0),
+    vec2(0.0, 0.0),
+    vec2(1.0, 1.0),
+    vec2(0.0, 1.0)
+);
+
+void main() {
+    vec2 corner = CORNERS[gl_VertexIndex];
+    gl_Position = camera.view_projection * in_model * vec4(corner - 0.5, 0.0, 1.0);
+    out_uv = mix(in_uv_rect.xy, in_uv_rect.zw, corner);
+    out_color = in_color;
+}
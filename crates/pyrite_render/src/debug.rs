@@ -0,0 +1,527 @@
+use ash::vk;
+use pyrite_app::{
+    plugin::Plugin,
+    resource::{Res, ResMut, Resource},
+    AppBuilder,
+};
+use pyrite_math::{Aabb, BVec3, Projection, Vec3, Vec4};
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator,
+    objects::{
+        Buffer, GraphicsPipeline, GraphicsPipelineCreateInfo, PipelineLayoutCreateInfo,
+        RenderingAttachment, RenderingInfo, Shader, TypedBuffer, TypedBufferCreateInfo,
+    },
+    Vulkan,
+};
+
+use crate::{
+    forward::{ForwardCamera, ForwardRenderer, FORWARD_RENDER_STAGE},
+    render_manager::RenderManager,
+};
+
+/// The stage the debug lines are drawn in, right after the [`FORWARD_RENDER_STAGE`].
+pub const DEBUG_DRAW_STAGE: &str = "debug_draw";
+
+/// The segments each sphere circle is drawn with.
+const SPHERE_SEGMENTS: usize = 32;
+
+/// Adds the [`DebugDraw`] and its renderer, see [`setup_debug_draw`].
+pub struct DebugDrawPlugin {
+    pub config: DebugDrawConfig,
+}
+
+impl Plugin for DebugDrawPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        setup_debug_draw(app_builder, &self.config);
+    }
+}
+
+/// Sets up the [`DebugDraw`] resource, its lines are drawn on top of the forward renderer's
+/// image every frame, which requires the forward renderer to be set up first.
+pub fn setup_debug_draw(app_builder: &mut AppBuilder, config: &DebugDrawConfig) {
+    let debug_renderer = DebugRenderer::new(
+        &app_builder.get_resource::<Vulkan>(),
+        &app_builder.get_resource::<ForwardRenderer>(),
+        &app_builder.get_resource::<RenderManager>(),
+        config,
+    );
+
+    app_builder
+        .add_resource(debug_renderer)
+        .add_resource(DebugDraw::default())
+        .add_stage_after(DEBUG_DRAW_STAGE, FORWARD_RENDER_STAGE)
+        .add_system_to_stage(DebugRenderer::render_system, DEBUG_DRAW_STAGE);
+}
+
+#[derive(Clone)]
+pub struct DebugDrawConfig {
+    /// The SPIR-V of the vertex shader, `shaders/debug_line.vert` in this crate reads the
+    /// [`DebugVertex`] attributes and the view projection push constant.
+    pub vertex_shader: Vec<u32>,
+    /// The SPIR-V of the fragment shader, `shaders/debug_line.frag` in this crate.
+    pub fragment_shader: Vec<u32>,
+}
+
+/// A vertex of a debug line, a `vec3` position at location 0 and a `vec4` color at location 1.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+struct DebugText {
+    position: Vec3,
+    text: String,
+    size: f32,
+    color: Vec4,
+}
+
+#[derive(Default)]
+struct DebugLines {
+    vertices: Vec<DebugVertex>,
+    texts: Vec<DebugText>,
+}
+
+/// Immediate mode lines for debugging, drawn for the frame they're added in and cleared after.
+///
+/// Shapes are tested against the depth of the scene unless [`DebugDraw::set_depth_test`]
+/// disabled it before adding them, in which case they're drawn over everything.
+#[derive(Resource)]
+pub struct DebugDraw {
+    depth_test: bool,
+    depth_tested: DebugLines,
+    overlay: DebugLines,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self {
+            depth_test: true,
+            depth_tested: DebugLines::default(),
+            overlay: DebugLines::default(),
+        }
+    }
+}
+
+impl DebugDraw {
+    /// Whether the shapes added after this are hidden behind the scene.
+    pub fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test;
+    }
+
+    pub fn depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    fn lines(&mut self) -> &mut DebugLines {
+        if self.depth_test {
+            &mut self.depth_tested
+        } else {
+            &mut self.overlay
+        }
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Vec4) {
+        let color = color.to_array();
+        self.lines().vertices.extend([
+            DebugVertex {
+                position: start.to_array(),
+                color,
+            },
+            DebugVertex {
+                position: end.to_array(),
+                color,
+            },
+        ]);
+    }
+
+    /// A line from `origin` to `origin + direction`.
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, color: Vec4) {
+        self.line(origin, origin + direction, color);
+    }
+
+    /// The 12 edges of the box.
+    pub fn aabb(&mut self, aabb: &Aabb, color: Vec4) {
+        let corner = |index: usize| {
+            let is_max = BVec3::new(index & 1 != 0, index & 2 != 0, index & 4 != 0);
+            Vec3::select(is_max, aabb.max, aabb.min)
+        };
+
+        // Every pair of corners differing in exactly one axis.
+        for start in 0..8 {
+            for axis in [1, 2, 4] {
+                if start & axis == 0 {
+                    self.line(corner(start), corner(start | axis), color);
+                }
+            }
+        }
+    }
+
+    /// A circle around each axis.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec4) {
+        for (u, v) in [(Vec3::X, Vec3::Y), (Vec3::Y, Vec3::Z), (Vec3::Z, Vec3::X)] {
+            let point = |segment: usize| {
+                let angle = segment as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::TAU;
+                center + (u * angle.cos() + v * angle.sin()) * radius
+            };
+            for segment in 0..SPHERE_SEGMENTS {
+                self.line(point(segment), point(segment + 1), color);
+            }
+        }
+    }
+
+    /// A grid on the XZ plane centered on `center`, with `cell_count` cells of `cell_size` along
+    /// each axis.
+    pub fn grid(&mut self, center: Vec3, cell_count: u32, cell_size: f32, color: Vec4) {
+        let half_size = cell_count as f32 * cell_size * 0.5;
+        for line in 0..=cell_count {
+            let offset = line as f32 * cell_size - half_size;
+            self.line(
+                center + Vec3::new(offset, 0.0, -half_size),
+                center + Vec3::new(offset, 0.0, half_size),
+                color,
+            );
+            self.line(
+                center + Vec3::new(-half_size, 0.0, offset),
+                center + Vec3::new(half_size, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    /// Text facing the camera, centered above `position` with its capitals `size` tall.
+    ///
+    /// The text is drawn with a segment font of digits, letters and a few symbols, lowercase
+    /// letters are drawn as capitals and other characters as spaces.
+    pub fn text_3d(&mut self, position: Vec3, text: impl Into<String>, size: f32, color: Vec4) {
+        self.lines().texts.push(DebugText {
+            position,
+            text: text.into(),
+            size,
+            color,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        [&self.depth_tested, &self.overlay]
+            .iter()
+            .all(|lines| lines.vertices.is_empty() && lines.texts.is_empty())
+    }
+}
+
+// The segments of the font, in a cell 1 wide and 2 tall with y pointing up.
+const TOP_LEFT: u16 = 1 << 0;
+const TOP_RIGHT: u16 = 1 << 1;
+const UPPER_RIGHT: u16 = 1 << 2;
+const LOWER_RIGHT: u16 = 1 << 3;
+const BOTTOM_RIGHT: u16 = 1 << 4;
+const BOTTOM_LEFT: u16 = 1 << 5;
+const LOWER_LEFT: u16 = 1 << 6;
+const UPPER_LEFT: u16 = 1 << 7;
+const MIDDLE_LEFT: u16 = 1 << 8;
+const MIDDLE_RIGHT: u16 = 1 << 9;
+const DIAGONAL_TOP_LEFT: u16 = 1 << 10;
+const UPPER_CENTER: u16 = 1 << 11;
+const DIAGONAL_TOP_RIGHT: u16 = 1 << 12;
+const DIAGONAL_BOTTOM_LEFT: u16 = 1 << 13;
+const LOWER_CENTER: u16 = 1 << 14;
+const DIAGONAL_BOTTOM_RIGHT: u16 = 1 << 15;
+
+const TOP: u16 = TOP_LEFT | TOP_RIGHT;
+const BOTTOM: u16 = BOTTOM_LEFT | BOTTOM_RIGHT;
+const MIDDLE: u16 = MIDDLE_LEFT | MIDDLE_RIGHT;
+const LEFT: u16 = UPPER_LEFT | LOWER_LEFT;
+const RIGHT: u16 = UPPER_RIGHT | LOWER_RIGHT;
+const CENTER: u16 = UPPER_CENTER | LOWER_CENTER;
+
+/// The start and end of each segment bit, in bit order.
+const SEGMENT_LINES: [[(f32, f32); 2]; 16] = [
+    [(0.0, 2.0), (0.5, 2.0)],
+    [(0.5, 2.0), (1.0, 2.0)],
+    [(1.0, 2.0), (1.0, 1.0)],
+    [(1.0, 1.0), (1.0, 0.0)],
+    [(0.5, 0.0), (1.0, 0.0)],
+    [(0.0, 0.0), (0.5, 0.0)],
+    [(0.0, 1.0), (0.0, 0.0)],
+    [(0.0, 2.0), (0.0, 1.0)],
+    [(0.0, 1.0), (0.5, 1.0)],
+    [(0.5, 1.0), (1.0, 1.0)],
+    [(0.0, 2.0), (0.5, 1.0)],
+    [(0.5, 2.0), (0.5, 1.0)],
+    [(1.0, 2.0), (0.5, 1.0)],
+    [(0.5, 1.0), (0.0, 0.0)],
+    [(0.5, 1.0), (0.5, 0.0)],
+    [(0.5, 1.0), (1.0, 0.0)],
+];
+
+fn segments(c: char) -> u16 {
+    match c.to_ascii_uppercase() {
+        '0' => TOP | RIGHT | BOTTOM | LEFT | DIAGONAL_TOP_RIGHT | DIAGONAL_BOTTOM_LEFT,
+        '1' => RIGHT | DIAGONAL_TOP_RIGHT,
+        '2' => TOP | UPPER_RIGHT | MIDDLE | LOWER_LEFT | BOTTOM,
+        '3' => TOP | RIGHT | MIDDLE_RIGHT | BOTTOM,
+        '4' => UPPER_LEFT | MIDDLE | RIGHT,
+        '5' => TOP | UPPER_LEFT | MIDDLE | LOWER_RIGHT | BOTTOM,
+        '6' => TOP | LEFT | MIDDLE | LOWER_RIGHT | BOTTOM,
+        '7' => TOP | RIGHT,
+        '8' => TOP | RIGHT | BOTTOM | LEFT | MIDDLE,
+        '9' => TOP | UPPER_LEFT | MIDDLE | RIGHT | BOTTOM,
+        'A' => TOP | LEFT | RIGHT | MIDDLE,
+        'B' => TOP | RIGHT | BOTTOM | CENTER | MIDDLE_RIGHT,
+        'C' => TOP | LEFT | BOTTOM,
+        'D' => TOP | RIGHT | BOTTOM | CENTER,
+        'E' => TOP | LEFT | BOTTOM | MIDDLE_LEFT,
+        'F' => TOP | LEFT | MIDDLE_LEFT,
+        'G' => TOP | LEFT | BOTTOM | LOWER_RIGHT | MIDDLE_RIGHT,
+        'H' => LEFT | RIGHT | MIDDLE,
+        'I' => TOP | CENTER | BOTTOM,
+        'J' => RIGHT | BOTTOM | LOWER_LEFT,
+        'K' => LEFT | MIDDLE_LEFT | DIAGONAL_TOP_RIGHT | DIAGONAL_BOTTOM_RIGHT,
+        'L' => LEFT | BOTTOM,
+        'M' => LEFT | RIGHT | DIAGONAL_TOP_LEFT | DIAGONAL_TOP_RIGHT,
+        'N' => LEFT | RIGHT | DIAGONAL_TOP_LEFT | DIAGONAL_BOTTOM_RIGHT,
+        'O' => TOP | RIGHT | BOTTOM | LEFT,
+        'P' => TOP | LEFT | UPPER_RIGHT | MIDDLE,
+        'Q' => TOP | RIGHT | BOTTOM | LEFT | DIAGONAL_BOTTOM_RIGHT,
+        'R' => TOP | LEFT | UPPER_RIGHT | MIDDLE | DIAGONAL_BOTTOM_RIGHT,
+        'S' => TOP | UPPER_LEFT | MIDDLE | LOWER_RIGHT | BOTTOM,
+        'T' => TOP | CENTER,
+        'U' => LEFT | RIGHT | BOTTOM,
+        'V' => LEFT | DIAGONAL_BOTTOM_LEFT | DIAGONAL_TOP_RIGHT,
+        'W' => LEFT | RIGHT | DIAGONAL_BOTTOM_LEFT | DIAGONAL_BOTTOM_RIGHT,
+        'X' => {
+            DIAGONAL_TOP_LEFT | DIAGONAL_TOP_RIGHT | DIAGONAL_BOTTOM_LEFT | DIAGONAL_BOTTOM_RIGHT
+        }
+        'Y' => DIAGONAL_TOP_LEFT | DIAGONAL_TOP_RIGHT | LOWER_CENTER,
+        'Z' => TOP | DIAGONAL_TOP_RIGHT | DIAGONAL_BOTTOM_LEFT | BOTTOM,
+        '-' => MIDDLE,
+        '+' => MIDDLE | CENTER,
+        '=' => MIDDLE | BOTTOM,
+        '_' => BOTTOM,
+        '|' => CENTER,
+        '/' => DIAGONAL_TOP_RIGHT | DIAGONAL_BOTTOM_LEFT,
+        '\\' => DIAGONAL_TOP_LEFT | DIAGONAL_BOTTOM_RIGHT,
+        '*' => {
+            MIDDLE
+                | CENTER
+                | DIAGONAL_TOP_LEFT
+                | DIAGONAL_TOP_RIGHT
+                | DIAGONAL_BOTTOM_LEFT
+                | DIAGONAL_BOTTOM_RIGHT
+        }
+        '<' | '(' => DIAGONAL_TOP_RIGHT | DIAGONAL_BOTTOM_RIGHT,
+        '>' | ')' => DIAGONAL_TOP_LEFT | DIAGONAL_BOTTOM_LEFT,
+        '[' => TOP_RIGHT | CENTER | BOTTOM_RIGHT,
+        ']' => TOP_LEFT | CENTER | BOTTOM_LEFT,
+        '.' | ',' => LOWER_CENTER,
+        _ => 0,
+    }
+}
+
+/// Adds the lines of a text facing along the camera's `right` and `up` axes.
+fn text_vertices(text: &DebugText, right: Vec3, up: Vec3, vertices: &mut Vec<DebugVertex>) {
+    let scale = text.size * 0.5;
+    let advance = 1.5;
+    let line_height = 3.0;
+    let color = text.color.to_array();
+
+    for (line_index, line) in text.text.lines().enumerate() {
+        let width = line.chars().count() as f32 * advance - (advance - 1.0);
+        let origin = text.position
+            - right * (width * 0.5 * scale)
+            - up * (line_index as f32 * line_height * scale);
+
+        for (index, c) in line.chars().enumerate() {
+            let segments = segments(c);
+            let x = index as f32 * advance;
+            for (bit, [start, end]) in SEGMENT_LINES.iter().enumerate() {
+                if segments & (1 << bit) == 0 {
+                    continue;
+                }
+
+                for &(point_x, point_y) in [start, end] {
+                    let position = origin + (right * (x + point_x) + up * point_y) * scale;
+                    vertices.push(DebugVertex {
+                        position: position.to_array(),
+                        color,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct DebugRenderer {
+    depth_tested_pipeline: GraphicsPipeline,
+    overlay_pipeline: GraphicsPipeline,
+    vertex_buffers: Vec<Option<TypedBuffer<DebugVertex>>>,
+}
+
+impl DebugRenderer {
+    fn new(
+        vulkan: &Vulkan,
+        forward_renderer: &ForwardRenderer,
+        render_manager: &RenderManager,
+        config: &DebugDrawConfig,
+    ) -> Self {
+        let vertex_shader = Shader::new(vulkan, &config.vertex_shader);
+        let fragment_shader = Shader::new(vulkan, &config.fragment_shader);
+        let create_pipeline = |depth_compare_op, name: &str| {
+            GraphicsPipeline::new(
+                vulkan,
+                GraphicsPipelineCreateInfo {
+                    vertex_shader: &vertex_shader,
+                    vertex_entry_point: "main".to_string(),
+                    fragment_shader: &fragment_shader,
+                    fragment_entry_point: "main".to_string(),
+                    pipeline_layout_info: PipelineLayoutCreateInfo::default(),
+                    vertex_bindings: Vec::new(),
+                    vertex_attributes: Vec::new(),
+                    topology: vk::PrimitiveTopology::LINE_LIST,
+                    polygon_mode: vk::PolygonMode::FILL,
+                    cull_mode: vk::CullModeFlags::NONE,
+                    front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+                    alpha_blending: true,
+                    depth_compare_op,
+                    rendering_formats: forward_renderer.rendering_formats(),
+                    name: Some(name.to_string()),
+                },
+            )
+        };
+
+        Self {
+            depth_tested_pipeline: create_pipeline(
+                render_manager.depth_compare_op(),
+                "debug lines depth tested",
+            ),
+            overlay_pipeline: create_pipeline(vk::CompareOp::ALWAYS, "debug lines overlay"),
+            vertex_buffers: (0..render_manager.frames_in_flight())
+                .map(|_| None)
+                .collect(),
+        }
+    }
+
+    pub fn render_system(
+        mut debug_renderer: ResMut<DebugRenderer>,
+        mut debug_draw: ResMut<DebugDraw>,
+        mut render_manager: ResMut<RenderManager>,
+        forward_renderer: Res<ForwardRenderer>,
+        camera: Res<ForwardCamera>,
+        vulkan: Res<Vulkan>,
+        mut vulkan_allocator: ResMut<VulkanMemoryAllocator>,
+    ) {
+        pyrite_util::profile_scope!("DebugRenderer::render_system");
+
+        let depth_tested = std::mem::take(&mut debug_draw.depth_tested);
+        let overlay = std::mem::take(&mut debug_draw.overlay);
+        let Some(attachments) = forward_renderer.attachments() else {
+            return;
+        };
+
+        let right = camera.transform.right();
+        let up = camera.transform.up();
+        let mut vertices = Vec::new();
+        let mut ranges = Vec::new();
+        for lines in [depth_tested, overlay] {
+            let first_vertex = vertices.len() as u32;
+            vertices.extend(lines.vertices);
+            for text in &lines.texts {
+                text_vertices(text, right, up, &mut vertices);
+            }
+            ranges.push(first_vertex..vertices.len() as u32);
+        }
+        if vertices.is_empty() {
+            return;
+        }
+
+        let frame_index = render_manager.frame_index();
+        debug_renderer.write_vertices(&vulkan, &mut vulkan_allocator, frame_index, &vertices);
+
+        let command_buffer = render_manager.command_buffer_mut();
+        let render_area = vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent: attachments.extent,
+        };
+        command_buffer.begin_label("debug lines", [1.0, 1.0, 1.0, 1.0]);
+        command_buffer.begin_rendering(RenderingInfo {
+            render_area,
+            color_attachments: vec![RenderingAttachment {
+                image: &attachments.color,
+                load_op: vk::AttachmentLoadOp::LOAD,
+                store_op: vk::AttachmentStoreOp::STORE,
+                clear_value: vk::ClearValue::default(),
+                resolve_image: None,
+            }],
+            depth_attachment: Some(RenderingAttachment {
+                image: &*attachments.depth,
+                load_op: vk::AttachmentLoadOp::LOAD,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                clear_value: vk::ClearValue::default(),
+                resolve_image: None,
+            }),
+            secondary_command_buffers: false,
+        });
+
+        let view_projection =
+            camera.projection.compute_matrix() * camera.transform.compute_view_matrix();
+        let vertex_buffer = debug_renderer.vertex_buffers[frame_index].as_ref().unwrap();
+        for (pipeline, range) in [
+            &debug_renderer.depth_tested_pipeline,
+            &debug_renderer.overlay_pipeline,
+        ]
+        .into_iter()
+        .zip(ranges)
+        {
+            if range.is_empty() {
+                continue;
+            }
+
+            command_buffer.bind_graphics_pipeline(pipeline);
+            command_buffer.set_viewport_and_scissor(render_area);
+            command_buffer.push_constants(
+                vk::PipelineBindPoint::GRAPHICS,
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                &view_projection,
+            );
+            command_buffer.bind_vertex_buffers(0, &[(vertex_buffer as &dyn Buffer, 0)]);
+            command_buffer.draw(range.len() as u32, 1, range.start, 0);
+        }
+
+        command_buffer.end_rendering();
+        command_buffer.transition_image(&attachments.color, vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+        command_buffer.end_label();
+    }
+
+    /// Writes the vertices to the frame's vertex buffer, growing it to the next power of two if
+    /// they don't fit.
+    fn write_vertices(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        frame_index: usize,
+        vertices: &[DebugVertex],
+    ) {
+        let vertex_buffer = &mut self.vertex_buffers[frame_index];
+        if vertex_buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.len() < vertices.len())
+        {
+            *vertex_buffer = Some(TypedBuffer::new(
+                vulkan,
+                vulkan_allocator,
+                &TypedBufferCreateInfo {
+                    len: vertices.len().next_power_of_two(),
+                    usage: vk::BufferUsageFlags::VERTEX_BUFFER,
+                    memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                        | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    name: Some("debug lines".to_string()),
+                },
+            ));
+        }
+
+        vertex_buffer.as_mut().unwrap().write_slice(0, vertices);
+    }
+}
@@ -0,0 +1,608 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use ash::vk;
+use pyrite_app::{
+    plugin::Plugin,
+    resource::{Res, ResMut, Resource},
+    AppBuilder,
+};
+use pyrite_math::{Aabb, Frustum, GlobalTransform, Mat4, PerspectiveProjection, Projection, Vec4};
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator,
+    objects::{
+        CommandBuffer, DescriptorSetHandle, DescriptorSetLayout, DescriptorSetPool,
+        GraphicsPipeline, Mesh, OwnedImage, RenderingAttachment, RenderingFormats, RenderingInfo,
+        TypedBuffer, TypedBufferCreateInfo, Vertex,
+    },
+    Vulkan,
+};
+
+use crate::{
+    material::Material,
+    render_manager::{FrameConfig, RenderManager, PRE_RENDER_STAGE},
+    render_target::create_render_target,
+    shadow::{ShadowRenderer, SHADOW_SET},
+};
+
+/// The stage the forward renderer records its passes in, right after the [`RenderManager`]
+/// begins the frame.
+pub const FORWARD_RENDER_STAGE: &str = "forward_render";
+
+/// The descriptor set holding the [`CameraUniform`] at binding 0, the [`Material`] set comes
+/// before it.
+pub const CAMERA_SET: u32 = 1;
+
+/// Adds the [`ForwardRenderer`] with its [`DrawList`] and [`ForwardCamera`], see
+/// [`setup_forward_renderer`].
+pub struct ForwardRendererPlugin {
+    pub config: ForwardRendererConfig,
+}
+
+impl Plugin for ForwardRendererPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        setup_forward_renderer(app_builder, &self.config);
+    }
+}
+
+/// Sets up a forward renderer drawing the [`DrawList`] from the [`ForwardCamera`] every frame,
+/// requires the [`RenderManager`] resource to be added first.
+///
+/// Draws outside the camera's frustum are culled. Opaque materials are drawn front to back,
+/// followed by the alpha blended materials back to front. The image is handed to the
+/// [`RenderManager`] as the frame's backbuffer, multisampled with the render manager's sample
+/// count.
+///
+/// Material shaders read the [`CameraUniform`] from [`CAMERA_SET`] and the model matrix of the
+/// draw from the push constants at offset 0. Material pipelines must be created with the
+/// [`ForwardRenderer::rendering_formats`] and test depth with
+/// [`RenderManager::depth_compare_op`]. Once shadows are set up, materials whose pipeline layout
+/// has the [`SHADOW_SET`] read them from it.
+pub fn setup_forward_renderer(app_builder: &mut AppBuilder, config: &ForwardRendererConfig) {
+    let (frames_in_flight, samples, depth_format) = {
+        let render_manager = app_builder.get_resource::<RenderManager>();
+        let depth_format = render_manager.depth_format().expect(
+            "The forward renderer draws to the RenderManager's depth image, which was disabled.",
+        );
+        (
+            render_manager.frames_in_flight() as usize,
+            render_manager.samples(),
+            depth_format,
+        )
+    };
+    let forward_renderer = ForwardRenderer::new(
+        &app_builder.get_resource::<Vulkan>(),
+        &mut app_builder.get_resource_mut::<VulkanMemoryAllocator>(),
+        frames_in_flight,
+        samples,
+        depth_format,
+        config.clone(),
+    );
+
+    app_builder
+        .add_resource(forward_renderer)
+        .add_resource(DrawList::default())
+        .add_resource(ForwardCamera::default())
+        .add_stage_after(FORWARD_RENDER_STAGE, PRE_RENDER_STAGE)
+        .add_system_to_stage(ForwardRenderer::render_system, FORWARD_RENDER_STAGE);
+}
+
+#[derive(Clone)]
+pub struct ForwardRendererConfig {
+    pub color_format: vk::Format,
+    pub clear_color: [f32; 4],
+}
+
+impl Default for ForwardRendererConfig {
+    fn default() -> Self {
+        Self {
+            color_format: vk::Format::R8G8B8A8_SRGB,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+/// The camera the forward renderer draws from, its projection follows the swapchain size.
+#[derive(Resource, Default)]
+pub struct ForwardCamera {
+    pub transform: GlobalTransform,
+    pub projection: PerspectiveProjection,
+}
+
+/// The camera matrices, laid out like a std140 uniform block of 3 `mat4`s and a `vec4`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub view_projection: Mat4,
+    /// The world position of the camera, `w` is 1.
+    pub position: Vec4,
+}
+
+/// A mesh which can be drawn by the forward renderer regardless of its vertex type.
+pub trait DrawMesh: Send + Sync {
+    fn bind(&self, command_buffer: &mut CommandBuffer);
+
+    fn draw(&self, command_buffer: &mut CommandBuffer, instance_count: u32);
+
+    /// The vertex buffer binding at binding 0, used to build pipelines for the mesh's layout.
+    fn vertex_binding(&self) -> vk::VertexInputBindingDescription;
+
+    fn vertex_attributes(&self) -> Vec<vk::VertexInputAttributeDescription>;
+}
+
+impl<V: Vertex + Send + Sync> DrawMesh for Mesh<V> {
+    fn bind(&self, command_buffer: &mut CommandBuffer) {
+        Mesh::bind(self, command_buffer);
+    }
+
+    fn draw(&self, command_buffer: &mut CommandBuffer, instance_count: u32) {
+        Mesh::draw(self, command_buffer, instance_count);
+    }
+
+    fn vertex_binding(&self) -> vk::VertexInputBindingDescription {
+        V::binding(0)
+    }
+
+    fn vertex_attributes(&self) -> Vec<vk::VertexInputAttributeDescription> {
+        V::attributes(0)
+    }
+}
+
+/// A mesh with the local bounds it's culled by.
+pub struct RenderMesh {
+    pub(crate) mesh: Box<dyn DrawMesh>,
+    bounds: Aabb,
+}
+
+impl RenderMesh {
+    pub fn new(mesh: impl DrawMesh + 'static, bounds: Aabb) -> Self {
+        Self {
+            mesh: Box::new(mesh),
+            bounds,
+        }
+    }
+
+    pub fn bounds(&self) -> &Aabb {
+        &self.bounds
+    }
+}
+
+/// A material which can be drawn by the forward renderer regardless of its parameters.
+pub trait DrawMaterial: Send {
+    /// Binds the pipeline and material descriptor set for the frame.
+    fn bind(&mut self, vulkan: &Vulkan, command_buffer: &mut CommandBuffer, frame_index: usize);
+
+    fn pipeline(&self) -> &GraphicsPipeline;
+
+    fn is_alpha_blended(&self) -> bool;
+}
+
+impl<P: bytemuck::Pod + Send, const N: usize> DrawMaterial for Material<P, N> {
+    fn bind(&mut self, vulkan: &Vulkan, command_buffer: &mut CommandBuffer, frame_index: usize) {
+        Material::bind(self, vulkan, command_buffer, frame_index);
+    }
+
+    fn pipeline(&self) -> &GraphicsPipeline {
+        Material::pipeline(self)
+    }
+
+    fn is_alpha_blended(&self) -> bool {
+        Material::is_alpha_blended(self)
+    }
+}
+
+pub(crate) struct Draw {
+    pub(crate) mesh: Arc<RenderMesh>,
+    material: Arc<Mutex<dyn DrawMaterial>>,
+    pub(crate) transform: GlobalTransform,
+}
+
+/// The draws of the current frame, systems submit to it every frame before the
+/// [`FORWARD_RENDER_STAGE`], which clears it.
+#[derive(Resource, Default)]
+pub struct DrawList {
+    draws: Vec<Draw>,
+}
+
+impl DrawList {
+    pub fn submit(
+        &mut self,
+        mesh: &Arc<RenderMesh>,
+        material: &Arc<Mutex<dyn DrawMaterial>>,
+        transform: GlobalTransform,
+    ) {
+        self.draws.push(Draw {
+            mesh: mesh.clone(),
+            material: material.clone(),
+            transform,
+        });
+    }
+
+    pub(crate) fn draws(&self) -> &[Draw] {
+        &self.draws
+    }
+
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+}
+
+pub(crate) struct Attachments {
+    pub(crate) color: OwnedImage,
+    /// The [`RenderManager`]'s depth image.
+    pub(crate) depth: Arc<OwnedImage>,
+    pub(crate) extent: vk::Extent2D,
+}
+
+#[derive(Resource)]
+pub struct ForwardRenderer {
+    config: ForwardRendererConfig,
+    /// The sample count of the attachments, following the [`RenderManager`]'s backbuffer.
+    samples: vk::SampleCountFlags,
+    /// The format of the [`RenderManager`]'s depth image.
+    depth_format: vk::Format,
+    attachments: Option<Attachments>,
+    camera_buffers: Vec<TypedBuffer<CameraUniform>>,
+    descriptor_set_pool: DescriptorSetPool,
+    /// The camera descriptor sets of each frame for every material set layout drawn so far,
+    /// holding on to the layout so its handle isn't reused.
+    camera_sets: HashMap<vk::DescriptorSetLayout, (DescriptorSetLayout, Vec<DescriptorSetHandle>)>,
+    /// Set up by [`crate::shadow::setup_shadows`].
+    shadow_renderer: Option<ShadowRenderer>,
+    /// The shadow descriptor sets of each frame for every material set layout drawn so far, like
+    /// the camera sets.
+    shadow_sets: HashMap<vk::DescriptorSetLayout, (DescriptorSetLayout, Vec<DescriptorSetHandle>)>,
+}
+
+impl ForwardRenderer {
+    fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        frames_in_flight: usize,
+        samples: vk::SampleCountFlags,
+        depth_format: vk::Format,
+        config: ForwardRendererConfig,
+    ) -> Self {
+        let camera_buffers = (0..frames_in_flight)
+            .map(|_| {
+                TypedBuffer::new(
+                    vulkan,
+                    vulkan_allocator,
+                    &TypedBufferCreateInfo {
+                        len: 1,
+                        usage: vk::BufferUsageFlags::UNIFORM_BUFFER,
+                        memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                            | vk::MemoryPropertyFlags::HOST_COHERENT,
+                        name: Some("forward camera".to_string()),
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            config,
+            samples,
+            depth_format,
+            attachments: None,
+            camera_buffers,
+            descriptor_set_pool: DescriptorSetPool::new(vulkan),
+            camera_sets: HashMap::new(),
+            shadow_renderer: None,
+            shadow_sets: HashMap::new(),
+        }
+    }
+
+    /// The attachments of the last frame rendered, the color attachment is in the transfer source
+    /// layout.
+    pub(crate) fn attachments(&self) -> Option<&Attachments> {
+        self.attachments.as_ref()
+    }
+
+    pub(crate) fn set_shadow_renderer(&mut self, shadow_renderer: ShadowRenderer) {
+        self.shadow_renderer = Some(shadow_renderer);
+    }
+
+    pub(crate) fn shadow_renderer_mut(&mut self) -> Option<&mut ShadowRenderer> {
+        self.shadow_renderer.as_mut()
+    }
+
+    /// The formats material pipelines drawn by the renderer must be created with.
+    pub fn rendering_formats(&self) -> RenderingFormats {
+        RenderingFormats {
+            color_formats: vec![self.config.color_format],
+            depth_format: Some(self.depth_format),
+            samples: self.samples,
+        }
+    }
+
+    pub fn render_system(
+        mut forward_renderer: ResMut<ForwardRenderer>,
+        mut render_manager: ResMut<RenderManager>,
+        mut draw_list: ResMut<DrawList>,
+        mut camera: ResMut<ForwardCamera>,
+        vulkan: Res<Vulkan>,
+        mut vulkan_allocator: ResMut<VulkanMemoryAllocator>,
+    ) {
+        pyrite_util::profile_scope!("ForwardRenderer::render_system");
+
+        let extent = render_manager.backbuffer_extent();
+        if extent.width > 0 && extent.height > 0 {
+            let depth = render_manager.depth_image().unwrap().clone();
+            forward_renderer.resize(&vulkan, &mut vulkan_allocator, depth, extent);
+            camera
+                .projection
+                .resize(extent.width as f32, extent.height as f32);
+        }
+        camera.projection.reverse_z = render_manager.reversed_z();
+
+        let draws = std::mem::take(&mut draw_list.draws);
+        let frame_index = render_manager.frame_index();
+        let depth_clear_value = render_manager.depth_clear_value();
+        forward_renderer.render(
+            &vulkan,
+            render_manager.command_buffer_mut(),
+            frame_index,
+            &camera,
+            depth_clear_value,
+            draws,
+        );
+
+        let attachments = forward_renderer.attachments.as_ref().unwrap();
+        render_manager.set_frame_config(
+            &FrameConfig::builder()
+                .backbuffer(
+                    &attachments.color,
+                    forward_renderer.config.color_format,
+                    attachments.extent,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                )
+                .build(),
+        );
+    }
+
+    /// Recreates the color attachment if the extent changed, the depth image is recreated by the
+    /// [`RenderManager`] along with the backbuffer.
+    fn resize(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        depth: Arc<OwnedImage>,
+        extent: vk::Extent2D,
+    ) {
+        if let Some(attachments) = self
+            .attachments
+            .as_mut()
+            .filter(|attachments| attachments.extent == extent)
+        {
+            attachments.depth = depth;
+            return;
+        }
+
+        self.attachments = Some(Attachments {
+            color: create_render_target(
+                vulkan,
+                vulkan_allocator,
+                self.config.color_format,
+                extent,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+                self.samples,
+                "forward color",
+            ),
+            depth,
+            extent,
+        });
+    }
+
+    fn render(
+        &mut self,
+        vulkan: &Vulkan,
+        command_buffer: &mut CommandBuffer,
+        frame_index: usize,
+        camera: &ForwardCamera,
+        depth_clear_value: f32,
+        draws: Vec<Draw>,
+    ) {
+        let view = camera.transform.compute_view_matrix();
+        let projection = camera.projection.compute_matrix();
+        let view_projection = projection * view;
+        let camera_position = camera.transform.translation();
+        self.camera_buffers[frame_index].write_slice(
+            0,
+            &[CameraUniform {
+                view,
+                projection,
+                view_projection,
+                position: camera_position.extend(1.0),
+            }],
+        );
+
+        let frustum = Frustum::from_view_projection(&view_projection);
+        let (mut transparent, mut opaque): (Vec<_>, Vec<_>) = draws
+            .into_iter()
+            .filter_map(|draw| {
+                let bounds = draw.mesh.bounds.transformed(&draw.transform.affine());
+                let distance = bounds.center().distance_squared(camera_position);
+                frustum.intersects_aabb(&bounds).then_some((distance, draw))
+            })
+            .partition(|(_, draw)| draw.material.lock().unwrap().is_alpha_blended());
+        opaque.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        transparent.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        let attachments = self
+            .attachments
+            .take()
+            .expect("The forward renderer wasn't resized before rendering");
+        let render_area = vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent: attachments.extent,
+        };
+        command_buffer.begin_rendering(RenderingInfo {
+            render_area,
+            color_attachments: vec![RenderingAttachment {
+                image: &attachments.color,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::STORE,
+                clear_value: vk::ClearValue {
+                    color: vk::ClearColorValue {
+                        float32: self.config.clear_color,
+                    },
+                },
+                resolve_image: None,
+            }],
+            depth_attachment: Some(RenderingAttachment {
+                image: &*attachments.depth,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                // Kept for passes drawing on top of the scene, like the debug lines.
+                store_op: vk::AttachmentStoreOp::STORE,
+                clear_value: vk::ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue {
+                        depth: depth_clear_value,
+                        stencil: 0,
+                    },
+                },
+                resolve_image: None,
+            }),
+            secondary_command_buffers: false,
+        });
+
+        for (label, draws) in [("opaque", opaque), ("transparent", transparent)] {
+            command_buffer.begin_label(label, [1.0, 1.0, 1.0, 1.0]);
+            for (_, draw) in &draws {
+                self.draw(vulkan, command_buffer, frame_index, draw, render_area);
+            }
+            command_buffer.end_label();
+        }
+
+        command_buffer.end_rendering();
+        command_buffer.transition_image(&attachments.color, vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+        self.attachments = Some(attachments);
+    }
+
+    fn draw(
+        &mut self,
+        vulkan: &Vulkan,
+        command_buffer: &mut CommandBuffer,
+        frame_index: usize,
+        draw: &Draw,
+        render_area: vk::Rect2D,
+    ) {
+        let mut material = draw.material.lock().unwrap();
+        material.bind(vulkan, command_buffer, frame_index);
+        command_buffer.set_viewport_and_scissor(render_area);
+
+        let pipeline_layout = material.pipeline().instance().pipeline_layout();
+        if let Some(layout) = pipeline_layout
+            .descriptor_set_layouts()
+            .get(CAMERA_SET as usize)
+        {
+            let camera_set = self.camera_set(vulkan, layout, frame_index);
+            command_buffer.bind_descriptor_sets(
+                vk::PipelineBindPoint::GRAPHICS,
+                CAMERA_SET,
+                &[self.descriptor_set_pool.get(camera_set).unwrap()],
+            );
+        }
+        if let Some(layout) = pipeline_layout
+            .descriptor_set_layouts()
+            .get(SHADOW_SET as usize)
+        {
+            if let Some(shadow_set) = self.shadow_set(vulkan, layout, frame_index) {
+                command_buffer.bind_descriptor_sets(
+                    vk::PipelineBindPoint::GRAPHICS,
+                    SHADOW_SET,
+                    &[self.descriptor_set_pool.get(shadow_set).unwrap()],
+                );
+            }
+        }
+        if let Some(range) = pipeline_layout
+            .push_constant_ranges()
+            .iter()
+            .find(|range| range.offset == 0 && range.size as usize >= std::mem::size_of::<Mat4>())
+        {
+            command_buffer.push_constants(
+                vk::PipelineBindPoint::GRAPHICS,
+                range.stage_flags,
+                0,
+                &draw.transform.compute_matrix(),
+            );
+        }
+
+        draw.mesh.mesh.bind(command_buffer);
+        draw.mesh.mesh.draw(command_buffer, 1);
+    }
+
+    /// The camera descriptor set of the frame for a material's camera set layout, allocating
+    /// and writing the sets of every frame the first time the layout is drawn.
+    fn camera_set(
+        &mut self,
+        vulkan: &Vulkan,
+        layout: &DescriptorSetLayout,
+        frame_index: usize,
+    ) -> DescriptorSetHandle {
+        let key = layout.instance().layout();
+        let (_, camera_sets) = self.camera_sets.entry(key).or_insert_with(|| {
+            let camera_sets = self
+                .camera_buffers
+                .iter()
+                .map(|camera_buffer| {
+                    let [camera_set] = self
+                        .descriptor_set_pool
+                        .allocate_descriptor_sets::<1>(layout);
+                    let mut writer = self
+                        .descriptor_set_pool
+                        .get_mut(camera_set)
+                        .unwrap()
+                        .writer();
+                    writer.uniform_buffer(0, 0, camera_buffer);
+                    writer.submit(vulkan);
+                    camera_set
+                })
+                .collect();
+
+            (layout.clone(), camera_sets)
+        });
+
+        camera_sets[frame_index]
+    }
+
+    /// The shadow descriptor set of the frame for a material's shadow set layout, like
+    /// [`Self::camera_set`]. `None` if shadows weren't set up.
+    fn shadow_set(
+        &mut self,
+        vulkan: &Vulkan,
+        layout: &DescriptorSetLayout,
+        frame_index: usize,
+    ) -> Option<DescriptorSetHandle> {
+        let shadow_renderer = self.shadow_renderer.as_ref()?;
+        let key = layout.instance().layout();
+        let (_, shadow_sets) = self.shadow_sets.entry(key).or_insert_with(|| {
+            let shadow_sets = (0..self.camera_buffers.len())
+                .map(|frame| {
+                    let [shadow_set] = self
+                        .descriptor_set_pool
+                        .allocate_descriptor_sets::<1>(layout);
+                    let mut writer = self
+                        .descriptor_set_pool
+                        .get_mut(shadow_set)
+                        .unwrap()
+                        .writer();
+                    shadow_renderer.write_shadow_set(&mut writer, frame);
+                    writer.submit(vulkan);
+                    shadow_set
+                })
+                .collect();
+
+            (layout.clone(), shadow_sets)
+        });
+
+        Some(shadow_sets[frame_index])
+    }
+}
@@ -1,3 +1,11 @@
+pub mod debug;
+pub mod forward;
+pub mod material;
 pub mod render_manager;
+pub mod render_target;
+pub mod shadow;
+pub mod sprite;
+pub mod text;
+pub mod transient;
 
 pub mod prelude {}
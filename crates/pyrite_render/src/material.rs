@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use ash::vk;
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator,
+    objects::{
+        CommandBuffer, DescriptorSetHandle, DescriptorSetPool, GraphicsPipeline,
+        GraphicsPipelineCreateInfo, Texture, TypedBuffer, TypedBufferCreateInfo,
+    },
+    Vulkan,
+};
+
+/// The descriptor set holding the parameter block and textures of a [`Material`].
+pub const MATERIAL_SET: u32 = 0;
+/// The binding of the parameter block, texture slot `i` is bound to binding `i + 1`.
+pub const MATERIAL_PARAMS_BINDING: u32 = 0;
+
+pub struct MaterialCreateInfo<'a, P> {
+    /// The pipeline drawing the material, its shaders read the parameter block and textures from
+    /// [`MATERIAL_SET`].
+    pub pipeline_info: GraphicsPipelineCreateInfo<'a>,
+    pub params: P,
+    /// The number of combined image samplers following the parameter block.
+    pub texture_slots: u32,
+    /// The name shown in debugging tools.
+    pub name: Option<String>,
+}
+
+/// The resources of a material for one frame in flight.
+struct MaterialFrame<P> {
+    params_buffer: TypedBuffer<P>,
+    descriptor_set: DescriptorSetHandle,
+    params_dirty: bool,
+    textures_dirty: bool,
+}
+
+/// A graphics pipeline with a typed parameter block and texture slots.
+///
+/// Each of the `N` frames in flight has its own uniform buffer and descriptor set, changes are
+/// written to a frame's copy the next time the material is bound for that frame, so a frame still
+/// executing on the GPU is never written to.
+///
+/// `P` is copied into a uniform buffer as bytes, so it must be a [`bytemuck::Pod`] `#[repr(C)]`
+/// struct of the glsl types laid out like the uniform block.
+pub struct Material<P: bytemuck::Pod, const N: usize> {
+    pipeline: GraphicsPipeline,
+    alpha_blending: bool,
+    descriptor_set_pool: DescriptorSetPool,
+    frames: [MaterialFrame<P>; N],
+    params: P,
+    textures: Vec<Option<Arc<Texture>>>,
+}
+
+impl<P: bytemuck::Pod, const N: usize> Material<P, N> {
+    /// # Panics
+    /// If the pipeline layout has no descriptor set layout for [`MATERIAL_SET`].
+    pub fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        create_info: MaterialCreateInfo<'_, P>,
+    ) -> Self {
+        let alpha_blending = create_info.pipeline_info.alpha_blending;
+        let pipeline = GraphicsPipeline::new(vulkan, create_info.pipeline_info);
+
+        let mut descriptor_set_pool = DescriptorSetPool::new(vulkan);
+        let descriptor_set_layout = pipeline
+            .instance()
+            .pipeline_layout()
+            .descriptor_set_layouts()
+            .get(MATERIAL_SET as usize)
+            .expect("The material's pipeline layout has no material descriptor set");
+        let descriptor_sets =
+            descriptor_set_pool.allocate_descriptor_sets::<N>(descriptor_set_layout);
+
+        let frames = descriptor_sets.map(|descriptor_set| MaterialFrame {
+            params_buffer: TypedBuffer::new(
+                vulkan,
+                vulkan_allocator,
+                &TypedBufferCreateInfo {
+                    len: 1,
+                    usage: vk::BufferUsageFlags::UNIFORM_BUFFER,
+                    memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                        | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    name: create_info
+                        .name
+                        .as_ref()
+                        .map(|name| format!("{} params", name)),
+                },
+            ),
+            descriptor_set,
+            params_dirty: true,
+            textures_dirty: true,
+        });
+
+        Self {
+            pipeline,
+            alpha_blending,
+            descriptor_set_pool,
+            frames,
+            params: create_info.params,
+            textures: vec![None; create_info.texture_slots as usize],
+        }
+    }
+
+    pub fn pipeline(&self) -> &GraphicsPipeline {
+        &self.pipeline
+    }
+
+    /// Whether the pipeline blends with the source alpha, such materials are drawn after the
+    /// opaque ones.
+    pub fn is_alpha_blended(&self) -> bool {
+        self.alpha_blending
+    }
+
+    pub fn params(&self) -> &P {
+        &self.params
+    }
+
+    /// The parameters, marking them to be written to every frame.
+    pub fn params_mut(&mut self) -> &mut P {
+        for frame in &mut self.frames {
+            frame.params_dirty = true;
+        }
+        &mut self.params
+    }
+
+    pub fn set_params(&mut self, params: P) {
+        *self.params_mut() = params;
+    }
+
+    pub fn texture(&self, slot: u32) -> Option<&Arc<Texture>> {
+        self.textures.get(slot as usize)?.as_ref()
+    }
+
+    /// Sets the texture of a slot, the texture must be in the shader read only layout when the
+    /// material is drawn.
+    ///
+    /// # Panics
+    /// If the material doesn't have the slot.
+    pub fn set_texture(&mut self, slot: u32, texture: Arc<Texture>) {
+        let texture_count = self.textures.len();
+        *self.textures.get_mut(slot as usize).unwrap_or_else(|| {
+            panic!(
+                "Texture slot {} is out of range, the material has {} slots",
+                slot, texture_count
+            )
+        }) = Some(texture);
+
+        for frame in &mut self.frames {
+            frame.textures_dirty = true;
+        }
+    }
+
+    /// Writes the changes for the frame and binds the pipeline and material descriptor set.
+    ///
+    /// The resources of `frame_index` must have been released, since its descriptor set and
+    /// uniform buffer may be written.
+    ///
+    /// # Panics
+    /// If a texture slot isn't set.
+    pub fn bind(
+        &mut self,
+        vulkan: &Vulkan,
+        command_buffer: &mut CommandBuffer,
+        frame_index: usize,
+    ) {
+        self.update_frame(vulkan, frame_index);
+
+        let descriptor_set = self
+            .descriptor_set_pool
+            .get(self.frames[frame_index].descriptor_set)
+            .expect("Material descriptor set was freed");
+        command_buffer.bind_graphics_pipeline(&self.pipeline);
+        command_buffer.bind_descriptor_sets(
+            vk::PipelineBindPoint::GRAPHICS,
+            MATERIAL_SET,
+            &[descriptor_set],
+        );
+    }
+
+    fn update_frame(&mut self, vulkan: &Vulkan, frame_index: usize) {
+        let frame = &mut self.frames[frame_index];
+        if !frame.params_dirty && !frame.textures_dirty {
+            return;
+        }
+
+        if frame.params_dirty {
+            frame.params_buffer.write_slice(0, &[self.params]);
+        }
+
+        let descriptor_set = self
+            .descriptor_set_pool
+            .get_mut(frame.descriptor_set)
+            .expect("Material descriptor set was freed");
+        let mut writer = descriptor_set.writer();
+        if frame.textures_dirty {
+            // The buffer of a frame never changes, it's written along with the textures so it's
+            // written at least once.
+            writer.uniform_buffer(MATERIAL_PARAMS_BINDING, 0, &frame.params_buffer);
+            for (slot, texture) in self.textures.iter().enumerate() {
+                let texture = texture
+                    .as_ref()
+                    .unwrap_or_else(|| panic!("Texture slot {} of the material isn't set", slot));
+                writer.combined_image_sampler(
+                    MATERIAL_PARAMS_BINDING + 1 + slot as u32,
+                    0,
+                    texture.image(),
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    texture.sampler(),
+                );
+            }
+        }
+        writer.submit(vulkan);
+
+        frame.params_dirty = false;
+        frame.textures_dirty = false;
+    }
+}
@@ -1,64 +1,166 @@
-use std::{any::Any, sync::Arc};
+use std::{path::PathBuf, sync::Arc};
 
 use ash::vk;
 use pyrite_app::{
-    resource::{ResMut, Resource},
+    event::EventWriter,
+    plugin::Plugin,
+    resource::{Res, ResMut, Resource},
     AppBuilder,
 };
-use pyrite_desktop::{POST_RENDER_STAGE, PRE_RENDER_STAGE};
-use pyrite_util::Dependable;
 use pyrite_vulkan::{
-    swapchain::{Swapchain, SwapchainDep},
-    CommandBuffer, CommandPool, Fence, Image, ImageDep, ImageInfo, Semaphore, Vulkan,
-    VulkanAllocator, VulkanDep,
+    allocator::VulkanMemoryAllocator,
+    executor::{QueueExecutor, QueueExecutorSubmitInfo},
+    objects::{
+        Buffer, BufferCreateInfo, CommandBuffer, CommandBufferHandle, CommandPool, Fence, Image,
+        ImageDep, ImageInstance, OwnedImage, OwnedImageCreateInfo, Semaphore, UntypedBuffer,
+    },
+    stager::VulkanStager,
+    swapchain::SwapchainManager,
+    util::{FrameResourceArena, GenericResourceDep},
+    Vulkan, VulkanDep, DEFAULT_QUEUE,
 };
 
-pub fn setup_render_manager(app_builder: &mut AppBuilder, config: &RenderManagerConfig) {
-    let render_manager = RenderManager::new(
-        &*app_builder.get_resource::<Vulkan>(),
-        &mut *app_builder.get_resource_mut::<VulkanAllocator>(),
-        &*app_builder.get_resource::<Swapchain>(),
-        config,
-    );
-    app_builder.add_resource(render_manager);
+use crate::{render_target::create_render_target, transient::TransientImagePool};
 
-    // Add systems.
-    app_builder.add_system_to_stage(RenderManager::pre_render_system, PRE_RENDER_STAGE);
-    app_builder.add_system_to_stage(RenderManager::post_render_system, POST_RENDER_STAGE);
+/// The stage the [`RenderManager`] begins the frame in, renderers add their stages after it.
+pub const PRE_RENDER_STAGE: &str = "pre_render";
+/// The stage the [`RenderManager`] submits and presents the frame in, after every other stage.
+pub const POST_RENDER_STAGE: &str = "post_render";
+
+/// The most frames the [`RenderManager`] can have in flight.
+pub const MAX_FRAMES_IN_FLIGHT: usize = 3;
+
+/// Adds the [`RenderManager`] and the [`PRE_RENDER_STAGE`] and [`POST_RENDER_STAGE`] after the
+/// existing stages. Requires the [`Vulkan`], [`VulkanMemoryAllocator`], [`VulkanStager`] and
+/// [`SwapchainManager`] resources to be added first, the stager with the same frames in flight.
+pub struct RenderManagerPlugin {
+    pub config: RenderManagerConfig,
+}
+
+impl Plugin for RenderManagerPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        let render_manager =
+            RenderManager::new(&app_builder.get_resource::<Vulkan>(), &self.config);
+        let transient_image_pool =
+            TransientImagePool::new(render_manager.frames_in_flight() as usize);
+        app_builder
+            .add_resource(render_manager)
+            .add_resource(transient_image_pool)
+            .add_event::<BackbufferResized>();
+
+        // Add stages and systems.
+        app_builder
+            .add_stage(PRE_RENDER_STAGE)
+            .add_stage(POST_RENDER_STAGE);
+        app_builder.add_system_to_stage(RenderManager::pre_render_system, PRE_RENDER_STAGE);
+        app_builder.add_system_to_stage(RenderManager::post_render_system, POST_RENDER_STAGE);
+    }
 }
 
 #[derive(Resource)]
 pub struct RenderManager {
     vulkan_dep: VulkanDep,
-    _swapchain_dep: SwapchainDep,
     command_pool: CommandPool,
+    /// Submits the frames to the default queue, keeping their resources alive until the frame in
+    /// flight is reused.
+    executor: QueueExecutor<MAX_FRAMES_IN_FLIGHT>,
     frames: Vec<Frame>,
     frame_config: Option<FrameConfig>,
+    /// The queues besides the default one work can be recorded for, see
+    /// [`RenderManager::queue_command_buffer_mut`].
+    queues: Vec<FrameQueue>,
 
     frame_index: usize,
-    used_objects: Vec<Arc<dyn Any + Send + Sync>>,
+
+    resize_mode: BackbufferResizeMode,
+    backbuffer_extent: vk::Extent2D,
+    samples: vk::SampleCountFlags,
+    /// The single sampled image a multisampled backbuffer is resolved to before the blit,
+    /// recreated when the backbuffer's format or extent changes.
+    resolve_target: Option<ResolveTarget>,
+    /// `None` if the render manager doesn't create a depth image.
+    depth_format: Option<vk::Format>,
+    /// Matches the backbuffer extent, recreated when it changes.
+    depth_image: Option<Arc<OwnedImage>>,
+    reversed_z: bool,
+
+    screenshot_requests: Vec<PathBuf>,
+    /// The screenshots copied by each frame in flight, saved once the frame finished.
+    pending_screenshots: Vec<Option<PendingScreenshot>>,
 }
 
-pub struct Frame {
+struct Frame {
     fence: Fence,
     image_available_semaphore: Semaphore,
     render_finished_semaphore: Semaphore,
-    command_buffer: CommandBuffer,
+    command_buffer: CommandBufferHandle,
 }
 
-impl Frame {
-    pub fn command_buffer(&self) -> &CommandBuffer {
-        &self.command_buffer
-    }
+/// A queue besides the default one, with a command buffer for each frame in flight.
+struct FrameQueue {
+    name: String,
+    command_pool: CommandPool,
+    executor: QueueExecutor<MAX_FRAMES_IN_FLIGHT>,
+    frames: Vec<QueueFrame>,
+}
 
-    pub fn command_buffer_mut(&mut self) -> &mut CommandBuffer {
-        &mut self.command_buffer
+struct QueueFrame {
+    fence: Fence,
+    command_buffer: CommandBufferHandle,
+    /// One for each submission of the frame waiting on this one, since a semaphore can only be
+    /// waited on once.
+    signal_semaphores: Vec<Arc<Semaphore>>,
+    is_recording: bool,
+    is_submitted: bool,
+}
+
+struct ResolveTarget {
+    image: OwnedImage,
+    format: vk::Format,
+    extent: vk::Extent2D,
+}
+
+/// How the size of the backbuffer follows the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackbufferResizeMode {
+    /// Keeps the backbuffer at a fixed size, which is scaled to the window when presenting.
+    FixedResolution { width: u32, height: u32 },
+    /// Matches the size of the swapchain.
+    MatchWindow,
+    /// Scales the size of the swapchain, such as 0.5 to render at half resolution.
+    ScaleFactor(f32),
+}
+
+impl BackbufferResizeMode {
+    /// The backbuffer extent for a swapchain of the given extent.
+    pub fn backbuffer_extent(&self, window_extent: vk::Extent2D) -> vk::Extent2D {
+        match *self {
+            Self::FixedResolution { width, height } => vk::Extent2D { width, height },
+            Self::MatchWindow => window_extent,
+            Self::ScaleFactor(scale_factor) => vk::Extent2D {
+                width: ((window_extent.width as f32 * scale_factor).round() as u32).max(1),
+                height: ((window_extent.height as f32 * scale_factor).round() as u32).max(1),
+            },
+        }
     }
 }
 
+/// Sent when the backbuffer extent of the [`RenderManager`] changed, so the targets rendered to
+/// the backbuffer should be recreated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackbufferResized {
+    pub width: u32,
+    pub height: u32,
+}
+
 #[derive(Clone)]
 pub struct RenderManagerConfig {
     frames_in_flight: u32,
+    resize_mode: BackbufferResizeMode,
+    samples: vk::SampleCountFlags,
+    depth: bool,
+    reversed_z: bool,
+    queues: Vec<String>,
 }
 
 impl RenderManagerConfig {
@@ -69,90 +171,289 @@ impl RenderManagerConfig {
 
 pub struct RenderManagerConfigBuilder {
     frames_in_flight: u32,
+    resize_mode: BackbufferResizeMode,
+    samples: vk::SampleCountFlags,
+    depth: bool,
+    reversed_z: bool,
+    queues: Vec<String>,
 }
 
 impl Default for RenderManagerConfigBuilder {
     fn default() -> Self {
         Self {
             frames_in_flight: 2,
+            resize_mode: BackbufferResizeMode::MatchWindow,
+            samples: vk::SampleCountFlags::TYPE_1,
+            depth: true,
+            reversed_z: false,
+            queues: Vec::new(),
         }
     }
 }
 
 impl RenderManagerConfigBuilder {
+    /// At most [`MAX_FRAMES_IN_FLIGHT`], 2 by default.
     pub fn frames_in_flight(mut self, frames_in_flight: u32) -> Self {
         self.frames_in_flight = frames_in_flight;
         self
     }
 
+    pub fn resize_mode(mut self, resize_mode: BackbufferResizeMode) -> Self {
+        self.resize_mode = resize_mode;
+        self
+    }
+
+    /// The sample count of the backbuffer, which is resolved before being blitted to the
+    /// swapchain when multisampled. Single sampled by default.
+    pub fn samples(mut self, samples: vk::SampleCountFlags) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Whether the render manager creates a depth image matching the backbuffer, see
+    /// [`RenderManager::depth_image`]. Enabled by default.
+    pub fn depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Whether depth is reversed, mapping the near plane to 1 and the far plane to 0. Reversing
+    /// depth spreads the float precision evenly over the distance. Disabled by default.
+    pub fn reversed_z(mut self, reversed_z: bool) -> Self {
+        self.reversed_z = reversed_z;
+        self
+    }
+
+    /// Adds a virtual queue of the `VulkanConfig`, such as an async compute or transfer queue,
+    /// that work can be recorded for with [`RenderManager::queue_command_buffer_mut`].
+    pub fn queue(mut self, queue_name: &str) -> Self {
+        self.queues.push(queue_name.to_string());
+        self
+    }
+
     pub fn build(self) -> RenderManagerConfig {
         RenderManagerConfig {
             frames_in_flight: self.frames_in_flight,
+            resize_mode: self.resize_mode,
+            samples: self.samples,
+            depth: self.depth,
+            reversed_z: self.reversed_z,
+            queues: self.queues,
+        }
+    }
+}
+
+/// An image of the frame config, kept alive by the frame using it.
+#[derive(Clone)]
+struct FrameImage {
+    instance: ImageDep,
+    generic_dep: GenericResourceDep,
+}
+
+impl FrameImage {
+    fn new(image: &dyn Image) -> Self {
+        Self {
+            instance: image.create_dep(),
+            generic_dep: image.create_generic_dep(),
         }
     }
 }
 
+impl Image for FrameImage {
+    fn instance(&self) -> &dyn ImageInstance {
+        self.instance.as_ref()
+    }
+
+    fn create_dep(&self) -> ImageDep {
+        self.instance.clone()
+    }
+
+    fn create_generic_dep(&self) -> GenericResourceDep {
+        self.generic_dep.clone()
+    }
+}
+
+/// An image rendered to during the frame besides the backbuffer, such as a GBuffer target.
+#[derive(Clone)]
+pub struct FrameAttachment {
+    name: String,
+    image: FrameImage,
+    final_layout: vk::ImageLayout,
+}
+
+impl FrameAttachment {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn image(&self) -> &dyn Image {
+        &self.image
+    }
+
+    /// The layout the image is left in by the frame's passes.
+    pub fn final_layout(&self) -> vk::ImageLayout {
+        self.final_layout
+    }
+}
+
+/// The submission of `queue` waits for the submission of `wait_for` in the same frame before
+/// executing `wait_stage`.
+#[derive(Debug, Clone)]
+pub struct QueueDependency {
+    pub queue: String,
+    pub wait_for: String,
+    pub wait_stage: vk::PipelineStageFlags2,
+}
+
 #[derive(Clone)]
 pub struct FrameConfig {
-    backbuffer_image: ImageDep,
+    backbuffer_image: FrameImage,
+    backbuffer_format: vk::Format,
+    backbuffer_extent: vk::Extent2D,
     backbuffer_final_layout: vk::ImageLayout,
-    backbuffer_final_access: vk::AccessFlags,
-    used_objects: Vec<Arc<dyn Any + Send + Sync>>,
+    attachments: Vec<FrameAttachment>,
+    queue_dependencies: Vec<QueueDependency>,
+    used_objects: Vec<GenericResourceDep>,
 }
 
 impl FrameConfig {
     pub fn builder() -> FrameConfigBuilder<'static> {
         FrameConfigBuilder::default()
     }
+
+    pub fn attachments(&self) -> &[FrameAttachment] {
+        &self.attachments
+    }
+
+    pub fn attachment(&self, name: &str) -> Option<&FrameAttachment> {
+        self.attachments
+            .iter()
+            .find(|attachment| attachment.name == name)
+    }
+
+    pub fn queue_dependencies(&self) -> &[QueueDependency] {
+        &self.queue_dependencies
+    }
 }
 
 pub struct FrameConfigBuilder<'a> {
-    backbuffer_image: Option<&'a Image>,
+    backbuffer_image: Option<&'a dyn Image>,
+    backbuffer_format: vk::Format,
+    backbuffer_extent: vk::Extent2D,
     backbuffer_final_layout: vk::ImageLayout,
-    backbuffer_final_access: vk::AccessFlags,
-    used_objects: Vec<Arc<dyn Any + Send + Sync>>,
+    attachments: Vec<FrameAttachment>,
+    queue_dependencies: Vec<QueueDependency>,
+    used_objects: Vec<GenericResourceDep>,
 }
 
 impl Default for FrameConfigBuilder<'_> {
     fn default() -> Self {
         Self {
             backbuffer_image: None,
+            backbuffer_format: vk::Format::UNDEFINED,
+            backbuffer_extent: vk::Extent2D::default(),
             backbuffer_final_layout: vk::ImageLayout::UNDEFINED,
-            backbuffer_final_access: vk::AccessFlags::empty(),
+            attachments: Vec::new(),
+            queue_dependencies: Vec::new(),
             used_objects: Vec::new(),
         }
     }
 }
 
 impl<'a> FrameConfigBuilder<'a> {
-    pub fn backbuffer(
-        mut self,
-        image: &'a Image,
+    /// The image blitted to the swapchain, left in `layout` by the frame's passes.
+    pub fn backbuffer<'b>(
+        self,
+        image: &'b dyn Image,
+        format: vk::Format,
+        extent: vk::Extent2D,
         layout: vk::ImageLayout,
-        access: vk::AccessFlags,
+    ) -> FrameConfigBuilder<'b> {
+        FrameConfigBuilder {
+            backbuffer_image: Some(image),
+            backbuffer_format: format,
+            backbuffer_extent: extent,
+            backbuffer_final_layout: layout,
+            attachments: self.attachments,
+            queue_dependencies: self.queue_dependencies,
+            used_objects: self.used_objects,
+        }
+    }
+
+    /// Declares an image the frame rendered to, which is kept alive until the frame finished
+    /// executing and can be looked up by name with [`RenderManager::frame_attachment`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if an attachment with the same name was already declared.
+    pub fn attachment(mut self, name: &str, image: &dyn Image, layout: vk::ImageLayout) -> Self {
+        assert!(
+            self.attachments
+                .iter()
+                .all(|attachment| attachment.name != name),
+            "Frame attachment {} declared twice.",
+            name
+        );
+
+        self.used_objects.push(image.create_generic_dep());
+        self.attachments.push(FrameAttachment {
+            name: name.to_string(),
+            image: FrameImage::new(image),
+            final_layout: layout,
+        });
+        self
+    }
+
+    /// Makes the work recorded for `queue` this frame wait for the work recorded for `wait_for`
+    /// before executing `wait_stage`. The default queue is named [`DEFAULT_QUEUE`], it's
+    /// submitted last since it presents, so it can wait for other queues but not the other way
+    /// around. Dependencies on queues nothing was recorded for are skipped.
+    ///
+    /// Images and buffers shared between queues of different families must also be transferred
+    /// between them with queue family ownership barriers, unless they're created as concurrent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `wait_for` is the default queue.
+    pub fn queue_dependency(
+        mut self,
+        queue: &str,
+        wait_for: &str,
+        wait_stage: vk::PipelineStageFlags2,
     ) -> Self {
-        self.backbuffer_image = Some(image);
-        self.backbuffer_final_layout = layout;
-        self.backbuffer_final_access = access;
+        assert!(
+            wait_for != DEFAULT_QUEUE,
+            "Queue {} can't wait for the default queue, which is submitted last.",
+            queue
+        );
+
+        self.queue_dependencies.push(QueueDependency {
+            queue: queue.to_string(),
+            wait_for: wait_for.to_string(),
+            wait_stage,
+        });
         self
     }
 
-    pub fn used_objects(mut self, used_objects: Vec<Arc<dyn Any + Send + Sync>>) -> Self {
-        self.used_objects = used_objects;
+    /// Resources the frame uses besides the backbuffer and attachments, kept alive until the
+    /// frame finished executing.
+    pub fn used_objects(mut self, used_objects: Vec<GenericResourceDep>) -> Self {
+        self.used_objects.extend(used_objects);
         self
     }
 
     pub fn build(mut self) -> FrameConfig {
-        if self.backbuffer_image.is_none() {
-            panic!("Backbuffer image not set.");
-        }
+        let backbuffer_image = self.backbuffer_image.expect("Backbuffer image not set.");
 
         self.used_objects
-            .push(self.backbuffer_image.unwrap().create_dep() as Arc<dyn Any + Send + Sync>);
+            .push(backbuffer_image.create_generic_dep());
         FrameConfig {
-            backbuffer_image: self.backbuffer_image.unwrap().create_dep(),
+            backbuffer_image: FrameImage::new(backbuffer_image),
+            backbuffer_format: self.backbuffer_format,
+            backbuffer_extent: self.backbuffer_extent,
             backbuffer_final_layout: self.backbuffer_final_layout,
-            backbuffer_final_access: self.backbuffer_final_access,
+            attachments: self.attachments,
+            queue_dependencies: self.queue_dependencies,
             used_objects: self.used_objects,
         }
     }
@@ -167,16 +468,26 @@ impl Drop for RenderManager {
 }
 
 impl RenderManager {
-    fn new(
-        vulkan: &Vulkan,
-        vulkan_allocator: &mut VulkanAllocator,
-        swapchain: &Swapchain,
-        config: &RenderManagerConfig,
-    ) -> Self {
-        let command_pool = CommandPool::new(vulkan);
-        let command_buffers = command_pool.allocate_command_buffers(config.frames_in_flight as u32);
+    fn new(vulkan: &Vulkan, config: &RenderManagerConfig) -> Self {
+        assert!(
+            (1..=MAX_FRAMES_IN_FLIGHT as u32).contains(&config.frames_in_flight),
+            "The render manager supports 1 to {} frames in flight, got {}.",
+            MAX_FRAMES_IN_FLIGHT,
+            config.frames_in_flight
+        );
 
-        let frames = command_buffers
+        let limits = &vulkan.physical_device().properties().limits;
+        let supported_samples =
+            limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+        if !supported_samples.contains(config.samples) {
+            panic!(
+                "Backbuffer sample count {:?} isn't supported, supported are {:?}.",
+                config.samples, supported_samples
+            );
+        }
+
+        let mut command_pool = CommandPool::new(vulkan);
+        let frames = allocate_command_buffers(&mut command_pool, config.frames_in_flight)
             .into_iter()
             .map(|command_buffer| Frame {
                 command_buffer,
@@ -186,23 +497,98 @@ impl RenderManager {
             })
             .collect();
 
+        let queues = config
+            .queues
+            .iter()
+            .map(|queue_name| {
+                let queue = vulkan.queue(queue_name).unwrap_or_else(|| {
+                    panic!("Queue {} isn't configured in the VulkanConfig.", queue_name)
+                });
+                let mut command_pool = CommandPool::new_for_queue(vulkan, queue);
+                let frames = allocate_command_buffers(&mut command_pool, config.frames_in_flight)
+                    .into_iter()
+                    .map(|command_buffer| QueueFrame {
+                        // Only waited on once submitted.
+                        fence: Fence::new(vulkan, false),
+                        command_buffer,
+                        signal_semaphores: Vec::new(),
+                        is_recording: false,
+                        is_submitted: false,
+                    })
+                    .collect();
+
+                FrameQueue {
+                    name: queue_name.clone(),
+                    command_pool,
+                    executor: QueueExecutor::new(vulkan, queue_name.clone()),
+                    frames,
+                }
+            })
+            .collect();
+
         Self {
             vulkan_dep: vulkan.create_dep(),
-            _swapchain_dep: swapchain.create_dep(),
             command_pool,
+            executor: QueueExecutor::new(vulkan, DEFAULT_QUEUE),
             frames,
             frame_config: None,
+            queues,
             frame_index: 0,
-            used_objects: Vec::new(),
+            resize_mode: config.resize_mode,
+            backbuffer_extent: vk::Extent2D::default(),
+            samples: config.samples,
+            resolve_target: None,
+            depth_format: config.depth.then(|| Self::select_depth_format(vulkan)),
+            depth_image: None,
+            reversed_z: config.reversed_z,
+            screenshot_requests: Vec::new(),
+            pending_screenshots: (0..config.frames_in_flight).map(|_| None).collect(),
         }
     }
 
-    pub fn frame(&self) -> &Frame {
-        &self.frames[self.frame_index]
+    /// The command buffer of the current frame on the default queue, begun in the pre render
+    /// stage.
+    pub fn command_buffer_mut(&mut self) -> &mut CommandBuffer {
+        self.command_pool
+            .get_mut(self.frames[self.frame_index].command_buffer)
+            .unwrap()
     }
 
-    pub fn frame_mut(&mut self) -> &mut Frame {
-        &mut self.frames[self.frame_index]
+    /// Resources added to the arena are kept alive until the current frame finished executing,
+    /// on every queue it was submitted to.
+    pub fn frame_arena_mut(&mut self) -> &mut FrameResourceArena {
+        self.executor.frame_arena_mut(self.frame_index)
+    }
+
+    /// The command buffer of the current frame for a queue added with
+    /// [`RenderManagerConfigBuilder::queue`], begun when first used this frame.
+    ///
+    /// The recorded work is submitted in the post render stage before the default queue's, in
+    /// the order of the frame config's [`QueueDependency`]s.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the queue wasn't added to the [`RenderManagerConfig`].
+    pub fn queue_command_buffer_mut(&mut self, queue_name: &str) -> &mut CommandBuffer {
+        let frame_index = self.frame_index;
+        let queue = self
+            .queues
+            .iter_mut()
+            .find(|queue| queue.name == queue_name)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Queue {} wasn't added to the RenderManagerConfig.",
+                    queue_name
+                )
+            });
+
+        let frame = &mut queue.frames[frame_index];
+        let command_buffer = queue.command_pool.get_mut(frame.command_buffer).unwrap();
+        if !frame.is_recording {
+            command_buffer.begin();
+            frame.is_recording = true;
+        }
+        command_buffer
     }
 
     pub fn frames_in_flight(&self) -> u32 {
@@ -213,199 +599,652 @@ impl RenderManager {
         self.frame_index
     }
 
+    /// The extent renderers should create the backbuffer with, following the window by the
+    /// [`BackbufferResizeMode`]. It's zero until the first frame began.
+    pub fn backbuffer_extent(&self) -> vk::Extent2D {
+        self.backbuffer_extent
+    }
+
+    /// The sample count renderers should create the backbuffer and its depth buffer with, see
+    /// [`RenderManagerConfigBuilder::samples`].
+    pub fn samples(&self) -> vk::SampleCountFlags {
+        self.samples
+    }
+
+    /// The format of the depth image, `D32_SFLOAT` if the device supports it and otherwise
+    /// `D24_UNORM_S8_UINT`. `None` if depth was disabled in the [`RenderManagerConfig`].
+    pub fn depth_format(&self) -> Option<vk::Format> {
+        self.depth_format
+    }
+
+    /// The depth image matching the backbuffer's extent and sample count, recreated with it.
+    /// `None` until the first frame began or if depth was disabled.
+    ///
+    /// The image is sampled besides being a depth attachment, so passes like SSAO can read the
+    /// depth written by an earlier pass of the frame.
+    pub fn depth_image(&self) -> Option<&Arc<OwnedImage>> {
+        self.depth_image.as_ref()
+    }
+
+    /// Whether depth is reversed, see [`RenderManagerConfigBuilder::reversed_z`]. Perspective
+    /// projections must set [`PerspectiveProjection::reverse_z`] to match.
+    ///
+    /// [`PerspectiveProjection::reverse_z`]: pyrite_math::PerspectiveProjection::reverse_z
+    pub fn reversed_z(&self) -> bool {
+        self.reversed_z
+    }
+
+    /// The depth compare op pipelines testing depth against the depth image should use, passing
+    /// fragments at least as close as the stored depth.
+    pub fn depth_compare_op(&self) -> vk::CompareOp {
+        if self.reversed_z {
+            vk::CompareOp::GREATER_OR_EQUAL
+        } else {
+            vk::CompareOp::LESS_OR_EQUAL
+        }
+    }
+
+    /// The depth the depth image is cleared to, the farthest depth.
+    pub fn depth_clear_value(&self) -> f32 {
+        if self.reversed_z {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    pub fn resize_mode(&self) -> BackbufferResizeMode {
+        self.resize_mode
+    }
+
+    /// Changes the resize mode, the backbuffer extent is updated when the next frame begins.
+    pub fn set_resize_mode(&mut self, resize_mode: BackbufferResizeMode) {
+        self.resize_mode = resize_mode;
+    }
+
+    /// Saves the backbuffer of the next presented frame as a PNG at `path`.
+    ///
+    /// The backbuffer is read back once the frame finished executing and the file is written on
+    /// a separate thread, so it appears a few frames later. Failures are logged.
+    pub fn request_screenshot(&mut self, path: impl Into<PathBuf>) {
+        self.screenshot_requests.push(path.into());
+    }
+
     pub fn pre_render_system(
         mut render_manager: ResMut<RenderManager>,
-        mut swapchain: ResMut<Swapchain>,
-        mut vulkan_stager: ResMut<pyrite_vulkan::VulkanStager>,
+        swapchain_manager: Res<SwapchainManager>,
+        mut vulkan_stager: ResMut<VulkanStager>,
+        mut transient_image_pool: ResMut<TransientImagePool>,
+        mut backbuffer_resized: EventWriter<BackbufferResized>,
+        vulkan: Res<Vulkan>,
+        mut vulkan_allocator: ResMut<VulkanMemoryAllocator>,
     ) {
+        pyrite_util::profile_scope!("RenderManager::pre_render_system");
+
         // Helps the borrow checker.
         let render_manager = &mut *render_manager;
+        let frame_index = render_manager.frame_index;
 
-        // Wait for the previous frame to finish.
-        {
-            let frame = render_manager
-                .frames
-                .get_mut(render_manager.frame_index)
-                .unwrap();
+        // The backbuffer keeps its size while the window is minimized.
+        if !swapchain_manager.is_minimized() {
+            let backbuffer_extent = render_manager
+                .resize_mode
+                .backbuffer_extent(swapchain_manager.info().extent().clone().into());
+            if backbuffer_extent != render_manager.backbuffer_extent {
+                render_manager.backbuffer_extent = backbuffer_extent;
+                backbuffer_resized.send(BackbufferResized {
+                    width: backbuffer_extent.width,
+                    height: backbuffer_extent.height,
+                });
+
+                if let Some(depth_format) = render_manager.depth_format {
+                    render_manager.depth_image = Some(Arc::new(create_render_target(
+                        &vulkan,
+                        &mut vulkan_allocator,
+                        depth_format,
+                        backbuffer_extent,
+                        vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                            | vk::ImageUsageFlags::SAMPLED,
+                        render_manager.samples,
+                        "depth",
+                    )));
+                }
+            }
+        }
 
-            // Wait for the fence to be signalled.
-            frame.fence.wait();
-            frame.fence.reset();
+        // Wait for the previous use of the frame in flight to finish, its fence is only reset
+        // once the frame is submitted again.
+        {
+            pyrite_util::profile_scope!("wait for frame fence");
+            render_manager.frames[frame_index].fence.wait();
+            for queue in &mut render_manager.queues {
+                let queue_frame = &mut queue.frames[frame_index];
+                if queue_frame.is_submitted {
+                    queue_frame.fence.wait_and_reset();
+                    queue_frame.is_submitted = false;
+                }
+                queue.executor.release_frame_resources(frame_index);
+            }
+        }
 
-            // Release last frame's used objects.
-            render_manager.used_objects.clear();
+        // Release the resources of the frame that last used this frame in flight.
+        render_manager.executor.release_frame_resources(frame_index);
+        vulkan_stager.begin_frame(frame_index);
+        transient_image_pool.begin_frame(frame_index);
 
-            // Begin recording the command buffer.
-            let command_buffer = &mut frame.command_buffer;
-            command_buffer.begin();
+        // Command buffers only hold on to the resources they use weakly, this keeps the depth
+        // image alive for the frame even if it's recreated.
+        if let Some(depth_image) = &render_manager.depth_image {
+            render_manager
+                .executor
+                .frame_arena_mut(frame_index)
+                .push(depth_image.create_generic_dep());
+        }
 
-            // Record vulkan stager immediate tasks.
-            render_manager.used_objects.extend(
-                vulkan_stager
-                    .record_immediate_tasks(
-                        command_buffer,
-                        vk::PipelineStageFlags::TOP_OF_PIPE,
-                        vk::PipelineStageFlags::ALL_COMMANDS,
-                    )
-                    .into_iter()
-                    // Rust want's me to dynamically cast this to Any + Send + Sync cause it's
-                    // dumb.
-                    .map(|x| x as Arc<dyn Any + Send + Sync>),
-            );
+        if let Some(screenshot) = render_manager.pending_screenshots[frame_index].take() {
+            screenshot.save();
         }
+
+        render_manager.command_buffer_mut().begin();
+
+        // Deferred uploads staged during the last frame start copying, finished ones are acquired
+        // before the frame uses them.
+        vulkan_stager
+            .submit_deferred()
+            .unwrap_or_else(|err| panic!("Failed to submit the deferred uploads: {}", err));
+        vulkan_stager.acquire_deferred(render_manager.command_buffer_mut());
     }
 
     pub fn set_frame_config(&mut self, frame_config: &FrameConfig) {
         self.frame_config = Some(frame_config.clone());
     }
 
+    /// An attachment declared by the frame config of the current frame, so passes recorded after
+    /// the config was set can read the targets of earlier passes.
+    pub fn frame_attachment(&self, name: &str) -> Option<&FrameAttachment> {
+        self.frame_config.as_ref()?.attachment(name)
+    }
+
+    /// Blits the backbuffer to the next swapchain image and submits the frame, presenting it
+    /// unless no swapchain image could be acquired, e.g. while the window is minimized. The frame
+    /// is still submitted then, so the uploads recorded into it aren't lost.
     pub fn post_render_system(
         mut render_manager: ResMut<RenderManager>,
-        mut swapchain: ResMut<Swapchain>,
-        mut vulkan_stager: ResMut<pyrite_vulkan::VulkanStager>,
+        mut swapchain_manager: ResMut<SwapchainManager>,
+        vulkan: Res<Vulkan>,
+        mut vulkan_allocator: ResMut<VulkanMemoryAllocator>,
     ) {
+        pyrite_util::profile_scope!("RenderManager::post_render_system");
+
         // Helps the borrow checker.
         let render_manager = &mut *render_manager;
+        let frame_index = render_manager.frame_index;
         let frame_config = render_manager
             .frame_config
             .take()
             .expect("Frame config not set.");
+        render_manager
+            .executor
+            .frame_arena_mut(frame_index)
+            .extend(frame_config.used_objects.iter().cloned());
 
-        for obj in frame_config.used_objects {
-            render_manager.used_objects.push(obj);
-        }
-        render_manager.used_objects.push(frame_config.backbuffer_image.clone());
+        let frame = &render_manager.frames[frame_index];
+        let image_index = swapchain_manager.acquire(&vulkan, &frame.image_available_semaphore);
 
-        // Process the current frame..
-        {
-            let frame = render_manager
-                .frames
-                .get_mut(render_manager.frame_index)
-                .unwrap();
-
-            let (image_index, is_outdated) =
-                swapchain.acquire_next_image(&frame.image_available_semaphore);
-            if is_outdated {
-                swapchain.refresh();
-                return;
-            }
+        let queue_waits = Self::submit_queue_work(
+            &vulkan,
+            &mut render_manager.queues,
+            frame_index,
+            &frame_config.queue_dependencies,
+        );
 
-            let swapchain_image = swapchain.image(image_index);
-
-            let command_buffer = &mut frame.command_buffer;
-
-            // Transition the backbuffer image to transfer source and swapchain image to transfer
-            // destination.
-            command_buffer.pipeline_barrier(
-                vk::PipelineStageFlags::ALL_COMMANDS,
-                vk::PipelineStageFlags::TRANSFER,
-                vk::DependencyFlags::empty(),
-                &[],
-                &[],
-                &[
-                    frame_config.backbuffer_image.image_memory_barrier(
-                        frame_config.backbuffer_final_layout,
-                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
-                        frame_config.backbuffer_final_access,
-                        vk::AccessFlags::TRANSFER_READ,
-                    ),
-                    swapchain_image.default_image_memory_barrier(
-                        vk::ImageLayout::UNDEFINED,
-                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                    ),
-                ],
-            );
+        let command_buffer = render_manager
+            .command_pool
+            .get_mut(frame.command_buffer)
+            .unwrap();
+        if let Some(image_index) = image_index {
+            let swapchain_image = swapchain_manager.image(image_index as usize);
+            let swapchain_extent: vk::Extent2D = swapchain_manager.info().extent().clone().into();
 
-            // Blit the backbuffer image to the swapchain image.
-            let blit_info = vk::ImageBlit::builder()
-                .src_subresource(
-                    vk::ImageSubresourceLayers::builder()
-                        .aspect_mask(vk::ImageAspectFlags::COLOR)
-                        .mip_level(0)
-                        .base_array_layer(0)
-                        .layer_count(1)
-                        .build(),
-                )
+            // A multisampled backbuffer is resolved first and the resolved image is blitted.
+            let backbuffer_image = &frame_config.backbuffer_image;
+            command_buffer.set_image_layout(backbuffer_image, frame_config.backbuffer_final_layout);
+            let blit_source: &dyn Image = if render_manager.samples == vk::SampleCountFlags::TYPE_1
+            {
+                command_buffer
+                    .transition_image(backbuffer_image, vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+                backbuffer_image
+            } else {
+                let resolve_target = Self::resolve_target(
+                    &mut render_manager.resolve_target,
+                    &vulkan,
+                    &mut vulkan_allocator,
+                    &frame_config,
+                );
+                Self::record_resolve(&vulkan, command_buffer, &frame_config, resolve_target);
+                command_buffer
+                    .transition_image(resolve_target, vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+                resolve_target
+            };
+            command_buffer.transition_image(swapchain_image, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+
+            // Blit the backbuffer image to the swapchain image, filtered since their sizes differ
+            // unless the backbuffer matches the window.
+            let subresource = color_subresource_layers();
+            let blit_info = vk::ImageBlit::default()
+                .src_subresource(subresource)
                 .src_offsets([
-                    vk::Offset3D::builder().x(0).y(0).z(0).build(),
-                    vk::Offset3D::builder()
-                        .x(frame_config.backbuffer_image.image_extent().width as i32)
-                        .y(frame_config.backbuffer_image.image_extent().height as i32)
-                        .z(1)
-                        .build(),
-                ])
-                .dst_subresource(
-                    vk::ImageSubresourceLayers::builder()
-                        .aspect_mask(vk::ImageAspectFlags::COLOR)
-                        .mip_level(0)
-                        .base_array_layer(0)
-                        .layer_count(1)
-                        .build(),
-                )
-                .dst_offsets([
-                    vk::Offset3D::builder().x(0).y(0).z(0).build(),
-                    vk::Offset3D::builder()
-                        .x(swapchain_image.image_extent().width as i32)
-                        .y(swapchain_image.image_extent().height as i32)
-                        .z(1)
-                        .build(),
+                    vk::Offset3D::default(),
+                    extent_offset(frame_config.backbuffer_extent),
                 ])
-                .build();
+                .dst_subresource(subresource)
+                .dst_offsets([vk::Offset3D::default(), extent_offset(swapchain_extent)]);
             unsafe {
-                render_manager.vulkan_dep.device().cmd_blit_image(
+                vulkan.device().cmd_blit_image(
                     command_buffer.command_buffer(),
-                    frame_config.backbuffer_image.image(),
+                    blit_source.instance().image(),
                     vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
-                    swapchain_image.image(),
+                    swapchain_image.instance().image(),
                     vk::ImageLayout::TRANSFER_DST_OPTIMAL,
                     &[blit_info],
-                    vk::Filter::NEAREST,
+                    vk::Filter::LINEAR,
                 );
             }
 
-            // Transfer the previous swapchain image to present source.
-            command_buffer.pipeline_barrier(
-                vk::PipelineStageFlags::TRANSFER,
-                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
-                vk::DependencyFlags::empty(),
-                &[],
-                &[],
-                &[swapchain_image.default_image_memory_barrier(
-                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                    vk::ImageLayout::PRESENT_SRC_KHR,
-                )],
-            );
+            if !render_manager.screenshot_requests.is_empty() {
+                let paths = std::mem::take(&mut render_manager.screenshot_requests);
+                render_manager.pending_screenshots[frame_index] = PendingScreenshot::record(
+                    &vulkan,
+                    &mut vulkan_allocator,
+                    command_buffer,
+                    blit_source,
+                    frame_config.backbuffer_format,
+                    frame_config.backbuffer_extent,
+                    paths,
+                );
+            }
+
+            command_buffer.transition_image(swapchain_image, vk::ImageLayout::PRESENT_SRC_KHR);
+        }
+        command_buffer.end();
+
+        // The swapchain image is only transitioned once it was acquired, which the whole
+        // submission waits for since its first use isn't known.
+        let mut wait_semaphores = queue_waits
+            .iter()
+            .map(|(semaphore, stage)| (semaphore.as_ref(), *stage))
+            .collect::<Vec<_>>();
+        let mut signal_semaphores = Vec::new();
+        if image_index.is_some() {
+            wait_semaphores.push((
+                &frame.image_available_semaphore,
+                vk::PipelineStageFlags2::ALL_COMMANDS,
+            ));
+            signal_semaphores.push(&frame.render_finished_semaphore);
+        }
+
+        frame.fence.reset();
+        render_manager
+            .executor
+            .submit(QueueExecutorSubmitInfo {
+                command_buffers: vec![command_buffer],
+                frame_index,
+                wait_semaphores,
+                signal_semaphores,
+                fence: Some(&frame.fence),
+            })
+            .unwrap_or_else(|err| panic!("Failed to submit the frame: {}", err));
 
-            // Finish recording the command buffer.
+        if let Some(image_index) = image_index {
+            if let Err(err) = swapchain_manager.present(
+                &mut render_manager.executor,
+                image_index,
+                vec![&frame.render_finished_semaphore],
+            ) {
+                log::error!("Failed to present the frame: {}", err);
+            }
+        }
+
+        // Update frame index.
+        render_manager.frame_index = (frame_index + 1) % render_manager.frames.len();
+    }
+
+    /// Submits the work recorded for the other queues this frame, ordered so each submission
+    /// comes after the ones it waits for. Returns the semaphores the default queue's submission
+    /// has to wait on.
+    fn submit_queue_work(
+        vulkan: &Vulkan,
+        queues: &mut [FrameQueue],
+        frame_index: usize,
+        queue_dependencies: &[QueueDependency],
+    ) -> Vec<(Arc<Semaphore>, vk::PipelineStageFlags2)> {
+        let is_recorded = |queue_name: &str| {
+            queues
+                .iter()
+                .any(|queue| queue.name == queue_name && queue.frames[frame_index].is_recording)
+        };
+        let queue_dependencies = queue_dependencies
+            .iter()
+            .filter(|dependency| {
+                is_recorded(&dependency.wait_for)
+                    && (dependency.queue == DEFAULT_QUEUE || is_recorded(&dependency.queue))
+            })
+            .collect::<Vec<_>>();
+
+        let mut remaining = (0..queues.len())
+            .filter(|index| queues[*index].frames[frame_index].is_recording)
+            .collect::<Vec<_>>();
+        let mut submit_order = Vec::new();
+        while !remaining.is_empty() {
+            let ready = remaining
+                .iter()
+                .position(|index| {
+                    queue_dependencies
+                        .iter()
+                        .filter(|dependency| dependency.queue == queues[*index].name)
+                        .all(|dependency| {
+                            remaining
+                                .iter()
+                                .all(|other| queues[*other].name != dependency.wait_for)
+                        })
+                })
+                .expect("The queue dependencies of the frame form a cycle.");
+            submit_order.push(remaining.remove(ready));
+        }
+
+        // The semaphores signalled by submitted queues, with the queue waiting on them.
+        let mut waits = Vec::new();
+        for index in submit_order {
+            let queue = &mut queues[index];
+            let frame = &mut queue.frames[frame_index];
+
+            let dependents = queue_dependencies
+                .iter()
+                .filter(|dependency| dependency.wait_for == queue.name)
+                .collect::<Vec<_>>();
+            while frame.signal_semaphores.len() < dependents.len() {
+                frame
+                    .signal_semaphores
+                    .push(Arc::new(Semaphore::new(vulkan)));
+            }
+            let signal_semaphores = frame.signal_semaphores[..dependents.len()].to_vec();
+
+            let wait_semaphores = waits
+                .iter()
+                .filter(|(queue_name, _, _)| *queue_name == queue.name)
+                .map(|(_, semaphore, stage)| (Arc::clone(semaphore), *stage))
+                .collect::<Vec<_>>();
+            waits.extend(dependents.iter().zip(&signal_semaphores).map(
+                |(dependency, semaphore)| {
+                    (
+                        dependency.queue.clone(),
+                        Arc::clone(semaphore),
+                        dependency.wait_stage,
+                    )
+                },
+            ));
+
+            let command_buffer = queue.command_pool.get_mut(frame.command_buffer).unwrap();
             command_buffer.end();
+            queue
+                .executor
+                .submit(QueueExecutorSubmitInfo {
+                    command_buffers: vec![command_buffer],
+                    frame_index,
+                    wait_semaphores: wait_semaphores
+                        .iter()
+                        .map(|(semaphore, stage)| (semaphore.as_ref(), *stage))
+                        .collect(),
+                    signal_semaphores: signal_semaphores.iter().map(Arc::as_ref).collect(),
+                    fence: Some(&frame.fence),
+                })
+                .unwrap_or_else(|err| panic!("Failed to submit the {} queue: {}", queue.name, err));
+            frame.is_recording = false;
+            frame.is_submitted = true;
+        }
 
-            unsafe {
-                render_manager
-                    .vulkan_dep
-                    .device()
-                    .queue_submit(
-                        render_manager.vulkan_dep.default_queue().queue(),
-                        &[vk::SubmitInfo::builder()
-                            .command_buffers(&[command_buffer.command_buffer()])
-                            .wait_semaphores(&[frame.image_available_semaphore.semaphore()])
-                            .wait_dst_stage_mask(&[vk::PipelineStageFlags::BOTTOM_OF_PIPE])
-                            .signal_semaphores(&[frame.render_finished_semaphore.semaphore()])
-                            .build()],
-                        frame.fence.fence(),
+        waits
+            .into_iter()
+            .filter(|(queue_name, _, _)| queue_name == DEFAULT_QUEUE)
+            .map(|(_, semaphore, stage)| (semaphore, stage))
+            .collect()
+    }
+
+    /// The first depth format the device supports as a sampled depth attachment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the device supports none of them.
+    fn select_depth_format(vulkan: &Vulkan) -> vk::Format {
+        [vk::Format::D32_SFLOAT, vk::Format::D24_UNORM_S8_UINT]
+            .into_iter()
+            .find(|&format| {
+                let format_properties = unsafe {
+                    vulkan.instance().get_physical_device_format_properties(
+                        vulkan.physical_device().physical_device(),
+                        format,
                     )
-                    .expect("Failed to submit queue");
-            }
+                };
+                format_properties.optimal_tiling_features.contains(
+                    vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+                        | vk::FormatFeatureFlags::SAMPLED_IMAGE,
+                )
+            })
+            .expect("The device supports neither D32_SFLOAT nor D24_UNORM_S8_UINT depth.")
+    }
+
+    /// The resolve target matching the multisampled backbuffer's format and extent, recreated
+    /// when either changed. The previous target is kept alive by the arenas of the frames using
+    /// it.
+    fn resolve_target<'a>(
+        resolve_target: &'a mut Option<ResolveTarget>,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        frame_config: &FrameConfig,
+    ) -> &'a OwnedImage {
+        let is_outdated = resolve_target.as_ref().is_none_or(|resolve_target| {
+            resolve_target.format != frame_config.backbuffer_format
+                || resolve_target.extent != frame_config.backbuffer_extent
+        });
+        if is_outdated {
+            let extent = frame_config.backbuffer_extent;
+            *resolve_target = Some(ResolveTarget {
+                image: OwnedImage::new(
+                    vulkan,
+                    vulkan_allocator,
+                    &OwnedImageCreateInfo {
+                        image_type: vk::ImageType::TYPE_2D,
+                        width: extent.width,
+                        height: extent.height,
+                        format: frame_config.backbuffer_format,
+                        usage: vk::ImageUsageFlags::TRANSFER_DST
+                            | vk::ImageUsageFlags::TRANSFER_SRC,
+                        samples: vk::SampleCountFlags::TYPE_1,
+                        mip_levels: 1,
+                        view_create_info: None,
+                        name: Some("backbuffer resolve target".to_string()),
+                    },
+                ),
+                format: frame_config.backbuffer_format,
+                extent,
+            });
+        }
+
+        &resolve_target.as_ref().unwrap().image
+    }
 
-            let present_result =
-                swapchain.present(image_index, &[&frame.render_finished_semaphore]);
-            if present_result.is_err() {
-                println!("Suboptimal khr");
-                swapchain.refresh();
+    /// Records resolving the multisampled backbuffer to `resolve_target`, leaving the backbuffer
+    /// in the transfer source and the resolve target in the transfer destination layout.
+    fn record_resolve(
+        vulkan: &Vulkan,
+        command_buffer: &mut CommandBuffer,
+        frame_config: &FrameConfig,
+        resolve_target: &OwnedImage,
+    ) {
+        command_buffer.transition_image(
+            &frame_config.backbuffer_image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        );
+        command_buffer.transition_image(resolve_target, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+
+        let subresource = color_subresource_layers();
+        let extent = frame_config.backbuffer_extent;
+        let region = vk::ImageResolve::default()
+            .src_subresource(subresource)
+            .dst_subresource(subresource)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            });
+        unsafe {
+            vulkan.device().cmd_resolve_image(
+                command_buffer.command_buffer(),
+                frame_config.backbuffer_image.instance().image(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                resolve_target.instance().image(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+            );
+        }
+    }
+}
+
+fn allocate_command_buffers(
+    command_pool: &mut CommandPool,
+    count: u32,
+) -> Vec<CommandBufferHandle> {
+    (0..count)
+        .map(|_| {
+            let [command_buffer] = command_pool.allocate::<1>();
+            command_buffer
+        })
+        .collect()
+}
+
+fn color_subresource_layers() -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .mip_level(0)
+        .base_array_layer(0)
+        .layer_count(1)
+}
+
+/// The far corner of an image of the extent, for blits.
+fn extent_offset(extent: vk::Extent2D) -> vk::Offset3D {
+    vk::Offset3D {
+        x: extent.width as i32,
+        y: extent.height as i32,
+        z: 1,
+    }
+}
+
+/// A copy of the backbuffer in host visible memory, waiting for its frame to finish.
+struct PendingScreenshot {
+    buffer: UntypedBuffer,
+    extent: vk::Extent2D,
+    /// Whether the backbuffer stores blue before red.
+    is_bgra: bool,
+    paths: Vec<PathBuf>,
+}
+
+impl PendingScreenshot {
+    /// Records copying the backbuffer, which must be in the transfer source layout, to a host
+    /// visible buffer. `None` if the backbuffer's format can't be saved.
+    fn record(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        command_buffer: &mut CommandBuffer,
+        backbuffer_image: &dyn Image,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        paths: Vec<PathBuf>,
+    ) -> Option<Self> {
+        let is_bgra = match format {
+            vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB => false,
+            vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => true,
+            format => {
+                log::error!("Screenshots of a {:?} backbuffer aren't supported.", format);
+                return None;
             }
+        };
+
+        let buffer = UntypedBuffer::new(
+            vulkan,
+            vulkan_allocator,
+            &BufferCreateInfo {
+                size: extent.width as u64 * extent.height as u64 * 4,
+                usage: vk::BufferUsageFlags::TRANSFER_DST,
+                memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+                name: Some("screenshot".to_string()),
+            },
+        );
+
+        let region = vk::BufferImageCopy::default()
+            .image_subresource(color_subresource_layers())
+            .image_extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            });
+        unsafe {
+            vulkan.device().cmd_copy_image_to_buffer(
+                command_buffer.command_buffer(),
+                backbuffer_image.instance().image(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer.instance().buffer(),
+                &[region],
+            );
         }
 
-        // Update frame index.
-        render_manager.frame_index =
-            (render_manager.frame_index + 1) % render_manager.frames_in_flight() as usize;
+        // Makes the copy visible to the host once the frame's fence is signalled.
+        command_buffer.memory_barrier(
+            vk::PipelineStageFlags2::ALL_TRANSFER,
+            vk::AccessFlags2::TRANSFER_WRITE,
+            vk::PipelineStageFlags2::HOST,
+            vk::AccessFlags2::HOST_READ,
+        );
+
+        Some(Self {
+            buffer,
+            extent,
+            is_bgra,
+            paths,
+        })
+    }
+
+    /// Reads the copy back and writes the PNGs on a separate thread.
+    fn save(self) {
+        let len = (self.extent.width * self.extent.height * 4) as usize;
+        let mut pixels = self.buffer.read_bytes(0, len);
+
+        let Self {
+            extent,
+            is_bgra,
+            paths,
+            ..
+        } = self;
+        std::thread::spawn(move || {
+            for pixel in pixels.chunks_exact_mut(4) {
+                if is_bgra {
+                    pixel.swap(0, 2);
+                }
+                // The backbuffer's alpha isn't meaningful once presented.
+                pixel[3] = u8::MAX;
+            }
+
+            for path in paths {
+                if let Err(err) = image::save_buffer(
+                    &path,
+                    &pixels,
+                    extent.width,
+                    extent.height,
+                    image::ColorType::Rgba8,
+                ) {
+                    log::error!("Failed to save screenshot to {}: {}", path.display(), err);
+                }
+            }
+        });
     }
 }
@@ -0,0 +1,390 @@
+use std::sync::Arc;
+
+use ash::vk;
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator,
+    objects::{
+        util::ImageViewCreateInfo, CommandBuffer, Image, OwnedImage, OwnedImageCreateInfo,
+        RenderingAttachment, RenderingFormats, RenderingInfo, Texture,
+    },
+    util::Extent2D,
+    Vulkan,
+};
+
+pub struct RenderTargetCreateInfo {
+    pub width: u32,
+    pub height: u32,
+    pub color_format: vk::Format,
+    /// The format of the depth target, `None` for a target without depth.
+    pub depth_format: Option<vk::Format>,
+    /// The sample count of the color and depth targets. A multisampled color target is resolved
+    /// to the texture when the pass ends.
+    pub samples: vk::SampleCountFlags,
+    /// The name shown in debugging tools.
+    pub name: String,
+}
+
+/// An offscreen color target with an optional depth target, rendered to by one pass and sampled
+/// as a texture by later ones, such as a minimap or a security camera screen.
+///
+/// ```ignore
+/// render_target.begin(command_buffer, [0.0, 0.0, 0.0, 1.0]);
+/// // Draw the scene from the minimap camera.
+/// render_target.end(command_buffer);
+///
+/// sprite_batch.submit(&Sprite::new(render_target.color().clone()), transform);
+/// ```
+pub struct RenderTarget {
+    color: Arc<Texture>,
+    /// The multisampled color target resolved to `color`, `None` with a single sample.
+    multisampled_color: Option<OwnedImage>,
+    depth: Option<OwnedImage>,
+    extent: vk::Extent2D,
+    color_format: vk::Format,
+    depth_format: Option<vk::Format>,
+    samples: vk::SampleCountFlags,
+}
+
+impl RenderTarget {
+    pub fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: &RenderTargetCreateInfo,
+    ) -> Self {
+        let extent = vk::Extent2D {
+            width: info.width,
+            height: info.height,
+        };
+        let color = create_render_target(
+            vulkan,
+            vulkan_allocator,
+            info.color_format,
+            extent,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            vk::SampleCountFlags::TYPE_1,
+            &format!("{} color", info.name),
+        );
+        let multisampled_color = (info.samples != vk::SampleCountFlags::TYPE_1).then(|| {
+            create_render_target(
+                vulkan,
+                vulkan_allocator,
+                info.color_format,
+                extent,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT,
+                info.samples,
+                &format!("{} multisampled color", info.name),
+            )
+        });
+        let depth = info.depth_format.map(|depth_format| {
+            create_render_target(
+                vulkan,
+                vulkan_allocator,
+                depth_format,
+                extent,
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                info.samples,
+                &format!("{} depth", info.name),
+            )
+        });
+
+        Self {
+            color: Arc::new(Texture::from_owned_image(
+                vulkan,
+                color,
+                Extent2D {
+                    width: info.width,
+                    height: info.height,
+                },
+            )),
+            multisampled_color,
+            depth,
+            extent,
+            color_format: info.color_format,
+            depth_format: info.depth_format,
+            samples: info.samples,
+        }
+    }
+
+    /// The color target as a texture, valid to sample once the pass rendering to it ended.
+    pub fn color(&self) -> &Arc<Texture> {
+        &self.color
+    }
+
+    pub fn depth(&self) -> Option<&OwnedImage> {
+        self.depth.as_ref()
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The formats pipelines drawing to the target are created with.
+    pub fn rendering_formats(&self) -> RenderingFormats {
+        RenderingFormats {
+            color_formats: vec![self.color_format],
+            depth_format: self.depth_format,
+            samples: self.samples,
+        }
+    }
+
+    /// Begins rendering to the whole target, clearing the color to `clear_color` and the depth
+    /// to 1.
+    pub fn begin(&self, command_buffer: &mut CommandBuffer, clear_color: [f32; 4]) {
+        // Only the resolved samples are kept when multisampling.
+        let (color_image, color_store_op, resolve_image) = match &self.multisampled_color {
+            Some(multisampled_color) => (
+                multisampled_color as &dyn Image,
+                vk::AttachmentStoreOp::DONT_CARE,
+                Some(self.color.image() as &dyn Image),
+            ),
+            None => (
+                self.color.image() as &dyn Image,
+                vk::AttachmentStoreOp::STORE,
+                None,
+            ),
+        };
+        command_buffer.begin_rendering(RenderingInfo {
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D::default(),
+                extent: self.extent,
+            },
+            color_attachments: vec![RenderingAttachment {
+                image: color_image,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: color_store_op,
+                clear_value: vk::ClearValue {
+                    color: vk::ClearColorValue {
+                        float32: clear_color,
+                    },
+                },
+                resolve_image,
+            }],
+            depth_attachment: self.depth.as_ref().map(|depth| RenderingAttachment {
+                image: depth,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                clear_value: vk::ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue {
+                        depth: 1.0,
+                        stencil: 0,
+                    },
+                },
+                resolve_image: None,
+            }),
+            secondary_command_buffers: false,
+        });
+        command_buffer.set_viewport_and_scissor(vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent: self.extent,
+        });
+    }
+
+    /// Ends rendering and transitions the color target to the shader read only layout, so the
+    /// passes recorded afterwards can sample it.
+    ///
+    /// Passes of later frames can keep sampling it without rendering again, since sampling
+    /// doesn't change its layout.
+    pub fn end(&self, command_buffer: &mut CommandBuffer) {
+        command_buffer.end_rendering();
+        command_buffer.transition_image(
+            self.color.image(),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+    }
+}
+
+/// An image of a [`RenderTargetSet`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderTargetDesc {
+    pub name: String,
+    pub format: vk::Format,
+    pub usage: vk::ImageUsageFlags,
+}
+
+impl RenderTargetDesc {
+    pub fn new(name: &str, format: vk::Format, usage: vk::ImageUsageFlags) -> Self {
+        Self {
+            name: name.to_string(),
+            format,
+            usage,
+        }
+    }
+}
+
+/// Render targets sharing an extent which are created and resized together, such as the color,
+/// normal and depth targets of a GBuffer.
+///
+/// Depth formats are picked up as the depth target, every other target is a color target in the
+/// order they were described.
+pub struct RenderTargetSet {
+    descs: Vec<RenderTargetDesc>,
+    samples: vk::SampleCountFlags,
+    targets: Vec<OwnedImage>,
+    extent: vk::Extent2D,
+}
+
+impl RenderTargetSet {
+    /// # Panics
+    ///
+    /// Panics if two targets share a name or more than one target has a depth format.
+    pub fn new(descs: Vec<RenderTargetDesc>, samples: vk::SampleCountFlags) -> Self {
+        for (index, desc) in descs.iter().enumerate() {
+            assert!(
+                descs[..index].iter().all(|other| other.name != desc.name),
+                "Render target {} described twice.",
+                desc.name
+            );
+        }
+        assert!(
+            descs
+                .iter()
+                .filter(|desc| is_depth_format(desc.format))
+                .count()
+                <= 1,
+            "A render target set can only have one depth target."
+        );
+
+        Self {
+            descs,
+            samples,
+            targets: Vec::new(),
+            extent: vk::Extent2D::default(),
+        }
+    }
+
+    /// Recreates every target if the extent changed, returning whether they were recreated. The
+    /// previous targets must no longer be used by frames in flight.
+    pub fn resize(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        extent: vk::Extent2D,
+    ) -> bool {
+        if !self.targets.is_empty() && self.extent == extent {
+            return false;
+        }
+
+        self.targets = self
+            .descs
+            .iter()
+            .map(|desc| {
+                create_render_target(
+                    vulkan,
+                    vulkan_allocator,
+                    desc.format,
+                    extent,
+                    desc.usage,
+                    self.samples,
+                    &desc.name,
+                )
+            })
+            .collect();
+        self.extent = extent;
+        true
+    }
+
+    /// The extent of the targets, zero until the set was first resized.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The target with the given name, `None` if there is none or the set wasn't resized yet.
+    pub fn get(&self, name: &str) -> Option<&OwnedImage> {
+        let index = self.descs.iter().position(|desc| desc.name == name)?;
+        self.targets.get(index)
+    }
+
+    /// The color targets in the order they were described, matching the color attachments of
+    /// [`RenderTargetSet::rendering_formats`].
+    pub fn color_targets(&self) -> impl Iterator<Item = &OwnedImage> {
+        self.descs
+            .iter()
+            .zip(&self.targets)
+            .filter(|(desc, _)| !is_depth_format(desc.format))
+            .map(|(_, target)| target)
+    }
+
+    pub fn depth_target(&self) -> Option<&OwnedImage> {
+        self.descs
+            .iter()
+            .zip(&self.targets)
+            .find(|(desc, _)| is_depth_format(desc.format))
+            .map(|(_, target)| target)
+    }
+
+    /// The formats pipelines drawing to the whole set are created with.
+    pub fn rendering_formats(&self) -> RenderingFormats {
+        RenderingFormats {
+            color_formats: self
+                .descs
+                .iter()
+                .filter(|desc| !is_depth_format(desc.format))
+                .map(|desc| desc.format)
+                .collect(),
+            depth_format: self
+                .descs
+                .iter()
+                .find(|desc| is_depth_format(desc.format))
+                .map(|desc| desc.format),
+            samples: self.samples,
+        }
+    }
+}
+
+pub(crate) fn is_depth_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D16_UNORM
+            | vk::Format::D32_SFLOAT
+            | vk::Format::X8_D24_UNORM_PACK32
+            | vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+/// Creates a single mip 2D image with a view covering it.
+pub(crate) fn create_render_target(
+    vulkan: &Vulkan,
+    vulkan_allocator: &mut VulkanMemoryAllocator,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    usage: vk::ImageUsageFlags,
+    samples: vk::SampleCountFlags,
+    name: &str,
+) -> OwnedImage {
+    let aspect_mask = match format {
+        vk::Format::D16_UNORM_S8_UINT
+        | vk::Format::D24_UNORM_S8_UINT
+        | vk::Format::D32_SFLOAT_S8_UINT => {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        }
+        format if is_depth_format(format) => vk::ImageAspectFlags::DEPTH,
+        _ => vk::ImageAspectFlags::COLOR,
+    };
+
+    OwnedImage::new(
+        vulkan,
+        vulkan_allocator,
+        &OwnedImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            width: extent.width,
+            height: extent.height,
+            format,
+            usage,
+            samples,
+            mip_levels: 1,
+            view_create_info: Some(ImageViewCreateInfo {
+                view_type: vk::ImageViewType::TYPE_2D,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+            }),
+            name: Some(name.to_string()),
+        },
+    )
+}
@@ -0,0 +1,610 @@
+use std::collections::HashMap;
+
+use ash::vk;
+use pyrite_app::{
+    plugin::Plugin,
+    resource::{Res, ResMut, Resource},
+    AppBuilder,
+};
+use pyrite_math::{
+    Frustum, GlobalTransform, Mat4, OrthographicProjection, PerspectiveProjection, Projection,
+    Vec3, Vec4,
+};
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator,
+    objects::{
+        CommandBuffer, DescriptorSetWriter, GraphicsPipeline, GraphicsPipelineCreateInfo,
+        OwnedImage, PipelineLayoutCreateInfo, RenderingAttachment, RenderingFormats, RenderingInfo,
+        Sampler, SamplerCreateInfo, Shader, TypedBuffer, TypedBufferCreateInfo,
+    },
+    Vulkan,
+};
+
+use crate::{
+    forward::{DrawList, DrawMesh, ForwardCamera, ForwardRenderer, FORWARD_RENDER_STAGE},
+    render_manager::RenderManager,
+    render_target::create_render_target,
+};
+
+/// The stage the shadow maps are rendered in, right before the [`FORWARD_RENDER_STAGE`].
+pub const SHADOW_RENDER_STAGE: &str = "shadow_render";
+
+/// The descriptor set holding the [`ShadowUniform`] at binding 0 and the shadow map at binding
+/// 1, bound by the forward renderer for materials whose pipeline layout has it.
+pub const SHADOW_SET: u32 = 2;
+
+/// The most cascades a shadow map can be split into.
+pub const MAX_SHADOW_CASCADES: usize = 4;
+
+/// The format of the shadow map, which every device supports as a sampled depth attachment.
+const SHADOW_MAP_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+
+/// Adds cascaded shadows of the [`DirectionalLight`] to the forward renderer, see
+/// [`setup_shadows`].
+pub struct ShadowPlugin {
+    pub config: ShadowConfig,
+}
+
+impl Plugin for ShadowPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        setup_shadows(app_builder, &self.config);
+    }
+}
+
+/// Sets up the [`DirectionalLight`] resource and renders the depth of the forward renderer's
+/// [`DrawList`] from it into cascaded shadow maps every frame, which requires the forward
+/// renderer to be set up first.
+///
+/// Material shaders include `shaders/shadow.glsl` in this crate to read the shadow maps from
+/// [`SHADOW_SET`] with percentage closer filtering.
+pub fn setup_shadows(app_builder: &mut AppBuilder, config: &ShadowConfig) {
+    let shadow_renderer = ShadowRenderer::new(
+        &app_builder.get_resource::<Vulkan>(),
+        &mut app_builder.get_resource_mut::<VulkanMemoryAllocator>(),
+        app_builder
+            .get_resource::<RenderManager>()
+            .frames_in_flight() as usize,
+        config.clone(),
+    );
+    app_builder
+        .get_resource_mut::<ForwardRenderer>()
+        .set_shadow_renderer(shadow_renderer);
+
+    app_builder
+        .add_resource(DirectionalLight::default())
+        .add_stage_before(SHADOW_RENDER_STAGE, FORWARD_RENDER_STAGE)
+        .add_system_to_stage(ShadowRenderer::render_system, SHADOW_RENDER_STAGE);
+}
+
+#[derive(Clone)]
+pub struct ShadowConfig {
+    /// The number of cascades the camera's view is split into, at most [`MAX_SHADOW_CASCADES`].
+    pub cascade_count: u32,
+    /// The width and height of each cascade's shadow map in texels.
+    pub resolution: u32,
+    /// The distance from the camera shadows are drawn up to, the camera's far plane if closer.
+    pub max_distance: f32,
+    /// Blends the cascade splits between even spacing at 0 and logarithmic spacing at 1, which
+    /// gives closer cascades more resolution.
+    pub split_lambda: f32,
+    /// How far towards the light casters outside of a cascade are still drawn into it.
+    pub caster_distance: f32,
+    /// The depth subtracted before comparing against the shadow map, against shadow acne.
+    pub depth_bias: f32,
+    /// The SPIR-V of the vertex shader, `shaders/shadow.vert` in this crate reads the position
+    /// at location 0 and the light space model view projection push constant.
+    pub vertex_shader: Vec<u32>,
+    /// The SPIR-V of the fragment shader, `shaders/shadow.frag` in this crate.
+    pub fragment_shader: Vec<u32>,
+}
+
+/// The light casting the shadows, shining in the same direction everywhere like the sun.
+#[derive(Resource, Clone, Copy)]
+pub struct DirectionalLight {
+    /// The direction the light travels in, normalized.
+    pub direction: Vec3,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(-0.4, -1.0, -0.3).normalize(),
+        }
+    }
+}
+
+/// The light space matrices of the cascades, laid out like the std140 uniform block in
+/// `shaders/shadow.glsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowUniform {
+    pub light_view_projections: [Mat4; MAX_SHADOW_CASCADES],
+    /// The view space distance each cascade ends at, unused cascades are 0.
+    pub cascade_splits: Vec4,
+    /// The direction the light travels in, `w` is 0.
+    pub light_direction: Vec4,
+    pub cascade_count: u32,
+    pub depth_bias: f32,
+    /// The size of a texel of the shadow map in UV coordinates.
+    pub texel_size: [f32; 2],
+}
+
+/// Renders the depth of the draws from the [`DirectionalLight`] into one shadow map with the
+/// cascades side by side, owned by the [`ForwardRenderer`] which binds it to [`SHADOW_SET`].
+pub struct ShadowRenderer {
+    config: ShadowConfig,
+    vertex_shader: Shader,
+    fragment_shader: Shader,
+    /// The depth only pipelines by the vertex stride, position format and position offset of
+    /// the meshes drawn so far.
+    pipelines: HashMap<(u32, vk::Format, u32), GraphicsPipeline>,
+    shadow_map: OwnedImage,
+    sampler: Sampler,
+    uniform_buffers: Vec<TypedBuffer<ShadowUniform>>,
+}
+
+impl ShadowRenderer {
+    /// # Panics
+    /// If the cascade count isn't between 1 and [`MAX_SHADOW_CASCADES`], or the cascades don't
+    /// fit in the largest image the device supports.
+    fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        frames_in_flight: usize,
+        config: ShadowConfig,
+    ) -> Self {
+        assert!(
+            (1..=MAX_SHADOW_CASCADES as u32).contains(&config.cascade_count),
+            "Shadow cascade count {} isn't between 1 and {}.",
+            config.cascade_count,
+            MAX_SHADOW_CASCADES
+        );
+        let max_image_dimension = vulkan
+            .physical_device()
+            .properties()
+            .limits
+            .max_image_dimension2_d;
+        assert!(
+            config.resolution * config.cascade_count <= max_image_dimension,
+            "{} shadow cascades of {} texels exceed the max image size of {}.",
+            config.cascade_count,
+            config.resolution,
+            max_image_dimension
+        );
+
+        let shadow_map = create_render_target(
+            vulkan,
+            vulkan_allocator,
+            SHADOW_MAP_FORMAT,
+            vk::Extent2D {
+                width: config.resolution * config.cascade_count,
+                height: config.resolution,
+            },
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            vk::SampleCountFlags::TYPE_1,
+            "shadow map",
+        );
+        // The shader filters the map itself and treats positions outside of it as lit.
+        let sampler = Sampler::new(
+            vulkan,
+            &SamplerCreateInfo {
+                mag_filter: vk::Filter::NEAREST,
+                min_filter: vk::Filter::NEAREST,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_anisotropy: None,
+            },
+        );
+        let uniform_buffers = (0..frames_in_flight)
+            .map(|_| {
+                TypedBuffer::new(
+                    vulkan,
+                    vulkan_allocator,
+                    &TypedBufferCreateInfo {
+                        len: 1,
+                        usage: vk::BufferUsageFlags::UNIFORM_BUFFER,
+                        memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                            | vk::MemoryPropertyFlags::HOST_COHERENT,
+                        name: Some("shadow".to_string()),
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            vertex_shader: Shader::new(vulkan, &config.vertex_shader),
+            fragment_shader: Shader::new(vulkan, &config.fragment_shader),
+            config,
+            pipelines: HashMap::new(),
+            shadow_map,
+            sampler,
+            uniform_buffers,
+        }
+    }
+
+    /// Writes the frame's [`ShadowUniform`] and the shadow map to a [`SHADOW_SET`].
+    pub(crate) fn write_shadow_set(
+        &self,
+        writer: &mut DescriptorSetWriter<'_>,
+        frame_index: usize,
+    ) {
+        writer
+            .uniform_buffer(0, 0, &self.uniform_buffers[frame_index])
+            .combined_image_sampler(
+                1,
+                0,
+                &self.shadow_map,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                &self.sampler,
+            );
+    }
+
+    pub fn render_system(
+        mut forward_renderer: ResMut<ForwardRenderer>,
+        mut render_manager: ResMut<RenderManager>,
+        draw_list: Res<DrawList>,
+        camera: Res<ForwardCamera>,
+        light: Res<DirectionalLight>,
+        vulkan: Res<Vulkan>,
+    ) {
+        pyrite_util::profile_scope!("ShadowRenderer::render_system");
+
+        let shadow_renderer = forward_renderer
+            .shadow_renderer_mut()
+            .expect("The shadow renderer wasn't set up");
+        let frame_index = render_manager.frame_index();
+        let cascades = shadow_renderer.cascades(&camera.transform, &camera.projection, &light);
+        shadow_renderer.write_uniform(frame_index, &cascades, &light);
+        shadow_renderer.render(
+            &vulkan,
+            render_manager.command_buffer_mut(),
+            &draw_list,
+            &cascades,
+        );
+    }
+
+    /// The far distance and light view projection of each cascade.
+    fn cascades(
+        &self,
+        camera_transform: &GlobalTransform,
+        camera_projection: &PerspectiveProjection,
+        light: &DirectionalLight,
+    ) -> Vec<(f32, Mat4)> {
+        let near = camera_projection.near;
+        let far = camera_projection.far.min(self.config.max_distance);
+        let splits = cascade_splits(
+            near,
+            far,
+            self.config.cascade_count,
+            self.config.split_lambda,
+        );
+
+        let camera_matrix = camera_transform.compute_matrix();
+        let mut cascade_near = near;
+        splits
+            .into_iter()
+            .map(|cascade_far| {
+                let corners = frustum_slice_corners(camera_projection, cascade_near, cascade_far)
+                    .map(|corner| camera_matrix.transform_point3(corner));
+                cascade_near = cascade_far;
+                (
+                    cascade_far,
+                    light_view_projection(
+                        &corners,
+                        light.direction,
+                        self.config.resolution,
+                        self.config.caster_distance,
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn write_uniform(
+        &mut self,
+        frame_index: usize,
+        cascades: &[(f32, Mat4)],
+        light: &DirectionalLight,
+    ) {
+        let mut light_view_projections = [Mat4::IDENTITY; MAX_SHADOW_CASCADES];
+        let mut splits = [0.0; MAX_SHADOW_CASCADES];
+        for (i, (split, light_view_projection)) in cascades.iter().enumerate() {
+            light_view_projections[i] = *light_view_projection;
+            splits[i] = *split;
+        }
+
+        self.uniform_buffers[frame_index].write_slice(
+            0,
+            &[ShadowUniform {
+                light_view_projections,
+                cascade_splits: Vec4::from_array(splits),
+                light_direction: light.direction.extend(0.0),
+                cascade_count: self.config.cascade_count,
+                depth_bias: self.config.depth_bias,
+                texel_size: [
+                    1.0 / (self.config.resolution * self.config.cascade_count) as f32,
+                    1.0 / self.config.resolution as f32,
+                ],
+            }],
+        );
+    }
+
+    /// Records the depth only pass drawing every cascade into its part of the shadow map,
+    /// leaving the map in the shader read only layout for the forward pass.
+    fn render(
+        &mut self,
+        vulkan: &Vulkan,
+        command_buffer: &mut CommandBuffer,
+        draw_list: &DrawList,
+        cascades: &[(f32, Mat4)],
+    ) {
+        let resolution = self.config.resolution;
+        command_buffer.begin_label("shadows", [1.0, 1.0, 1.0, 1.0]);
+        command_buffer.begin_rendering(RenderingInfo {
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D::default(),
+                extent: vk::Extent2D {
+                    width: resolution * self.config.cascade_count,
+                    height: resolution,
+                },
+            },
+            color_attachments: Vec::new(),
+            depth_attachment: Some(RenderingAttachment {
+                image: &self.shadow_map,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::STORE,
+                clear_value: vk::ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue {
+                        depth: 1.0,
+                        stencil: 0,
+                    },
+                },
+                resolve_image: None,
+            }),
+            secondary_command_buffers: false,
+        });
+
+        for (i, (_, light_view_projection)) in cascades.iter().enumerate() {
+            command_buffer.set_viewport_and_scissor(vk::Rect2D {
+                offset: vk::Offset2D {
+                    x: (i as u32 * resolution) as i32,
+                    y: 0,
+                },
+                extent: vk::Extent2D {
+                    width: resolution,
+                    height: resolution,
+                },
+            });
+
+            let frustum = Frustum::from_view_projection(light_view_projection);
+            for draw in draw_list.draws() {
+                let bounds = draw.mesh.bounds().transformed(&draw.transform.affine());
+                if !frustum.intersects_aabb(&bounds) {
+                    continue;
+                }
+
+                let mesh = &*draw.mesh.mesh;
+                let Some(pipeline) = self.pipeline(vulkan, mesh) else {
+                    continue;
+                };
+                command_buffer.bind_graphics_pipeline(pipeline);
+                command_buffer.push_constants(
+                    vk::PipelineBindPoint::GRAPHICS,
+                    vk::ShaderStageFlags::VERTEX,
+                    0,
+                    &(*light_view_projection * draw.transform.compute_matrix()),
+                );
+                mesh.bind(command_buffer);
+                mesh.draw(command_buffer, 1);
+            }
+        }
+
+        command_buffer.end_rendering();
+        command_buffer
+            .transition_image(&self.shadow_map, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        command_buffer.end_label();
+    }
+
+    /// The depth only pipeline reading the position of the mesh's vertices, created the first
+    /// time a vertex layout is drawn. `None` if the mesh has no attribute at location 0.
+    fn pipeline(&mut self, vulkan: &Vulkan, mesh: &dyn DrawMesh) -> Option<&GraphicsPipeline> {
+        let binding = mesh.vertex_binding();
+        let position = mesh
+            .vertex_attributes()
+            .into_iter()
+            .find(|attribute| attribute.location == 0)?;
+
+        let key = (binding.stride, position.format, position.offset);
+        Some(self.pipelines.entry(key).or_insert_with(|| {
+            GraphicsPipeline::new(
+                vulkan,
+                GraphicsPipelineCreateInfo {
+                    vertex_shader: &self.vertex_shader,
+                    vertex_entry_point: "main".to_string(),
+                    fragment_shader: &self.fragment_shader,
+                    fragment_entry_point: "main".to_string(),
+                    pipeline_layout_info: PipelineLayoutCreateInfo::default(),
+                    vertex_bindings: vec![binding],
+                    vertex_attributes: vec![position],
+                    topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                    polygon_mode: vk::PolygonMode::FILL,
+                    // Both faces are drawn so open meshes still cast shadows.
+                    cull_mode: vk::CullModeFlags::NONE,
+                    front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+                    alpha_blending: false,
+                    depth_compare_op: vk::CompareOp::LESS_OR_EQUAL,
+                    rendering_formats: RenderingFormats {
+                        color_formats: Vec::new(),
+                        depth_format: Some(SHADOW_MAP_FORMAT),
+                        samples: vk::SampleCountFlags::TYPE_1,
+                    },
+                    name: Some(format!("shadow stride {}", binding.stride)),
+                },
+            )
+        }))
+    }
+}
+
+/// The view space distances the cascades between `near` and `far` end at, blending even and
+/// logarithmic splits by `lambda`.
+pub fn cascade_splits(near: f32, far: f32, cascade_count: u32, lambda: f32) -> Vec<f32> {
+    (1..=cascade_count)
+        .map(|i| {
+            let t = i as f32 / cascade_count as f32;
+            let logarithmic = near * (far / near).powf(t);
+            let uniform = near + (far - near) * t;
+            lambda * logarithmic + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// The view space corners of the part of the camera's frustum between `near` and `far`.
+fn frustum_slice_corners(projection: &PerspectiveProjection, near: f32, far: f32) -> [Vec3; 8] {
+    let tan_half_fov_y = (projection.fov_y * 0.5).tan();
+    let corners = |distance: f32| {
+        let half_height = distance * tan_half_fov_y;
+        let half_width = half_height * projection.aspect_ratio;
+        [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+            .map(|(x, y)| Vec3::new(x * half_width, y * half_height, -distance))
+    };
+
+    let [a, b, c, d] = corners(near);
+    let [e, f, g, h] = corners(far);
+    [a, b, c, d, e, f, g, h]
+}
+
+/// An orthographic light view projection covering the bounding sphere of a frustum slice.
+///
+/// Bounding the sphere keeps the projection's size constant as the camera rotates, and its
+/// center is snapped to whole texels, so the shadow edges don't shimmer when the camera moves.
+fn light_view_projection(
+    corners: &[Vec3; 8],
+    light_direction: Vec3,
+    resolution: u32,
+    caster_distance: f32,
+) -> Mat4 {
+    let center = corners.iter().copied().sum::<Vec3>() / corners.len() as f32;
+    let radius = corners
+        .iter()
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max);
+    // Rounded up so floating point noise doesn't change the texel size between frames.
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let up = if light_direction.cross(Vec3::Y).length_squared() < 1e-6 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let light_rotation = Mat4::look_to_rh(Vec3::ZERO, light_direction, up);
+    let texel_size = radius * 2.0 / resolution as f32;
+    let light_space_center = light_rotation.transform_point3(center);
+    let snapped_center = light_rotation.inverse().transform_point3(Vec3::new(
+        (light_space_center.x / texel_size).floor() * texel_size,
+        (light_space_center.y / texel_size).floor() * texel_size,
+        light_space_center.z,
+    ));
+
+    let eye = snapped_center - light_direction * (radius + caster_distance);
+    let view = Mat4::look_to_rh(eye, light_direction, up);
+    let projection = OrthographicProjection {
+        left: -radius,
+        right: radius,
+        bottom: -radius,
+        top: radius,
+        near: 0.0,
+        far: radius * 2.0 + caster_distance,
+    };
+    projection.compute_matrix() * view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn cascade_splits_blend_uniform_and_logarithmic() {
+        let uniform = cascade_splits(1.0, 100.0, 4, 0.0);
+        for (split, expected) in uniform.iter().zip([25.75, 50.5, 75.25, 100.0]) {
+            assert_close(*split, expected);
+        }
+
+        let logarithmic = cascade_splits(1.0, 100.0, 4, 1.0);
+        for (split, expected) in logarithmic.iter().zip([3.1623, 10.0, 31.623, 100.0]) {
+            assert_close(*split, expected);
+        }
+
+        let blended = cascade_splits(1.0, 100.0, 4, 0.5);
+        for ((split, uniform), logarithmic) in blended.iter().zip(&uniform).zip(&logarithmic) {
+            assert_close(*split, (uniform + logarithmic) * 0.5);
+        }
+    }
+
+    #[test]
+    fn frustum_slice_corners_follow_the_field_of_view() {
+        let projection = PerspectiveProjection {
+            fov_y: std::f32::consts::FRAC_PI_2,
+            aspect_ratio: 2.0,
+            ..Default::default()
+        };
+        let corners = frustum_slice_corners(&projection, 1.0, 3.0);
+
+        for corner in &corners[..4] {
+            assert_close(corner.z, -1.0);
+            assert_close(corner.x.abs(), 2.0);
+            assert_close(corner.y.abs(), 1.0);
+        }
+        for corner in &corners[4..] {
+            assert_close(corner.z, -3.0);
+            assert_close(corner.x.abs(), 6.0);
+            assert_close(corner.y.abs(), 3.0);
+        }
+    }
+
+    #[test]
+    fn light_view_projection_covers_the_slice() {
+        let projection = PerspectiveProjection::default();
+        let corners = frustum_slice_corners(&projection, 0.5, 20.0);
+        let resolution = 1024;
+        // The snapped center can move the slice by up to a texel.
+        let texel = 2.0 / resolution as f32;
+
+        for light_direction in [Vec3::new(-1.0, -2.0, -0.5).normalize(), Vec3::NEG_Y] {
+            let view_projection =
+                light_view_projection(&corners, light_direction, resolution, 50.0);
+            for corner in &corners {
+                let clip = view_projection.project_point3(*corner);
+                assert!(clip.is_finite());
+                assert!(clip.x.abs() <= 1.0 + texel && clip.y.abs() <= 1.0 + texel);
+                assert!((0.0..=1.0).contains(&clip.z));
+            }
+        }
+    }
+
+    #[test]
+    fn light_view_projection_moves_in_whole_texels() {
+        let projection = PerspectiveProjection::default();
+        let corners = frustum_slice_corners(&projection, 0.5, 20.0);
+        let light_direction = Vec3::new(-1.0, -2.0, -0.5).normalize();
+        let resolution = 1024;
+        let view_projection = light_view_projection(&corners, light_direction, resolution, 50.0);
+
+        // Moving the camera by a fraction of a texel moves the shadow map by whole texels.
+        let offset = Vec3::new(0.013, 0.0, 0.007);
+        let moved_corners = corners.map(|corner| corner + offset);
+        let moved_view_projection =
+            light_view_projection(&moved_corners, light_direction, resolution, 50.0);
+
+        let point = Vec3::new(1.0, -2.0, -5.0);
+        let texels = (moved_view_projection.project_point3(point)
+            - view_projection.project_point3(point))
+            * resolution as f32
+            * 0.5;
+        assert!((texels.x - texels.x.round()).abs() < 0.01, "{}", texels.x);
+        assert!((texels.y - texels.y.round()).abs() < 0.01, "{}", texels.y);
+    }
+}
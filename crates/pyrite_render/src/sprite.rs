@@ -0,0 +1,546 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ash::vk;
+use pyrite_app::{
+    plugin::Plugin,
+    resource::{Res, ResMut, Resource},
+    AppBuilder,
+};
+use pyrite_math::{
+    Aabb, Affine3A, Frustum, GlobalTransform, Mat4, OrthographicProjection, Projection, Vec2, Vec3,
+    Vec4,
+};
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator,
+    objects::{
+        util::ImageViewCreateInfo, Buffer, CommandBuffer, DescriptorSetHandle, DescriptorSetPool,
+        GraphicsPipeline, GraphicsPipelineCreateInfo, OwnedImage, OwnedImageCreateInfo,
+        PipelineLayoutCreateInfo, RenderingAttachment, RenderingFormats, RenderingInfo, Shader,
+        Texture, TypedBuffer, TypedBufferCreateInfo,
+    },
+    Vulkan,
+};
+
+use crate::render_manager::{FrameConfig, RenderManager, PRE_RENDER_STAGE};
+
+/// The stage the sprite renderer records its pass in, right after the [`RenderManager`] begins
+/// the frame.
+pub const SPRITE_RENDER_STAGE: &str = "sprite_render";
+
+/// The descriptor set holding the texture of a batch at binding 0.
+pub const SPRITE_TEXTURE_SET: u32 = 0;
+
+/// The number of sets a [`DescriptorSetPool`] is created with.
+const TEXTURE_SETS_PER_POOL: usize = 100;
+
+/// Adds the [`SpriteRenderer`] with its [`SpriteBatch`] and [`SpriteCamera`], see
+/// [`setup_sprite_renderer`].
+pub struct SpriteRendererPlugin {
+    pub config: SpriteRendererConfig,
+}
+
+impl Plugin for SpriteRendererPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        setup_sprite_renderer(app_builder, &self.config);
+    }
+}
+
+/// Sets up a sprite renderer drawing the [`SpriteBatch`] from the [`SpriteCamera`] every frame,
+/// requires the [`RenderManager`] resource to be added first.
+///
+/// Sprites are drawn back to front by the z translation of their transform, consecutive sprites
+/// sharing a texture are drawn as one instanced draw, so packing sprites into atlas textures
+/// keeps the number of draws down. The image is handed to the [`RenderManager`] as the frame's
+/// backbuffer.
+pub fn setup_sprite_renderer(app_builder: &mut AppBuilder, config: &SpriteRendererConfig) {
+    let sprite_renderer = SpriteRenderer::new(
+        &app_builder.get_resource::<Vulkan>(),
+        app_builder
+            .get_resource::<RenderManager>()
+            .frames_in_flight() as usize,
+        config.clone(),
+    );
+
+    app_builder
+        .add_resource(sprite_renderer)
+        .add_resource(SpriteBatch::default())
+        .add_resource(SpriteCamera::default())
+        .add_stage_after(SPRITE_RENDER_STAGE, PRE_RENDER_STAGE)
+        .add_system_to_stage(SpriteRenderer::render_system, SPRITE_RENDER_STAGE);
+}
+
+#[derive(Clone)]
+pub struct SpriteRendererConfig {
+    /// The SPIR-V of the vertex shader, `shaders/sprite.vert` in this crate reads the
+    /// [`SpriteInstance`] attributes and the view projection push constant.
+    pub vertex_shader: Vec<u32>,
+    /// The SPIR-V of the fragment shader, `shaders/sprite.frag` in this crate samples the texture
+    /// from [`SPRITE_TEXTURE_SET`] and multiplies it by the sprite's color.
+    pub fragment_shader: Vec<u32>,
+    pub color_format: vk::Format,
+    pub clear_color: [f32; 4],
+}
+
+/// The camera the sprite renderer draws from, looking down the negative z axis so sprites should
+/// be placed between `-near` and `-far` in front of it.
+#[derive(Resource)]
+pub struct SpriteCamera {
+    pub transform: GlobalTransform,
+    pub projection: OrthographicProjection,
+    /// Replaces the projection every frame so one unit is one pixel with the origin at the top
+    /// left, otherwise the projection keeps its vertical extent as the swapchain is resized.
+    pub screen_space: bool,
+}
+
+impl Default for SpriteCamera {
+    fn default() -> Self {
+        Self {
+            transform: GlobalTransform::default(),
+            projection: OrthographicProjection::from_screen_size(1.0, 1.0),
+            screen_space: true,
+        }
+    }
+}
+
+/// A region of a texture in texels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteRect {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl SpriteRect {
+    pub fn new(position: Vec2, size: Vec2) -> Self {
+        Self { position, size }
+    }
+}
+
+/// A textured quad, the size of its rect in texels before it's transformed and centered on the
+/// translation of its transform.
+#[derive(Clone)]
+pub struct Sprite {
+    /// The texture to sample, it must be in the shader read only layout when the sprite is drawn.
+    pub texture: Arc<Texture>,
+    /// The region of the texture to draw, such as the cell of an atlas.
+    pub rect: SpriteRect,
+    /// Multiplied with the sampled color.
+    pub color: Vec4,
+}
+
+impl Sprite {
+    /// A sprite of the whole texture.
+    pub fn new(texture: Arc<Texture>) -> Self {
+        let extent = texture.extent();
+        Self {
+            rect: SpriteRect::new(
+                Vec2::ZERO,
+                Vec2::new(extent.width as f32, extent.height as f32),
+            ),
+            texture,
+            color: Vec4::ONE,
+        }
+    }
+
+    pub fn with_rect(mut self, rect: SpriteRect) -> Self {
+        self.rect = rect;
+        self
+    }
+
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// The per instance vertex attributes of a sprite, the model matrix at locations 0 to 3, the uv
+/// rect at location 4 as `vec4(min, max)` and the color at location 5.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpriteInstance {
+    pub model: Mat4,
+    pub uv_rect: Vec4,
+    pub color: Vec4,
+}
+
+impl SpriteInstance {
+    fn vertex_bindings() -> Vec<vk::VertexInputBindingDescription> {
+        vec![vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: std::mem::size_of::<SpriteInstance>() as u32,
+            input_rate: vk::VertexInputRate::INSTANCE,
+        }]
+    }
+
+    fn vertex_attributes() -> Vec<vk::VertexInputAttributeDescription> {
+        let vec4_size = std::mem::size_of::<Vec4>() as u32;
+        (0..6)
+            .map(|location| vk::VertexInputAttributeDescription {
+                location,
+                binding: 0,
+                format: vk::Format::R32G32B32A32_SFLOAT,
+                offset: location * vec4_size,
+            })
+            .collect()
+    }
+}
+
+struct SpriteDraw {
+    sprite: Sprite,
+    transform: GlobalTransform,
+}
+
+/// The sprites of the current frame, systems submit to it every frame before the
+/// [`SPRITE_RENDER_STAGE`], which clears it.
+#[derive(Resource, Default)]
+pub struct SpriteBatch {
+    draws: Vec<SpriteDraw>,
+}
+
+impl SpriteBatch {
+    pub fn submit(&mut self, sprite: &Sprite, transform: GlobalTransform) {
+        self.draws.push(SpriteDraw {
+            sprite: sprite.clone(),
+            transform,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+}
+
+/// A run of instances sharing a texture.
+struct Batch {
+    texture: Arc<Texture>,
+    first_instance: u32,
+    instance_count: u32,
+}
+
+#[derive(Resource)]
+pub struct SpriteRenderer {
+    config: SpriteRendererConfig,
+    pipeline: GraphicsPipeline,
+    color: Option<OwnedImage>,
+    extent: vk::Extent2D,
+    instance_buffers: Vec<Option<TypedBuffer<SpriteInstance>>>,
+    descriptor_set_pools: Vec<DescriptorSetPool>,
+    /// The descriptor set of every texture drawn so far, with the index of its pool, keyed by
+    /// the address of the texture which is kept alive by the entry.
+    texture_sets: HashMap<usize, (Arc<Texture>, usize, DescriptorSetHandle)>,
+}
+
+impl SpriteRenderer {
+    fn new(vulkan: &Vulkan, frames_in_flight: usize, config: SpriteRendererConfig) -> Self {
+        let vertex_shader = Shader::new(vulkan, &config.vertex_shader);
+        let fragment_shader = Shader::new(vulkan, &config.fragment_shader);
+        let pipeline = GraphicsPipeline::new(
+            vulkan,
+            GraphicsPipelineCreateInfo {
+                vertex_shader: &vertex_shader,
+                vertex_entry_point: "main".to_string(),
+                fragment_shader: &fragment_shader,
+                fragment_entry_point: "main".to_string(),
+                pipeline_layout_info: PipelineLayoutCreateInfo::default(),
+                vertex_bindings: SpriteInstance::vertex_bindings(),
+                vertex_attributes: SpriteInstance::vertex_attributes(),
+                topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+                polygon_mode: vk::PolygonMode::FILL,
+                cull_mode: vk::CullModeFlags::NONE,
+                front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+                alpha_blending: true,
+                depth_compare_op: vk::CompareOp::ALWAYS,
+                rendering_formats: RenderingFormats {
+                    color_formats: vec![config.color_format],
+                    depth_format: None,
+                    samples: vk::SampleCountFlags::TYPE_1,
+                },
+                name: Some("sprite".to_string()),
+            },
+        );
+
+        Self {
+            config,
+            pipeline,
+            color: None,
+            extent: vk::Extent2D::default(),
+            instance_buffers: (0..frames_in_flight).map(|_| None).collect(),
+            descriptor_set_pools: Vec::new(),
+            texture_sets: HashMap::new(),
+        }
+    }
+
+    pub fn render_system(
+        mut sprite_renderer: ResMut<SpriteRenderer>,
+        mut render_manager: ResMut<RenderManager>,
+        mut sprite_batch: ResMut<SpriteBatch>,
+        mut camera: ResMut<SpriteCamera>,
+        vulkan: Res<Vulkan>,
+        mut vulkan_allocator: ResMut<VulkanMemoryAllocator>,
+    ) {
+        pyrite_util::profile_scope!("SpriteRenderer::render_system");
+
+        let extent = render_manager.backbuffer_extent();
+        if extent.width > 0 && extent.height > 0 {
+            sprite_renderer.resize(&vulkan, &mut vulkan_allocator, extent);
+            let (width, height) = (extent.width as f32, extent.height as f32);
+            if camera.screen_space {
+                camera.projection = OrthographicProjection::from_screen_size(width, height);
+            } else {
+                camera.projection.resize(width, height);
+            }
+        }
+
+        let draws = std::mem::take(&mut sprite_batch.draws);
+        let frame_index = render_manager.frame_index();
+        sprite_renderer.render(
+            &vulkan,
+            &mut vulkan_allocator,
+            render_manager.command_buffer_mut(),
+            frame_index,
+            &camera,
+            draws,
+        );
+
+        let color = sprite_renderer.color.as_ref().unwrap();
+        render_manager.set_frame_config(
+            &FrameConfig::builder()
+                .backbuffer(
+                    color,
+                    sprite_renderer.config.color_format,
+                    sprite_renderer.extent,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                )
+                .build(),
+        );
+    }
+
+    /// Recreates the color attachment if the extent changed.
+    fn resize(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        extent: vk::Extent2D,
+    ) {
+        if self.color.is_some() && self.extent == extent {
+            return;
+        }
+
+        self.color = Some(OwnedImage::new(
+            vulkan,
+            vulkan_allocator,
+            &OwnedImageCreateInfo {
+                image_type: vk::ImageType::TYPE_2D,
+                width: extent.width,
+                height: extent.height,
+                format: self.config.color_format,
+                usage: vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+                samples: vk::SampleCountFlags::TYPE_1,
+                mip_levels: 1,
+                view_create_info: Some(ImageViewCreateInfo {
+                    view_type: vk::ImageViewType::TYPE_2D,
+                    subresource_range: vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                }),
+                name: Some("sprite color".to_string()),
+            },
+        ));
+        self.extent = extent;
+    }
+
+    fn render(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        command_buffer: &mut CommandBuffer,
+        frame_index: usize,
+        camera: &SpriteCamera,
+        mut draws: Vec<SpriteDraw>,
+    ) {
+        let view_projection =
+            camera.projection.compute_matrix() * camera.transform.compute_view_matrix();
+        let frustum = Frustum::from_view_projection(&view_projection);
+        let quad_bounds = Aabb::new(Vec3::new(-0.5, -0.5, 0.0), Vec3::new(0.5, 0.5, 0.0));
+
+        // Sorting by texture within a depth keeps sprites sharing a texture next to each other.
+        draws.sort_by(|a, b| {
+            a.transform
+                .translation()
+                .z
+                .total_cmp(&b.transform.translation().z)
+                .then_with(|| Arc::as_ptr(&a.sprite.texture).cmp(&Arc::as_ptr(&b.sprite.texture)))
+        });
+
+        let mut instances = Vec::with_capacity(draws.len());
+        let mut batches: Vec<Batch> = Vec::new();
+        for draw in draws {
+            let model = draw.transform.compute_matrix()
+                * Mat4::from_scale(draw.sprite.rect.size.extend(1.0));
+            let bounds = quad_bounds.transformed(&Affine3A::from_mat4(model));
+            if !frustum.intersects_aabb(&bounds) {
+                continue;
+            }
+
+            let extent = draw.sprite.texture.extent();
+            let texture_size = Vec2::new(extent.width as f32, extent.height as f32);
+            let uv_min = draw.sprite.rect.position / texture_size;
+            let uv_max = (draw.sprite.rect.position + draw.sprite.rect.size) / texture_size;
+            instances.push(SpriteInstance {
+                model,
+                uv_rect: Vec4::new(uv_min.x, uv_min.y, uv_max.x, uv_max.y),
+                color: draw.sprite.color,
+            });
+
+            match batches.last_mut() {
+                Some(batch) if Arc::ptr_eq(&batch.texture, &draw.sprite.texture) => {
+                    batch.instance_count += 1;
+                }
+                _ => batches.push(Batch {
+                    texture: draw.sprite.texture,
+                    first_instance: instances.len() as u32 - 1,
+                    instance_count: 1,
+                }),
+            }
+        }
+
+        let color = self
+            .color
+            .take()
+            .expect("The sprite renderer wasn't resized before rendering");
+        let render_area = vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent: self.extent,
+        };
+        command_buffer.begin_rendering(RenderingInfo {
+            render_area,
+            color_attachments: vec![RenderingAttachment {
+                image: &color,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::STORE,
+                clear_value: vk::ClearValue {
+                    color: vk::ClearColorValue {
+                        float32: self.config.clear_color,
+                    },
+                },
+                resolve_image: None,
+            }],
+            depth_attachment: None,
+            secondary_command_buffers: false,
+        });
+
+        if !batches.is_empty() {
+            command_buffer.begin_label("sprites", [1.0, 1.0, 1.0, 1.0]);
+            self.write_instances(vulkan, vulkan_allocator, frame_index, &instances);
+
+            command_buffer.bind_graphics_pipeline(&self.pipeline);
+            command_buffer.set_viewport_and_scissor(render_area);
+            command_buffer.push_constants(
+                vk::PipelineBindPoint::GRAPHICS,
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                &view_projection,
+            );
+            let instance_buffer = self.instance_buffers[frame_index].as_ref().unwrap();
+            command_buffer.bind_vertex_buffers(0, &[(instance_buffer as &dyn Buffer, 0)]);
+
+            for batch in &batches {
+                let (pool_index, texture_set) = self.texture_set(vulkan, &batch.texture);
+                command_buffer.bind_descriptor_sets(
+                    vk::PipelineBindPoint::GRAPHICS,
+                    SPRITE_TEXTURE_SET,
+                    &[self.descriptor_set_pools[pool_index]
+                        .get(texture_set)
+                        .unwrap()],
+                );
+                command_buffer.draw(6, batch.instance_count, 0, batch.first_instance);
+            }
+            command_buffer.end_label();
+        }
+
+        command_buffer.end_rendering();
+        command_buffer.transition_image(&color, vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+        self.color = Some(color);
+    }
+
+    /// Writes the instances to the frame's instance buffer, growing it to the next power of two
+    /// if they don't fit.
+    fn write_instances(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        frame_index: usize,
+        instances: &[SpriteInstance],
+    ) {
+        let instance_buffer = &mut self.instance_buffers[frame_index];
+        if instance_buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.len() < instances.len())
+        {
+            *instance_buffer = Some(TypedBuffer::new(
+                vulkan,
+                vulkan_allocator,
+                &TypedBufferCreateInfo {
+                    len: instances.len().next_power_of_two(),
+                    usage: vk::BufferUsageFlags::VERTEX_BUFFER,
+                    memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                        | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    name: Some("sprite instances".to_string()),
+                },
+            ));
+        }
+
+        instance_buffer.as_mut().unwrap().write_slice(0, instances);
+    }
+
+    /// The descriptor set of a texture with the index of its pool, allocating and writing it the
+    /// first time the texture is drawn.
+    fn texture_set(
+        &mut self,
+        vulkan: &Vulkan,
+        texture: &Arc<Texture>,
+    ) -> (usize, DescriptorSetHandle) {
+        let key = Arc::as_ptr(texture) as usize;
+        if let Some((_, pool_index, texture_set)) = self.texture_sets.get(&key) {
+            return (*pool_index, *texture_set);
+        }
+
+        if self
+            .texture_sets
+            .len()
+            .is_multiple_of(TEXTURE_SETS_PER_POOL)
+        {
+            self.descriptor_set_pools
+                .push(DescriptorSetPool::new(vulkan));
+        }
+        let pool_index = self.descriptor_set_pools.len() - 1;
+        let descriptor_set_pool = &mut self.descriptor_set_pools[pool_index];
+
+        let layout = &self
+            .pipeline
+            .instance()
+            .pipeline_layout()
+            .descriptor_set_layouts()[SPRITE_TEXTURE_SET as usize];
+        let [texture_set] = descriptor_set_pool.allocate_descriptor_sets::<1>(layout);
+        let mut writer = descriptor_set_pool.get_mut(texture_set).unwrap().writer();
+        writer.combined_image_sampler(
+            0,
+            0,
+            texture.image(),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            texture.sampler(),
+        );
+        writer.submit(vulkan);
+
+        self.texture_sets
+            .insert(key, (texture.clone(), pool_index, texture_set));
+        (pool_index, texture_set)
+    }
+}
@@ -0,0 +1,501 @@
+use std::{collections::HashMap, sync::Arc};
+
+use pyrite_app::{
+    plugin::Plugin,
+    resource::{Res, ResMut, Resource},
+    AppBuilder,
+};
+use pyrite_asset::{
+    loaders::font::{
+        ab_glyph::{self, Font as _, ScaleFont},
+        Font,
+    },
+    Handle,
+};
+use pyrite_math::{GlobalTransform, Transform, Vec2, Vec4};
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator, objects::Texture, stager::VulkanStager, util::Extent2D,
+    Vulkan,
+};
+
+use crate::{
+    render_manager::RenderManager,
+    sprite::{Sprite, SpriteBatch, SpriteRect, SPRITE_RENDER_STAGE},
+};
+
+/// The stage the text renderer rasterizes new glyphs and submits its sprites in, right before
+/// the [`SPRITE_RENDER_STAGE`].
+pub const TEXT_RENDER_STAGE: &str = "text_render";
+
+const INITIAL_ATLAS_SIZE: u32 = 512;
+const MAX_ATLAS_SIZE: u32 = 8192;
+/// The empty texels around every glyph, so neighbouring glyphs don't bleed in when sampling.
+const GLYPH_PADDING: u32 = 1;
+
+/// Adds the [`TextRenderer`], see [`setup_text_renderer`].
+pub struct TextRendererPlugin;
+
+impl Plugin for TextRendererPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        setup_text_renderer(app_builder);
+    }
+}
+
+/// Sets up a text renderer drawing its text as sprites, requires the sprite renderer to be set
+/// up first and a [`VulkanStager`] resource to upload the glyph atlas with.
+pub fn setup_text_renderer(app_builder: &mut AppBuilder) {
+    app_builder
+        .add_resource(TextRenderer::default())
+        .add_stage_before(TEXT_RENDER_STAGE, SPRITE_RENDER_STAGE)
+        .add_system_to_stage(TextRenderer::render_system, TEXT_RENDER_STAGE);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStyle {
+    /// The height of the font in pixels.
+    pub size: f32,
+    pub color: Vec4,
+    /// How lines are aligned within the width of the text.
+    pub alignment: TextAlignment,
+    /// Wraps lines at whitespace when they get wider, words wider than this are broken up.
+    pub max_width: Option<f32>,
+    /// The distance between baselines, the font's line height by default.
+    pub line_height: Option<f32>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            color: Vec4::ONE,
+            alignment: TextAlignment::Left,
+            max_width: None,
+            line_height: None,
+        }
+    }
+}
+
+struct LineGlyph {
+    id: ab_glyph::GlyphId,
+    x: f32,
+    advance: f32,
+    is_whitespace: bool,
+}
+
+#[derive(Default)]
+struct Line {
+    glyphs: Vec<LineGlyph>,
+}
+
+impl Line {
+    /// The width up to the end of the last glyph which isn't whitespace.
+    fn width(&self) -> f32 {
+        self.glyphs
+            .iter()
+            .filter(|glyph| !glyph.is_whitespace)
+            .map(|glyph| glyph.x + glyph.advance)
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Positioned glyphs with their pen position on the baseline, in pixels from the top left of
+/// the text with y pointing down.
+struct TextLayout {
+    glyphs: Vec<(ab_glyph::GlyphId, Vec2)>,
+    size: Vec2,
+}
+
+impl TextLayout {
+    fn new(font: &ab_glyph::FontVec, text: &str, style: &TextStyle) -> Self {
+        let font = font.as_scaled(ab_glyph::PxScale::from(style.size));
+        let line_height = style.line_height.unwrap_or(font.height() + font.line_gap());
+
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = Line::default();
+            // The index of the first glyph after the last whitespace, where the line can wrap.
+            let mut wrap_index = None;
+            let mut previous = None;
+            let mut x = 0.0;
+
+            for c in paragraph.chars() {
+                let id = font.glyph_id(c);
+                if let Some(previous) = previous {
+                    x += font.kern(previous, id);
+                }
+                previous = Some(id);
+                let advance = font.h_advance(id);
+
+                // Whitespace may hang past the edge, it isn't part of the line's width.
+                if c.is_whitespace() {
+                    line.glyphs.push(LineGlyph {
+                        id,
+                        x,
+                        advance,
+                        is_whitespace: true,
+                    });
+                    x += advance;
+                    wrap_index = Some(line.glyphs.len());
+                    continue;
+                }
+
+                if style
+                    .max_width
+                    .is_some_and(|max_width| x + advance > max_width)
+                    && !line.glyphs.is_empty()
+                {
+                    let mut wrapped = line
+                        .glyphs
+                        .split_off(wrap_index.unwrap_or(line.glyphs.len()));
+                    let shift = wrapped.first().map_or(x, |glyph| glyph.x);
+                    for glyph in &mut wrapped {
+                        glyph.x -= shift;
+                    }
+                    x -= shift;
+
+                    lines.push(std::mem::replace(&mut line, Line { glyphs: wrapped }));
+                    wrap_index = None;
+                }
+
+                line.glyphs.push(LineGlyph {
+                    id,
+                    x,
+                    advance,
+                    is_whitespace: false,
+                });
+                x += advance;
+            }
+            lines.push(line);
+        }
+
+        let widths = lines.iter().map(Line::width).collect::<Vec<_>>();
+        let width = style
+            .max_width
+            .unwrap_or_else(|| widths.iter().copied().fold(0.0, f32::max));
+        let glyphs = lines
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .flat_map(|(index, (line, line_width))| {
+                let offset = match style.alignment {
+                    TextAlignment::Left => 0.0,
+                    TextAlignment::Center => (width - line_width) * 0.5,
+                    TextAlignment::Right => width - line_width,
+                };
+                let baseline = font.ascent() + index as f32 * line_height;
+                line.glyphs
+                    .iter()
+                    .filter(|glyph| !glyph.is_whitespace)
+                    .map(move |glyph| (glyph.id, Vec2::new(offset + glyph.x, baseline)))
+            })
+            .collect();
+
+        Self {
+            glyphs,
+            size: Vec2::new(
+                width,
+                (lines.len() - 1) as f32 * line_height + font.height(),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font_id: u64,
+    glyph_id: ab_glyph::GlyphId,
+    size_bits: u32,
+}
+
+/// A rasterized glyph, with its offset from the pen position to the top left of its rect.
+#[derive(Clone, Copy)]
+struct AtlasGlyph {
+    rect: SpriteRect,
+    offset: Vec2,
+}
+
+/// A row of glyphs in the atlas, filled from left to right.
+struct Shelf {
+    y: u32,
+    height: u32,
+    x: u32,
+}
+
+/// An RGBA8 image of white glyphs with their coverage in the alpha channel, packed into shelves
+/// and doubling in size when full.
+struct GlyphAtlas {
+    size: u32,
+    texels: Vec<u8>,
+    shelves: Vec<Shelf>,
+    /// The uploaded atlas, `None` if it grew since.
+    texture: Option<Arc<Texture>>,
+    dirty: bool,
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self {
+            size: INITIAL_ATLAS_SIZE,
+            texels: vec![0; (INITIAL_ATLAS_SIZE * INITIAL_ATLAS_SIZE * 4) as usize],
+            shelves: Vec::new(),
+            texture: None,
+            dirty: true,
+        }
+    }
+}
+
+impl GlyphAtlas {
+    /// Copies the glyph's coverage into the atlas, returning its rect or `None` if the atlas is
+    /// full at its maximum size.
+    fn insert(&mut self, outline: &ab_glyph::OutlinedGlyph) -> Option<SpriteRect> {
+        let bounds = outline.px_bounds();
+        let width = bounds.width().ceil() as u32;
+        let height = bounds.height().ceil() as u32;
+
+        let (x, y) = loop {
+            if let Some(position) = self.allocate(width, height) {
+                break position;
+            }
+            if self.size >= MAX_ATLAS_SIZE {
+                return None;
+            }
+            self.grow();
+        };
+
+        outline.draw(|glyph_x, glyph_y, coverage| {
+            let index = (((y + glyph_y) * self.size + x + glyph_x) * 4) as usize;
+            self.texels[index..index + 4].copy_from_slice(&[
+                255,
+                255,
+                255,
+                (coverage * 255.0).round() as u8,
+            ]);
+        });
+        self.dirty = true;
+
+        Some(SpriteRect::new(
+            Vec2::new(x as f32, y as f32),
+            Vec2::new(width as f32, height as f32),
+        ))
+    }
+
+    /// Finds room for a rect on the first shelf tall enough or on a new shelf.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let padded_width = width + GLYPH_PADDING * 2;
+        let padded_height = height + GLYPH_PADDING * 2;
+
+        let shelf = match self
+            .shelves
+            .iter_mut()
+            .find(|shelf| shelf.height >= padded_height && shelf.x + padded_width <= self.size)
+        {
+            Some(shelf) => shelf,
+            None => {
+                let y = self
+                    .shelves
+                    .last()
+                    .map_or(0, |shelf| shelf.y + shelf.height);
+                if y + padded_height > self.size || padded_width > self.size {
+                    return None;
+                }
+
+                self.shelves.push(Shelf {
+                    y,
+                    height: padded_height,
+                    x: 0,
+                });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+
+        let position = (shelf.x + GLYPH_PADDING, shelf.y + GLYPH_PADDING);
+        shelf.x += padded_width;
+        Some(position)
+    }
+
+    /// Doubles the size, keeping the glyphs at the same texels.
+    fn grow(&mut self) {
+        let size = self.size * 2;
+        let mut texels = vec![0; (size * size * 4) as usize];
+        let row_len = (self.size * 4) as usize;
+        for (row, old_row) in self.texels.chunks_exact(row_len).enumerate() {
+            let start = row * (size * 4) as usize;
+            texels[start..start + row_len].copy_from_slice(old_row);
+        }
+
+        self.size = size;
+        self.texels = texels;
+        self.texture = None;
+        self.dirty = true;
+    }
+
+    /// Uploads the atlas if it changed, creating a new texture if it grew.
+    fn upload(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        render_manager: &mut RenderManager,
+    ) -> Arc<Texture> {
+        let command_buffer = render_manager.command_buffer_mut();
+        match &self.texture {
+            Some(texture) if self.dirty => texture.write_rgba8(
+                vulkan,
+                vulkan_allocator,
+                stager,
+                command_buffer,
+                &self.texels,
+            ),
+            Some(_) => {}
+            None => {
+                self.texture = Some(Arc::new(Texture::new_dynamic(
+                    vulkan,
+                    vulkan_allocator,
+                    stager,
+                    command_buffer,
+                    &self.texels,
+                    Extent2D {
+                        width: self.size,
+                        height: self.size,
+                    },
+                )));
+            }
+        }
+
+        self.dirty = false;
+        self.texture.clone().unwrap()
+    }
+}
+
+struct TextDraw {
+    font: Handle<Font>,
+    text: String,
+    style: TextStyle,
+    transform: GlobalTransform,
+}
+
+/// Draws UTF-8 text with fonts loaded by the font loader, by rasterizing the glyphs into an
+/// atlas and submitting a sprite per glyph to the [`SpriteBatch`].
+///
+/// One unit of the transform is one pixel of the font, with the top left of the text at the
+/// transform's translation and y pointing down like the screen space
+/// [`crate::sprite::SpriteCamera`].
+#[derive(Resource, Default)]
+pub struct TextRenderer {
+    atlas: GlyphAtlas,
+    /// Every glyph rasterized so far, `None` for glyphs without an outline like spaces.
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+    draws: Vec<TextDraw>,
+}
+
+impl TextRenderer {
+    /// Draws the text this frame, nothing is drawn while the font is loading.
+    pub fn draw(
+        &mut self,
+        font: &Handle<Font>,
+        text: impl Into<String>,
+        style: &TextStyle,
+        transform: GlobalTransform,
+    ) {
+        self.draws.push(TextDraw {
+            font: font.clone(),
+            text: text.into(),
+            style: style.clone(),
+            transform,
+        });
+    }
+
+    /// The size the text is drawn at in pixels, `None` while the font is loading.
+    pub fn measure(font: &Handle<Font>, text: &str, style: &TextStyle) -> Option<Vec2> {
+        Some(TextLayout::new(font.get()?.font(), text, style).size)
+    }
+
+    pub fn render_system(
+        mut text_renderer: ResMut<TextRenderer>,
+        mut sprite_batch: ResMut<SpriteBatch>,
+        mut render_manager: ResMut<RenderManager>,
+        mut stager: ResMut<VulkanStager>,
+        vulkan: Res<Vulkan>,
+        mut vulkan_allocator: ResMut<VulkanMemoryAllocator>,
+    ) {
+        pyrite_util::profile_scope!("TextRenderer::render_system");
+
+        let text_renderer = &mut *text_renderer;
+        let mut sprites = Vec::new();
+        for draw in std::mem::take(&mut text_renderer.draws) {
+            let Some(font) = draw.font.get() else {
+                continue;
+            };
+
+            let layout = TextLayout::new(font.font(), &draw.text, &draw.style);
+            for (glyph_id, pen) in layout.glyphs {
+                let key = GlyphKey {
+                    font_id: font.id(),
+                    glyph_id,
+                    size_bits: draw.style.size.to_bits(),
+                };
+                let Some(glyph) = *text_renderer.glyphs.entry(key).or_insert_with(|| {
+                    Self::rasterize(&mut text_renderer.atlas, font.font(), glyph_id, &draw.style)
+                }) else {
+                    continue;
+                };
+
+                let center = pen + glyph.offset + glyph.rect.size * 0.5;
+                let transform = draw
+                    .transform
+                    .mul_transform(&Transform::from_translation(center.extend(0.0)));
+                sprites.push((glyph.rect, draw.style.color, transform));
+            }
+        }
+
+        if sprites.is_empty() {
+            return;
+        }
+
+        // The glyphs are only submitted once they're all in the atlas, since growing it replaces
+        // the texture.
+        let texture = text_renderer.atlas.upload(
+            &vulkan,
+            &mut vulkan_allocator,
+            &mut stager,
+            &mut render_manager,
+        );
+        for (rect, color, transform) in sprites {
+            sprite_batch.submit(
+                &Sprite {
+                    texture: texture.clone(),
+                    rect,
+                    color,
+                },
+                transform,
+            );
+        }
+    }
+
+    fn rasterize(
+        atlas: &mut GlyphAtlas,
+        font: &ab_glyph::FontVec,
+        glyph_id: ab_glyph::GlyphId,
+        style: &TextStyle,
+    ) -> Option<AtlasGlyph> {
+        let outline = font.outline_glyph(glyph_id.with_scale(style.size))?;
+        let offset = Vec2::new(outline.px_bounds().min.x, outline.px_bounds().min.y);
+        let Some(rect) = atlas.insert(&outline) else {
+            log::warn!(
+                "The glyph atlas is full, glyphs of {}px text are missing",
+                style.size
+            );
+            return None;
+        };
+
+        Some(AtlasGlyph { rect, offset })
+    }
+}
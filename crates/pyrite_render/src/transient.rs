@@ -0,0 +1,152 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ash::vk;
+use pyrite_app::resource::Resource;
+use pyrite_vulkan::{allocator::VulkanMemoryAllocator, objects::OwnedImage, Vulkan};
+
+use crate::render_target::create_render_target;
+
+/// The number of frames an image can stay unused in the pool before it's destroyed.
+const MAX_UNUSED_FRAMES: u64 = 8;
+
+/// The properties images handed out by the [`TransientImagePool`] are shared by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransientImageDesc {
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    pub usage: vk::ImageUsageFlags,
+    pub samples: vk::SampleCountFlags,
+}
+
+impl TransientImageDesc {
+    /// An image with a single sample per pixel.
+    pub fn new(format: vk::Format, extent: vk::Extent2D, usage: vk::ImageUsageFlags) -> Self {
+        Self {
+            format,
+            extent,
+            usage,
+            samples: vk::SampleCountFlags::TYPE_1,
+        }
+    }
+}
+
+struct FreeImage {
+    image: Arc<OwnedImage>,
+    last_used_frame: u64,
+}
+
+/// Hands out render target images for intermediate passes, recycling them across passes and
+/// frames so they only take memory while something renders to them.
+///
+/// Images acquired during a frame are kept alive until the frame in flight finished executing,
+/// so they don't have to be added to the frame's used objects. An image can be released early so
+/// later passes of the same frame can reuse it, the barriers recorded by the command buffer keep
+/// the passes ordered.
+#[derive(Resource)]
+pub struct TransientImagePool {
+    free_images: HashMap<TransientImageDesc, Vec<FreeImage>>,
+    /// The images acquired by each frame in flight, returned to the free images once the frame
+    /// comes around again.
+    acquired_images: Vec<Vec<(TransientImageDesc, Arc<OwnedImage>)>>,
+    /// The images released during the current frame, which only its later passes can reuse
+    /// since earlier frames in flight may still be using them.
+    released_images: HashMap<TransientImageDesc, Vec<Arc<OwnedImage>>>,
+    frame_index: usize,
+    frame_count: u64,
+}
+
+impl TransientImagePool {
+    pub fn new(frames_in_flight: usize) -> Self {
+        Self {
+            free_images: HashMap::new(),
+            acquired_images: (0..frames_in_flight).map(|_| Vec::new()).collect(),
+            released_images: HashMap::new(),
+            frame_index: 0,
+            frame_count: 0,
+        }
+    }
+
+    /// Returns a free image matching the description, creating one if there is none.
+    ///
+    /// The contents and layout of the image are undefined, so its first pass should clear it.
+    pub fn acquire(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        desc: &TransientImageDesc,
+    ) -> Arc<OwnedImage> {
+        let image = self
+            .released_images
+            .get_mut(desc)
+            .and_then(|images| images.pop())
+            .or_else(|| {
+                self.free_images
+                    .get_mut(desc)
+                    .and_then(|images| images.pop())
+                    .map(|free_image| free_image.image)
+            })
+            .unwrap_or_else(|| {
+                Arc::new(create_render_target(
+                    vulkan,
+                    vulkan_allocator,
+                    desc.format,
+                    desc.extent,
+                    desc.usage,
+                    desc.samples,
+                    "transient_image",
+                ))
+            });
+
+        self.acquired_images[self.frame_index].push((*desc, image.clone()));
+        image
+    }
+
+    /// Lets later passes of this frame acquire the image again, so it mustn't be used by passes
+    /// recorded after this.
+    pub fn release(&mut self, image: &Arc<OwnedImage>) {
+        let acquired_images = &mut self.acquired_images[self.frame_index];
+        let Some(index) = acquired_images
+            .iter()
+            .position(|(_, acquired_image)| Arc::ptr_eq(acquired_image, image))
+        else {
+            return;
+        };
+
+        let (desc, image) = acquired_images.swap_remove(index);
+        self.released_images.entry(desc).or_default().push(image);
+    }
+
+    /// The number of images the pool owns, whether they're acquired or free.
+    pub fn image_count(&self) -> usize {
+        self.free_images.values().map(Vec::len).sum::<usize>()
+            + self.acquired_images.iter().map(Vec::len).sum::<usize>()
+            + self.released_images.values().map(Vec::len).sum::<usize>()
+    }
+
+    /// Frees the images of the frame in flight, called by the
+    /// [`crate::render_manager::RenderManager`] once the frame's fence was waited on.
+    pub(crate) fn begin_frame(&mut self, frame_index: usize) {
+        // Released images are kept with the frame that used them until it finished.
+        let previous_frame_images = &mut self.acquired_images[self.frame_index];
+        for (desc, images) in self.released_images.drain() {
+            previous_frame_images.extend(images.into_iter().map(|image| (desc, image)));
+        }
+
+        self.frame_index = frame_index;
+        self.frame_count += 1;
+
+        let frame_count = self.frame_count;
+        for (desc, image) in self.acquired_images[frame_index].drain(..) {
+            self.free_images.entry(desc).or_default().push(FreeImage {
+                image,
+                last_used_frame: frame_count,
+            });
+        }
+
+        for images in self.free_images.values_mut() {
+            images
+                .retain(|free_image| frame_count - free_image.last_used_frame < MAX_UNUSED_FRAMES);
+        }
+        self.free_images.retain(|_, images| !images.is_empty());
+    }
+}
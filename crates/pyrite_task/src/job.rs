@@ -0,0 +1,166 @@
+use std::{
+    any::Any,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{self, AtomicUsize},
+        Arc,
+    },
+};
+
+use parking_lot::{Condvar, Mutex};
+
+type JobFn = Box<dyn FnOnce() + Send>;
+type PanicPayload = Box<dyn Any + Send>;
+
+pub(crate) struct JobNode {
+    /// The number of unfinished dependencies, plus one while the job is still being set up so it
+    /// can't be scheduled before all of its dependencies are registered.
+    remaining_dependencies: AtomicUsize,
+    job_fn: Mutex<Option<JobFn>>,
+    state: Mutex<JobNodeState>,
+    finished_condvar: Condvar,
+}
+
+struct JobNodeState {
+    is_finished: bool,
+    /// The payload of the panic if the job panicked, resumed when the job is joined.
+    panic: Option<PanicPayload>,
+    dependents: Vec<Arc<JobNode>>,
+}
+
+impl JobNode {
+    pub(crate) fn new(job_fn: JobFn) -> Arc<Self> {
+        Arc::new(Self {
+            remaining_dependencies: AtomicUsize::new(1),
+            job_fn: Mutex::new(Some(job_fn)),
+            state: Mutex::new(JobNodeState {
+                is_finished: false,
+                panic: None,
+                dependents: Vec::new(),
+            }),
+            finished_condvar: Condvar::new(),
+        })
+    }
+
+    /// Registers `dependent` to run after this job, returns false if this job already finished.
+    pub(crate) fn add_dependent(&self, dependent: &Arc<JobNode>) -> bool {
+        let mut state = self.state.lock();
+        if state.is_finished {
+            return false;
+        }
+
+        dependent
+            .remaining_dependencies
+            .fetch_add(1, atomic::Ordering::AcqRel);
+        state.dependents.push(dependent.clone());
+        true
+    }
+
+    /// Marks one dependency as finished, returns true if the job is now ready to run.
+    pub(crate) fn release_dependency(&self) -> bool {
+        self.remaining_dependencies
+            .fetch_sub(1, atomic::Ordering::AcqRel)
+            == 1
+    }
+
+    /// Runs the job and returns the dependents that became ready to run.
+    ///
+    /// A panicking job still finishes, so its dependents are released and joining it doesn't
+    /// block forever. The panic is caught here and resumed on the thread joining the job.
+    pub(crate) fn run(&self) -> Vec<Arc<JobNode>> {
+        let job_fn = self.job_fn.lock().take();
+        let panic =
+            job_fn.and_then(|job_fn| std::panic::catch_unwind(AssertUnwindSafe(job_fn)).err());
+
+        let dependents = {
+            let mut state = self.state.lock();
+            state.is_finished = true;
+            state.panic = panic;
+            std::mem::take(&mut state.dependents)
+        };
+        self.finished_condvar.notify_all();
+
+        dependents
+            .into_iter()
+            .filter(|dependent| dependent.release_dependency())
+            .collect()
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.state.lock().is_finished
+    }
+
+    pub(crate) fn wait(&self) {
+        let mut state = self.state.lock();
+        while !state.is_finished {
+            self.finished_condvar.wait(&mut state);
+        }
+    }
+
+    pub(crate) fn take_panic(&self) -> Option<PanicPayload> {
+        self.state.lock().panic.take()
+    }
+}
+
+/// A dependency on a spawned job, used to order jobs without needing the job's output type.
+#[derive(Clone)]
+pub struct JobDependency {
+    pub(crate) node: Arc<JobNode>,
+}
+
+impl JobDependency {
+    pub fn is_finished(&self) -> bool {
+        self.node.is_finished()
+    }
+}
+
+/// A handle to a job spawned on a [`crate::TaskPool`].
+pub struct JobHandle<T> {
+    pub(crate) node: Arc<JobNode>,
+    pub(crate) output: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> JobHandle<T> {
+    pub fn is_finished(&self) -> bool {
+        self.node.is_finished()
+    }
+
+    pub fn dependency(&self) -> JobDependency {
+        JobDependency {
+            node: self.node.clone(),
+        }
+    }
+
+    /// Takes the output of the job if it has finished.
+    pub fn try_take(&mut self) -> Option<T> {
+        if !self.is_finished() {
+            return None;
+        }
+
+        self.output.lock().take()
+    }
+
+    /// Blocks the current thread until the job has finished and returns its output.
+    ///
+    /// Avoid calling this from within a job since it blocks the worker thread, express the
+    /// ordering with a dependency instead.
+    ///
+    /// If the job panicked, the panic is resumed on the calling thread.
+    pub fn join(self) -> T {
+        self.node.wait();
+        if let Some(panic) = self.node.take_panic() {
+            std::panic::resume_unwind(panic);
+        }
+
+        self.output
+            .lock()
+            .take()
+            .expect("Job output was already taken.")
+    }
+}
+
+impl<T> From<&JobHandle<T>> for JobDependency {
+    fn from(handle: &JobHandle<T>) -> Self {
+        handle.dependency()
+    }
+}
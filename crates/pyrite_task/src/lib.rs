@@ -0,0 +1,12 @@
+mod job;
+mod task_pool;
+
+pub use job::*;
+pub use task_pool::*;
+
+pub mod prelude {
+    pub use crate::{
+        job::{JobDependency, JobHandle},
+        task_pool::TaskPool,
+    };
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use pyrite_app::resource::Resource;
+
+use crate::job::{JobDependency, JobHandle, JobNode};
+
+/// A work-stealing pool for running jobs in parallel.
+///
+/// Jobs can depend on other jobs, in which case they are only scheduled once all of their
+/// dependencies have finished.
+///
+/// This is deliberately separate from pyrite_app's `AsyncTaskPool`. Jobs are short CPU bound work
+/// which callers wait on with [`TaskPool::scope`] or [`JobHandle::join`] within a frame, so they
+/// can't share workers with asset loads or file io that may block for a long time.
+#[derive(Resource)]
+pub struct TaskPool {
+    threads: Arc<rayon::ThreadPool>,
+}
+
+pub struct TaskPoolConfig {
+    /// The number of worker threads, defaults to the number of logical cores.
+    pub num_threads: Option<usize>,
+    pub thread_name: String,
+}
+
+impl Default for TaskPoolConfig {
+    fn default() -> Self {
+        Self {
+            num_threads: None,
+            thread_name: "pyrite_task".to_string(),
+        }
+    }
+}
+
+impl TaskPool {
+    pub fn new(config: &TaskPoolConfig) -> Self {
+        let thread_name = config.thread_name.clone();
+        let mut builder = rayon::ThreadPoolBuilder::new()
+            .thread_name(move |index| format!("{}_{}", thread_name, index));
+        if let Some(num_threads) = config.num_threads {
+            builder = builder.num_threads(num_threads);
+        }
+
+        Self {
+            threads: Arc::new(
+                builder
+                    .build()
+                    .expect("Failed to create task pool threads."),
+            ),
+        }
+    }
+
+    pub fn num_threads(&self) -> usize {
+        self.threads.current_num_threads()
+    }
+
+    /// Spawns a job that is scheduled immediately.
+    pub fn spawn<T, F>(&self, f: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.spawn_after(&[], f)
+    }
+
+    /// Spawns a job that is scheduled once all of `dependencies` have finished.
+    pub fn spawn_after<T, F>(&self, dependencies: &[JobDependency], f: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let output = Arc::new(Mutex::new(None));
+        let job_output = output.clone();
+        let node = JobNode::new(Box::new(move || {
+            job_output.lock().replace(f());
+        }));
+
+        for dependency in dependencies {
+            dependency.node.add_dependent(&node);
+        }
+
+        // Release the setup dependency now that all the real dependencies are registered.
+        if node.release_dependency() {
+            Self::schedule(&self.threads, node.clone());
+        }
+
+        JobHandle { node, output }
+    }
+
+    /// Runs `f` on the pool with a scope that can borrow from the caller's stack, blocking until
+    /// every job spawned in the scope has finished.
+    pub fn scope<'scope, R, F>(&self, f: F) -> R
+    where
+        R: Send,
+        F: FnOnce(&rayon::Scope<'scope>) -> R + Send,
+    {
+        self.threads.install(|| rayon::scope(f))
+    }
+
+    /// Runs `f` on the pool, allowing rayon's parallel iterators to be used on the pool's
+    /// threads.
+    pub fn install<R, F>(&self, f: F) -> R
+    where
+        R: Send,
+        F: FnOnce() -> R + Send,
+    {
+        self.threads.install(f)
+    }
+
+    fn schedule(threads: &Arc<rayon::ThreadPool>, node: Arc<JobNode>) {
+        let pool = threads.clone();
+        threads.spawn(move || {
+            for ready_dependent in node.run() {
+                Self::schedule(&pool, ready_dependent);
+            }
+        });
+    }
+}
+
+impl Default for TaskPool {
+    fn default() -> Self {
+        Self::new(&TaskPoolConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        panic::AssertUnwindSafe,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    #[test]
+    fn dependencies_run_first() {
+        let task_pool = TaskPool::default();
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let first_counter = counter.clone();
+        let first = task_pool.spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            first_counter.fetch_add(1, Ordering::SeqCst)
+        });
+
+        let second_counter = counter.clone();
+        let second = task_pool.spawn_after(&[first.dependency()], move || {
+            second_counter.fetch_add(1, Ordering::SeqCst)
+        });
+
+        assert_eq!(second.join(), 1);
+        assert_eq!(first.join(), 0);
+    }
+
+    #[test]
+    fn panics_are_resumed_when_joining() {
+        let task_pool = TaskPool::new(&TaskPoolConfig {
+            num_threads: Some(1),
+            ..Default::default()
+        });
+
+        let panicking = task_pool.spawn(|| -> u32 { panic!("job failed") });
+        let dependent = task_pool.spawn_after(&[panicking.dependency()], || 2);
+
+        // Dependents of a panicked job are still released.
+        assert_eq!(dependent.join(), 2);
+
+        let panic = std::panic::catch_unwind(AssertUnwindSafe(|| panicking.join())).unwrap_err();
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"job failed"));
+
+        // The worker thread survived the panic.
+        assert_eq!(task_pool.spawn(|| 3).join(), 3);
+    }
+}
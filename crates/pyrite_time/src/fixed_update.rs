@@ -0,0 +1,99 @@
+use pyrite_app::{plugin::Plugin, stage::UPDATE_STAGE, AppBuilder};
+
+use crate::time::{Time, DEFAULT_FIXED_TICK_RATE};
+
+/// Runs once for every fixed step taken from the frame time, zero or more times per frame,
+/// before the update stage.
+pub const FIXED_UPDATE_STAGE: &str = "fixed_update";
+
+/// Adds the [`FIXED_UPDATE_STAGE`], ticking `ticks_per_second` times a second of virtual time.
+pub struct FixedUpdatePlugin {
+    pub ticks_per_second: u32,
+}
+
+impl Default for FixedUpdatePlugin {
+    fn default() -> Self {
+        Self {
+            ticks_per_second: DEFAULT_FIXED_TICK_RATE,
+        }
+    }
+}
+
+impl Plugin for FixedUpdatePlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        setup_fixed_update(app_builder, self.ticks_per_second);
+    }
+}
+
+/// Adds the [`FIXED_UPDATE_STAGE`] and the [`Time`] resource if it doesn't exist yet.
+///
+/// [`Time`] must be updated before the fixed update stage, e.g. in the pre update stage.
+pub fn setup_fixed_update(app_builder: &mut AppBuilder, ticks_per_second: u32) {
+    if app_builder.contains_resource::<Time>() {
+        app_builder
+            .get_resource_mut::<Time>()
+            .set_fixed_tick_rate(ticks_per_second);
+    } else {
+        app_builder.add_resource(Time::new().with_fixed_tick_rate(ticks_per_second));
+    }
+
+    app_builder
+        .add_stage_before(FIXED_UPDATE_STAGE, UPDATE_STAGE)
+        .repeat_stage_while(FIXED_UPDATE_STAGE, |resource_bank| {
+            resource_bank.get_resource_mut::<Time>().expend_fixed_step()
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pyrite_app::{
+        resource::{Res, ResMut, Resource},
+        stage::PRE_UPDATE_STAGE,
+    };
+
+    use super::*;
+
+    #[derive(Resource)]
+    struct FrameDelta(Duration);
+
+    #[derive(Resource, Default)]
+    struct FixedRuns(u32);
+
+    fn advance_time(mut time: ResMut<Time>, frame_delta: Res<FrameDelta>) {
+        time.advance(frame_delta.0);
+    }
+
+    fn count_fixed_runs(mut runs: ResMut<FixedRuns>) {
+        runs.0 += 1;
+    }
+
+    fn run_frame(app: &mut pyrite_app::Application, delta: Duration) -> u32 {
+        app.get_resource_mut::<FrameDelta>().0 = delta;
+        app.get_resource_mut::<FixedRuns>().0 = 0;
+        app.execute_schedule();
+        app.get_resource::<FixedRuns>().0
+    }
+
+    #[test]
+    fn fixed_update_runs_once_per_fixed_step() {
+        let mut app_builder = AppBuilder::new();
+        setup_fixed_update(&mut app_builder, 10);
+        app_builder
+            .add_resource(FrameDelta(Duration::ZERO))
+            .add_resource(FixedRuns::default())
+            .add_system_to_stage(advance_time, PRE_UPDATE_STAGE)
+            .add_system_to_stage(count_fixed_runs, FIXED_UPDATE_STAGE);
+        app_builder.set_entry_point(|mut app| {
+            assert_eq!(run_frame(&mut app, Duration::from_millis(250)), 2);
+            // The 50ms left over from the last frame carry over.
+            assert_eq!(run_frame(&mut app, Duration::from_millis(300)), 3);
+            assert_eq!(run_frame(&mut app, Duration::from_millis(50)), 1);
+            assert_eq!(run_frame(&mut app, Duration::from_millis(50)), 0);
+            // A long frame only catches up on a limited number of steps.
+            assert_eq!(run_frame(&mut app, Duration::from_secs(2)), 8);
+        });
+        app_builder.run();
+    }
+}
@@ -0,0 +1,179 @@
+use std::{collections::VecDeque, time::Duration};
+
+use pyrite_app::resource::Resource;
+
+const DEFAULT_HISTORY_LEN: usize = 240;
+
+/// How much each new frame contributes to the smoothed frame time, lower is smoother but slower
+/// to react.
+const SMOOTHING_FACTOR: f64 = 0.1;
+
+/// Frame rate and frame time statistics over the last frames, for performance overlays and
+/// logging. Recorded with the real frame time, so slow motion and pausing don't affect them.
+#[derive(Resource)]
+pub struct FrameStats {
+    frame_times: VecDeque<Duration>,
+    history_len: usize,
+    smoothed_frame_time: Duration,
+    longest_frame_time: Duration,
+    frame_count: u64,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self::with_history_len(DEFAULT_HISTORY_LEN)
+    }
+
+    /// Keeps the frame times of the last `history_len` frames.
+    pub fn with_history_len(history_len: usize) -> Self {
+        assert!(history_len > 0, "The frame time history can't be empty.");
+
+        Self {
+            frame_times: VecDeque::with_capacity(history_len),
+            history_len,
+            smoothed_frame_time: Duration::ZERO,
+            longest_frame_time: Duration::ZERO,
+            frame_count: 0,
+        }
+    }
+
+    pub fn record(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == self.history_len {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+
+        self.smoothed_frame_time = if self.frame_count == 0 {
+            frame_time
+        } else {
+            self.smoothed_frame_time.mul_f64(1.0 - SMOOTHING_FACTOR)
+                + frame_time.mul_f64(SMOOTHING_FACTOR)
+        };
+        self.longest_frame_time = self.longest_frame_time.max(frame_time);
+        self.frame_count += 1;
+    }
+
+    /// The frame rate of the last frame alone.
+    pub fn fps(&self) -> f32 {
+        to_fps(self.frame_time())
+    }
+
+    /// The frame rate averaged over the recent frames, steady enough to display.
+    pub fn smoothed_fps(&self) -> f32 {
+        to_fps(self.smoothed_frame_time)
+    }
+
+    pub fn frame_time(&self) -> Duration {
+        self.frame_times.back().copied().unwrap_or_default()
+    }
+
+    pub fn smoothed_frame_time(&self) -> Duration {
+        self.smoothed_frame_time
+    }
+
+    pub fn average_frame_time(&self) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::ZERO;
+        }
+        self.frame_times.iter().sum::<Duration>() / self.frame_times.len() as u32
+    }
+
+    /// The longest frame within the history.
+    pub fn longest_recent_frame_time(&self) -> Duration {
+        self.frame_times.iter().max().copied().unwrap_or_default()
+    }
+
+    /// The longest frame since the stats were created or last reset.
+    pub fn longest_frame_time(&self) -> Duration {
+        self.longest_frame_time
+    }
+
+    pub fn reset_longest_frame_time(&mut self) {
+        self.longest_frame_time = Duration::ZERO;
+    }
+
+    /// The frame times of the history from oldest to newest.
+    pub fn frame_times(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.history_len
+    }
+
+    /// The number of frames recorded.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn to_fps(frame_time: Duration) -> f32 {
+    if frame_time.is_zero() {
+        return 0.0;
+    }
+
+    1.0 / frame_time.as_secs_f32()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn empty_stats_report_zero() {
+        let frame_stats = FrameStats::default();
+
+        assert_eq!(frame_stats.frame_time(), Duration::ZERO);
+        assert_eq!(frame_stats.average_frame_time(), Duration::ZERO);
+        assert_eq!(frame_stats.fps(), 0.0);
+        assert_eq!(frame_stats.history_len(), DEFAULT_HISTORY_LEN);
+    }
+
+    #[test]
+    fn average_covers_only_the_history() {
+        let mut frame_stats = FrameStats::with_history_len(3);
+        for frame_time in [100, 10, 20, 30] {
+            frame_stats.record(millis(frame_time));
+        }
+
+        assert_eq!(frame_stats.frame_count(), 4);
+        assert_eq!(
+            frame_stats.frame_times().collect::<Vec<_>>(),
+            vec![millis(10), millis(20), millis(30)]
+        );
+        assert_eq!(frame_stats.average_frame_time(), millis(20));
+        assert_eq!(frame_stats.frame_time(), millis(30));
+        assert_eq!(frame_stats.longest_recent_frame_time(), millis(30));
+        assert_eq!(frame_stats.longest_frame_time(), millis(100));
+
+        frame_stats.reset_longest_frame_time();
+        assert_eq!(frame_stats.longest_frame_time(), Duration::ZERO);
+    }
+
+    #[test]
+    fn smoothed_frame_time_moves_towards_new_frames() {
+        let mut frame_stats = FrameStats::new();
+        frame_stats.record(millis(10));
+        assert_eq!(frame_stats.smoothed_frame_time(), millis(10));
+        assert!((frame_stats.smoothed_fps() - 100.0).abs() < 0.01);
+
+        frame_stats.record(millis(20));
+        assert_eq!(frame_stats.smoothed_frame_time(), millis(11));
+
+        for _ in 0..200 {
+            frame_stats.record(millis(20));
+        }
+        let smoothed = frame_stats.smoothed_frame_time().as_secs_f64();
+        assert!((smoothed - 0.020).abs() < 1e-6);
+    }
+}
@@ -1,6 +1,17 @@
+mod fixed_update;
+mod frame_stats;
 mod time;
+mod timer;
+pub use fixed_update::*;
+pub use frame_stats::*;
 pub use time::*;
+pub use timer::*;
 
 pub mod prelude {
-    pub use crate::time::Time;
+    pub use crate::{
+        fixed_update::{FixedUpdatePlugin, FIXED_UPDATE_STAGE},
+        frame_stats::FrameStats,
+        time::{Clock, Time},
+        timer::{Stopwatch, Timer, TimerMode},
+    };
 }
@@ -2,27 +2,215 @@ use std::time::{Duration, Instant};
 
 use pyrite_app::resource::Resource;
 
+pub const DEFAULT_FIXED_TICK_RATE: u32 = 60;
+
+/// The most fixed steps that can be pending at once, so a long frame doesn't cause every
+/// following frame to fall further behind trying to catch up.
+const MAX_PENDING_FIXED_STEPS: u32 = 8;
+
+/// A clock advanced by the frame time, which can be slowed down, sped up or paused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clock {
+    delta: Duration,
+    elapsed: Duration,
+    scale: f32,
+    paused: bool,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            delta: Duration::from_secs(0),
+            elapsed: Duration::from_secs(0),
+            scale: 1.0,
+            paused: false,
+        }
+    }
+
+    /// The time the clock advanced by during the last update, zero while paused.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// The time the clock advanced by since it was created.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets how fast the clock runs relative to real time, 0.5 for half speed.
+    pub fn set_scale(&mut self, scale: f32) {
+        assert!(
+            scale >= 0.0 && scale.is_finite(),
+            "The time scale must be finite and not negative."
+        );
+        self.scale = scale;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advances the clock by the real time passed, scaled by the clock's scale.
+    pub fn advance(&mut self, real_delta: Duration) {
+        self.delta = if self.paused {
+            Duration::from_secs(0)
+        } else {
+            real_delta.mul_f32(self.scale)
+        };
+        self.elapsed += self.delta;
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The frame timing of the app.
+///
+/// [`Time::delta`] and fixed steps follow the virtual clock, which can be scaled for slow motion
+/// and paused for pause menus. Systems which should keep running regardless, like menus and
+/// camera controls, use the real clock instead.
 #[derive(Resource)]
 pub struct Time {
-    delta: Duration,
+    real: Clock,
+    virtual_clock: Clock,
     last: Instant,
+    fixed_delta: Duration,
+    fixed_accumulator: Duration,
 }
 
 impl Time {
     pub fn new() -> Self {
         Self {
-            delta: Duration::from_secs(0),
+            real: Clock::new(),
+            virtual_clock: Clock::new(),
             last: Instant::now(),
+            fixed_delta: Duration::from_secs(1) / DEFAULT_FIXED_TICK_RATE,
+            fixed_accumulator: Duration::from_secs(0),
         }
     }
 
+    pub fn with_fixed_tick_rate(mut self, ticks_per_second: u32) -> Self {
+        self.set_fixed_tick_rate(ticks_per_second);
+        self
+    }
+
+    /// The scaled time since the last frame, zero while paused.
     pub fn delta(&self) -> Duration {
-        self.delta
+        self.virtual_clock.delta()
+    }
+
+    /// The scaled time since the app started, not advancing while paused.
+    pub fn elapsed(&self) -> Duration {
+        self.virtual_clock.elapsed()
+    }
+
+    /// The time since the last frame, unaffected by the time scale and pausing.
+    pub fn real_delta(&self) -> Duration {
+        self.real.delta()
+    }
+
+    /// The time since the app started, unaffected by the time scale and pausing.
+    pub fn real_elapsed(&self) -> Duration {
+        self.real.elapsed()
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.virtual_clock.scale()
+    }
+
+    /// Sets how fast the virtual clock runs relative to real time, 0.5 for half speed.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.virtual_clock.set_scale(scale);
+    }
+
+    /// Pauses the virtual clock, so [`Time::delta`] is zero and no fixed steps are taken.
+    pub fn pause(&mut self) {
+        self.virtual_clock.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.virtual_clock.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.virtual_clock.is_paused()
+    }
+
+    pub fn real(&self) -> &Clock {
+        &self.real
+    }
+
+    pub fn virtual_clock(&self) -> &Clock {
+        &self.virtual_clock
+    }
+
+    pub fn virtual_clock_mut(&mut self) -> &mut Clock {
+        &mut self.virtual_clock
+    }
+
+    /// The time step of fixed updates, independent of the frame rate.
+    pub fn fixed_delta(&self) -> Duration {
+        self.fixed_delta
+    }
+
+    pub fn set_fixed_tick_rate(&mut self, ticks_per_second: u32) {
+        assert!(ticks_per_second > 0, "The fixed tick rate must be above 0.");
+        self.fixed_delta = Duration::from_secs(1) / ticks_per_second;
+    }
+
+    /// How far the current frame is between the last fixed step and the next one, in the range
+    /// `[0, 1)`. Used to interpolate the state of fixed updates when rendering.
+    pub fn alpha(&self) -> f32 {
+        self.fixed_accumulator.as_secs_f32() / self.fixed_delta.as_secs_f32()
+    }
+
+    /// Consumes one fixed step from the accumulated frame time, returning false once there isn't
+    /// enough time left for another step this frame.
+    ///
+    /// ```ignore
+    /// while time.expend_fixed_step() {
+    ///     simulate(time.fixed_delta());
+    /// }
+    /// ```
+    pub fn expend_fixed_step(&mut self) -> bool {
+        if self.fixed_accumulator < self.fixed_delta {
+            return false;
+        }
+
+        self.fixed_accumulator -= self.fixed_delta;
+        true
     }
 
     pub fn update(&mut self) {
         let now = Instant::now();
-        self.delta = now.duration_since(self.last);
+        let real_delta = now.duration_since(self.last);
         self.last = now;
+
+        self.advance(real_delta);
+    }
+
+    /// Advances the clocks and accumulates fixed steps as if `real_delta` passed since the last
+    /// frame, [`Time::update`] measures it instead.
+    pub fn advance(&mut self, real_delta: Duration) {
+        self.real.advance(real_delta);
+        self.virtual_clock.advance(real_delta);
+
+        self.fixed_accumulator = (self.fixed_accumulator + self.virtual_clock.delta())
+            .min(self.fixed_delta * MAX_PENDING_FIXED_STEPS);
     }
 }
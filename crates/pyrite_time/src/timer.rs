@@ -0,0 +1,310 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerMode {
+    /// Finishes once and stays finished until reset.
+    Once,
+    /// Starts over every time it finishes, carrying over the excess time.
+    Repeating,
+}
+
+/// Counts down a duration ticked by the frame time, for cooldowns, spawners and animations.
+///
+/// ```ignore
+/// spawner.timer.tick(time.delta());
+/// for _ in 0..spawner.timer.times_finished_this_tick() {
+///     spawn_enemy();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timer {
+    duration: Duration,
+    elapsed: Duration,
+    mode: TimerMode,
+    paused: bool,
+    finished: bool,
+    times_finished_this_tick: u32,
+}
+
+impl Timer {
+    pub fn new(duration: Duration, mode: TimerMode) -> Self {
+        Self {
+            duration,
+            elapsed: Duration::from_secs(0),
+            mode,
+            paused: false,
+            finished: false,
+            times_finished_this_tick: 0,
+        }
+    }
+
+    pub fn once(duration: Duration) -> Self {
+        Self::new(duration, TimerMode::Once)
+    }
+
+    pub fn repeating(duration: Duration) -> Self {
+        Self::new(duration, TimerMode::Repeating)
+    }
+
+    /// Advances the timer, does nothing while paused.
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        self.times_finished_this_tick = 0;
+        if self.paused {
+            if self.mode == TimerMode::Repeating {
+                self.finished = false;
+            }
+            return self;
+        }
+        if self.mode == TimerMode::Once && self.finished {
+            return self;
+        }
+
+        self.elapsed += delta;
+        if self.elapsed < self.duration {
+            self.finished = false;
+            return self;
+        }
+
+        self.finished = true;
+        match self.mode {
+            TimerMode::Once => {
+                self.elapsed = self.duration;
+                self.times_finished_this_tick = 1;
+            }
+            // A zero duration timer finishes once per tick instead of infinitely often.
+            TimerMode::Repeating if self.duration.is_zero() => {
+                self.elapsed = Duration::from_secs(0);
+                self.times_finished_this_tick = 1;
+            }
+            TimerMode::Repeating => {
+                let times_finished = self.elapsed.as_nanos() / self.duration.as_nanos();
+                self.elapsed -= self.duration * times_finished as u32;
+                self.times_finished_this_tick = times_finished as u32;
+            }
+        }
+        self
+    }
+
+    /// Whether the timer finished, on the tick it finished for repeating timers and from then
+    /// on until it's reset for once timers.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Whether the timer finished during the last tick.
+    pub fn just_finished(&self) -> bool {
+        self.times_finished_this_tick > 0
+    }
+
+    /// How often the timer finished during the last tick, more than once if a repeating timer
+    /// was ticked by more than its duration.
+    pub fn times_finished_this_tick(&self) -> u32 {
+        self.times_finished_this_tick
+    }
+
+    /// How far the timer is through its duration, in the range 0 to 1.
+    pub fn percent(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+
+        self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+    }
+
+    pub fn percent_left(&self) -> f32 {
+        1.0 - self.percent()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Changes the duration without resetting the elapsed time, so the timer may finish on the
+    /// next tick.
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: TimerMode) {
+        self.mode = mode;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Starts the timer over, keeping whether it's paused.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::from_secs(0);
+        self.finished = false;
+        self.times_finished_this_tick = 0;
+    }
+}
+
+/// Counts up the time it's ticked by, for measuring how long something has been going on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stopwatch {
+    elapsed: Duration,
+    paused: bool,
+}
+
+impl Stopwatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advances the stopwatch, does nothing while paused.
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        if !self.paused {
+            self.elapsed += delta;
+        }
+        self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Sets the elapsed time back to zero, keeping whether it's paused.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::from_secs(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn once_timer_finishes_once_and_stays_finished() {
+        let mut timer = Timer::once(millis(100));
+
+        timer.tick(millis(60));
+        assert!(!timer.finished());
+        assert!(!timer.just_finished());
+        assert_eq!(timer.remaining(), millis(40));
+
+        timer.tick(millis(60));
+        assert!(timer.finished());
+        assert!(timer.just_finished());
+        assert_eq!(timer.elapsed(), millis(100));
+        assert_eq!(timer.percent(), 1.0);
+
+        timer.tick(millis(60));
+        assert!(timer.finished());
+        assert!(!timer.just_finished());
+        assert_eq!(timer.times_finished_this_tick(), 0);
+
+        timer.reset();
+        assert!(!timer.finished());
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn repeating_timer_carries_over_excess_time() {
+        let mut timer = Timer::repeating(millis(100));
+
+        timer.tick(millis(250));
+        assert!(timer.just_finished());
+        assert_eq!(timer.times_finished_this_tick(), 2);
+        assert_eq!(timer.elapsed(), millis(50));
+
+        timer.tick(millis(20));
+        assert!(!timer.finished());
+        assert_eq!(timer.times_finished_this_tick(), 0);
+
+        timer.tick(millis(30));
+        assert!(timer.finished());
+        assert_eq!(timer.times_finished_this_tick(), 1);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn zero_duration_repeating_timer_finishes_once_per_tick() {
+        let mut timer = Timer::repeating(Duration::ZERO);
+
+        timer.tick(millis(16));
+        assert_eq!(timer.times_finished_this_tick(), 1);
+        assert_eq!(timer.percent(), 1.0);
+    }
+
+    #[test]
+    fn paused_timer_does_not_advance() {
+        let mut timer = Timer::repeating(millis(100));
+        timer.tick(millis(100));
+        assert!(timer.finished());
+
+        timer.pause();
+        timer.tick(millis(500));
+        assert!(timer.is_paused());
+        assert!(!timer.finished());
+        assert_eq!(timer.times_finished_this_tick(), 0);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+
+        timer.resume();
+        timer.tick(millis(100));
+        assert_eq!(timer.times_finished_this_tick(), 1);
+    }
+
+    #[test]
+    fn stopwatch_only_counts_while_running() {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.tick(millis(30));
+
+        stopwatch.pause();
+        stopwatch.tick(millis(1000));
+        assert_eq!(stopwatch.elapsed(), millis(30));
+
+        stopwatch.resume();
+        stopwatch.tick(millis(20));
+        assert_eq!(stopwatch.elapsed(), millis(50));
+
+        stopwatch.reset();
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+        assert!(!stopwatch.is_paused());
+    }
+}
@@ -0,0 +1,40 @@
+use pyrite_math::Vec2;
+
+use crate::style::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl Rect {
+    pub fn new(position: Vec2, size: Vec2) -> Self {
+        Self { position, size }
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.position).all() && point.cmplt(self.position + self.size).all()
+    }
+}
+
+/// A primitive for a renderer to draw, in screen pixels with the origin at the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UiDrawCommand {
+    Rect {
+        rect: Rect,
+        color: Color,
+    },
+    Text {
+        position: Vec2,
+        text: String,
+        font_size: f32,
+        color: Color,
+    },
+}
+
+/// The draw commands of a ui, ordered back to front.
+#[derive(Debug, Clone, Default)]
+pub struct UiDrawList {
+    pub commands: Vec<UiDrawCommand>,
+}
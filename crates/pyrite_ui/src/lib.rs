@@ -0,0 +1,14 @@
+pub mod draw;
+pub mod style;
+mod ui;
+pub mod widget;
+
+pub use ui::*;
+
+pub mod prelude {
+    pub use crate::{
+        style::{Anchor, Color, Layout, Size, Style},
+        ui::{Ui, UiNodeHandle},
+        widget::Widget,
+    };
+}
@@ -0,0 +1,153 @@
+use pyrite_math::Vec2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const TRANSPARENT: Self = Self::rgba(0.0, 0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self::rgba(1.0, 1.0, 1.0, 1.0);
+    pub const BLACK: Self = Self::rgba(0.0, 0.0, 0.0, 1.0);
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgba(r, g, b, 1.0)
+    }
+
+    /// Scales the color channels, leaving alpha untouched.
+    pub fn shade(&self, factor: f32) -> Self {
+        Self::rgba(self.r * factor, self.g * factor, self.b * factor, self.a)
+    }
+}
+
+/// The point of the parent a node is positioned relative to, the same point of the node is
+/// placed on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// The anchor point as a fraction of a rect's size.
+    pub fn factor(&self) -> Vec2 {
+        match self {
+            Anchor::TopLeft => Vec2::new(0.0, 0.0),
+            Anchor::Top => Vec2::new(0.5, 0.0),
+            Anchor::TopRight => Vec2::new(1.0, 0.0),
+            Anchor::Left => Vec2::new(0.0, 0.5),
+            Anchor::Center => Vec2::new(0.5, 0.5),
+            Anchor::Right => Vec2::new(1.0, 0.5),
+            Anchor::BottomLeft => Vec2::new(0.0, 1.0),
+            Anchor::Bottom => Vec2::new(0.5, 1.0),
+            Anchor::BottomRight => Vec2::new(1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Size {
+    /// Sized to fit the node's content.
+    #[default]
+    Auto,
+    Pixels(f32),
+    /// A fraction of the parent's content size.
+    Percent(f32),
+}
+
+/// How a node positions its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Children are positioned by their own anchor and offset.
+    #[default]
+    Free,
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub anchor: Anchor,
+    pub offset: Vec2,
+    pub width: Size,
+    pub height: Size,
+    pub padding: f32,
+    pub layout: Layout,
+    /// The space between children in vertical and horizontal layouts.
+    pub spacing: f32,
+    pub background: Color,
+    pub text_color: Color,
+    pub font_size: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            anchor: Anchor::TopLeft,
+            offset: Vec2::ZERO,
+            width: Size::Auto,
+            height: Size::Auto,
+            padding: 0.0,
+            layout: Layout::Free,
+            spacing: 0.0,
+            background: Color::TRANSPARENT,
+            text_color: Color::WHITE,
+            font_size: 16.0,
+        }
+    }
+}
+
+impl Style {
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn offset(mut self, x: f32, y: f32) -> Self {
+        self.offset = Vec2::new(x, y);
+        self
+    }
+
+    pub fn size(mut self, width: Size, height: Size) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout, spacing: f32) -> Self {
+        self.layout = layout;
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn background(mut self, background: Color) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn text(mut self, color: Color, font_size: f32) -> Self {
+        self.text_color = color;
+        self.font_size = font_size;
+        self
+    }
+}
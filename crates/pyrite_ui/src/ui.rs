@@ -0,0 +1,586 @@
+use pyrite_app::resource::{Res, ResMut, Resource};
+use pyrite_input::{mouse::Button, Input};
+use pyrite_math::Vec2;
+use pyrite_util::HandleMap;
+
+use crate::{
+    draw::{Rect, UiDrawCommand, UiDrawList},
+    style::{Layout, Size, Style},
+    widget::{InteractionState, Widget},
+};
+
+pyrite_util::new_handle_type! { pub struct UiNodeHandle; }
+
+type TextMeasurer = Box<dyn Fn(&str, f32) -> Vec2 + Send + Sync>;
+
+pub struct UiNode {
+    widget: Widget,
+    style: Style,
+    visible: bool,
+    parent: Option<UiNodeHandle>,
+    children: Vec<UiNodeHandle>,
+    rect: Rect,
+    interaction: InteractionState,
+}
+
+impl UiNode {
+    pub fn widget(&self) -> &Widget {
+        &self.widget
+    }
+
+    pub fn style(&self) -> &Style {
+        &self.style
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn parent(&self) -> Option<UiNodeHandle> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[UiNodeHandle] {
+        &self.children
+    }
+
+    /// The rect computed during the last layout, in screen pixels.
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn interaction(&self) -> InteractionState {
+        self.interaction
+    }
+}
+
+/// A retained mode ui tree.
+///
+/// Nodes are laid out and hit tested against the mouse in [`Ui::update`], the result can then be
+/// drawn by a renderer from [`Ui::draw_list`].
+#[derive(Resource)]
+pub struct Ui {
+    nodes: HandleMap<UiNodeHandle, UiNode>,
+    roots: Vec<UiNodeHandle>,
+    screen_size: Vec2,
+    hovered: Option<UiNodeHandle>,
+    pressed: Option<UiNodeHandle>,
+    clicked: Vec<UiNodeHandle>,
+    text_measurer: TextMeasurer,
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ui {
+    pub fn new() -> Self {
+        Self {
+            nodes: HandleMap::new(),
+            roots: Vec::new(),
+            screen_size: Vec2::ZERO,
+            hovered: None,
+            pressed: None,
+            clicked: Vec::new(),
+            // A rough monospace estimate until a font is provided by the text renderer.
+            text_measurer: Box::new(|text, font_size| {
+                Vec2::new(text.chars().count() as f32 * font_size * 0.5, font_size)
+            }),
+        }
+    }
+
+    /// Sets the function used to measure text for auto sized nodes.
+    pub fn set_text_measurer(
+        &mut self,
+        text_measurer: impl Fn(&str, f32) -> Vec2 + Send + Sync + 'static,
+    ) {
+        self.text_measurer = Box::new(text_measurer);
+    }
+
+    pub fn set_screen_size(&mut self, width: f32, height: f32) {
+        self.screen_size = Vec2::new(width, height);
+    }
+
+    pub fn screen_size(&self) -> Vec2 {
+        self.screen_size
+    }
+
+    pub fn add_root(&mut self, widget: Widget, style: Style) -> UiNodeHandle {
+        let handle = self.insert_node(None, widget, style);
+        self.roots.push(handle);
+        handle
+    }
+
+    pub fn add_child(
+        &mut self,
+        parent: UiNodeHandle,
+        widget: Widget,
+        style: Style,
+    ) -> UiNodeHandle {
+        let handle = self.insert_node(Some(parent), widget, style);
+        self.nodes
+            .get_mut(parent)
+            .expect("Tried to add a ui node to a parent that doesn't exist.")
+            .children
+            .push(handle);
+        handle
+    }
+
+    /// Removes the node and all of its children.
+    pub fn remove(&mut self, handle: UiNodeHandle) {
+        let Some(node) = self.nodes.remove(handle) else {
+            return;
+        };
+
+        match node.parent {
+            Some(parent) => {
+                if let Some(parent) = self.nodes.get_mut(parent) {
+                    parent.children.retain(|child| *child != handle);
+                }
+            }
+            None => self.roots.retain(|root| *root != handle),
+        }
+
+        for child in node.children {
+            self.remove(child);
+        }
+    }
+
+    pub fn node(&self, handle: UiNodeHandle) -> Option<&UiNode> {
+        self.nodes.get(handle)
+    }
+
+    pub fn set_style(&mut self, handle: UiNodeHandle, style: Style) {
+        if let Some(node) = self.nodes.get_mut(handle) {
+            node.style = style;
+        }
+    }
+
+    pub fn set_widget(&mut self, handle: UiNodeHandle, widget: Widget) {
+        if let Some(node) = self.nodes.get_mut(handle) {
+            node.widget = widget;
+        }
+    }
+
+    pub fn set_visible(&mut self, handle: UiNodeHandle, visible: bool) {
+        if let Some(node) = self.nodes.get_mut(handle) {
+            node.visible = visible;
+        }
+    }
+
+    /// Returns true if the button was clicked during the last update.
+    pub fn was_clicked(&self, handle: UiNodeHandle) -> bool {
+        self.clicked.contains(&handle)
+    }
+
+    pub fn is_hovered(&self, handle: UiNodeHandle) -> bool {
+        self.hovered == Some(handle)
+    }
+
+    /// Returns true if the mouse is over any interactive node, useful for not passing clicks
+    /// through to the game.
+    pub fn is_pointer_captured(&self) -> bool {
+        self.hovered.is_some()
+    }
+
+    /// Lays out the ui and updates the interaction state from the input.
+    pub fn update(&mut self, input: &Input) {
+        self.layout();
+
+        let (mouse_x, mouse_y) = input.mouse_position();
+        let mouse_position = Vec2::new(mouse_x, mouse_y);
+
+        self.hovered = None;
+        for root in self.roots.clone() {
+            self.hit_test(root, mouse_position);
+        }
+
+        self.clicked.clear();
+        if input.is_mouse_button_pressed(Button::Left) {
+            self.pressed = self.hovered;
+        }
+        if input.is_mouse_button_released(Button::Left) {
+            if let Some(pressed) = self.pressed.take() {
+                if self.hovered == Some(pressed) {
+                    self.clicked.push(pressed);
+                }
+            }
+        }
+
+        let (hovered, pressed) = (self.hovered, self.pressed);
+        for (handle, node) in self.nodes.iter_mut() {
+            node.interaction = if pressed == Some(handle) && hovered == Some(handle) {
+                InteractionState::Pressed
+            } else if hovered == Some(handle) {
+                InteractionState::Hovered
+            } else {
+                InteractionState::None
+            };
+        }
+    }
+
+    pub fn draw_list(&self) -> UiDrawList {
+        let mut draw_list = UiDrawList::default();
+        for root in &self.roots {
+            self.draw_node(*root, &mut draw_list);
+        }
+        draw_list
+    }
+
+    pub fn update_system(mut ui: ResMut<Ui>, input: Res<Input>) {
+        ui.update(&input);
+    }
+
+    fn insert_node(
+        &mut self,
+        parent: Option<UiNodeHandle>,
+        widget: Widget,
+        style: Style,
+    ) -> UiNodeHandle {
+        self.nodes.insert(UiNode {
+            widget,
+            style,
+            visible: true,
+            parent,
+            children: Vec::new(),
+            rect: Rect::new(Vec2::ZERO, Vec2::ZERO),
+            interaction: InteractionState::None,
+        })
+    }
+
+    fn layout(&mut self) {
+        let screen_rect = Rect::new(Vec2::ZERO, self.screen_size);
+        for root in self.roots.clone() {
+            let mut cursor = Vec2::ZERO;
+            self.layout_node(root, screen_rect, Layout::Free, &mut cursor, 0.0);
+        }
+    }
+
+    /// Positions the node inside of the parent's content rect, `cursor` is the position of the
+    /// next node in vertical and horizontal layouts.
+    fn layout_node(
+        &mut self,
+        handle: UiNodeHandle,
+        parent_content: Rect,
+        parent_layout: Layout,
+        cursor: &mut Vec2,
+        spacing: f32,
+    ) {
+        let measured_size = self.measure(handle);
+        let node = self.nodes.get(handle).unwrap();
+        if !node.visible {
+            return;
+        }
+        let style = node.style.clone();
+
+        let size = Vec2::new(
+            resolve_size(style.width, parent_content.size.x, measured_size.x),
+            resolve_size(style.height, parent_content.size.y, measured_size.y),
+        );
+        let position = match parent_layout {
+            Layout::Free => {
+                let factor = style.anchor.factor();
+                parent_content.position + parent_content.size * factor - size * factor
+                    + style.offset
+            }
+            Layout::Vertical => {
+                let position = parent_content.position + *cursor + style.offset;
+                cursor.y += size.y + spacing;
+                position
+            }
+            Layout::Horizontal => {
+                let position = parent_content.position + *cursor + style.offset;
+                cursor.x += size.x + spacing;
+                position
+            }
+        };
+
+        let rect = Rect::new(position, size);
+        let content = Rect::new(
+            position + Vec2::splat(style.padding),
+            (size - Vec2::splat(style.padding * 2.0)).max(Vec2::ZERO),
+        );
+
+        let node = self.nodes.get_mut(handle).unwrap();
+        node.rect = rect;
+        let children = node.children.clone();
+
+        let mut child_cursor = Vec2::ZERO;
+        for child in children {
+            self.layout_node(
+                child,
+                content,
+                style.layout,
+                &mut child_cursor,
+                style.spacing,
+            );
+        }
+    }
+
+    /// The size the node wants based on its content, including padding.
+    fn measure(&self, handle: UiNodeHandle) -> Vec2 {
+        let node = self.nodes.get(handle).unwrap();
+        let style = &node.style;
+
+        let content_size = match node.widget.text() {
+            Some(text) => (self.text_measurer)(text, style.font_size),
+            None => {
+                let child_sizes = node
+                    .children
+                    .iter()
+                    .filter(|child| self.nodes.get(**child).unwrap().visible)
+                    .map(|child| self.fixed_size(*child))
+                    .collect::<Vec<_>>();
+                let total_spacing = style.spacing * child_sizes.len().saturating_sub(1) as f32;
+
+                match style.layout {
+                    Layout::Free => child_sizes
+                        .iter()
+                        .fold(Vec2::ZERO, |size, child| size.max(*child)),
+                    Layout::Vertical => Vec2::new(
+                        child_sizes
+                            .iter()
+                            .fold(0.0, |width, child| child.x.max(width)),
+                        child_sizes.iter().map(|child| child.y).sum::<f32>() + total_spacing,
+                    ),
+                    Layout::Horizontal => Vec2::new(
+                        child_sizes.iter().map(|child| child.x).sum::<f32>() + total_spacing,
+                        child_sizes
+                            .iter()
+                            .fold(0.0, |height, child| child.y.max(height)),
+                    ),
+                }
+            }
+        };
+
+        content_size + Vec2::splat(style.padding * 2.0)
+    }
+
+    /// The size of the node when it doesn't depend on the parent, percentage sizes count as 0.
+    fn fixed_size(&self, handle: UiNodeHandle) -> Vec2 {
+        let style = &self.nodes.get(handle).unwrap().style;
+        let measured_size = self.measure(handle);
+        Vec2::new(
+            resolve_size(style.width, 0.0, measured_size.x),
+            resolve_size(style.height, 0.0, measured_size.y),
+        )
+    }
+
+    /// Finds the top most interactive node under the mouse, later nodes are drawn on top.
+    fn hit_test(&mut self, handle: UiNodeHandle, mouse_position: Vec2) {
+        let node = self.nodes.get(handle).unwrap();
+        if !node.visible {
+            return;
+        }
+
+        if node.widget.is_interactive() && node.rect.contains(mouse_position) {
+            self.hovered = Some(handle);
+        }
+
+        for child in node.children.clone() {
+            self.hit_test(child, mouse_position);
+        }
+    }
+
+    fn draw_node(&self, handle: UiNodeHandle, draw_list: &mut UiDrawList) {
+        let node = self.nodes.get(handle).unwrap();
+        if !node.visible {
+            return;
+        }
+        let style = &node.style;
+
+        let background = match node.interaction {
+            InteractionState::None => style.background,
+            InteractionState::Hovered => style.background.shade(1.2),
+            InteractionState::Pressed => style.background.shade(0.8),
+        };
+        if background.a > 0.0 {
+            draw_list.commands.push(UiDrawCommand::Rect {
+                rect: node.rect,
+                color: background,
+            });
+        }
+
+        if let Some(text) = node.widget.text() {
+            draw_list.commands.push(UiDrawCommand::Text {
+                position: node.rect.position + Vec2::splat(style.padding),
+                text: text.to_string(),
+                font_size: style.font_size,
+                color: style.text_color,
+            });
+        }
+
+        for child in &node.children {
+            self.draw_node(*child, draw_list);
+        }
+    }
+}
+
+fn resolve_size(size: Size, parent_size: f32, measured_size: f32) -> f32 {
+    match size {
+        Size::Auto => measured_size,
+        Size::Pixels(pixels) => pixels,
+        Size::Percent(percent) => parent_size * percent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyrite_input::{mouse, InputEvent};
+
+    use super::*;
+    use crate::style::{Anchor, Color};
+
+    fn move_mouse(input: &mut Input, x: f32, y: f32) {
+        input.submit_input(InputEvent::Mouse(mouse::SubmitInput::Position(x, y)));
+    }
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect::new(Vec2::new(x, y), Vec2::new(width, height))
+    }
+
+    #[test]
+    fn nodes_are_laid_out_by_anchor_and_layout() {
+        let mut ui = Ui::new();
+        ui.set_screen_size(800.0, 600.0);
+
+        let panel = ui.add_root(
+            Widget::Panel,
+            Style::default()
+                .anchor(Anchor::Center)
+                .size(Size::Pixels(200.0), Size::Pixels(100.0))
+                .padding(10.0)
+                .layout(Layout::Vertical, 5.0),
+        );
+        let text = ui.add_child(
+            panel,
+            Widget::Text("abcd".to_string()),
+            Style::default().text(Color::WHITE, 10.0),
+        );
+        let hidden = ui.add_child(panel, Widget::Text("hidden".to_string()), Style::default());
+        ui.set_visible(hidden, false);
+        let button = ui.add_child(
+            panel,
+            Widget::Button("ok".to_string()),
+            Style::default().size(Size::Percent(0.5), Size::Pixels(20.0)),
+        );
+
+        let corner = ui.add_root(
+            Widget::Panel,
+            Style::default()
+                .anchor(Anchor::BottomRight)
+                .offset(-10.0, -10.0),
+        );
+        ui.add_child(
+            corner,
+            Widget::Text("ab".to_string()),
+            Style::default().padding(2.0).text(Color::WHITE, 20.0),
+        );
+
+        ui.update(&Input::new());
+
+        let rect_of = |handle| ui.node(handle).unwrap().rect();
+        assert_eq!(rect_of(panel), rect(300.0, 250.0, 200.0, 100.0));
+        // The default text measurer is a monospace estimate of half the font size per character.
+        assert_eq!(rect_of(text), rect(310.0, 260.0, 20.0, 10.0));
+        // Hidden nodes don't take up space in the layout.
+        assert_eq!(rect_of(button), rect(310.0, 275.0, 90.0, 20.0));
+        // Auto sized panels fit their children, including the children's padding.
+        assert_eq!(rect_of(corner), rect(766.0, 566.0, 24.0, 24.0));
+    }
+
+    #[test]
+    fn hit_testing_finds_the_top_most_visible_button() {
+        let mut ui = Ui::new();
+        ui.set_screen_size(800.0, 600.0);
+        let panel = ui.add_root(
+            Widget::Panel,
+            Style::default().size(Size::Percent(1.0), Size::Percent(1.0)),
+        );
+        let button_style = Style::default().size(Size::Pixels(100.0), Size::Pixels(50.0));
+        let bottom = ui.add_child(
+            panel,
+            Widget::Button("bottom".to_string()),
+            button_style.clone(),
+        );
+        let top = ui.add_child(
+            panel,
+            Widget::Button("top".to_string()),
+            button_style.offset(50.0, 0.0),
+        );
+        let label = ui.add_child(
+            panel,
+            Widget::Text("label".to_string()),
+            Style::default().offset(0.0, 100.0),
+        );
+
+        let mut input = Input::new();
+        move_mouse(&mut input, 75.0, 25.0);
+        ui.update(&input);
+        assert!(ui.is_hovered(top));
+        assert!(!ui.is_hovered(bottom));
+        assert_eq!(
+            ui.node(top).unwrap().interaction(),
+            InteractionState::Hovered
+        );
+
+        ui.set_visible(top, false);
+        ui.update(&input);
+        assert!(ui.is_hovered(bottom));
+
+        // Text isn't interactive, so the mouse over it isn't captured by the ui.
+        move_mouse(&mut input, 10.0, 105.0);
+        ui.update(&input);
+        assert!(ui
+            .node(label)
+            .unwrap()
+            .rect()
+            .contains(Vec2::new(10.0, 105.0)));
+        assert!(!ui.is_pointer_captured());
+    }
+
+    #[test]
+    fn buttons_are_clicked_when_released_over_the_pressed_button() {
+        let mut ui = Ui::new();
+        ui.set_screen_size(800.0, 600.0);
+        let button = ui.add_root(
+            Widget::Button("button".to_string()),
+            Style::default().size(Size::Pixels(100.0), Size::Pixels(50.0)),
+        );
+
+        let mut input = Input::new();
+        move_mouse(&mut input, 10.0, 10.0);
+        input.submit_input(InputEvent::Mouse(mouse::SubmitInput::Pressed(Button::Left)));
+        ui.update(&input);
+        assert_eq!(
+            ui.node(button).unwrap().interaction(),
+            InteractionState::Pressed
+        );
+        assert!(!ui.was_clicked(button));
+
+        input.clear_inputs();
+        input.submit_input(InputEvent::Mouse(mouse::SubmitInput::Released(
+            Button::Left,
+        )));
+        ui.update(&input);
+        assert!(ui.was_clicked(button));
+
+        // Releasing outside of the button cancels the click.
+        input.clear_inputs();
+        input.submit_input(InputEvent::Mouse(mouse::SubmitInput::Pressed(Button::Left)));
+        ui.update(&input);
+        input.clear_inputs();
+        move_mouse(&mut input, 200.0, 10.0);
+        input.submit_input(InputEvent::Mouse(mouse::SubmitInput::Released(
+            Button::Left,
+        )));
+        ui.update(&input);
+        assert!(!ui.was_clicked(button));
+        assert_eq!(
+            ui.node(button).unwrap().interaction(),
+            InteractionState::None
+        );
+    }
+}
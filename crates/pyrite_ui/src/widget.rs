@@ -0,0 +1,28 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum Widget {
+    /// A container for other widgets.
+    Panel,
+    Text(String),
+    Button(String),
+}
+
+impl Widget {
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Widget::Panel => None,
+            Widget::Text(text) | Widget::Button(text) => Some(text),
+        }
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        matches!(self, Widget::Button(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InteractionState {
+    #[default]
+    None,
+    Hovered,
+    Pressed,
+}
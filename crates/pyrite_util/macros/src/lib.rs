@@ -122,6 +122,10 @@ pub fn dependable(_attr: TokenStream, item: TokenStream) -> TokenStream {
             fn create_dep(&self) -> std::sync::Arc<Self::Dep> {
                 self.inner.clone()
             }
+
+            fn create_weak_dep(&self) -> std::sync::Weak<Self::Dep> {
+                std::sync::Arc::downgrade(&self.inner)
+            }
         }
 
         impl std::ops::Deref for #name {
@@ -134,9 +138,11 @@ pub fn dependable(_attr: TokenStream, item: TokenStream) -> TokenStream {
     };
 
     let dep_type_name = syn::Ident::new(&format!("{}Dep", ast.name), ast.name.span());
+    let weak_dep_type_name = syn::Ident::new(&format!("{}WeakDep", ast.name), ast.name.span());
     let ref_type_name = syn::Ident::new(&format!("{}Ref", ast.name), ast.name.span());
     let impl_types = quote! {
         #visibility type #dep_type_name = std::sync::Arc<#inner_name>;
+        #visibility type #weak_dep_type_name = std::sync::Weak<#inner_name>;
         #visibility type #ref_type_name<'a> = &'a #inner_name;
     };
 
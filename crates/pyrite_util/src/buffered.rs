@@ -0,0 +1,135 @@
+/// A value that is buffered across `N` frames.
+///
+/// The current value is written to during a frame while the values of the previous frames can
+/// still be read, [`Buffered::swap`] is then called on the frame boundary to move to the next
+/// value.
+pub struct Buffered<T, const N: usize> {
+    values: [T; N],
+    current_index: usize,
+}
+
+pub type DoubleBuffered<T> = Buffered<T, 2>;
+pub type TripleBuffered<T> = Buffered<T, 3>;
+
+impl<T, const N: usize> Buffered<T, N> {
+    pub fn new(values: [T; N]) -> Self {
+        assert!(N > 0, "A buffered value needs at least one value.");
+
+        Self {
+            values,
+            current_index: 0,
+        }
+    }
+
+    pub fn from_fn(f: impl FnMut(usize) -> T) -> Self {
+        Self::new(std::array::from_fn(f))
+    }
+
+    pub fn current(&self) -> &T {
+        &self.values[self.current_index]
+    }
+
+    pub fn current_mut(&mut self) -> &mut T {
+        &mut self.values[self.current_index]
+    }
+
+    /// The value of the previous frame.
+    pub fn previous(&self) -> &T {
+        self.frames_ago(1)
+    }
+
+    /// The value from `frames_ago` frames before the current frame, wrapping around after `N`
+    /// frames.
+    pub fn frames_ago(&self, frames_ago: usize) -> &T {
+        &self.values[(self.current_index + N - (frames_ago % N)) % N]
+    }
+
+    /// The index of the current value, useful for indexing per-frame resources that live outside
+    /// of this buffer.
+    pub fn current_index(&self) -> usize {
+        self.current_index
+    }
+
+    /// Moves to the next value, leaving the next value's contents from `N` frames ago untouched.
+    pub fn swap(&mut self) {
+        self.current_index = (self.current_index + 1) % N;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.values.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.values.iter_mut()
+    }
+}
+
+impl<T: Clone, const N: usize> Buffered<T, N> {
+    pub fn from_value(value: T) -> Self {
+        Self::from_fn(|_| value.clone())
+    }
+
+    /// Moves to the next value and initializes it with a copy of the previous value.
+    pub fn swap_cloned(&mut self) {
+        self.swap();
+        let previous = self.previous().clone();
+        *self.current_mut() = previous;
+    }
+}
+
+impl<T: Default, const N: usize> Default for Buffered<T, N> {
+    fn default() -> Self {
+        Self::from_fn(|_| T::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_wraps_around() {
+        let mut buffered = TripleBuffered::from_fn(|i| i);
+
+        assert_eq!(buffered.current_index(), 0);
+        buffered.swap();
+        buffered.swap();
+        assert_eq!(buffered.current_index(), 2);
+        assert_eq!(*buffered.previous(), 1);
+        assert_eq!(*buffered.frames_ago(2), 0);
+
+        buffered.swap();
+        assert_eq!(buffered.current_index(), 0);
+        assert_eq!(*buffered.current(), 0);
+        assert_eq!(*buffered.previous(), 2);
+        assert_eq!(*buffered.frames_ago(3), 0);
+    }
+
+    #[test]
+    fn reads_values_written_in_previous_frames() {
+        let mut buffered = DoubleBuffered::<Vec<u32>>::default();
+
+        buffered.current_mut().push(1);
+        buffered.swap();
+        assert!(buffered.current().is_empty());
+        assert_eq!(buffered.previous(), &[1]);
+
+        buffered.current_mut().push(2);
+        buffered.swap();
+        assert_eq!(buffered.current(), &[1]);
+        assert_eq!(buffered.previous(), &[2]);
+    }
+
+    #[test]
+    fn swap_cloned_carries_the_previous_value() {
+        let mut buffered = DoubleBuffered::from_value(0u32);
+
+        *buffered.current_mut() = 5;
+        buffered.swap_cloned();
+        assert_eq!(*buffered.current(), 5);
+
+        *buffered.current_mut() += 1;
+        assert_eq!(*buffered.previous(), 5);
+        assert_eq!(*buffered.current(), 6);
+    }
+}
@@ -0,0 +1,229 @@
+use std::marker::PhantomData;
+
+/// An index paired with the generation of the slot it points to.
+///
+/// Generations start at 1 so the default index is always stale, which makes it usable as a null
+/// handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct GenerationalIndex {
+    index: u32,
+    generation: u32,
+}
+
+impl GenerationalIndex {
+    pub fn new(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.generation == 0
+    }
+}
+
+/// A typed wrapper around a [`GenerationalIndex`], usually defined with [`new_handle_type`].
+pub trait Handle: Copy + Eq + std::hash::Hash {
+    fn from_generational_index(index: GenerationalIndex) -> Self;
+    fn generational_index(&self) -> GenerationalIndex;
+}
+
+impl Handle for GenerationalIndex {
+    fn from_generational_index(index: GenerationalIndex) -> Self {
+        index
+    }
+
+    fn generational_index(&self) -> GenerationalIndex {
+        *self
+    }
+}
+
+/// Defines a new handle type backed by a [`GenerationalIndex`].
+///
+/// ```
+/// pyrite_util::new_handle_type! { pub struct MeshHandle; }
+/// ```
+#[macro_export]
+macro_rules! new_handle_type {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident;) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+        $vis struct $name($crate::handle::GenerationalIndex);
+
+        impl $crate::handle::Handle for $name {
+            fn from_generational_index(index: $crate::handle::GenerationalIndex) -> Self {
+                Self(index)
+            }
+
+            fn generational_index(&self) -> $crate::handle::GenerationalIndex {
+                self.0
+            }
+        }
+    };
+}
+
+struct Slot<T> {
+    value: Option<T>,
+    generation: u32,
+}
+
+/// A map that owns its values and hands out generation-checked handles to them.
+///
+/// Removing a value bumps the generation of its slot, so handles to removed values are detected
+/// as stale even after the slot is reused.
+pub struct HandleMap<H: Handle, T> {
+    slots: Vec<Slot<T>>,
+    free_indices: Vec<u32>,
+    len: usize,
+    _marker: PhantomData<fn(H) -> H>,
+}
+
+impl<H: Handle, T> HandleMap<H, T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free_indices: Vec::new(),
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> H {
+        self.len += 1;
+
+        if let Some(index) = self.free_indices.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+
+            return H::from_generational_index(GenerationalIndex::new(index, slot.generation));
+        }
+
+        let index = self.slots.len() as u32;
+        self.slots.push(Slot {
+            value: Some(value),
+            generation: 1,
+        });
+
+        H::from_generational_index(GenerationalIndex::new(index, 1))
+    }
+
+    pub fn remove(&mut self, handle: H) -> Option<T> {
+        let index = handle.generational_index();
+        let slot = self.slots.get_mut(index.index() as usize)?;
+        if slot.generation != index.generation() || slot.value.is_none() {
+            return None;
+        }
+
+        // Skip generation 0 when wrapping so the null handle never becomes valid.
+        slot.generation = slot.generation.wrapping_add(1).max(1);
+        self.free_indices.push(index.index());
+        self.len -= 1;
+
+        slot.value.take()
+    }
+
+    pub fn get(&self, handle: H) -> Option<&T> {
+        let index = handle.generational_index();
+        self.slots
+            .get(index.index() as usize)
+            .filter(|slot| slot.generation == index.generation())
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: H) -> Option<&mut T> {
+        let index = handle.generational_index();
+        self.slots
+            .get_mut(index.index() as usize)
+            .filter(|slot| slot.generation == index.generation())
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    pub fn contains(&self, handle: H) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (H, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.value.as_ref().map(|value| {
+                (
+                    H::from_generational_index(GenerationalIndex::new(
+                        index as u32,
+                        slot.generation,
+                    )),
+                    value,
+                )
+            })
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (H, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let generation = slot.generation;
+                slot.value.as_mut().map(|value| {
+                    (
+                        H::from_generational_index(GenerationalIndex::new(
+                            index as u32,
+                            generation,
+                        )),
+                        value,
+                    )
+                })
+            })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+}
+
+impl<H: Handle, T> Default for HandleMap<H, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::new_handle_type! { struct TestHandle; }
+
+    #[test]
+    fn removed_handles_are_stale() {
+        let mut map = HandleMap::<TestHandle, u32>::new();
+
+        let first = map.insert(1);
+        assert_eq!(map.remove(first), Some(1));
+        assert!(map.get(first).is_none());
+
+        let second = map.insert(2);
+        assert_eq!(
+            first.generational_index().index(),
+            second.generational_index().index()
+        );
+        assert!(map.get(first).is_none());
+        assert_eq!(map.get(second), Some(&2));
+        assert!(!map.contains(TestHandle::default()));
+    }
+}
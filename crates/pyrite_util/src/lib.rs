@@ -1,7 +1,17 @@
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 
 pub use pyrite_util_macros::dependable;
 
+pub mod buffered;
+pub mod handle;
+pub mod logging;
+pub mod pool;
+pub mod profiling;
+
+pub use buffered::{Buffered, DoubleBuffered, TripleBuffered};
+pub use handle::{GenerationalIndex, Handle, HandleMap};
+pub use pool::{Pool, PoolHandle};
+
 pub mod prelude {
     pub use crate::Dependable;
 }
@@ -12,4 +22,10 @@ pub trait Dependable {
     type Dep;
 
     fn create_dep(&self) -> Arc<Self::Dep>;
+
+    /// Creates a dependency that doesn't keep the underlying object alive, useful for long-lived
+    /// caches that shouldn't extend the lifetime of what they reference.
+    fn create_weak_dep(&self) -> Weak<Self::Dep> {
+        Arc::downgrade(&self.create_dep())
+    }
 }
@@ -0,0 +1,110 @@
+//! Logging setup for pyrite apps.
+//!
+//! Engine crates log through the `log` facade using their module path as the target, so levels
+//! can be configured per module, e.g. `pyrite_vulkan::validation` for validation layer messages.
+//! Shipping builds can silence engine crates with [`LevelFilter::Off`] or redirect the output
+//! with [`LogTarget::Writer`].
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use log::LevelFilter;
+
+pub use log::{debug, error, info, trace, warn};
+
+/// Where log records are written to.
+#[derive(Clone)]
+pub enum LogTarget {
+    Stderr,
+    Stdout,
+    /// A writer such as a log file, shared so the config can be kept after installing the
+    /// logger.
+    Writer(Arc<Mutex<dyn Write + Send>>),
+}
+
+impl LogTarget {
+    pub fn writer(writer: impl Write + Send + 'static) -> Self {
+        Self::Writer(Arc::new(Mutex::new(writer)))
+    }
+}
+
+pub struct LoggerConfig {
+    pub default_level: LevelFilter,
+    pub module_levels: Vec<(String, LevelFilter)>,
+    pub target: LogTarget,
+
+    /// Whether `RUST_LOG` should be parsed after the configured levels, allowing it to override
+    /// them.
+    pub parse_env: bool,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            default_level: LevelFilter::Info,
+            module_levels: Vec::new(),
+            target: LogTarget::Stderr,
+            parse_env: true,
+        }
+    }
+}
+
+impl LoggerConfig {
+    pub fn default_level(mut self, level: LevelFilter) -> Self {
+        self.default_level = level;
+        self
+    }
+
+    pub fn module_level(mut self, module: impl Into<String>, level: LevelFilter) -> Self {
+        self.module_levels.push((module.into(), level));
+        self
+    }
+
+    pub fn target(mut self, target: LogTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn parse_env(mut self, parse_env: bool) -> Self {
+        self.parse_env = parse_env;
+        self
+    }
+}
+
+/// Installs the global logger, does nothing if a logger was already installed.
+pub fn init_logger(config: &LoggerConfig) {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(config.default_level);
+    for (module, level) in &config.module_levels {
+        builder.filter_module(module, *level);
+    }
+    if config.parse_env {
+        builder.parse_default_env();
+    }
+    match &config.target {
+        LogTarget::Stderr => builder.target(env_logger::Target::Stderr),
+        LogTarget::Stdout => builder.target(env_logger::Target::Stdout),
+        // Colors would end up as escape codes in files.
+        LogTarget::Writer(writer) => builder
+            .target(env_logger::Target::Pipe(Box::new(SharedWriter(
+                writer.clone(),
+            ))))
+            .write_style(env_logger::WriteStyle::Never),
+    };
+
+    let _ = builder.try_init();
+}
+
+struct SharedWriter(Arc<Mutex<dyn Write + Send>>);
+
+impl Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
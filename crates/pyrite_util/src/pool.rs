@@ -0,0 +1,155 @@
+use crate::handle::{GenerationalIndex, Handle};
+
+crate::new_handle_type! {
+    /// A handle to an object acquired from a [`Pool`].
+    ///
+    /// The handle stores the generation of the slot it was acquired from, so once the object is
+    /// released the handle becomes stale and lookups with it fail.
+    pub struct PoolHandle;
+}
+
+struct PoolEntry<T> {
+    value: T,
+    generation: u32,
+    in_use: bool,
+}
+
+type CreateFn<T> = Box<dyn FnMut() -> T + Send + Sync>;
+type ResetFn<T> = Box<dyn FnMut(&mut T) + Send + Sync>;
+
+/// A pool of reusable objects.
+///
+/// Objects are created lazily when no released object is available and are reset, if a reset
+/// callback is set, when they are released back into the pool.
+pub struct Pool<T> {
+    entries: Vec<PoolEntry<T>>,
+    free_indices: Vec<u32>,
+    create: CreateFn<T>,
+    reset: Option<ResetFn<T>>,
+}
+
+impl<T> Pool<T> {
+    pub fn new(create: impl FnMut() -> T + Send + Sync + 'static) -> Self {
+        Self {
+            entries: Vec::new(),
+            free_indices: Vec::new(),
+            create: Box::new(create),
+            reset: None,
+        }
+    }
+
+    /// Sets the callback that is run on an object when it is released back into the pool.
+    pub fn with_reset(mut self, reset: impl FnMut(&mut T) + Send + Sync + 'static) -> Self {
+        self.reset = Some(Box::new(reset));
+        self
+    }
+
+    /// Acquires an object from the pool, creating a new one if none are available.
+    pub fn acquire(&mut self) -> PoolHandle {
+        if let Some(index) = self.free_indices.pop() {
+            let entry = &mut self.entries[index as usize];
+            entry.in_use = true;
+
+            return PoolHandle(GenerationalIndex::new(index, entry.generation));
+        }
+
+        let index = self.entries.len() as u32;
+        self.entries.push(PoolEntry {
+            value: (self.create)(),
+            generation: 1,
+            in_use: true,
+        });
+
+        PoolHandle(GenerationalIndex::new(index, 1))
+    }
+
+    /// Releases the object back into the pool, invalidating the handle.
+    ///
+    /// Returns false if the handle was stale.
+    pub fn release(&mut self, handle: PoolHandle) -> bool {
+        let Some(entry) = self
+            .entries
+            .get_mut(handle.0.index() as usize)
+            .filter(|entry| entry.in_use && entry.generation == handle.0.generation())
+        else {
+            return false;
+        };
+
+        if let Some(reset) = &mut self.reset {
+            reset(&mut entry.value);
+        }
+        entry.in_use = false;
+        entry.generation = entry.generation.wrapping_add(1).max(1);
+        self.free_indices.push(handle.generational_index().index());
+
+        true
+    }
+
+    pub fn get(&self, handle: PoolHandle) -> Option<&T> {
+        self.entries
+            .get(handle.0.index() as usize)
+            .filter(|entry| entry.in_use && entry.generation == handle.0.generation())
+            .map(|entry| &entry.value)
+    }
+
+    pub fn get_mut(&mut self, handle: PoolHandle) -> Option<&mut T> {
+        self.entry_mut(handle).map(|entry| &mut entry.value)
+    }
+
+    pub fn contains(&self, handle: PoolHandle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// The number of objects currently acquired.
+    pub fn in_use_count(&self) -> usize {
+        self.entries.len() - self.free_indices.len()
+    }
+
+    /// The number of objects waiting in the pool to be acquired.
+    pub fn available_count(&self) -> usize {
+        self.free_indices.len()
+    }
+
+    /// The total number of objects created by the pool.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn entry_mut(&mut self, handle: PoolHandle) -> Option<&mut PoolEntry<T>> {
+        self.entries
+            .get_mut(handle.0.index() as usize)
+            .filter(|entry| entry.in_use && entry.generation == handle.0.generation())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_released_objects() {
+        let mut pool = Pool::new(Vec::<u32>::new).with_reset(|v| v.clear());
+
+        let first = pool.acquire();
+        pool.get_mut(first).unwrap().push(1);
+        assert!(pool.release(first));
+
+        let second = pool.acquire();
+        assert_eq!(pool.capacity(), 1);
+        assert!(pool.get(second).unwrap().is_empty());
+    }
+
+    #[test]
+    fn stale_handles_are_rejected() {
+        let mut pool = Pool::new(|| 0u32);
+
+        let handle = pool.acquire();
+        assert!(pool.release(handle));
+        assert!(!pool.release(handle));
+        assert!(pool.get(handle).is_none());
+
+        let reacquired = pool.acquire();
+        assert_ne!(handle, reacquired);
+        assert!(pool.contains(reacquired));
+    }
+}
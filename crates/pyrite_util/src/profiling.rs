@@ -0,0 +1,117 @@
+//! Profiling scopes that compile to nothing unless a profiler backend feature is enabled.
+//!
+//! Enable `profile-puffin` or `profile-tracy` on `pyrite_util` (or the `pyrite` crate) to emit
+//! zones, then call [`enable`] once at startup.
+//!
+//! ```
+//! fn update() {
+//!     pyrite_util::profile_scope!("update");
+//!     // ...
+//! }
+//! ```
+
+#[cfg(feature = "profile-puffin")]
+pub use puffin;
+#[cfg(feature = "profile-tracy")]
+pub use tracy_client;
+
+/// Turns on the enabled profiler backends, does nothing if no backend is enabled.
+pub fn enable() {
+    #[cfg(feature = "profile-puffin")]
+    puffin::set_scopes_on(true);
+
+    #[cfg(feature = "profile-tracy")]
+    std::mem::forget(tracy_client::Client::start());
+}
+
+/// Profiles the rest of the enclosing scope under `name`.
+///
+/// An optional second argument attaches dynamic data to the zone, such as a system name.
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:literal) => {
+        $crate::__profile_scope_puffin!($name, "");
+        $crate::__profile_scope_tracy!($name, "");
+    };
+    ($name:literal, $data:expr) => {
+        $crate::__profile_scope_puffin!($name, $data);
+        $crate::__profile_scope_tracy!($name, $data);
+    };
+}
+
+/// Marks the end of a frame for the enabled profiler backends.
+#[macro_export]
+macro_rules! profile_frame {
+    () => {
+        $crate::__profile_frame_puffin!();
+        $crate::__profile_frame_tracy!();
+    };
+}
+
+#[cfg(feature = "profile-puffin")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __profile_scope_puffin {
+    ($name:literal, $data:expr) => {
+        $crate::profiling::puffin::profile_scope!($name, $data);
+    };
+}
+
+#[cfg(not(feature = "profile-puffin"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __profile_scope_puffin {
+    ($name:literal, $data:expr) => {};
+}
+
+#[cfg(feature = "profile-tracy")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __profile_scope_tracy {
+    ($name:literal, $data:expr) => {
+        let _tracy_span = $crate::profiling::tracy_client::span!($name);
+        let _tracy_data: &str = $data;
+        if !_tracy_data.is_empty() {
+            _tracy_span.emit_text(_tracy_data);
+        }
+    };
+}
+
+#[cfg(not(feature = "profile-tracy"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __profile_scope_tracy {
+    ($name:literal, $data:expr) => {};
+}
+
+#[cfg(feature = "profile-puffin")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __profile_frame_puffin {
+    () => {
+        $crate::profiling::puffin::GlobalProfiler::lock().new_frame();
+    };
+}
+
+#[cfg(not(feature = "profile-puffin"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __profile_frame_puffin {
+    () => {};
+}
+
+#[cfg(feature = "profile-tracy")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __profile_frame_tracy {
+    () => {
+        $crate::profiling::tracy_client::frame_mark();
+    };
+}
+
+#[cfg(not(feature = "profile-tracy"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __profile_frame_tracy {
+    () => {};
+}
@@ -0,0 +1,232 @@
+//! Simulates particles bouncing in a box on the gpu with a compute shader, without a window.
+//!
+//! Shows the path through the object model for compute only work: a [`Shader`] and
+//! [`ComputePipeline`] with a layout derived from the shader, a descriptor set from a
+//! [`DescriptorSetPool`] and command buffers submitted by a [`QueueExecutor`] with a frame per
+//! fence in flight.
+
+use ash::vk;
+use pyrite_util::logging::{self, info};
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator,
+    executor::{QueueExecutor, QueueExecutorSubmitInfo},
+    objects::{
+        CommandPool, ComputePipeline, ComputePipelineCreateInfo, DescriptorSetPool, Fence, Shader,
+        TypedBuffer, TypedBufferCreateInfo,
+    },
+    Vulkan, VulkanConfig, DEFAULT_QUEUE,
+};
+
+const FRAMES_IN_FLIGHT: usize = 2;
+const PARTICLE_COUNT: u32 = 4096;
+const STEP_COUNT: u32 = 600;
+const DELTA_TIME: f32 = 1.0 / 60.0;
+
+const SIMULATE_SHADER: &str = r#"
+#version 450
+
+layout(local_size_x = 64) in;
+
+struct Particle {
+    vec2 position;
+    vec2 velocity;
+};
+
+layout(set = 0, binding = 0) buffer Particles {
+    Particle particles[];
+};
+
+layout(push_constant) uniform Constants {
+    float delta_time;
+    uint particle_count;
+};
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= particle_count) {
+        return;
+    }
+
+    Particle particle = particles[index];
+    particle.velocity.y -= 9.81 * delta_time;
+    particle.position += particle.velocity * delta_time;
+
+    // Bounce off the walls of the unit box, losing some energy.
+    for (int axis = 0; axis < 2; axis++) {
+        if (particle.position[axis] < -1.0 || particle.position[axis] > 1.0) {
+            particle.position[axis] = clamp(particle.position[axis], -1.0, 1.0);
+            particle.velocity[axis] *= -0.8;
+        }
+    }
+
+    particles[index] = particle;
+}
+"#;
+
+/// Matches the std430 layout of `Particle` in the shader.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+}
+
+/// Matches the push constant block of the shader.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Constants {
+    delta_time: f32,
+    particle_count: u32,
+}
+
+fn compile_shader(source: &str) -> Vec<u32> {
+    let compiler = shaderc::Compiler::new().expect("Failed to create the shader compiler.");
+    compiler
+        .compile_into_spirv(
+            source,
+            shaderc::ShaderKind::Compute,
+            "simulate.comp",
+            "main",
+            None,
+        )
+        .unwrap_or_else(|err| panic!("Failed to compile the shader: {}", err))
+        .as_binary()
+        .to_vec()
+}
+
+fn main() {
+    logging::init_logger(&Default::default());
+
+    let vulkan = match Vulkan::try_new(&VulkanConfig {
+        app_name: "compute_particles".to_string(),
+        ..Default::default()
+    }) {
+        Ok(vulkan) => vulkan,
+        Err(err) => {
+            logging::error!("Can't run the example: {}", err);
+            return;
+        }
+    };
+    let mut vulkan_allocator = VulkanMemoryAllocator::new(&vulkan);
+
+    // Host visible so the particles can be written and read back without staging.
+    let mut particles = TypedBuffer::<Particle>::new(
+        &vulkan,
+        &mut vulkan_allocator,
+        &TypedBufferCreateInfo {
+            len: PARTICLE_COUNT as usize,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+            name: Some("particles".to_string()),
+        },
+    );
+    let initial_particles = (0..PARTICLE_COUNT)
+        .map(|index| {
+            let angle = index as f32 * 0.1;
+            Particle {
+                position: [0.0, 0.5],
+                velocity: [angle.cos() * 2.0, angle.sin() * 2.0],
+            }
+        })
+        .collect::<Vec<_>>();
+    particles.write_slice(0, &initial_particles);
+
+    let shader = Shader::new(&vulkan, &compile_shader(SIMULATE_SHADER));
+    let pipeline = ComputePipeline::new(
+        &vulkan,
+        ComputePipelineCreateInfo {
+            shader: &shader,
+            shader_entry_point: "main".to_string(),
+            pipeline_layout_info: Default::default(),
+            name: Some("simulate particles".to_string()),
+        },
+    );
+
+    let mut descriptor_set_pool = DescriptorSetPool::new(&vulkan);
+    let [descriptor_set] =
+        descriptor_set_pool.allocate_descriptor_sets::<1>(&pipeline.descriptor_set_layouts()[0]);
+    {
+        let mut writer = descriptor_set_pool
+            .get_mut(descriptor_set)
+            .unwrap()
+            .writer();
+        writer.storage_buffer(0, 0, &particles);
+        writer.submit(&vulkan);
+    }
+
+    let mut command_pool = CommandPool::new(&vulkan);
+    let command_buffers = command_pool.allocate::<FRAMES_IN_FLIGHT>();
+    let fences: [Fence; FRAMES_IN_FLIGHT] = std::array::from_fn(|_| Fence::new(&vulkan, true));
+    let mut executor = QueueExecutor::<FRAMES_IN_FLIGHT>::new(&vulkan, DEFAULT_QUEUE);
+
+    let [group_count_x, _, _] = pipeline.group_count([PARTICLE_COUNT, 1, 1]);
+    for step in 0..STEP_COUNT {
+        let frame_index = step as usize % FRAMES_IN_FLIGHT;
+        fences[frame_index].wait_and_reset();
+        executor.release_frame_resources(frame_index);
+
+        let command_buffer = command_pool.get_mut(command_buffers[frame_index]).unwrap();
+        command_buffer.begin();
+        // The previous step may still be writing the particles on the gpu.
+        command_buffer.memory_barrier(
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+        );
+        command_buffer.bind_compute_pipeline(&pipeline);
+        command_buffer.bind_descriptor_sets(
+            vk::PipelineBindPoint::COMPUTE,
+            0,
+            &[descriptor_set_pool.get(descriptor_set).unwrap()],
+        );
+        command_buffer.push_constants(
+            vk::PipelineBindPoint::COMPUTE,
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            &Constants {
+                delta_time: DELTA_TIME,
+                particle_count: PARTICLE_COUNT,
+            },
+        );
+        command_buffer.dispatch(group_count_x, 1, 1);
+        // Makes the writes visible to the host once the fence signalled.
+        command_buffer.memory_barrier(
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            vk::PipelineStageFlags2::HOST,
+            vk::AccessFlags2::HOST_READ,
+        );
+        command_buffer.end();
+
+        executor
+            .submit(QueueExecutorSubmitInfo {
+                command_buffers: vec![command_buffer],
+                frame_index,
+                wait_semaphores: Vec::new(),
+                signal_semaphores: Vec::new(),
+                fence: Some(&fences[frame_index]),
+            })
+            .unwrap_or_else(|err| panic!("Failed to submit the simulation: {}", err));
+    }
+
+    for fence in &fences {
+        fence.wait();
+    }
+
+    let simulated_particles = particles.read_slice();
+    let average_height = simulated_particles
+        .iter()
+        .map(|particle| particle.position[1])
+        .sum::<f32>()
+        / PARTICLE_COUNT as f32;
+    info!(
+        "Simulated {} particles for {} steps, their average height is {:.3}.",
+        PARTICLE_COUNT, STEP_COUNT, average_height
+    );
+    info!(
+        "{} validation errors were reported.",
+        vulkan.validation_error_count()
+    );
+}
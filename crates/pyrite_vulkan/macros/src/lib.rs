@@ -0,0 +1,102 @@
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+fn get_calling_crate() -> String {
+    std::env::var("CARGO_PKG_NAME").unwrap()
+}
+
+fn vulkan_mod_path() -> proc_macro2::TokenStream {
+    if get_calling_crate().starts_with("pyrite_") {
+        quote! { pyrite_vulkan }
+    } else {
+        quote! { pyrite::vulkan }
+    }
+}
+
+#[proc_macro_derive(Vertex)]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    impl_derive_vertex(&ast)
+}
+
+fn impl_derive_vertex(ast: &DeriveInput) -> TokenStream {
+    let name = &ast.ident;
+
+    if !is_repr_c(ast) {
+        return syn::Error::new_spanned(
+            name,
+            "Vertex can only be derived for #[repr(C)] structs, so the field offsets are stable.",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let fields = match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            _ => {
+                return syn::Error::new_spanned(
+                    name,
+                    "Vertex can only be derived for structs with named fields.",
+                )
+                .to_compile_error()
+                .into();
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "Vertex can only be derived for structs.")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    let vulkan_mod_path = vulkan_mod_path();
+    let attributes = fields.iter().enumerate().map(|(location, field)| {
+        let location = location as u32;
+        let field_name = field.ident.as_ref().unwrap();
+        let field_type = &field.ty;
+        quote! {
+            #vulkan_mod_path::ash::vk::VertexInputAttributeDescription {
+                location: #location,
+                binding,
+                format: <#field_type as #vulkan_mod_path::objects::VertexAttribute>::FORMAT,
+                offset: ::std::mem::offset_of!(#name, #field_name) as u32,
+            }
+        }
+    });
+
+    let gen = quote! {
+        impl #vulkan_mod_path::objects::Vertex for #name {
+            fn attributes(binding: u32) -> Vec<#vulkan_mod_path::ash::vk::VertexInputAttributeDescription> {
+                vec![#(#attributes),*]
+            }
+        }
+    };
+
+    gen.into()
+}
+
+fn is_repr_c(ast: &DeriveInput) -> bool {
+    let mut is_repr_c = false;
+    for attr in ast.attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") {
+                is_repr_c = true;
+            }
+            // Skip the arguments of reprs like align(16).
+            if meta.input.peek(syn::token::Paren) {
+                let _content;
+                syn::parenthesized!(_content in meta.input);
+            }
+            Ok(())
+        });
+    }
+    is_repr_c
+}
@@ -1,9 +1,16 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Weak},
+};
 
 use ash::vk;
+use parking_lot::Mutex;
 use pyrite_app::resource::Resource;
 
-use crate::{Vulkan, VulkanDep};
+use crate::{
+    util::{GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep, VulkanError, VulkanFeature, VulkanResult,
+};
 
 pub struct MemoryAllocation {
     instance: Arc<MemoryAllocationInstance>,
@@ -13,25 +20,134 @@ impl MemoryAllocation {
     pub fn instance(&self) -> &MemoryAllocationInstance {
         &self.instance
     }
+
+    /// Keeps the memory alive while the device may still access it, e.g. after unbinding it
+    /// from a sparse resource.
+    pub fn create_generic_dep(&self) -> GenericResourceDep {
+        self.instance.clone()
+    }
 }
 
+/// A range of device memory, either suballocated from a shared memory block or given its own
+/// dedicated device memory when it is larger than the block size.
 pub struct MemoryAllocationInstance {
-    vulkan_dep: VulkanDep,
-    device_memory: vk::DeviceMemory,
+    block: Arc<MemoryBlock>,
+    offset: u64,
     size: u64,
+    /// The buddy order of the range in the block, `None` for dedicated allocations.
+    order: Option<u32>,
 }
 
 impl MemoryAllocationInstance {
     pub fn device_memory(&self) -> vk::DeviceMemory {
-        self.device_memory
+        self.block.device_memory
+    }
+
+    /// The offset of the allocation in the device memory, needed when binding resources.
+    pub fn offset(&self) -> u64 {
+        self.offset
     }
 
     pub fn size(&self) -> u64 {
         self.size
     }
+
+    /// A pointer to the start of the allocation if its memory is host visible, the memory stays
+    /// mapped for the lifetime of the allocation.
+    pub fn mapped_ptr(&self) -> Option<*mut u8> {
+        self.block
+            .mapped_ptr
+            .as_ref()
+            .map(|mapped_ptr| unsafe { mapped_ptr.0.add(self.offset as usize) })
+    }
+
+    /// Makes host writes to the mapped memory visible to the device, does nothing if the memory
+    /// is host coherent.
+    pub fn flush(&self) {
+        if let Some(range) = self.non_coherent_range() {
+            unsafe {
+                self.block
+                    .vulkan_dep
+                    .device()
+                    .flush_mapped_memory_ranges(&[range])
+                    .expect("Failed to flush mapped memory");
+            }
+        }
+    }
+
+    /// Makes device writes to the memory visible to the mapped memory, does nothing if the
+    /// memory is host coherent.
+    pub fn invalidate(&self) {
+        if let Some(range) = self.non_coherent_range() {
+            unsafe {
+                self.block
+                    .vulkan_dep
+                    .device()
+                    .invalidate_mapped_memory_ranges(&[range])
+                    .expect("Failed to invalidate mapped memory");
+            }
+        }
+    }
+
+    /// The range of the allocation aligned to `nonCoherentAtomSize`, `None` if the memory isn't
+    /// mapped or is host coherent.
+    fn non_coherent_range(&self) -> Option<vk::MappedMemoryRange<'static>> {
+        if self.block.mapped_ptr.is_none() || self.block.is_coherent {
+            return None;
+        }
+
+        let atom_size = self
+            .block
+            .vulkan_dep
+            .physical_device()
+            .properties()
+            .limits
+            .non_coherent_atom_size;
+        let start = self.offset / atom_size * atom_size;
+        let end = (self.offset + self.size).div_ceil(atom_size) * atom_size;
+        let size = if end >= self.block.size {
+            vk::WHOLE_SIZE
+        } else {
+            end - start
+        };
+
+        Some(
+            vk::MappedMemoryRange::default()
+                .memory(self.block.device_memory)
+                .offset(start)
+                .size(size),
+        )
+    }
 }
 
+impl VulkanResource for MemoryAllocationInstance {}
+
 impl Drop for MemoryAllocationInstance {
+    fn drop(&mut self) {
+        if let (Some(buddy), Some(order)) = (&self.block.buddy, self.order) {
+            buddy.lock().free(self.offset, order);
+        }
+    }
+}
+
+struct MappedPtr(*mut u8);
+
+// Safety: The pointer is only handed out offset to allocations, which never overlap.
+unsafe impl Send for MappedPtr {}
+unsafe impl Sync for MappedPtr {}
+
+struct MemoryBlock {
+    vulkan_dep: VulkanDep,
+    device_memory: vk::DeviceMemory,
+    size: u64,
+    buddy: Option<Mutex<BuddyAllocator>>,
+    /// Host visible blocks are mapped once when created, since memory can't be mapped by
+    /// multiple allocations at the same time.
+    mapped_ptr: Option<MappedPtr>,
+    is_coherent: bool,
+}
+
+impl Drop for MemoryBlock {
     fn drop(&mut self) {
         unsafe {
             self.vulkan_dep
@@ -41,57 +157,283 @@ impl Drop for MemoryAllocationInstance {
     }
 }
 
+pub struct VulkanMemoryAllocatorConfig {
+    /// The size of the blocks that device local allocations are suballocated from, rounded up
+    /// to a power of two.
+    pub device_local_block_size: u64,
+    /// The size of the blocks that host visible allocations are suballocated from, rounded up
+    /// to a power of two.
+    pub host_visible_block_size: u64,
+    /// The smallest range handed out from a block, smaller allocations are rounded up to it.
+    pub min_allocation_size: u64,
+}
+
+impl Default for VulkanMemoryAllocatorConfig {
+    fn default() -> Self {
+        Self {
+            device_local_block_size: 256 * 1024 * 1024,
+            host_visible_block_size: 64 * 1024 * 1024,
+            min_allocation_size: 256,
+        }
+    }
+}
+
+/// Memory usage of the allocator, used to decide when fragmentation is worth compacting.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VulkanMemoryAllocatorStats {
+    /// The number of device memory allocations, including dedicated allocations.
+    pub device_memory_count: usize,
+    pub allocation_count: usize,
+    /// The total size of all device memory.
+    pub reserved_bytes: u64,
+    /// The bytes handed out to allocations, including the padding from rounding up to the
+    /// buddy sizes.
+    pub used_bytes: u64,
+    /// The largest allocation that could currently be suballocated without a new block.
+    pub largest_free_range: u64,
+    /// The unused bytes of the blocks, excluding the space lost to padding.
+    pub free_bytes: u64,
+}
+
+impl VulkanMemoryAllocatorStats {
+    /// How scattered the free memory is, 0 if all free memory of a block is one range and
+    /// approaching 1 as it splits into many small ranges.
+    pub fn fragmentation(&self) -> f32 {
+        if self.free_bytes == 0 {
+            return 0.0;
+        }
+
+        1.0 - self.largest_free_range as f32 / self.free_bytes as f32
+    }
+}
+
+/// Suballocates device memory out of large blocks with a buddy allocator, so the number of
+/// device memory allocations stays far below `maxMemoryAllocationCount`.
+///
+/// Ranges are aligned to their power of two size within the block, which satisfies the alignment
+/// requirements of any resource that fits in them.
 #[derive(Resource)]
 pub struct VulkanMemoryAllocator {
     vulkan_dep: VulkanDep,
+    config: VulkanMemoryAllocatorConfig,
+    /// The blocks of each memory type, linear and optimal resources are kept in separate blocks
+    /// so they never need to be padded by `bufferImageGranularity`.
+    blocks: HashMap<(u32, bool), Vec<Arc<MemoryBlock>>>,
+    dedicated_allocations: Vec<Weak<MemoryAllocationInstance>>,
 }
 
 pub struct VulkanAllocationInfo {
     pub size: u64,
+    pub alignment: u64,
     pub memory_proprties: vk::MemoryPropertyFlags,
     pub memory_type_bits: u32,
+    /// True for buffers and linearly tiled images.
+    pub linear: bool,
 }
 
 impl VulkanMemoryAllocator {
     pub fn new(vulkan: &Vulkan) -> Self {
+        Self::with_config(vulkan, VulkanMemoryAllocatorConfig::default())
+    }
+
+    pub fn with_config(vulkan: &Vulkan, config: VulkanMemoryAllocatorConfig) -> Self {
         Self {
             vulkan_dep: vulkan.create_dep(),
+            config,
+            blocks: HashMap::new(),
+            dedicated_allocations: Vec::new(),
         }
     }
 
+    /// # Panics
+    /// If the memory can't be allocated, see [`VulkanMemoryAllocator::try_allocate`].
     pub fn allocate(&mut self, info: &VulkanAllocationInfo) -> MemoryAllocation {
-        let memory_allocate_info = vk::MemoryAllocateInfo::default()
-            .allocation_size(info.size)
-            .memory_type_index(
-                self.find_memory_type_index(info.memory_type_bits, info.memory_proprties),
-            );
+        self.try_allocate(info)
+            .unwrap_or_else(|err| panic!("Failed to allocate memory: {}", err))
+    }
 
-        let device_memory = unsafe {
-            self.vulkan_dep
-                .device()
-                .allocate_memory(&memory_allocate_info, None)
-                .expect("Failed to allocate memory")
-        };
-        let allocated_size = unsafe {
-            self.vulkan_dep
-                .device()
-                .get_device_memory_commitment(device_memory)
-        };
+    pub fn try_allocate(&mut self, info: &VulkanAllocationInfo) -> VulkanResult<MemoryAllocation> {
+        let memory_type_index =
+            self.find_memory_type_index(info.memory_type_bits, info.memory_proprties)?;
+        let block_size = self.block_size(memory_type_index);
+        let size = info.size.max(info.alignment);
+
+        if size > block_size {
+            let instance = Arc::new(MemoryAllocationInstance {
+                block: Arc::new(self.allocate_device_memory(memory_type_index, size, None)?),
+                offset: 0,
+                size,
+                order: None,
+            });
+
+            self.dedicated_allocations
+                .retain(|allocation| allocation.strong_count() > 0);
+            self.dedicated_allocations.push(Arc::downgrade(&instance));
+            return Ok(MemoryAllocation { instance });
+        }
+
+        Ok(MemoryAllocation {
+            instance: self.suballocate((memory_type_index, info.linear), block_size, size)?,
+        })
+    }
+
+    /// Frees the device memory of blocks that have no allocations left, blocks are otherwise
+    /// kept around to be reused.
+    pub fn release_unused_blocks(&mut self) {
+        for blocks in self.blocks.values_mut() {
+            // Every allocation in a block holds a reference to it.
+            blocks.retain(|block| Arc::strong_count(block) > 1);
+        }
+    }
+
+    pub fn stats(&self) -> VulkanMemoryAllocatorStats {
+        let mut stats = VulkanMemoryAllocatorStats::default();
+
+        for allocation in self.dedicated_allocations.iter().filter_map(Weak::upgrade) {
+            stats.device_memory_count += 1;
+            stats.allocation_count += 1;
+            stats.reserved_bytes += allocation.size;
+            stats.used_bytes += allocation.size;
+        }
 
-        MemoryAllocation {
-            instance: Arc::new(MemoryAllocationInstance {
-                vulkan_dep: self.vulkan_dep.clone(),
-                device_memory,
-                size: allocated_size,
-            }),
+        for block in self.blocks.values().flatten() {
+            let buddy = block.buddy.as_ref().unwrap().lock();
+            stats.device_memory_count += 1;
+            stats.allocation_count += Arc::strong_count(block) - 1;
+            stats.reserved_bytes += block.size;
+            stats.free_bytes += buddy.free_bytes();
+            stats.used_bytes += block.size - buddy.free_bytes();
+            stats.largest_free_range = stats.largest_free_range.max(buddy.largest_free_range());
         }
+
+        stats
+    }
+
+    fn suballocate(
+        &mut self,
+        block_key: (u32, bool),
+        block_size: u64,
+        size: u64,
+    ) -> VulkanResult<Arc<MemoryAllocationInstance>> {
+        let blocks = self.blocks.entry(block_key).or_default();
+        for block in blocks.iter() {
+            let range = block.buddy.as_ref().unwrap().lock().allocate(size);
+            if let Some((offset, order)) = range {
+                return Ok(Arc::new(MemoryAllocationInstance {
+                    block: block.clone(),
+                    offset,
+                    size,
+                    order: Some(order),
+                }));
+            }
+        }
+
+        let buddy = BuddyAllocator::new(block_size, self.config.min_allocation_size);
+        let block = Arc::new(self.allocate_device_memory(
+            block_key.0,
+            block_size,
+            Some(Mutex::new(buddy)),
+        )?);
+        let (offset, order) = block
+            .buddy
+            .as_ref()
+            .unwrap()
+            .lock()
+            .allocate(size)
+            .expect("A new memory block should fit any allocation up to the block size.");
+
+        self.blocks
+            .entry(block_key)
+            .or_default()
+            .push(block.clone());
+
+        Ok(Arc::new(MemoryAllocationInstance {
+            block,
+            offset,
+            size,
+            order: Some(order),
+        }))
+    }
+
+    fn allocate_device_memory(
+        &self,
+        memory_type_index: u32,
+        size: u64,
+        buddy: Option<Mutex<BuddyAllocator>>,
+    ) -> VulkanResult<MemoryBlock> {
+        // Buffers used by ray tracing are referenced by their device address, which needs to be
+        // allowed for the whole memory.
+        let mut memory_allocate_flags_info =
+            vk::MemoryAllocateFlagsInfo::default().flags(vk::MemoryAllocateFlags::DEVICE_ADDRESS);
+        let mut memory_allocate_info = vk::MemoryAllocateInfo::default()
+            .allocation_size(size)
+            .memory_type_index(memory_type_index);
+        if self
+            .vulkan_dep
+            .is_feature_enabled(VulkanFeature::RayTracing)
+        {
+            memory_allocate_info = memory_allocate_info.push_next(&mut memory_allocate_flags_info);
+        }
+
+        let device = self.vulkan_dep.device();
+        let device_memory = unsafe { device.allocate_memory(&memory_allocate_info, None)? };
+
+        let property_flags = self.memory_type_flags(memory_type_index);
+        let mapped_ptr = if property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+            let ptr = unsafe {
+                device.map_memory(
+                    device_memory,
+                    0,
+                    vk::WHOLE_SIZE,
+                    vk::MemoryMapFlags::empty(),
+                )
+            };
+            match ptr {
+                Ok(ptr) => Some(MappedPtr(ptr as *mut u8)),
+                Err(result) => {
+                    unsafe { device.free_memory(device_memory, None) };
+                    return Err(result.into());
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(MemoryBlock {
+            vulkan_dep: self.vulkan_dep.clone(),
+            device_memory,
+            size,
+            buddy,
+            mapped_ptr,
+            is_coherent: property_flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT),
+        })
+    }
+
+    fn memory_type_flags(&self, memory_type_index: u32) -> vk::MemoryPropertyFlags {
+        self.vulkan_dep
+            .physical_device()
+            .memory_properties()
+            .memory_types[memory_type_index as usize]
+            .property_flags
+    }
+
+    fn block_size(&self, memory_type_index: u32) -> u64 {
+        let block_size = if self
+            .memory_type_flags(memory_type_index)
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+        {
+            self.config.host_visible_block_size
+        } else {
+            self.config.device_local_block_size
+        };
+        block_size.next_power_of_two()
     }
 
     fn find_memory_type_index(
         &self,
         memory_type_bits: u32,
         properties: vk::MemoryPropertyFlags,
-    ) -> u32 {
+    ) -> VulkanResult<u32> {
         self.vulkan_dep
             .physical_device()
             .memory_properties()
@@ -103,6 +445,180 @@ impl VulkanMemoryAllocator {
                     && memory_type.property_flags.contains(properties)
             })
             .map(|(index, _)| index as u32)
-            .expect("Failed to find memory type index")
+            .ok_or(VulkanError::NoSuitableMemoryType)
+    }
+}
+
+/// Splits a power of two sized block into power of two ranges, merging freed ranges back with
+/// their buddy so free space doesn't stay split up.
+struct BuddyAllocator {
+    min_size: u64,
+    /// The offsets of the free ranges of each order, the size of a range is `min_size << order`.
+    free_ranges: Vec<BTreeSet<u64>>,
+}
+
+impl BuddyAllocator {
+    fn new(size: u64, min_size: u64) -> Self {
+        let min_size = min_size.next_power_of_two().min(size);
+        let max_order = (size / min_size).trailing_zeros();
+
+        let mut free_ranges = vec![BTreeSet::new(); max_order as usize + 1];
+        free_ranges[max_order as usize].insert(0);
+
+        Self {
+            min_size,
+            free_ranges,
+        }
+    }
+
+    /// Returns the offset and order of a free range that fits `size`.
+    fn allocate(&mut self, size: u64) -> Option<(u64, u32)> {
+        let order = self.order_of(size)?;
+        let free_order = (order..self.free_ranges.len() as u32)
+            .find(|free_order| !self.free_ranges[*free_order as usize].is_empty())?;
+
+        let offset = self.free_ranges[free_order as usize].pop_first().unwrap();
+        // Split the range in halves until it's the requested size, freeing the upper halves.
+        for split_order in (order..free_order).rev() {
+            let upper_half = offset + self.range_size(split_order);
+            self.free_ranges[split_order as usize].insert(upper_half);
+        }
+
+        Some((offset, order))
+    }
+
+    fn free(&mut self, mut offset: u64, mut order: u32) {
+        while (order as usize) < self.free_ranges.len() - 1 {
+            let buddy = offset ^ self.range_size(order);
+            if !self.free_ranges[order as usize].remove(&buddy) {
+                break;
+            }
+
+            offset = offset.min(buddy);
+            order += 1;
+        }
+
+        self.free_ranges[order as usize].insert(offset);
+    }
+
+    fn free_bytes(&self) -> u64 {
+        self.free_ranges
+            .iter()
+            .enumerate()
+            .map(|(order, ranges)| ranges.len() as u64 * self.range_size(order as u32))
+            .sum()
+    }
+
+    fn largest_free_range(&self) -> u64 {
+        (0..self.free_ranges.len() as u32)
+            .rev()
+            .find(|order| !self.free_ranges[*order as usize].is_empty())
+            .map_or(0, |order| self.range_size(order))
+    }
+
+    fn order_of(&self, size: u64) -> Option<u32> {
+        let order = (size.max(self.min_size).next_power_of_two() / self.min_size).trailing_zeros();
+        ((order as usize) < self.free_ranges.len()).then_some(order)
+    }
+
+    fn range_size(&self, order: u32) -> u64 {
+        self.min_size << order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn free_ranges(buddy: &BuddyAllocator) -> Vec<Vec<u64>> {
+        buddy
+            .free_ranges
+            .iter()
+            .map(|ranges| ranges.iter().copied().collect())
+            .collect()
+    }
+
+    #[test]
+    fn buddy_splits_on_allocate_and_merges_on_free() {
+        let mut buddy = BuddyAllocator::new(1024, 64);
+
+        assert_eq!(buddy.allocate(64), Some((0, 0)));
+        assert_eq!(
+            free_ranges(&buddy),
+            [vec![64], vec![128], vec![256], vec![512], vec![]]
+        );
+        assert_eq!(buddy.allocate(64), Some((64, 0)));
+        assert_eq!(buddy.allocate(100), Some((128, 1)));
+
+        // Ranges only merge once their buddy is free too.
+        buddy.free(0, 0);
+        assert_eq!(
+            free_ranges(&buddy),
+            [vec![0], vec![], vec![256], vec![512], vec![]]
+        );
+        buddy.free(128, 1);
+        buddy.free(64, 0);
+        assert_eq!(
+            free_ranges(&buddy),
+            [vec![], vec![], vec![], vec![], vec![0]]
+        );
+    }
+
+    #[test]
+    fn buddy_orders_round_up_to_powers_of_two() {
+        let buddy = BuddyAllocator::new(1024, 64);
+        assert_eq!(buddy.order_of(1), Some(0));
+        assert_eq!(buddy.order_of(64), Some(0));
+        assert_eq!(buddy.order_of(65), Some(1));
+        assert_eq!(buddy.order_of(1024), Some(4));
+        assert_eq!(buddy.order_of(1025), None);
+
+        let mut buddy = BuddyAllocator::new(1024, 100);
+        assert_eq!(buddy.range_size(0), 128);
+        assert_eq!(buddy.allocate(2048), None);
+        assert_eq!(buddy.allocate(1024), Some((0, 3)));
+        assert_eq!(buddy.allocate(1), None);
+    }
+
+    #[test]
+    fn buddy_tracks_free_space_across_allocations() {
+        let mut buddy = BuddyAllocator::new(1024, 64);
+
+        let a = buddy.allocate(64).unwrap();
+        let b = buddy.allocate(256).unwrap();
+        let c = buddy.allocate(64).unwrap();
+        assert_eq!((a.0, b.0, c.0), (0, 256, 64));
+        assert_eq!(buddy.free_bytes(), 640);
+        assert_eq!(buddy.largest_free_range(), 512);
+
+        buddy.free(a.0, a.1);
+        assert_eq!(buddy.free_bytes(), 704);
+        assert_eq!(buddy.largest_free_range(), 512);
+
+        let d = buddy.allocate(512).unwrap();
+        assert_eq!(buddy.free_bytes(), 192);
+        assert_eq!(buddy.largest_free_range(), 128);
+
+        buddy.free(c.0, c.1);
+        buddy.free(b.0, b.1);
+        assert_eq!(buddy.free_bytes(), 512);
+        assert_eq!(buddy.largest_free_range(), 512);
+
+        buddy.free(d.0, d.1);
+        assert_eq!(buddy.free_bytes(), 1024);
+        assert_eq!(buddy.largest_free_range(), 1024);
+    }
+
+    #[test]
+    fn fragmentation_compares_the_largest_range_to_all_free_space() {
+        let stats = |largest_free_range, free_bytes| VulkanMemoryAllocatorStats {
+            largest_free_range,
+            free_bytes,
+            ..Default::default()
+        };
+
+        assert_eq!(stats(0, 0).fragmentation(), 0.0);
+        assert_eq!(stats(1024, 1024).fragmentation(), 0.0);
+        assert_eq!(stats(256, 1024).fragmentation(), 0.75);
     }
 }
@@ -0,0 +1,104 @@
+use std::ffi::CString;
+
+use ash::vk;
+
+use crate::VulkanFeature;
+
+pub type VulkanResult<T> = Result<T, VulkanError>;
+
+/// The ways creating Vulkan objects can fail, returned by the `try_new` constructors.
+#[derive(Debug)]
+pub enum VulkanError {
+    /// The Vulkan loader couldn't be found or loaded.
+    Loading(ash::LoadingError),
+    /// The window or display handle of the swapchain support wasn't available.
+    WindowHandle(raw_window_handle::HandleError),
+    /// A requested instance layer isn't installed, e.g. the validation layers.
+    MissingLayer(CString),
+    /// The device doesn't support some of the requested features and extensions.
+    Unsupported {
+        features: Vec<VulkanFeature>,
+        extensions: Vec<CString>,
+    },
+    NoPhysicalDevice,
+    /// The queue configs are invalid, or a queue with [`crate::QueueResolution::Panic`]
+    /// couldn't be created.
+    InvalidQueueConfig(String),
+    /// No memory type matches the memory requirements and requested properties.
+    NoSuitableMemoryType,
+    OutOfMemory(vk::Result),
+    /// The shader code couldn't be reflected.
+    InvalidShader(String),
+    Vk(vk::Result),
+}
+
+impl From<vk::Result> for VulkanError {
+    fn from(result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY | vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => {
+                Self::OutOfMemory(result)
+            }
+            _ => Self::Vk(result),
+        }
+    }
+}
+
+impl From<ash::LoadingError> for VulkanError {
+    fn from(error: ash::LoadingError) -> Self {
+        Self::Loading(error)
+    }
+}
+
+impl From<raw_window_handle::HandleError> for VulkanError {
+    fn from(error: raw_window_handle::HandleError) -> Self {
+        Self::WindowHandle(error)
+    }
+}
+
+impl std::fmt::Display for VulkanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Loading(error) => write!(f, "failed to load Vulkan: {}", error),
+            Self::WindowHandle(error) => write!(f, "the window handle isn't available: {}", error),
+            Self::MissingLayer(layer) => {
+                write!(f, "the layer {} isn't installed", layer.to_string_lossy())
+            }
+            Self::Unsupported {
+                features,
+                extensions,
+            } => {
+                let mut unsupported = features
+                    .iter()
+                    .map(|feature| format!("feature {:?}", feature))
+                    .chain(
+                        extensions
+                            .iter()
+                            .map(|extension| format!("extension {}", extension.to_string_lossy())),
+                    )
+                    .collect::<Vec<_>>();
+                unsupported.sort();
+                write!(
+                    f,
+                    "the device doesn't support the requested {}",
+                    unsupported.join(", ")
+                )
+            }
+            Self::NoPhysicalDevice => write!(f, "no Vulkan device was found"),
+            Self::InvalidQueueConfig(message) => write!(f, "invalid queue config: {}", message),
+            Self::NoSuitableMemoryType => write!(f, "no suitable memory type was found"),
+            Self::OutOfMemory(result) => write!(f, "out of memory: {:?}", result),
+            Self::InvalidShader(message) => write!(f, "invalid shader: {}", message),
+            Self::Vk(result) => write!(f, "Vulkan call failed: {:?}", result),
+        }
+    }
+}
+
+impl std::error::Error for VulkanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Loading(error) => Some(error),
+            Self::WindowHandle(error) => Some(error),
+            _ => None,
+        }
+    }
+}
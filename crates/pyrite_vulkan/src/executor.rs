@@ -1,112 +1,207 @@
+use std::fmt::{Display, Formatter};
+
 use ash::vk;
 
-use crate::objects::{CommandBuffer, Fence, Semaphore};
+use crate::objects::{sync::legacy_stage_flags, CommandBuffer, Fence, Semaphore};
 use crate::swapchain::Swapchain;
-use crate::util::{GenericResourceDep, VulkanResourceDep};
+use crate::util::{FrameResourceArena, VulkanResourceDep};
 use crate::VulkanQueue;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitError {
+    /// The device was lost, every following submission fails as well. The hooks registered with
+    /// [`crate::VulkanInstance::on_device_lost`] were already called.
+    DeviceLost,
+    /// The swapchain no longer matches the surface and has to be recreated before presenting.
+    OutOfDate,
+    /// The image was presented, but the swapchain should be recreated to match the surface.
+    Suboptimal,
+    OutOfMemory,
+    Other(vk::Result),
+}
+
+impl SubmitError {
+    fn from_vk(result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_DEVICE_LOST => SubmitError::DeviceLost,
+            vk::Result::ERROR_OUT_OF_DATE_KHR => SubmitError::OutOfDate,
+            vk::Result::SUBOPTIMAL_KHR => SubmitError::Suboptimal,
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY | vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => {
+                SubmitError::OutOfMemory
+            }
+            result => SubmitError::Other(result),
+        }
+    }
+}
+
+impl Display for SubmitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmitError::DeviceLost => write!(f, "The device was lost"),
+            SubmitError::OutOfDate => write!(f, "The swapchain is out of date"),
+            SubmitError::Suboptimal => write!(f, "The swapchain is suboptimal"),
+            SubmitError::OutOfMemory => write!(f, "Ran out of memory"),
+            SubmitError::Other(result) => write!(f, "Vulkan error: {:?}", result),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
 /// A queue exectutor keeps track of in flight frame resources.
 pub struct QueueExecutor<const N: usize> {
     vulkan_dep: crate::VulkanDep,
     queue_name: String,
-    in_flight_dependencies: [Vec<GenericResourceDep>; N],
+    frame_arenas: [FrameResourceArena; N],
 }
 
 pub struct QueueExecutorSubmitInfo<'a> {
     pub command_buffers: Vec<&'a mut CommandBuffer>,
     pub frame_index: usize,
-    pub wait_semaphores: Vec<(&'a Semaphore, vk::PipelineStageFlags)>,
+    pub wait_semaphores: Vec<(&'a Semaphore, vk::PipelineStageFlags2)>,
     pub signal_semaphores: Vec<&'a Semaphore>,
     pub fence: Option<&'a Fence>,
 }
 
 impl<const N: usize> QueueExecutor<N> {
     pub fn new(vulkan: &crate::Vulkan, queue_name: impl Into<String>) -> Self {
-        let in_flight_dependencies = (0..N)
-            .map(|_| Vec::new())
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap_or_else(|_| panic!("Failed to create frames in flight."));
-
         Self {
             vulkan_dep: vulkan.create_dep(),
             queue_name: queue_name.into(),
-            in_flight_dependencies,
+            frame_arenas: std::array::from_fn(|_| FrameResourceArena::new()),
         }
     }
 
-    /// Block until the in flight frame resources are ready to be used.
-    /// Releases all the previously in flight resources.
+    /// Releases all the resources of the frame in flight, call it once the frame's fence was
+    /// signalled.
     pub fn release_frame_resources(&mut self, frame_index: usize) {
-        self.in_flight_dependencies[frame_index].clear();
+        self.frame_arenas[frame_index].clear();
     }
 
-    pub fn submit(&mut self, mut info: QueueExecutorSubmitInfo) {
-        let in_flight_dependencies = &mut self.in_flight_dependencies[info.frame_index as usize];
-        in_flight_dependencies.extend(
-            info.command_buffers
-                .iter_mut()
-                .flat_map(|command_buffer| command_buffer.take_recorded_dependencies()
-                    .into_iter()
-                    .map(|weak_dep| weak_dep.upgrade().expect("Tried to submit a command buffer with a dependency that was already dropped."))),
-        );
-        in_flight_dependencies.extend(
+    /// The resources kept alive for the frame in flight, other resources the frame uses can be
+    /// added to it.
+    pub fn frame_arena(&self, frame_index: usize) -> &FrameResourceArena {
+        &self.frame_arenas[frame_index]
+    }
+
+    pub fn frame_arena_mut(&mut self, frame_index: usize) -> &mut FrameResourceArena {
+        &mut self.frame_arenas[frame_index]
+    }
+
+    /// Submits the command buffers, keeping their dependencies alive until the frame is released
+    /// again.
+    pub fn submit(&mut self, mut info: QueueExecutorSubmitInfo) -> Result<(), SubmitError> {
+        pyrite_util::profile_scope!("QueueExecutor::submit");
+
+        let frame_arena = &mut self.frame_arenas[info.frame_index];
+        for command_buffer in info.command_buffers.iter_mut() {
+            frame_arena.extend_weak(command_buffer.take_recorded_dependencies());
+        }
+        frame_arena.extend(
             info.wait_semaphores
                 .iter()
                 .map(|(semaphore, _)| semaphore.create_dep().into_generic()),
         );
-        in_flight_dependencies.extend(
+        frame_arena.extend(
             info.signal_semaphores
                 .iter()
                 .map(|semaphore| semaphore.create_dep().into_generic()),
         );
         if let Some(fence) = info.fence {
-            in_flight_dependencies.push(fence.create_dep().into_generic());
+            frame_arena.push(fence.create_dep().into_generic());
         }
 
-        let vk_command_buffers = info
-            .command_buffers
-            .iter()
-            .map(|command_buffer| command_buffer.command_buffer())
-            .collect::<Vec<_>>();
-        let vk_wait_semaphores = info
-            .wait_semaphores
-            .iter()
-            .map(|semaphore| semaphore.0.semaphore())
-            .collect::<Vec<_>>();
-        let vk_wait_stages = info
-            .wait_semaphores
-            .iter()
-            .map(|semaphore| semaphore.1)
-            .collect::<Vec<_>>();
-        let vk_signal_semaphores = info
-            .signal_semaphores
-            .iter()
-            .map(|semaphore| semaphore.semaphore())
-            .collect::<Vec<_>>();
-        let vk_submit_infos = [vk::SubmitInfo::default()
-            .command_buffers(&vk_command_buffers)
-            .wait_semaphores(&vk_wait_semaphores)
-            .wait_dst_stage_mask(&vk_wait_stages)
-            .signal_semaphores(&vk_signal_semaphores)];
         let vk_fence = match info.fence {
             Some(fence) => fence.fence(),
             None => vk::Fence::null(),
         };
-        unsafe {
-            self.vulkan_dep
-                .device()
-                .queue_submit(self.queue().queue(), &vk_submit_infos, vk_fence)
-                .expect("Failed to submit queue")
+
+        let result = if let Some(synchronization2) = self.vulkan_dep.synchronization2() {
+            let vk_command_buffer_infos = info
+                .command_buffers
+                .iter()
+                .map(|command_buffer| {
+                    vk::CommandBufferSubmitInfo::default()
+                        .command_buffer(command_buffer.command_buffer())
+                })
+                .collect::<Vec<_>>();
+            let vk_wait_semaphore_infos = info
+                .wait_semaphores
+                .iter()
+                .map(|(semaphore, stage)| {
+                    vk::SemaphoreSubmitInfo::default()
+                        .semaphore(semaphore.semaphore())
+                        .stage_mask(*stage)
+                })
+                .collect::<Vec<_>>();
+            let vk_signal_semaphore_infos = info
+                .signal_semaphores
+                .iter()
+                .map(|semaphore| {
+                    vk::SemaphoreSubmitInfo::default()
+                        .semaphore(semaphore.semaphore())
+                        .stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)
+                })
+                .collect::<Vec<_>>();
+            let vk_submit_infos = [vk::SubmitInfo2::default()
+                .command_buffer_infos(&vk_command_buffer_infos)
+                .wait_semaphore_infos(&vk_wait_semaphore_infos)
+                .signal_semaphore_infos(&vk_signal_semaphore_infos)];
+
+            unsafe {
+                synchronization2.queue_submit2(self.queue().queue(), &vk_submit_infos, vk_fence)
+            }
+        } else {
+            let vk_command_buffers = info
+                .command_buffers
+                .iter()
+                .map(|command_buffer| command_buffer.command_buffer())
+                .collect::<Vec<_>>();
+            let vk_wait_semaphores = info
+                .wait_semaphores
+                .iter()
+                .map(|semaphore| semaphore.0.semaphore())
+                .collect::<Vec<_>>();
+            let vk_wait_stages = info
+                .wait_semaphores
+                .iter()
+                .map(|semaphore| {
+                    legacy_stage_flags(semaphore.1, vk::PipelineStageFlags::TOP_OF_PIPE)
+                })
+                .collect::<Vec<_>>();
+            let vk_signal_semaphores = info
+                .signal_semaphores
+                .iter()
+                .map(|semaphore| semaphore.semaphore())
+                .collect::<Vec<_>>();
+            let vk_submit_infos = [vk::SubmitInfo::default()
+                .command_buffers(&vk_command_buffers)
+                .wait_semaphores(&vk_wait_semaphores)
+                .wait_dst_stage_mask(&vk_wait_stages)
+                .signal_semaphores(&vk_signal_semaphores)];
+
+            unsafe {
+                self.vulkan_dep.device().queue_submit(
+                    self.queue().queue(),
+                    &vk_submit_infos,
+                    vk_fence,
+                )
+            }
         };
+
+        result.map_err(|result| self.handle_error(result))
     }
 
+    /// Presents the image, returning an error if the swapchain is out of date or suboptimal and
+    /// should be recreated. A suboptimal swapchain still presented the image.
     pub fn present(
         &mut self,
         swapchain: &Swapchain,
         image_index: u32,
         wait_semaphores: Vec<&Semaphore>,
-    ) {
+    ) -> Result<(), SubmitError> {
+        pyrite_util::profile_scope!("QueueExecutor::present");
+
         let image_indices = [image_index];
         let wait_semaphores = wait_semaphores
             .iter()
@@ -124,11 +219,19 @@ impl<const N: usize> QueueExecutor<N> {
                 .swapchain_loader()
                 .queue_present(self.queue().queue(), &present_info)
         };
-        if let Err(present_error) = present_result {
-            match present_error {
-                _ => panic!("Unknown error occured when presenting to the swapchain."),
-            }
+        match present_result {
+            Ok(false) => Ok(()),
+            Ok(true) => Err(SubmitError::Suboptimal),
+            Err(result) => Err(self.handle_error(result)),
+        }
+    }
+
+    fn handle_error(&self, result: vk::Result) -> SubmitError {
+        let error = SubmitError::from_vk(result);
+        if error == SubmitError::DeviceLost {
+            self.vulkan_dep.report_device_lost();
         }
+        error
     }
 
     pub fn wait_idle(&self) {
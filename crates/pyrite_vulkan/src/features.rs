@@ -0,0 +1,204 @@
+use std::{collections::HashSet, ffi::CStr};
+
+use ash::vk;
+
+use crate::VulkanPhysicalDevice;
+
+/// An optional device feature which can be requested in the [`crate::VulkanConfig`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum VulkanFeature {
+    /// Anisotropic filtering in samplers.
+    SamplerAnisotropy,
+    /// The line and point polygon modes.
+    FillModeNonSolid,
+    /// Indirect draws with a draw count above one.
+    MultiDrawIndirect,
+    /// The BC compressed texture formats.
+    TextureCompressionBc,
+    /// The ASTC LDR compressed texture formats.
+    TextureCompressionAstc,
+    /// The ETC2 and EAC compressed texture formats.
+    TextureCompressionEtc2,
+    /// Indirect draws reading their draw count from a buffer.
+    DrawIndirectCount,
+    /// `gl_DrawID`, `gl_BaseVertex` and `gl_BaseInstance` in vertex shaders.
+    ShaderDrawParameters,
+    /// Occlusion queries counting the exact number of passing samples, required for precise
+    /// [`crate::objects::OcclusionQuerySet`]s.
+    OcclusionQueryPrecise,
+    /// Pipeline statistics queries, required to create a
+    /// [`crate::objects::PipelineStatsQuerySet`].
+    PipelineStatisticsQuery,
+    /// Sparse binding and residency of 2D images, required to create a
+    /// [`crate::objects::SparseImage`].
+    SparseResidencyImage2D,
+    /// Runtime sized, partially bound and update after bind descriptor arrays, which are required
+    /// to create a [`crate::objects::BindlessTable`].
+    DescriptorIndexing,
+    /// `VK_KHR_dynamic_rendering`, which is required to create a
+    /// [`crate::objects::GraphicsPipeline`].
+    DynamicRendering,
+    /// `VK_KHR_synchronization2`, which is enabled whenever the device supports it. Requesting it
+    /// makes it required.
+    Synchronization2,
+    /// `VK_KHR_acceleration_structure` and `VK_KHR_ray_tracing_pipeline` with buffer device
+    /// addresses, which are required to create [`crate::objects::AccelerationStructure`]s and
+    /// [`crate::objects::RayTracingPipeline`]s.
+    RayTracing,
+    /// `VK_EXT_mesh_shader` with task shaders, which is required to create a mesh shading
+    /// [`crate::objects::GraphicsPipeline`], see [`crate::objects::GraphicsPipeline::new_mesh`].
+    MeshShader,
+}
+
+impl VulkanFeature {
+    /// The device extensions the feature requires, empty for core features.
+    pub fn extensions(&self) -> &'static [&'static CStr] {
+        match self {
+            Self::DynamicRendering => &[ash::extensions::khr::DynamicRendering::NAME],
+            Self::Synchronization2 => &[ash::extensions::khr::Synchronization2::NAME],
+            Self::RayTracing => &[
+                ash::extensions::khr::AccelerationStructure::NAME,
+                ash::extensions::khr::RayTracingPipeline::NAME,
+                ash::extensions::khr::DeferredHostOperations::NAME,
+            ],
+            Self::MeshShader => &[ash::extensions::ext::MeshShader::NAME],
+            _ => &[],
+        }
+    }
+}
+
+/// Queries which of the features the physical device supports.
+///
+/// Extension features are only queried if the device supports the extension.
+pub(crate) fn supported_features(
+    instance: &ash::Instance,
+    physical_device: &VulkanPhysicalDevice,
+) -> HashSet<VulkanFeature> {
+    let supports_dynamic_rendering =
+        physical_device.supports_extension(ash::extensions::khr::DynamicRendering::NAME);
+    let supports_synchronization2 =
+        physical_device.supports_extension(ash::extensions::khr::Synchronization2::NAME);
+    let supports_ray_tracing = VulkanFeature::RayTracing
+        .extensions()
+        .iter()
+        .all(|extension| physical_device.supports_extension(extension));
+    let supports_mesh_shader =
+        physical_device.supports_extension(ash::extensions::ext::MeshShader::NAME);
+
+    let mut vulkan11_features = vk::PhysicalDeviceVulkan11Features::default();
+    let mut vulkan12_features = vk::PhysicalDeviceVulkan12Features::default();
+    let mut dynamic_rendering_features = vk::PhysicalDeviceDynamicRenderingFeatures::default();
+    let mut synchronization2_features = vk::PhysicalDeviceSynchronization2Features::default();
+    let mut acceleration_structure_features =
+        vk::PhysicalDeviceAccelerationStructureFeaturesKHR::default();
+    let mut ray_tracing_pipeline_features =
+        vk::PhysicalDeviceRayTracingPipelineFeaturesKHR::default();
+    let mut mesh_shader_features = vk::PhysicalDeviceMeshShaderFeaturesEXT::default();
+    let mut features2 = vk::PhysicalDeviceFeatures2::default()
+        .push_next(&mut vulkan11_features)
+        .push_next(&mut vulkan12_features);
+    if supports_dynamic_rendering {
+        features2 = features2.push_next(&mut dynamic_rendering_features);
+    }
+    if supports_synchronization2 {
+        features2 = features2.push_next(&mut synchronization2_features);
+    }
+    if supports_ray_tracing {
+        features2 = features2
+            .push_next(&mut acceleration_structure_features)
+            .push_next(&mut ray_tracing_pipeline_features);
+    }
+    if supports_mesh_shader {
+        features2 = features2.push_next(&mut mesh_shader_features);
+    }
+    unsafe {
+        instance.get_physical_device_features2(physical_device.physical_device(), &mut features2);
+    }
+    let core_features = features2.features;
+
+    let descriptor_indexing = [
+        vulkan12_features.runtime_descriptor_array,
+        vulkan12_features.descriptor_binding_partially_bound,
+        vulkan12_features.descriptor_binding_variable_descriptor_count,
+        vulkan12_features.descriptor_binding_sampled_image_update_after_bind,
+        vulkan12_features.descriptor_binding_storage_buffer_update_after_bind,
+        vulkan12_features.shader_sampled_image_array_non_uniform_indexing,
+        vulkan12_features.shader_storage_buffer_array_non_uniform_indexing,
+    ]
+    .into_iter()
+    .all(|supported| supported == vk::TRUE);
+
+    [
+        (
+            VulkanFeature::SamplerAnisotropy,
+            core_features.sampler_anisotropy == vk::TRUE,
+        ),
+        (
+            VulkanFeature::FillModeNonSolid,
+            core_features.fill_mode_non_solid == vk::TRUE,
+        ),
+        (
+            VulkanFeature::MultiDrawIndirect,
+            core_features.multi_draw_indirect == vk::TRUE,
+        ),
+        (
+            VulkanFeature::TextureCompressionBc,
+            core_features.texture_compression_bc == vk::TRUE,
+        ),
+        (
+            VulkanFeature::TextureCompressionAstc,
+            core_features.texture_compression_astc_ldr == vk::TRUE,
+        ),
+        (
+            VulkanFeature::TextureCompressionEtc2,
+            core_features.texture_compression_etc2 == vk::TRUE,
+        ),
+        (
+            VulkanFeature::OcclusionQueryPrecise,
+            core_features.occlusion_query_precise == vk::TRUE,
+        ),
+        (
+            VulkanFeature::PipelineStatisticsQuery,
+            core_features.pipeline_statistics_query == vk::TRUE,
+        ),
+        (
+            VulkanFeature::SparseResidencyImage2D,
+            core_features.sparse_binding == vk::TRUE
+                && core_features.sparse_residency_image2_d == vk::TRUE,
+        ),
+        (
+            VulkanFeature::DrawIndirectCount,
+            vulkan12_features.draw_indirect_count == vk::TRUE,
+        ),
+        (
+            VulkanFeature::ShaderDrawParameters,
+            vulkan11_features.shader_draw_parameters == vk::TRUE,
+        ),
+        (VulkanFeature::DescriptorIndexing, descriptor_indexing),
+        (
+            VulkanFeature::DynamicRendering,
+            supports_dynamic_rendering && dynamic_rendering_features.dynamic_rendering == vk::TRUE,
+        ),
+        (
+            VulkanFeature::Synchronization2,
+            supports_synchronization2 && synchronization2_features.synchronization2 == vk::TRUE,
+        ),
+        (
+            VulkanFeature::RayTracing,
+            supports_ray_tracing
+                && acceleration_structure_features.acceleration_structure == vk::TRUE
+                && ray_tracing_pipeline_features.ray_tracing_pipeline == vk::TRUE
+                && vulkan12_features.buffer_device_address == vk::TRUE,
+        ),
+        (
+            VulkanFeature::MeshShader,
+            supports_mesh_shader
+                && mesh_shader_features.mesh_shader == vk::TRUE
+                && mesh_shader_features.task_shader == vk::TRUE,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, supported)| *supported)
+    .map(|(feature, _)| feature)
+    .collect()
+}
@@ -1,9 +1,29 @@
+pub use ash;
+
+// Lets the derive macros, which name `pyrite_vulkan`, be used within the crate.
+extern crate self as pyrite_vulkan;
+
 mod vulkan;
 pub use vulkan::*;
 
+mod features;
+pub use features::*;
+
+mod error;
+pub use error::*;
+
+mod validation;
+pub use validation::{
+    ValidationErrorAction, ValidationMessage, ValidationMessageId, ValidationPolicy,
+    ValidationSeverity,
+};
+
 pub mod allocator;
 pub mod executor;
 pub mod objects;
+pub mod profiler;
+pub mod recorder;
+pub mod stager;
 pub mod swapchain;
 pub mod util;
 
@@ -0,0 +1,442 @@
+use std::sync::Arc;
+
+use ash::vk;
+use pyrite_app::resource::Resource;
+
+use crate::{
+    allocator::VulkanMemoryAllocator,
+    util::{FrameResourceArena, GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep, VulkanFeature, VulkanInstance, VulkanRayTracing,
+};
+
+use super::{
+    Buffer, BufferCreateInfo, CommandBuffer, TypedBuffer, TypedBufferCreateInfo, UntypedBuffer,
+};
+
+pub type AccelerationStructureDep = Arc<AccelerationStructureInstance>;
+
+pub struct AccelerationStructureInstance {
+    vulkan_dep: VulkanDep,
+    acceleration_structure: vk::AccelerationStructureKHR,
+    ty: vk::AccelerationStructureTypeKHR,
+    device_address: vk::DeviceAddress,
+    buffer: UntypedBuffer,
+    /// The bottom level acceleration structures referenced by a top level one, which have to
+    /// outlive it.
+    referenced_dependencies: Vec<GenericResourceDep>,
+}
+
+impl AccelerationStructureInstance {
+    pub fn acceleration_structure(&self) -> vk::AccelerationStructureKHR {
+        self.acceleration_structure
+    }
+
+    pub fn ty(&self) -> vk::AccelerationStructureTypeKHR {
+        self.ty
+    }
+
+    /// The address referenced by top level acceleration structure instances.
+    pub fn device_address(&self) -> vk::DeviceAddress {
+        self.device_address
+    }
+
+    pub fn buffer(&self) -> &UntypedBuffer {
+        &self.buffer
+    }
+
+    pub fn referenced_dependencies(&self) -> &[GenericResourceDep] {
+        &self.referenced_dependencies
+    }
+}
+
+impl VulkanResource for AccelerationStructureInstance {}
+
+impl Drop for AccelerationStructureInstance {
+    fn drop(&mut self) {
+        unsafe {
+            ray_tracing(&self.vulkan_dep)
+                .acceleration_structure()
+                .destroy_acceleration_structure(self.acceleration_structure, None);
+        }
+    }
+}
+
+/// A bottom or top level acceleration structure built by an [`AccelerationStructureBuilder`].
+pub struct AccelerationStructure {
+    instance: Arc<AccelerationStructureInstance>,
+}
+
+impl AccelerationStructure {
+    pub fn instance(&self) -> &AccelerationStructureInstance {
+        &self.instance
+    }
+
+    pub fn create_dep(&self) -> AccelerationStructureDep {
+        self.instance.clone()
+    }
+
+    pub fn create_generic_dep(&self) -> GenericResourceDep {
+        self.instance.clone()
+    }
+}
+
+/// Triangles to build a bottom level acceleration structure from.
+///
+/// The buffers need the `ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR` and
+/// `SHADER_DEVICE_ADDRESS` usages, and their contents must be visible to the
+/// `ACCELERATION_STRUCTURE_BUILD_KHR` stage when the build executes.
+pub struct AccelerationStructureTriangles<'a> {
+    pub vertex_buffer: &'a dyn Buffer,
+    /// The format of the vertex positions, usually `R32G32B32_SFLOAT`.
+    pub vertex_format: vk::Format,
+    pub vertex_stride: u64,
+    pub vertex_count: u32,
+    /// `u32` indices, the vertices are read in order if `None`.
+    pub index_buffer: Option<&'a dyn Buffer>,
+    pub triangle_count: u32,
+    /// Whether any hit shaders are skipped for the triangles.
+    pub opaque: bool,
+}
+
+/// A placement of a bottom level acceleration structure in a top level one.
+pub struct BlasInstance<'a> {
+    pub blas: &'a AccelerationStructure,
+    /// The row major 3x4 transform from the bottom level space to the top level space.
+    pub transform: [[f32; 4]; 3],
+    /// The value of `gl_InstanceCustomIndexEXT`, only the lower 24 bits are used.
+    pub custom_index: u32,
+    /// Rays only hit the instance if their cull mask shares a bit with it.
+    pub mask: u8,
+    /// The offset of the instance's hit groups in the shader binding table, only the lower 24
+    /// bits are used.
+    pub hit_group_offset: u32,
+    pub flags: vk::GeometryInstanceFlagsKHR,
+}
+
+/// The layout of a `vk::AccelerationStructureInstanceKHR`, which can be written to buffers since
+/// it's [`bytemuck::Pod`] unlike the ash struct.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PackedBlasInstance {
+    transform: [f32; 12],
+    custom_index_and_mask: u32,
+    hit_group_offset_and_flags: u32,
+    blas_device_address: u64,
+}
+
+impl PackedBlasInstance {
+    fn new(instance: &BlasInstance<'_>) -> Self {
+        // Packed like `vk::Packed24_8`, the low 24 bits followed by the high 8 bits.
+        let pack_24_8 = |low_24: u32, high_8: u8| (low_24 & 0xff_ffff) | ((high_8 as u32) << 24);
+
+        Self {
+            transform: bytemuck::cast(instance.transform),
+            custom_index_and_mask: pack_24_8(instance.custom_index, instance.mask),
+            hit_group_offset_and_flags: pack_24_8(
+                instance.hit_group_offset,
+                instance.flags.as_raw() as u8,
+            ),
+            blas_device_address: instance.blas.instance().device_address(),
+        }
+    }
+}
+
+/// Builds acceleration structures, managing the scratch and instance buffers of the builds.
+///
+/// Like the [`crate::stager::VulkanStager`], the buffers are kept alive until their frame in
+/// flight comes around again, at which point the builds recorded into that frame's command
+/// buffers have finished.
+///
+/// # Panics
+/// Building panics if [`VulkanFeature::RayTracing`] wasn't enabled.
+#[derive(Resource)]
+pub struct AccelerationStructureBuilder {
+    frames: Vec<FrameResourceArena>,
+    frame_index: usize,
+}
+
+impl AccelerationStructureBuilder {
+    pub fn new(frames_in_flight: usize) -> Self {
+        Self {
+            frames: (0..frames_in_flight)
+                .map(|_| FrameResourceArena::new())
+                .collect(),
+            frame_index: 0,
+        }
+    }
+
+    /// Releases the scratch buffers of the last use of `frame_index`, the following builds are
+    /// recorded into that frame.
+    ///
+    /// Must be called after the frame's fence was waited on.
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        self.frame_index = frame_index;
+        self.frames[frame_index].clear();
+    }
+
+    /// Records the build of a bottom level acceleration structure from `geometries`.
+    ///
+    /// The build is followed by a barrier making it visible to later builds and ray tracing
+    /// shaders.
+    pub fn build_blas(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        command_buffer: &mut CommandBuffer,
+        geometries: &[AccelerationStructureTriangles<'_>],
+        flags: vk::BuildAccelerationStructureFlagsKHR,
+        name: Option<String>,
+    ) -> AccelerationStructure {
+        let mut dependencies = Vec::new();
+        let vk_geometries = geometries
+            .iter()
+            .map(|geometry| {
+                dependencies.push(geometry.vertex_buffer.create_generic_dep());
+                let mut triangles = vk::AccelerationStructureGeometryTrianglesDataKHR::default()
+                    .vertex_format(geometry.vertex_format)
+                    .vertex_data(vk::DeviceOrHostAddressConstKHR {
+                        device_address: geometry.vertex_buffer.instance().device_address(),
+                    })
+                    .vertex_stride(geometry.vertex_stride)
+                    .max_vertex(geometry.vertex_count.saturating_sub(1))
+                    .index_type(vk::IndexType::NONE_KHR);
+                if let Some(index_buffer) = geometry.index_buffer {
+                    dependencies.push(index_buffer.create_generic_dep());
+                    triangles = triangles.index_type(vk::IndexType::UINT32).index_data(
+                        vk::DeviceOrHostAddressConstKHR {
+                            device_address: index_buffer.instance().device_address(),
+                        },
+                    );
+                }
+
+                vk::AccelerationStructureGeometryKHR::default()
+                    .geometry_type(vk::GeometryTypeKHR::TRIANGLES)
+                    .geometry(vk::AccelerationStructureGeometryDataKHR { triangles })
+                    .flags(if geometry.opaque {
+                        vk::GeometryFlagsKHR::OPAQUE
+                    } else {
+                        vk::GeometryFlagsKHR::empty()
+                    })
+            })
+            .collect::<Vec<_>>();
+        let primitive_counts = geometries
+            .iter()
+            .map(|geometry| geometry.triangle_count)
+            .collect::<Vec<_>>();
+
+        self.build(
+            vulkan,
+            vulkan_allocator,
+            command_buffer,
+            vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL,
+            &vk_geometries,
+            &primitive_counts,
+            flags,
+            dependencies,
+            Vec::new(),
+            name,
+        )
+    }
+
+    /// Records the build of a top level acceleration structure from `instances`, which keeps the
+    /// referenced bottom level acceleration structures alive.
+    ///
+    /// The bottom level builds must be visible to the build, which they are if they were
+    /// recorded with [`AccelerationStructureBuilder::build_blas`].
+    pub fn build_tlas(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        command_buffer: &mut CommandBuffer,
+        instances: &[BlasInstance<'_>],
+        flags: vk::BuildAccelerationStructureFlagsKHR,
+        name: Option<String>,
+    ) -> AccelerationStructure {
+        let packed_instances = instances
+            .iter()
+            .map(PackedBlasInstance::new)
+            .collect::<Vec<_>>();
+
+        // Written by the host before the submission, which makes the writes visible to the build.
+        let mut instance_buffer = TypedBuffer::<PackedBlasInstance>::new(
+            vulkan,
+            vulkan_allocator,
+            &TypedBufferCreateInfo {
+                len: packed_instances.len().max(1),
+                usage: vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
+                    | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+                memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+                name: Some("acceleration structure instances".to_string()),
+            },
+        );
+        instance_buffer.write_slice(0, &packed_instances);
+
+        let vk_geometry = vk::AccelerationStructureGeometryKHR::default()
+            .geometry_type(vk::GeometryTypeKHR::INSTANCES)
+            .geometry(vk::AccelerationStructureGeometryDataKHR {
+                instances: vk::AccelerationStructureGeometryInstancesDataKHR::default()
+                    .array_of_pointers(false)
+                    .data(vk::DeviceOrHostAddressConstKHR {
+                        device_address: instance_buffer.instance().device_address(),
+                    }),
+            });
+        let referenced_dependencies = instances
+            .iter()
+            .map(|instance| instance.blas.create_generic_dep())
+            .collect::<Vec<_>>();
+
+        let tlas = self.build(
+            vulkan,
+            vulkan_allocator,
+            command_buffer,
+            vk::AccelerationStructureTypeKHR::TOP_LEVEL,
+            &[vk_geometry],
+            &[packed_instances.len() as u32],
+            flags,
+            vec![instance_buffer.create_generic_dep()],
+            referenced_dependencies,
+            name,
+        );
+        self.frames[self.frame_index].push(instance_buffer.create_generic_dep());
+        tlas
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        command_buffer: &mut CommandBuffer,
+        ty: vk::AccelerationStructureTypeKHR,
+        geometries: &[vk::AccelerationStructureGeometryKHR<'_>],
+        primitive_counts: &[u32],
+        flags: vk::BuildAccelerationStructureFlagsKHR,
+        mut dependencies: Vec<GenericResourceDep>,
+        referenced_dependencies: Vec<GenericResourceDep>,
+        name: Option<String>,
+    ) -> AccelerationStructure {
+        assert!(
+            vulkan.is_feature_enabled(VulkanFeature::RayTracing),
+            "Acceleration structures require VulkanFeature::RayTracing."
+        );
+        let ray_tracing = ray_tracing(vulkan);
+
+        let mut build_info = vk::AccelerationStructureBuildGeometryInfoKHR::default()
+            .ty(ty)
+            .flags(flags)
+            .mode(vk::BuildAccelerationStructureModeKHR::BUILD)
+            .geometries(geometries);
+        let mut build_sizes = vk::AccelerationStructureBuildSizesInfoKHR::default();
+        unsafe {
+            ray_tracing
+                .acceleration_structure()
+                .get_acceleration_structure_build_sizes(
+                    vk::AccelerationStructureBuildTypeKHR::DEVICE,
+                    &build_info,
+                    primitive_counts,
+                    &mut build_sizes,
+                );
+        }
+
+        let buffer = UntypedBuffer::new(
+            vulkan,
+            vulkan_allocator,
+            &BufferCreateInfo {
+                size: build_sizes.acceleration_structure_size,
+                usage: vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR
+                    | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+                memory_properties: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                name: name.clone(),
+            },
+        );
+        let acceleration_structure = unsafe {
+            ray_tracing
+                .acceleration_structure()
+                .create_acceleration_structure(
+                    &vk::AccelerationStructureCreateInfoKHR::default()
+                        .buffer(buffer.instance().buffer())
+                        .size(build_sizes.acceleration_structure_size)
+                        .ty(ty),
+                    None,
+                )
+                .unwrap_or_else(|err| panic!("Failed to create acceleration structure: {}", err))
+        };
+        if let Some(name) = &name {
+            vulkan.set_debug_name(acceleration_structure, name);
+        }
+        let device_address = unsafe {
+            ray_tracing
+                .acceleration_structure()
+                .get_acceleration_structure_device_address(
+                    &vk::AccelerationStructureDeviceAddressInfoKHR::default()
+                        .acceleration_structure(acceleration_structure),
+                )
+        };
+        let acceleration_structure = AccelerationStructure {
+            instance: Arc::new(AccelerationStructureInstance {
+                vulkan_dep: vulkan.create_dep(),
+                acceleration_structure,
+                ty,
+                device_address,
+                buffer,
+                referenced_dependencies,
+            }),
+        };
+
+        // The buffer's alignment isn't necessarily enough for the scratch data, so the address is
+        // aligned within a larger buffer.
+        let scratch_alignment = ray_tracing
+            .properties()
+            .min_acceleration_structure_scratch_offset_alignment
+            as u64;
+        let scratch_buffer = UntypedBuffer::new(
+            vulkan,
+            vulkan_allocator,
+            &BufferCreateInfo {
+                size: build_sizes.build_scratch_size + scratch_alignment,
+                usage: vk::BufferUsageFlags::STORAGE_BUFFER
+                    | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+                memory_properties: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                name: Some("acceleration structure scratch".to_string()),
+            },
+        );
+        let scratch_address = scratch_buffer
+            .instance()
+            .device_address()
+            .next_multiple_of(scratch_alignment.max(1));
+
+        build_info = build_info
+            .dst_acceleration_structure(acceleration_structure.instance().acceleration_structure())
+            .scratch_data(vk::DeviceOrHostAddressKHR {
+                device_address: scratch_address,
+            });
+        let build_ranges = primitive_counts
+            .iter()
+            .map(|&primitive_count| {
+                vk::AccelerationStructureBuildRangeInfoKHR::default()
+                    .primitive_count(primitive_count)
+            })
+            .collect::<Vec<_>>();
+
+        dependencies.push(acceleration_structure.create_generic_dep());
+        dependencies.push(scratch_buffer.create_generic_dep());
+        command_buffer.build_acceleration_structure(&build_info, &build_ranges, &dependencies);
+        command_buffer.memory_barrier(
+            vk::PipelineStageFlags2::ACCELERATION_STRUCTURE_BUILD_KHR,
+            vk::AccessFlags2::ACCELERATION_STRUCTURE_WRITE_KHR,
+            vk::PipelineStageFlags2::ACCELERATION_STRUCTURE_BUILD_KHR
+                | vk::PipelineStageFlags2::RAY_TRACING_SHADER_KHR,
+            vk::AccessFlags2::ACCELERATION_STRUCTURE_READ_KHR,
+        );
+
+        self.frames[self.frame_index].push(scratch_buffer.create_generic_dep());
+        acceleration_structure
+    }
+}
+
+pub(crate) fn ray_tracing(vulkan: &VulkanInstance) -> &VulkanRayTracing {
+    vulkan
+        .ray_tracing()
+        .expect("Ray tracing requires VulkanFeature::RayTracing.")
+}
@@ -0,0 +1,472 @@
+use std::sync::Arc;
+
+use ash::vk;
+use pyrite_app::resource::Resource;
+
+use crate::{
+    util::{GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep, VulkanFeature,
+};
+
+use super::{Buffer, DescriptorSetLayout, Image, Sampler};
+
+/// The set index of the sampled image array in pipeline layouts using the bindless table.
+pub const BINDLESS_SAMPLED_IMAGE_SET: u32 = 0;
+/// The set index of the storage buffer array in pipeline layouts using the bindless table.
+pub const BINDLESS_STORAGE_BUFFER_SET: u32 = 1;
+
+pub type BindlessSetDep = Arc<BindlessSetInstance>;
+
+/// A descriptor set with a single variable count array binding, along with the pool it was
+/// allocated from.
+pub struct BindlessSetInstance {
+    vulkan_dep: VulkanDep,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+}
+
+impl BindlessSetInstance {
+    fn new(
+        vulkan: &Vulkan,
+        layout: &DescriptorSetLayout,
+        descriptor_type: vk::DescriptorType,
+        capacity: u32,
+    ) -> Self {
+        let descriptor_pool_sizes = [vk::DescriptorPoolSize::default()
+            .ty(descriptor_type)
+            .descriptor_count(capacity)];
+        let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo::default()
+            .pool_sizes(&descriptor_pool_sizes)
+            .max_sets(1)
+            .flags(vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND);
+
+        // Safety: The descriptor pool is dropped when the bindless set instance is dropped
+        let descriptor_pool = unsafe {
+            vulkan
+                .device()
+                .create_descriptor_pool(&descriptor_pool_create_info, None)
+                .expect("Failed to create bindless descriptor pool")
+        };
+
+        let descriptor_set_layouts = [layout.instance().layout()];
+        let descriptor_counts = [capacity];
+        let mut variable_count_allocate_info =
+            vk::DescriptorSetVariableDescriptorCountAllocateInfo::default()
+                .descriptor_counts(&descriptor_counts);
+        let descriptor_set_allocate_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&descriptor_set_layouts)
+            .push_next(&mut variable_count_allocate_info);
+
+        let descriptor_set = unsafe {
+            vulkan
+                .device()
+                .allocate_descriptor_sets(&descriptor_set_allocate_info)
+                .expect("Failed to allocate bindless descriptor set")[0]
+        };
+
+        Self {
+            vulkan_dep: vulkan.create_dep(),
+            descriptor_pool,
+            descriptor_set,
+        }
+    }
+
+    pub fn descriptor_set(&self) -> vk::DescriptorSet {
+        self.descriptor_set
+    }
+}
+
+impl VulkanResource for BindlessSetInstance {}
+
+impl Drop for BindlessSetInstance {
+    fn drop(&mut self) {
+        unsafe {
+            self.vulkan_dep
+                .device()
+                .destroy_descriptor_pool(self.descriptor_pool, None);
+        }
+    }
+}
+
+enum BindlessDescriptor {
+    Buffer(vk::DescriptorBufferInfo),
+    Image(vk::DescriptorImageInfo),
+}
+
+struct BindlessEntry {
+    descriptor: BindlessDescriptor,
+    /// Keeps the resources alive while they are in the table.
+    _dependencies: Vec<GenericResourceDep>,
+}
+
+/// Hands out the indices of a bindless array, reusing removed indices before new ones.
+struct BindlessIndices {
+    capacity: u32,
+    max_capacity: u32,
+    /// The number of indices handed out so far, including removed ones.
+    len: u32,
+    free_indices: Vec<u32>,
+}
+
+impl BindlessIndices {
+    fn new(initial_capacity: u32, max_capacity: u32) -> Self {
+        Self {
+            capacity: initial_capacity.clamp(1, max_capacity),
+            max_capacity,
+            len: 0,
+            free_indices: Vec::new(),
+        }
+    }
+
+    /// Takes a free index, doubling the capacity if it doesn't fit. `None` if every index up to
+    /// the max capacity is in use.
+    fn allocate(&mut self) -> Option<u32> {
+        if let Some(index) = self.free_indices.pop() {
+            return Some(index);
+        }
+        if self.len >= self.max_capacity {
+            return None;
+        }
+
+        let index = self.len;
+        self.len += 1;
+        if index >= self.capacity {
+            self.capacity = (self.capacity * 2).min(self.max_capacity);
+        }
+        Some(index)
+    }
+
+    fn free(&mut self, index: u32) {
+        self.free_indices.push(index);
+    }
+
+    fn capacity(&self) -> u32 {
+        self.capacity
+    }
+}
+
+/// A growable array of descriptors of a single type, indices stay valid until they are removed.
+struct BindlessArray {
+    descriptor_type: vk::DescriptorType,
+    layout: DescriptorSetLayout,
+    set: Arc<BindlessSetInstance>,
+    indices: BindlessIndices,
+    entries: Vec<Option<BindlessEntry>>,
+}
+
+impl BindlessArray {
+    fn new(
+        vulkan: &Vulkan,
+        descriptor_type: vk::DescriptorType,
+        initial_capacity: u32,
+        max_capacity: u32,
+    ) -> Self {
+        let mut layout_builder = DescriptorSetLayout::builder();
+        layout_builder.add_binding_with_flags(
+            0,
+            descriptor_type,
+            max_capacity,
+            vk::ShaderStageFlags::ALL,
+            vk::DescriptorBindingFlags::PARTIALLY_BOUND
+                | vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT
+                | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+        );
+        let layout = layout_builder.build(vulkan);
+
+        let indices = BindlessIndices::new(initial_capacity, max_capacity);
+        let set = Arc::new(BindlessSetInstance::new(
+            vulkan,
+            &layout,
+            descriptor_type,
+            indices.capacity(),
+        ));
+
+        Self {
+            descriptor_type,
+            layout,
+            set,
+            indices,
+            entries: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, vulkan: &Vulkan, entry: BindlessEntry) -> u32 {
+        let capacity = self.indices.capacity();
+        let index = self.indices.allocate().unwrap_or_else(|| {
+            panic!(
+                "The bindless table is full, it can hold at most {} {:?} descriptors.",
+                self.indices.max_capacity, self.descriptor_type
+            )
+        });
+        if self.indices.capacity() != capacity {
+            self.grow(vulkan);
+        }
+        if index as usize >= self.entries.len() {
+            self.entries.push(None);
+        }
+
+        self.write(vulkan, &self.set, index, &entry.descriptor);
+        self.entries[index as usize] = Some(entry);
+        index
+    }
+
+    fn remove(&mut self, index: u32) {
+        // The descriptor is left as is since the binding is partially bound, shaders must not
+        // access the index after it was removed.
+        let removed = self.entries.get_mut(index as usize).and_then(Option::take);
+        assert!(
+            removed.is_some(),
+            "Tried to remove a bindless index that isn't in use."
+        );
+
+        self.indices.free(index);
+    }
+
+    /// Replaces the set with one of the grown capacity and rewrites every entry into it.
+    ///
+    /// The old set is kept alive by any command buffers that bound it, so it can be replaced at
+    /// any point in a frame.
+    fn grow(&mut self, vulkan: &Vulkan) {
+        let set = Arc::new(BindlessSetInstance::new(
+            vulkan,
+            &self.layout,
+            self.descriptor_type,
+            self.indices.capacity(),
+        ));
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            if let Some(entry) = entry {
+                self.write(vulkan, &set, index as u32, &entry.descriptor);
+            }
+        }
+
+        self.set = set;
+    }
+
+    fn write(
+        &self,
+        vulkan: &Vulkan,
+        set: &BindlessSetInstance,
+        index: u32,
+        descriptor: &BindlessDescriptor,
+    ) {
+        let vk_write = vk::WriteDescriptorSet::default()
+            .dst_set(set.descriptor_set)
+            .dst_binding(0)
+            .dst_array_element(index)
+            .descriptor_type(self.descriptor_type);
+        let vk_write = match descriptor {
+            BindlessDescriptor::Buffer(buffer_info) => {
+                vk_write.buffer_info(std::slice::from_ref(buffer_info))
+            }
+            BindlessDescriptor::Image(image_info) => {
+                vk_write.image_info(std::slice::from_ref(image_info))
+            }
+        };
+
+        unsafe {
+            vulkan.device().update_descriptor_sets(&[vk_write], &[]);
+        }
+    }
+}
+
+pub struct BindlessTableConfig {
+    /// The number of descriptors of each type the sets are first allocated with.
+    pub initial_capacity: u32,
+    /// The most sampled images the table can hold, clamped to the device limit.
+    pub max_sampled_images: u32,
+    /// The most storage buffers the table can hold, clamped to the device limit.
+    pub max_storage_buffers: u32,
+}
+
+impl Default for BindlessTableConfig {
+    fn default() -> Self {
+        Self {
+            initial_capacity: 256,
+            max_sampled_images: 1 << 16,
+            max_storage_buffers: 1 << 16,
+        }
+    }
+}
+
+/// Hands out stable indices into global arrays of sampled images and storage buffers, so draws
+/// can index their resources in the shader instead of binding descriptor sets per draw.
+///
+/// Sampled images are in set [`BINDLESS_SAMPLED_IMAGE_SET`] and storage buffers in set
+/// [`BINDLESS_STORAGE_BUFFER_SET`], both at binding 0 as runtime sized arrays. The underlying
+/// sets are reallocated as the table grows, so [`BindlessTable::descriptor_sets`] should be bound
+/// every frame rather than cached.
+#[derive(Resource)]
+pub struct BindlessTable {
+    sampled_images: BindlessArray,
+    storage_buffers: BindlessArray,
+}
+
+impl BindlessTable {
+    /// # Panics
+    /// If [`crate::VulkanFeature::DescriptorIndexing`] wasn't enabled.
+    pub fn new(vulkan: &Vulkan, config: &BindlessTableConfig) -> Self {
+        assert!(
+            vulkan.is_feature_enabled(VulkanFeature::DescriptorIndexing),
+            "Descriptor indexing must be enabled to create a bindless table."
+        );
+
+        let mut descriptor_indexing_properties =
+            vk::PhysicalDeviceDescriptorIndexingProperties::default();
+        let mut properties =
+            vk::PhysicalDeviceProperties2::default().push_next(&mut descriptor_indexing_properties);
+        unsafe {
+            vulkan.instance().get_physical_device_properties2(
+                vulkan.physical_device().physical_device(),
+                &mut properties,
+            );
+        }
+
+        let max_sampled_images = config.max_sampled_images.min(
+            descriptor_indexing_properties.max_descriptor_set_update_after_bind_sampled_images,
+        );
+        let max_storage_buffers = config.max_storage_buffers.min(
+            descriptor_indexing_properties.max_descriptor_set_update_after_bind_storage_buffers,
+        );
+
+        Self {
+            sampled_images: BindlessArray::new(
+                vulkan,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                config.initial_capacity,
+                max_sampled_images,
+            ),
+            storage_buffers: BindlessArray::new(
+                vulkan,
+                vk::DescriptorType::STORAGE_BUFFER,
+                config.initial_capacity,
+                max_storage_buffers,
+            ),
+        }
+    }
+
+    /// Adds an image which will be in `image_layout` whenever it is sampled, returning its index
+    /// in the sampled image array.
+    ///
+    /// # Panics
+    /// If the image has no image view or the table is full.
+    pub fn add_sampled_image(
+        &mut self,
+        vulkan: &Vulkan,
+        image: &impl Image,
+        image_layout: vk::ImageLayout,
+        sampler: &Sampler,
+    ) -> u32 {
+        let image_view = image
+            .instance()
+            .image_view()
+            .expect("Tried to add an image without an image view to the bindless table.");
+        let image_info = vk::DescriptorImageInfo::default()
+            .image_view(image_view)
+            .image_layout(image_layout)
+            .sampler(sampler.sampler());
+
+        self.sampled_images.insert(
+            vulkan,
+            BindlessEntry {
+                descriptor: BindlessDescriptor::Image(image_info),
+                _dependencies: vec![image.create_generic_dep(), sampler.create_generic_dep()],
+            },
+        )
+    }
+
+    /// Adds a whole buffer, returning its index in the storage buffer array.
+    ///
+    /// # Panics
+    /// If the table is full.
+    pub fn add_storage_buffer(&mut self, vulkan: &Vulkan, buffer: &impl Buffer) -> u32 {
+        let buffer_info = vk::DescriptorBufferInfo::default()
+            .buffer(buffer.instance().buffer())
+            .offset(0)
+            .range(vk::WHOLE_SIZE);
+
+        self.storage_buffers.insert(
+            vulkan,
+            BindlessEntry {
+                descriptor: BindlessDescriptor::Buffer(buffer_info),
+                _dependencies: vec![buffer.create_generic_dep()],
+            },
+        )
+    }
+
+    /// Frees the index so it can be handed out again.
+    ///
+    /// # Panics
+    /// If the index isn't in use.
+    pub fn remove_sampled_image(&mut self, index: u32) {
+        self.sampled_images.remove(index);
+    }
+
+    /// Frees the index so it can be handed out again.
+    ///
+    /// # Panics
+    /// If the index isn't in use.
+    pub fn remove_storage_buffer(&mut self, index: u32) {
+        self.storage_buffers.remove(index);
+    }
+
+    /// The layouts to put in pipeline layouts, in set order.
+    pub fn descriptor_set_layouts(&self) -> [&DescriptorSetLayout; 2] {
+        [&self.sampled_images.layout, &self.storage_buffers.layout]
+    }
+
+    /// The current descriptor sets, in set order.
+    pub fn descriptor_sets(&self) -> [vk::DescriptorSet; 2] {
+        [
+            self.sampled_images.set.descriptor_set,
+            self.storage_buffers.set.descriptor_set,
+        ]
+    }
+
+    /// Dependencies on the current descriptor sets, to keep them alive while a command buffer that
+    /// bound them is executing.
+    pub fn create_generic_deps(&self) -> [GenericResourceDep; 2] {
+        [
+            self.sampled_images.set.clone(),
+            self.storage_buffers.set.clone(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indices_grow_up_to_the_max_capacity() {
+        let mut indices = BindlessIndices::new(2, 5);
+        let mut capacities = Vec::new();
+        for expected in 0..5 {
+            assert_eq!(indices.allocate(), Some(expected));
+            capacities.push(indices.capacity());
+        }
+        assert_eq!(capacities, [2, 2, 4, 4, 5]);
+        assert_eq!(indices.allocate(), None);
+
+        assert_eq!(BindlessIndices::new(0, 5).capacity(), 1);
+        assert_eq!(BindlessIndices::new(8, 5).capacity(), 5);
+    }
+
+    #[test]
+    fn removed_indices_are_reused_before_new_ones() {
+        let mut indices = BindlessIndices::new(4, 8);
+        for _ in 0..4 {
+            indices.allocate();
+        }
+
+        indices.free(1);
+        indices.free(3);
+        assert_eq!(indices.allocate(), Some(3));
+        assert_eq!(indices.allocate(), Some(1));
+        assert_eq!(indices.capacity(), 4);
+
+        assert_eq!(indices.allocate(), Some(4));
+        assert_eq!(indices.capacity(), 8);
+    }
+}
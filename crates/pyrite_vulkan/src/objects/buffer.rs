@@ -0,0 +1,321 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use ash::vk;
+
+use crate::{
+    allocator::{MemoryAllocation, VulkanAllocationInfo, VulkanMemoryAllocator},
+    util::{GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep, VulkanResult,
+};
+
+pub type BufferDep = Arc<BufferInstance>;
+
+pub trait Buffer {
+    fn instance(&self) -> &BufferInstance;
+    fn create_dep(&self) -> BufferDep;
+    fn create_generic_dep(&self) -> GenericResourceDep;
+}
+
+pub struct BufferInstance {
+    vulkan_dep: VulkanDep,
+    buffer: vk::Buffer,
+    size: u64,
+    allocation: MemoryAllocation,
+}
+
+impl BufferInstance {
+    pub fn buffer(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    /// The size of the buffer in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn allocation(&self) -> &MemoryAllocation {
+        &self.allocation
+    }
+
+    /// The address of the buffer for ray tracing, the buffer needs the `SHADER_DEVICE_ADDRESS`
+    /// usage.
+    ///
+    /// # Panics
+    /// If [`crate::VulkanFeature::RayTracing`] wasn't enabled, which enables buffer device
+    /// addresses.
+    pub fn device_address(&self) -> vk::DeviceAddress {
+        assert!(
+            self.vulkan_dep
+                .is_feature_enabled(crate::VulkanFeature::RayTracing),
+            "Buffer device addresses require VulkanFeature::RayTracing."
+        );
+
+        unsafe {
+            self.vulkan_dep.device().get_buffer_device_address(
+                &vk::BufferDeviceAddressInfo::default().buffer(self.buffer),
+            )
+        }
+    }
+}
+
+impl VulkanResource for BufferInstance {}
+
+impl Drop for BufferInstance {
+    fn drop(&mut self) {
+        unsafe {
+            self.vulkan_dep.device().destroy_buffer(self.buffer, None);
+        }
+    }
+}
+
+pub struct BufferCreateInfo {
+    /// The size of the buffer in bytes.
+    pub size: u64,
+    pub usage: vk::BufferUsageFlags,
+    pub memory_properties: vk::MemoryPropertyFlags,
+    /// The name shown in debugging tools.
+    pub name: Option<String>,
+}
+
+pub struct UntypedBuffer {
+    instance: Arc<BufferInstance>,
+}
+
+impl UntypedBuffer {
+    /// # Panics
+    /// If the buffer can't be created, see [`UntypedBuffer::try_new`].
+    pub fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: &BufferCreateInfo,
+    ) -> Self {
+        Self::try_new(vulkan, vulkan_allocator, info)
+            .unwrap_or_else(|err| panic!("Failed to create buffer: {}", err))
+    }
+
+    pub fn try_new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: &BufferCreateInfo,
+    ) -> VulkanResult<Self> {
+        let buffer_create_info = vk::BufferCreateInfo::default()
+            .size(info.size)
+            .usage(info.usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+
+        let buffer = unsafe { vulkan.device().create_buffer(&buffer_create_info, None)? };
+
+        let memory_requirements = unsafe { vulkan.device().get_buffer_memory_requirements(buffer) };
+
+        // The buffer is destroyed if allocating or binding the memory fails, the allocation is
+        // freed when dropped.
+        let bound_allocation = vulkan_allocator
+            .try_allocate(&VulkanAllocationInfo {
+                size: memory_requirements.size,
+                alignment: memory_requirements.alignment,
+                memory_proprties: info.memory_properties,
+                memory_type_bits: memory_requirements.memory_type_bits,
+                linear: true,
+            })
+            .and_then(|allocation| {
+                unsafe {
+                    vulkan.device().bind_buffer_memory(
+                        buffer,
+                        allocation.instance().device_memory(),
+                        allocation.instance().offset(),
+                    )?
+                };
+                Ok(allocation)
+            });
+        let allocation = match bound_allocation {
+            Ok(allocation) => allocation,
+            Err(err) => {
+                unsafe { vulkan.device().destroy_buffer(buffer, None) };
+                return Err(err);
+            }
+        };
+
+        if let Some(name) = &info.name {
+            vulkan.set_debug_name(buffer, name);
+        }
+
+        Ok(Self {
+            instance: Arc::new(BufferInstance {
+                vulkan_dep: vulkan.create_dep(),
+                buffer,
+                size: info.size,
+                allocation,
+            }),
+        })
+    }
+
+    /// Copies `data` into the buffer at the byte `offset` and flushes it.
+    ///
+    /// # Panics
+    /// If the buffer isn't host visible or the data doesn't fit in the buffer.
+    pub fn write_bytes(&mut self, offset: u64, data: &[u8]) {
+        assert!(
+            offset + data.len() as u64 <= self.instance.size,
+            "Tried to write {} bytes at offset {} to a buffer of size {}.",
+            data.len(),
+            offset,
+            self.instance.size
+        );
+
+        let allocation = self.instance.allocation.instance();
+        let mapped_ptr = allocation
+            .mapped_ptr()
+            .expect("Tried to write to a buffer that isn't host visible.");
+
+        // Safety: The range was checked to be within the buffer, which is within the allocation.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                mapped_ptr.add(offset as usize),
+                data.len(),
+            );
+        }
+        allocation.flush();
+    }
+
+    /// Reads `len` bytes from the buffer at the byte `offset`, invalidating the mapped memory
+    /// first so device writes are visible.
+    ///
+    /// # Panics
+    /// If the buffer isn't host visible or the range isn't within the buffer.
+    pub fn read_bytes(&self, offset: u64, len: usize) -> Vec<u8> {
+        assert!(
+            offset + len as u64 <= self.instance.size,
+            "Tried to read {} bytes at offset {} from a buffer of size {}.",
+            len,
+            offset,
+            self.instance.size
+        );
+
+        let allocation = self.instance.allocation.instance();
+        let mapped_ptr = allocation
+            .mapped_ptr()
+            .expect("Tried to read from a buffer that isn't host visible.");
+        allocation.invalidate();
+
+        // Safety: The range was checked to be within the buffer, which is within the allocation.
+        unsafe { std::slice::from_raw_parts(mapped_ptr.add(offset as usize), len).to_vec() }
+    }
+}
+
+impl Buffer for UntypedBuffer {
+    fn instance(&self) -> &BufferInstance {
+        self.instance.as_ref()
+    }
+
+    fn create_dep(&self) -> BufferDep {
+        self.instance.clone()
+    }
+
+    fn create_generic_dep(&self) -> GenericResourceDep {
+        self.instance.clone()
+    }
+}
+
+/// A buffer of `len` elements of `T`.
+///
+/// Elements are copied to and from the buffer as bytes, so `T` must be [`bytemuck::Pod`] with a
+/// layout matching what the shaders expect, e.g. `#[repr(C)]` structs of the glsl types.
+pub struct TypedBuffer<T> {
+    untyped_buffer: UntypedBuffer,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+pub struct TypedBufferCreateInfo {
+    /// The number of elements in the buffer.
+    pub len: usize,
+    pub usage: vk::BufferUsageFlags,
+    pub memory_properties: vk::MemoryPropertyFlags,
+    /// The name shown in debugging tools.
+    pub name: Option<String>,
+}
+
+impl<T: bytemuck::Pod> TypedBuffer<T> {
+    /// # Panics
+    /// If the buffer can't be created, see [`TypedBuffer::try_new`].
+    pub fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: &TypedBufferCreateInfo,
+    ) -> Self {
+        Self::try_new(vulkan, vulkan_allocator, info)
+            .unwrap_or_else(|err| panic!("Failed to create buffer: {}", err))
+    }
+
+    pub fn try_new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: &TypedBufferCreateInfo,
+    ) -> VulkanResult<Self> {
+        Ok(Self {
+            untyped_buffer: UntypedBuffer::try_new(
+                vulkan,
+                vulkan_allocator,
+                &BufferCreateInfo {
+                    size: (info.len * std::mem::size_of::<T>()) as u64,
+                    usage: info.usage,
+                    memory_properties: info.memory_properties,
+                    name: info.name.clone(),
+                },
+            )?,
+            len: info.len,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Writes `data` to the buffer starting at the element `offset`.
+    ///
+    /// # Panics
+    /// If the buffer isn't host visible or the data doesn't fit in the buffer.
+    pub fn write_slice(&mut self, offset: usize, data: &[T]) {
+        self.untyped_buffer.write_bytes(
+            (offset * std::mem::size_of::<T>()) as u64,
+            bytemuck::cast_slice(data),
+        );
+    }
+
+    /// Reads every element of the buffer.
+    ///
+    /// # Panics
+    /// If the buffer isn't host visible.
+    pub fn read_slice(&self) -> Vec<T> {
+        let bytes = self
+            .untyped_buffer
+            .read_bytes(0, self.len * std::mem::size_of::<T>());
+
+        // Copies into a new vec since the bytes are only aligned for u8.
+        bytemuck::pod_collect_to_vec(&bytes)
+    }
+
+    pub fn untyped(&self) -> &UntypedBuffer {
+        &self.untyped_buffer
+    }
+}
+
+impl<T> Buffer for TypedBuffer<T> {
+    fn instance(&self) -> &BufferInstance {
+        self.untyped_buffer.instance()
+    }
+
+    fn create_dep(&self) -> BufferDep {
+        self.untyped_buffer.create_dep()
+    }
+
+    fn create_generic_dep(&self) -> GenericResourceDep {
+        self.untyped_buffer.create_generic_dep()
+    }
+}
@@ -1,28 +1,191 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use ash::vk;
-use slotmap::{new_key_type, SlotMap};
+use pyrite_util::HandleMap;
 
 use crate::{
-    util::{VulkanResource, VulkanResourceDep, WeakGenericResourceDep},
-    Vulkan, VulkanDep,
+    util::{GenericResourceDep, VulkanResource, VulkanResourceDep, WeakGenericResourceDep},
+    Vulkan, VulkanDep, VulkanFeature, VulkanQueue,
 };
 
-use super::{Image, ImageMemoryBarrier};
+use super::{
+    acceleration_structure::ray_tracing,
+    sync::{legacy_access_flags, legacy_stage_flags},
+    Buffer, ComputePipeline, DescriptorSet, GraphicsPipeline, Image, ImageMemoryBarrier,
+    PipelineLayoutInstance, PushConstantRange, QueryPool, RayTracingPipeline, RenderingFormats,
+    ShaderBindingTable,
+};
+
+pyrite_util::new_handle_type! { pub struct CommandBufferHandle; }
+
+/// The last known state of an image within a command buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageState {
+    pub layout: vk::ImageLayout,
+    pub access: vk::AccessFlags2,
+    pub stage: vk::PipelineStageFlags2,
+}
+
+impl ImageState {
+    /// The state of an image whose contents can be discarded.
+    pub const UNDEFINED: Self = Self {
+        layout: vk::ImageLayout::UNDEFINED,
+        access: vk::AccessFlags2::NONE,
+        stage: vk::PipelineStageFlags2::NONE,
+    };
+
+    /// The access and stages that typically use an image in `layout`.
+    pub fn from_layout(layout: vk::ImageLayout) -> Self {
+        let (access, stage) = match layout {
+            vk::ImageLayout::UNDEFINED => return Self::UNDEFINED,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => (
+                vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+                vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+            ),
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL => (
+                vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+            ),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => (
+                vk::AccessFlags2::SHADER_SAMPLED_READ,
+                vk::PipelineStageFlags2::FRAGMENT_SHADER | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            ),
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL => (
+                vk::AccessFlags2::TRANSFER_READ,
+                vk::PipelineStageFlags2::ALL_TRANSFER,
+            ),
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL => (
+                vk::AccessFlags2::TRANSFER_WRITE,
+                vk::PipelineStageFlags2::ALL_TRANSFER,
+            ),
+            vk::ImageLayout::PRESENT_SRC_KHR => {
+                (vk::AccessFlags2::NONE, vk::PipelineStageFlags2::NONE)
+            }
+            // General and any other layouts can be used by anything.
+            _ => (
+                vk::AccessFlags2::MEMORY_READ | vk::AccessFlags2::MEMORY_WRITE,
+                vk::PipelineStageFlags2::ALL_COMMANDS,
+            ),
+        };
 
-new_key_type! { pub struct CommandBufferHandle; }
+        Self {
+            layout,
+            access,
+            stage,
+        }
+    }
+
+    fn has_writes(&self) -> bool {
+        self.access.intersects(
+            vk::AccessFlags2::SHADER_WRITE
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE
+                | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE
+                | vk::AccessFlags2::TRANSFER_WRITE
+                | vk::AccessFlags2::HOST_WRITE
+                | vk::AccessFlags2::MEMORY_WRITE,
+        )
+    }
+}
+
+pub struct RenderingAttachment<'a> {
+    /// The image rendered to, it must have an image view.
+    pub image: &'a dyn Image,
+    pub load_op: vk::AttachmentLoadOp,
+    pub store_op: vk::AttachmentStoreOp,
+    pub clear_value: vk::ClearValue,
+    /// A single sampled image the multisampled `image` is resolved to when the rendering ends,
+    /// averaging color samples and taking the first depth sample. It must have an image view.
+    pub resolve_image: Option<&'a dyn Image>,
+}
+
+pub struct RenderingInfo<'a> {
+    pub render_area: vk::Rect2D,
+    pub color_attachments: Vec<RenderingAttachment<'a>>,
+    pub depth_attachment: Option<RenderingAttachment<'a>>,
+    /// Whether the rendering is recorded in secondary command buffers executed with
+    /// [`CommandBuffer::execute_commands`] instead of inline.
+    pub secondary_command_buffers: bool,
+}
 
 pub struct CommandBuffer {
     vulkan_dep: VulkanDep,
     command_pool: std::sync::Weak<CommandPoolInstance>,
     command_buffer: ash::vk::CommandBuffer,
     recorded_dependencies: Vec<WeakGenericResourceDep>,
+    image_states: HashMap<vk::Image, ImageState>,
+    /// The layout of the pipeline last bound to each bind point, used for binding descriptor sets
+    /// and push constants.
+    bound_pipeline_layouts: HashMap<vk::PipelineBindPoint, BoundPipelineLayout>,
+}
+
+/// The parts of a bound pipeline's layout needed to record commands using it.
+struct BoundPipelineLayout {
+    layout: vk::PipelineLayout,
+    push_constant_ranges: Vec<PushConstantRange>,
+}
+
+impl BoundPipelineLayout {
+    fn new(pipeline_layout: &PipelineLayoutInstance) -> Self {
+        Self {
+            layout: pipeline_layout.layout(),
+            push_constant_ranges: pipeline_layout.push_constant_ranges().to_vec(),
+        }
+    }
+}
+
+/// Checks the push constant rules of `vkCmdPushConstants`, since violating them is undefined
+/// behavior without the validation layers.
+fn validate_push_constants(
+    ranges: &[PushConstantRange],
+    stages: vk::ShaderStageFlags,
+    offset: u32,
+    bytes: &[u8],
+) {
+    let size = bytes.len() as u32;
+    assert!(
+        offset.is_multiple_of(4) && size.is_multiple_of(4) && size > 0,
+        "Push constants must have an offset and size that are multiples of 4, got offset {} \
+         and size {}.",
+        offset,
+        size
+    );
+
+    for word_offset in (offset..offset + size).step_by(4) {
+        let overlapping_ranges = ranges
+            .iter()
+            .filter(|range| range.offset <= word_offset && word_offset < range.offset + range.size);
+
+        let mut covered_stages = vk::ShaderStageFlags::empty();
+        for range in overlapping_ranges {
+            assert!(
+                stages.contains(range.stage_flags),
+                "The push constants at offset {} must be pushed to all stages of the range \
+                 {:?}, got {:?}.",
+                word_offset,
+                range.stage_flags,
+                stages
+            );
+            covered_stages |= range.stage_flags;
+        }
+        assert!(
+            covered_stages.contains(stages),
+            "The push constants at offset {} for stages {:?} aren't in a push constant range of \
+             the pipeline layout.",
+            word_offset,
+            stages
+        );
+    }
 }
 
 impl CommandBuffer {
     pub fn begin(&mut self) {
         self.recorded_dependencies
             .push(self.command_pool.into_generic_weak());
+        self.image_states.clear();
+        self.bound_pipeline_layouts.clear();
 
         let command_buffer_begin_info = vk::CommandBufferBeginInfo::default()
             .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
@@ -35,6 +198,46 @@ impl CommandBuffer {
         }
     }
 
+    /// Begins a secondary command buffer.
+    ///
+    /// If `rendering_formats` is set the command buffer continues the dynamic rendering of the
+    /// primary command buffer executing it, which must have begun rendering to attachments with
+    /// these formats and [`RenderingInfo::secondary_command_buffers`] set.
+    pub fn begin_secondary(&mut self, rendering_formats: Option<&RenderingFormats>) {
+        self.recorded_dependencies
+            .push(self.command_pool.into_generic_weak());
+        self.image_states.clear();
+        self.bound_pipeline_layouts.clear();
+
+        let mut vk_inheritance_rendering_info =
+            vk::CommandBufferInheritanceRenderingInfo::default();
+        let mut vk_inheritance_info = vk::CommandBufferInheritanceInfo::default();
+        let mut flags = vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT;
+        if let Some(rendering_formats) = rendering_formats {
+            vk_inheritance_rendering_info = vk_inheritance_rendering_info
+                .rasterization_samples(rendering_formats.samples)
+                .color_attachment_formats(&rendering_formats.color_formats)
+                .depth_attachment_format(
+                    rendering_formats
+                        .depth_format
+                        .unwrap_or(vk::Format::UNDEFINED),
+                );
+            vk_inheritance_info = vk_inheritance_info.push_next(&mut vk_inheritance_rendering_info);
+            flags |= vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE;
+        }
+
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(flags)
+            .inheritance_info(&vk_inheritance_info);
+
+        unsafe {
+            self.vulkan_dep
+                .device()
+                .begin_command_buffer(self.command_buffer, &command_buffer_begin_info)
+                .expect("Failed to begin secondary command buffer");
+        }
+    }
+
     pub fn end(&mut self) {
         unsafe {
             self.vulkan_dep
@@ -44,40 +247,803 @@ impl CommandBuffer {
         }
     }
 
+    /// Records a barrier for the images, using synchronization2 if the device supports it.
+    ///
+    /// On devices without synchronization2 the stages and access masks are widened to their
+    /// legacy equivalents.
     pub fn pipeline_barrier(
         &mut self,
-        src_stage: vk::PipelineStageFlags,
-        dst_stage: vk::PipelineStageFlags,
+        src_stage: vk::PipelineStageFlags2,
+        dst_stage: vk::PipelineStageFlags2,
         image_memory_barriers: Vec<ImageMemoryBarrier>,
     ) {
         self.recorded_dependencies
             .extend(image_memory_barriers.iter().map(|image_memory_barrier| {
                 Arc::downgrade(&image_memory_barrier.image.create_generic_dep())
             }));
-        let vk_image_memory_barriers = image_memory_barriers
-            .into_iter()
-            .map(|image_memory_barrier| image_memory_barrier.into())
+        for image_memory_barrier in &image_memory_barriers {
+            self.image_states.insert(
+                image_memory_barrier.image.instance().image(),
+                ImageState {
+                    layout: image_memory_barrier.new_layout,
+                    access: image_memory_barrier.dst_access_mask,
+                    stage: dst_stage,
+                },
+            );
+        }
+
+        if let Some(synchronization2) = self.vulkan_dep.synchronization2() {
+            let vk_image_memory_barriers = image_memory_barriers
+                .into_iter()
+                .map(|image_memory_barrier| image_memory_barrier.into_vk2(src_stage, dst_stage))
+                .collect::<Vec<_>>();
+            let vk_dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&vk_image_memory_barriers);
+
+            unsafe {
+                synchronization2.cmd_pipeline_barrier2(self.command_buffer, &vk_dependency_info);
+            }
+        } else {
+            let vk_image_memory_barriers = image_memory_barriers
+                .into_iter()
+                .map(|image_memory_barrier| image_memory_barrier.into())
+                .collect::<Vec<vk::ImageMemoryBarrier>>();
+
+            unsafe {
+                self.vulkan_dep.device().cmd_pipeline_barrier(
+                    self.command_buffer,
+                    legacy_stage_flags(src_stage, vk::PipelineStageFlags::TOP_OF_PIPE),
+                    legacy_stage_flags(dst_stage, vk::PipelineStageFlags::BOTTOM_OF_PIPE),
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &vk_image_memory_barriers,
+                );
+            }
+        }
+    }
+
+    /// Records a global memory barrier, e.g. to make buffer writes visible to later commands.
+    pub fn memory_barrier(
+        &mut self,
+        src_stage: vk::PipelineStageFlags2,
+        src_access: vk::AccessFlags2,
+        dst_stage: vk::PipelineStageFlags2,
+        dst_access: vk::AccessFlags2,
+    ) {
+        if let Some(synchronization2) = self.vulkan_dep.synchronization2() {
+            let vk_memory_barriers = [vk::MemoryBarrier2::default()
+                .src_stage_mask(src_stage)
+                .src_access_mask(src_access)
+                .dst_stage_mask(dst_stage)
+                .dst_access_mask(dst_access)];
+            let vk_dependency_info =
+                vk::DependencyInfo::default().memory_barriers(&vk_memory_barriers);
+
+            unsafe {
+                synchronization2.cmd_pipeline_barrier2(self.command_buffer, &vk_dependency_info);
+            }
+        } else {
+            let vk_memory_barrier = vk::MemoryBarrier::default()
+                .src_access_mask(legacy_access_flags(src_access))
+                .dst_access_mask(legacy_access_flags(dst_access));
+
+            unsafe {
+                self.vulkan_dep.device().cmd_pipeline_barrier(
+                    self.command_buffer,
+                    legacy_stage_flags(src_stage, vk::PipelineStageFlags::TOP_OF_PIPE),
+                    legacy_stage_flags(dst_stage, vk::PipelineStageFlags::BOTTOM_OF_PIPE),
+                    vk::DependencyFlags::empty(),
+                    &[vk_memory_barrier],
+                    &[],
+                    &[],
+                );
+            }
+        }
+    }
+
+    /// Records the release or acquire half of a queue family ownership transfer. The barriers
+    /// only hold raw handles, so the caller keeps the resources alive.
+    ///
+    /// The images' known states are updated to their new layouts and destination accesses.
+    pub(crate) fn queue_ownership_barrier(
+        &mut self,
+        buffer_memory_barriers: &[vk::BufferMemoryBarrier2],
+        image_memory_barriers: &[vk::ImageMemoryBarrier2],
+    ) {
+        if buffer_memory_barriers.is_empty() && image_memory_barriers.is_empty() {
+            return;
+        }
+
+        for image_memory_barrier in image_memory_barriers {
+            self.image_states.insert(
+                image_memory_barrier.image,
+                ImageState {
+                    layout: image_memory_barrier.new_layout,
+                    access: image_memory_barrier.dst_access_mask,
+                    stage: image_memory_barrier.dst_stage_mask,
+                },
+            );
+        }
+
+        if let Some(synchronization2) = self.vulkan_dep.synchronization2() {
+            let vk_dependency_info = vk::DependencyInfo::default()
+                .buffer_memory_barriers(buffer_memory_barriers)
+                .image_memory_barriers(image_memory_barriers);
+
+            unsafe {
+                synchronization2.cmd_pipeline_barrier2(self.command_buffer, &vk_dependency_info);
+            }
+        } else {
+            let mut src_stage = vk::PipelineStageFlags2::NONE;
+            let mut dst_stage = vk::PipelineStageFlags2::NONE;
+            let vk_buffer_memory_barriers = buffer_memory_barriers
+                .iter()
+                .map(|barrier| {
+                    src_stage |= barrier.src_stage_mask;
+                    dst_stage |= barrier.dst_stage_mask;
+                    vk::BufferMemoryBarrier::default()
+                        .src_access_mask(legacy_access_flags(barrier.src_access_mask))
+                        .dst_access_mask(legacy_access_flags(barrier.dst_access_mask))
+                        .src_queue_family_index(barrier.src_queue_family_index)
+                        .dst_queue_family_index(barrier.dst_queue_family_index)
+                        .buffer(barrier.buffer)
+                        .offset(barrier.offset)
+                        .size(barrier.size)
+                })
+                .collect::<Vec<_>>();
+            let vk_image_memory_barriers = image_memory_barriers
+                .iter()
+                .map(|barrier| {
+                    src_stage |= barrier.src_stage_mask;
+                    dst_stage |= barrier.dst_stage_mask;
+                    vk::ImageMemoryBarrier::default()
+                        .src_access_mask(legacy_access_flags(barrier.src_access_mask))
+                        .dst_access_mask(legacy_access_flags(barrier.dst_access_mask))
+                        .old_layout(barrier.old_layout)
+                        .new_layout(barrier.new_layout)
+                        .src_queue_family_index(barrier.src_queue_family_index)
+                        .dst_queue_family_index(barrier.dst_queue_family_index)
+                        .image(barrier.image)
+                        .subresource_range(barrier.subresource_range)
+                })
+                .collect::<Vec<_>>();
+
+            unsafe {
+                self.vulkan_dep.device().cmd_pipeline_barrier(
+                    self.command_buffer,
+                    legacy_stage_flags(src_stage, vk::PipelineStageFlags::TOP_OF_PIPE),
+                    legacy_stage_flags(dst_stage, vk::PipelineStageFlags::BOTTOM_OF_PIPE),
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &vk_buffer_memory_barriers,
+                    &vk_image_memory_barriers,
+                );
+            }
+        }
+    }
+
+    pub fn copy_buffer(
+        &mut self,
+        src_buffer: &dyn Buffer,
+        dst_buffer: &dyn Buffer,
+        regions: &[vk::BufferCopy],
+    ) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&src_buffer.create_generic_dep()));
+        self.recorded_dependencies
+            .push(Arc::downgrade(&dst_buffer.create_generic_dep()));
+
+        unsafe {
+            self.vulkan_dep.device().cmd_copy_buffer(
+                self.command_buffer,
+                src_buffer.instance().buffer(),
+                dst_buffer.instance().buffer(),
+                regions,
+            );
+        }
+    }
+
+    /// Copies tightly packed texels or blocks from `buffer` into a mip level of the image, which
+    /// must be in the transfer destination layout. `width` and `height` are the extent of the
+    /// mip level.
+    pub fn copy_buffer_to_image(
+        &mut self,
+        buffer: &dyn Buffer,
+        image: &dyn Image,
+        mip_level: u32,
+        width: u32,
+        height: u32,
+    ) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&buffer.create_generic_dep()));
+        self.recorded_dependencies
+            .push(Arc::downgrade(&image.create_generic_dep()));
+
+        let region = vk::BufferImageCopy::default()
+            .image_subresource(
+                vk::ImageSubresourceLayers::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .mip_level(mip_level)
+                    .base_array_layer(0)
+                    .layer_count(1),
+            )
+            .image_extent(vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            });
+        unsafe {
+            self.vulkan_dep.device().cmd_copy_buffer_to_image(
+                self.command_buffer,
+                buffer.instance().buffer(),
+                image.instance().image(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+            );
+        }
+    }
+
+    /// Fills the mip levels after the first by repeatedly blitting each level into the next,
+    /// then transitions every level to `final_layout`.
+    ///
+    /// The whole image must be in the transfer destination layout with the first level written,
+    /// and its format must support linear blits.
+    pub fn generate_mipmaps(
+        &mut self,
+        image: &dyn Image,
+        width: u32,
+        height: u32,
+        mip_levels: u32,
+        final_layout: vk::ImageLayout,
+    ) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&image.create_generic_dep()));
+        let vk_image = image.instance().image();
+        let transfer_dst = ImageState::from_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+        let transfer_src = ImageState::from_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+        let final_state = ImageState::from_layout(final_layout);
+
+        let mip_offset = |level: u32| vk::Offset3D {
+            x: (width >> level).max(1) as i32,
+            y: (height >> level).max(1) as i32,
+            z: 1,
+        };
+        let mip_subresource = |level: u32| {
+            vk::ImageSubresourceLayers::default()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .mip_level(level)
+                .base_array_layer(0)
+                .layer_count(1)
+        };
+
+        for level in 1..mip_levels {
+            self.mip_barrier(vk_image, level - 1, 1, transfer_dst, transfer_src);
+
+            let blit = vk::ImageBlit {
+                src_subresource: mip_subresource(level - 1),
+                src_offsets: [vk::Offset3D::default(), mip_offset(level - 1)],
+                dst_subresource: mip_subresource(level),
+                dst_offsets: [vk::Offset3D::default(), mip_offset(level)],
+            };
+            unsafe {
+                self.vulkan_dep.device().cmd_blit_image(
+                    self.command_buffer,
+                    vk_image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk_image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[blit],
+                    vk::Filter::LINEAR,
+                );
+            }
+        }
+
+        // Every level but the last was a blit source.
+        if mip_levels > 1 {
+            self.mip_barrier(vk_image, 0, mip_levels - 1, transfer_src, final_state);
+        }
+        self.mip_barrier(vk_image, mip_levels - 1, 1, transfer_dst, final_state);
+
+        self.image_states.insert(vk_image, final_state);
+    }
+
+    /// Records a color image barrier for a range of mip levels, which [`ImageMemoryBarrier`]
+    /// can't express since it always covers the whole image.
+    fn mip_barrier(
+        &mut self,
+        image: vk::Image,
+        base_mip_level: u32,
+        level_count: u32,
+        old_state: ImageState,
+        new_state: ImageState,
+    ) {
+        let subresource_range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(base_mip_level)
+            .level_count(level_count)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        if let Some(synchronization2) = self.vulkan_dep.synchronization2() {
+            let vk_image_memory_barriers = [vk::ImageMemoryBarrier2::default()
+                .image(image)
+                .old_layout(old_state.layout)
+                .new_layout(new_state.layout)
+                .src_stage_mask(old_state.stage)
+                .src_access_mask(old_state.access)
+                .dst_stage_mask(new_state.stage)
+                .dst_access_mask(new_state.access)
+                .subresource_range(subresource_range)];
+            let vk_dependency_info =
+                vk::DependencyInfo::default().image_memory_barriers(&vk_image_memory_barriers);
+
+            unsafe {
+                synchronization2.cmd_pipeline_barrier2(self.command_buffer, &vk_dependency_info);
+            }
+        } else {
+            let vk_image_memory_barrier = vk::ImageMemoryBarrier::default()
+                .image(image)
+                .old_layout(old_state.layout)
+                .new_layout(new_state.layout)
+                .src_access_mask(legacy_access_flags(old_state.access))
+                .dst_access_mask(legacy_access_flags(new_state.access))
+                .subresource_range(subresource_range);
+
+            unsafe {
+                self.vulkan_dep.device().cmd_pipeline_barrier(
+                    self.command_buffer,
+                    legacy_stage_flags(old_state.stage, vk::PipelineStageFlags::TOP_OF_PIPE),
+                    legacy_stage_flags(new_state.stage, vk::PipelineStageFlags::BOTTOM_OF_PIPE),
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[vk_image_memory_barrier],
+                );
+            }
+        }
+    }
+
+    /// Transitions the image to `new_layout`, emitting a barrier from the last known state of the
+    /// image in this command buffer.
+    ///
+    /// Images that weren't used yet in this command buffer are assumed to be undefined, so their
+    /// contents are discarded, unless their layout was set with
+    /// [`CommandBuffer::set_image_layout`]. No barrier is emitted if the image is already in
+    /// `new_layout` and was only read from.
+    pub fn transition_image(&mut self, image: &dyn Image, new_layout: vk::ImageLayout) {
+        let old_state = self.image_state(image);
+        let new_state = ImageState::from_layout(new_layout);
+        if old_state.layout == new_layout && !old_state.has_writes() && !new_state.has_writes() {
+            return;
+        }
+
+        self.pipeline_barrier(
+            old_state.stage,
+            new_state.stage,
+            vec![ImageMemoryBarrier {
+                image,
+                old_layout: old_state.layout,
+                new_layout,
+                src_access_mask: old_state.access,
+                dst_access_mask: new_state.access,
+            }],
+        );
+    }
+
+    /// Begins dynamic rendering to the attachments, transitioning them to their attachment
+    /// layouts first.
+    ///
+    /// # Panics
+    /// If [`crate::VulkanFeature::DynamicRendering`] wasn't enabled.
+    pub fn begin_rendering(&mut self, info: RenderingInfo<'_>) {
+        for attachment in &info.color_attachments {
+            self.transition_image(attachment.image, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+            if let Some(resolve_image) = attachment.resolve_image {
+                self.transition_image(resolve_image, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+            }
+        }
+        if let Some(attachment) = &info.depth_attachment {
+            self.transition_image(
+                attachment.image,
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            );
+            if let Some(resolve_image) = attachment.resolve_image {
+                self.transition_image(
+                    resolve_image,
+                    vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                );
+            }
+        }
+
+        let image_view = |image: &dyn Image| {
+            image
+                .instance()
+                .image_view()
+                .expect("Rendering attachments must have an image view.")
+        };
+        let vk_attachment = |attachment: &RenderingAttachment<'_>, layout, resolve_mode| {
+            let vk_attachment = vk::RenderingAttachmentInfo::default()
+                .image_view(image_view(attachment.image))
+                .image_layout(layout)
+                .load_op(attachment.load_op)
+                .store_op(attachment.store_op)
+                .clear_value(attachment.clear_value);
+            match attachment.resolve_image {
+                Some(resolve_image) => vk_attachment
+                    .resolve_mode(resolve_mode)
+                    .resolve_image_view(image_view(resolve_image))
+                    .resolve_image_layout(layout),
+                None => vk_attachment,
+            }
+        };
+        let vk_color_attachments = info
+            .color_attachments
+            .iter()
+            .map(|attachment| {
+                vk_attachment(
+                    attachment,
+                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    vk::ResolveModeFlags::AVERAGE,
+                )
+            })
             .collect::<Vec<_>>();
+        let vk_depth_attachment = info.depth_attachment.as_ref().map(|attachment| {
+            vk_attachment(
+                attachment,
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                vk::ResolveModeFlags::SAMPLE_ZERO,
+            )
+        });
+
+        let flags = if info.secondary_command_buffers {
+            vk::RenderingFlags::CONTENTS_SECONDARY_COMMAND_BUFFERS
+        } else {
+            vk::RenderingFlags::empty()
+        };
+        let mut vk_rendering_info = vk::RenderingInfo::default()
+            .flags(flags)
+            .render_area(info.render_area)
+            .layer_count(1)
+            .color_attachments(&vk_color_attachments);
+        if let Some(vk_depth_attachment) = &vk_depth_attachment {
+            vk_rendering_info = vk_rendering_info.depth_attachment(vk_depth_attachment);
+        }
+
+        self.recorded_dependencies.extend(
+            info.color_attachments
+                .iter()
+                .chain(info.depth_attachment.iter())
+                .flat_map(|attachment| {
+                    std::iter::once(attachment.image).chain(attachment.resolve_image)
+                })
+                .map(|image| Arc::downgrade(&image.create_generic_dep())),
+        );
+
+        unsafe {
+            self.dynamic_rendering()
+                .cmd_begin_rendering(self.command_buffer, &vk_rendering_info);
+        }
+    }
+
+    pub fn end_rendering(&mut self) {
+        unsafe {
+            self.dynamic_rendering()
+                .cmd_end_rendering(self.command_buffer);
+        }
+    }
+
+    fn dynamic_rendering(&self) -> &ash::extensions::khr::DynamicRendering {
+        self.vulkan_dep
+            .dynamic_rendering()
+            .expect("Dynamic rendering must be enabled to render without a render pass.")
+    }
+
+    /// Binds the pipeline, its viewport and scissor must be set before drawing.
+    pub fn bind_graphics_pipeline(&mut self, pipeline: &GraphicsPipeline) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&pipeline.create_generic_dep()));
+        self.bound_pipeline_layouts.insert(
+            vk::PipelineBindPoint::GRAPHICS,
+            BoundPipelineLayout::new(pipeline.instance().pipeline_layout()),
+        );
 
         unsafe {
-            self.vulkan_dep.device().cmd_pipeline_barrier(
+            self.vulkan_dep.device().cmd_bind_pipeline(
                 self.command_buffer,
-                src_stage,
-                dst_stage,
-                vk::DependencyFlags::empty(),
-                &[],
-                &[],
-                &vk_image_memory_barriers,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.instance().pipeline(),
             );
         }
     }
 
+    /// Binds the buffers with their offsets to the vertex bindings starting at `first_binding`.
+    pub fn bind_vertex_buffers(&mut self, first_binding: u32, buffers: &[(&dyn Buffer, u64)]) {
+        self.recorded_dependencies.extend(
+            buffers
+                .iter()
+                .map(|(buffer, _)| Arc::downgrade(&buffer.create_generic_dep())),
+        );
+
+        let vk_buffers = buffers
+            .iter()
+            .map(|(buffer, _)| buffer.instance().buffer())
+            .collect::<Vec<_>>();
+        let vk_offsets = buffers
+            .iter()
+            .map(|(_, offset)| *offset)
+            .collect::<Vec<_>>();
+        unsafe {
+            self.vulkan_dep.device().cmd_bind_vertex_buffers(
+                self.command_buffer,
+                first_binding,
+                &vk_buffers,
+                &vk_offsets,
+            );
+        }
+    }
+
+    pub fn bind_index_buffer(
+        &mut self,
+        buffer: &impl Buffer,
+        offset: u64,
+        index_type: vk::IndexType,
+    ) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&buffer.create_generic_dep()));
+
+        unsafe {
+            self.vulkan_dep.device().cmd_bind_index_buffer(
+                self.command_buffer,
+                buffer.instance().buffer(),
+                offset,
+                index_type,
+            );
+        }
+    }
+
+    pub fn set_viewport(&mut self, viewport: vk::Viewport) {
+        unsafe {
+            self.vulkan_dep
+                .device()
+                .cmd_set_viewport(self.command_buffer, 0, &[viewport]);
+        }
+    }
+
+    pub fn set_scissor(&mut self, scissor: vk::Rect2D) {
+        unsafe {
+            self.vulkan_dep
+                .device()
+                .cmd_set_scissor(self.command_buffer, 0, &[scissor]);
+        }
+    }
+
+    /// Sets the viewport and scissor to cover `area`, with a depth range of 0 to 1.
+    pub fn set_viewport_and_scissor(&mut self, area: vk::Rect2D) {
+        self.set_viewport(vk::Viewport {
+            x: area.offset.x as f32,
+            y: area.offset.y as f32,
+            width: area.extent.width as f32,
+            height: area.extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        });
+        self.set_scissor(area);
+    }
+
+    pub fn draw(
+        &mut self,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.vulkan_dep.device().cmd_draw(
+                self.command_buffer,
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            );
+        }
+    }
+
+    pub fn draw_indexed(
+        &mut self,
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.vulkan_dep.device().cmd_draw_indexed(
+                self.command_buffer,
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            );
+        }
+    }
+
+    /// Draws `draw_count` times with the `vk::DrawIndirectCommand`s read from `buffer` at
+    /// `offset`, `stride` bytes apart.
+    ///
+    /// # Panics
+    /// If `draw_count` is above one without [`VulkanFeature::MultiDrawIndirect`].
+    pub fn draw_indirect(
+        &mut self,
+        buffer: &dyn Buffer,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        self.assert_multi_draw_indirect(draw_count);
+        self.recorded_dependencies
+            .push(Arc::downgrade(&buffer.create_generic_dep()));
+
+        unsafe {
+            self.vulkan_dep.device().cmd_draw_indirect(
+                self.command_buffer,
+                buffer.instance().buffer(),
+                offset,
+                draw_count,
+                stride,
+            );
+        }
+    }
+
+    /// Draws `draw_count` times with the `vk::DrawIndexedIndirectCommand`s read from `buffer` at
+    /// `offset`, `stride` bytes apart.
+    ///
+    /// # Panics
+    /// If `draw_count` is above one without [`VulkanFeature::MultiDrawIndirect`].
+    pub fn draw_indexed_indirect(
+        &mut self,
+        buffer: &dyn Buffer,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        self.assert_multi_draw_indirect(draw_count);
+        self.recorded_dependencies
+            .push(Arc::downgrade(&buffer.create_generic_dep()));
+
+        unsafe {
+            self.vulkan_dep.device().cmd_draw_indexed_indirect(
+                self.command_buffer,
+                buffer.instance().buffer(),
+                offset,
+                draw_count,
+                stride,
+            );
+        }
+    }
+
+    /// Like [`CommandBuffer::draw_indexed_indirect`], but the draw count is read as a `u32` from
+    /// `count_buffer` at `count_offset`, clamped to `max_draw_count`.
+    ///
+    /// # Panics
+    /// If [`VulkanFeature::DrawIndirectCount`] wasn't enabled.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_indexed_indirect_count(
+        &mut self,
+        buffer: &dyn Buffer,
+        offset: u64,
+        count_buffer: &dyn Buffer,
+        count_offset: u64,
+        max_draw_count: u32,
+        stride: u32,
+    ) {
+        assert!(
+            self.vulkan_dep
+                .is_feature_enabled(VulkanFeature::DrawIndirectCount),
+            "Draw indirect count must be enabled to read the draw count from a buffer."
+        );
+        self.recorded_dependencies
+            .push(Arc::downgrade(&buffer.create_generic_dep()));
+        self.recorded_dependencies
+            .push(Arc::downgrade(&count_buffer.create_generic_dep()));
+
+        unsafe {
+            self.vulkan_dep.device().cmd_draw_indexed_indirect_count(
+                self.command_buffer,
+                buffer.instance().buffer(),
+                offset,
+                count_buffer.instance().buffer(),
+                count_offset,
+                max_draw_count,
+                stride,
+            );
+        }
+    }
+
+    /// Launches `group_count_x * group_count_y * group_count_z` task shader workgroups, or mesh
+    /// shader workgroups if the bound pipeline has no task shader.
+    ///
+    /// # Panics
+    /// If [`VulkanFeature::MeshShader`] wasn't enabled.
+    pub fn draw_mesh_tasks(&mut self, group_count_x: u32, group_count_y: u32, group_count_z: u32) {
+        unsafe {
+            self.mesh_shader().cmd_draw_mesh_tasks(
+                self.command_buffer,
+                group_count_x,
+                group_count_y,
+                group_count_z,
+            );
+        }
+    }
+
+    /// Draws `draw_count` times with the `vk::DrawMeshTasksIndirectCommandEXT`s read from
+    /// `buffer` at `offset`, `stride` bytes apart.
+    ///
+    /// # Panics
+    /// If [`VulkanFeature::MeshShader`] wasn't enabled, or if `draw_count` is above one without
+    /// [`VulkanFeature::MultiDrawIndirect`].
+    pub fn draw_mesh_tasks_indirect(
+        &mut self,
+        buffer: &dyn Buffer,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        self.assert_multi_draw_indirect(draw_count);
+        self.recorded_dependencies
+            .push(Arc::downgrade(&buffer.create_generic_dep()));
+
+        unsafe {
+            self.mesh_shader().cmd_draw_mesh_tasks_indirect(
+                self.command_buffer,
+                buffer.instance().buffer(),
+                offset,
+                draw_count,
+                stride,
+            );
+        }
+    }
+
+    fn mesh_shader(&self) -> &ash::extensions::ext::MeshShader {
+        self.vulkan_dep
+            .mesh_shader()
+            .expect("Mesh shader must be enabled to draw mesh tasks.")
+    }
+
+    fn assert_multi_draw_indirect(&self, draw_count: u32) {
+        assert!(
+            draw_count <= 1
+                || self
+                    .vulkan_dep
+                    .is_feature_enabled(VulkanFeature::MultiDrawIndirect),
+            "Multi draw indirect must be enabled to draw more than once per indirect draw."
+        );
+    }
+
+    /// Sets the known layout of an image that was transitioned outside of this command buffer,
+    /// e.g. by the previous frame.
+    pub fn set_image_layout(&mut self, image: &dyn Image, layout: vk::ImageLayout) {
+        self.image_states
+            .insert(image.instance().image(), ImageState::from_layout(layout));
+    }
+
+    /// The last known state of the image in this command buffer.
+    pub fn image_state(&self, image: &dyn Image) -> ImageState {
+        self.image_states
+            .get(&image.instance().image())
+            .copied()
+            .unwrap_or(ImageState::UNDEFINED)
+    }
+
+    /// Clears the image, transitioning it to `TRANSFER_DST_OPTIMAL` first.
     pub fn clear_color_image(
         &mut self,
         image: &dyn Image,
         clear_color: vk::ClearColorValue,
         subresource_range: vk::ImageSubresourceRange,
     ) {
+        self.transition_image(image, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
         self.recorded_dependencies
             .push(Arc::downgrade(&image.create_generic_dep()));
 
@@ -90,6 +1056,380 @@ impl CommandBuffer {
                 &[subresource_range],
             );
         }
+
+        self.image_states.insert(
+            image.instance().image(),
+            ImageState::from_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+        );
+    }
+
+    pub fn bind_compute_pipeline(&mut self, pipeline: &ComputePipeline) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&pipeline.create_generic_dep()));
+        self.bound_pipeline_layouts.insert(
+            vk::PipelineBindPoint::COMPUTE,
+            BoundPipelineLayout::new(pipeline.instance().pipeline_layout()),
+        );
+
+        unsafe {
+            self.vulkan_dep.device().cmd_bind_pipeline(
+                self.command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline.instance().pipeline(),
+            );
+        }
+    }
+
+    /// Binds the descriptor sets starting at `first_set`, using the layout of the pipeline last
+    /// bound to `bind_point`.
+    ///
+    /// # Panics
+    /// If no pipeline was bound to `bind_point` yet.
+    pub fn bind_descriptor_sets(
+        &mut self,
+        bind_point: vk::PipelineBindPoint,
+        first_set: u32,
+        descriptor_sets: &[&DescriptorSet],
+    ) {
+        let pipeline_layout = self.bound_pipeline_layout(bind_point).layout;
+        for descriptor_set in descriptor_sets {
+            self.recorded_dependencies
+                .push(descriptor_set.layout_dep().into_generic_weak());
+            self.recorded_dependencies
+                .extend(descriptor_set.written_dependencies().cloned());
+        }
+
+        let vk_descriptor_sets = descriptor_sets
+            .iter()
+            .map(|descriptor_set| descriptor_set.descriptor_set())
+            .collect::<Vec<_>>();
+        unsafe {
+            self.vulkan_dep.device().cmd_bind_descriptor_sets(
+                self.command_buffer,
+                bind_point,
+                pipeline_layout,
+                first_set,
+                &vk_descriptor_sets,
+                &[],
+            );
+        }
+    }
+
+    /// Updates the push constants at `offset`, using the layout of the pipeline last bound to
+    /// `bind_point`.
+    ///
+    /// # Panics
+    /// If no pipeline was bound to `bind_point` yet, or the constants don't match the push
+    /// constant ranges of its layout, see [`CommandBuffer::push_constants_with_layout`].
+    pub fn push_constants<T: bytemuck::Pod>(
+        &mut self,
+        bind_point: vk::PipelineBindPoint,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        constants: &T,
+    ) {
+        let bound_pipeline_layout = self.bound_pipeline_layout(bind_point);
+        let bytes = bytemuck::bytes_of(constants);
+        validate_push_constants(
+            &bound_pipeline_layout.push_constant_ranges,
+            stages,
+            offset,
+            bytes,
+        );
+
+        let pipeline_layout = bound_pipeline_layout.layout;
+        self.record_push_constants(pipeline_layout, stages, offset, bytes);
+    }
+
+    /// Updates the push constants at `offset` using `pipeline_layout`, which only has to be
+    /// compatible with the layout of the pipelines using them, e.g. to push constants before
+    /// binding a pipeline.
+    ///
+    /// # Panics
+    /// If `offset` or the size of `T` isn't a multiple of 4, or for any of `stages` the bytes
+    /// aren't covered by the push constant ranges of the layout declaring that stage. Pad `T`
+    /// to a multiple of 4 bytes with explicit fields.
+    pub fn push_constants_with_layout<T: bytemuck::Pod>(
+        &mut self,
+        pipeline_layout: &PipelineLayoutInstance,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        constants: &T,
+    ) {
+        let bytes = bytemuck::bytes_of(constants);
+        validate_push_constants(
+            pipeline_layout.push_constant_ranges(),
+            stages,
+            offset,
+            bytes,
+        );
+
+        self.record_push_constants(pipeline_layout.layout(), stages, offset, bytes);
+    }
+
+    fn record_push_constants(
+        &mut self,
+        pipeline_layout: vk::PipelineLayout,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        bytes: &[u8],
+    ) {
+        unsafe {
+            self.vulkan_dep.device().cmd_push_constants(
+                self.command_buffer,
+                pipeline_layout,
+                stages,
+                offset,
+                bytes,
+            );
+        }
+    }
+
+    fn bound_pipeline_layout(&self, bind_point: vk::PipelineBindPoint) -> &BoundPipelineLayout {
+        self.bound_pipeline_layouts
+            .get(&bind_point)
+            .unwrap_or_else(|| panic!("No pipeline is bound to {:?}.", bind_point))
+    }
+
+    pub fn dispatch(&mut self, group_count_x: u32, group_count_y: u32, group_count_z: u32) {
+        unsafe {
+            self.vulkan_dep.device().cmd_dispatch(
+                self.command_buffer,
+                group_count_x,
+                group_count_y,
+                group_count_z,
+            );
+        }
+    }
+
+    /// Dispatches with the group counts read from a `vk::DispatchIndirectCommand` in `buffer` at
+    /// `offset`.
+    pub fn dispatch_indirect(&mut self, buffer: &impl Buffer, offset: u64) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&buffer.create_generic_dep()));
+
+        unsafe {
+            self.vulkan_dep.device().cmd_dispatch_indirect(
+                self.command_buffer,
+                buffer.instance().buffer(),
+                offset,
+            );
+        }
+    }
+
+    /// Records an acceleration structure build, see
+    /// [`super::AccelerationStructureBuilder`] which manages the scratch memory.
+    pub(crate) fn build_acceleration_structure(
+        &mut self,
+        build_info: &vk::AccelerationStructureBuildGeometryInfoKHR<'_>,
+        build_ranges: &[vk::AccelerationStructureBuildRangeInfoKHR],
+        dependencies: &[GenericResourceDep],
+    ) {
+        self.recorded_dependencies
+            .extend(dependencies.iter().map(Arc::downgrade));
+
+        unsafe {
+            ray_tracing(&self.vulkan_dep)
+                .acceleration_structure()
+                .cmd_build_acceleration_structures(
+                    self.command_buffer,
+                    std::slice::from_ref(build_info),
+                    &[build_ranges],
+                );
+        }
+    }
+
+    pub fn bind_ray_tracing_pipeline(&mut self, pipeline: &RayTracingPipeline) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&pipeline.create_generic_dep()));
+        self.bound_pipeline_layouts.insert(
+            vk::PipelineBindPoint::RAY_TRACING_KHR,
+            BoundPipelineLayout::new(pipeline.instance().pipeline_layout()),
+        );
+
+        unsafe {
+            self.vulkan_dep.device().cmd_bind_pipeline(
+                self.command_buffer,
+                vk::PipelineBindPoint::RAY_TRACING_KHR,
+                pipeline.instance().pipeline(),
+            );
+        }
+    }
+
+    /// Traces `width * height * depth` rays with the shaders of `shader_binding_table`, whose
+    /// pipeline must be the bound ray tracing pipeline.
+    pub fn trace_rays(
+        &mut self,
+        shader_binding_table: &ShaderBindingTable,
+        width: u32,
+        height: u32,
+        depth: u32,
+    ) {
+        self.recorded_dependencies.push(Arc::downgrade(
+            &shader_binding_table.buffer().create_generic_dep(),
+        ));
+
+        unsafe {
+            ray_tracing(&self.vulkan_dep)
+                .ray_tracing_pipeline()
+                .cmd_trace_rays(
+                    self.command_buffer,
+                    shader_binding_table.ray_generation_region(),
+                    shader_binding_table.miss_region(),
+                    shader_binding_table.hit_region(),
+                    &vk::StridedDeviceAddressRegionKHR::default(),
+                    width,
+                    height,
+                    depth,
+                );
+        }
+    }
+
+    /// Executes the ended secondary command buffers, taking over their recorded dependencies and
+    /// the last known states of their images.
+    pub fn execute_commands(&mut self, command_buffers: &mut [&mut CommandBuffer]) {
+        for command_buffer in command_buffers.iter_mut() {
+            self.recorded_dependencies
+                .extend(command_buffer.take_recorded_dependencies());
+            self.image_states
+                .extend(command_buffer.image_states.drain());
+        }
+
+        let vk_command_buffers = command_buffers
+            .iter()
+            .map(|command_buffer| command_buffer.command_buffer)
+            .collect::<Vec<_>>();
+        unsafe {
+            self.vulkan_dep
+                .device()
+                .cmd_execute_commands(self.command_buffer, &vk_command_buffers);
+        }
+    }
+
+    /// Resets the queries, which must happen before they are written again.
+    pub fn reset_query_pool(&mut self, query_pool: &QueryPool, first_query: u32, query_count: u32) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&query_pool.create_generic_dep()));
+
+        unsafe {
+            self.vulkan_dep.device().cmd_reset_query_pool(
+                self.command_buffer,
+                query_pool.instance().query_pool(),
+                first_query,
+                query_count,
+            );
+        }
+    }
+
+    /// Begins the occlusion or pipeline statistics query, counting the commands recorded until
+    /// the matching [`CommandBuffer::end_query`]. The query must have been reset.
+    ///
+    /// `precise` occlusion queries count the exact number of passing samples and require
+    /// [`VulkanFeature::OcclusionQueryPrecise`].
+    pub fn begin_query(&mut self, query_pool: &QueryPool, query: u32, precise: bool) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&query_pool.create_generic_dep()));
+
+        let flags = if precise {
+            vk::QueryControlFlags::PRECISE
+        } else {
+            vk::QueryControlFlags::empty()
+        };
+        unsafe {
+            self.vulkan_dep.device().cmd_begin_query(
+                self.command_buffer,
+                query_pool.instance().query_pool(),
+                query,
+                flags,
+            );
+        }
+    }
+
+    pub fn end_query(&mut self, query_pool: &QueryPool, query: u32) {
+        unsafe {
+            self.vulkan_dep.device().cmd_end_query(
+                self.command_buffer,
+                query_pool.instance().query_pool(),
+                query,
+            );
+        }
+    }
+
+    /// Writes a timestamp once every previous command finished `stage`.
+    pub fn write_timestamp(
+        &mut self,
+        query_pool: &QueryPool,
+        stage: vk::PipelineStageFlags2,
+        query: u32,
+    ) {
+        self.recorded_dependencies
+            .push(Arc::downgrade(&query_pool.create_generic_dep()));
+
+        unsafe {
+            if let Some(synchronization2) = self.vulkan_dep.synchronization2() {
+                synchronization2.cmd_write_timestamp2(
+                    self.command_buffer,
+                    stage,
+                    query_pool.instance().query_pool(),
+                    query,
+                );
+            } else {
+                self.vulkan_dep.device().cmd_write_timestamp(
+                    self.command_buffer,
+                    legacy_stage_flags(stage, vk::PipelineStageFlags::TOP_OF_PIPE),
+                    query_pool.instance().query_pool(),
+                    query,
+                );
+            }
+        }
+    }
+
+    /// Opens a label grouping the following commands in debugging tools, closed by
+    /// [`CommandBuffer::end_label`]. Does nothing if validation isn't enabled.
+    pub fn begin_label(&mut self, name: &str, color: [f32; 4]) {
+        let Some(debug_utils) = self.vulkan_dep.debug_utils() else {
+            return;
+        };
+
+        let name = std::ffi::CString::new(name).expect("Labels can't contain nul bytes.");
+        let label = vk::DebugUtilsLabelEXT::default()
+            .label_name(&name)
+            .color(color);
+        unsafe {
+            debug_utils
+                .loader()
+                .cmd_begin_debug_utils_label(self.command_buffer, &label);
+        }
+    }
+
+    pub fn end_label(&mut self) {
+        let Some(debug_utils) = self.vulkan_dep.debug_utils() else {
+            return;
+        };
+
+        unsafe {
+            debug_utils
+                .loader()
+                .cmd_end_debug_utils_label(self.command_buffer);
+        }
+    }
+
+    /// Marks a single point between commands in debugging tools. Does nothing if validation isn't
+    /// enabled.
+    pub fn insert_label(&mut self, name: &str, color: [f32; 4]) {
+        let Some(debug_utils) = self.vulkan_dep.debug_utils() else {
+            return;
+        };
+
+        let name = std::ffi::CString::new(name).expect("Labels can't contain nul bytes.");
+        let label = vk::DebugUtilsLabelEXT::default()
+            .label_name(&name)
+            .color(color);
+        unsafe {
+            debug_utils
+                .loader()
+                .cmd_insert_debug_utils_label(self.command_buffer, &label);
+        }
     }
 
     pub fn take_recorded_dependencies(&mut self) -> Vec<WeakGenericResourceDep> {
@@ -122,13 +1462,19 @@ impl Drop for CommandPoolInstance {
 
 pub struct CommandPool {
     instance: Arc<CommandPoolInstance>,
-    command_buffers: SlotMap<CommandBufferHandle, CommandBuffer>,
+    command_buffers: HandleMap<CommandBufferHandle, CommandBuffer>,
 }
 
 impl CommandPool {
     pub fn new(vulkan: &Vulkan) -> Self {
-        let command_pool_create_info = vk::CommandPoolCreateInfo::default()
-            .queue_family_index(vulkan.default_queue().queue_family_index());
+        Self::new_for_queue(vulkan, vulkan.default_queue())
+    }
+
+    /// Creates a command pool whose command buffers are submitted to `queue`, e.g. a dedicated
+    /// transfer queue.
+    pub fn new_for_queue(vulkan: &Vulkan, queue: &VulkanQueue) -> Self {
+        let command_pool_create_info =
+            vk::CommandPoolCreateInfo::default().queue_family_index(queue.queue_family_index());
 
         // Safety: The command pool is dropped when the internal command pool is dropped
         let command_pool = unsafe {
@@ -143,7 +1489,7 @@ impl CommandPool {
                 vulkan_dep: vulkan.create_dep(),
                 command_pool,
             }),
-            command_buffers: SlotMap::with_key(),
+            command_buffers: HandleMap::new(),
         }
     }
 
@@ -188,10 +1534,24 @@ impl CommandPool {
     }
 
     pub fn allocate<const N: usize>(&mut self) -> [CommandBufferHandle; N] {
+        Self::into_array(self.allocate_level(vk::CommandBufferLevel::PRIMARY, N as u32))
+    }
+
+    /// Allocates secondary command buffers, which are recorded with
+    /// [`CommandBuffer::begin_secondary`] and executed by a primary command buffer.
+    pub fn allocate_secondary<const N: usize>(&mut self) -> [CommandBufferHandle; N] {
+        Self::into_array(self.allocate_level(vk::CommandBufferLevel::SECONDARY, N as u32))
+    }
+
+    fn allocate_level(
+        &mut self,
+        level: vk::CommandBufferLevel,
+        count: u32,
+    ) -> Vec<CommandBufferHandle> {
         let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::default()
             .command_pool(self.instance.command_pool)
-            .level(vk::CommandBufferLevel::PRIMARY)
-            .command_buffer_count(N as u32);
+            .level(level)
+            .command_buffer_count(count);
 
         let command_buffers = unsafe {
             self.instance
@@ -206,6 +1566,8 @@ impl CommandPool {
             command_pool: Arc::downgrade(&self.instance),
             command_buffer,
             recorded_dependencies: Vec::new(),
+            image_states: HashMap::new(),
+            bound_pipeline_layouts: HashMap::new(),
         })
         .collect::<Vec<_>>();
 
@@ -213,7 +1575,10 @@ impl CommandPool {
         for command_buffer in command_buffers {
             handles.push(self.command_buffers.insert(command_buffer));
         }
+        handles
+    }
 
+    fn into_array<const N: usize>(handles: Vec<CommandBufferHandle>) -> [CommandBufferHandle; N] {
         handles.try_into().unwrap_or_else(|_| {
             panic!(
                 "Failed to convert command buffer handles into array of length {}",
@@ -222,3 +1587,64 @@ impl CommandPool {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_states_match_their_layout() {
+        let attachment = ImageState::from_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        assert_eq!(
+            attachment.stage,
+            vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+        );
+        assert!(attachment
+            .access
+            .contains(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE));
+
+        let sampled = ImageState::from_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        assert_eq!(sampled.access, vk::AccessFlags2::SHADER_SAMPLED_READ);
+        assert!(sampled
+            .stage
+            .contains(vk::PipelineStageFlags2::FRAGMENT_SHADER));
+
+        let transfer_dst = ImageState::from_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+        assert_eq!(transfer_dst.access, vk::AccessFlags2::TRANSFER_WRITE);
+        assert_eq!(transfer_dst.stage, vk::PipelineStageFlags2::ALL_TRANSFER);
+
+        assert_eq!(
+            ImageState::from_layout(vk::ImageLayout::UNDEFINED),
+            ImageState::UNDEFINED
+        );
+        assert_eq!(
+            ImageState::from_layout(vk::ImageLayout::GENERAL).stage,
+            vk::PipelineStageFlags2::ALL_COMMANDS
+        );
+    }
+
+    #[test]
+    fn only_writing_states_have_writes() {
+        for layout in [
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::GENERAL,
+        ] {
+            assert!(ImageState::from_layout(layout).has_writes(), "{:?}", layout);
+        }
+
+        for layout in [
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::ImageLayout::PRESENT_SRC_KHR,
+        ] {
+            assert!(
+                !ImageState::from_layout(layout).has_writes(),
+                "{:?}",
+                layout
+            );
+        }
+    }
+}
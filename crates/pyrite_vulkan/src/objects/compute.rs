@@ -2,9 +2,12 @@ use std::sync::Arc;
 
 use ash::vk;
 
-use crate::{util::VulkanResource, Vulkan, VulkanDep};
+use crate::{
+    util::{GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep, VulkanResult,
+};
 
-use super::{PipelineLayoutCreateInfo, PipelineLayoutInstance, Shader};
+use super::{DescriptorSetLayout, PipelineLayoutCreateInfo, PipelineLayoutInstance, Shader};
 
 pub type ComputePipelineDep = Arc<ComputePipelineInstance>;
 
@@ -12,6 +15,7 @@ pub struct ComputePipelineInstance {
     vulkan_dep: VulkanDep,
     pipeline_layout: PipelineLayoutInstance,
     pipeline: vk::Pipeline,
+    local_size: Option<[u32; 3]>,
 }
 
 impl ComputePipelineInstance {
@@ -22,6 +26,11 @@ impl ComputePipelineInstance {
     pub fn pipeline_layout(&self) -> &PipelineLayoutInstance {
         &self.pipeline_layout
     }
+
+    /// The workgroup size reflected from the shader, see [`super::ShaderReflection::local_size`].
+    pub fn local_size(&self) -> Option<[u32; 3]> {
+        self.local_size
+    }
 }
 
 impl VulkanResource for ComputePipelineInstance {}
@@ -39,7 +48,10 @@ impl Drop for ComputePipelineInstance {
 pub struct ComputePipelineCreateInfo<'a> {
     pub shader: &'a Shader,
     pub shader_entry_point: String,
+    /// Descriptor set layouts and push constant ranges left empty are derived from the shader.
     pub pipeline_layout_info: PipelineLayoutCreateInfo<'a>,
+    /// The name shown in debugging tools.
+    pub name: Option<String>,
 }
 
 pub struct ComputePipeline {
@@ -47,8 +59,25 @@ pub struct ComputePipeline {
 }
 
 impl ComputePipeline {
+    /// # Panics
+    /// If the pipeline can't be created, see [`ComputePipeline::try_new`].
     pub fn new(vulkan: &Vulkan, create_info: ComputePipelineCreateInfo<'_>) -> Self {
-        let pipeline_layout = PipelineLayoutInstance::new(vulkan, create_info.pipeline_layout_info);
+        Self::try_new(vulkan, create_info)
+            .unwrap_or_else(|err| panic!("Failed to create compute pipeline: {}", err))
+    }
+
+    pub fn try_new(
+        vulkan: &Vulkan,
+        create_info: ComputePipelineCreateInfo<'_>,
+    ) -> VulkanResult<Self> {
+        let pipeline_layout = PipelineLayoutInstance::try_new_with_reflection(
+            vulkan,
+            create_info.pipeline_layout_info,
+            &[(
+                vk::ShaderStageFlags::COMPUTE,
+                create_info.shader.reflection(),
+            )],
+        )?;
 
         let vk_shader_name = std::ffi::CString::new(create_info.shader_entry_point).unwrap();
         let vk_create_info = vk::ComputePipelineCreateInfo::default()
@@ -64,23 +93,54 @@ impl ComputePipeline {
             vulkan
                 .device()
                 .create_compute_pipelines(vk::PipelineCache::null(), &[vk_create_info], None)
-                .unwrap()[0]
+                .map_err(|(_, result)| result)?[0]
         };
+        if let Some(name) = &create_info.name {
+            vulkan.set_debug_name(pipeline, name);
+        }
 
-        Self {
+        Ok(Self {
             instance: Arc::new(ComputePipelineInstance {
                 vulkan_dep: vulkan.create_dep(),
                 pipeline_layout,
                 pipeline,
+                local_size: create_info.shader.reflection().local_size,
             }),
-        }
+        })
     }
 
     pub fn instance(&self) -> &ComputePipelineInstance {
         &self.instance
     }
 
+    /// The descriptor set layouts of the pipeline, including the ones derived from the shader,
+    /// to allocate the pipeline's descriptor sets from a [`super::DescriptorSetPool`].
+    pub fn descriptor_set_layouts(&self) -> &[DescriptorSetLayout] {
+        self.instance.pipeline_layout.descriptor_set_layouts()
+    }
+
+    /// The number of workgroups to dispatch so at least `invocations` invocations run in each
+    /// dimension, the shader should skip the ones past the end.
+    ///
+    /// # Panics
+    /// If the workgroup size of the shader couldn't be reflected.
+    pub fn group_count(&self, invocations: [u32; 3]) -> [u32; 3] {
+        let local_size = self
+            .instance
+            .local_size
+            .expect("The workgroup size of the compute shader wasn't reflected.");
+        [
+            invocations[0].div_ceil(local_size[0]),
+            invocations[1].div_ceil(local_size[1]),
+            invocations[2].div_ceil(local_size[2]),
+        ]
+    }
+
     pub fn create_dep(&self) -> ComputePipelineDep {
         self.instance.clone()
     }
+
+    pub fn create_generic_dep(&self) -> GenericResourceDep {
+        self.instance.clone()
+    }
 }
@@ -1,24 +1,32 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use ash::vk;
-use slotmap::{new_key_type, SlotMap};
+use pyrite_util::HandleMap;
 
 use crate::{
     util::{GenericResourceDep, VulkanResource, WeakGenericResourceDep},
-    Vulkan, VulkanDep,
+    Vulkan, VulkanDep, VulkanFeature,
 };
 
+use super::{AccelerationStructure, Buffer, Image, Sampler};
+
 pub type DescriptorSetLayoutDep = Arc<DescriptorSetLayoutInstance>;
 
 pub struct DescriptorSetLayoutInstance {
     vulkan_dep: VulkanDep,
     descriptor_set_layout: vk::DescriptorSetLayout,
+    binding_types: HashMap<u32, vk::DescriptorType>,
 }
 
 impl DescriptorSetLayoutInstance {
     pub fn layout(&self) -> vk::DescriptorSetLayout {
         self.descriptor_set_layout
     }
+
+    /// The descriptor type of `binding`, `None` if the layout doesn't have the binding.
+    pub fn binding_type(&self, binding: u32) -> Option<vk::DescriptorType> {
+        self.binding_types.get(&binding).copied()
+    }
 }
 
 impl VulkanResource for DescriptorSetLayoutInstance {}
@@ -33,6 +41,7 @@ impl Drop for DescriptorSetLayoutInstance {
     }
 }
 
+#[derive(Clone)]
 pub struct DescriptorSetLayout {
     instance: Arc<DescriptorSetLayoutInstance>,
 }
@@ -53,12 +62,14 @@ impl DescriptorSetLayout {
 
 pub struct DescriptorSetLayoutBuilder<'a> {
     bindings: Vec<vk::DescriptorSetLayoutBinding<'a>>,
+    binding_flags: Vec<vk::DescriptorBindingFlags>,
 }
 
 impl DescriptorSetLayoutBuilder<'_> {
     pub fn new() -> Self {
         Self {
             bindings: Vec::new(),
+            binding_flags: Vec::new(),
         }
     }
 
@@ -68,6 +79,25 @@ impl DescriptorSetLayoutBuilder<'_> {
         descriptor_type: vk::DescriptorType,
         descriptor_count: u32,
         stage_flags: vk::ShaderStageFlags,
+    ) -> &mut Self {
+        self.add_binding_with_flags(
+            binding,
+            descriptor_type,
+            descriptor_count,
+            stage_flags,
+            vk::DescriptorBindingFlags::empty(),
+        )
+    }
+
+    /// Adds a binding with descriptor indexing flags, which require
+    /// [`crate::VulkanFeature::DescriptorIndexing`] for any flags other than empty.
+    pub fn add_binding_with_flags(
+        &mut self,
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        descriptor_count: u32,
+        stage_flags: vk::ShaderStageFlags,
+        binding_flags: vk::DescriptorBindingFlags,
     ) -> &mut Self {
         self.bindings.push(
             vk::DescriptorSetLayoutBinding::default()
@@ -76,13 +106,31 @@ impl DescriptorSetLayoutBuilder<'_> {
                 .descriptor_count(descriptor_count)
                 .stage_flags(stage_flags),
         );
+        self.binding_flags.push(binding_flags);
         self
     }
 
     pub fn build(self, vulkan: &Vulkan) -> DescriptorSetLayout {
-        let descriptor_set_layout_create_info = vk::DescriptorSetLayoutCreateInfo::default()
+        let update_after_bind = self
+            .binding_flags
+            .iter()
+            .any(|flags| flags.contains(vk::DescriptorBindingFlags::UPDATE_AFTER_BIND));
+        let layout_flags = if update_after_bind {
+            vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL
+        } else {
+            vk::DescriptorSetLayoutCreateFlags::empty()
+        };
+
+        let mut binding_flags_create_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::default()
+                .binding_flags(&self.binding_flags);
+        let mut descriptor_set_layout_create_info = vk::DescriptorSetLayoutCreateInfo::default()
             .bindings(&self.bindings)
-            .flags(vk::DescriptorSetLayoutCreateFlags::empty());
+            .flags(layout_flags);
+        if self.binding_flags.iter().any(|flags| !flags.is_empty()) {
+            descriptor_set_layout_create_info =
+                descriptor_set_layout_create_info.push_next(&mut binding_flags_create_info);
+        }
 
         // Safety: The descriptor set layout is dropped when the internal descriptor set layout is dropped
         let descriptor_set_layout = unsafe {
@@ -96,16 +144,23 @@ impl DescriptorSetLayoutBuilder<'_> {
             instance: Arc::new(DescriptorSetLayoutInstance {
                 vulkan_dep: vulkan.create_dep(),
                 descriptor_set_layout,
+                binding_types: self
+                    .bindings
+                    .iter()
+                    .map(|binding| (binding.binding, binding.descriptor_type))
+                    .collect(),
             }),
         }
     }
 }
 
-new_key_type! { pub struct DescriptorSetHandle; }
+pyrite_util::new_handle_type! { pub struct DescriptorSetHandle; }
 
 pub struct DescriptorSet {
     descriptor_set: vk::DescriptorSet,
-    written_dependencies: Vec<WeakGenericResourceDep>,
+    layout_dep: DescriptorSetLayoutDep,
+    /// The resources written to each (binding, array element) of the set.
+    written_dependencies: HashMap<(u32, u32), Vec<WeakGenericResourceDep>>,
 }
 
 impl DescriptorSet {
@@ -113,8 +168,262 @@ impl DescriptorSet {
         self.descriptor_set
     }
 
-    pub fn written_dependencies(&self) -> &[WeakGenericResourceDep] {
-        &self.written_dependencies
+    pub fn layout(&self) -> &DescriptorSetLayoutInstance {
+        &self.layout_dep
+    }
+
+    pub fn layout_dep(&self) -> &DescriptorSetLayoutDep {
+        &self.layout_dep
+    }
+
+    pub fn written_dependencies(&self) -> impl Iterator<Item = &WeakGenericResourceDep> {
+        self.written_dependencies.values().flatten()
+    }
+
+    pub fn writer(&mut self) -> DescriptorSetWriter<'_> {
+        DescriptorSetWriter {
+            descriptor_set: self,
+            writes: Vec::new(),
+        }
+    }
+}
+
+enum DescriptorInfo {
+    Buffer(vk::DescriptorBufferInfo),
+    Image(vk::DescriptorImageInfo),
+    AccelerationStructure(vk::AccelerationStructureKHR),
+}
+
+struct DescriptorWrite {
+    binding: u32,
+    array_element: u32,
+    descriptor_type: vk::DescriptorType,
+    info: DescriptorInfo,
+    dependencies: Vec<GenericResourceDep>,
+}
+
+/// Batches descriptor writes to a [`DescriptorSet`], which are applied with a single
+/// `vkUpdateDescriptorSets` call in [`DescriptorSetWriter::submit`].
+///
+/// The set must not be in use by any executing command buffers when the writes are submitted.
+pub struct DescriptorSetWriter<'a> {
+    descriptor_set: &'a mut DescriptorSet,
+    writes: Vec<DescriptorWrite>,
+}
+
+impl DescriptorSetWriter<'_> {
+    /// # Panics
+    /// If the layout doesn't have `binding` or it isn't a uniform buffer.
+    pub fn uniform_buffer(
+        &mut self,
+        binding: u32,
+        array_element: u32,
+        buffer: &impl Buffer,
+    ) -> &mut Self {
+        self.push_buffer(
+            binding,
+            array_element,
+            vk::DescriptorType::UNIFORM_BUFFER,
+            buffer,
+        )
+    }
+
+    /// # Panics
+    /// If the layout doesn't have `binding` or it isn't a storage buffer.
+    pub fn storage_buffer(
+        &mut self,
+        binding: u32,
+        array_element: u32,
+        buffer: &impl Buffer,
+    ) -> &mut Self {
+        self.push_buffer(
+            binding,
+            array_element,
+            vk::DescriptorType::STORAGE_BUFFER,
+            buffer,
+        )
+    }
+
+    /// Writes an image which will be in `image_layout` when the set is used, usually
+    /// `SHADER_READ_ONLY_OPTIMAL`.
+    ///
+    /// # Panics
+    /// If the layout doesn't have `binding` or it isn't a combined image sampler, or if the image
+    /// has no image view.
+    pub fn combined_image_sampler(
+        &mut self,
+        binding: u32,
+        array_element: u32,
+        image: &impl Image,
+        image_layout: vk::ImageLayout,
+        sampler: &Sampler,
+    ) -> &mut Self {
+        let image_info = vk::DescriptorImageInfo::default()
+            .image_view(Self::image_view(image))
+            .image_layout(image_layout)
+            .sampler(sampler.sampler());
+
+        self.push(DescriptorWrite {
+            binding,
+            array_element,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            info: DescriptorInfo::Image(image_info),
+            dependencies: vec![image.create_generic_dep(), sampler.create_generic_dep()],
+        })
+    }
+
+    /// Writes an image which will be in the `GENERAL` layout when the set is used.
+    ///
+    /// # Panics
+    /// If the layout doesn't have `binding` or it isn't a storage image, or if the image has no
+    /// image view.
+    pub fn storage_image(
+        &mut self,
+        binding: u32,
+        array_element: u32,
+        image: &impl Image,
+    ) -> &mut Self {
+        let image_info = vk::DescriptorImageInfo::default()
+            .image_view(Self::image_view(image))
+            .image_layout(vk::ImageLayout::GENERAL);
+
+        self.push(DescriptorWrite {
+            binding,
+            array_element,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            info: DescriptorInfo::Image(image_info),
+            dependencies: vec![image.create_generic_dep()],
+        })
+    }
+
+    /// Writes a top level acceleration structure, which keeps its bottom level acceleration
+    /// structures alive.
+    ///
+    /// # Panics
+    /// If the layout doesn't have `binding` or it isn't an acceleration structure.
+    pub fn acceleration_structure(
+        &mut self,
+        binding: u32,
+        array_element: u32,
+        acceleration_structure: &AccelerationStructure,
+    ) -> &mut Self {
+        self.push(DescriptorWrite {
+            binding,
+            array_element,
+            descriptor_type: vk::DescriptorType::ACCELERATION_STRUCTURE_KHR,
+            info: DescriptorInfo::AccelerationStructure(
+                acceleration_structure.instance().acceleration_structure(),
+            ),
+            dependencies: vec![acceleration_structure.create_generic_dep()],
+        })
+    }
+
+    /// Applies every write and records the written resources in the set.
+    pub fn submit(self, vulkan: &Vulkan) {
+        if self.writes.is_empty() {
+            return;
+        }
+
+        // Acceleration structures are written through a struct chained to the write.
+        let mut acceleration_structure_writes = self
+            .writes
+            .iter()
+            .filter_map(|write| match &write.info {
+                DescriptorInfo::AccelerationStructure(acceleration_structure) => Some(
+                    vk::WriteDescriptorSetAccelerationStructureKHR::default()
+                        .acceleration_structures(std::slice::from_ref(acceleration_structure)),
+                ),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut acceleration_structure_writes = acceleration_structure_writes.iter_mut();
+
+        let vk_writes = self
+            .writes
+            .iter()
+            .map(|write| {
+                let vk_write = vk::WriteDescriptorSet::default()
+                    .dst_set(self.descriptor_set.descriptor_set)
+                    .dst_binding(write.binding)
+                    .dst_array_element(write.array_element)
+                    .descriptor_type(write.descriptor_type);
+
+                match &write.info {
+                    DescriptorInfo::Buffer(buffer_info) => {
+                        vk_write.buffer_info(std::slice::from_ref(buffer_info))
+                    }
+                    DescriptorInfo::Image(image_info) => {
+                        vk_write.image_info(std::slice::from_ref(image_info))
+                    }
+                    DescriptorInfo::AccelerationStructure(_) => {
+                        let mut vk_write =
+                            vk_write.push_next(acceleration_structure_writes.next().unwrap());
+                        vk_write.descriptor_count = 1;
+                        vk_write
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        unsafe {
+            vulkan.device().update_descriptor_sets(&vk_writes, &[]);
+        }
+
+        for write in self.writes {
+            self.descriptor_set.written_dependencies.insert(
+                (write.binding, write.array_element),
+                write.dependencies.iter().map(Arc::downgrade).collect(),
+            );
+        }
+    }
+
+    fn push_buffer(
+        &mut self,
+        binding: u32,
+        array_element: u32,
+        descriptor_type: vk::DescriptorType,
+        buffer: &impl Buffer,
+    ) -> &mut Self {
+        let buffer_info = vk::DescriptorBufferInfo::default()
+            .buffer(buffer.instance().buffer())
+            .offset(0)
+            .range(vk::WHOLE_SIZE);
+
+        self.push(DescriptorWrite {
+            binding,
+            array_element,
+            descriptor_type,
+            info: DescriptorInfo::Buffer(buffer_info),
+            dependencies: vec![buffer.create_generic_dep()],
+        })
+    }
+
+    fn push(&mut self, write: DescriptorWrite) -> &mut Self {
+        let layout_type = self
+            .descriptor_set
+            .layout_dep
+            .binding_type(write.binding)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Tried to write to binding {} which isn't in the descriptor set layout.",
+                    write.binding
+                )
+            });
+        assert_eq!(
+            layout_type, write.descriptor_type,
+            "Tried to write a {:?} to binding {} which is a {:?}.",
+            write.descriptor_type, write.binding, layout_type
+        );
+
+        self.writes.push(write);
+        self
+    }
+
+    fn image_view(image: &impl Image) -> vk::ImageView {
+        image
+            .instance()
+            .image_view()
+            .expect("Tried to write an image without an image view to a descriptor set.")
     }
 }
 
@@ -139,19 +448,32 @@ impl Drop for DescriptorSetPoolInstance {
 
 pub struct DescriptorSetPool {
     instance: Arc<DescriptorSetPoolInstance>,
-    descriptor_sets: SlotMap<DescriptorSetHandle, DescriptorSet>,
+    descriptor_sets: HandleMap<DescriptorSetHandle, DescriptorSet>,
 }
 
 impl DescriptorSetPool {
     pub fn new(vulkan: &Vulkan) -> Self {
-        let descriptor_pool_sizes = [
+        let mut descriptor_pool_sizes = vec![
             vk::DescriptorPoolSize::default()
                 .ty(vk::DescriptorType::UNIFORM_BUFFER)
                 .descriptor_count(100),
             vk::DescriptorPoolSize::default()
                 .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
                 .descriptor_count(100),
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(100),
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::STORAGE_IMAGE)
+                .descriptor_count(100),
         ];
+        if vulkan.is_feature_enabled(VulkanFeature::RayTracing) {
+            descriptor_pool_sizes.push(
+                vk::DescriptorPoolSize::default()
+                    .ty(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
+                    .descriptor_count(100),
+            );
+        }
 
         let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo::default()
             .pool_sizes(&descriptor_pool_sizes)
@@ -170,7 +492,7 @@ impl DescriptorSetPool {
                 vulkan_dep: vulkan.create_dep(),
                 descriptor_pool,
             }),
-            descriptor_sets: SlotMap::with_key(),
+            descriptor_sets: HandleMap::new(),
         }
     }
 
@@ -221,7 +543,8 @@ impl DescriptorSetPool {
         .into_iter()
         .map(|descriptor_set| DescriptorSet {
             descriptor_set,
-            written_dependencies: Vec::new(),
+            layout_dep: layout.create_dep(),
+            written_dependencies: HashMap::new(),
         })
         .collect::<Vec<_>>();
 
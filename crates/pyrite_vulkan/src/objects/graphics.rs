@@ -0,0 +1,391 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{
+    util::{GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep, VulkanFeature, VulkanResult,
+};
+
+use super::{PipelineLayoutCreateInfo, PipelineLayoutInstance, Shader};
+
+pub type GraphicsPipelineDep = Arc<GraphicsPipelineInstance>;
+
+pub struct GraphicsPipelineInstance {
+    vulkan_dep: VulkanDep,
+    pipeline_layout: PipelineLayoutInstance,
+    pipeline: vk::Pipeline,
+}
+
+impl GraphicsPipelineInstance {
+    pub fn pipeline(&self) -> vk::Pipeline {
+        self.pipeline
+    }
+
+    pub fn pipeline_layout(&self) -> &PipelineLayoutInstance {
+        &self.pipeline_layout
+    }
+}
+
+impl VulkanResource for GraphicsPipelineInstance {}
+
+impl Drop for GraphicsPipelineInstance {
+    fn drop(&mut self) {
+        unsafe {
+            self.vulkan_dep
+                .device()
+                .destroy_pipeline(self.pipeline, None);
+        }
+    }
+}
+
+/// The formats of the attachments the pipeline renders to, used instead of a render pass.
+pub struct RenderingFormats {
+    pub color_formats: Vec<vk::Format>,
+    pub depth_format: Option<vk::Format>,
+    /// The sample count of every attachment, `TYPE_1` unless multisampling.
+    pub samples: vk::SampleCountFlags,
+}
+
+pub struct GraphicsPipelineCreateInfo<'a> {
+    pub vertex_shader: &'a Shader,
+    pub vertex_entry_point: String,
+    pub fragment_shader: &'a Shader,
+    pub fragment_entry_point: String,
+    /// Descriptor set layouts and push constant ranges left empty are derived from the shaders.
+    pub pipeline_layout_info: PipelineLayoutCreateInfo<'a>,
+    /// If both the bindings and attributes are empty, the vertex shader's inputs are read from
+    /// one tightly packed buffer at binding 0, see
+    /// [`super::ShaderReflection::packed_vertex_input`].
+    pub vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+    pub vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    pub topology: vk::PrimitiveTopology,
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+    /// Blends color attachments with the source alpha instead of overwriting them.
+    pub alpha_blending: bool,
+    /// The depth comparison, only used if there is a depth format.
+    pub depth_compare_op: vk::CompareOp,
+    pub rendering_formats: RenderingFormats,
+    /// The name shown in debugging tools.
+    pub name: Option<String>,
+}
+
+pub struct MeshGraphicsPipelineCreateInfo<'a> {
+    /// The task shader launching the mesh shader workgroups and its entry point, the mesh shader
+    /// is launched directly by the draw if `None`.
+    pub task_shader: Option<(&'a Shader, String)>,
+    pub mesh_shader: &'a Shader,
+    pub mesh_entry_point: String,
+    pub fragment_shader: &'a Shader,
+    pub fragment_entry_point: String,
+    /// Descriptor set layouts and push constant ranges left empty are derived from the shaders.
+    pub pipeline_layout_info: PipelineLayoutCreateInfo<'a>,
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+    /// Blends color attachments with the source alpha instead of overwriting them.
+    pub alpha_blending: bool,
+    /// The depth comparison, only used if there is a depth format.
+    pub depth_compare_op: vk::CompareOp,
+    pub rendering_formats: RenderingFormats,
+    /// The name shown in debugging tools.
+    pub name: Option<String>,
+}
+
+/// The state shared by vertex and mesh shading pipelines.
+struct FixedFunctionState<'a> {
+    polygon_mode: vk::PolygonMode,
+    cull_mode: vk::CullModeFlags,
+    front_face: vk::FrontFace,
+    alpha_blending: bool,
+    depth_compare_op: vk::CompareOp,
+    rendering_formats: &'a RenderingFormats,
+    name: Option<&'a str>,
+}
+
+/// A graphics pipeline using dynamic rendering, so it only needs the formats of its attachments
+/// instead of a render pass.
+///
+/// The viewport and scissor are dynamic state and must be set after binding the pipeline.
+pub struct GraphicsPipeline {
+    instance: Arc<GraphicsPipelineInstance>,
+}
+
+impl GraphicsPipeline {
+    /// # Panics
+    /// If [`crate::VulkanFeature::DynamicRendering`] wasn't enabled or the pipeline can't be
+    /// created, see [`GraphicsPipeline::try_new`].
+    pub fn new(vulkan: &Vulkan, create_info: GraphicsPipelineCreateInfo<'_>) -> Self {
+        Self::try_new(vulkan, create_info)
+            .unwrap_or_else(|err| panic!("Failed to create graphics pipeline: {}", err))
+    }
+
+    /// # Panics
+    /// If [`crate::VulkanFeature::DynamicRendering`] wasn't enabled.
+    pub fn try_new(
+        vulkan: &Vulkan,
+        create_info: GraphicsPipelineCreateInfo<'_>,
+    ) -> VulkanResult<Self> {
+        assert!(
+            vulkan.dynamic_rendering().is_some(),
+            "Dynamic rendering must be enabled to create a graphics pipeline."
+        );
+
+        let pipeline_layout = PipelineLayoutInstance::try_new_with_reflection(
+            vulkan,
+            create_info.pipeline_layout_info,
+            &[
+                (
+                    vk::ShaderStageFlags::VERTEX,
+                    create_info.vertex_shader.reflection(),
+                ),
+                (
+                    vk::ShaderStageFlags::FRAGMENT,
+                    create_info.fragment_shader.reflection(),
+                ),
+            ],
+        )?;
+
+        let vk_vertex_name = std::ffi::CString::new(create_info.vertex_entry_point).unwrap();
+        let vk_fragment_name = std::ffi::CString::new(create_info.fragment_entry_point).unwrap();
+        let vk_stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(create_info.vertex_shader.module())
+                .name(vk_vertex_name.as_c_str()),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(create_info.fragment_shader.module())
+                .name(vk_fragment_name.as_c_str()),
+        ];
+
+        let (vertex_bindings, vertex_attributes) =
+            if create_info.vertex_bindings.is_empty() && create_info.vertex_attributes.is_empty() {
+                create_info
+                    .vertex_shader
+                    .reflection()
+                    .packed_vertex_input(0)
+            } else {
+                (create_info.vertex_bindings, create_info.vertex_attributes)
+            };
+        let vk_vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&vertex_bindings)
+            .vertex_attribute_descriptions(&vertex_attributes);
+        let vk_input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(create_info.topology)
+            .primitive_restart_enable(false);
+
+        Self::try_new_with_stages(
+            vulkan,
+            pipeline_layout,
+            &vk_stages,
+            Some((&vk_vertex_input_state, &vk_input_assembly_state)),
+            &FixedFunctionState {
+                polygon_mode: create_info.polygon_mode,
+                cull_mode: create_info.cull_mode,
+                front_face: create_info.front_face,
+                alpha_blending: create_info.alpha_blending,
+                depth_compare_op: create_info.depth_compare_op,
+                rendering_formats: &create_info.rendering_formats,
+                name: create_info.name.as_deref(),
+            },
+        )
+    }
+
+    /// Creates a pipeline with an optional task shader and a mesh shader instead of the vertex
+    /// input and vertex shader, drawn with [`super::CommandBuffer::draw_mesh_tasks`].
+    ///
+    /// # Panics
+    /// If [`crate::VulkanFeature::MeshShader`] or [`crate::VulkanFeature::DynamicRendering`]
+    /// wasn't enabled or the pipeline can't be created, see [`GraphicsPipeline::try_new_mesh`].
+    pub fn new_mesh(vulkan: &Vulkan, create_info: MeshGraphicsPipelineCreateInfo<'_>) -> Self {
+        Self::try_new_mesh(vulkan, create_info)
+            .unwrap_or_else(|err| panic!("Failed to create mesh graphics pipeline: {}", err))
+    }
+
+    /// # Panics
+    /// If [`crate::VulkanFeature::MeshShader`] or [`crate::VulkanFeature::DynamicRendering`]
+    /// wasn't enabled.
+    pub fn try_new_mesh(
+        vulkan: &Vulkan,
+        create_info: MeshGraphicsPipelineCreateInfo<'_>,
+    ) -> VulkanResult<Self> {
+        assert!(
+            vulkan.dynamic_rendering().is_some(),
+            "Dynamic rendering must be enabled to create a graphics pipeline."
+        );
+        assert!(
+            vulkan.is_feature_enabled(VulkanFeature::MeshShader),
+            "VulkanFeature::MeshShader must be enabled to create a mesh graphics pipeline."
+        );
+
+        let mut shaders = Vec::new();
+        if let Some((task_shader, task_entry_point)) = create_info.task_shader {
+            shaders.push((
+                vk::ShaderStageFlags::TASK_EXT,
+                task_shader,
+                task_entry_point,
+            ));
+        }
+        shaders.push((
+            vk::ShaderStageFlags::MESH_EXT,
+            create_info.mesh_shader,
+            create_info.mesh_entry_point,
+        ));
+        shaders.push((
+            vk::ShaderStageFlags::FRAGMENT,
+            create_info.fragment_shader,
+            create_info.fragment_entry_point,
+        ));
+
+        let pipeline_layout = PipelineLayoutInstance::try_new_with_reflection(
+            vulkan,
+            create_info.pipeline_layout_info,
+            &shaders
+                .iter()
+                .map(|(stage, shader, _)| (*stage, shader.reflection()))
+                .collect::<Vec<_>>(),
+        )?;
+
+        let vk_names = shaders
+            .iter()
+            .map(|(_, _, entry_point)| std::ffi::CString::new(entry_point.as_str()).unwrap())
+            .collect::<Vec<_>>();
+        let vk_stages = shaders
+            .iter()
+            .zip(&vk_names)
+            .map(|((stage, shader, _), vk_name)| {
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(*stage)
+                    .module(shader.module())
+                    .name(vk_name.as_c_str())
+            })
+            .collect::<Vec<_>>();
+
+        Self::try_new_with_stages(
+            vulkan,
+            pipeline_layout,
+            &vk_stages,
+            None,
+            &FixedFunctionState {
+                polygon_mode: create_info.polygon_mode,
+                cull_mode: create_info.cull_mode,
+                front_face: create_info.front_face,
+                alpha_blending: create_info.alpha_blending,
+                depth_compare_op: create_info.depth_compare_op,
+                rendering_formats: &create_info.rendering_formats,
+                name: create_info.name.as_deref(),
+            },
+        )
+    }
+
+    /// Creates the pipeline from its stages, with the vertex input and input assembly state for
+    /// vertex shading pipelines.
+    fn try_new_with_stages(
+        vulkan: &Vulkan,
+        pipeline_layout: PipelineLayoutInstance,
+        vk_stages: &[vk::PipelineShaderStageCreateInfo<'_>],
+        vertex_input: Option<(
+            &vk::PipelineVertexInputStateCreateInfo<'_>,
+            &vk::PipelineInputAssemblyStateCreateInfo<'_>,
+        )>,
+        state: &FixedFunctionState<'_>,
+    ) -> VulkanResult<Self> {
+        let vk_viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let vk_rasterization_state = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(state.polygon_mode)
+            .cull_mode(state.cull_mode)
+            .front_face(state.front_face)
+            .line_width(1.0);
+        let vk_multisample_state = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(state.rendering_formats.samples);
+
+        let has_depth = state.rendering_formats.depth_format.is_some();
+        let vk_depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(has_depth)
+            .depth_write_enable(has_depth)
+            .depth_compare_op(state.depth_compare_op);
+
+        let vk_color_blend_attachment = if state.alpha_blending {
+            vk::PipelineColorBlendAttachmentState::default()
+                .blend_enable(true)
+                .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .color_blend_op(vk::BlendOp::ADD)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .alpha_blend_op(vk::BlendOp::ADD)
+        } else {
+            vk::PipelineColorBlendAttachmentState::default()
+        }
+        .color_write_mask(vk::ColorComponentFlags::RGBA);
+        let vk_color_blend_attachments =
+            vec![vk_color_blend_attachment; state.rendering_formats.color_formats.len()];
+        let vk_color_blend_state = vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&vk_color_blend_attachments);
+
+        let vk_dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let vk_dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&vk_dynamic_states);
+
+        let mut vk_rendering_create_info = vk::PipelineRenderingCreateInfo::default()
+            .color_attachment_formats(&state.rendering_formats.color_formats)
+            .depth_attachment_format(
+                state
+                    .rendering_formats
+                    .depth_format
+                    .unwrap_or(vk::Format::UNDEFINED),
+            );
+
+        let mut vk_create_info = vk::GraphicsPipelineCreateInfo::default()
+            .stages(vk_stages)
+            .viewport_state(&vk_viewport_state)
+            .rasterization_state(&vk_rasterization_state)
+            .multisample_state(&vk_multisample_state)
+            .depth_stencil_state(&vk_depth_stencil_state)
+            .color_blend_state(&vk_color_blend_state)
+            .dynamic_state(&vk_dynamic_state)
+            .layout(pipeline_layout.layout())
+            .push_next(&mut vk_rendering_create_info);
+        // Mesh shading pipelines generate their primitives without vertex input.
+        if let Some((vk_vertex_input_state, vk_input_assembly_state)) = vertex_input {
+            vk_create_info = vk_create_info
+                .vertex_input_state(vk_vertex_input_state)
+                .input_assembly_state(vk_input_assembly_state);
+        }
+
+        let pipeline = unsafe {
+            vulkan
+                .device()
+                .create_graphics_pipelines(vk::PipelineCache::null(), &[vk_create_info], None)
+                .map_err(|(_, result)| result)?[0]
+        };
+        if let Some(name) = state.name {
+            vulkan.set_debug_name(pipeline, name);
+        }
+
+        Ok(Self {
+            instance: Arc::new(GraphicsPipelineInstance {
+                vulkan_dep: vulkan.create_dep(),
+                pipeline_layout,
+                pipeline,
+            }),
+        })
+    }
+
+    pub fn instance(&self) -> &GraphicsPipelineInstance {
+        &self.instance
+    }
+
+    pub fn create_dep(&self) -> GraphicsPipelineDep {
+        self.instance.clone()
+    }
+
+    pub fn create_generic_dep(&self) -> GenericResourceDep {
+        self.instance.clone()
+    }
+}
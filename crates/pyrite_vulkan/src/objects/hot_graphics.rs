@@ -0,0 +1,89 @@
+use pyrite_asset::{loaders::shader::SpirvShader, Assets, WatchedHandle};
+
+use crate::Vulkan;
+
+use super::{GraphicsPipeline, Shader};
+
+type BuildPipelineFn = Box<dyn Fn(&Vulkan, &Shader, &Shader) -> GraphicsPipeline + Send + Sync>;
+
+/// A graphics pipeline that is rebuilt whenever its glsl sources change on disk.
+///
+/// The sources are compiled by the shader loader, which must be added to the [`Assets`]. Swapping
+/// the pipeline is safe at any point in a frame since command buffers keep the pipelines they
+/// recorded alive until they finish executing.
+pub struct HotGraphicsPipeline {
+    vertex_shader: WatchedHandle<SpirvShader>,
+    fragment_shader: WatchedHandle<SpirvShader>,
+    build_pipeline: BuildPipelineFn,
+    pipeline: Option<GraphicsPipeline>,
+    needs_build: bool,
+}
+
+impl HotGraphicsPipeline {
+    /// Starts loading the shaders, `build_pipeline` creates the pipeline from the compiled
+    /// shaders every time they change.
+    pub fn new<F>(
+        assets: &mut Assets,
+        vertex_shader_path: impl ToString,
+        fragment_shader_path: impl ToString,
+        build_pipeline: F,
+    ) -> Self
+    where
+        F: Fn(&Vulkan, &Shader, &Shader) -> GraphicsPipeline + Send + Sync + 'static,
+    {
+        Self {
+            vertex_shader: assets.load(vertex_shader_path).into_watched(),
+            fragment_shader: assets.load(fragment_shader_path).into_watched(),
+            build_pipeline: Box::new(build_pipeline),
+            pipeline: None,
+            needs_build: true,
+        }
+    }
+
+    /// Rebuilds the pipeline if either shader was reloaded, should be called once per frame.
+    /// Returns true if the pipeline was rebuilt.
+    ///
+    /// If a shader fails to compile the error is logged and the previous pipeline is kept, so a
+    /// typo doesn't take down the app.
+    pub fn update(&mut self, vulkan: &Vulkan, assets: &mut Assets) -> bool {
+        // Both handles are updated every frame so neither misses its reload.
+        let vertex_reloaded = self.vertex_shader.update(assets);
+        let fragment_reloaded = self.fragment_shader.update(assets);
+        self.needs_build |= vertex_reloaded || fragment_reloaded;
+        if !self.needs_build || !self.vertex_shader.is_loaded() || !self.fragment_shader.is_loaded()
+        {
+            return false;
+        }
+        self.needs_build = false;
+
+        for shader in [&self.vertex_shader, &self.fragment_shader] {
+            if let Some(error) = shader.get_error() {
+                log::error!(
+                    "Failed to compile shader, keeping the last pipeline: {}",
+                    error
+                );
+                return false;
+            }
+        }
+
+        let pipeline = {
+            let (Some(vertex_spirv), Some(fragment_spirv)) =
+                (self.vertex_shader.get(), self.fragment_shader.get())
+            else {
+                return false;
+            };
+
+            let vertex_shader = Shader::from_spirv(vulkan, &vertex_spirv);
+            let fragment_shader = Shader::from_spirv(vulkan, &fragment_spirv);
+            (self.build_pipeline)(vulkan, &vertex_shader, &fragment_shader)
+        };
+        self.pipeline = Some(pipeline);
+
+        true
+    }
+
+    /// The current pipeline, `None` until the shaders have loaded.
+    pub fn pipeline(&self) -> Option<&GraphicsPipeline> {
+        self.pipeline.as_ref()
+    }
+}
@@ -5,7 +5,7 @@ use ash::vk;
 use crate::{
     allocator::{MemoryAllocation, VulkanAllocationInfo, VulkanMemoryAllocator},
     util::{GenericResourceDep, VulkanResource, VulkanResourceDep},
-    Vulkan, VulkanDep,
+    Vulkan, VulkanDep, VulkanResult,
 };
 use util::ImageViewCreateInfo;
 
@@ -84,15 +84,30 @@ pub struct OwnedImageCreateInfo {
     pub format: vk::Format,
     pub usage: vk::ImageUsageFlags,
     pub samples: vk::SampleCountFlags,
+    /// The number of mip levels, at least 1.
+    pub mip_levels: u32,
     pub view_create_info: Option<ImageViewCreateInfo>,
+    /// The name shown in debugging tools, also used for the image view.
+    pub name: Option<String>,
 }
 
 impl OwnedImage {
+    /// # Panics
+    /// If the image can't be created, see [`OwnedImage::try_new`].
     pub fn new(
         vulkan: &Vulkan,
         vulkan_allocator: &mut VulkanMemoryAllocator,
         info: &OwnedImageCreateInfo,
     ) -> Self {
+        Self::try_new(vulkan, vulkan_allocator, info)
+            .unwrap_or_else(|err| panic!("Failed to create image: {}", err))
+    }
+
+    pub fn try_new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: &OwnedImageCreateInfo,
+    ) -> VulkanResult<Self> {
         let image_create_info = vk::ImageCreateInfo::default()
             .image_type(info.image_type)
             .extent(vk::Extent3D {
@@ -100,7 +115,7 @@ impl OwnedImage {
                 height: info.height,
                 depth: 1,
             })
-            .mip_levels(1)
+            .mip_levels(info.mip_levels)
             .array_layers(1)
             .format(info.format)
             .tiling(vk::ImageTiling::OPTIMAL)
@@ -109,20 +124,37 @@ impl OwnedImage {
             .sharing_mode(vk::SharingMode::EXCLUSIVE)
             .samples(info.samples);
 
-        let image = unsafe {
-            vulkan
-                .device()
-                .create_image(&image_create_info, None)
-                .expect("Failed to create image")
-        };
+        let image = unsafe { vulkan.device().create_image(&image_create_info, None)? };
 
         let memory_requirements = unsafe { vulkan.device().get_image_memory_requirements(image) };
 
-        let memory_allocation = vulkan_allocator.allocate(&VulkanAllocationInfo {
-            size: memory_requirements.size,
-            memory_proprties: vk::MemoryPropertyFlags::DEVICE_LOCAL,
-            memory_type_bits: memory_requirements.memory_type_bits,
-        });
+        // The image is destroyed if anything after creating it fails, the allocation is freed when
+        // dropped.
+        let bound_allocation = vulkan_allocator
+            .try_allocate(&VulkanAllocationInfo {
+                size: memory_requirements.size,
+                alignment: memory_requirements.alignment,
+                memory_proprties: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                memory_type_bits: memory_requirements.memory_type_bits,
+                linear: false,
+            })
+            .and_then(|memory_allocation| {
+                unsafe {
+                    vulkan.device().bind_image_memory(
+                        image,
+                        memory_allocation.instance().device_memory(),
+                        memory_allocation.instance().offset(),
+                    )?
+                };
+                Ok(memory_allocation)
+            });
+        let memory_allocation = match bound_allocation {
+            Ok(memory_allocation) => memory_allocation,
+            Err(err) => {
+                unsafe { vulkan.device().destroy_image(image, None) };
+                return Err(err);
+            }
+        };
 
         let image_view = match &info.view_create_info {
             Some(view_create_info) => {
@@ -137,22 +169,34 @@ impl OwnedImage {
                     vulkan
                         .device()
                         .create_image_view(&image_view_create_info, None)
-                        .expect("Failed to create image view")
                 };
 
-                Some(image_view)
+                match image_view {
+                    Ok(image_view) => Some(image_view),
+                    Err(result) => {
+                        unsafe { vulkan.device().destroy_image(image, None) };
+                        return Err(result.into());
+                    }
+                }
             }
             None => None,
         };
 
-        Self {
+        if let Some(name) = &info.name {
+            vulkan.set_debug_name(image, name);
+            if let Some(image_view) = image_view {
+                vulkan.set_debug_name(image_view, &format!("{} view", name));
+            }
+        }
+
+        Ok(Self {
             instance: Arc::new(OwnedImageInstance {
                 vulkan_dep: vulkan.create_dep(),
                 image,
                 image_view,
                 allocation: memory_allocation,
             }),
-        }
+        })
     }
 }
 
@@ -227,26 +271,64 @@ pub struct ImageMemoryBarrier<'a> {
     pub image: &'a dyn Image,
     pub old_layout: vk::ImageLayout,
     pub new_layout: vk::ImageLayout,
-    pub src_access_mask: vk::AccessFlags,
-    pub dst_access_mask: vk::AccessFlags,
+    pub src_access_mask: vk::AccessFlags2,
+    pub dst_access_mask: vk::AccessFlags2,
 }
 
-impl<'a> Into<vk::ImageMemoryBarrier<'a>> for ImageMemoryBarrier<'a> {
-    fn into(self) -> vk::ImageMemoryBarrier<'a> {
-        vk::ImageMemoryBarrier::default()
+impl<'a> ImageMemoryBarrier<'a> {
+    /// Converts the barrier for `vkCmdPipelineBarrier2`, which takes the stages per barrier.
+    pub fn into_vk2(
+        self,
+        src_stage_mask: vk::PipelineStageFlags2,
+        dst_stage_mask: vk::PipelineStageFlags2,
+    ) -> vk::ImageMemoryBarrier2<'a> {
+        vk::ImageMemoryBarrier2::default()
             .image(self.image.instance().image())
             .old_layout(self.old_layout)
             .new_layout(self.new_layout)
+            .src_stage_mask(src_stage_mask)
             .src_access_mask(self.src_access_mask)
+            .dst_stage_mask(dst_stage_mask)
             .dst_access_mask(self.dst_access_mask)
-            .subresource_range(
-                vk::ImageSubresourceRange::default()
-                    .aspect_mask(vk::ImageAspectFlags::COLOR)
-                    .base_mip_level(0)
-                    .level_count(1)
-                    .base_array_layer(0)
-                    .layer_count(1),
+            .subresource_range(self.subresource_range())
+    }
+
+    fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        // Images don't know their format, so depth images are recognized by their layouts.
+        let is_depth_layout = |layout| {
+            matches!(
+                layout,
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+                    | vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL
+                    | vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL
+                    | vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL
             )
+        };
+        let aspect_mask = if is_depth_layout(self.old_layout) || is_depth_layout(self.new_layout) {
+            vk::ImageAspectFlags::DEPTH
+        } else {
+            vk::ImageAspectFlags::COLOR
+        };
+
+        vk::ImageSubresourceRange::default()
+            .aspect_mask(aspect_mask)
+            .base_mip_level(0)
+            .level_count(vk::REMAINING_MIP_LEVELS)
+            .base_array_layer(0)
+            .layer_count(vk::REMAINING_ARRAY_LAYERS)
+    }
+}
+
+impl<'a> Into<vk::ImageMemoryBarrier<'a>> for ImageMemoryBarrier<'a> {
+    // Converts the barrier for the legacy `vkCmdPipelineBarrier`, widening the access masks.
+    fn into(self) -> vk::ImageMemoryBarrier<'a> {
+        vk::ImageMemoryBarrier::default()
+            .image(self.image.instance().image())
+            .old_layout(self.old_layout)
+            .new_layout(self.new_layout)
+            .src_access_mask(super::sync::legacy_access_flags(self.src_access_mask))
+            .dst_access_mask(super::sync::legacy_access_flags(self.dst_access_mask))
+            .subresource_range(self.subresource_range())
     }
 }
 
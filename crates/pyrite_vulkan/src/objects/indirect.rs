@@ -0,0 +1,143 @@
+use ash::vk;
+
+use crate::{allocator::VulkanMemoryAllocator, Vulkan};
+
+use super::{CommandBuffer, TypedBuffer, TypedBufferCreateInfo};
+
+/// The layout of a `vk::DrawIndexedIndirectCommand`, which can be written to buffers since it's
+/// [`bytemuck::Pod`] unlike the ash struct.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawIndexedIndirectCommand {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub vertex_offset: i32,
+    pub first_instance: u32,
+}
+
+/// Indexed indirect draw commands built on the cpu and staged to a device local buffer.
+///
+/// The commands are written to a host visible staging buffer and copied by
+/// [`IndirectCommandBuffer::upload`], so neither buffer may be in use by an executing command
+/// buffer when uploading, e.g. use one per frame in flight.
+pub struct IndirectCommandBuffer {
+    commands: Vec<DrawIndexedIndirectCommand>,
+    staging_buffer: TypedBuffer<DrawIndexedIndirectCommand>,
+    buffer: TypedBuffer<DrawIndexedIndirectCommand>,
+}
+
+impl IndirectCommandBuffer {
+    /// Creates a buffer which can hold up to `capacity` commands.
+    pub fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            commands: Vec::with_capacity(capacity),
+            staging_buffer: TypedBuffer::new(
+                vulkan,
+                vulkan_allocator,
+                &TypedBufferCreateInfo {
+                    len: capacity,
+                    usage: vk::BufferUsageFlags::TRANSFER_SRC,
+                    memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                        | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    name: Some("indirect_commands_staging".to_string()),
+                },
+            ),
+            buffer: TypedBuffer::new(
+                vulkan,
+                vulkan_allocator,
+                &TypedBufferCreateInfo {
+                    len: capacity,
+                    usage: vk::BufferUsageFlags::INDIRECT_BUFFER
+                        | vk::BufferUsageFlags::STORAGE_BUFFER
+                        | vk::BufferUsageFlags::TRANSFER_DST,
+                    memory_properties: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                    name: Some("indirect_commands".to_string()),
+                },
+            ),
+        }
+    }
+
+    /// Adds a command, returning its index.
+    ///
+    /// # Panics
+    /// If the buffer is full.
+    pub fn push(&mut self, command: DrawIndexedIndirectCommand) -> usize {
+        assert!(
+            self.commands.len() < self.capacity(),
+            "Tried to push more than {} indirect commands.",
+            self.capacity()
+        );
+
+        self.commands.push(command);
+        self.commands.len() - 1
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn commands(&self) -> &[DrawIndexedIndirectCommand] {
+        &self.commands
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Copies the commands to the device local buffer, followed by a barrier making them
+    /// visible to indirect draws and compute shaders, e.g. for gpu culling.
+    pub fn upload(&mut self, command_buffer: &mut CommandBuffer) {
+        if self.commands.is_empty() {
+            return;
+        }
+
+        self.staging_buffer.write_slice(0, &self.commands);
+        command_buffer.copy_buffer(
+            &self.staging_buffer,
+            &self.buffer,
+            &[vk::BufferCopy::default()
+                .size(std::mem::size_of_val(self.commands.as_slice()) as u64)],
+        );
+        command_buffer.memory_barrier(
+            vk::PipelineStageFlags2::ALL_TRANSFER,
+            vk::AccessFlags2::TRANSFER_WRITE,
+            vk::PipelineStageFlags2::DRAW_INDIRECT | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::INDIRECT_COMMAND_READ | vk::AccessFlags2::SHADER_STORAGE_READ,
+        );
+    }
+
+    /// Draws every command, which must have been uploaded.
+    ///
+    /// # Panics
+    /// If there is more than one command without [`crate::VulkanFeature::MultiDrawIndirect`].
+    pub fn draw(&self, command_buffer: &mut CommandBuffer) {
+        if self.commands.is_empty() {
+            return;
+        }
+
+        command_buffer.draw_indexed_indirect(
+            &self.buffer,
+            0,
+            self.commands.len() as u32,
+            std::mem::size_of::<DrawIndexedIndirectCommand>() as u32,
+        );
+    }
+
+    /// The device local buffer, e.g. to be written by a culling compute shader.
+    pub fn buffer(&self) -> &TypedBuffer<DrawIndexedIndirectCommand> {
+        &self.buffer
+    }
+}
@@ -0,0 +1,211 @@
+use ash::vk;
+use pyrite_math::{Vec2, Vec3, Vec4};
+
+use crate::{allocator::VulkanMemoryAllocator, stager::VulkanStager, Vulkan};
+
+use super::{Buffer, CommandBuffer, TypedBuffer, TypedBufferCreateInfo};
+
+pub use pyrite_vulkan_macros::Vertex;
+
+/// A type which can be read by shaders as a single vertex attribute.
+pub trait VertexAttribute {
+    const FORMAT: vk::Format;
+}
+
+macro_rules! impl_vertex_attribute {
+    ($($ty:ty => $format:ident),* $(,)?) => {
+        $(
+            impl VertexAttribute for $ty {
+                const FORMAT: vk::Format = vk::Format::$format;
+            }
+        )*
+    };
+}
+
+impl_vertex_attribute! {
+    f32 => R32_SFLOAT,
+    [f32; 2] => R32G32_SFLOAT,
+    [f32; 3] => R32G32B32_SFLOAT,
+    [f32; 4] => R32G32B32A32_SFLOAT,
+    u32 => R32_UINT,
+    [u32; 2] => R32G32_UINT,
+    [u32; 3] => R32G32B32_UINT,
+    [u32; 4] => R32G32B32A32_UINT,
+    i32 => R32_SINT,
+    [i32; 2] => R32G32_SINT,
+    [i32; 3] => R32G32B32_SINT,
+    [i32; 4] => R32G32B32A32_SINT,
+    Vec2 => R32G32_SFLOAT,
+    Vec3 => R32G32B32_SFLOAT,
+    Vec4 => R32G32B32A32_SFLOAT,
+}
+
+/// A vertex whose fields are read as vertex attributes.
+///
+/// Usually implemented with `#[derive(Vertex)]` on a `#[repr(C)]` struct, which assigns the
+/// fields consecutive locations starting at 0, every field must implement [`VertexAttribute`].
+/// Vertices are uploaded as bytes, so the struct must also derive [`bytemuck::Pod`].
+pub trait Vertex: bytemuck::Pod {
+    fn attributes(binding: u32) -> Vec<vk::VertexInputAttributeDescription>;
+
+    fn binding(binding: u32) -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription {
+            binding,
+            stride: std::mem::size_of::<Self>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }
+    }
+}
+
+/// Device local vertex and `u32` index buffers, drawn as an indexed triangle list.
+pub struct Mesh<V: Vertex> {
+    vertex_buffer: TypedBuffer<V>,
+    index_buffer: TypedBuffer<u32>,
+}
+
+impl<V: Vertex> Mesh<V> {
+    /// Creates the mesh, recording the uploads into `command_buffer` followed by a barrier
+    /// making them visible to vertex input.
+    ///
+    /// # Panics
+    /// If there are no vertices or indices.
+    pub fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+        vertices: &[V],
+        indices: &[u32],
+    ) -> Self {
+        assert!(
+            !vertices.is_empty() && !indices.is_empty(),
+            "Tried to create a mesh without vertices or indices."
+        );
+
+        let vertex_buffer = TypedBuffer::new(
+            vulkan,
+            vulkan_allocator,
+            &TypedBufferCreateInfo {
+                len: vertices.len(),
+                usage: vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+                memory_properties: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                name: Some("mesh_vertices".to_string()),
+            },
+        );
+        let index_buffer = TypedBuffer::new(
+            vulkan,
+            vulkan_allocator,
+            &TypedBufferCreateInfo {
+                len: indices.len(),
+                usage: vk::BufferUsageFlags::INDEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+                memory_properties: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                name: Some("mesh_indices".to_string()),
+            },
+        );
+
+        stager.stage_buffer(
+            vulkan,
+            vulkan_allocator,
+            command_buffer,
+            &vertex_buffer,
+            0,
+            bytemuck::cast_slice(vertices),
+        );
+        stager.stage_buffer(
+            vulkan,
+            vulkan_allocator,
+            command_buffer,
+            &index_buffer,
+            0,
+            bytemuck::cast_slice(indices),
+        );
+        command_buffer.memory_barrier(
+            vk::PipelineStageFlags2::ALL_TRANSFER,
+            vk::AccessFlags2::TRANSFER_WRITE,
+            vk::PipelineStageFlags2::VERTEX_INPUT,
+            vk::AccessFlags2::VERTEX_ATTRIBUTE_READ | vk::AccessFlags2::INDEX_READ,
+        );
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+        }
+    }
+
+    /// The vertex binding for a [`super::GraphicsPipelineCreateInfo`] drawing the mesh.
+    pub fn vertex_bindings() -> Vec<vk::VertexInputBindingDescription> {
+        vec![V::binding(0)]
+    }
+
+    /// The vertex attributes for a [`super::GraphicsPipelineCreateInfo`] drawing the mesh.
+    pub fn vertex_attributes() -> Vec<vk::VertexInputAttributeDescription> {
+        V::attributes(0)
+    }
+
+    /// Binds the vertex and index buffers.
+    pub fn bind(&self, command_buffer: &mut CommandBuffer) {
+        command_buffer.bind_vertex_buffers(0, &[(&self.vertex_buffer as &dyn Buffer, 0)]);
+        command_buffer.bind_index_buffer(&self.index_buffer, 0, vk::IndexType::UINT32);
+    }
+
+    /// Draws the bound mesh `instance_count` times.
+    pub fn draw(&self, command_buffer: &mut CommandBuffer, instance_count: u32) {
+        command_buffer.draw_indexed(self.index_count(), instance_count, 0, 0, 0);
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_buffer.len() as u32
+    }
+
+    pub fn index_count(&self) -> u32 {
+        self.index_buffer.len() as u32
+    }
+
+    pub fn vertex_buffer(&self) -> &TypedBuffer<V> {
+        &self.vertex_buffer
+    }
+
+    pub fn index_buffer(&self) -> &TypedBuffer<u32> {
+        &self.index_buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable, Vertex)]
+    struct TestVertex {
+        position: Vec3,
+        normal: [f32; 3],
+        uv: Vec2,
+        material: u32,
+    }
+
+    #[test]
+    fn derived_attributes_follow_the_fields() {
+        let attributes = TestVertex::attributes(1)
+            .into_iter()
+            .map(|attribute| {
+                (
+                    attribute.location,
+                    attribute.binding,
+                    attribute.format,
+                    attribute.offset,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            attributes,
+            [
+                (0, 1, vk::Format::R32G32B32_SFLOAT, 0),
+                (1, 1, vk::Format::R32G32B32_SFLOAT, 12),
+                (2, 1, vk::Format::R32G32_SFLOAT, 24),
+                (3, 1, vk::Format::R32_UINT, 32),
+            ]
+        );
+        assert_eq!(TestVertex::binding(1).stride, 36);
+    }
+}
@@ -1,3 +1,12 @@
+pub mod acceleration_structure;
+pub use acceleration_structure::*;
+
+pub mod bindless;
+pub use bindless::*;
+
+pub mod buffer;
+pub use buffer::*;
+
 pub mod command;
 pub use command::*;
 
@@ -10,16 +19,46 @@ pub use compute::*;
 pub mod descriptor_set;
 pub use descriptor_set::*;
 
+pub mod graphics;
+pub use graphics::*;
+
+pub mod hot_graphics;
+pub use hot_graphics::*;
+
 pub mod image;
 pub use image::*;
 
+pub mod indirect;
+pub use indirect::*;
+
+pub mod mesh;
+pub use mesh::*;
+
 pub mod pipeline_layout;
 pub use pipeline_layout::*;
 
+pub mod ray_tracing;
+pub use ray_tracing::*;
+
+pub mod reflection;
+pub use reflection::*;
+
+pub mod query;
+pub use query::*;
+
+pub mod sampler;
+pub use sampler::*;
+
+pub mod sparse;
+pub use sparse::*;
+
 pub mod shader;
 pub use shader::*;
 
 pub mod sync;
 pub use sync::*;
 
+pub mod texture;
+pub use texture::*;
+
 pub mod glsl;
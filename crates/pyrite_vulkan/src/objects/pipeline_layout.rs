@@ -1,32 +1,78 @@
+use std::collections::BTreeMap;
+
 use ash::vk;
 
-use crate::{Vulkan, VulkanDep};
+use crate::{Vulkan, VulkanDep, VulkanResult};
 
-use super::{DescriptorSetLayout, DescriptorSetLayoutDep, PushConstantRange};
+use super::{DescriptorSetLayout, PushConstantRange, ShaderReflection};
 
 pub struct PipelineLayoutInstance {
     vulkan_dep: VulkanDep,
-    descriptor_set_layout_dependencies: Vec<DescriptorSetLayoutDep>,
+    descriptor_set_layouts: Vec<DescriptorSetLayout>,
+    push_constant_ranges: Vec<PushConstantRange>,
     pipeline_layout: vk::PipelineLayout,
 }
 
 impl PipelineLayoutInstance {
     pub fn new(vulkan: &Vulkan, create_info: PipelineLayoutCreateInfo<'_>) -> Self {
-        let descriptor_set_layout_dependencies = create_info
-            .descriptor_set_layouts
-            .iter()
-            .map(|layout| layout.create_dep())
-            .collect::<Vec<_>>();
+        Self::new_with_reflection(vulkan, create_info, &[])
+    }
+
+    pub fn try_new(
+        vulkan: &Vulkan,
+        create_info: PipelineLayoutCreateInfo<'_>,
+    ) -> VulkanResult<Self> {
+        Self::try_new_with_reflection(vulkan, create_info, &[])
+    }
+
+    /// Creates the layout, deriving the descriptor set layouts and push constant ranges left
+    /// empty in the create info from the reflection of the pipeline's shaders.
+    ///
+    /// Derived layouts have no descriptor binding flags, so bindless layouts must be specified.
+    ///
+    /// # Panics
+    /// If descriptor set layouts are derived and a shader declares a runtime sized array, or
+    /// the stages declare a binding with different descriptor types.
+    pub fn new_with_reflection(
+        vulkan: &Vulkan,
+        create_info: PipelineLayoutCreateInfo<'_>,
+        shaders: &[(vk::ShaderStageFlags, &ShaderReflection)],
+    ) -> Self {
+        Self::try_new_with_reflection(vulkan, create_info, shaders)
+            .unwrap_or_else(|err| panic!("Failed to create pipeline layout: {}", err))
+    }
+
+    /// Like [`PipelineLayoutInstance::new_with_reflection`], but returns an error if the Vulkan
+    /// objects can't be created. Invalid shader interfaces still panic.
+    pub fn try_new_with_reflection(
+        vulkan: &Vulkan,
+        create_info: PipelineLayoutCreateInfo<'_>,
+        shaders: &[(vk::ShaderStageFlags, &ShaderReflection)],
+    ) -> VulkanResult<Self> {
+        let descriptor_set_layouts = if create_info.descriptor_set_layouts.is_empty() {
+            Self::reflect_descriptor_set_layouts(vulkan, shaders)
+        } else {
+            create_info
+                .descriptor_set_layouts
+                .into_iter()
+                .cloned()
+                .collect()
+        };
 
-        let vk_descriptor_set_layouts = descriptor_set_layout_dependencies
+        let push_constant_ranges = if create_info.push_constant_ranges.is_empty() {
+            Self::reflect_push_constant_ranges(shaders)
+        } else {
+            create_info.push_constant_ranges
+        };
+
+        let vk_descriptor_set_layouts = descriptor_set_layouts
             .iter()
-            .map(|layout| layout.layout())
+            .map(|layout| layout.instance().layout())
             .collect::<Vec<_>>();
 
-        let vk_push_constant_ranges = create_info
-            .push_constant_ranges
-            .into_iter()
-            .map(|range| range.into())
+        let vk_push_constant_ranges = push_constant_ranges
+            .iter()
+            .map(|range| range.clone().into())
             .collect::<Vec<_>>();
 
         let vk_create_info = vk::PipelineLayoutCreateInfo::default()
@@ -35,20 +81,113 @@ impl PipelineLayoutInstance {
         let pipeline_layout = unsafe {
             vulkan
                 .device()
-                .create_pipeline_layout(&vk_create_info, None)
-                .unwrap()
+                .create_pipeline_layout(&vk_create_info, None)?
         };
 
-        Self {
+        Ok(Self {
             vulkan_dep: vulkan.create_dep(),
-            descriptor_set_layout_dependencies,
+            descriptor_set_layouts,
+            push_constant_ranges,
             pipeline_layout,
+        })
+    }
+
+    /// One layout per set up to the highest set any stage uses, sets without bindings get an
+    /// empty layout.
+    fn reflect_descriptor_set_layouts(
+        vulkan: &Vulkan,
+        shaders: &[(vk::ShaderStageFlags, &ShaderReflection)],
+    ) -> Vec<DescriptorSetLayout> {
+        let mut sets: BTreeMap<
+            u32,
+            BTreeMap<u32, (vk::DescriptorType, u32, vk::ShaderStageFlags)>,
+        > = BTreeMap::new();
+        for (stage, reflection) in shaders {
+            for binding in &reflection.bindings {
+                let count = binding.count.unwrap_or_else(|| {
+                    panic!(
+                        "Binding {} of set {} is a runtime sized array, specify its layout",
+                        binding.binding, binding.set
+                    )
+                });
+
+                let (descriptor_type, descriptor_count, stage_flags) = sets
+                    .entry(binding.set)
+                    .or_default()
+                    .entry(binding.binding)
+                    .or_insert((
+                        binding.descriptor_type,
+                        count,
+                        vk::ShaderStageFlags::empty(),
+                    ));
+                assert_eq!(
+                    *descriptor_type, binding.descriptor_type,
+                    "Binding {} of set {} has a different descriptor type in each stage",
+                    binding.binding, binding.set
+                );
+                *descriptor_count = (*descriptor_count).max(count);
+                *stage_flags |= *stage;
+            }
+        }
+
+        let set_count = sets.keys().next_back().map_or(0, |set| set + 1);
+        (0..set_count)
+            .map(|set| {
+                let mut builder = DescriptorSetLayout::builder();
+                for (binding, (descriptor_type, descriptor_count, stage_flags)) in
+                    sets.get(&set).into_iter().flatten()
+                {
+                    builder.add_binding(
+                        *binding,
+                        *descriptor_type,
+                        *descriptor_count,
+                        *stage_flags,
+                    );
+                }
+                builder.build(vulkan)
+            })
+            .collect()
+    }
+
+    /// A single range from the start of the push constants covering the largest block of any
+    /// stage, visible to every stage declaring one.
+    fn reflect_push_constant_ranges(
+        shaders: &[(vk::ShaderStageFlags, &ShaderReflection)],
+    ) -> Vec<PushConstantRange> {
+        let mut stage_flags = vk::ShaderStageFlags::empty();
+        let mut size = 0;
+        for (stage, reflection) in shaders {
+            if let Some(push_constant_size) = reflection.push_constant_size {
+                stage_flags |= *stage;
+                size = size.max(push_constant_size);
+            }
         }
+
+        if stage_flags.is_empty() {
+            return Vec::new();
+        }
+
+        vec![PushConstantRange {
+            stage_flags,
+            offset: 0,
+            size,
+        }]
     }
 
     pub fn layout(&self) -> vk::PipelineLayout {
         self.pipeline_layout
     }
+
+    /// The descriptor set layouts of the pipeline layout, including derived layouts, used to
+    /// allocate descriptor sets for the pipeline.
+    pub fn descriptor_set_layouts(&self) -> &[DescriptorSetLayout] {
+        &self.descriptor_set_layouts
+    }
+
+    /// The push constant ranges of the pipeline layout, including derived ranges.
+    pub fn push_constant_ranges(&self) -> &[PushConstantRange] {
+        &self.push_constant_ranges
+    }
 }
 
 impl Drop for PipelineLayoutInstance {
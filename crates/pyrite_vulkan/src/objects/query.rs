@@ -0,0 +1,496 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{
+    util::{GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep, VulkanFeature,
+};
+
+use super::CommandBuffer;
+
+pub type QueryPoolDep = Arc<QueryPoolInstance>;
+
+pub struct QueryPoolInstance {
+    vulkan_dep: VulkanDep,
+    query_pool: vk::QueryPool,
+    query_type: vk::QueryType,
+    query_count: u32,
+    pipeline_statistics: vk::QueryPipelineStatisticFlags,
+}
+
+impl QueryPoolInstance {
+    pub fn query_pool(&self) -> vk::QueryPool {
+        self.query_pool
+    }
+
+    pub fn query_type(&self) -> vk::QueryType {
+        self.query_type
+    }
+
+    pub fn query_count(&self) -> u32 {
+        self.query_count
+    }
+
+    /// The statistics counted by a pipeline statistics pool, empty for other pools.
+    pub fn pipeline_statistics(&self) -> vk::QueryPipelineStatisticFlags {
+        self.pipeline_statistics
+    }
+
+    /// The number of values each query writes, one per counted statistic for pipeline
+    /// statistics pools.
+    pub fn values_per_query(&self) -> usize {
+        if self.query_type == vk::QueryType::PIPELINE_STATISTICS {
+            self.pipeline_statistics.as_raw().count_ones() as usize
+        } else {
+            1
+        }
+    }
+}
+
+impl VulkanResource for QueryPoolInstance {}
+
+impl Drop for QueryPoolInstance {
+    fn drop(&mut self) {
+        unsafe {
+            self.vulkan_dep
+                .device()
+                .destroy_query_pool(self.query_pool, None);
+        }
+    }
+}
+
+/// A pool of timestamp, occlusion or pipeline statistics queries.
+pub struct QueryPool {
+    instance: Arc<QueryPoolInstance>,
+}
+
+impl QueryPool {
+    pub fn new_timestamp(vulkan: &Vulkan, query_count: u32) -> Self {
+        Self::new(
+            vulkan,
+            vk::QueryType::TIMESTAMP,
+            query_count,
+            vk::QueryPipelineStatisticFlags::empty(),
+        )
+    }
+
+    /// Creates a pool of queries counting the samples passing the depth and stencil tests.
+    pub fn new_occlusion(vulkan: &Vulkan, query_count: u32) -> Self {
+        Self::new(
+            vulkan,
+            vk::QueryType::OCCLUSION,
+            query_count,
+            vk::QueryPipelineStatisticFlags::empty(),
+        )
+    }
+
+    /// Creates a pool of queries counting the `statistics`.
+    ///
+    /// # Panics
+    /// If [`VulkanFeature::PipelineStatisticsQuery`] wasn't enabled or `statistics` is empty.
+    pub fn new_pipeline_statistics(
+        vulkan: &Vulkan,
+        query_count: u32,
+        statistics: vk::QueryPipelineStatisticFlags,
+    ) -> Self {
+        assert!(
+            vulkan.is_feature_enabled(VulkanFeature::PipelineStatisticsQuery),
+            "Pipeline statistics queries require VulkanFeature::PipelineStatisticsQuery."
+        );
+        assert!(
+            !statistics.is_empty(),
+            "A pipeline statistics query pool must count at least one statistic."
+        );
+
+        Self::new(
+            vulkan,
+            vk::QueryType::PIPELINE_STATISTICS,
+            query_count,
+            statistics,
+        )
+    }
+
+    fn new(
+        vulkan: &Vulkan,
+        query_type: vk::QueryType,
+        query_count: u32,
+        pipeline_statistics: vk::QueryPipelineStatisticFlags,
+    ) -> Self {
+        let query_pool_create_info = vk::QueryPoolCreateInfo::default()
+            .query_type(query_type)
+            .query_count(query_count)
+            .pipeline_statistics(pipeline_statistics);
+
+        let query_pool = unsafe {
+            vulkan
+                .device()
+                .create_query_pool(&query_pool_create_info, None)
+                .expect("Failed to create query pool")
+        };
+
+        Self {
+            instance: Arc::new(QueryPoolInstance {
+                vulkan_dep: vulkan.create_dep(),
+                query_pool,
+                query_type,
+                query_count,
+                pipeline_statistics,
+            }),
+        }
+    }
+
+    /// Reads the raw timestamps of the queries starting at `first_query`, `None` if any of them
+    /// aren't available yet.
+    pub fn timestamps(&self, first_query: u32, query_count: u32) -> Option<Vec<u64>> {
+        let mut timestamps = vec![0; query_count as usize];
+        let result = unsafe {
+            self.instance.vulkan_dep.device().get_query_pool_results(
+                self.instance.query_pool,
+                first_query,
+                &mut timestamps,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+
+        match result {
+            Ok(()) => Some(timestamps),
+            Err(vk::Result::NOT_READY) => None,
+            Err(err) => panic!("Failed to get query pool results: {:?}", err),
+        }
+    }
+
+    /// Reads the values of the queries starting at `first_query` without waiting, `None` for
+    /// the queries that aren't available, e.g. because they weren't ended since their reset.
+    ///
+    /// Each query has [`QueryPoolInstance::values_per_query`] values.
+    pub fn results(&self, first_query: u32, query_count: u32) -> Vec<Option<Vec<u64>>> {
+        // Each query writes its values followed by its availability.
+        let stride = self.instance.values_per_query() + 1;
+        let mut data = vec![0u64; query_count as usize * stride];
+
+        let device = self.instance.vulkan_dep.device();
+        let result = unsafe {
+            (device.fp_v1_0().get_query_pool_results)(
+                device.handle(),
+                self.instance.query_pool,
+                first_query,
+                query_count,
+                std::mem::size_of_val(data.as_slice()),
+                data.as_mut_ptr().cast(),
+                (stride * std::mem::size_of::<u64>()) as vk::DeviceSize,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WITH_AVAILABILITY,
+            )
+        };
+        match result {
+            vk::Result::SUCCESS | vk::Result::NOT_READY => {}
+            err => panic!("Failed to get query pool results: {:?}", err),
+        }
+
+        data.chunks_exact(stride)
+            .map(|query| {
+                let (values, availability) = query.split_at(stride - 1);
+                (availability[0] != 0).then(|| values.to_vec())
+            })
+            .collect()
+    }
+
+    pub fn instance(&self) -> &QueryPoolInstance {
+        &self.instance
+    }
+
+    pub fn create_dep(&self) -> QueryPoolDep {
+        self.instance.clone()
+    }
+
+    pub fn create_generic_dep(&self) -> GenericResourceDep {
+        self.instance.clone()
+    }
+}
+
+/// A query pool per frame in flight, handing out the queries of the current frame in order.
+struct FrameQueryPools {
+    query_pools: Vec<QueryPool>,
+    used_queries: Vec<u32>,
+    frame_index: usize,
+    /// The query begun and not yet ended in the current frame, if any.
+    open_query: Option<u32>,
+}
+
+impl FrameQueryPools {
+    fn new(frames_in_flight: usize, create_query_pool: impl FnMut(usize) -> QueryPool) -> Self {
+        Self {
+            query_pools: (0..frames_in_flight).map(create_query_pool).collect(),
+            used_queries: vec![0; frames_in_flight],
+            frame_index: 0,
+            open_query: None,
+        }
+    }
+
+    /// Reads the results of the last use of `frame_index` and resets its queries.
+    fn begin_frame(
+        &mut self,
+        frame_index: usize,
+        command_buffer: &mut CommandBuffer,
+    ) -> Vec<Option<Vec<u64>>> {
+        assert!(
+            self.open_query.is_none(),
+            "The previous query frame still has an open query."
+        );
+        self.frame_index = frame_index;
+
+        let query_pool = &self.query_pools[frame_index];
+        let used_queries = std::mem::take(&mut self.used_queries[frame_index]);
+        let results = if used_queries > 0 {
+            query_pool.results(0, used_queries)
+        } else {
+            Vec::new()
+        };
+        command_buffer.reset_query_pool(query_pool, 0, query_pool.instance().query_count());
+
+        results
+    }
+
+    fn begin_query(&mut self, command_buffer: &mut CommandBuffer, precise: bool) -> Option<u32> {
+        assert!(
+            self.open_query.is_none(),
+            "Tried to begin a query while another one is open."
+        );
+
+        let query_pool = &self.query_pools[self.frame_index];
+        let query = self.used_queries[self.frame_index];
+        if query == query_pool.instance().query_count() {
+            log::warn!("Ran out of queries, ignoring query.");
+            return None;
+        }
+
+        self.used_queries[self.frame_index] += 1;
+        self.open_query = Some(query);
+        command_buffer.begin_query(query_pool, query, precise);
+        Some(query)
+    }
+
+    fn end_query(&mut self, command_buffer: &mut CommandBuffer) {
+        if let Some(query) = self.open_query.take() {
+            command_buffer.end_query(&self.query_pools[self.frame_index], query);
+        }
+    }
+}
+
+/// Occlusion queries counting the samples of the draws between [`OcclusionQuerySet::begin_query`]
+/// and [`OcclusionQuerySet::end_query`] passing the depth and stencil tests, e.g. as feedback for
+/// occlusion culling.
+///
+/// Every frame in flight has its own query pool, whose results are read back by
+/// [`OcclusionQuerySet::begin_frame`] when the frame comes around again, so the results lag
+/// `frames_in_flight` frames behind.
+pub struct OcclusionQuerySet {
+    query_pools: FrameQueryPools,
+    precise: bool,
+    results: Vec<Option<u64>>,
+}
+
+impl OcclusionQuerySet {
+    /// Creates a set with up to `max_queries` queries per frame. Unless `precise` is set the
+    /// queries may only count whether any sample passed instead of the exact number.
+    ///
+    /// # Panics
+    /// If `precise` is set without [`VulkanFeature::OcclusionQueryPrecise`].
+    pub fn new(vulkan: &Vulkan, frames_in_flight: usize, max_queries: u32, precise: bool) -> Self {
+        assert!(
+            !precise || vulkan.is_feature_enabled(VulkanFeature::OcclusionQueryPrecise),
+            "Precise occlusion queries require VulkanFeature::OcclusionQueryPrecise."
+        );
+
+        Self {
+            query_pools: FrameQueryPools::new(frames_in_flight, |_| {
+                QueryPool::new_occlusion(vulkan, max_queries)
+            }),
+            precise,
+            results: Vec::new(),
+        }
+    }
+
+    /// Reads back the results of the last use of `frame_index`, available from
+    /// [`OcclusionQuerySet::results`], and resets its queries.
+    ///
+    /// Must be called after the frame's fence was waited on, in `command_buffer` outside of any
+    /// rendering.
+    pub fn begin_frame(&mut self, frame_index: usize, command_buffer: &mut CommandBuffer) {
+        self.results = self
+            .query_pools
+            .begin_frame(frame_index, command_buffer)
+            .into_iter()
+            .map(|values| values.map(|values| values[0]))
+            .collect();
+    }
+
+    /// Begins counting the samples of the following draws, returning the index of the query in
+    /// the frame's results. Only one query can be open at a time.
+    ///
+    /// Returns `None` with a warning if the frame ran out of queries.
+    pub fn begin_query(&mut self, command_buffer: &mut CommandBuffer) -> Option<u32> {
+        self.query_pools.begin_query(command_buffer, self.precise)
+    }
+
+    /// Ends the open query, does nothing if no query was begun.
+    pub fn end_query(&mut self, command_buffer: &mut CommandBuffer) {
+        self.query_pools.end_query(command_buffer);
+    }
+
+    /// The passing sample counts of the queries read back by the last
+    /// [`OcclusionQuerySet::begin_frame`], indexed by query. `None` for queries whose results
+    /// weren't available.
+    pub fn results(&self) -> &[Option<u64>] {
+        &self.results
+    }
+}
+
+/// The counters of a pipeline statistics query, statistics the query set doesn't count are zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineStatistics {
+    pub input_assembly_vertices: u64,
+    pub input_assembly_primitives: u64,
+    pub vertex_shader_invocations: u64,
+    pub geometry_shader_invocations: u64,
+    pub geometry_shader_primitives: u64,
+    pub clipping_invocations: u64,
+    pub clipping_primitives: u64,
+    pub fragment_shader_invocations: u64,
+    pub tessellation_control_shader_patches: u64,
+    pub tessellation_evaluation_shader_invocations: u64,
+    pub compute_shader_invocations: u64,
+}
+
+impl PipelineStatistics {
+    /// Parses the values of a query counting `statistics`, which are written in the order of
+    /// the statistic bits.
+    fn from_values(statistics: vk::QueryPipelineStatisticFlags, values: &[u64]) -> Self {
+        type Flags = vk::QueryPipelineStatisticFlags;
+
+        let mut pipeline_statistics = Self::default();
+        let fields = [
+            (
+                Flags::INPUT_ASSEMBLY_VERTICES,
+                &mut pipeline_statistics.input_assembly_vertices,
+            ),
+            (
+                Flags::INPUT_ASSEMBLY_PRIMITIVES,
+                &mut pipeline_statistics.input_assembly_primitives,
+            ),
+            (
+                Flags::VERTEX_SHADER_INVOCATIONS,
+                &mut pipeline_statistics.vertex_shader_invocations,
+            ),
+            (
+                Flags::GEOMETRY_SHADER_INVOCATIONS,
+                &mut pipeline_statistics.geometry_shader_invocations,
+            ),
+            (
+                Flags::GEOMETRY_SHADER_PRIMITIVES,
+                &mut pipeline_statistics.geometry_shader_primitives,
+            ),
+            (
+                Flags::CLIPPING_INVOCATIONS,
+                &mut pipeline_statistics.clipping_invocations,
+            ),
+            (
+                Flags::CLIPPING_PRIMITIVES,
+                &mut pipeline_statistics.clipping_primitives,
+            ),
+            (
+                Flags::FRAGMENT_SHADER_INVOCATIONS,
+                &mut pipeline_statistics.fragment_shader_invocations,
+            ),
+            (
+                Flags::TESSELLATION_CONTROL_SHADER_PATCHES,
+                &mut pipeline_statistics.tessellation_control_shader_patches,
+            ),
+            (
+                Flags::TESSELLATION_EVALUATION_SHADER_INVOCATIONS,
+                &mut pipeline_statistics.tessellation_evaluation_shader_invocations,
+            ),
+            (
+                Flags::COMPUTE_SHADER_INVOCATIONS,
+                &mut pipeline_statistics.compute_shader_invocations,
+            ),
+        ];
+
+        let mut values = values.iter();
+        for (flag, field) in fields {
+            if statistics.contains(flag) {
+                *field = *values.next().unwrap();
+            }
+        }
+
+        pipeline_statistics
+    }
+}
+
+/// Pipeline statistics queries counting e.g. the primitives and shader invocations of the
+/// commands between [`PipelineStatsQuerySet::begin_query`] and
+/// [`PipelineStatsQuerySet::end_query`].
+///
+/// Like [`OcclusionQuerySet`] every frame in flight has its own query pool, whose results are
+/// read back by [`PipelineStatsQuerySet::begin_frame`] when the frame comes around again.
+pub struct PipelineStatsQuerySet {
+    query_pools: FrameQueryPools,
+    statistics: vk::QueryPipelineStatisticFlags,
+    results: Vec<Option<PipelineStatistics>>,
+}
+
+impl PipelineStatsQuerySet {
+    /// Creates a set with up to `max_queries` queries per frame, counting `statistics`.
+    ///
+    /// # Panics
+    /// If [`VulkanFeature::PipelineStatisticsQuery`] wasn't enabled or `statistics` is empty.
+    pub fn new(
+        vulkan: &Vulkan,
+        frames_in_flight: usize,
+        max_queries: u32,
+        statistics: vk::QueryPipelineStatisticFlags,
+    ) -> Self {
+        Self {
+            query_pools: FrameQueryPools::new(frames_in_flight, |_| {
+                QueryPool::new_pipeline_statistics(vulkan, max_queries, statistics)
+            }),
+            statistics,
+            results: Vec::new(),
+        }
+    }
+
+    /// Reads back the results of the last use of `frame_index`, available from
+    /// [`PipelineStatsQuerySet::results`], and resets its queries.
+    ///
+    /// Must be called after the frame's fence was waited on, in `command_buffer` outside of any
+    /// rendering.
+    pub fn begin_frame(&mut self, frame_index: usize, command_buffer: &mut CommandBuffer) {
+        let statistics = self.statistics;
+        self.results = self
+            .query_pools
+            .begin_frame(frame_index, command_buffer)
+            .into_iter()
+            .map(|values| values.map(|values| PipelineStatistics::from_values(statistics, &values)))
+            .collect();
+    }
+
+    /// Begins counting the following commands, returning the index of the query in the frame's
+    /// results. Only one query can be open at a time.
+    ///
+    /// Returns `None` with a warning if the frame ran out of queries.
+    pub fn begin_query(&mut self, command_buffer: &mut CommandBuffer) -> Option<u32> {
+        self.query_pools.begin_query(command_buffer, false)
+    }
+
+    /// Ends the open query, does nothing if no query was begun.
+    pub fn end_query(&mut self, command_buffer: &mut CommandBuffer) {
+        self.query_pools.end_query(command_buffer);
+    }
+
+    /// The statistics of the queries read back by the last
+    /// [`PipelineStatsQuerySet::begin_frame`], indexed by query. `None` for queries whose
+    /// results weren't available.
+    pub fn results(&self) -> &[Option<PipelineStatistics>] {
+        &self.results
+    }
+}
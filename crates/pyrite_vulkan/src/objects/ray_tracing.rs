@@ -0,0 +1,348 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{
+    allocator::VulkanMemoryAllocator,
+    util::{GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep, VulkanFeature, VulkanResult,
+};
+
+use super::{
+    acceleration_structure::ray_tracing, Buffer, BufferCreateInfo, DescriptorSetLayout,
+    PipelineLayoutCreateInfo, PipelineLayoutInstance, Shader, UntypedBuffer,
+};
+
+pub type RayTracingPipelineDep = Arc<RayTracingPipelineInstance>;
+
+pub struct RayTracingPipelineInstance {
+    vulkan_dep: VulkanDep,
+    pipeline_layout: PipelineLayoutInstance,
+    pipeline: vk::Pipeline,
+    miss_count: u32,
+    hit_group_count: u32,
+}
+
+impl RayTracingPipelineInstance {
+    pub fn pipeline(&self) -> vk::Pipeline {
+        self.pipeline
+    }
+
+    pub fn pipeline_layout(&self) -> &PipelineLayoutInstance {
+        &self.pipeline_layout
+    }
+
+    pub fn miss_count(&self) -> u32 {
+        self.miss_count
+    }
+
+    pub fn hit_group_count(&self) -> u32 {
+        self.hit_group_count
+    }
+}
+
+impl VulkanResource for RayTracingPipelineInstance {}
+
+impl Drop for RayTracingPipelineInstance {
+    fn drop(&mut self) {
+        unsafe {
+            self.vulkan_dep
+                .device()
+                .destroy_pipeline(self.pipeline, None);
+        }
+    }
+}
+
+/// A triangle hit group, either shader may be skipped.
+#[derive(Default)]
+pub struct RayTracingHitGroup<'a> {
+    pub closest_hit: Option<&'a Shader>,
+    pub any_hit: Option<&'a Shader>,
+}
+
+/// The shaders of a ray tracing pipeline, which all use the `main` entry point.
+///
+/// The shader groups are laid out as the ray generation shader, followed by the miss shaders and
+/// then the hit groups, indexed in that order by `traceRayEXT`'s miss index and the instances'
+/// hit group offsets.
+pub struct RayTracingPipelineCreateInfo<'a> {
+    pub ray_generation: &'a Shader,
+    pub miss: Vec<&'a Shader>,
+    pub hit_groups: Vec<RayTracingHitGroup<'a>>,
+    /// At most [`crate::RayTracingProperties::max_ray_recursion_depth`].
+    pub max_recursion_depth: u32,
+    /// Descriptor set layouts and push constant ranges left empty are derived from the shaders.
+    pub pipeline_layout_info: PipelineLayoutCreateInfo<'a>,
+    /// The name shown in debugging tools.
+    pub name: Option<String>,
+}
+
+pub struct RayTracingPipeline {
+    instance: Arc<RayTracingPipelineInstance>,
+}
+
+impl RayTracingPipeline {
+    /// # Panics
+    /// If the pipeline can't be created, see [`RayTracingPipeline::try_new`].
+    pub fn new(vulkan: &Vulkan, create_info: RayTracingPipelineCreateInfo<'_>) -> Self {
+        Self::try_new(vulkan, create_info)
+            .unwrap_or_else(|err| panic!("Failed to create ray tracing pipeline: {}", err))
+    }
+
+    /// # Panics
+    /// If [`VulkanFeature::RayTracing`] wasn't enabled or the recursion depth exceeds the
+    /// device's limit.
+    pub fn try_new(
+        vulkan: &Vulkan,
+        create_info: RayTracingPipelineCreateInfo<'_>,
+    ) -> VulkanResult<Self> {
+        assert!(
+            vulkan.is_feature_enabled(VulkanFeature::RayTracing),
+            "Ray tracing pipelines require VulkanFeature::RayTracing."
+        );
+        let ray_tracing = ray_tracing(vulkan);
+        let max_ray_recursion_depth = ray_tracing.properties().max_ray_recursion_depth;
+        assert!(
+            create_info.max_recursion_depth <= max_ray_recursion_depth,
+            "The ray recursion depth {} exceeds the device's limit of {}.",
+            create_info.max_recursion_depth,
+            max_ray_recursion_depth
+        );
+
+        let mut stages = vec![(vk::ShaderStageFlags::RAYGEN_KHR, create_info.ray_generation)];
+        let mut groups = vec![general_shader_group(0)];
+        for miss in &create_info.miss {
+            groups.push(general_shader_group(stages.len() as u32));
+            stages.push((vk::ShaderStageFlags::MISS_KHR, *miss));
+        }
+        for hit_group in &create_info.hit_groups {
+            let mut push_stage = |stage, shader: Option<_>| match shader {
+                Some(shader) => {
+                    stages.push((stage, shader));
+                    stages.len() as u32 - 1
+                }
+                None => vk::SHADER_UNUSED_KHR,
+            };
+            let closest_hit =
+                push_stage(vk::ShaderStageFlags::CLOSEST_HIT_KHR, hit_group.closest_hit);
+            let any_hit = push_stage(vk::ShaderStageFlags::ANY_HIT_KHR, hit_group.any_hit);
+            groups.push(
+                vk::RayTracingShaderGroupCreateInfoKHR::default()
+                    .ty(vk::RayTracingShaderGroupTypeKHR::TRIANGLES_HIT_GROUP)
+                    .general_shader(vk::SHADER_UNUSED_KHR)
+                    .closest_hit_shader(closest_hit)
+                    .any_hit_shader(any_hit)
+                    .intersection_shader(vk::SHADER_UNUSED_KHR),
+            );
+        }
+
+        let pipeline_layout = PipelineLayoutInstance::try_new_with_reflection(
+            vulkan,
+            create_info.pipeline_layout_info,
+            &stages
+                .iter()
+                .map(|(stage, shader)| (*stage, shader.reflection()))
+                .collect::<Vec<_>>(),
+        )?;
+
+        let vk_shader_name = std::ffi::CString::new("main").unwrap();
+        let vk_stages = stages
+            .iter()
+            .map(|(stage, shader)| {
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(*stage)
+                    .module(shader.module())
+                    .name(vk_shader_name.as_c_str())
+            })
+            .collect::<Vec<_>>();
+        let vk_create_info = vk::RayTracingPipelineCreateInfoKHR::default()
+            .stages(&vk_stages)
+            .groups(&groups)
+            .max_pipeline_ray_recursion_depth(create_info.max_recursion_depth)
+            .layout(pipeline_layout.layout());
+
+        let pipeline = unsafe {
+            ray_tracing
+                .ray_tracing_pipeline()
+                .create_ray_tracing_pipelines(
+                    vk::DeferredOperationKHR::null(),
+                    vk::PipelineCache::null(),
+                    &[vk_create_info],
+                    None,
+                )
+                .map_err(|(_, result)| result)?[0]
+        };
+        if let Some(name) = &create_info.name {
+            vulkan.set_debug_name(pipeline, name);
+        }
+
+        Ok(Self {
+            instance: Arc::new(RayTracingPipelineInstance {
+                vulkan_dep: vulkan.create_dep(),
+                pipeline_layout,
+                pipeline,
+                miss_count: create_info.miss.len() as u32,
+                hit_group_count: create_info.hit_groups.len() as u32,
+            }),
+        })
+    }
+
+    pub fn instance(&self) -> &RayTracingPipelineInstance {
+        &self.instance
+    }
+
+    /// The descriptor set layouts of the pipeline, including the ones derived from the shaders.
+    pub fn descriptor_set_layouts(&self) -> &[DescriptorSetLayout] {
+        self.instance.pipeline_layout.descriptor_set_layouts()
+    }
+
+    pub fn create_dep(&self) -> RayTracingPipelineDep {
+        self.instance.clone()
+    }
+
+    pub fn create_generic_dep(&self) -> GenericResourceDep {
+        self.instance.clone()
+    }
+}
+
+fn general_shader_group(shader: u32) -> vk::RayTracingShaderGroupCreateInfoKHR<'static> {
+    vk::RayTracingShaderGroupCreateInfoKHR::default()
+        .ty(vk::RayTracingShaderGroupTypeKHR::GENERAL)
+        .general_shader(shader)
+        .closest_hit_shader(vk::SHADER_UNUSED_KHR)
+        .any_hit_shader(vk::SHADER_UNUSED_KHR)
+        .intersection_shader(vk::SHADER_UNUSED_KHR)
+}
+
+/// The shader group handles of a [`RayTracingPipeline`] laid out for
+/// [`super::CommandBuffer::trace_rays`], which keeps the pipeline alive.
+pub struct ShaderBindingTable {
+    pipeline: RayTracingPipelineDep,
+    buffer: UntypedBuffer,
+    ray_generation_region: vk::StridedDeviceAddressRegionKHR,
+    miss_region: vk::StridedDeviceAddressRegionKHR,
+    hit_region: vk::StridedDeviceAddressRegionKHR,
+}
+
+impl ShaderBindingTable {
+    /// # Panics
+    /// If the table can't be created, see [`ShaderBindingTable::try_new`].
+    pub fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        pipeline: &RayTracingPipeline,
+    ) -> Self {
+        Self::try_new(vulkan, vulkan_allocator, pipeline)
+            .unwrap_or_else(|err| panic!("Failed to create shader binding table: {}", err))
+    }
+
+    pub fn try_new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        pipeline: &RayTracingPipeline,
+    ) -> VulkanResult<Self> {
+        let ray_tracing = ray_tracing(vulkan);
+        let properties = ray_tracing.properties();
+        let handle_size = properties.shader_group_handle_size as u64;
+        let handle_stride =
+            handle_size.next_multiple_of(properties.shader_group_handle_alignment as u64);
+        let base_alignment = properties.shader_group_base_alignment as u64;
+
+        let miss_count = pipeline.instance.miss_count as u64;
+        let hit_group_count = pipeline.instance.hit_group_count as u64;
+        let group_count = 1 + miss_count + hit_group_count;
+        let handles = unsafe {
+            ray_tracing
+                .ray_tracing_pipeline()
+                .get_ray_tracing_shader_group_handles(
+                    pipeline.instance.pipeline,
+                    0,
+                    group_count as u32,
+                    (group_count * handle_size) as usize,
+                )?
+        };
+
+        // Each region starts at a multiple of the base alignment, and the ray generation region's
+        // size has to equal its stride.
+        let ray_generation_size = handle_stride.next_multiple_of(base_alignment);
+        let miss_size = (miss_count * handle_stride).next_multiple_of(base_alignment);
+        let hit_size = (hit_group_count * handle_stride).next_multiple_of(base_alignment);
+
+        // The buffer's alignment isn't necessarily the base alignment, so the table is aligned
+        // within a larger buffer.
+        let mut buffer = UntypedBuffer::try_new(
+            vulkan,
+            vulkan_allocator,
+            &BufferCreateInfo {
+                size: ray_generation_size + miss_size + hit_size + base_alignment,
+                usage: vk::BufferUsageFlags::SHADER_BINDING_TABLE_KHR
+                    | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+                memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+                name: Some("shader binding table".to_string()),
+            },
+        )?;
+        let buffer_address = buffer.instance().device_address();
+        let table_offset = buffer_address.next_multiple_of(base_alignment) - buffer_address;
+
+        let region_offsets = [
+            table_offset,
+            table_offset + ray_generation_size,
+            table_offset + ray_generation_size + miss_size,
+        ];
+        let region_group_counts = [1, miss_count, hit_group_count];
+        let mut group = 0;
+        for (region_offset, region_group_count) in region_offsets.iter().zip(region_group_counts) {
+            for index in 0..region_group_count {
+                let handle_start = (group * handle_size) as usize;
+                buffer.write_bytes(
+                    region_offset + index * handle_stride,
+                    &handles[handle_start..handle_start + handle_size as usize],
+                );
+                group += 1;
+            }
+        }
+
+        let region = |offset: u64, stride: u64, size: u64| {
+            if size == 0 {
+                return vk::StridedDeviceAddressRegionKHR::default();
+            }
+            vk::StridedDeviceAddressRegionKHR::default()
+                .device_address(buffer_address + offset)
+                .stride(stride)
+                .size(size)
+        };
+
+        Ok(Self {
+            pipeline: pipeline.create_dep(),
+            ray_generation_region: region(
+                region_offsets[0],
+                ray_generation_size,
+                ray_generation_size,
+            ),
+            miss_region: region(region_offsets[1], handle_stride, miss_size),
+            hit_region: region(region_offsets[2], handle_stride, hit_size),
+            buffer,
+        })
+    }
+
+    pub fn pipeline(&self) -> &RayTracingPipelineInstance {
+        &self.pipeline
+    }
+
+    pub fn ray_generation_region(&self) -> &vk::StridedDeviceAddressRegionKHR {
+        &self.ray_generation_region
+    }
+
+    pub fn miss_region(&self) -> &vk::StridedDeviceAddressRegionKHR {
+        &self.miss_region
+    }
+
+    pub fn hit_region(&self) -> &vk::StridedDeviceAddressRegionKHR {
+        &self.hit_region
+    }
+
+    pub fn buffer(&self) -> &UntypedBuffer {
+        &self.buffer
+    }
+}
@@ -0,0 +1,530 @@
+use std::collections::HashMap;
+
+use ash::vk;
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+const OP_ENTRY_POINT: u32 = 15;
+const OP_EXECUTION_MODE: u32 = 16;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_SPEC_CONSTANT: u32 = 50;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const OP_TYPE_ACCELERATION_STRUCTURE: u32 = 5341;
+
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ROW_MAJOR: u32 = 4;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_CLASS_INPUT: u32 = 1;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+const EXECUTION_MODEL_VERTEX: u32 = 0;
+
+const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+
+/// A descriptor binding declared by a shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReflectedBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    /// The number of descriptors in the binding, `None` for runtime sized arrays.
+    pub count: Option<u32>,
+}
+
+/// An input of a vertex shader, matrices take one input per column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReflectedVertexInput {
+    pub location: u32,
+    pub format: vk::Format,
+    /// The size of the input in bytes.
+    pub size: u32,
+}
+
+/// The interface of a shader read from its SPIR-V, used to derive pipeline layouts and vertex
+/// input state which aren't specified.
+#[derive(Debug, Clone, Default)]
+pub struct ShaderReflection {
+    pub bindings: Vec<ReflectedBinding>,
+    /// The size of the push constant block, `None` if the shader doesn't have one.
+    pub push_constant_size: Option<u32>,
+    /// The inputs of a vertex shader sorted by location, empty for other stages.
+    pub vertex_inputs: Vec<ReflectedVertexInput>,
+    /// The workgroup size of a compute shader, `None` for other stages or if it's set by
+    /// specialization constants.
+    pub local_size: Option<[u32; 3]>,
+}
+
+impl ShaderReflection {
+    pub fn new(code: &[u32]) -> Result<Self, String> {
+        if code.len() < 5 || code[0] != SPIRV_MAGIC {
+            return Err("The code isn't a SPIR-V module".to_string());
+        }
+
+        let mut module = SpirvModule::default();
+        let mut offset = 5;
+        while offset < code.len() {
+            let word_count = (code[offset] >> 16) as usize;
+            let opcode = code[offset] & 0xffff;
+            if word_count == 0 || offset + word_count > code.len() {
+                return Err(format!("Malformed instruction at word {}", offset));
+            }
+
+            module.parse_instruction(opcode, &code[offset + 1..offset + word_count]);
+            offset += word_count;
+        }
+
+        Ok(module.reflect())
+    }
+
+    /// Vertex input state reading every input from one tightly packed, per vertex buffer at
+    /// `binding`, in the order of their locations.
+    pub fn packed_vertex_input(
+        &self,
+        binding: u32,
+    ) -> (
+        Vec<vk::VertexInputBindingDescription>,
+        Vec<vk::VertexInputAttributeDescription>,
+    ) {
+        if self.vertex_inputs.is_empty() {
+            return (Vec::new(), Vec::new());
+        }
+
+        let mut stride = 0;
+        let attributes = self
+            .vertex_inputs
+            .iter()
+            .map(|input| {
+                let attribute = vk::VertexInputAttributeDescription::default()
+                    .location(input.location)
+                    .binding(binding)
+                    .format(input.format)
+                    .offset(stride);
+                stride += input.size;
+                attribute
+            })
+            .collect();
+
+        let bindings = vec![vk::VertexInputBindingDescription::default()
+            .binding(binding)
+            .stride(stride)
+            .input_rate(vk::VertexInputRate::VERTEX)];
+
+        (bindings, attributes)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ScalarKind {
+    Int,
+    Uint,
+    Float,
+}
+
+enum SpirvType {
+    Scalar { kind: ScalarKind, width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, columns: u32 },
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    AccelerationStructure,
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 },
+}
+
+#[derive(Default)]
+struct Decorations {
+    block: bool,
+    buffer_block: bool,
+    built_in: bool,
+    array_stride: Option<u32>,
+    location: Option<u32>,
+    binding: Option<u32>,
+    set: Option<u32>,
+}
+
+#[derive(Default)]
+struct MemberDecorations {
+    offset: u32,
+    matrix_stride: Option<u32>,
+    row_major: bool,
+}
+
+/// The parts of a SPIR-V module needed for reflection, keyed by result id.
+#[derive(Default)]
+struct SpirvModule {
+    execution_model: Option<u32>,
+    local_size: Option<[u32; 3]>,
+    types: HashMap<u32, SpirvType>,
+    constants: HashMap<u32, u32>,
+    /// The pointer type, result id and storage class of every global variable.
+    variables: Vec<(u32, u32, u32)>,
+    decorations: HashMap<u32, Decorations>,
+    member_decorations: HashMap<(u32, u32), MemberDecorations>,
+}
+
+impl SpirvModule {
+    /// Records the instruction, instructions which don't matter for reflection are skipped.
+    fn parse_instruction(&mut self, opcode: u32, operands: &[u32]) {
+        match (opcode, operands) {
+            (OP_ENTRY_POINT, [execution_model, ..]) => {
+                self.execution_model.get_or_insert(*execution_model);
+            }
+            (OP_EXECUTION_MODE, [_, EXECUTION_MODE_LOCAL_SIZE, x, y, z]) => {
+                self.local_size.get_or_insert([*x, *y, *z]);
+            }
+            (OP_TYPE_INT, [id, width, signedness]) => {
+                let kind = if *signedness == 0 {
+                    ScalarKind::Uint
+                } else {
+                    ScalarKind::Int
+                };
+                self.types.insert(
+                    *id,
+                    SpirvType::Scalar {
+                        kind,
+                        width: *width,
+                    },
+                );
+            }
+            (OP_TYPE_FLOAT, [id, width, ..]) => {
+                self.types.insert(
+                    *id,
+                    SpirvType::Scalar {
+                        kind: ScalarKind::Float,
+                        width: *width,
+                    },
+                );
+            }
+            (OP_TYPE_VECTOR, [id, component, count]) => {
+                self.types.insert(
+                    *id,
+                    SpirvType::Vector {
+                        component: *component,
+                        count: *count,
+                    },
+                );
+            }
+            (OP_TYPE_MATRIX, [id, column, columns]) => {
+                self.types.insert(
+                    *id,
+                    SpirvType::Matrix {
+                        column: *column,
+                        columns: *columns,
+                    },
+                );
+            }
+            (OP_TYPE_IMAGE, [id, _, dim, _, _, _, sampled, ..]) => {
+                self.types.insert(
+                    *id,
+                    SpirvType::Image {
+                        dim: *dim,
+                        sampled: *sampled,
+                    },
+                );
+            }
+            (OP_TYPE_SAMPLER, [id]) => {
+                self.types.insert(*id, SpirvType::Sampler);
+            }
+            (OP_TYPE_SAMPLED_IMAGE, [id, _]) => {
+                self.types.insert(*id, SpirvType::SampledImage);
+            }
+            (OP_TYPE_ACCELERATION_STRUCTURE, [id]) => {
+                self.types.insert(*id, SpirvType::AccelerationStructure);
+            }
+            (OP_TYPE_ARRAY, [id, element, length]) => {
+                self.types.insert(
+                    *id,
+                    SpirvType::Array {
+                        element: *element,
+                        length: *length,
+                    },
+                );
+            }
+            (OP_TYPE_RUNTIME_ARRAY, [id, element]) => {
+                self.types
+                    .insert(*id, SpirvType::RuntimeArray { element: *element });
+            }
+            (OP_TYPE_STRUCT, [id, members @ ..]) => {
+                self.types.insert(
+                    *id,
+                    SpirvType::Struct {
+                        members: members.to_vec(),
+                    },
+                );
+            }
+            (OP_TYPE_POINTER, [id, _, pointee]) => {
+                self.types
+                    .insert(*id, SpirvType::Pointer { pointee: *pointee });
+            }
+            // Specialization constants are reflected with their default value.
+            (OP_CONSTANT | OP_SPEC_CONSTANT, [_, id, value, ..]) => {
+                self.constants.insert(*id, *value);
+            }
+            (OP_VARIABLE, [pointer_type, id, storage_class, ..]) => {
+                self.variables.push((*pointer_type, *id, *storage_class));
+            }
+            (OP_DECORATE, [id, decoration, literals @ ..]) => {
+                let decorations = self.decorations.entry(*id).or_default();
+                match (*decoration, literals) {
+                    (DECORATION_BLOCK, _) => decorations.block = true,
+                    (DECORATION_BUFFER_BLOCK, _) => decorations.buffer_block = true,
+                    (DECORATION_BUILT_IN, _) => decorations.built_in = true,
+                    (DECORATION_ARRAY_STRIDE, [stride]) => decorations.array_stride = Some(*stride),
+                    (DECORATION_LOCATION, [location]) => decorations.location = Some(*location),
+                    (DECORATION_BINDING, [binding]) => decorations.binding = Some(*binding),
+                    (DECORATION_DESCRIPTOR_SET, [set]) => decorations.set = Some(*set),
+                    _ => {}
+                }
+            }
+            (OP_MEMBER_DECORATE, [id, member, decoration, literals @ ..]) => {
+                let decorations = self
+                    .member_decorations
+                    .entry((*id, *member))
+                    .or_default();
+                match (*decoration, literals) {
+                    (DECORATION_OFFSET, [offset]) => decorations.offset = *offset,
+                    (DECORATION_MATRIX_STRIDE, [stride]) => {
+                        decorations.matrix_stride = Some(*stride)
+                    }
+                    (DECORATION_ROW_MAJOR, _) => decorations.row_major = true,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn reflect(&self) -> ShaderReflection {
+        let mut reflection = ShaderReflection {
+            local_size: self.local_size,
+            ..Default::default()
+        };
+        let is_vertex_shader = self.execution_model == Some(EXECUTION_MODEL_VERTEX);
+
+        for (pointer_type, id, storage_class) in &self.variables {
+            let Some(SpirvType::Pointer { pointee }) = self.types.get(pointer_type) else {
+                continue;
+            };
+            let decorations = self.decorations.get(id);
+
+            match *storage_class {
+                STORAGE_CLASS_PUSH_CONSTANT => {
+                    // Push constant ranges must be a multiple of 4 bytes.
+                    let size = self.size_of(*pointee).next_multiple_of(4);
+                    reflection.push_constant_size = Some(size);
+                }
+                STORAGE_CLASS_INPUT if is_vertex_shader => {
+                    let Some(location) = decorations
+                        .filter(|decorations| !decorations.built_in)
+                        .and_then(|decorations| decorations.location)
+                    else {
+                        continue;
+                    };
+                    reflection
+                        .vertex_inputs
+                        .extend(self.vertex_inputs(*pointee, location));
+                }
+                storage_class => {
+                    let (Some(set), Some(binding)) = (
+                        decorations.and_then(|decorations| decorations.set),
+                        decorations.and_then(|decorations| decorations.binding),
+                    ) else {
+                        continue;
+                    };
+                    let Some((descriptor_type, count)) =
+                        self.descriptor_type(*pointee, storage_class)
+                    else {
+                        continue;
+                    };
+
+                    reflection.bindings.push(ReflectedBinding {
+                        set,
+                        binding,
+                        descriptor_type,
+                        count,
+                    });
+                }
+            }
+        }
+
+        reflection.bindings.sort_by_key(|binding| (binding.set, binding.binding));
+        reflection.vertex_inputs.sort_by_key(|input| input.location);
+        reflection
+    }
+
+    /// The descriptor type and count of a variable, `None` if it isn't a descriptor.
+    fn descriptor_type(
+        &self,
+        type_id: u32,
+        storage_class: u32,
+    ) -> Option<(vk::DescriptorType, Option<u32>)> {
+        let (type_id, count) = match self.types.get(&type_id)? {
+            SpirvType::Array { element, length } => (*element, Some(self.constant(*length))),
+            SpirvType::RuntimeArray { element } => (*element, None),
+            _ => (type_id, Some(1)),
+        };
+        let is_buffer_block = self
+            .decorations
+            .get(&type_id)
+            .is_some_and(|decorations| decorations.buffer_block);
+
+        let descriptor_type = match (storage_class, self.types.get(&type_id)?) {
+            (STORAGE_CLASS_UNIFORM_CONSTANT, SpirvType::Sampler) => vk::DescriptorType::SAMPLER,
+            (STORAGE_CLASS_UNIFORM_CONSTANT, SpirvType::SampledImage) => {
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+            }
+            (STORAGE_CLASS_UNIFORM_CONSTANT, SpirvType::Image { dim, sampled }) => {
+                match (*dim, *sampled) {
+                    (DIM_SUBPASS_DATA, _) => vk::DescriptorType::INPUT_ATTACHMENT,
+                    (DIM_BUFFER, 2) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+                    (DIM_BUFFER, _) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                    (_, 2) => vk::DescriptorType::STORAGE_IMAGE,
+                    _ => vk::DescriptorType::SAMPLED_IMAGE,
+                }
+            }
+            (STORAGE_CLASS_UNIFORM_CONSTANT, SpirvType::AccelerationStructure) => {
+                vk::DescriptorType::ACCELERATION_STRUCTURE_KHR
+            }
+            (STORAGE_CLASS_UNIFORM, SpirvType::Struct { .. }) if is_buffer_block => {
+                vk::DescriptorType::STORAGE_BUFFER
+            }
+            (STORAGE_CLASS_UNIFORM, SpirvType::Struct { .. }) => {
+                vk::DescriptorType::UNIFORM_BUFFER
+            }
+            (STORAGE_CLASS_STORAGE_BUFFER, _) => vk::DescriptorType::STORAGE_BUFFER,
+            _ => return None,
+        };
+
+        Some((descriptor_type, count))
+    }
+
+    /// The inputs of a vertex shader variable starting at `location`, skipping types which
+    /// don't map to a 32 bit vertex format.
+    fn vertex_inputs(&self, type_id: u32, location: u32) -> Vec<ReflectedVertexInput> {
+        let (column, columns) = match self.types.get(&type_id) {
+            Some(SpirvType::Matrix { column, columns }) => (*column, *columns),
+            _ => (type_id, 1),
+        };
+        let Some((format, size)) = self.vertex_format(column) else {
+            return Vec::new();
+        };
+
+        (0..columns)
+            .map(|column| ReflectedVertexInput {
+                location: location + column,
+                format,
+                size,
+            })
+            .collect()
+    }
+
+    fn vertex_format(&self, type_id: u32) -> Option<(vk::Format, u32)> {
+        let (component, count) = match self.types.get(&type_id)? {
+            SpirvType::Scalar { .. } => (type_id, 1),
+            SpirvType::Vector { component, count } => (*component, *count),
+            _ => return None,
+        };
+        let SpirvType::Scalar { kind, width: 32 } = self.types.get(&component)? else {
+            return None;
+        };
+
+        let format = match (kind, count) {
+            (ScalarKind::Float, 1) => vk::Format::R32_SFLOAT,
+            (ScalarKind::Float, 2) => vk::Format::R32G32_SFLOAT,
+            (ScalarKind::Float, 3) => vk::Format::R32G32B32_SFLOAT,
+            (ScalarKind::Float, 4) => vk::Format::R32G32B32A32_SFLOAT,
+            (ScalarKind::Int, 1) => vk::Format::R32_SINT,
+            (ScalarKind::Int, 2) => vk::Format::R32G32_SINT,
+            (ScalarKind::Int, 3) => vk::Format::R32G32B32_SINT,
+            (ScalarKind::Int, 4) => vk::Format::R32G32B32A32_SINT,
+            (ScalarKind::Uint, 1) => vk::Format::R32_UINT,
+            (ScalarKind::Uint, 2) => vk::Format::R32G32_UINT,
+            (ScalarKind::Uint, 3) => vk::Format::R32G32B32_UINT,
+            (ScalarKind::Uint, 4) => vk::Format::R32G32B32A32_UINT,
+            _ => return None,
+        };
+
+        Some((format, 4 * count))
+    }
+
+    /// The size of a type in bytes following its explicit layout decorations.
+    fn size_of(&self, type_id: u32) -> u32 {
+        match self.types.get(&type_id) {
+            Some(SpirvType::Scalar { width, .. }) => width / 8,
+            Some(SpirvType::Vector { component, count }) => self.size_of(*component) * count,
+            Some(SpirvType::Matrix { column, columns }) => self.size_of(*column) * columns,
+            Some(SpirvType::Array { element, length }) => {
+                let stride = self
+                    .decorations
+                    .get(&type_id)
+                    .and_then(|decorations| decorations.array_stride)
+                    .unwrap_or_else(|| self.size_of(*element));
+                stride * self.constant(*length)
+            }
+            Some(SpirvType::Struct { members }) => members
+                .iter()
+                .enumerate()
+                .map(|(index, member)| self.member_end(type_id, index as u32, *member))
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// The offset of the end of a struct member, matrices are sized by their matrix stride.
+    fn member_end(&self, struct_id: u32, index: u32, member_type: u32) -> u32 {
+        let Some(decorations) = self.member_decorations.get(&(struct_id, index)) else {
+            return self.size_of(member_type);
+        };
+
+        let size = match (self.types.get(&member_type), decorations.matrix_stride) {
+            (Some(SpirvType::Matrix { column, columns }), Some(stride)) => {
+                if decorations.row_major {
+                    match self.types.get(column) {
+                        Some(SpirvType::Vector { count, .. }) => stride * count,
+                        _ => stride * columns,
+                    }
+                } else {
+                    stride * columns
+                }
+            }
+            _ => self.size_of(member_type),
+        };
+
+        decorations.offset + size
+    }
+
+    fn constant(&self, id: u32) -> u32 {
+        self.constants.get(&id).copied().unwrap_or(1)
+    }
+}
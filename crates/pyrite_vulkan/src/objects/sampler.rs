@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{
+    util::{GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep,
+};
+
+pub type SamplerDep = Arc<SamplerInstance>;
+
+pub struct SamplerInstance {
+    vulkan_dep: VulkanDep,
+    sampler: vk::Sampler,
+}
+
+impl SamplerInstance {
+    pub fn sampler(&self) -> vk::Sampler {
+        self.sampler
+    }
+}
+
+impl VulkanResource for SamplerInstance {}
+
+impl Drop for SamplerInstance {
+    fn drop(&mut self) {
+        unsafe {
+            self.vulkan_dep.device().destroy_sampler(self.sampler, None);
+        }
+    }
+}
+
+pub struct SamplerCreateInfo {
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    pub address_mode: vk::SamplerAddressMode,
+    /// The max anisotropy, anisotropic filtering is disabled if `None`. Requires
+    /// [`crate::VulkanFeature::SamplerAnisotropy`].
+    pub max_anisotropy: Option<f32>,
+}
+
+impl Default for SamplerCreateInfo {
+    fn default() -> Self {
+        Self {
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_mode: vk::SamplerAddressMode::REPEAT,
+            max_anisotropy: None,
+        }
+    }
+}
+
+pub struct Sampler {
+    instance: Arc<SamplerInstance>,
+}
+
+impl Sampler {
+    pub fn new(vulkan: &Vulkan, info: &SamplerCreateInfo) -> Self {
+        let sampler_create_info = vk::SamplerCreateInfo::default()
+            .mag_filter(info.mag_filter)
+            .min_filter(info.min_filter)
+            .mipmap_mode(info.mipmap_mode)
+            .address_mode_u(info.address_mode)
+            .address_mode_v(info.address_mode)
+            .address_mode_w(info.address_mode)
+            .anisotropy_enable(info.max_anisotropy.is_some())
+            .max_anisotropy(info.max_anisotropy.unwrap_or(1.0))
+            .max_lod(vk::LOD_CLAMP_NONE);
+
+        let sampler = unsafe {
+            vulkan
+                .device()
+                .create_sampler(&sampler_create_info, None)
+                .expect("Failed to create sampler")
+        };
+
+        Self {
+            instance: Arc::new(SamplerInstance {
+                vulkan_dep: vulkan.create_dep(),
+                sampler,
+            }),
+        }
+    }
+
+    pub fn sampler(&self) -> vk::Sampler {
+        self.instance.sampler
+    }
+
+    pub fn create_dep(&self) -> SamplerDep {
+        self.instance.clone()
+    }
+
+    pub fn create_generic_dep(&self) -> GenericResourceDep {
+        self.instance.clone()
+    }
+}
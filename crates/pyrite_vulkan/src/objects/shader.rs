@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
 use ash::vk;
+use pyrite_asset::loaders::shader::SpirvShader;
 
-use crate::{util::VulkanResource, Vulkan, VulkanDep};
+use crate::{util::VulkanResource, Vulkan, VulkanDep, VulkanError, VulkanResult};
+
+use super::ShaderReflection;
 
 pub type ShaderDep = Arc<ShaderInstance>;
 
@@ -31,29 +34,48 @@ impl Drop for ShaderInstance {
 
 pub struct Shader {
     instance: Arc<ShaderInstance>,
+    reflection: ShaderReflection,
 }
 
 impl Shader {
+    /// # Panics
+    /// If the code isn't a valid SPIR-V module, see [`Shader::try_new`].
     pub fn new(vulkan: &Vulkan, code: &[u32]) -> Self {
+        Self::try_new(vulkan, code).unwrap_or_else(|err| panic!("Failed to create shader: {}", err))
+    }
+
+    pub fn try_new(vulkan: &Vulkan, code: &[u32]) -> VulkanResult<Self> {
+        let reflection = ShaderReflection::new(code).map_err(VulkanError::InvalidShader)?;
         let module = unsafe {
             vulkan
                 .device()
-                .create_shader_module(&vk::ShaderModuleCreateInfo::default().code(code), None)
-                .unwrap()
+                .create_shader_module(&vk::ShaderModuleCreateInfo::default().code(code), None)?
         };
 
-        Self {
+        Ok(Self {
             instance: Arc::new(ShaderInstance {
                 vulkan_dep: vulkan.create_dep(),
                 module,
             }),
-        }
+            reflection,
+        })
+    }
+
+    /// Creates the shader module of a shader compiled by the shader loader, the entry point is
+    /// passed to the pipeline separately.
+    pub fn from_spirv(vulkan: &Vulkan, shader: &SpirvShader) -> Self {
+        Self::new(vulkan, &shader.code)
     }
 
     pub fn module(&self) -> vk::ShaderModule {
         self.instance.module()
     }
 
+    /// The descriptor bindings, push constants and vertex inputs of the shader.
+    pub fn reflection(&self) -> &ShaderReflection {
+        &self.reflection
+    }
+
     pub fn create_dep(&self) -> ShaderDep {
         self.instance.clone()
     }
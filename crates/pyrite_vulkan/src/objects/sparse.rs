@@ -0,0 +1,782 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ash::vk;
+
+use crate::{
+    allocator::{MemoryAllocation, VulkanAllocationInfo, VulkanMemoryAllocator},
+    util::{GenericResourceDep, VulkanResource},
+    Vulkan, VulkanDep, VulkanError, VulkanFeature, VulkanResult,
+};
+
+use super::{util::ImageViewCreateInfo, Fence, Image, ImageDep, ImageInstance, Semaphore};
+
+/// A page of a [`SparseImage`], the unit memory is bound in. `x` and `y` count pages, not texels.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SparsePage {
+    pub mip_level: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl SparsePage {
+    /// Packs the page into 32 bits, e.g. for feedback written by shaders: the mip level in the
+    /// top 4 bits followed by 14 bits each for `x` and `y`.
+    pub fn pack(&self) -> u32 {
+        debug_assert!(self.mip_level < 1 << 4 && self.x < 1 << 14 && self.y < 1 << 14);
+        self.mip_level << 28 | self.x << 14 | self.y
+    }
+
+    pub fn unpack(packed: u32) -> Self {
+        Self {
+            mip_level: packed >> 28,
+            x: (packed >> 14) & 0x3fff,
+            y: packed & 0x3fff,
+        }
+    }
+
+    /// The page covering this page's texels in the next coarser mip level.
+    pub fn parent(&self) -> Self {
+        Self {
+            mip_level: self.mip_level + 1,
+            x: self.x / 2,
+            y: self.y / 2,
+        }
+    }
+}
+
+pub struct SparseImageInstance {
+    vulkan_dep: VulkanDep,
+    image: vk::Image,
+    image_view: Option<vk::ImageView>,
+    /// The memory of the mip tail, bound for the lifetime of the image.
+    _mip_tail_allocation: Option<MemoryAllocation>,
+}
+
+impl ImageInstance for SparseImageInstance {
+    fn image(&self) -> vk::Image {
+        self.image
+    }
+
+    fn image_view(&self) -> Option<vk::ImageView> {
+        self.image_view
+    }
+}
+
+impl VulkanResource for SparseImageInstance {}
+
+impl Drop for SparseImageInstance {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(image_view) = self.image_view {
+                self.vulkan_dep
+                    .device()
+                    .destroy_image_view(image_view, None);
+            }
+            self.vulkan_dep.device().destroy_image(self.image, None);
+        }
+    }
+}
+
+pub struct SparseImageCreateInfo {
+    pub width: u32,
+    pub height: u32,
+    pub format: vk::Format,
+    pub usage: vk::ImageUsageFlags,
+    /// The number of mip levels, at least 1.
+    pub mip_levels: u32,
+    pub view_create_info: Option<ImageViewCreateInfo>,
+    /// The queue memory binding operations are submitted to, which needs
+    /// [`crate::QueueCapability::SparseBinding`].
+    pub bind_queue: String,
+    /// The name shown in debugging tools, also used for the image view.
+    pub name: Option<String>,
+}
+
+/// The pages to change in [`SparseImage::update_bindings`].
+pub struct SparseBindInfo<'a> {
+    /// Pages to allocate memory for and bind, resident pages are skipped.
+    pub bind: &'a [SparsePage],
+    /// Pages to unbind, pages that aren't resident are skipped.
+    pub unbind: &'a [SparsePage],
+    pub wait_semaphores: Vec<&'a Semaphore>,
+    pub signal_semaphores: Vec<&'a Semaphore>,
+    pub fence: Option<&'a Fence>,
+}
+
+/// A partially resident 2D image whose memory is bound page by page, for textures too large to
+/// keep in memory like terrain or megatextures.
+///
+/// The mip levels past [`SparseImage::mip_tail_first_lod`] form the mip tail, which is smaller
+/// than a page and always resident. Reading a page that isn't resident returns undefined values,
+/// so shaders should only sample mip levels known to be resident, e.g. with the feedback driving
+/// a [`SparseResidencyManager`].
+pub struct SparseImage {
+    instance: Arc<SparseImageInstance>,
+    width: u32,
+    height: u32,
+    mip_levels: u32,
+    bind_queue: String,
+    page_extent: vk::Extent3D,
+    page_size: u64,
+    memory_type_bits: u32,
+    mip_tail_first_lod: u32,
+    resident_pages: HashMap<SparsePage, MemoryAllocation>,
+}
+
+impl SparseImage {
+    /// # Panics
+    /// If the image can't be created, see [`SparseImage::try_new`].
+    pub fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: &SparseImageCreateInfo,
+    ) -> Self {
+        Self::try_new(vulkan, vulkan_allocator, info)
+            .unwrap_or_else(|err| panic!("Failed to create sparse image: {}", err))
+    }
+
+    /// Creates the image with only its mip tail resident, waiting until the mip tail is bound.
+    ///
+    /// Returns [`VulkanError::Vk`] with `ERROR_FORMAT_NOT_SUPPORTED` if the format and usage
+    /// can't be sparse.
+    ///
+    /// # Panics
+    /// If [`VulkanFeature::SparseResidencyImage2D`] wasn't enabled, or the bind queue doesn't
+    /// exist or can't bind sparse memory.
+    pub fn try_new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: &SparseImageCreateInfo,
+    ) -> VulkanResult<Self> {
+        assert!(
+            vulkan.is_feature_enabled(VulkanFeature::SparseResidencyImage2D),
+            "Sparse images require VulkanFeature::SparseResidencyImage2D."
+        );
+        let bind_queue = vulkan
+            .queue(&info.bind_queue)
+            .unwrap_or_else(|| panic!("The queue {} doesn't exist.", info.bind_queue));
+        assert!(
+            vulkan.physical_device().queue_families()[bind_queue.queue_family_index() as usize]
+                .queue_flags
+                .contains(vk::QueueFlags::SPARSE_BINDING),
+            "The queue {} can't bind sparse memory.",
+            info.bind_queue
+        );
+
+        let format_properties = unsafe {
+            vulkan
+                .instance()
+                .get_physical_device_sparse_image_format_properties(
+                    vulkan.physical_device().physical_device(),
+                    info.format,
+                    vk::ImageType::TYPE_2D,
+                    vk::SampleCountFlags::TYPE_1,
+                    info.usage,
+                    vk::ImageTiling::OPTIMAL,
+                )
+        };
+        if format_properties.is_empty() {
+            return Err(VulkanError::Vk(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+        }
+
+        let image_create_info = vk::ImageCreateInfo::default()
+            .flags(vk::ImageCreateFlags::SPARSE_BINDING | vk::ImageCreateFlags::SPARSE_RESIDENCY)
+            .image_type(vk::ImageType::TYPE_2D)
+            .extent(vk::Extent3D {
+                width: info.width,
+                height: info.height,
+                depth: 1,
+            })
+            .mip_levels(info.mip_levels)
+            .array_layers(1)
+            .format(info.format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(info.usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .samples(vk::SampleCountFlags::TYPE_1);
+
+        let image = unsafe { vulkan.device().create_image(&image_create_info, None)? };
+
+        // The image is destroyed if anything after creating it fails, the mip tail memory is
+        // freed when dropped.
+        let (page_requirements, mip_tail_allocation) =
+            match Self::bind_mip_tail(vulkan, vulkan_allocator, info, image) {
+                Ok(mip_tail) => mip_tail,
+                Err(err) => {
+                    unsafe { vulkan.device().destroy_image(image, None) };
+                    return Err(err);
+                }
+            };
+
+        let image_view = match &info.view_create_info {
+            Some(view_create_info) => {
+                let image_view_create_info = vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(view_create_info.view_type)
+                    .format(info.format)
+                    .subresource_range(view_create_info.subresource_range);
+
+                let image_view = unsafe {
+                    vulkan
+                        .device()
+                        .create_image_view(&image_view_create_info, None)
+                };
+                match image_view {
+                    Ok(image_view) => Some(image_view),
+                    Err(result) => {
+                        unsafe { vulkan.device().destroy_image(image, None) };
+                        return Err(result.into());
+                    }
+                }
+            }
+            None => None,
+        };
+
+        if let Some(name) = &info.name {
+            vulkan.set_debug_name(image, name);
+            if let Some(image_view) = image_view {
+                vulkan.set_debug_name(image_view, &format!("{} view", name));
+            }
+        }
+
+        Ok(Self {
+            instance: Arc::new(SparseImageInstance {
+                vulkan_dep: vulkan.create_dep(),
+                image,
+                image_view,
+                _mip_tail_allocation: mip_tail_allocation,
+            }),
+            width: info.width,
+            height: info.height,
+            mip_levels: info.mip_levels,
+            bind_queue: info.bind_queue.clone(),
+            page_extent: page_requirements.page_extent,
+            page_size: page_requirements.page_size,
+            memory_type_bits: page_requirements.memory_type_bits,
+            mip_tail_first_lod: page_requirements.mip_tail_first_lod,
+            resident_pages: HashMap::new(),
+        })
+    }
+
+    /// Queries the page layout of the image and binds memory to its mip tail, if it has one.
+    fn bind_mip_tail(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: &SparseImageCreateInfo,
+        image: vk::Image,
+    ) -> VulkanResult<(PageRequirements, Option<MemoryAllocation>)> {
+        let memory_requirements = unsafe { vulkan.device().get_image_memory_requirements(image) };
+        let sparse_requirements =
+            unsafe { vulkan.device().get_image_sparse_memory_requirements(image) }
+                .into_iter()
+                .find(|requirements| {
+                    requirements
+                        .format_properties
+                        .aspect_mask
+                        .contains(vk::ImageAspectFlags::COLOR)
+                })
+                .ok_or(VulkanError::Vk(vk::Result::ERROR_FORMAT_NOT_SUPPORTED))?;
+
+        let page_requirements = PageRequirements {
+            page_extent: sparse_requirements.format_properties.image_granularity,
+            page_size: memory_requirements.alignment,
+            memory_type_bits: memory_requirements.memory_type_bits,
+            mip_tail_first_lod: sparse_requirements
+                .image_mip_tail_first_lod
+                .min(info.mip_levels),
+        };
+        if page_requirements.mip_tail_first_lod == info.mip_levels {
+            return Ok((page_requirements, None));
+        }
+
+        let allocation = vulkan_allocator.try_allocate(&VulkanAllocationInfo {
+            size: sparse_requirements.image_mip_tail_size,
+            alignment: memory_requirements.alignment,
+            memory_proprties: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            memory_type_bits: memory_requirements.memory_type_bits,
+            linear: false,
+        })?;
+        let memory_binds = [vk::SparseMemoryBind {
+            resource_offset: sparse_requirements.image_mip_tail_offset,
+            size: sparse_requirements.image_mip_tail_size,
+            memory: allocation.instance().device_memory(),
+            memory_offset: allocation.instance().offset(),
+            flags: vk::SparseMemoryBindFlags::empty(),
+        }];
+        let opaque_bind_infos = [vk::SparseImageOpaqueMemoryBindInfo::default()
+            .image(image)
+            .binds(&memory_binds)];
+        let bind_sparse_info = vk::BindSparseInfo::default().image_opaque_binds(&opaque_bind_infos);
+
+        // Only happens once per image, so waiting keeps the mip tail simple to use.
+        let fence = Fence::new(vulkan, false);
+        unsafe {
+            vulkan.device().queue_bind_sparse(
+                vulkan.queue(&info.bind_queue).unwrap().queue(),
+                &[bind_sparse_info],
+                fence.fence(),
+            )?;
+        }
+        fence.wait();
+
+        Ok((page_requirements, Some(allocation)))
+    }
+
+    /// Binds and unbinds pages in one sparse binding operation on the bind queue.
+    ///
+    /// Binding operations aren't ordered with other submissions, even on the same queue, so work
+    /// sampling the image must wait for a signalled semaphore and the binding must wait for the
+    /// work sampling pages about to be unbound.
+    ///
+    /// Returns the memory of the unbound pages, which must be kept alive until the binding
+    /// finished executing, e.g. in the [`crate::util::FrameResourceArena`] of the frame.
+    pub fn update_bindings(
+        &mut self,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        info: SparseBindInfo,
+    ) -> VulkanResult<Vec<GenericResourceDep>> {
+        // Allocate everything first so running out of memory leaves the image unchanged.
+        let mut bound_allocations = Vec::<(SparsePage, MemoryAllocation)>::new();
+        for page in info.bind {
+            if self.resident_pages.contains_key(page)
+                || bound_allocations
+                    .iter()
+                    .any(|(bound_page, _)| bound_page == page)
+            {
+                continue;
+            }
+            assert!(
+                self.contains_page(page),
+                "The page {:?} is outside the image's paged mip levels.",
+                page
+            );
+
+            let allocation = vulkan_allocator.try_allocate(&VulkanAllocationInfo {
+                size: self.page_size,
+                alignment: self.page_size,
+                memory_proprties: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                memory_type_bits: self.memory_type_bits,
+                linear: false,
+            })?;
+            bound_allocations.push((*page, allocation));
+        }
+
+        let mut memory_binds = Vec::new();
+        let mut unbound_allocations = Vec::new();
+        for page in info.unbind {
+            if let Some(allocation) = self.resident_pages.remove(page) {
+                memory_binds.push(self.page_memory_bind(page, vk::DeviceMemory::null(), 0));
+                unbound_allocations.push(allocation.create_generic_dep());
+            }
+        }
+        let mut bound_pages = Vec::new();
+        for (page, allocation) in bound_allocations {
+            memory_binds.push(self.page_memory_bind(
+                &page,
+                allocation.instance().device_memory(),
+                allocation.instance().offset(),
+            ));
+            bound_pages.push(page);
+            self.resident_pages.insert(page, allocation);
+        }
+
+        let image_bind_infos = [vk::SparseImageMemoryBindInfo::default()
+            .image(self.instance.image)
+            .binds(&memory_binds)];
+        let vk_wait_semaphores = info
+            .wait_semaphores
+            .iter()
+            .map(|semaphore| semaphore.semaphore())
+            .collect::<Vec<_>>();
+        let vk_signal_semaphores = info
+            .signal_semaphores
+            .iter()
+            .map(|semaphore| semaphore.semaphore())
+            .collect::<Vec<_>>();
+        let mut bind_sparse_info = vk::BindSparseInfo::default()
+            .wait_semaphores(&vk_wait_semaphores)
+            .signal_semaphores(&vk_signal_semaphores);
+        if !memory_binds.is_empty() {
+            bind_sparse_info = bind_sparse_info.image_binds(&image_bind_infos);
+        }
+
+        let vulkan_dep = &self.instance.vulkan_dep;
+        let result = unsafe {
+            vulkan_dep.device().queue_bind_sparse(
+                vulkan_dep.queue(&self.bind_queue).unwrap().queue(),
+                &[bind_sparse_info],
+                info.fence.map_or(vk::Fence::null(), |fence| fence.fence()),
+            )
+        };
+        if let Err(result) = result {
+            // The bindings are in an unknown state, so forget the pages bound by this call.
+            for page in bound_pages {
+                self.resident_pages.remove(&page);
+            }
+            return Err(result.into());
+        }
+
+        Ok(unbound_allocations)
+    }
+
+    fn page_memory_bind(
+        &self,
+        page: &SparsePage,
+        memory: vk::DeviceMemory,
+        memory_offset: u64,
+    ) -> vk::SparseImageMemoryBind {
+        let (mip_width, mip_height) = self.mip_extent(page.mip_level);
+        let offset = vk::Offset3D {
+            x: (page.x * self.page_extent.width) as i32,
+            y: (page.y * self.page_extent.height) as i32,
+            z: 0,
+        };
+
+        vk::SparseImageMemoryBind {
+            subresource: vk::ImageSubresource {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: page.mip_level,
+                array_layer: 0,
+            },
+            offset,
+            // Pages on the right and bottom edge may be cut off by the mip level's extent.
+            extent: vk::Extent3D {
+                width: self.page_extent.width.min(mip_width - offset.x as u32),
+                height: self.page_extent.height.min(mip_height - offset.y as u32),
+                depth: 1,
+            },
+            memory,
+            memory_offset,
+            flags: vk::SparseMemoryBindFlags::empty(),
+        }
+    }
+
+    fn mip_extent(&self, mip_level: u32) -> (u32, u32) {
+        (
+            (self.width >> mip_level).max(1),
+            (self.height >> mip_level).max(1),
+        )
+    }
+
+    /// The number of pages in each dimension of `mip_level`, zero for the mip tail.
+    pub fn page_counts(&self, mip_level: u32) -> (u32, u32) {
+        if mip_level >= self.mip_tail_first_lod {
+            return (0, 0);
+        }
+
+        let (mip_width, mip_height) = self.mip_extent(mip_level);
+        (
+            mip_width.div_ceil(self.page_extent.width),
+            mip_height.div_ceil(self.page_extent.height),
+        )
+    }
+
+    /// Whether the page is in one of the mip levels before the mip tail.
+    pub fn contains_page(&self, page: &SparsePage) -> bool {
+        let (pages_x, pages_y) = self.page_counts(page.mip_level);
+        page.x < pages_x && page.y < pages_y
+    }
+
+    pub fn is_resident(&self, page: &SparsePage) -> bool {
+        self.resident_pages.contains_key(page)
+    }
+
+    pub fn resident_pages(&self) -> impl Iterator<Item = &SparsePage> {
+        self.resident_pages.keys()
+    }
+
+    pub fn resident_page_count(&self) -> usize {
+        self.resident_pages.len()
+    }
+
+    /// The texel extent of a page, which is also the unit of copies into a page.
+    pub fn page_extent(&self) -> vk::Extent3D {
+        self.page_extent
+    }
+
+    /// The number of bytes of memory bound per page.
+    pub fn page_size(&self) -> u64 {
+        self.page_size
+    }
+
+    /// The first mip level in the always resident mip tail, equal to the mip level count if the
+    /// image has no mip tail.
+    pub fn mip_tail_first_lod(&self) -> u32 {
+        self.mip_tail_first_lod
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+}
+
+struct PageRequirements {
+    page_extent: vk::Extent3D,
+    page_size: u64,
+    memory_type_bits: u32,
+    mip_tail_first_lod: u32,
+}
+
+impl Image for SparseImage {
+    fn instance(&self) -> &dyn ImageInstance {
+        self.instance.as_ref()
+    }
+
+    fn create_dep(&self) -> ImageDep {
+        self.instance.clone()
+    }
+
+    fn create_generic_dep(&self) -> GenericResourceDep {
+        self.instance.clone()
+    }
+}
+
+/// The pages a [`SparseResidencyManager`] decided to change, to apply with
+/// [`SparseImage::update_bindings`] before streaming the texels of the bound pages.
+#[derive(Default)]
+pub struct SparseResidencyUpdate {
+    pub bind: Vec<SparsePage>,
+    pub unbind: Vec<SparsePage>,
+}
+
+/// The page layout and residency the [`SparseResidencyManager`] decides on, separate from the
+/// [`SparseImage`] so the bookkeeping doesn't need a device.
+trait PageTable {
+    fn contains_page(&self, page: &SparsePage) -> bool;
+    fn is_resident(&self, page: &SparsePage) -> bool;
+    fn resident_pages(&self) -> impl Iterator<Item = &SparsePage>;
+    fn resident_page_count(&self) -> usize;
+}
+
+impl PageTable for SparseImage {
+    fn contains_page(&self, page: &SparsePage) -> bool {
+        SparseImage::contains_page(self, page)
+    }
+
+    fn is_resident(&self, page: &SparsePage) -> bool {
+        SparseImage::is_resident(self, page)
+    }
+
+    fn resident_pages(&self) -> impl Iterator<Item = &SparsePage> {
+        SparseImage::resident_pages(self)
+    }
+
+    fn resident_page_count(&self) -> usize {
+        SparseImage::resident_page_count(self)
+    }
+}
+
+/// Decides which pages of a [`SparseImage`] are resident from the pages the renderer reported
+/// needing, e.g. decoded from a feedback buffer with [`SparsePage::unpack`].
+///
+/// Requesting a page also requests the pages covering it in every coarser mip level, so
+/// sampling can fall back to a coarser resident mip level while finer pages stream in. Once the
+/// budget is exceeded the least recently requested pages are evicted, pages requested since the
+/// last update are never evicted.
+pub struct SparseResidencyManager {
+    max_resident_pages: usize,
+    max_binds_per_update: usize,
+    /// The update each page was last requested in, for resident and requested pages.
+    last_requested: HashMap<SparsePage, u64>,
+    update_index: u64,
+}
+
+impl SparseResidencyManager {
+    /// Creates a manager keeping at most `max_resident_pages` pages resident, binding at most
+    /// `max_binds_per_update` pages per update to bound the streaming cost of a frame.
+    pub fn new(max_resident_pages: usize, max_binds_per_update: usize) -> Self {
+        Self {
+            max_resident_pages,
+            max_binds_per_update,
+            last_requested: HashMap::new(),
+            update_index: 0,
+        }
+    }
+
+    /// Marks the pages and their coarser parents as needed, pages outside the paged mip levels
+    /// of `image` are ignored.
+    pub fn request_pages(
+        &mut self,
+        image: &SparseImage,
+        pages: impl IntoIterator<Item = SparsePage>,
+    ) {
+        self.request(image, pages);
+    }
+
+    /// Decides which requested pages to bind and which pages to evict, coarser pages are bound
+    /// first.
+    pub fn update(&mut self, image: &SparseImage) -> SparseResidencyUpdate {
+        self.update_pages(image)
+    }
+
+    fn request(&mut self, image: &impl PageTable, pages: impl IntoIterator<Item = SparsePage>) {
+        for mut page in pages {
+            while image.contains_page(&page) {
+                let last_requested = self.last_requested.entry(page).or_insert(0);
+                if *last_requested == self.update_index + 1 {
+                    // The parents were requested along with the page already.
+                    break;
+                }
+                *last_requested = self.update_index + 1;
+                page = page.parent();
+            }
+        }
+    }
+
+    fn update_pages(&mut self, image: &impl PageTable) -> SparseResidencyUpdate {
+        self.update_index += 1;
+        let update_index = self.update_index;
+
+        let mut bind = self
+            .last_requested
+            .iter()
+            .filter(|(page, last_requested)| {
+                **last_requested == update_index && !image.is_resident(page)
+            })
+            .map(|(page, _)| *page)
+            .collect::<Vec<_>>();
+        bind.sort_by_key(|page| std::cmp::Reverse(page.mip_level));
+        bind.truncate(self.max_binds_per_update);
+
+        let mut evictable = image
+            .resident_pages()
+            .filter(|page| self.last_requested.get(page).copied().unwrap_or(0) < update_index)
+            .copied()
+            .collect::<Vec<_>>();
+        evictable.sort_by_key(|page| self.last_requested.get(page).copied().unwrap_or(0));
+
+        let resident_count = image.resident_page_count();
+        let evict_count = (resident_count + bind.len()).saturating_sub(self.max_resident_pages);
+        let unbind = evictable.into_iter().take(evict_count).collect::<Vec<_>>();
+        // If too few pages can be evicted the budget only has room for some of the pages.
+        let free_pages = self
+            .max_resident_pages
+            .saturating_sub(resident_count - unbind.len());
+        bind.truncate(free_pages);
+
+        // Forget pages that are neither resident nor requested anymore.
+        self.last_requested.retain(|page, last_requested| {
+            *last_requested == update_index || (image.is_resident(page) && !unbind.contains(page))
+        });
+
+        SparseResidencyUpdate { bind, unbind }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// A 4x4 page image with two more paged mip levels before the mip tail.
+    #[derive(Default)]
+    struct TestPages {
+        resident: HashSet<SparsePage>,
+    }
+
+    impl TestPages {
+        fn apply(&mut self, update: &SparseResidencyUpdate) {
+            for page in &update.unbind {
+                assert!(self.resident.remove(page));
+            }
+            for page in &update.bind {
+                assert!(self.resident.insert(*page));
+            }
+        }
+    }
+
+    impl PageTable for TestPages {
+        fn contains_page(&self, page: &SparsePage) -> bool {
+            page.mip_level < 3 && page.x < 4 >> page.mip_level && page.y < 4 >> page.mip_level
+        }
+
+        fn is_resident(&self, page: &SparsePage) -> bool {
+            self.resident.contains(page)
+        }
+
+        fn resident_pages(&self) -> impl Iterator<Item = &SparsePage> {
+            self.resident.iter()
+        }
+
+        fn resident_page_count(&self) -> usize {
+            self.resident.len()
+        }
+    }
+
+    fn page(mip_level: u32, x: u32, y: u32) -> SparsePage {
+        SparsePage { mip_level, x, y }
+    }
+
+    #[test]
+    fn requested_pages_bind_with_their_parents_coarsest_first() {
+        let mut pages = TestPages::default();
+        let mut manager = SparseResidencyManager::new(16, 16);
+
+        // Pages outside the paged mip levels are ignored.
+        manager.request(&pages, [page(0, 3, 3), page(0, 4, 0), page(3, 0, 0)]);
+        let update = manager.update_pages(&pages);
+        assert_eq!(update.bind, [page(2, 0, 0), page(1, 1, 1), page(0, 3, 3)]);
+        assert!(update.unbind.is_empty());
+        pages.apply(&update);
+
+        // Resident pages aren't bound again.
+        manager.request(&pages, [page(0, 3, 3), page(0, 2, 3)]);
+        let update = manager.update_pages(&pages);
+        assert_eq!(update.bind, [page(0, 2, 3)]);
+    }
+
+    #[test]
+    fn binds_are_limited_per_update() {
+        let mut pages = TestPages::default();
+        let mut manager = SparseResidencyManager::new(16, 2);
+
+        manager.request(&pages, [page(0, 0, 0)]);
+        let update = manager.update_pages(&pages);
+        assert_eq!(update.bind, [page(2, 0, 0), page(1, 0, 0)]);
+        pages.apply(&update);
+
+        // Pages have to be requested again every update to stay wanted.
+        assert!(manager.update_pages(&pages).bind.is_empty());
+        manager.request(&pages, [page(0, 0, 0)]);
+        assert_eq!(manager.update_pages(&pages).bind, [page(0, 0, 0)]);
+    }
+
+    #[test]
+    fn least_recently_requested_pages_are_evicted_over_budget() {
+        let mut pages = TestPages::default();
+        let mut manager = SparseResidencyManager::new(4, 16);
+
+        manager.request(&pages, [page(0, 0, 0)]);
+        pages.apply(&manager.update_pages(&pages));
+        manager.request(&pages, [page(0, 1, 0)]);
+        pages.apply(&manager.update_pages(&pages));
+        assert_eq!(pages.resident.len(), 4);
+
+        // The first page was requested longest ago, the parents are shared with the new page.
+        manager.request(&pages, [page(0, 1, 1)]);
+        let update = manager.update_pages(&pages);
+        assert_eq!(update.bind, [page(0, 1, 1)]);
+        assert_eq!(update.unbind, [page(0, 0, 0)]);
+        pages.apply(&update);
+        assert_eq!(pages.resident.len(), 4);
+    }
+
+    #[test]
+    fn pages_requested_this_update_are_never_evicted() {
+        let mut pages = TestPages::default();
+        let mut manager = SparseResidencyManager::new(2, 16);
+
+        manager.request(&pages, [page(0, 0, 0)]);
+        let update = manager.update_pages(&pages);
+        // The budget only has room for the coarser pages.
+        assert_eq!(update.bind, [page(2, 0, 0), page(1, 0, 0)]);
+        pages.apply(&update);
+
+        manager.request(&pages, [page(0, 0, 0)]);
+        let update = manager.update_pages(&pages);
+        assert!(update.bind.is_empty());
+        assert!(update.unbind.is_empty());
+    }
+}
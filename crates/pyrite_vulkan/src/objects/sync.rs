@@ -73,6 +73,17 @@ impl Fence {
         }
     }
 
+    /// Whether the fence is signaled, without waiting on it.
+    pub fn is_signaled(&self) -> bool {
+        unsafe {
+            self.instance
+                .vulkan_dep
+                .device()
+                .get_fence_status(self.instance.fence)
+                .expect("Failed to get fence status")
+        }
+    }
+
     pub fn wait_and_reset(&self) {
         self.wait();
         self.reset();
@@ -140,3 +151,53 @@ impl Semaphore {
         self.instance.clone()
     }
 }
+
+/// Converts synchronization2 stages to the legacy stages for devices without synchronization2.
+///
+/// The finer-grained stages are widened to the legacy stage containing them, an empty mask maps
+/// to `empty_stage` since legacy barriers can't have empty stage masks.
+pub(crate) fn legacy_stage_flags(
+    stages: vk::PipelineStageFlags2,
+    empty_stage: vk::PipelineStageFlags,
+) -> vk::PipelineStageFlags {
+    if stages.is_empty() {
+        return empty_stage;
+    }
+
+    let mut legacy_stages = vk::PipelineStageFlags::from_raw(stages.as_raw() as u32);
+    if stages.intersects(
+        vk::PipelineStageFlags2::COPY
+            | vk::PipelineStageFlags2::RESOLVE
+            | vk::PipelineStageFlags2::BLIT
+            | vk::PipelineStageFlags2::CLEAR,
+    ) {
+        legacy_stages |= vk::PipelineStageFlags::TRANSFER;
+    }
+    if stages.intersects(
+        vk::PipelineStageFlags2::INDEX_INPUT | vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT,
+    ) {
+        legacy_stages |= vk::PipelineStageFlags::VERTEX_INPUT;
+    }
+    if stages.contains(vk::PipelineStageFlags2::PRE_RASTERIZATION_SHADERS) {
+        legacy_stages |= vk::PipelineStageFlags::VERTEX_SHADER
+            | vk::PipelineStageFlags::TESSELLATION_CONTROL_SHADER
+            | vk::PipelineStageFlags::TESSELLATION_EVALUATION_SHADER
+            | vk::PipelineStageFlags::GEOMETRY_SHADER;
+    }
+    legacy_stages
+}
+
+/// Converts synchronization2 access flags to the legacy access flags for devices without
+/// synchronization2.
+pub(crate) fn legacy_access_flags(access: vk::AccessFlags2) -> vk::AccessFlags {
+    let mut legacy_access = vk::AccessFlags::from_raw(access.as_raw() as u32);
+    if access
+        .intersects(vk::AccessFlags2::SHADER_SAMPLED_READ | vk::AccessFlags2::SHADER_STORAGE_READ)
+    {
+        legacy_access |= vk::AccessFlags::SHADER_READ;
+    }
+    if access.contains(vk::AccessFlags2::SHADER_STORAGE_WRITE) {
+        legacy_access |= vk::AccessFlags::SHADER_WRITE;
+    }
+    legacy_access
+}
@@ -0,0 +1,475 @@
+use ash::vk;
+use pyrite_asset::{
+    loaders::{
+        image::{ImageData, ImageFormat},
+        ktx2::{Ktx2Encoding, Ktx2Texture, TranscodeTarget},
+    },
+    Assets, Handle,
+};
+
+use crate::{
+    allocator::VulkanMemoryAllocator, stager::VulkanStager, util::Extent2D, Vulkan, VulkanFeature,
+};
+
+use super::{
+    util::ImageViewCreateInfo, CommandBuffer, OwnedImage, OwnedImageCreateInfo, Sampler,
+    SamplerCreateInfo,
+};
+
+/// A sampled image with its mip chain and its sampler.
+pub struct Texture {
+    image: OwnedImage,
+    sampler: Sampler,
+    extent: Extent2D,
+    mip_levels: u32,
+}
+
+impl Texture {
+    /// Creates a texture from tightly packed sRGB RGBA8 texels, recording the upload and the mip
+    /// generation into `command_buffer`.
+    ///
+    /// The texture is in the shader read only layout once `command_buffer` has executed.
+    ///
+    /// # Panics
+    /// If `data` doesn't hold exactly `extent` texels.
+    pub fn from_rgba8(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+        data: &[u8],
+        extent: Extent2D,
+    ) -> Self {
+        Self::new(
+            vulkan,
+            vulkan_allocator,
+            stager,
+            command_buffer,
+            data,
+            extent,
+            vk::Format::R8G8B8A8_SRGB,
+        )
+    }
+
+    /// Creates a texture without mips from tightly packed sRGB RGBA8 texels, for textures whose
+    /// texels are replaced at runtime with [`Texture::write_rgba8`], such as glyph atlases.
+    ///
+    /// The texture is in the shader read only layout once `command_buffer` has executed.
+    ///
+    /// # Panics
+    /// If `data` doesn't hold exactly `extent` texels.
+    pub fn new_dynamic(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+        data: &[u8],
+        extent: Extent2D,
+    ) -> Self {
+        let texture = Self {
+            image: Self::create_image(
+                vulkan,
+                vulkan_allocator,
+                &extent,
+                vk::Format::R8G8B8A8_SRGB,
+                1,
+                vk::ImageUsageFlags::empty(),
+            ),
+            sampler: Sampler::new(vulkan, &SamplerCreateInfo::default()),
+            extent,
+            mip_levels: 1,
+        };
+        texture.write_rgba8(vulkan, vulkan_allocator, stager, command_buffer, data);
+
+        texture
+    }
+
+    /// Replaces every texel of a texture without mips, recording the upload into
+    /// `command_buffer`.
+    ///
+    /// The texture is in the shader read only layout once `command_buffer` has executed.
+    ///
+    /// # Panics
+    /// If the texture has mips or `data` doesn't hold exactly the texture's texels.
+    pub fn write_rgba8(
+        &self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+        data: &[u8],
+    ) {
+        assert_eq!(
+            self.mip_levels, 1,
+            "Only textures without mips can be written."
+        );
+        assert_eq!(
+            data.len(),
+            (self.extent.width * self.extent.height * 4) as usize,
+            "The texture data doesn't match its {}x{} extent.",
+            self.extent.width,
+            self.extent.height
+        );
+
+        stager.stage_image(
+            vulkan,
+            vulkan_allocator,
+            command_buffer,
+            &self.image,
+            0,
+            self.extent.width,
+            self.extent.height,
+            data,
+        );
+        command_buffer.transition_image(&self.image, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+    }
+
+    /// Wraps an image without mips which is rendered to rather than uploaded, such as the color
+    /// target of an offscreen pass, so it can be sampled like any other texture.
+    ///
+    /// The image must have been created with the sampled usage, and be in the shader read only
+    /// layout whenever the texture is sampled.
+    pub fn from_owned_image(vulkan: &Vulkan, image: OwnedImage, extent: Extent2D) -> Self {
+        Self {
+            image,
+            sampler: Sampler::new(vulkan, &SamplerCreateInfo::default()),
+            extent,
+            mip_levels: 1,
+        }
+    }
+
+    /// Creates a texture from an image loaded by the image loader, see
+    /// [`Texture::from_rgba8`].
+    pub fn from_image(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+        image: &ImageData,
+    ) -> Self {
+        let format = match image.format {
+            ImageFormat::Rgba8Srgb => vk::Format::R8G8B8A8_SRGB,
+            ImageFormat::Rgba8Unorm => vk::Format::R8G8B8A8_UNORM,
+        };
+
+        Self::new(
+            vulkan,
+            vulkan_allocator,
+            stager,
+            command_buffer,
+            &image.bytes,
+            Extent2D {
+                width: image.extent.width,
+                height: image.extent.height,
+            },
+            format,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+        data: &[u8],
+        extent: Extent2D,
+        format: vk::Format,
+    ) -> Self {
+        assert_eq!(
+            data.len(),
+            (extent.width * extent.height * 4) as usize,
+            "The texture data doesn't match its {}x{} extent.",
+            extent.width,
+            extent.height
+        );
+
+        let mip_levels = u32::BITS - extent.width.max(extent.height).leading_zeros();
+        let image = Self::create_image(
+            vulkan,
+            vulkan_allocator,
+            &extent,
+            format,
+            mip_levels,
+            vk::ImageUsageFlags::TRANSFER_SRC,
+        );
+
+        stager.stage_image(
+            vulkan,
+            vulkan_allocator,
+            command_buffer,
+            &image,
+            0,
+            extent.width,
+            extent.height,
+            data,
+        );
+        command_buffer.generate_mipmaps(
+            &image,
+            extent.width,
+            extent.height,
+            mip_levels,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+
+        Self {
+            image,
+            sampler: Sampler::new(vulkan, &SamplerCreateInfo::default()),
+            extent,
+            mip_levels,
+        }
+    }
+
+    /// Creates a texture from a KTX2 texture, recording the upload of its levels into
+    /// `command_buffer`.
+    ///
+    /// UASTC textures are transcoded to the first block format whose texture compression
+    /// feature is enabled out of BC7, ASTC and ETC2, falling back to uncompressed RGBA8. Mips
+    /// can't be generated for block formats, so only the levels in the file are uploaded.
+    ///
+    /// Fails if transcoding fails or the device can't sample the native format of the texture.
+    pub fn from_ktx2(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+        texture: &Ktx2Texture,
+    ) -> Result<Self, String> {
+        let (format, levels) = match texture.encoding {
+            Ktx2Encoding::Native { vk_format } => {
+                let format = vk::Format::from_raw(vk_format as i32);
+                let format_properties = unsafe {
+                    vulkan.instance().get_physical_device_format_properties(
+                        vulkan.physical_device().physical_device(),
+                        format,
+                    )
+                };
+                if !format_properties
+                    .optimal_tiling_features
+                    .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE)
+                {
+                    return Err(format!("The device can't sample {:?} textures", format));
+                }
+
+                (format, texture.levels.clone())
+            }
+            Ktx2Encoding::Uastc { .. } => {
+                let target = Self::transcode_target(vulkan);
+                (
+                    Self::transcoded_format(target, texture.srgb),
+                    texture.transcode(target)?,
+                )
+            }
+        };
+        if !texture.is_mip_complete() {
+            log::warn!("KTX2 texture doesn't have a full mip chain, it may alias when minified.");
+        }
+
+        let extent = Extent2D {
+            width: texture.extent.width,
+            height: texture.extent.height,
+        };
+        let mip_levels = levels.len() as u32;
+        let image = Self::create_image(
+            vulkan,
+            vulkan_allocator,
+            &extent,
+            format,
+            mip_levels,
+            vk::ImageUsageFlags::empty(),
+        );
+
+        for (level, data) in levels.iter().enumerate() {
+            let level_extent = texture.level_extent(level);
+            stager.stage_image(
+                vulkan,
+                vulkan_allocator,
+                command_buffer,
+                &image,
+                level as u32,
+                level_extent.width,
+                level_extent.height,
+                data,
+            );
+        }
+        command_buffer.transition_image(&image, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+        Ok(Self {
+            image,
+            sampler: Sampler::new(vulkan, &SamplerCreateInfo::default()),
+            extent,
+            mip_levels,
+        })
+    }
+
+    fn transcode_target(vulkan: &Vulkan) -> TranscodeTarget {
+        if vulkan.is_feature_enabled(VulkanFeature::TextureCompressionBc) {
+            TranscodeTarget::Bc7
+        } else if vulkan.is_feature_enabled(VulkanFeature::TextureCompressionAstc) {
+            TranscodeTarget::Astc4x4
+        } else if vulkan.is_feature_enabled(VulkanFeature::TextureCompressionEtc2) {
+            TranscodeTarget::Etc2Rgba
+        } else {
+            TranscodeTarget::Rgba8
+        }
+    }
+
+    fn transcoded_format(target: TranscodeTarget, srgb: bool) -> vk::Format {
+        match (target, srgb) {
+            (TranscodeTarget::Bc7, true) => vk::Format::BC7_SRGB_BLOCK,
+            (TranscodeTarget::Bc7, false) => vk::Format::BC7_UNORM_BLOCK,
+            (TranscodeTarget::Astc4x4, true) => vk::Format::ASTC_4X4_SRGB_BLOCK,
+            (TranscodeTarget::Astc4x4, false) => vk::Format::ASTC_4X4_UNORM_BLOCK,
+            (TranscodeTarget::Etc2Rgba, true) => vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK,
+            (TranscodeTarget::Etc2Rgba, false) => vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK,
+            (TranscodeTarget::Rgba8, true) => vk::Format::R8G8B8A8_SRGB,
+            (TranscodeTarget::Rgba8, false) => vk::Format::R8G8B8A8_UNORM,
+        }
+    }
+
+    /// Creates a sampled image which can be uploaded to, with `usage` added.
+    fn create_image(
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        extent: &Extent2D,
+        format: vk::Format,
+        mip_levels: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> OwnedImage {
+        OwnedImage::new(
+            vulkan,
+            vulkan_allocator,
+            &OwnedImageCreateInfo {
+                image_type: vk::ImageType::TYPE_2D,
+                width: extent.width,
+                height: extent.height,
+                format,
+                usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST | usage,
+                samples: vk::SampleCountFlags::TYPE_1,
+                mip_levels,
+                view_create_info: Some(ImageViewCreateInfo {
+                    view_type: vk::ImageViewType::TYPE_2D,
+                    subresource_range: vk::ImageSubresourceRange::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .base_mip_level(0)
+                        .level_count(mip_levels)
+                        .base_array_layer(0)
+                        .layer_count(1),
+                }),
+                name: Some("texture".to_string()),
+            },
+        )
+    }
+
+    pub fn image(&self) -> &OwnedImage {
+        &self.image
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    pub fn extent(&self) -> &Extent2D {
+        &self.extent
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+}
+
+enum TextureSource {
+    Image(Handle<ImageData>),
+    Ktx2(Handle<Ktx2Texture>),
+}
+
+/// A texture loaded from an image or KTX2 file through the [`Assets`].
+///
+/// Loaders can't record gpu work, so the file is decoded by the image or KTX2 loader, which must
+/// be added to the [`Assets`], and uploaded by [`AssetTexture::update`] once it has loaded.
+pub struct AssetTexture {
+    source: Option<TextureSource>,
+    texture: Option<Texture>,
+}
+
+impl AssetTexture {
+    /// Starts loading the file, `.ktx2` files are loaded as KTX2 textures and everything else as
+    /// images.
+    pub fn new(assets: &mut Assets, file_path: impl ToString) -> Self {
+        let file_path = file_path.to_string();
+        let source = if file_path.ends_with(".ktx2") {
+            TextureSource::Ktx2(assets.load(file_path))
+        } else {
+            TextureSource::Image(assets.load(file_path))
+        };
+
+        Self {
+            source: Some(source),
+            texture: None,
+        }
+    }
+
+    /// Creates the texture once the file has loaded, should be called once per frame. Returns
+    /// true if the texture was created.
+    ///
+    /// If the file fails to load the error is logged and the texture is never created.
+    pub fn update(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        stager: &mut VulkanStager,
+        command_buffer: &mut CommandBuffer,
+    ) -> bool {
+        let result = match &self.source {
+            None => return false,
+            Some(TextureSource::Image(image)) => {
+                if !image.is_loaded() {
+                    return false;
+                }
+
+                match (image.get_error(), image.get()) {
+                    (Some(error), _) => Err(error.to_string()),
+                    (None, Some(image)) => Ok(Texture::from_image(
+                        vulkan,
+                        vulkan_allocator,
+                        stager,
+                        command_buffer,
+                        &image,
+                    )),
+                    (None, None) => return false,
+                }
+            }
+            Some(TextureSource::Ktx2(texture)) => {
+                if !texture.is_loaded() {
+                    return false;
+                }
+
+                match (texture.get_error(), texture.get()) {
+                    (Some(error), _) => Err(error.to_string()),
+                    (None, Some(texture)) => Texture::from_ktx2(
+                        vulkan,
+                        vulkan_allocator,
+                        stager,
+                        command_buffer,
+                        &texture,
+                    ),
+                    (None, None) => return false,
+                }
+            }
+        };
+
+        match result {
+            Ok(texture) => self.texture = Some(texture),
+            Err(error) => log::error!("Failed to load texture: {}", error),
+        }
+
+        // The decoded file isn't needed anymore once it was uploaded.
+        self.source = None;
+        self.texture.is_some()
+    }
+
+    /// The texture, `None` until the file has loaded.
+    pub fn texture(&self) -> Option<&Texture> {
+        self.texture.as_ref()
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use ash::vk;
+use pyrite_app::{diagnostics::DiagnosticsStore, resource::Resource};
+
+use crate::{
+    objects::{CommandBuffer, QueryPool},
+    Vulkan,
+};
+
+/// The scope covering the whole frame, opened by [`GpuProfiler::begin_frame`].
+pub const GPU_FRAME_SCOPE: &str = "frame";
+
+struct GpuScope {
+    name: String,
+    begin_query: u32,
+    end_query: Option<u32>,
+}
+
+struct GpuProfilerFrame {
+    query_pool: QueryPool,
+    scopes: Vec<GpuScope>,
+    next_query: u32,
+}
+
+/// Measures the gpu time of command buffer regions with timestamp queries.
+///
+/// Every frame in flight has its own query pool, whose results are read back when the frame comes
+/// around again, so the timings lag `frames_in_flight` frames behind. The timings are published
+/// to the [`DiagnosticsStore`] as gpu scopes, including the [`GPU_FRAME_SCOPE`] covering
+/// everything recorded between [`GpuProfiler::begin_frame`] and [`GpuProfiler::end_frame`].
+///
+/// If the queue doesn't support timestamps the profiler does nothing.
+#[derive(Resource)]
+pub struct GpuProfiler {
+    frames: Vec<GpuProfilerFrame>,
+    frame_index: usize,
+    /// The indices of the currently open scopes in the current frame, innermost last.
+    open_scopes: Vec<usize>,
+    /// The number of nanoseconds per timestamp tick.
+    timestamp_period: f64,
+    timestamp_mask: u64,
+    enabled: bool,
+}
+
+impl GpuProfiler {
+    /// Creates a profiler for command buffers submitted to the default queue, each frame can
+    /// have at most `max_scopes` scopes including the frame scope.
+    pub fn new(vulkan: &Vulkan, frames_in_flight: usize, max_scopes: u32) -> Self {
+        let timestamp_valid_bits = vulkan.physical_device().queue_families()
+            [vulkan.default_queue().queue_family_index() as usize]
+            .timestamp_valid_bits;
+        let enabled = timestamp_valid_bits > 0;
+        if !enabled {
+            log::warn!("The default queue doesn't support timestamps, gpu profiling is disabled.");
+        }
+
+        Self {
+            frames: (0..frames_in_flight)
+                .map(|_| GpuProfilerFrame {
+                    query_pool: QueryPool::new_timestamp(vulkan, max_scopes * 2),
+                    scopes: Vec::new(),
+                    next_query: 0,
+                })
+                .collect(),
+            frame_index: 0,
+            open_scopes: Vec::new(),
+            timestamp_period: vulkan
+                .physical_device()
+                .properties()
+                .limits
+                .timestamp_period as f64,
+            timestamp_mask: u64::MAX >> (64 - timestamp_valid_bits.clamp(1, 64)),
+            enabled,
+        }
+    }
+
+    /// Publishes the timings of the last use of `frame_index` and starts recording a new frame,
+    /// opening the frame scope.
+    ///
+    /// Must be called after the frame's fence was waited on, at the start of `command_buffer`
+    /// outside of any rendering.
+    pub fn begin_frame(
+        &mut self,
+        frame_index: usize,
+        command_buffer: &mut CommandBuffer,
+        diagnostics: Option<&mut DiagnosticsStore>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        assert!(
+            self.open_scopes.is_empty(),
+            "The previous gpu profiler frame still has open scopes."
+        );
+
+        self.frame_index = frame_index;
+        if let Some(diagnostics) = diagnostics {
+            self.publish_frame(diagnostics);
+        }
+
+        let frame = &mut self.frames[frame_index];
+        frame.scopes.clear();
+        frame.next_query = 0;
+        command_buffer.reset_query_pool(
+            &frame.query_pool,
+            0,
+            frame.query_pool.instance().query_count(),
+        );
+
+        self.begin_scope(command_buffer, GPU_FRAME_SCOPE);
+    }
+
+    /// Closes the frame scope, every other scope must already be closed.
+    pub fn end_frame(&mut self, command_buffer: &mut CommandBuffer) {
+        if !self.enabled {
+            return;
+        }
+        assert_eq!(
+            self.open_scopes.len(),
+            1,
+            "Tried to end a gpu profiler frame with open scopes."
+        );
+
+        self.end_scope(command_buffer);
+    }
+
+    /// Opens a scope measuring the commands recorded until the matching
+    /// [`GpuProfiler::end_scope`], scopes can be nested.
+    ///
+    /// Scopes past the frame's `max_scopes` are ignored with a warning.
+    pub fn begin_scope(&mut self, command_buffer: &mut CommandBuffer, name: impl Into<String>) {
+        if !self.enabled {
+            return;
+        }
+
+        let frame = &mut self.frames[self.frame_index];
+        // Each open scope still needs a query for its end.
+        let reserved_queries = self
+            .open_scopes
+            .iter()
+            .filter(|scope_index| frame.scopes[**scope_index].begin_query != u32::MAX)
+            .count() as u32;
+        if frame.next_query + reserved_queries + 2 > frame.query_pool.instance().query_count() {
+            log::warn!("Ran out of gpu profiler queries, ignoring scope.");
+            // Still push the scope so the matching end_scope is balanced.
+            frame.scopes.push(GpuScope {
+                name: name.into(),
+                begin_query: u32::MAX,
+                end_query: None,
+            });
+            self.open_scopes.push(frame.scopes.len() - 1);
+            return;
+        }
+
+        let begin_query = frame.next_query;
+        frame.next_query += 1;
+        command_buffer.write_timestamp(
+            &frame.query_pool,
+            vk::PipelineStageFlags2::TOP_OF_PIPE,
+            begin_query,
+        );
+
+        frame.scopes.push(GpuScope {
+            name: name.into(),
+            begin_query,
+            end_query: None,
+        });
+        self.open_scopes.push(frame.scopes.len() - 1);
+    }
+
+    /// Closes the innermost open scope.
+    pub fn end_scope(&mut self, command_buffer: &mut CommandBuffer) {
+        if !self.enabled {
+            return;
+        }
+
+        let scope_index = self
+            .open_scopes
+            .pop()
+            .expect("Tried to end a gpu profiler scope that wasn't begun.");
+        let frame = &mut self.frames[self.frame_index];
+        if frame.scopes[scope_index].begin_query == u32::MAX {
+            return;
+        }
+
+        let end_query = frame.next_query;
+        frame.next_query += 1;
+        command_buffer.write_timestamp(
+            &frame.query_pool,
+            vk::PipelineStageFlags2::BOTTOM_OF_PIPE,
+            end_query,
+        );
+        frame.scopes[scope_index].end_query = Some(end_query);
+    }
+
+    fn publish_frame(&self, diagnostics: &mut DiagnosticsStore) {
+        let frame = &self.frames[self.frame_index];
+        if frame.next_query == 0 {
+            return;
+        }
+
+        let Some(timestamps) = frame.query_pool.timestamps(0, frame.next_query) else {
+            log::warn!("Gpu profiler queries weren't available, skipping the frame.");
+            return;
+        };
+
+        for scope in &frame.scopes {
+            let Some(end_query) = scope.end_query else {
+                continue;
+            };
+
+            let begin = timestamps[scope.begin_query as usize] & self.timestamp_mask;
+            let end = timestamps[end_query as usize] & self.timestamp_mask;
+            let ticks = end.wrapping_sub(begin) & self.timestamp_mask;
+            let nanos = ticks as f64 * self.timestamp_period;
+            diagnostics.record_gpu_scope(&scope.name, Duration::from_nanos(nanos as u64));
+        }
+    }
+}
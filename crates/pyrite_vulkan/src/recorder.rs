@@ -0,0 +1,116 @@
+use pyrite_app::resource::Resource;
+use pyrite_task::TaskPool;
+
+use crate::{
+    objects::{CommandBuffer, CommandBufferHandle, CommandPool, RenderingFormats},
+    Vulkan,
+};
+
+struct RecorderThread {
+    command_pool: CommandPool,
+    command_buffers: Vec<CommandBufferHandle>,
+    /// The number of command buffers used since the pool was last reset.
+    used_command_buffers: usize,
+}
+
+impl RecorderThread {
+    fn next_command_buffer(&mut self) -> &mut CommandBuffer {
+        if self.used_command_buffers == self.command_buffers.len() {
+            let [handle] = self.command_pool.allocate_secondary::<1>();
+            self.command_buffers.push(handle);
+        }
+
+        let handle = self.command_buffers[self.used_command_buffers];
+        self.used_command_buffers += 1;
+        self.command_pool.get_mut(handle).unwrap()
+    }
+
+    fn last_command_buffer(&mut self) -> &mut CommandBuffer {
+        let handle = self.command_buffers[self.used_command_buffers - 1];
+        self.command_pool.get_mut(handle).unwrap()
+    }
+}
+
+/// Records secondary command buffers in parallel on a [`TaskPool`].
+///
+/// Command pools can't be used by multiple threads at once, so every thread of the task pool
+/// has its own command pool for each frame in flight.
+#[derive(Resource)]
+pub struct ParallelRecorder {
+    frames: Vec<Vec<RecorderThread>>,
+    frame_index: usize,
+}
+
+impl ParallelRecorder {
+    pub fn new(vulkan: &Vulkan, task_pool: &TaskPool, frames_in_flight: usize) -> Self {
+        Self {
+            frames: (0..frames_in_flight)
+                .map(|_| {
+                    (0..task_pool.num_threads())
+                        .map(|_| RecorderThread {
+                            command_pool: CommandPool::new(vulkan),
+                            command_buffers: Vec::new(),
+                            used_command_buffers: 0,
+                        })
+                        .collect()
+                })
+                .collect(),
+            frame_index: 0,
+        }
+    }
+
+    /// Resets the command pools of `frame_index` and records into them until the next call.
+    ///
+    /// Must be called after the frame's fence was waited on.
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        self.frame_index = frame_index;
+        for thread in &mut self.frames[frame_index] {
+            thread.command_pool.reset();
+            thread.used_command_buffers = 0;
+        }
+    }
+
+    /// Splits `items` into a chunk per thread and records each chunk into its own secondary
+    /// command buffer with `record`, then executes them in order in `command_buffer`.
+    ///
+    /// If `rendering_formats` is set the secondary command buffers continue the rendering
+    /// `command_buffer` is in, see [`CommandBuffer::begin_secondary`].
+    pub fn record<T, F>(
+        &mut self,
+        task_pool: &TaskPool,
+        command_buffer: &mut CommandBuffer,
+        rendering_formats: Option<&RenderingFormats>,
+        items: &[T],
+        record: F,
+    ) where
+        T: Sync,
+        F: Fn(&mut CommandBuffer, &[T]) + Sync,
+    {
+        if items.is_empty() {
+            return;
+        }
+
+        let threads = &mut self.frames[self.frame_index];
+        let chunk_size = items.len().div_ceil(threads.len());
+        let chunk_count = items.len().div_ceil(chunk_size);
+
+        let record = &record;
+        task_pool.scope(|scope| {
+            for (thread, chunk) in threads.iter_mut().zip(items.chunks(chunk_size)) {
+                scope.spawn(move |_| {
+                    let secondary = thread.next_command_buffer();
+                    secondary.begin_secondary(rendering_formats);
+                    record(secondary, chunk);
+                    secondary.end();
+                });
+            }
+        });
+
+        let mut secondaries = threads
+            .iter_mut()
+            .take(chunk_count)
+            .map(|thread| thread.last_command_buffer())
+            .collect::<Vec<_>>();
+        command_buffer.execute_commands(&mut secondaries);
+    }
+}
@@ -0,0 +1,697 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use ash::vk;
+use pyrite_app::resource::Resource;
+
+use crate::{
+    allocator::VulkanMemoryAllocator,
+    executor::{QueueExecutor, QueueExecutorSubmitInfo, SubmitError},
+    objects::{
+        Buffer, BufferCreateInfo, CommandBuffer, CommandBufferHandle, CommandPool, Fence, Image,
+        ImageState, UntypedBuffer,
+    },
+    util::GenericResourceDep,
+    QueueCapability, QueueConfig, QueuePriority, QueueResolution, Vulkan, DEFAULT_QUEUE,
+};
+
+/// The name of the transfer queue deferred uploads are recorded on, see [`staging_queue_config`].
+pub const STAGING_QUEUE: &str = "pyrite_vulkan_staging";
+
+/// A transfer queue on its own queue family for the deferred uploads of the [`VulkanStager`],
+/// falling back to the default queue if the device has no such family.
+pub fn staging_queue_config() -> QueueConfig {
+    QueueConfig {
+        name: STAGING_QUEUE.to_string(),
+        capabilities: vec![QueueCapability::Transfer],
+        priority: QueuePriority::Exclusive,
+        resolution: QueueResolution::Fallback(DEFAULT_QUEUE.to_string()),
+    }
+}
+
+/// Uploads data to device local resources through host visible staging buffers.
+///
+/// The staging buffers are kept alive until their frame in flight comes around again, at which
+/// point the copies recorded into that frame's command buffers have finished.
+///
+/// Large uploads can be deferred instead, recording them on the [`STAGING_QUEUE`] so they don't
+/// stall the frame. They're submitted with [`VulkanStager::submit_deferred`] and become usable
+/// once [`VulkanStager::acquire_deferred`] recorded their acquire barriers into a command buffer
+/// of the default queue.
+#[derive(Resource)]
+pub struct VulkanStager {
+    frames: Vec<StagerFrame>,
+    frame_index: usize,
+    deferred_batches: Vec<DeferredBatch>,
+}
+
+#[derive(Default)]
+struct StagerFrame {
+    staging_buffers: Vec<UntypedBuffer>,
+    /// The destinations of the deferred uploads acquired in the frame, as the acquire barriers
+    /// only hold raw handles.
+    acquired_resources: Vec<GenericResourceDep>,
+}
+
+impl VulkanStager {
+    pub fn new(frames_in_flight: usize) -> Self {
+        Self {
+            frames: (0..frames_in_flight)
+                .map(|_| StagerFrame::default())
+                .collect(),
+            frame_index: 0,
+            deferred_batches: Vec::new(),
+        }
+    }
+
+    /// Releases the staging buffers of the last use of `frame_index`, the following uploads are
+    /// recorded into that frame.
+    ///
+    /// Must be called after the frame's fence was waited on.
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        self.frame_index = frame_index;
+        let frame = &mut self.frames[frame_index];
+        frame.staging_buffers.clear();
+        frame.acquired_resources.clear();
+    }
+
+    /// Records a copy of `data` into `dst_buffer` at the byte `dst_offset`.
+    ///
+    /// The copy isn't synchronized with later commands, a barrier from the transfer stage must be
+    /// recorded before the buffer is used.
+    pub fn stage_buffer(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        command_buffer: &mut CommandBuffer,
+        dst_buffer: &dyn Buffer,
+        dst_offset: u64,
+        data: &[u8],
+    ) {
+        if data.is_empty() {
+            return;
+        }
+
+        let staging_buffers = &mut self.frames[self.frame_index].staging_buffers;
+        staging_buffers.push(create_staging_buffer(vulkan, vulkan_allocator, data));
+        command_buffer.copy_buffer(
+            staging_buffers.last().unwrap(),
+            dst_buffer,
+            &[vk::BufferCopy::default()
+                .dst_offset(dst_offset)
+                .size(data.len() as u64)],
+        );
+    }
+
+    /// Records a copy of tightly packed texels or blocks into a mip level of `dst_image`, where
+    /// `width` and `height` are the extent of the mip level.
+    ///
+    /// Unless the image is already in the transfer destination layout, the whole image is
+    /// transitioned to it first, discarding its contents if it wasn't used in `command_buffer`
+    /// yet.
+    #[allow(clippy::too_many_arguments)]
+    pub fn stage_image(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        command_buffer: &mut CommandBuffer,
+        dst_image: &dyn Image,
+        mip_level: u32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) {
+        if command_buffer.image_state(dst_image).layout != vk::ImageLayout::TRANSFER_DST_OPTIMAL {
+            command_buffer.transition_image(dst_image, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+        }
+
+        let staging_buffers = &mut self.frames[self.frame_index].staging_buffers;
+        staging_buffers.push(create_staging_buffer(vulkan, vulkan_allocator, data));
+        command_buffer.copy_buffer_to_image(
+            staging_buffers.last().unwrap(),
+            dst_image,
+            mip_level,
+            width,
+            height,
+        );
+    }
+
+    /// Records a copy of `data` into `dst_buffer` at the byte `dst_offset` on the staging queue.
+    ///
+    /// `dst_stage` and `dst_access` are the first use of the buffer, which the acquire barrier
+    /// makes the copy visible to. The buffer must not be used before the returned upload
+    /// completed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn stage_buffer_deferred(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        dst_buffer: &dyn Buffer,
+        dst_offset: u64,
+        data: &[u8],
+        dst_stage: vk::PipelineStageFlags2,
+        dst_access: vk::AccessFlags2,
+    ) -> DeferredUpload {
+        if data.is_empty() {
+            return DeferredUpload {
+                is_complete: Arc::new(AtomicBool::new(true)),
+            };
+        }
+
+        let batch = self.recording_batch(vulkan);
+        batch
+            .staging_buffers
+            .push(create_staging_buffer(vulkan, vulkan_allocator, data));
+        batch
+            .command_pool
+            .get_mut(batch.command_buffer)
+            .unwrap()
+            .copy_buffer(
+                batch.staging_buffers.last().unwrap(),
+                dst_buffer,
+                &[vk::BufferCopy::default()
+                    .dst_offset(dst_offset)
+                    .size(data.len() as u64)],
+            );
+
+        batch.transfers.buffers.push(BufferTransfer {
+            buffer: dst_buffer.instance().buffer(),
+            offset: dst_offset,
+            size: data.len() as u64,
+            dst_stage,
+            dst_access,
+        });
+        batch.dst_resources.push(dst_buffer.create_generic_dep());
+        batch.add_upload()
+    }
+
+    /// Records a copy of tightly packed texels or blocks into a mip level of `dst_image` on the
+    /// staging queue, where `width` and `height` are the extent of the mip level. The image ends
+    /// up in `final_layout` once the upload completed.
+    ///
+    /// The image's contents are discarded when it's first staged in a batch, so all of its mip
+    /// levels must be staged before the next [`VulkanStager::submit_deferred`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn stage_image_deferred(
+        &mut self,
+        vulkan: &Vulkan,
+        vulkan_allocator: &mut VulkanMemoryAllocator,
+        dst_image: &dyn Image,
+        mip_level: u32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        final_layout: vk::ImageLayout,
+    ) -> DeferredUpload {
+        let batch = self.recording_batch(vulkan);
+        batch
+            .staging_buffers
+            .push(create_staging_buffer(vulkan, vulkan_allocator, data));
+        let command_buffer = batch.command_pool.get_mut(batch.command_buffer).unwrap();
+        if command_buffer.image_state(dst_image).layout != vk::ImageLayout::TRANSFER_DST_OPTIMAL {
+            command_buffer.transition_image(dst_image, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+        }
+        command_buffer.copy_buffer_to_image(
+            batch.staging_buffers.last().unwrap(),
+            dst_image,
+            mip_level,
+            width,
+            height,
+        );
+
+        batch
+            .transfers
+            .add_image(dst_image.instance().image(), final_layout);
+        batch.dst_resources.push(dst_image.create_generic_dep());
+        batch.add_upload()
+    }
+
+    /// Submits the deferred uploads staged since the last submission to the staging queue,
+    /// releasing the ownership of their destinations to the default queue's family.
+    pub fn submit_deferred(&mut self) -> Result<(), SubmitError> {
+        let Some(batch) = self
+            .deferred_batches
+            .iter_mut()
+            .find(|batch| batch.state == DeferredBatchState::Recording)
+        else {
+            return Ok(());
+        };
+
+        let command_buffer = batch.command_pool.get_mut(batch.command_buffer).unwrap();
+        command_buffer.queue_ownership_barrier(
+            &batch.transfers.release_buffer_barriers(),
+            &batch.transfers.release_image_barriers(),
+        );
+        command_buffer.end();
+
+        let result = batch.executor.submit(QueueExecutorSubmitInfo {
+            command_buffers: vec![command_buffer],
+            frame_index: 0,
+            wait_semaphores: Vec::new(),
+            signal_semaphores: Vec::new(),
+            fence: Some(&batch.fence),
+        });
+        match result {
+            Ok(()) => {
+                batch.state = DeferredBatchState::Submitted;
+                Ok(())
+            }
+            Err(err) => {
+                // The uploads of the batch never complete.
+                batch.reset();
+                Err(err)
+            }
+        }
+    }
+
+    /// Records the acquire barriers of the submitted uploads that finished executing into
+    /// `command_buffer`, which must be submitted to the default queue. The uploads are complete
+    /// for the commands recorded after it.
+    pub fn acquire_deferred(&mut self, command_buffer: &mut CommandBuffer) {
+        for batch in &mut self.deferred_batches {
+            if batch.state != DeferredBatchState::Submitted || !batch.fence.is_signaled() {
+                continue;
+            }
+
+            command_buffer.queue_ownership_barrier(
+                &batch.transfers.acquire_buffer_barriers(),
+                &batch.transfers.acquire_image_barriers(),
+            );
+            self.frames[self.frame_index]
+                .acquired_resources
+                .append(&mut batch.dst_resources);
+            for upload in batch.uploads.drain(..) {
+                upload.store(true, Ordering::Release);
+            }
+            batch.reset();
+        }
+    }
+
+    /// The batch currently recording deferred uploads, beginning an idle or new batch if there's
+    /// none.
+    fn recording_batch(&mut self, vulkan: &Vulkan) -> &mut DeferredBatch {
+        let batch_index = self
+            .deferred_batches
+            .iter()
+            .position(|batch| batch.state == DeferredBatchState::Recording)
+            .or_else(|| {
+                self.deferred_batches
+                    .iter()
+                    .position(|batch| batch.state == DeferredBatchState::Idle)
+            })
+            .unwrap_or_else(|| {
+                self.deferred_batches.push(DeferredBatch::new(vulkan));
+                self.deferred_batches.len() - 1
+            });
+
+        let batch = &mut self.deferred_batches[batch_index];
+        if batch.state == DeferredBatchState::Idle {
+            batch
+                .command_pool
+                .get_mut(batch.command_buffer)
+                .unwrap()
+                .begin();
+            batch.state = DeferredBatchState::Recording;
+        }
+        batch
+    }
+}
+
+fn create_staging_buffer(
+    vulkan: &Vulkan,
+    vulkan_allocator: &mut VulkanMemoryAllocator,
+    data: &[u8],
+) -> UntypedBuffer {
+    let mut staging_buffer = UntypedBuffer::new(
+        vulkan,
+        vulkan_allocator,
+        &BufferCreateInfo {
+            size: data.len() as u64,
+            usage: vk::BufferUsageFlags::TRANSFER_SRC,
+            memory_properties: vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+            name: Some("staging".to_string()),
+        },
+    );
+    staging_buffer.write_bytes(0, data);
+    staging_buffer
+}
+
+/// A handle to a deferred upload, which can be polled to know when the destination can be used.
+#[derive(Clone)]
+pub struct DeferredUpload {
+    is_complete: Arc<AtomicBool>,
+}
+
+impl DeferredUpload {
+    /// Whether the copy finished and the destination was acquired by a command buffer of the
+    /// default queue, so commands recorded after [`VulkanStager::acquire_deferred`] can use it.
+    pub fn is_complete(&self) -> bool {
+        self.is_complete.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeferredBatchState {
+    Idle,
+    Recording,
+    Submitted,
+}
+
+/// The deferred uploads submitted together to the staging queue, with their own command pool and
+/// fence so they can be in flight for any number of frames.
+struct DeferredBatch {
+    command_pool: CommandPool,
+    command_buffer: CommandBufferHandle,
+    fence: Fence,
+    executor: QueueExecutor<1>,
+    staging_buffers: Vec<UntypedBuffer>,
+    dst_resources: Vec<GenericResourceDep>,
+    transfers: OwnershipTransfers,
+    uploads: Vec<Arc<AtomicBool>>,
+    state: DeferredBatchState,
+}
+
+impl DeferredBatch {
+    fn new(vulkan: &Vulkan) -> Self {
+        let queue_name = if vulkan.queue(STAGING_QUEUE).is_some() {
+            STAGING_QUEUE
+        } else {
+            DEFAULT_QUEUE
+        };
+        let queue = vulkan.queue(queue_name).unwrap();
+        let mut command_pool = CommandPool::new_for_queue(vulkan, queue);
+        let [command_buffer] = command_pool.allocate();
+
+        Self {
+            command_pool,
+            command_buffer,
+            // Only waited on once submitted.
+            fence: Fence::new(vulkan, false),
+            executor: QueueExecutor::new(vulkan, queue_name),
+            staging_buffers: Vec::new(),
+            dst_resources: Vec::new(),
+            transfers: OwnershipTransfers::new(
+                queue.queue_family_index(),
+                vulkan.default_queue().queue_family_index(),
+            ),
+            uploads: Vec::new(),
+            state: DeferredBatchState::Idle,
+        }
+    }
+
+    fn add_upload(&mut self) -> DeferredUpload {
+        let is_complete = Arc::new(AtomicBool::new(false));
+        self.uploads.push(is_complete.clone());
+        DeferredUpload { is_complete }
+    }
+
+    /// Releases the resources of the batch so it can record new uploads, once its fence was
+    /// signalled.
+    fn reset(&mut self) {
+        self.fence.reset();
+        self.executor.release_frame_resources(0);
+        self.command_pool.reset();
+        self.staging_buffers.clear();
+        self.dst_resources.clear();
+        self.transfers.clear();
+        self.uploads.clear();
+        self.state = DeferredBatchState::Idle;
+    }
+}
+
+impl Drop for DeferredBatch {
+    fn drop(&mut self) {
+        // The copies still use the staging buffers and destinations.
+        if self.state == DeferredBatchState::Submitted {
+            self.fence.wait();
+        }
+    }
+}
+
+/// The destinations written by a batch of deferred uploads, whose ownership is released by the
+/// staging queue's family and acquired by the default queue's family.
+///
+/// If both queues are of the same family no ownership is transferred, the fence already made the
+/// copies available so acquiring only makes them visible to their first use.
+struct OwnershipTransfers {
+    src_queue_family_index: u32,
+    dst_queue_family_index: u32,
+    buffers: Vec<BufferTransfer>,
+    images: Vec<ImageTransfer>,
+}
+
+struct BufferTransfer {
+    buffer: vk::Buffer,
+    offset: u64,
+    size: u64,
+    dst_stage: vk::PipelineStageFlags2,
+    dst_access: vk::AccessFlags2,
+}
+
+struct ImageTransfer {
+    image: vk::Image,
+    final_layout: vk::ImageLayout,
+}
+
+impl OwnershipTransfers {
+    fn new(src_queue_family_index: u32, dst_queue_family_index: u32) -> Self {
+        Self {
+            src_queue_family_index,
+            dst_queue_family_index,
+            buffers: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    /// Adds an image unless it's already transferred, keeping its first final layout since all of
+    /// its mip levels are transferred at once.
+    fn add_image(&mut self, image: vk::Image, final_layout: vk::ImageLayout) {
+        if !self.images.iter().any(|transfer| transfer.image == image) {
+            self.images.push(ImageTransfer {
+                image,
+                final_layout,
+            });
+        }
+    }
+
+    fn clear(&mut self) {
+        self.buffers.clear();
+        self.images.clear();
+    }
+
+    fn transfers_ownership(&self) -> bool {
+        self.src_queue_family_index != self.dst_queue_family_index
+    }
+
+    fn queue_family_indices(&self) -> (u32, u32) {
+        if self.transfers_ownership() {
+            (self.src_queue_family_index, self.dst_queue_family_index)
+        } else {
+            (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+        }
+    }
+
+    fn release_buffer_barriers(&self) -> Vec<vk::BufferMemoryBarrier2<'static>> {
+        if !self.transfers_ownership() {
+            return Vec::new();
+        }
+
+        self.buffers
+            .iter()
+            .map(|transfer| {
+                self.buffer_barrier(transfer)
+                    .src_stage_mask(vk::PipelineStageFlags2::ALL_TRANSFER)
+                    .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::NONE)
+                    .dst_access_mask(vk::AccessFlags2::NONE)
+            })
+            .collect()
+    }
+
+    /// The images are transitioned to their final layouts by the release, even without an
+    /// ownership transfer.
+    fn release_image_barriers(&self) -> Vec<vk::ImageMemoryBarrier2<'static>> {
+        self.images
+            .iter()
+            .map(|transfer| {
+                self.image_barrier(transfer)
+                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .new_layout(transfer.final_layout)
+                    .src_stage_mask(vk::PipelineStageFlags2::ALL_TRANSFER)
+                    .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::NONE)
+                    .dst_access_mask(vk::AccessFlags2::NONE)
+            })
+            .collect()
+    }
+
+    fn acquire_buffer_barriers(&self) -> Vec<vk::BufferMemoryBarrier2<'static>> {
+        self.buffers
+            .iter()
+            .map(|transfer| {
+                self.buffer_barrier(transfer)
+                    .src_stage_mask(vk::PipelineStageFlags2::NONE)
+                    .src_access_mask(vk::AccessFlags2::NONE)
+                    .dst_stage_mask(transfer.dst_stage)
+                    .dst_access_mask(transfer.dst_access)
+            })
+            .collect()
+    }
+
+    /// Repeats the layout transition of the release when transferring ownership, as both halves
+    /// must match.
+    fn acquire_image_barriers(&self) -> Vec<vk::ImageMemoryBarrier2<'static>> {
+        self.images
+            .iter()
+            .map(|transfer| {
+                let old_layout = if self.transfers_ownership() {
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL
+                } else {
+                    transfer.final_layout
+                };
+                let dst_state = ImageState::from_layout(transfer.final_layout);
+
+                self.image_barrier(transfer)
+                    .old_layout(old_layout)
+                    .new_layout(transfer.final_layout)
+                    .src_stage_mask(vk::PipelineStageFlags2::NONE)
+                    .src_access_mask(vk::AccessFlags2::NONE)
+                    .dst_stage_mask(dst_state.stage)
+                    .dst_access_mask(dst_state.access)
+            })
+            .collect()
+    }
+
+    fn buffer_barrier(&self, transfer: &BufferTransfer) -> vk::BufferMemoryBarrier2<'static> {
+        let (src_queue_family_index, dst_queue_family_index) = self.queue_family_indices();
+        vk::BufferMemoryBarrier2::default()
+            .src_queue_family_index(src_queue_family_index)
+            .dst_queue_family_index(dst_queue_family_index)
+            .buffer(transfer.buffer)
+            .offset(transfer.offset)
+            .size(transfer.size)
+    }
+
+    fn image_barrier(&self, transfer: &ImageTransfer) -> vk::ImageMemoryBarrier2<'static> {
+        let (src_queue_family_index, dst_queue_family_index) = self.queue_family_indices();
+        vk::ImageMemoryBarrier2::default()
+            .src_queue_family_index(src_queue_family_index)
+            .dst_queue_family_index(dst_queue_family_index)
+            .image(transfer.image)
+            .subresource_range(
+                vk::ImageSubresourceRange::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .base_mip_level(0)
+                    .level_count(vk::REMAINING_MIP_LEVELS)
+                    .base_array_layer(0)
+                    .layer_count(vk::REMAINING_ARRAY_LAYERS),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::Handle;
+
+    use super::*;
+
+    fn transfers(src_queue_family_index: u32, dst_queue_family_index: u32) -> OwnershipTransfers {
+        let mut transfers = OwnershipTransfers::new(src_queue_family_index, dst_queue_family_index);
+        transfers.buffers.push(BufferTransfer {
+            buffer: vk::Buffer::from_raw(1),
+            offset: 64,
+            size: 256,
+            dst_stage: vk::PipelineStageFlags2::VERTEX_INPUT,
+            dst_access: vk::AccessFlags2::VERTEX_ATTRIBUTE_READ,
+        });
+        transfers.add_image(
+            vk::Image::from_raw(2),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+        // Another mip level of the same image.
+        transfers.add_image(vk::Image::from_raw(2), vk::ImageLayout::GENERAL);
+        transfers
+    }
+
+    #[test]
+    fn ownership_is_released_and_acquired_between_families() {
+        let transfers = transfers(1, 0);
+        assert_eq!(transfers.images.len(), 1);
+
+        let release_buffers = transfers.release_buffer_barriers();
+        let acquire_buffers = transfers.acquire_buffer_barriers();
+        assert_eq!(release_buffers.len(), 1);
+        assert_eq!(acquire_buffers.len(), 1);
+        for barrier in [&release_buffers[0], &acquire_buffers[0]] {
+            assert_eq!(barrier.src_queue_family_index, 1);
+            assert_eq!(barrier.dst_queue_family_index, 0);
+            assert_eq!((barrier.offset, barrier.size), (64, 256));
+        }
+        assert_eq!(
+            release_buffers[0].src_access_mask,
+            vk::AccessFlags2::TRANSFER_WRITE
+        );
+        assert_eq!(release_buffers[0].dst_access_mask, vk::AccessFlags2::NONE);
+        assert_eq!(acquire_buffers[0].src_access_mask, vk::AccessFlags2::NONE);
+        assert_eq!(
+            acquire_buffers[0].dst_stage_mask,
+            vk::PipelineStageFlags2::VERTEX_INPUT
+        );
+
+        let release_images = transfers.release_image_barriers();
+        let acquire_images = transfers.acquire_image_barriers();
+        assert_eq!(release_images.len(), 1);
+        assert_eq!(acquire_images.len(), 1);
+        for barrier in [&release_images[0], &acquire_images[0]] {
+            assert_eq!(barrier.src_queue_family_index, 1);
+            assert_eq!(barrier.dst_queue_family_index, 0);
+            assert_eq!(barrier.old_layout, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+            assert_eq!(
+                barrier.new_layout,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            );
+        }
+        let sampled = ImageState::from_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        assert_eq!(acquire_images[0].dst_stage_mask, sampled.stage);
+        assert_eq!(acquire_images[0].dst_access_mask, sampled.access);
+    }
+
+    #[test]
+    fn same_family_uploads_are_only_made_visible() {
+        let transfers = transfers(0, 0);
+
+        assert!(transfers.release_buffer_barriers().is_empty());
+        let acquire_buffers = transfers.acquire_buffer_barriers();
+        assert_eq!(
+            acquire_buffers[0].src_queue_family_index,
+            vk::QUEUE_FAMILY_IGNORED
+        );
+        assert_eq!(
+            acquire_buffers[0].dst_access_mask,
+            vk::AccessFlags2::VERTEX_ATTRIBUTE_READ
+        );
+
+        // The release still transitions the image, the acquire keeps its layout.
+        let release_images = transfers.release_image_barriers();
+        assert_eq!(
+            release_images[0].src_queue_family_index,
+            vk::QUEUE_FAMILY_IGNORED
+        );
+        assert_eq!(
+            release_images[0].new_layout,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        );
+        let acquire_images = transfers.acquire_image_barriers();
+        assert_eq!(
+            acquire_images[0].old_layout,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        );
+        assert_eq!(
+            acquire_images[0].dst_queue_family_index,
+            vk::QUEUE_FAMILY_IGNORED
+        );
+    }
+}
@@ -7,6 +7,7 @@ use ash::vk;
 use pyrite_app::resource::Resource;
 
 use crate::{
+    executor::{QueueExecutor, SubmitError},
     objects::{
         image::{self, util::ImageViewCreateInfo, BorrowedImageCreateInfo},
         BorrowedImage, Semaphore,
@@ -28,6 +29,9 @@ impl VulkanResource for SwapchainInstanceInternal {}
 pub struct SwapchainInfo {
     extent: Extent2D,
     format: vk::Format,
+    color_space: vk::ColorSpaceKHR,
+    surface_format: SurfaceFormat,
+    present_mode: PresentMode,
 }
 
 impl SwapchainInfo {
@@ -38,6 +42,120 @@ impl SwapchainInfo {
     pub fn format(&self) -> vk::Format {
         self.format
     }
+
+    pub fn color_space(&self) -> vk::ColorSpaceKHR {
+        self.color_space
+    }
+
+    /// The kind of surface format that was chosen from the preferences, [`SurfaceFormat::Other`]
+    /// if none of them were supported.
+    pub fn surface_format(&self) -> SurfaceFormat {
+        self.surface_format
+    }
+
+    /// The present mode that was chosen from the [`PresentationConfig`].
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+}
+
+/// A kind of surface format, each matching a few formats in a specific color space.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum SurfaceFormat {
+    /// 8 bit sRGB formats, which encode the gamma when written to.
+    Srgb,
+    /// 8 bit unorm formats in the sRGB color space, for renderers that encode the gamma
+    /// themselves.
+    Unorm,
+    /// 10 bit formats with the PQ transfer function in the BT.2020 color space.
+    Hdr10,
+    /// 16 bit float formats in the linear extended sRGB color space.
+    ScRgb,
+    /// Whatever format the surface reported first, used if none of the preferences are
+    /// supported.
+    Other,
+}
+
+impl SurfaceFormat {
+    fn matches(self, surface_format: &vk::SurfaceFormatKHR) -> bool {
+        let (formats, color_space): (&[vk::Format], _) = match self {
+            Self::Srgb => (
+                &[vk::Format::B8G8R8A8_SRGB, vk::Format::R8G8B8A8_SRGB],
+                vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            ),
+            Self::Unorm => (
+                &[vk::Format::B8G8R8A8_UNORM, vk::Format::R8G8B8A8_UNORM],
+                vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            ),
+            Self::Hdr10 => (
+                &[
+                    vk::Format::A2B10G10R10_UNORM_PACK32,
+                    vk::Format::A2R10G10B10_UNORM_PACK32,
+                ],
+                vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+            ),
+            Self::ScRgb => (
+                &[vk::Format::R16G16B16A16_SFLOAT],
+                vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+            ),
+            Self::Other => return true,
+        };
+
+        surface_format.color_space == color_space && formats.contains(&surface_format.format)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Presents immediately without vsync, which may tear.
+    Immediate,
+    /// Vsync that replaces the queued image instead of blocking, low latency without tearing.
+    Mailbox,
+    /// Vsync that presents immediately if a frame missed the vertical blank, which may tear.
+    FifoRelaxed,
+    /// Vsync, always supported.
+    Fifo,
+}
+
+impl PresentMode {
+    fn to_vk(self) -> vk::PresentModeKHR {
+        match self {
+            Self::Immediate => vk::PresentModeKHR::IMMEDIATE,
+            Self::Mailbox => vk::PresentModeKHR::MAILBOX,
+            Self::FifoRelaxed => vk::PresentModeKHR::FIFO_RELAXED,
+            Self::Fifo => vk::PresentModeKHR::FIFO,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PresentationConfig {
+    /// The present modes in order of preference, the first one the surface supports is used and
+    /// [`PresentMode::Fifo`] is the fallback if none are.
+    pub present_modes: Vec<PresentMode>,
+}
+
+impl PresentationConfig {
+    /// Vsync uses fifo, otherwise the lowest latency mode that's supported is used.
+    pub fn vsync(enabled: bool) -> Self {
+        let present_modes = if enabled {
+            vec![PresentMode::Fifo]
+        } else {
+            vec![
+                PresentMode::Immediate,
+                PresentMode::Mailbox,
+                PresentMode::FifoRelaxed,
+            ]
+        };
+
+        Self { present_modes }
+    }
+}
+
+impl Default for PresentationConfig {
+    fn default() -> Self {
+        Self::vsync(true)
+    }
 }
 
 pub struct SwapchainInstance {
@@ -49,7 +167,11 @@ pub struct SwapchainInstance {
 pub struct SwapchainCreateInfo {
     pub width: u32,
     pub height: u32,
-    pub preferred_present_mode: ash::vk::PresentModeKHR,
+    pub presentation: PresentationConfig,
+    /// The surface formats in order of preference, the first format the surface reported is
+    /// used if none are supported. The HDR formats are only available if the instance supports
+    /// `VK_EXT_swapchain_colorspace`.
+    pub surface_formats: Vec<SurfaceFormat>,
     pub preferred_image_count: u32,
     pub image_usage: ash::vk::ImageUsageFlags,
     pub create_image_views: bool,
@@ -94,24 +216,52 @@ impl SwapchainInstance {
                 .expect("Failed to get supported surface capabilities")
         };
 
-        let format = supported_surface_formats
-            .first()
+        let (surface_format, format) = info
+            .surface_formats
+            .iter()
+            .chain(std::iter::once(&SurfaceFormat::Other))
+            .find_map(|preference| {
+                supported_surface_formats
+                    .iter()
+                    .find(|supported| preference.matches(supported))
+                    .map(|supported| (*preference, supported))
+            })
             .expect("No supported formats found for the swapchain.");
 
-        let image_count = min(
-            max(
-                surface_capabilities.min_image_count,
-                info.preferred_image_count,
-            ),
-            surface_capabilities.max_image_count,
+        // A max image count of zero means there is no limit.
+        let mut image_count = max(
+            surface_capabilities.min_image_count,
+            info.preferred_image_count,
         );
+        if surface_capabilities.max_image_count > 0 {
+            image_count = min(image_count, surface_capabilities.max_image_count);
+        }
 
-        let present_mode = if supported_present_modes.contains(&info.preferred_present_mode) {
-            info.preferred_present_mode
+        // The surface dictates the extent unless its current extent is the special value
+        // u32::MAX, in which case the requested size is clamped to the supported range.
+        let extent = if surface_capabilities.current_extent.width != u32::MAX {
+            surface_capabilities.current_extent
         } else {
-            ash::vk::PresentModeKHR::FIFO
+            vk::Extent2D {
+                width: info.width.clamp(
+                    surface_capabilities.min_image_extent.width,
+                    surface_capabilities.max_image_extent.width,
+                ),
+                height: info.height.clamp(
+                    surface_capabilities.min_image_extent.height,
+                    surface_capabilities.max_image_extent.height,
+                ),
+            }
         };
 
+        let present_mode = info
+            .presentation
+            .present_modes
+            .iter()
+            .copied()
+            .find(|present_mode| supported_present_modes.contains(&present_mode.to_vk()))
+            .unwrap_or(PresentMode::Fifo);
+
         let swapchain = {
             let swapchain_loader =
                 ash::extensions::khr::Swapchain::new(vulkan.instance(), vulkan.device());
@@ -123,15 +273,12 @@ impl SwapchainInstance {
                         .image_array_layers(1)
                         .image_color_space(format.color_space)
                         .image_format(format.format)
-                        .image_extent(ash::vk::Extent2D {
-                            width: info.width,
-                            height: info.height,
-                        })
+                        .image_extent(extent)
                         .image_usage(info.image_usage)
                         .image_sharing_mode(ash::vk::SharingMode::EXCLUSIVE)
                         .pre_transform(ash::vk::SurfaceTransformFlagsKHR::IDENTITY)
                         .composite_alpha(ash::vk::CompositeAlphaFlagsKHR::OPAQUE)
-                        .present_mode(present_mode)
+                        .present_mode(present_mode.to_vk())
                         .clipped(true)
                         .old_swapchain(old_swapchain.unwrap_or(ash::vk::SwapchainKHR::null())),
                     None,
@@ -181,10 +328,13 @@ impl SwapchainInstance {
         Self {
             info: SwapchainInfo {
                 extent: Extent2D {
-                    width: info.width,
-                    height: info.height,
+                    width: extent.width,
+                    height: extent.height,
                 },
                 format: format.format,
+                color_space: format.color_space,
+                surface_format,
+                present_mode,
             },
             swapchain,
             images,
@@ -261,6 +411,7 @@ impl Swapchain {
             Ok((image_index, _)) => Ok(image_index),
             Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Err(SwapchainError::OutOfDate),
             Err(vk::Result::SUBOPTIMAL_KHR) => Err(SwapchainError::SubOptimal),
+            Err(vk::Result::ERROR_DEVICE_LOST) => Err(SwapchainError::DeviceLost),
             Err(_) => Err(SwapchainError::Unknown),
         }
     }
@@ -274,5 +425,142 @@ impl Swapchain {
 pub enum SwapchainError {
     OutOfDate,
     SubOptimal,
+    DeviceLost,
     Unknown,
 }
+
+/// Owns the swapchain and its create info, recreating it whenever the window is resized or the
+/// swapchain goes out of date so callers only need to acquire and present images.
+///
+/// Renderers holding resources sized to the swapchain should compare
+/// [`SwapchainManager::generation`] every frame and rebuild them when it changes.
+#[derive(Resource)]
+pub struct SwapchainManager {
+    create_info: SwapchainCreateInfo,
+    swapchain: Swapchain,
+    needs_recreate: bool,
+    generation: u64,
+}
+
+impl SwapchainManager {
+    pub fn new(vulkan: &Vulkan, create_info: SwapchainCreateInfo) -> Self {
+        let mut swapchain = Swapchain::new();
+        swapchain.refresh(vulkan, &create_info);
+
+        Self {
+            create_info,
+            swapchain,
+            needs_recreate: false,
+            generation: 0,
+        }
+    }
+
+    /// Recreates the swapchain with the new size before the next image is acquired, a zero size
+    /// means the window is minimized and pauses acquiring images until it is resized again.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if self.create_info.width == width && self.create_info.height == height {
+            return;
+        }
+
+        self.create_info.width = width;
+        self.create_info.height = height;
+        self.needs_recreate = true;
+    }
+
+    /// Switches the present mode, recreating the swapchain before the next image is acquired.
+    pub fn set_presentation(&mut self, presentation: PresentationConfig) {
+        if self.create_info.presentation == presentation {
+            return;
+        }
+
+        self.create_info.presentation = presentation;
+        self.needs_recreate = true;
+    }
+
+    /// Switches to `present_mode`, falling back to [`PresentMode::Fifo`] if it isn't supported.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        self.set_presentation(PresentationConfig {
+            present_modes: vec![present_mode],
+        });
+    }
+
+    /// Toggles vsync, see [`PresentationConfig::vsync`].
+    pub fn set_vsync(&mut self, enabled: bool) {
+        self.set_presentation(PresentationConfig::vsync(enabled));
+    }
+
+    /// Acquires the next image, recreating the swapchain first if it is out of date.
+    ///
+    /// Returns `None` while the window is minimized or once the device was lost, in which case
+    /// nothing should be rendered or presented this frame and `signal_semaphore` isn't signaled.
+    pub fn acquire(&mut self, vulkan: &Vulkan, signal_semaphore: &Semaphore) -> Option<u32> {
+        if self.is_minimized() {
+            return None;
+        }
+
+        // Recreating once is enough unless the window is resized mid recreation, in which case the
+        // next frame tries again.
+        for _ in 0..2 {
+            if self.needs_recreate {
+                self.recreate(vulkan);
+            }
+
+            match self.swapchain.get_next_image_index(signal_semaphore) {
+                Ok(image_index) => return Some(image_index),
+                Err(SwapchainError::OutOfDate) => self.needs_recreate = true,
+                Err(SwapchainError::DeviceLost) => {
+                    vulkan.report_device_lost();
+                    return None;
+                }
+                Err(err) => panic!("Failed to acquire the next swapchain image: {:?}", err),
+            }
+        }
+
+        None
+    }
+
+    /// Presents the image through the executor's queue, scheduling a recreation if the swapchain
+    /// is out of date or suboptimal. Other errors, like a lost device, are returned.
+    pub fn present<const N: usize>(
+        &mut self,
+        executor: &mut QueueExecutor<N>,
+        image_index: u32,
+        wait_semaphores: Vec<&Semaphore>,
+    ) -> Result<(), SubmitError> {
+        match executor.present(&self.swapchain, image_index, wait_semaphores) {
+            Ok(()) => Ok(()),
+            Err(SubmitError::OutOfDate | SubmitError::Suboptimal) => {
+                self.needs_recreate = true;
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn image(&self, index: usize) -> &BorrowedImage {
+        self.swapchain.image(index)
+    }
+
+    pub fn info(&self) -> &SwapchainInfo {
+        self.swapchain.instance().info()
+    }
+
+    pub fn swapchain(&self) -> &Swapchain {
+        &self.swapchain
+    }
+
+    /// Incremented every time the swapchain is recreated.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.create_info.width == 0 || self.create_info.height == 0
+    }
+
+    fn recreate(&mut self, vulkan: &Vulkan) {
+        self.swapchain.refresh(vulkan, &self.create_info);
+        self.needs_recreate = false;
+        self.generation += 1;
+    }
+}
@@ -39,6 +39,70 @@ where
     }
 }
 
+/// Keeps the resources used by the work of one frame in flight alive until the frame finished
+/// executing, shared by the [`crate::executor::QueueExecutor`] and renderers managing their own
+/// frames.
+#[derive(Default)]
+pub struct FrameResourceArena {
+    resources: Vec<GenericResourceDep>,
+    dropped_count: usize,
+}
+
+impl FrameResourceArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, resource: GenericResourceDep) {
+        self.resources.push(resource);
+    }
+
+    /// Keeps the resource alive if it wasn't dropped yet, such as the weak dependencies recorded
+    /// by a command buffer. A dropped resource is counted and logged instead of panicking, since
+    /// the frame is already recorded and there's nothing left to keep alive.
+    pub fn push_weak(&mut self, resource: &WeakGenericResourceDep) {
+        match resource.upgrade() {
+            Some(resource) => self.resources.push(resource),
+            None => {
+                self.dropped_count += 1;
+                log::warn!("A resource used by the frame was dropped before it was submitted.");
+            }
+        }
+    }
+
+    pub fn extend_weak(&mut self, resources: impl IntoIterator<Item = WeakGenericResourceDep>) {
+        for resource in resources {
+            self.push_weak(&resource);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    /// The number of weak dependencies that were already dropped when pushed since the arena was
+    /// last cleared.
+    pub fn dropped_count(&self) -> usize {
+        self.dropped_count
+    }
+
+    /// Releases the resources, call it once the frame's fence was signalled.
+    pub fn clear(&mut self) {
+        self.resources.clear();
+        self.dropped_count = 0;
+    }
+}
+
+impl Extend<GenericResourceDep> for FrameResourceArena {
+    fn extend<T: IntoIterator<Item = GenericResourceDep>>(&mut self, resources: T) {
+        self.resources.extend(resources);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Extent2D {
     pub width: u32,
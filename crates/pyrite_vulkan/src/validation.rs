@@ -0,0 +1,202 @@
+use std::{
+    collections::VecDeque,
+    ffi::CStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use ash::vk;
+
+/// What happens when the validation layers report an error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationErrorAction {
+    /// Logs the error and keeps running.
+    Log,
+    /// Logs the error and panics, for CI runs which should fail on the first error. The panic
+    /// aborts the process since it can't unwind through the driver.
+    Panic,
+}
+
+/// How validation messages are filtered, reported and kept, set in the
+/// [`crate::VulkanConfig`].
+#[derive(Clone, Debug)]
+pub struct ValidationPolicy {
+    /// The messages to drop, such as false positives of the validation layers, by id number or id
+    /// name like `"VUID-vkCmdDraw-None-02699"`.
+    pub ignored_messages: Vec<ValidationMessageId>,
+    pub error_action: ValidationErrorAction,
+    /// The number of recent messages kept for [`crate::VulkanInstance::validation_messages`].
+    pub message_capacity: usize,
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        Self {
+            ignored_messages: Vec::new(),
+            error_action: ValidationErrorAction::Log,
+            message_capacity: 64,
+        }
+    }
+}
+
+impl ValidationPolicy {
+    pub fn ignore(mut self, id: ValidationMessageId) -> Self {
+        self.ignored_messages.push(id);
+        self
+    }
+
+    pub fn error_action(mut self, error_action: ValidationErrorAction) -> Self {
+        self.error_action = error_action;
+        self
+    }
+
+    pub fn message_capacity(mut self, message_capacity: usize) -> Self {
+        self.message_capacity = message_capacity;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationMessageId {
+    Number(i32),
+    Name(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ValidationSeverity {
+    Verbose,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct ValidationMessage {
+    pub severity: ValidationSeverity,
+    pub id_number: i32,
+    pub id_name: String,
+    pub message: String,
+}
+
+/// The validation messages received by the debug messenger, shared with its callback through
+/// the user data pointer.
+pub(crate) struct ValidationState {
+    policy: ValidationPolicy,
+    messages: Mutex<VecDeque<ValidationMessage>>,
+    error_count: AtomicU64,
+    warning_count: AtomicU64,
+}
+
+impl ValidationState {
+    pub fn new(policy: ValidationPolicy) -> Self {
+        Self {
+            messages: Mutex::new(VecDeque::with_capacity(policy.message_capacity)),
+            policy,
+            error_count: AtomicU64::new(0),
+            warning_count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn messages(&self) -> Vec<ValidationMessage> {
+        self.messages.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn take_messages(&self) -> Vec<ValidationMessage> {
+        self.messages.lock().unwrap().drain(..).collect()
+    }
+
+    pub fn error_count(&self) -> u64 {
+        self.error_count.load(Ordering::Relaxed)
+    }
+
+    pub fn warning_count(&self) -> u64 {
+        self.warning_count.load(Ordering::Relaxed)
+    }
+
+    fn is_ignored(&self, id_number: i32, id_name: &str) -> bool {
+        self.policy.ignored_messages.iter().any(|id| match id {
+            ValidationMessageId::Number(number) => *number == id_number,
+            ValidationMessageId::Name(name) => name == id_name,
+        })
+    }
+
+    fn submit(&self, message: ValidationMessage) {
+        match message.severity {
+            ValidationSeverity::Error => self.error_count.fetch_add(1, Ordering::Relaxed),
+            ValidationSeverity::Warning => self.warning_count.fetch_add(1, Ordering::Relaxed),
+            _ => 0,
+        };
+
+        let level = match message.severity {
+            ValidationSeverity::Error => log::Level::Error,
+            ValidationSeverity::Warning => log::Level::Warn,
+            ValidationSeverity::Info => log::Level::Info,
+            ValidationSeverity::Verbose => log::Level::Trace,
+        };
+        log::log!(
+            target: "pyrite_vulkan::validation",
+            level,
+            "[{}] {}",
+            message.id_name,
+            message.message
+        );
+
+        let panic_message = (message.severity == ValidationSeverity::Error
+            && self.policy.error_action == ValidationErrorAction::Panic)
+            .then(|| format!("Vulkan validation error: {}", message.message));
+
+        if self.policy.message_capacity > 0 {
+            let mut messages = self.messages.lock().unwrap();
+            if messages.len() == self.policy.message_capacity {
+                messages.pop_front();
+            }
+            messages.push_back(message);
+        }
+
+        if let Some(panic_message) = panic_message {
+            panic!("{}", panic_message);
+        }
+    }
+}
+
+pub(crate) unsafe extern "system" fn debug_messenger_callback(
+    message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    _message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    p_user_data: *mut std::ffi::c_void,
+) -> vk::Bool32 {
+    let state = &*(p_user_data as *const ValidationState);
+    let callback_data = &*p_callback_data;
+
+    let to_string = |ptr: *const std::ffi::c_char| {
+        if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr).to_string_lossy().into_owned()
+        }
+    };
+    let id_name = to_string(callback_data.p_message_id_name);
+    if state.is_ignored(callback_data.message_id_number, &id_name) {
+        return vk::FALSE;
+    }
+
+    let severity = if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+        ValidationSeverity::Error
+    } else if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+        ValidationSeverity::Warning
+    } else if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+        ValidationSeverity::Info
+    } else {
+        ValidationSeverity::Verbose
+    };
+    state.submit(ValidationMessage {
+        severity,
+        id_number: callback_data.message_id_number,
+        id_name,
+        message: to_string(callback_data.p_message),
+    });
+
+    vk::FALSE
+}
@@ -1,9 +1,21 @@
-use std::{collections::HashMap, ffi::CString, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{CStr, CString},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
 
 use ash::vk;
 use pyrite_app::resource::Resource;
 use raw_window_handle::HasWindowHandle;
 
+use crate::{
+    validation::{debug_messenger_callback, ValidationMessage, ValidationState},
+    ValidationPolicy, VulkanError, VulkanFeature, VulkanResult,
+};
+
 // The default queue name.
 pub const DEFAULT_QUEUE: &str = "pyrite_vulkan_default";
 
@@ -16,6 +28,8 @@ pub enum QueueCapability {
     Compute,
     Transfer,
     Present,
+    /// Binding memory to sparse resources, e.g. [`crate::objects::SparseImage`] pages.
+    SparseBinding,
 }
 
 #[derive(Clone, Debug)]
@@ -38,7 +52,8 @@ pub enum QueueResolution {
     /// The fallback queue to use if this queue can't be constructed.
     Fallback(String),
 
-    // Panic if the queue can't be constructed.
+    /// Fail creating the instance if the queue can't be constructed, which panics when using
+    /// [`VulkanInstance::new`].
     Panic,
 }
 
@@ -73,7 +88,17 @@ pub struct VulkanConfig<'a> {
     pub app_name: String,
     pub queues: Vec<QueueConfig>,
     pub enable_validation: bool,
+    /// How validation messages are handled, only used if validation is enabled.
+    pub validation_policy: ValidationPolicy,
     pub swapchain_support: SwapchainSupport<'a>,
+    /// The features to enable, creating the device fails if any aren't supported.
+    pub features: Vec<VulkanFeature>,
+    /// The features to enable if the device supports them, check which were enabled with
+    /// [`VulkanInstance::is_feature_enabled`].
+    pub optional_features: Vec<VulkanFeature>,
+    /// Additional device extensions to enable, on top of the ones required by the swapchain and
+    /// the requested features.
+    pub extensions: Vec<CString>,
 }
 
 impl Default for VulkanConfig<'_> {
@@ -92,7 +117,11 @@ impl Default for VulkanConfig<'_> {
                 resolution: QueueResolution::Panic,
             }],
             enable_validation: true,
+            validation_policy: ValidationPolicy::default(),
             swapchain_support: SwapchainSupport::None,
+            features: Vec::new(),
+            optional_features: Vec::new(),
+            extensions: Vec::new(),
         }
     }
 }
@@ -133,6 +162,7 @@ pub struct VulkanPhysicalDevice {
     features: vk::PhysicalDeviceFeatures,
     memory_properties: vk::PhysicalDeviceMemoryProperties,
     queue_families: Vec<vk::QueueFamilyProperties>,
+    extensions: Vec<CString>,
 }
 
 impl VulkanPhysicalDevice {
@@ -155,6 +185,81 @@ impl VulkanPhysicalDevice {
     pub fn queue_families(&self) -> &Vec<vk::QueueFamilyProperties> {
         &self.queue_families
     }
+
+    pub fn supports_extension(&self, extension_name: &CStr) -> bool {
+        self.extensions
+            .iter()
+            .any(|extension| extension.as_c_str() == extension_name)
+    }
+}
+
+/// The ray tracing loaders and limits, available if [`VulkanFeature::RayTracing`] was enabled.
+pub struct VulkanRayTracing {
+    acceleration_structure: ash::extensions::khr::AccelerationStructure,
+    ray_tracing_pipeline: ash::extensions::khr::RayTracingPipeline,
+    properties: RayTracingProperties,
+}
+
+/// The limits of `VkPhysicalDeviceRayTracingPipelinePropertiesKHR` and
+/// `VkPhysicalDeviceAccelerationStructurePropertiesKHR` needed to build acceleration structures
+/// and shader binding tables.
+#[derive(Clone, Copy, Debug)]
+pub struct RayTracingProperties {
+    pub shader_group_handle_size: u32,
+    pub shader_group_handle_alignment: u32,
+    pub shader_group_base_alignment: u32,
+    pub max_ray_recursion_depth: u32,
+    pub min_acceleration_structure_scratch_offset_alignment: u32,
+}
+
+impl VulkanRayTracing {
+    fn new(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: &VulkanPhysicalDevice,
+    ) -> Self {
+        let mut ray_tracing_pipeline_properties =
+            vk::PhysicalDeviceRayTracingPipelinePropertiesKHR::default();
+        let mut acceleration_structure_properties =
+            vk::PhysicalDeviceAccelerationStructurePropertiesKHR::default();
+        let mut properties2 = vk::PhysicalDeviceProperties2::default()
+            .push_next(&mut ray_tracing_pipeline_properties)
+            .push_next(&mut acceleration_structure_properties);
+        unsafe {
+            instance
+                .get_physical_device_properties2(physical_device.physical_device, &mut properties2);
+        }
+
+        Self {
+            acceleration_structure: ash::extensions::khr::AccelerationStructure::new(
+                instance, device,
+            ),
+            ray_tracing_pipeline: ash::extensions::khr::RayTracingPipeline::new(instance, device),
+            properties: RayTracingProperties {
+                shader_group_handle_size: ray_tracing_pipeline_properties.shader_group_handle_size,
+                shader_group_handle_alignment: ray_tracing_pipeline_properties
+                    .shader_group_handle_alignment,
+                shader_group_base_alignment: ray_tracing_pipeline_properties
+                    .shader_group_base_alignment,
+                max_ray_recursion_depth: ray_tracing_pipeline_properties.max_ray_recursion_depth,
+                min_acceleration_structure_scratch_offset_alignment:
+                    acceleration_structure_properties
+                        .min_acceleration_structure_scratch_offset_alignment,
+            },
+        }
+    }
+
+    pub fn acceleration_structure(&self) -> &ash::extensions::khr::AccelerationStructure {
+        &self.acceleration_structure
+    }
+
+    pub fn ray_tracing_pipeline(&self) -> &ash::extensions::khr::RayTracingPipeline {
+        &self.ray_tracing_pipeline
+    }
+
+    pub fn properties(&self) -> &RayTracingProperties {
+        &self.properties
+    }
 }
 
 pub struct VulkanQueue {
@@ -176,20 +281,40 @@ pub struct VulkanInstance {
     entry: ash::Entry,
     instance: ash::Instance,
     debug_utils: Option<VulkanDebugUtils>,
+    validation: Option<Arc<ValidationState>>,
     surface: Option<VulkanSurface>,
     physical_device: VulkanPhysicalDevice,
     device: ash::Device,
     queues: HashMap<String, VulkanQueue>,
     queue_aliases: HashMap<String, String>,
+    dynamic_rendering: Option<ash::extensions::khr::DynamicRendering>,
+    synchronization2: Option<ash::extensions::khr::Synchronization2>,
+    ray_tracing: Option<VulkanRayTracing>,
+    mesh_shader: Option<ash::extensions::ext::MeshShader>,
+    enabled_features: HashSet<VulkanFeature>,
+    enabled_extensions: Vec<CString>,
+    device_lost: AtomicBool,
+    device_lost_hooks: Mutex<Vec<DeviceLostHook>>,
 }
 
+/// Called once the device was lost, see [`VulkanInstance::on_device_lost`].
+pub type DeviceLostHook = Box<dyn Fn(&VulkanInstance) + Send + Sync>;
+
 impl VulkanInstance {
+    /// # Panics
+    /// If the instance or device can't be created, see [`VulkanInstance::try_new`].
     pub fn new(config: &VulkanConfig) -> Self {
+        Self::try_new(config).unwrap_or_else(|err| panic!("Failed to create Vulkan: {}", err))
+    }
+
+    /// Creates the instance and device, failing if Vulkan can't be loaded, the validation layers
+    /// aren't installed or the device doesn't support the requested features and extensions.
+    pub fn try_new(config: &VulkanConfig) -> VulkanResult<Self> {
         if config.enable_validation {
-            println!("[pyrite_vulkan]: Validation enabled.");
+            log::info!("Validation enabled.");
         }
 
-        let entry = unsafe { ash::Entry::load().expect("Failed to load Vulkan.") };
+        let entry = unsafe { ash::Entry::load()? };
 
         let instance = {
             let app_name = CString::new(config.app_name.clone()).unwrap();
@@ -207,8 +332,16 @@ impl VulkanInstance {
 
             // Add validation layers and debug utils if validation is enabled.
             if config.enable_validation {
+                let validation_layer_name = c"VK_LAYER_KHRONOS_validation";
+                let supported_layers = unsafe { entry.enumerate_instance_layer_properties()? };
+                if !supported_layers.iter().any(|layer| unsafe {
+                    CStr::from_ptr(layer.layer_name.as_ptr()) == validation_layer_name
+                }) {
+                    return Err(VulkanError::MissingLayer(validation_layer_name.to_owned()));
+                }
+
                 instance_extensions.push(ash::extensions::ext::DebugUtils::NAME.to_owned());
-                instance_layers.push(CString::new("VK_LAYER_KHRONOS_validation").unwrap());
+                instance_layers.push(validation_layer_name.to_owned());
             }
 
             let mut ptr_instance_extensions = instance_extensions
@@ -223,11 +356,24 @@ impl VulkanInstance {
             // Add window extensions if swapchain support is enabled.
             if let SwapchainSupport::Supported(has_display_handle, _) = config.swapchain_support {
                 let window_extensions = ash_window::enumerate_required_extensions(
-                    has_display_handle.display_handle().unwrap(),
-                )
-                .unwrap();
+                    has_display_handle.display_handle()?,
+                )?;
 
                 ptr_instance_extensions.extend(window_extensions);
+
+                // Needed for the HDR swapchain color spaces, which are optional.
+                let swapchain_colorspace_name = c"VK_EXT_swapchain_colorspace";
+                let supported_instance_extensions =
+                    unsafe { entry.enumerate_instance_extension_properties(None)? };
+                if supported_instance_extensions
+                    .iter()
+                    .any(|extension| unsafe {
+                        CStr::from_ptr(extension.extension_name.as_ptr())
+                            == swapchain_colorspace_name
+                    })
+                {
+                    ptr_instance_extensions.push(swapchain_colorspace_name.as_ptr());
+                }
             }
 
             let instance_create_info = vk::InstanceCreateInfo::default()
@@ -235,18 +381,17 @@ impl VulkanInstance {
                 .enabled_extension_names(&ptr_instance_extensions)
                 .enabled_layer_names(&ptr_instance_layers);
 
-            unsafe {
-                entry
-                    .create_instance(&instance_create_info, None)
-                    .expect("Failed to create Vulkan instance.")
-            }
+            unsafe { entry.create_instance(&instance_create_info, None)? }
         };
 
-        let debug_utils = match config.enable_validation {
-            true => {
+        let validation = config
+            .enable_validation
+            .then(|| Arc::new(ValidationState::new(config.validation_policy.clone())));
+        let debug_utils = match &validation {
+            Some(validation) => {
                 let debug_utils_loader = ash::extensions::ext::DebugUtils::new(&entry, &instance);
                 let debug_utils_messenger = {
-                    let debug_utils_messenger_create_info =
+                    let mut debug_utils_messenger_create_info =
                         vk::DebugUtilsMessengerCreateInfoEXT::default()
                             .message_severity(
                                 vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
@@ -256,12 +401,17 @@ impl VulkanInstance {
                                 vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
                                     | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION,
                             )
-                            .pfn_user_callback(Some(Self::debug_messenger_callback));
+                            .pfn_user_callback(Some(debug_messenger_callback));
+                    // The messenger is never destroyed, so its reference to the state is leaked to
+                    // keep the pointer valid for as long as the callback can be called.
+                    debug_utils_messenger_create_info.p_user_data =
+                        Arc::into_raw(validation.clone()) as *mut std::ffi::c_void;
 
                     unsafe {
-                        debug_utils_loader
-                            .create_debug_utils_messenger(&debug_utils_messenger_create_info, None)
-                            .expect("Failed to create Vulkan debug utils messenger.")
+                        debug_utils_loader.create_debug_utils_messenger(
+                            &debug_utils_messenger_create_info,
+                            None,
+                        )?
                     }
                 };
 
@@ -270,7 +420,7 @@ impl VulkanInstance {
                     debug_utils_messenger,
                 })
             }
-            false => None,
+            None => None,
         };
 
         let surface = match config.swapchain_support {
@@ -281,11 +431,10 @@ impl VulkanInstance {
                     ash_window::create_surface(
                         &entry,
                         &instance,
-                        has_display_handle.display_handle().unwrap(),
-                        has_window_handle.window_handle().unwrap(),
+                        has_display_handle.display_handle()?,
+                        has_window_handle.window_handle()?,
                         None,
-                    )
-                    .expect("Failed to create Vulkan surface.")
+                    )?
                 };
 
                 Some(VulkanSurface {
@@ -296,13 +445,11 @@ impl VulkanInstance {
         };
 
         let physical_device = {
-            let physical_devices = unsafe {
-                instance
-                    .enumerate_physical_devices()
-                    .expect("Failed to enumerate physical devices.")
-            };
+            let physical_devices = unsafe { instance.enumerate_physical_devices()? };
 
-            let chosen_device = physical_devices.first().unwrap().clone();
+            let chosen_device = *physical_devices
+                .first()
+                .ok_or(VulkanError::NoPhysicalDevice)?;
 
             VulkanPhysicalDevice {
                 physical_device: chosen_device,
@@ -314,14 +461,68 @@ impl VulkanInstance {
                 queue_families: unsafe {
                     instance.get_physical_device_queue_family_properties(chosen_device)
                 },
+                extensions: unsafe {
+                    instance.enumerate_device_extension_properties(chosen_device)?
+                }
+                .iter()
+                .map(|extension| unsafe {
+                    CStr::from_ptr(extension.extension_name.as_ptr()).to_owned()
+                })
+                .collect(),
             }
         };
 
+        let supported_features = crate::features::supported_features(&instance, &physical_device);
+        let mut enabled_features = config.features.iter().copied().collect::<HashSet<_>>();
+        for feature in &config.optional_features {
+            if supported_features.contains(feature) {
+                enabled_features.insert(*feature);
+            } else {
+                log::info!("The optional feature {:?} isn't supported.", feature);
+            }
+        }
+        if supported_features.contains(&VulkanFeature::Synchronization2) {
+            enabled_features.insert(VulkanFeature::Synchronization2);
+        } else {
+            log::info!("Synchronization2 isn't supported, falling back to the legacy barriers.");
+        }
+
+        let mut enabled_extensions = config.extensions.clone();
+        if let SwapchainSupport::Supported(_, _) = config.swapchain_support {
+            enabled_extensions.push(ash::extensions::khr::Swapchain::NAME.to_owned());
+        }
+        enabled_extensions.extend(
+            enabled_features
+                .iter()
+                .flat_map(|feature| feature.extensions())
+                .map(|extension| (*extension).to_owned()),
+        );
+        enabled_extensions.sort();
+        enabled_extensions.dedup();
+
+        // Collect everything that's unsupported so it can be fixed in one go.
+        let unsupported_features = enabled_features
+            .iter()
+            .filter(|feature| !supported_features.contains(feature))
+            .copied()
+            .collect::<Vec<_>>();
+        let unsupported_extensions = enabled_extensions
+            .iter()
+            .filter(|extension| !physical_device.supports_extension(extension))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !unsupported_features.is_empty() || !unsupported_extensions.is_empty() {
+            return Err(VulkanError::Unsupported {
+                features: unsupported_features,
+                extensions: unsupported_extensions,
+            });
+        }
+
         let (device, queues, queue_aliases) = {
             let resolved_queue_definitions =
-                utils::resolve_queue_definitions(&physical_device, &config, &surface);
-            dbg!(
-                "[pyrite_vulkan]: Resolved queue definitions: {:?}",
+                utils::resolve_queue_definitions(&physical_device, &config, &surface)?;
+            log::debug!(
+                "Resolved queue definitions: {:?}",
                 &resolved_queue_definitions
             );
 
@@ -351,23 +552,83 @@ impl VulkanInstance {
                 );
             }
 
-            let mut device_extensions = Vec::new();
-            if let SwapchainSupport::Supported(_, _) = config.swapchain_support {
-                device_extensions.push(ash::extensions::khr::Swapchain::NAME.to_owned());
-            }
-            let ptr_device_extensions = device_extensions
+            let ptr_device_extensions = enabled_extensions
                 .iter()
                 .map(|s| s.as_ptr())
                 .collect::<Vec<_>>();
 
-            let device_create_info = vk::DeviceCreateInfo::default()
+            let is_enabled = |feature| enabled_features.contains(&feature);
+            let core_features = vk::PhysicalDeviceFeatures::default()
+                .sampler_anisotropy(is_enabled(VulkanFeature::SamplerAnisotropy))
+                .fill_mode_non_solid(is_enabled(VulkanFeature::FillModeNonSolid))
+                .multi_draw_indirect(is_enabled(VulkanFeature::MultiDrawIndirect))
+                .texture_compression_bc(is_enabled(VulkanFeature::TextureCompressionBc))
+                .texture_compression_astc_ldr(is_enabled(VulkanFeature::TextureCompressionAstc))
+                .texture_compression_etc2(is_enabled(VulkanFeature::TextureCompressionEtc2))
+                .occlusion_query_precise(is_enabled(VulkanFeature::OcclusionQueryPrecise))
+                .pipeline_statistics_query(is_enabled(VulkanFeature::PipelineStatisticsQuery))
+                .sparse_binding(is_enabled(VulkanFeature::SparseResidencyImage2D))
+                .sparse_residency_image2_d(is_enabled(VulkanFeature::SparseResidencyImage2D));
+            let mut vulkan11_features = vk::PhysicalDeviceVulkan11Features::default()
+                .shader_draw_parameters(is_enabled(VulkanFeature::ShaderDrawParameters));
+            let mut device_create_info = vk::DeviceCreateInfo::default()
                 .enabled_extension_names(&ptr_device_extensions)
-                .queue_create_infos(&queue_definitions);
+                .enabled_features(&core_features)
+                .queue_create_infos(&queue_definitions)
+                .push_next(&mut vulkan11_features);
+
+            let mut dynamic_rendering_features =
+                vk::PhysicalDeviceDynamicRenderingFeatures::default().dynamic_rendering(true);
+            if is_enabled(VulkanFeature::DynamicRendering) {
+                device_create_info = device_create_info.push_next(&mut dynamic_rendering_features);
+            }
+
+            let mut synchronization2_features =
+                vk::PhysicalDeviceSynchronization2Features::default().synchronization2(true);
+            if is_enabled(VulkanFeature::Synchronization2) {
+                device_create_info = device_create_info.push_next(&mut synchronization2_features);
+            }
+
+            let mut acceleration_structure_features =
+                vk::PhysicalDeviceAccelerationStructureFeaturesKHR::default()
+                    .acceleration_structure(true);
+            let mut ray_tracing_pipeline_features =
+                vk::PhysicalDeviceRayTracingPipelineFeaturesKHR::default()
+                    .ray_tracing_pipeline(true);
+            if is_enabled(VulkanFeature::RayTracing) {
+                device_create_info = device_create_info
+                    .push_next(&mut acceleration_structure_features)
+                    .push_next(&mut ray_tracing_pipeline_features);
+            }
+
+            let mut mesh_shader_features = vk::PhysicalDeviceMeshShaderFeaturesEXT::default()
+                .mesh_shader(true)
+                .task_shader(true);
+            if is_enabled(VulkanFeature::MeshShader) {
+                device_create_info = device_create_info.push_next(&mut mesh_shader_features);
+            }
+
+            // Descriptor indexing and indirect count are core in Vulkan 1.2, so only the features
+            // need to be enabled.
+            let descriptor_indexing = is_enabled(VulkanFeature::DescriptorIndexing);
+            let mut vulkan12_features = vk::PhysicalDeviceVulkan12Features::default()
+                .draw_indirect_count(is_enabled(VulkanFeature::DrawIndirectCount))
+                .buffer_device_address(is_enabled(VulkanFeature::RayTracing))
+                .runtime_descriptor_array(descriptor_indexing)
+                .descriptor_binding_partially_bound(descriptor_indexing)
+                .descriptor_binding_variable_descriptor_count(descriptor_indexing)
+                .descriptor_binding_sampled_image_update_after_bind(descriptor_indexing)
+                .descriptor_binding_storage_buffer_update_after_bind(descriptor_indexing)
+                .shader_sampled_image_array_non_uniform_indexing(descriptor_indexing)
+                .shader_storage_buffer_array_non_uniform_indexing(descriptor_indexing);
+            device_create_info = device_create_info.push_next(&mut vulkan12_features);
 
             let device = unsafe {
-                instance
-                    .create_device(physical_device.physical_device, &device_create_info, None)
-                    .expect("Failed to create Vulkan device.")
+                instance.create_device(
+                    physical_device.physical_device,
+                    &device_create_info,
+                    None,
+                )?
             };
 
             let mut queues = HashMap::new();
@@ -393,16 +654,38 @@ impl VulkanInstance {
             (device, queues, queue_aliases)
         };
 
-        Self {
+        let dynamic_rendering = enabled_features
+            .contains(&VulkanFeature::DynamicRendering)
+            .then(|| ash::extensions::khr::DynamicRendering::new(&instance, &device));
+        let synchronization2 = enabled_features
+            .contains(&VulkanFeature::Synchronization2)
+            .then(|| ash::extensions::khr::Synchronization2::new(&instance, &device));
+        let ray_tracing = enabled_features
+            .contains(&VulkanFeature::RayTracing)
+            .then(|| VulkanRayTracing::new(&instance, &device, &physical_device));
+        let mesh_shader = enabled_features
+            .contains(&VulkanFeature::MeshShader)
+            .then(|| ash::extensions::ext::MeshShader::new(&instance, &device));
+
+        Ok(Self {
             entry,
             instance,
             debug_utils,
+            validation,
             surface,
             physical_device,
             device,
             queues,
             queue_aliases,
-        }
+            dynamic_rendering,
+            synchronization2,
+            ray_tracing,
+            mesh_shader,
+            enabled_features,
+            enabled_extensions,
+            device_lost: AtomicBool::new(false),
+            device_lost_hooks: Mutex::new(Vec::new()),
+        })
     }
 
     pub fn entry(&self) -> &ash::Entry {
@@ -417,6 +700,34 @@ impl VulkanInstance {
         &self.debug_utils
     }
 
+    /// The most recent validation messages, oldest first. Empty if validation isn't enabled.
+    pub fn validation_messages(&self) -> Vec<ValidationMessage> {
+        self.validation
+            .as_ref()
+            .map_or_else(Vec::new, |validation| validation.messages())
+    }
+
+    /// Takes the recent validation messages, so the next call only returns newer ones.
+    pub fn take_validation_messages(&self) -> Vec<ValidationMessage> {
+        self.validation
+            .as_ref()
+            .map_or_else(Vec::new, |validation| validation.take_messages())
+    }
+
+    /// The number of validation errors reported since the instance was created, ignored messages
+    /// excluded. Tests can compare it before and after a frame to assert it emitted no errors.
+    pub fn validation_error_count(&self) -> u64 {
+        self.validation
+            .as_ref()
+            .map_or(0, |validation| validation.error_count())
+    }
+
+    pub fn validation_warning_count(&self) -> u64 {
+        self.validation
+            .as_ref()
+            .map_or(0, |validation| validation.warning_count())
+    }
+
     pub fn surface(&self) -> &Option<VulkanSurface> {
         &self.surface
     }
@@ -438,24 +749,95 @@ impl VulkanInstance {
         self.queues.get(&queue_name)
     }
 
-    pub fn default_queue(&self) -> &VulkanQueue {
-        self.queue(DEFAULT_QUEUE)
-            .expect("[pyrite_vulkan]: Default queue was not found.")
+    /// The dynamic rendering loader, `None` if [`VulkanFeature::DynamicRendering`] wasn't enabled.
+    pub fn dynamic_rendering(&self) -> Option<&ash::extensions::khr::DynamicRendering> {
+        self.dynamic_rendering.as_ref()
+    }
+
+    /// The synchronization2 loader, `None` if the device doesn't support it, in which case the
+    /// legacy barrier and submit commands are used instead.
+    pub fn synchronization2(&self) -> Option<&ash::extensions::khr::Synchronization2> {
+        self.synchronization2.as_ref()
     }
 
-    unsafe extern "system" fn debug_messenger_callback(
-        message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
-        message_type: vk::DebugUtilsMessageTypeFlagsEXT,
-        p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-        _p_user_data: *mut std::ffi::c_void,
-    ) -> vk::Bool32 {
-        let message = std::ffi::CStr::from_ptr((*p_callback_data).p_message);
-        println!(
-            "[pyrite_vulkan]: {:?} {:?} {:?}",
-            message_severity, message_type, message
+    /// Names the object in debugging tools like RenderDoc and in validation messages, does nothing
+    /// if validation isn't enabled since that's when debug utils are loaded.
+    pub fn set_debug_name(&self, handle: impl vk::Handle, name: &str) {
+        let Some(debug_utils) = &self.debug_utils else {
+            return;
+        };
+
+        let name = CString::new(name).expect("Debug names can't contain nul bytes.");
+        let name_info = vk::DebugUtilsObjectNameInfoEXT::default()
+            .object_handle(handle)
+            .object_name(&name);
+        unsafe {
+            debug_utils
+                .debug_utils_loader
+                .set_debug_utils_object_name(self.device.handle(), &name_info)
+                .expect("Failed to set debug name.");
+        }
+    }
+
+    /// The mesh shader loader, `None` if [`VulkanFeature::MeshShader`] wasn't enabled.
+    pub fn mesh_shader(&self) -> Option<&ash::extensions::ext::MeshShader> {
+        self.mesh_shader.as_ref()
+    }
+
+    /// The ray tracing loaders, `None` unless [`VulkanFeature::RayTracing`] was enabled.
+    pub fn ray_tracing(&self) -> Option<&VulkanRayTracing> {
+        self.ray_tracing.as_ref()
+    }
+
+    pub fn is_feature_enabled(&self, feature: VulkanFeature) -> bool {
+        self.enabled_features.contains(&feature)
+    }
+
+    pub fn is_extension_enabled(&self, extension_name: &CStr) -> bool {
+        self.enabled_extensions
+            .iter()
+            .any(|extension| extension.as_c_str() == extension_name)
+    }
+
+    /// Registers a hook called once the device is lost, to save state, tear down and recreate
+    /// gpu resources or exit gracefully. Hooks registered after the device was lost are never
+    /// called.
+    pub fn on_device_lost(&self, hook: impl Fn(&VulkanInstance) + Send + Sync + 'static) {
+        self.device_lost_hooks.lock().unwrap().push(Box::new(hook));
+    }
+
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+
+    /// Marks the device as lost, logging diagnostics and calling the device lost hooks the first
+    /// time. Called by the [`crate::executor::QueueExecutor`] when a submission fails with
+    /// `VK_ERROR_DEVICE_LOST`, other code observing the error should call it as well.
+    pub fn report_device_lost(&self) {
+        if self.device_lost.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let device_name =
+            unsafe { CStr::from_ptr(self.physical_device.properties().device_name.as_ptr()) };
+        log::error!(
+            "The device {} was lost after {} validation errors.",
+            device_name.to_string_lossy(),
+            self.validation_error_count()
         );
+        for message in self.validation_messages() {
+            log::error!("Recent validation message: {}", message.message);
+        }
+
+        let hooks = std::mem::take(&mut *self.device_lost_hooks.lock().unwrap());
+        for hook in hooks {
+            hook(self);
+        }
+    }
 
-        vk::FALSE
+    pub fn default_queue(&self) -> &VulkanQueue {
+        self.queue(DEFAULT_QUEUE)
+            .expect("[pyrite_vulkan]: Default queue was not found.")
     }
 }
 
@@ -475,12 +857,20 @@ impl std::ops::Deref for Vulkan {
 }
 
 impl Vulkan {
+    /// # Panics
+    /// If the instance or device can't be created, see [`Vulkan::try_new`].
     pub fn new(config: &VulkanConfig) -> Self {
         Self {
             instance: Arc::new(VulkanInstance::new(config)),
         }
     }
 
+    pub fn try_new(config: &VulkanConfig) -> VulkanResult<Self> {
+        Ok(Self {
+            instance: Arc::new(VulkanInstance::try_new(config)?),
+        })
+    }
+
     pub fn create_dep(&self) -> VulkanDep {
         Arc::clone(&self.instance)
     }
@@ -513,27 +903,27 @@ pub(super) mod utils {
         physical_device: &VulkanPhysicalDevice,
         vulkan_config: &VulkanConfig,
         vulkan_surface: &Option<VulkanSurface>,
-    ) -> ResolvedQueueDefinitions {
+    ) -> VulkanResult<ResolvedQueueDefinitions> {
         // Check if the vulkan config queue definitions are valid.
         {
             let mut queue_names = HashSet::new();
             for queue_config in &vulkan_config.queues {
                 // Check if the queue name is unique.
                 if queue_names.contains(&queue_config.name) {
-                    panic!(
-                        "[pyrite_vulkan]: Queue name '{}' is not unique. Queue names must be uniquely named.",
+                    return Err(VulkanError::InvalidQueueConfig(format!(
+                        "Queue name '{}' is not unique. Queue names must be uniquely named.",
                         queue_config.name
-                    );
+                    )));
                 }
 
                 // Check for duplicate capabilities.
                 let mut capabilities = HashSet::new();
                 for capability in &queue_config.capabilities {
                     if capabilities.contains(capability) {
-                        panic!(
-                            "[pyrite_vulkan]: Queue capability '{:?}' is duplicated in queue '{}'. Queue capabilities must be unique.",
+                        return Err(VulkanError::InvalidQueueConfig(format!(
+                            "Queue capability '{:?}' is duplicated in queue '{}'. Queue capabilities must be unique.",
                             capability, queue_config.name
-                        );
+                        )));
                     }
 
                     capabilities.insert(capability);
@@ -542,10 +932,10 @@ pub(super) mod utils {
                 // Check if the queue priority is valid if non-exclusive.
                 if let QueuePriority::Shared(priority) = &queue_config.priority {
                     if *priority < 0.0 || *priority > 1.0 {
-                        panic!(
-                            "[pyrite_vulkan]: Queue priority value '{}' is invalid. Queue priority must be between 0.0 and 1.0.",
+                        return Err(VulkanError::InvalidQueueConfig(format!(
+                            "Queue priority value '{}' is invalid. Queue priority must be between 0.0 and 1.0.",
                             priority
-                        );
+                        )));
                     }
                 }
 
@@ -556,10 +946,10 @@ pub(super) mod utils {
                         .iter()
                         .any(|queue| &queue.name == fallback_queue_name)
                     {
-                        panic!(
-                            "[pyrite_vulkan]: Queue fallback '{}' is invalid. If specified, the fallback queue be valid, otherwise set it to None.",
+                        return Err(VulkanError::InvalidQueueConfig(format!(
+                            "Queue fallback '{}' is invalid. If specified, the fallback queue be valid, otherwise set it to None.",
                             fallback_queue_name
-                        );
+                        )));
                     }
 
                     // Ensure the fallback queue doesn't have a circular dependency to this queue.
@@ -575,10 +965,10 @@ pub(super) mod utils {
                     {
                         // Check for circular dependencies.
                         if visited_queue_names.contains(current_fallback_queue_name) {
-                            panic!(
-                                "[pyrite_vulkan]: Circular dependency detected in queue fallbacks. Queue '{}' is dependent on itself.",
+                            return Err(VulkanError::InvalidQueueConfig(format!(
+                                "Circular dependency detected in queue fallbacks. Queue '{}' is dependent on itself.",
                                 current_fallback_queue_name
-                            );
+                            )));
                         }
 
                         current_queue_name = current_fallback_queue_name.clone();
@@ -676,11 +1066,12 @@ pub(super) mod utils {
                         continue;
                     }
                     QueueResolution::Panic => {
-                        // Panic if the queue can't be constructed.
-                        panic!(
-                            "[pyrite_vulkan]: Queue config '{}' is invalid. No queue families found that match the queue config.",
+                        // Fail creating the instance if the queue can't be constructed, which panics
+                        // when using `Vulkan::new`.
+                        return Err(VulkanError::InvalidQueueConfig(format!(
+                            "Queue config '{}' is invalid. No queue families found that match the queue config.",
                             queue_config.name
-                        );
+                        )));
                     }
                 }
             }
@@ -719,10 +1110,10 @@ pub(super) mod utils {
 
                 // Validate that the final alias's definition was constructed.
                 if final_definition.is_none() {
-                    panic!(
-                        "[pyrite_vulkan]: Virtual queue alias '{}' is invalid. The resolved virtual queue '{}' was not constructed.",
+                    return Err(VulkanError::InvalidQueueConfig(format!(
+                        "Virtual queue alias '{}' is invalid. The resolved virtual queue '{}' was not constructed.",
                         alias, final_alias
-                    );
+                    )));
                 }
 
                 flattened_virtual_queue_aliases
@@ -732,10 +1123,10 @@ pub(super) mod utils {
             flattened_virtual_queue_aliases
         };
 
-        ResolvedQueueDefinitions {
+        Ok(ResolvedQueueDefinitions {
             queue_family_indices,
             virtual_queue_aliases,
-        }
+        })
     }
 
     fn is_queue_family_valid(
@@ -758,6 +1149,9 @@ pub(super) mod utils {
                 QueueCapability::Transfer => {
                     queue_family.queue_flags.contains(vk::QueueFlags::TRANSFER)
                 }
+                QueueCapability::SparseBinding => queue_family
+                    .queue_flags
+                    .contains(vk::QueueFlags::SPARSE_BINDING),
                 QueueCapability::Present => {
                     if let Some(vulkan_surface) = vulkan_surface {
                         let surface_loader = &vulkan_surface.surface_loader;
@@ -770,7 +1164,7 @@ pub(super) mod utils {
                                     queue_family_index as u32,
                                     surface,
                                 )
-                                .unwrap()
+                                .unwrap_or(false)
                         }
                     } else {
                         false
@@ -214,12 +214,12 @@ impl SwapchainInner {
         };
 
         let extent = swapchain_support.capabilities.current_extent;
-        println!("Swapchain extent: {:?}", extent);
-        println!(
+        log::debug!("Swapchain extent: {:?}", extent);
+        log::debug!(
             "Swapchain min extent: {:?}",
             swapchain_support.capabilities.min_image_extent
         );
-        println!(
+        log::debug!(
             "Swapchain max extent: {:?}",
             swapchain_support.capabilities.max_image_extent
         );
@@ -107,7 +107,7 @@ impl VulkanInner {
 
             #[cfg(debug_assertions)]
             let instance_extensions = {
-                println!("Debug mode enabled. Enabling debug extensions.");
+                log::debug!("Debug mode enabled. Enabling debug extensions.");
                 let mut instance_extensions = instance_extensions;
                 instance_extensions.push(ash::extensions::ext::DebugUtils::NAME.as_ptr());
                 instance_extensions
@@ -120,7 +120,7 @@ impl VulkanInner {
             };
 
             instance_layer_settings.iter().for_each(|layer| {
-                println!(
+                log::trace!(
                     "Instance layer: {}",
                     unsafe { CStr::from_ptr(layer.layer_name.as_ptr()) }
                         .to_str()
@@ -131,7 +131,7 @@ impl VulkanInner {
             let mut instance_layers: Vec<CString> = Vec::new();
             #[cfg(debug_assertions)]
             {
-                println!("Debug mode enabled. Enabling validation layers.");
+                log::debug!("Debug mode enabled. Enabling validation layers.");
                 instance_layers.push(CString::new("VK_LAYER_KHRONOS_validation").unwrap());
             }
 
@@ -315,7 +315,7 @@ impl VulkanInner {
 
             // Add queue to queue family.
             if let Some(queue_family_index) = queue_family_index {
-                println!("Queue family index: {}", queue_family_index);
+                log::debug!("Queue family index: {}", queue_family_index);
                 if queue_family_indices.contains_key(&queue_family_index) {
                     queue_family_indices
                         .get_mut(&queue_family_index)
@@ -326,7 +326,7 @@ impl VulkanInner {
                 }
             } else {
                 // Queue family isn't found so toss queue out.
-                println!("Queue family not found for queue: {}", queue.queue_name());
+                log::warn!("Queue family not found for queue: {}", queue.queue_name());
             }
         });
 
@@ -420,9 +420,21 @@ unsafe extern "system" fn debug_messenger_callback(
     _p_user_data: *mut std::ffi::c_void,
 ) -> vk::Bool32 {
     let message = unsafe { CStr::from_ptr((*p_callback_data).p_message) };
-    println!(
-        "[Vulkan] {:?} {:?} {:?}",
-        message_severity, message_type, message
+    let level = if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+        log::Level::Error
+    } else if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+        log::Level::Warn
+    } else if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+        log::Level::Info
+    } else {
+        log::Level::Trace
+    };
+    log::log!(
+        target: "pyrite_vulkan::validation",
+        level,
+        "{:?} {}",
+        message_type,
+        message.to_string_lossy()
     );
     vk::FALSE
 }
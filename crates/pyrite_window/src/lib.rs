@@ -1,8 +1,10 @@
+mod monitor;
 pub mod util;
 mod window;
 
+pub use monitor::{MonitorInfo, VideoModeInfo};
 pub use window::*;
 
 pub mod prelude {
-    pub use crate::window::{Window, WindowConfig};
+    pub use crate::window::{Window, WindowConfig, WindowIcon, WindowResized, WindowState};
 }
@@ -0,0 +1,67 @@
+use winit::monitor::{MonitorHandle, VideoMode};
+
+/// A display mode a monitor supports in exclusive fullscreen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoModeInfo {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u16,
+    pub refresh_rate_millihertz: u32,
+}
+
+impl VideoModeInfo {
+    fn new(video_mode: &VideoMode) -> Self {
+        let size = video_mode.size();
+        Self {
+            width: size.width,
+            height: size.height,
+            bit_depth: video_mode.bit_depth(),
+            refresh_rate_millihertz: video_mode.refresh_rate_millihertz(),
+        }
+    }
+}
+
+/// A monitor connected when it was queried with [`crate::Window::monitors`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    /// The position of the top left corner on the desktop in physical pixels.
+    pub position: (i32, i32),
+    /// The current resolution in physical pixels.
+    pub size: (u32, u32),
+    pub scale_factor: f64,
+    /// The current refresh rate, `None` if the platform doesn't report it.
+    pub refresh_rate_millihertz: Option<u32>,
+    pub video_modes: Vec<VideoModeInfo>,
+}
+
+impl MonitorInfo {
+    pub(crate) fn new(monitor: &MonitorHandle) -> Self {
+        let position = monitor.position();
+        let size = monitor.size();
+        Self {
+            name: monitor.name(),
+            position: (position.x, position.y),
+            size: (size.width, size.height),
+            scale_factor: monitor.scale_factor(),
+            refresh_rate_millihertz: monitor.refresh_rate_millihertz(),
+            video_modes: monitor
+                .video_modes()
+                .map(|video_mode| VideoModeInfo::new(&video_mode))
+                .collect(),
+        }
+    }
+}
+
+/// The video mode used for exclusive fullscreen on a monitor, the highest resolution with the
+/// highest refresh rate.
+pub(crate) fn best_video_mode(monitor: &MonitorHandle) -> Option<VideoMode> {
+    monitor.video_modes().max_by_key(|video_mode| {
+        let size = video_mode.size();
+        (
+            size.width * size.height,
+            video_mode.refresh_rate_millihertz(),
+            video_mode.bit_depth(),
+        )
+    })
+}
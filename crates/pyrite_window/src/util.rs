@@ -1,5 +1,12 @@
-use pyrite_input::{keyboard::Key, mouse::Button};
-use winit::{event::ButtonId, keyboard::KeyCode as WinitKeyCode};
+use pyrite_input::{
+    keyboard::Key,
+    mouse::{self, Button, ScrollDelta},
+    text::{self, ImePreedit},
+};
+use winit::{
+    event::{ButtonId, ElementState, Ime, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::KeyCode as WinitKeyCode,
+};
 
 pub fn to_pyrite_key(code: WinitKeyCode) -> Option<Key> {
     match code {
@@ -84,3 +91,55 @@ pub(crate) fn to_pyrite_button(button: ButtonId) -> Option<Button> {
         _ => None,
     }
 }
+
+/// Translates the cursor, button and scroll events of a window event, to be submitted to
+/// [`pyrite_input::mouse::Mouse`].
+pub fn to_pyrite_mouse_input(event: &WindowEvent) -> Option<mouse::SubmitInput> {
+    match event {
+        WindowEvent::CursorMoved { position, .. } => Some(mouse::SubmitInput::Position(
+            position.x as f32,
+            position.y as f32,
+        )),
+        WindowEvent::CursorEntered { .. } => Some(mouse::SubmitInput::CursorEntered),
+        WindowEvent::CursorLeft { .. } => Some(mouse::SubmitInput::CursorLeft),
+        WindowEvent::MouseInput { state, button, .. } => {
+            let button = match button {
+                MouseButton::Left => Button::Left,
+                MouseButton::Right => Button::Right,
+                MouseButton::Middle => Button::Middle,
+                _ => return None,
+            };
+            match state {
+                ElementState::Pressed => Some(mouse::SubmitInput::Pressed(button)),
+                ElementState::Released => Some(mouse::SubmitInput::Released(button)),
+            }
+        }
+        WindowEvent::MouseWheel { delta, .. } => Some(mouse::SubmitInput::Scroll(match delta {
+            MouseScrollDelta::LineDelta(x, y) => ScrollDelta::Lines(*x, *y),
+            MouseScrollDelta::PixelDelta(delta) => {
+                ScrollDelta::Pixels(delta.x as f32, delta.y as f32)
+            }
+        })),
+        _ => None,
+    }
+}
+
+/// Translates the text typed and input method editor events of a window event, to be submitted
+/// to [`pyrite_input::text::TextInput`].
+pub fn to_pyrite_text_input(event: &WindowEvent) -> Option<text::SubmitInput> {
+    match event {
+        WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => event
+            .text
+            .as_ref()
+            .map(|text| text::SubmitInput::Text(text.to_string())),
+        WindowEvent::Ime(Ime::Preedit(text, cursor)) => {
+            Some(text::SubmitInput::Preedit(Some(ImePreedit {
+                text: text.clone(),
+                cursor: *cursor,
+            })))
+        }
+        WindowEvent::Ime(Ime::Commit(text)) => Some(text::SubmitInput::Commit(text.clone())),
+        WindowEvent::Ime(Ime::Disabled) => Some(text::SubmitInput::Preedit(None)),
+        _ => None,
+    }
+}
@@ -1,14 +1,74 @@
 use pyrite_app::resource::Resource;
-use winit::{self, window::Window as WinitWindow};
+use pyrite_asset::loaders::image::ImageData;
+use winit::{
+    self,
+    dpi::{PhysicalPosition, PhysicalSize},
+    window::{BadIcon, Fullscreen, Icon, Window as WinitWindow, WindowLevel},
+};
 
+use crate::monitor::{best_video_mode, MonitorInfo};
+
+/// How the window covers its monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowState {
+    #[default]
+    Windowed,
+    /// Takes exclusive control of the monitor with its highest resolution video mode.
+    Fullscreen,
+    /// Covers the monitor with a borderless window at the desktop resolution, which switches
+    /// faster than exclusive fullscreen.
+    BorderlessFullscreen,
+}
+
+/// The icon shown in the window's title bar and the taskbar.
+#[derive(Clone)]
+pub struct WindowIcon(Icon);
+
+impl WindowIcon {
+    /// Fails if `rgba` doesn't hold exactly `width` by `height` RGBA8 pixels.
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
+        Icon::from_rgba(rgba, width, height).map(Self)
+    }
+
+    /// Creates an icon from an image loaded by the image loader.
+    pub fn from_image(image: &ImageData) -> Result<Self, BadIcon> {
+        Self::from_rgba(image.bytes.clone(), image.extent.width, image.extent.height)
+    }
+}
+
+#[derive(Clone)]
 pub struct WindowConfig {
     pub title: String,
+    pub state: WindowState,
+    pub resizable: bool,
+    pub decorations: bool,
+    /// Whether the window's background is transparent where nothing is presented, which also
+    /// requires a swapchain with a composite alpha mode other than opaque.
+    pub transparent: bool,
+    pub always_on_top: bool,
+    /// The index of the monitor the window is centered on, in the order of
+    /// [`winit::event_loop::EventLoop::available_monitors`]. The platform decides if `None`.
+    pub monitor: Option<usize>,
+    pub icon: Option<WindowIcon>,
+    /// The smallest inner size in physical pixels the window can be resized to.
+    pub min_inner_size: Option<(u32, u32)>,
+    /// The largest inner size in physical pixels the window can be resized to.
+    pub max_inner_size: Option<(u32, u32)>,
 }
 
 impl Default for WindowConfig {
     fn default() -> Self {
         Self {
             title: "Pyrite App".to_string(),
+            state: WindowState::Windowed,
+            resizable: true,
+            decorations: true,
+            transparent: false,
+            always_on_top: false,
+            monitor: None,
+            icon: None,
+            min_inner_size: None,
+            max_inner_size: None,
         }
     }
 }
@@ -18,11 +78,65 @@ impl WindowConfig {
         self.title = title.into();
         self
     }
+
+    pub fn state(mut self, state: WindowState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn always_on_top(mut self, always_on_top: bool) -> Self {
+        self.always_on_top = always_on_top;
+        self
+    }
+
+    pub fn monitor(mut self, monitor: usize) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    pub fn icon(mut self, icon: WindowIcon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    pub fn min_inner_size(mut self, width: u32, height: u32) -> Self {
+        self.min_inner_size = Some((width, height));
+        self
+    }
+
+    pub fn max_inner_size(mut self, width: u32, height: u32) -> Self {
+        self.max_inner_size = Some((width, height));
+        self
+    }
+}
+
+/// Sent when the inner size of the window changes, the size is zero while the window is
+/// minimized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowResized {
+    pub width: u32,
+    pub height: u32,
 }
 
 #[derive(Resource)]
 pub struct Window {
     winit_window: WinitWindow,
+    state: WindowState,
 }
 
 impl raw_window_handle::HasDisplayHandle for Window {
@@ -43,19 +157,145 @@ impl raw_window_handle::HasWindowHandle for Window {
 
 impl Window {
     pub fn new(config: &WindowConfig, event_loop: &winit::event_loop::EventLoop<()>) -> Self {
-        let winit_window = winit::window::WindowBuilder::new()
+        let mut window_builder = winit::window::WindowBuilder::new()
             .with_title(config.title.clone())
             .with_visible(false)
-            .build(event_loop)
-            .unwrap();
+            .with_resizable(config.resizable)
+            .with_decorations(config.decorations)
+            .with_transparent(config.transparent)
+            .with_window_level(window_level(config.always_on_top))
+            .with_window_icon(config.icon.clone().map(|icon| icon.0));
+        if let Some((width, height)) = config.min_inner_size {
+            window_builder = window_builder.with_min_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some((width, height)) = config.max_inner_size {
+            window_builder = window_builder.with_max_inner_size(PhysicalSize::new(width, height));
+        }
+        let winit_window = window_builder.build(event_loop).unwrap();
+
+        // The window is centered once built, since its outer size isn't known before.
+        let monitor = config
+            .monitor
+            .and_then(|index| event_loop.available_monitors().nth(index));
+        if let Some(monitor) = monitor {
+            let monitor_position = monitor.position();
+            let monitor_size = monitor.size();
+            let window_size = winit_window.outer_size();
+            winit_window.set_outer_position(PhysicalPosition::new(
+                monitor_position.x + (monitor_size.width as i32 - window_size.width as i32) / 2,
+                monitor_position.y + (monitor_size.height as i32 - window_size.height as i32) / 2,
+            ));
+        }
+
+        let mut window = Self {
+            winit_window,
+            state: WindowState::Windowed,
+        };
+        window.set_state(config.state);
+        window
+    }
+
+    pub fn state(&self) -> WindowState {
+        self.state
+    }
+
+    /// Switches between windowed and fullscreen on the monitor the window is on.
+    ///
+    /// The window's new size is reported through a resize event like any other resize, so a
+    /// swapchain resized from [`WindowResized`] events follows it.
+    pub fn set_state(&mut self, state: WindowState) {
+        let fullscreen = match state {
+            WindowState::Windowed => None,
+            // Without video modes exclusive fullscreen falls back to borderless.
+            WindowState::Fullscreen => Some(
+                self.winit_window
+                    .current_monitor()
+                    .and_then(|monitor| best_video_mode(&monitor))
+                    .map(Fullscreen::Exclusive)
+                    .unwrap_or(Fullscreen::Borderless(None)),
+            ),
+            WindowState::BorderlessFullscreen => {
+                Some(Fullscreen::Borderless(self.winit_window.current_monitor()))
+            }
+        };
 
-        Self { winit_window }
+        self.winit_window.set_fullscreen(fullscreen);
+        self.state = state;
+    }
+
+    /// The monitors currently connected.
+    pub fn monitors(&self) -> Vec<MonitorInfo> {
+        self.winit_window
+            .available_monitors()
+            .map(|monitor| MonitorInfo::new(&monitor))
+            .collect()
+    }
+
+    /// The monitor the window is mostly on, `None` if it can't be determined.
+    pub fn current_monitor(&self) -> Option<MonitorInfo> {
+        self.winit_window
+            .current_monitor()
+            .map(|monitor| MonitorInfo::new(&monitor))
+    }
+
+    /// The monitor the platform considers primary, `None` on platforms without one.
+    pub fn primary_monitor(&self) -> Option<MonitorInfo> {
+        self.winit_window
+            .primary_monitor()
+            .map(|monitor| MonitorInfo::new(&monitor))
     }
 
     pub fn set_visible(&mut self, visible: bool) {
         self.winit_window.set_visible(visible);
     }
 
+    pub fn set_title(&mut self, title: &str) {
+        self.winit_window.set_title(title);
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.winit_window.set_resizable(resizable);
+    }
+
+    pub fn set_decorations(&mut self, decorations: bool) {
+        self.winit_window.set_decorations(decorations);
+    }
+
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.winit_window
+            .set_window_level(window_level(always_on_top));
+    }
+
+    pub fn set_icon(&mut self, icon: Option<WindowIcon>) {
+        self.winit_window.set_window_icon(icon.map(|icon| icon.0));
+    }
+
+    pub fn set_min_inner_size(&mut self, size: Option<(u32, u32)>) {
+        self.winit_window
+            .set_min_inner_size(size.map(|(width, height)| PhysicalSize::new(width, height)));
+    }
+
+    pub fn set_max_inner_size(&mut self, size: Option<(u32, u32)>) {
+        self.winit_window
+            .set_max_inner_size(size.map(|(width, height)| PhysicalSize::new(width, height)));
+    }
+
+    /// Enables input method editors for the window, so languages composed of several
+    /// keystrokes can be typed into [`pyrite_input::Input::text`]. Should be enabled while a text
+    /// field is focused and disabled again afterwards, since the editor may capture keys.
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        self.winit_window.set_ime_allowed(allowed);
+    }
+
+    /// Tells the input method editor where the focused text field is in physical pixels, so it
+    /// can show its candidate window next to it.
+    pub fn set_ime_cursor_area(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        self.winit_window.set_ime_cursor_area(
+            PhysicalPosition::new(x, y),
+            PhysicalSize::new(width, height),
+        );
+    }
+
     pub fn width(&self) -> u32 {
         self.winit_window.inner_size().width
     }
@@ -64,3 +304,11 @@ impl Window {
         self.winit_window.inner_size().height
     }
 }
+
+fn window_level(always_on_top: bool) -> WindowLevel {
+    if always_on_top {
+        WindowLevel::AlwaysOnTop
+    } else {
+        WindowLevel::Normal
+    }
+}
@@ -17,7 +17,7 @@ fn should_increment_counter(counter: &Counter) -> bool {
 }
 
 fn increment_counter(counter: &mut Counter) {
-    println!(
+    pyrite::util::logging::info!(
         "Incrementing counter from {} to {}",
         counter.count,
         counter.count + 1
@@ -26,6 +26,8 @@ fn increment_counter(counter: &mut Counter) {
 }
 
 fn main() {
+    pyrite::util::logging::init_logger(&Default::default());
+
     let mut app_builder = AppBuilder::new();
     app_builder.add_resource(Counter { count: 0 });
 
@@ -1,3 +1,6 @@
+pub mod preset;
+pub mod swapchain;
+
 pub mod app {
     pub use pyrite_app::*;
 }
@@ -6,6 +9,14 @@ pub mod asset {
     pub use pyrite_asset::*;
 }
 
+pub mod audio {
+    pub use pyrite_audio::*;
+}
+
+pub mod camera {
+    pub use pyrite_camera::*;
+}
+
 pub mod vulkan {
     pub use pyrite_vulkan::*;
 }
@@ -14,10 +25,22 @@ pub mod input {
     pub use pyrite_input::*;
 }
 
+pub mod math {
+    pub use pyrite_math::*;
+}
+
+pub mod task {
+    pub use pyrite_task::*;
+}
+
 pub mod time {
     pub use pyrite_time::*;
 }
 
+pub mod ui {
+    pub use pyrite_ui::*;
+}
+
 pub mod util {
     pub use pyrite_util::*;
 }
@@ -29,8 +52,13 @@ pub mod window {
 pub mod prelude {
     pub use pyrite_app::prelude::*;
     pub use pyrite_asset::prelude::*;
+    pub use pyrite_audio::prelude::*;
+    pub use pyrite_camera::prelude::*;
     pub use pyrite_input::prelude::*;
+    pub use pyrite_math::prelude::*;
+    pub use pyrite_task::prelude::*;
     pub use pyrite_time::prelude::*;
+    pub use pyrite_ui::prelude::*;
     pub use pyrite_util::prelude::*;
     pub use pyrite_vulkan::prelude::*;
     pub use pyrite_window::prelude::*;
@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+
+use pyrite_app::{
+    event::EventWriter,
+    resource::{Res, ResMut},
+    stage::{POST_UPDATE_STAGE, PRE_UPDATE_STAGE},
+    task::AsyncTaskPool,
+    AppBuilder, AppExit, Application,
+};
+use pyrite_asset::{AssetEvent, Assets};
+use pyrite_time::{setup_fixed_update, FrameStats, Time, DEFAULT_FIXED_TICK_RATE};
+use pyrite_util::logging::{init_logger, LoggerConfig};
+use pyrite_vulkan::{
+    allocator::VulkanMemoryAllocator,
+    stager::{staging_queue_config, VulkanStager},
+    QueueCapability, QueueConfig, QueuePriority, QueueResolution, SwapchainSupport,
+    ValidationPolicy, Vulkan, VulkanConfig, VulkanFeature, DEFAULT_QUEUE,
+};
+
+pub struct HeadlessPresetConfig {
+    pub app_name: String,
+    pub enable_validation: bool,
+    /// The most frames executed per second, unlimited if `None`.
+    pub frame_rate_limit: Option<u32>,
+    /// How many times a second the fixed update stage runs.
+    pub fixed_tick_rate: u32,
+    /// How many frames the [`VulkanStager`] keeps staging buffers alive for.
+    pub frames_in_flight: usize,
+    /// The logger installed by the preset, `None` for apps installing their own.
+    pub logger: Option<LoggerConfig>,
+}
+
+impl Default for HeadlessPresetConfig {
+    fn default() -> Self {
+        Self {
+            app_name: "Pyrite".to_string(),
+            enable_validation: true,
+            frame_rate_limit: Some(60),
+            fixed_tick_rate: DEFAULT_FIXED_TICK_RATE,
+            frames_in_flight: 2,
+            logger: Some(LoggerConfig::default()),
+        }
+    }
+}
+
+/// Sets up an app without a window or swapchain, for compute workloads and render tests on
+/// machines without a display server.
+///
+/// The app calls [`VulkanStager::begin_frame`] itself once it waited on a frame's fence, as
+/// there's no swapchain pacing the frames, and submits and acquires its deferred uploads with
+/// [`VulkanStager::submit_deferred`] and [`VulkanStager::acquire_deferred`].
+pub fn setup_headless_preset(app_builder: &mut AppBuilder, config: &HeadlessPresetConfig) {
+    if let Some(logger) = &config.logger {
+        init_logger(logger);
+    }
+
+    let vulkan = Vulkan::new(&VulkanConfig {
+        app_name: config.app_name.clone(),
+        // The default queue requires present support which is never available without a
+        // surface.
+        queues: vec![
+            QueueConfig {
+                name: DEFAULT_QUEUE.to_string(),
+                capabilities: vec![
+                    QueueCapability::Graphics,
+                    QueueCapability::Compute,
+                    QueueCapability::Transfer,
+                ],
+                priority: QueuePriority::Exclusive,
+                resolution: QueueResolution::Panic,
+            },
+            staging_queue_config(),
+        ],
+        enable_validation: config.enable_validation,
+        validation_policy: ValidationPolicy::default(),
+        swapchain_support: SwapchainSupport::None,
+        features: vec![VulkanFeature::DynamicRendering],
+        optional_features: Vec::new(),
+        extensions: Vec::new(),
+    });
+    let vulkan_memory_allocator = VulkanMemoryAllocator::new(&vulkan);
+    let assets = Assets::new(&app_builder.get_resource::<AsyncTaskPool>());
+
+    setup_fixed_update(app_builder, config.fixed_tick_rate);
+    app_builder
+        .add_resource(vulkan)
+        .add_resource(vulkan_memory_allocator)
+        .add_resource(VulkanStager::new(config.frames_in_flight))
+        .add_resource(assets)
+        .add_resource(FrameStats::new())
+        .add_event::<AssetEvent>()
+        .add_system_to_stage(update_time, PRE_UPDATE_STAGE)
+        .add_system_to_stage(update_assets, PRE_UPDATE_STAGE)
+        .add_system_to_stage(exit_on_device_lost, POST_UPDATE_STAGE);
+
+    let min_frame_time = config
+        .frame_rate_limit
+        .map(|frame_rate_limit| Duration::from_secs(1) / frame_rate_limit);
+    app_builder.set_entry_point(move |app| run_frame_limited(app, min_frame_time));
+}
+
+fn update_time(mut time: ResMut<Time>, mut frame_stats: ResMut<FrameStats>) {
+    time.update();
+    frame_stats.record(time.real_delta());
+}
+
+/// Exits gracefully once the device was lost, the diagnostics were already logged when it was
+/// reported.
+fn exit_on_device_lost(vulkan: Res<Vulkan>, mut app_exit: ResMut<AppExit>) {
+    if vulkan.is_device_lost() {
+        app_exit.request();
+    }
+}
+
+fn update_assets(mut assets: ResMut<Assets>, mut asset_events: EventWriter<AssetEvent>) {
+    assets.update();
+    assets.garbage_collect();
+    asset_events.send_batch(assets.drain_events());
+}
+
+/// Executes the schedule until the app exits, sleeping away the rest of each frame that was
+/// faster than `min_frame_time`.
+fn run_frame_limited(mut app: Application, min_frame_time: Option<Duration>) {
+    while !app.should_exit() {
+        let frame_start = Instant::now();
+        app.execute_schedule();
+
+        if let Some(min_frame_time) = min_frame_time {
+            std::thread::sleep(min_frame_time.saturating_sub(frame_start.elapsed()));
+        }
+    }
+    app.shutdown();
+}
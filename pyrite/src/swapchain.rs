@@ -0,0 +1,14 @@
+use pyrite_app::{event::EventReader, resource::ResMut};
+use pyrite_vulkan::swapchain::SwapchainManager;
+use pyrite_window::WindowResized;
+
+/// Resizes the swapchain to the latest window size, for apps that send [`WindowResized`] events
+/// from their window's event loop and render through a [`SwapchainManager`].
+pub fn resize_swapchain(
+    resize_events: EventReader<WindowResized>,
+    mut swapchain_manager: ResMut<SwapchainManager>,
+) {
+    if let Some(resized) = resize_events.iter().last() {
+        swapchain_manager.resize(resized.width, resized.height);
+    }
+}